@@ -0,0 +1,142 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Fields, GenericArgument, LitStr, Path, PathArguments, Type,
+    parse_macro_input,
+};
+
+/// Derive `FromMultipart` and `FromRequest` for a struct of form fields, so it can be
+/// taken directly as a handler argument.
+///
+/// Fields typed `FileInput`, `Option<FileInput>` or `Vec<FileInput>` are read from the
+/// uploaded files, the others with `Multipart::post`.
+///
+/// Attributes:
+/// - `#[multipart(rename = "name")]` on a field reads another form field
+/// - `#[multipart(error = "path::to::Error")]` on the struct sets the extractor error, which
+///   must implement `From<MultipartError>`; `MultipartError` by default
+#[proc_macro_derive(FromMultipart, attributes(multipart))]
+pub fn derive_from_multipart(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "FromMultipart can't be derived for generic structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "FromMultipart requires a struct with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "FromMultipart can only be derived for structs",
+            ));
+        }
+    };
+
+    let mut error: Path = syn::parse_quote!(::foxtive_ntex_multipart::MultipartError);
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("multipart"))
+    {
+        attr.parse_nested_meta(|meta| match meta.path.is_ident("error") {
+            true => {
+                error = meta.value()?.parse::<LitStr>()?.parse()?;
+                Ok(())
+            }
+            false => Err(meta.error("expected `error = \"...\"`")),
+        })?;
+    }
+
+    let mut values = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut form_name = ident.to_string();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("multipart"))
+        {
+            attr.parse_nested_meta(|meta| match meta.path.is_ident("rename") {
+                true => {
+                    form_name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                }
+                false => Err(meta.error("expected `rename = \"...\"`")),
+            })?;
+        }
+
+        let ty = &field.ty;
+        let value = match holds_file(ty) {
+            true => quote! {
+                <#ty as ::foxtive_ntex_multipart::FormFiles>::from_files(multipart, #form_name)?
+            },
+            false => quote! { multipart.post::<#ty>(#form_name)? },
+        };
+        values.push(quote! { #ident: #value });
+    }
+
+    Ok(quote! {
+        impl ::foxtive_ntex_multipart::FromMultipart for #name {
+            fn from_multipart(
+                multipart: &::foxtive_ntex_multipart::Multipart,
+            ) -> ::foxtive_ntex_multipart::MultipartResult<Self> {
+                Ok(Self { #(#values,)* })
+            }
+        }
+
+        impl<Err> ::foxtive_ntex_multipart::__private::FromRequest<Err> for #name {
+            type Error = #error;
+
+            async fn from_request(
+                req: &::foxtive_ntex_multipart::__private::HttpRequest,
+                payload: &mut ::foxtive_ntex_multipart::__private::Payload,
+            ) -> ::core::result::Result<Self, Self::Error> {
+                ::foxtive_ntex_multipart::Multipart::extract::<Self>(req, payload)
+                    .await
+                    .map_err(::core::convert::Into::into)
+            }
+        }
+    })
+}
+
+/// Whether `ty` is `FileInput`, or an `Option`/`Vec` of it
+fn holds_file(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return false;
+    };
+
+    match segment.ident.to_string().as_str() {
+        "FileInput" => true,
+        "Option" | "Vec" => match &segment.arguments {
+            PathArguments::AngleBracketed(args) => matches!(
+                args.args.first(),
+                Some(GenericArgument::Type(Type::Path(inner)))
+                    if inner.path.segments.last().is_some_and(|s| s.ident == "FileInput")
+            ),
+            _ => false,
+        },
+        _ => false,
+    }
+}
@@ -0,0 +1,454 @@
+use crate::result::{MultipartError, MultipartResult};
+use crate::storage::{FsStorage, unique_id};
+use futures::{Stream, StreamExt};
+use ntex::http::error::PayloadError;
+use ntex::util::Bytes;
+use sha2::{Digest, Sha256};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+/// Storage with a native multipart upload API, e.g. S3's `CreateMultipartUpload`,
+/// `UploadPart`, `CompleteMultipartUpload` and `AbortMultipartUpload`.
+///
+/// Parts are streamed straight to the backend, nothing is buffered by [`ChunkedUploads`].
+#[allow(async_fn_in_trait)]
+pub trait MultipartUploadBackend {
+    /// Start an upload of `key`, returning the backend's upload id
+    async fn initiate(&self, key: &str, content_type: &str) -> MultipartResult<String>;
+
+    /// Store one part, returning its ETag. Uploading a part number again replaces it.
+    async fn upload_part<S>(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: u32,
+        body: S,
+    ) -> MultipartResult<String>
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin;
+
+    /// Assemble the parts, ordered by number, into the final object
+    async fn complete(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[UploadedPart],
+    ) -> MultipartResult<()>;
+
+    /// Discard the uploaded parts
+    async fn abort(&self, key: &str, upload_id: &str) -> MultipartResult<()>;
+}
+
+/// Part of an [`UploadSession`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadedPart {
+    pub number: u32,
+    pub etag: String,
+    pub size: usize,
+}
+
+/// Upload in progress, tracked by an [`UploadSessionStore`] between requests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadSession {
+    /// id handed to the client
+    pub id: String,
+    pub key: String,
+    /// id of the upload on the backend
+    pub upload_id: String,
+    pub file_name: String,
+    pub content_type: String,
+    /// size announced by the client, checked on completion
+    pub declared_size: Option<usize>,
+    pub parts: Vec<UploadedPart>,
+    /// unix timestamp (seconds)
+    pub created_at: u64,
+}
+
+impl UploadSession {
+    pub fn uploaded_size(&self) -> usize {
+        self.parts.iter().map(|part| part.size).sum()
+    }
+}
+
+/// Where upload sessions live between requests, in memory by default. Use a shared store
+/// (database, redis...) when requests of an upload may reach different instances.
+#[allow(async_fn_in_trait)]
+pub trait UploadSessionStore {
+    async fn insert(&self, session: UploadSession) -> MultipartResult<()>;
+
+    async fn get(&self, id: &str) -> MultipartResult<Option<UploadSession>>;
+
+    /// Record a part, replacing the one with the same number. Parts may be uploaded
+    /// concurrently, this must not lose any.
+    async fn add_part(&self, id: &str, part: UploadedPart) -> MultipartResult<()>;
+
+    async fn remove(&self, id: &str) -> MultipartResult<()>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<String, UploadSession>>,
+}
+
+impl UploadSessionStore for MemorySessionStore {
+    async fn insert(&self, session: UploadSession) -> MultipartResult<()> {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.insert(session.id.clone(), session);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> MultipartResult<Option<UploadSession>> {
+        Ok(self
+            .sessions
+            .lock()
+            .ok()
+            .and_then(|sessions| sessions.get(id).cloned()))
+    }
+
+    async fn add_part(&self, id: &str, part: UploadedPart) -> MultipartResult<()> {
+        let mut sessions = match self.sessions.lock() {
+            Ok(sessions) => sessions,
+            Err(_) => return Err(MultipartError::UploadSessionNotFound(id.to_string())),
+        };
+
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| MultipartError::UploadSessionNotFound(id.to_string()))?;
+        session
+            .parts
+            .retain(|existing| existing.number != part.number);
+        session.parts.push(part);
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str) -> MultipartResult<()> {
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.remove(id);
+        }
+        Ok(())
+    }
+}
+
+/// Object assembled by [`ChunkedUploads::complete`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedUpload {
+    pub key: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: usize,
+    pub parts: usize,
+}
+
+/// Handler helpers for uploads sent in chunks, for files too large for one request.
+///
+/// The client initiates an upload, sends numbered parts (in any order, possibly in
+/// parallel), then completes or aborts it. Each call maps to the backend's multipart API.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex_multipart::{ChunkedUploads, FsStorage, MemorySessionStore};
+///
+/// let uploads = ChunkedUploads::new(FsStorage::new("/var/uploads"), MemorySessionStore::default())
+///     .max_part_size(64 * 1024 * 1024)
+///     .max_object_size(10 * 1024 * 1024 * 1024);
+///
+/// // POST   /uploads                  -> uploads.initiate(name, content_type, size)
+/// // PUT    /uploads/{id}/parts/{n}   -> uploads.upload_part(id, n, payload)
+/// // POST   /uploads/{id}/complete    -> uploads.complete(id)
+/// // DELETE /uploads/{id}             -> uploads.abort(id)
+/// ```
+pub struct ChunkedUploads<B, S> {
+    backend: B,
+    sessions: S,
+    max_part_size: usize,
+    max_parts: u32,
+    max_object_size: Option<usize>,
+}
+
+impl<B, S> ChunkedUploads<B, S>
+where
+    B: MultipartUploadBackend,
+    S: UploadSessionStore,
+{
+    pub fn new(backend: B, sessions: S) -> Self {
+        Self {
+            backend,
+            sessions,
+            max_part_size: 5 * 1024 * 1024 * 1024,
+            max_parts: 10_000,
+            max_object_size: None,
+        }
+    }
+
+    /// Largest part accepted, 5 GiB by default
+    pub fn max_part_size(mut self, bytes: usize) -> Self {
+        self.max_part_size = bytes;
+        self
+    }
+
+    /// Highest part number accepted, 10 000 by default
+    pub fn max_parts(mut self, count: u32) -> Self {
+        self.max_parts = count;
+        self
+    }
+
+    /// Largest object accepted, unlimited by default
+    pub fn max_object_size(mut self, bytes: usize) -> Self {
+        self.max_object_size = Some(bytes);
+        self
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub async fn initiate(
+        &self,
+        file_name: &str,
+        content_type: &str,
+        declared_size: Option<usize>,
+    ) -> MultipartResult<UploadSession> {
+        if let (Some(size), Some(max)) = (declared_size, self.max_object_size)
+            && size > max
+        {
+            return Err(MultipartError::UploadTooLarge(max));
+        }
+
+        let key = match file_name.rsplit_once('.') {
+            Some((_, extension))
+                if !extension.is_empty()
+                    && extension.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                format!("{}.{}", unique_id(), extension.to_lowercase())
+            }
+            _ => unique_id(),
+        };
+
+        let upload_id = self.backend.initiate(&key, content_type).await?;
+        let session = UploadSession {
+            id: unique_id(),
+            key,
+            upload_id,
+            file_name: file_name.to_string(),
+            content_type: content_type.to_string(),
+            declared_size,
+            parts: vec![],
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+
+        self.sessions.insert(session.clone()).await?;
+        Ok(session)
+    }
+
+    /// Stream a part to the backend, `part_number` starts at 1
+    pub async fn upload_part<P>(
+        &self,
+        session_id: &str,
+        part_number: u32,
+        body: P,
+    ) -> MultipartResult<UploadedPart>
+    where
+        P: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+    {
+        let session = self.session(session_id).await?;
+        if part_number == 0 || part_number > self.max_parts {
+            return Err(MultipartError::InvalidUploadPart(format!(
+                "part number must be between 1 and {}",
+                self.max_parts
+            )));
+        }
+
+        // other parts count toward the object size, a replaced part doesn't
+        let others: usize = session
+            .parts
+            .iter()
+            .filter(|part| part.number != part_number)
+            .map(|part| part.size)
+            .sum();
+        let limit = match self.max_object_size {
+            Some(max) => self.max_part_size.min(max.saturating_sub(others)),
+            None => self.max_part_size,
+        };
+
+        let received = Rc::new(Cell::new(0usize));
+        let counter = received.clone();
+        let body = body.map(move |chunk| {
+            let chunk = chunk?;
+            counter.set(counter.get() + chunk.len());
+            match counter.get() > limit {
+                true => Err(PayloadError::Overflow),
+                false => Ok(chunk),
+            }
+        });
+
+        let uploaded = self
+            .backend
+            .upload_part(&session.key, &session.upload_id, part_number, body)
+            .await;
+
+        let etag = match uploaded {
+            Err(_) if received.get() > limit => {
+                let max = match self.max_object_size {
+                    Some(max) if limit < self.max_part_size => max,
+                    _ => self.max_part_size,
+                };
+                return Err(MultipartError::UploadTooLarge(max));
+            }
+            result => result?,
+        };
+
+        let part = UploadedPart {
+            number: part_number,
+            etag,
+            size: received.get(),
+        };
+        self.sessions.add_part(session_id, part.clone()).await?;
+        Ok(part)
+    }
+
+    /// Assemble the object once parts 1 to N were uploaded, ending the session
+    pub async fn complete(&self, session_id: &str) -> MultipartResult<CompletedUpload> {
+        let mut session = self.session(session_id).await?;
+        session.parts.sort_by_key(|part| part.number);
+
+        if session.parts.is_empty() {
+            return Err(MultipartError::InvalidUploadPart(
+                "no part was uploaded".to_string(),
+            ));
+        }
+        if let Some(missing) = (1..)
+            .zip(&session.parts)
+            .find(|(n, part)| part.number != *n)
+        {
+            return Err(MultipartError::InvalidUploadPart(format!(
+                "part {} is missing",
+                missing.0
+            )));
+        }
+
+        let size = session.uploaded_size();
+        if let Some(declared) = session.declared_size
+            && declared != size
+        {
+            return Err(MultipartError::InvalidUploadPart(format!(
+                "uploaded {size} bytes, {declared} were announced"
+            )));
+        }
+
+        self.backend
+            .complete(&session.key, &session.upload_id, &session.parts)
+            .await?;
+        self.sessions.remove(session_id).await?;
+
+        Ok(CompletedUpload {
+            key: session.key,
+            file_name: session.file_name,
+            content_type: session.content_type,
+            size,
+            parts: session.parts.len(),
+        })
+    }
+
+    /// Discard the uploaded parts and end the session
+    pub async fn abort(&self, session_id: &str) -> MultipartResult<()> {
+        let session = self.session(session_id).await?;
+        self.backend.abort(&session.key, &session.upload_id).await?;
+        self.sessions.remove(session_id).await
+    }
+
+    pub async fn session(&self, session_id: &str) -> MultipartResult<UploadSession> {
+        self.sessions
+            .get(session_id)
+            .await?
+            .ok_or_else(|| MultipartError::UploadSessionNotFound(session_id.to_string()))
+    }
+}
+
+impl FsStorage {
+    /// Directory holding the parts of an upload until it completes
+    fn parts_dir(&self, upload_id: &str) -> PathBuf {
+        self.path_of(".uploads").join(upload_id)
+    }
+}
+
+/// Parts are kept as files under `.uploads/{upload_id}` and concatenated on completion
+impl MultipartUploadBackend for FsStorage {
+    async fn initiate(&self, _key: &str, _content_type: &str) -> MultipartResult<String> {
+        let upload_id = unique_id();
+        tokio::fs::create_dir_all(self.parts_dir(&upload_id)).await?;
+        Ok(upload_id)
+    }
+
+    async fn upload_part<S>(
+        &self,
+        _key: &str,
+        upload_id: &str,
+        part_number: u32,
+        mut body: S,
+    ) -> MultipartResult<String>
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+    {
+        let path = self.parts_dir(upload_id).join(part_number.to_string());
+        let mut file = File::create(&path).await?;
+        let mut hasher = Sha256::new();
+
+        while let Some(chunk) = body.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    drop(file);
+                    let _ = tokio::fs::remove_file(&path).await;
+                    return Err(MultipartError::NtexError(
+                        ntex_multipart::MultipartError::Payload(err),
+                    ));
+                }
+            };
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+        }
+
+        file.flush().await?;
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect())
+    }
+
+    async fn complete(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[UploadedPart],
+    ) -> MultipartResult<()> {
+        let path = self.path_of(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let dir = self.parts_dir(upload_id);
+        let mut file = File::create(&path).await?;
+        for part in parts {
+            let mut source = File::open(dir.join(part.number.to_string())).await?;
+            tokio::io::copy(&mut source, &mut file).await?;
+        }
+        file.flush().await?;
+
+        Ok(tokio::fs::remove_dir_all(dir).await?)
+    }
+
+    async fn abort(&self, _key: &str, upload_id: &str) -> MultipartResult<()> {
+        Ok(tokio::fs::remove_dir_all(self.parts_dir(upload_id)).await?)
+    }
+}
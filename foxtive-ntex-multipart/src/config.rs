@@ -0,0 +1,100 @@
+use crate::Validator;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+
+static DEFAULT_CONFIG: OnceLock<RwLock<MultipartConfig>> = OnceLock::new();
+
+/// How [`crate::Multipart::process`] holds uploaded files.
+///
+/// Files are buffered in memory by default. With a `memory_threshold`, a file growing
+/// past it is moved to a temporary file under `temp_dir` and the rest of its bytes are
+/// streamed there, see [`crate::FileInput::temp_path`]. The temporary file is deleted
+/// once the last clone of its `FileInput` is dropped.
+///
+/// A default `validator` checks the files of every request, see
+/// [`MultipartConfig::validator`].
+#[derive(Debug, Clone)]
+pub struct MultipartConfig {
+    /// Size in bytes above which a file is spilled to disk, `None` (never) by default
+    pub memory_threshold: Option<usize>,
+    /// Directory of the spilled files, the system temporary directory by default
+    pub temp_dir: PathBuf,
+    /// Rules applied by `process()` to the files of every request
+    pub validator: Option<Arc<Validator>>,
+    /// Whether `process()` records an `extractor` span, enabled by default
+    pub tracing: bool,
+}
+
+/// Validators are equal when they are the same instance
+impl PartialEq for MultipartConfig {
+    fn eq(&self, other: &Self) -> bool {
+        let same_validator = match (&self.validator, &other.validator) {
+            (Some(validator), Some(other)) => Arc::ptr_eq(validator, other),
+            (None, None) => true,
+            _ => false,
+        };
+
+        same_validator
+            && self.memory_threshold == other.memory_threshold
+            && self.temp_dir == other.temp_dir
+            && self.tracing == other.tracing
+    }
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        Self {
+            memory_threshold: None,
+            temp_dir: std::env::temp_dir(),
+            validator: None,
+            tracing: true,
+        }
+    }
+}
+
+impl MultipartConfig {
+    pub fn memory_threshold(mut self, bytes: usize) -> Self {
+        self.memory_threshold = Some(bytes);
+        self
+    }
+
+    pub fn temp_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.temp_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Check the files of every request with `validator` once `process()` read them, on top
+    /// of the validators given to `Multipart::validate`. Its rules only apply to the fields
+    /// they name.
+    pub fn validator(mut self, validator: impl Into<Arc<Validator>>) -> Self {
+        self.validator = Some(validator.into());
+        self
+    }
+
+    pub fn tracing(mut self, enabled: bool) -> Self {
+        self.tracing = enabled;
+        self
+    }
+
+    /// Whether a file of `size` bytes is kept on disk
+    pub(crate) fn spills(&self, size: usize) -> bool {
+        self.memory_threshold
+            .is_some_and(|threshold| size > threshold)
+    }
+
+    /// Config used by `Multipart` instances created from requests, unless one is registered
+    /// as app state
+    pub fn set_default(config: MultipartConfig) {
+        let lock = DEFAULT_CONFIG.get_or_init(|| RwLock::new(MultipartConfig::default()));
+        if let Ok(mut current) = lock.write() {
+            *current = config;
+        }
+    }
+
+    pub fn current_default() -> MultipartConfig {
+        DEFAULT_CONFIG
+            .get()
+            .and_then(|lock| lock.read().ok().map(|config| config.clone()))
+            .unwrap_or_default()
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Default, Clone)]
 pub struct ContentDisposition {
@@ -54,16 +54,32 @@ impl ContentDisposition {
     }
 
     /// Parses a content disposition string into a HashMap of variables.
+    ///
+    /// Extended parameters (RFC 5987/6266) like `filename*=UTF-8''%E2%82%AC.txt` are decoded
+    /// and stored under their plain name, taking precedence over it.
     pub fn parse(content_disposition: &str) -> ContentDispositionParseResult {
         let mut variables = HashMap::new();
+        let mut extended = HashSet::new();
 
         for part in content_disposition.split(';') {
             let part = part.trim();
             if let Some((key, value)) = part.split_once('=') {
+                let key = key.trim();
+                if let Some(key) = key.strip_suffix('*') {
+                    if let Some(value) = Self::decode_extended(value.trim()) {
+                        extended.insert(key.to_string());
+                        variables.insert(key.to_string(), value);
+                    }
+                    continue;
+                }
+
+                if extended.contains(key) {
+                    continue;
+                }
+
                 // Trim whitespace and remove any surrounding quotes from the value
-                let key = key.trim().to_string();
                 let value = value.trim().trim_matches('"').to_string();
-                variables.insert(key, value);
+                variables.insert(key.to_string(), value);
             }
         }
 
@@ -73,6 +89,33 @@ impl ContentDisposition {
             variables,
         }
     }
+
+    /// Decodes an extended parameter value: `charset'language'percent-encoded`,
+    /// `None` for an unsupported charset or a malformed value
+    fn decode_extended(value: &str) -> Option<String> {
+        let value = value.trim_matches('"');
+        let mut parts = value.splitn(3, '\'');
+        let (charset, _language, encoded) = (parts.next()?, parts.next()?, parts.next()?);
+
+        let mut bytes = Vec::with_capacity(encoded.len());
+        let mut input = encoded.bytes();
+        while let Some(byte) = input.next() {
+            match byte {
+                b'%' => {
+                    let high = (input.next()? as char).to_digit(16)?;
+                    let low = (input.next()? as char).to_digit(16)?;
+                    bytes.push((high * 16 + low) as u8);
+                }
+                byte => bytes.push(byte),
+            }
+        }
+
+        match charset.to_ascii_lowercase().as_str() {
+            "utf-8" => String::from_utf8(bytes).ok(),
+            "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+            _ => None,
+        }
+    }
 }
 
 impl From<HashMap<String, String>> for ContentDisposition {
@@ -254,4 +297,27 @@ mod tests {
             Some(&"example.txt".to_string())
         );
     }
+
+    // Test for decoding RFC 5987 extended filenames, preferred over the plain one
+    #[test]
+    fn test_parse_extended_filename() {
+        let content_disposition = "form-data; name=\"file\"; filename*=UTF-8''%E2%82%AC%20rates.txt; filename=\"rates.txt\"";
+        let content = ContentDisposition::create(content_disposition);
+        assert_eq!(content.get_filename(), Some("€ rates.txt"));
+        assert!(content.is_file_field());
+
+        let content = ContentDisposition::create(
+            "form-data; name=\"file\"; filename=\"a.txt\"; filename*=iso-8859-1'en'%E9t%E9.txt",
+        );
+        assert_eq!(content.get_filename(), Some("été.txt"));
+
+        // unsupported charset or malformed value, the plain filename is kept
+        let content = ContentDisposition::create(
+            "form-data; filename=\"plain.txt\"; filename*=UTF-16''%FF%FE",
+        );
+        assert_eq!(content.get_filename(), Some("plain.txt"));
+        let content =
+            ContentDisposition::create("form-data; filename=\"plain.txt\"; filename*=UTF-8''%E2%8");
+        assert_eq!(content.get_filename(), Some("plain.txt"));
+    }
 }
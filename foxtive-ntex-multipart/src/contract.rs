@@ -1,6 +1,5 @@
+use crate::Multipart;
 use crate::result::MultipartResult;
-use crate::{Multipart, MultipartError};
-use std::str::FromStr;
 
 /// Trait for types that can be parsed from multipart form data
 pub trait PostParseable: Sized {
@@ -26,15 +25,20 @@ pub trait PostParseable: Sized {
 /// automatically supported, and users can add support for their custom types
 /// via the provided macro.
 pub trait PostParseableFromStr: Sized + sealed::Sealed {
-    /// Parse a value from multipart data using FromStr
+    /// Parse a single value of `field`
     ///
     /// This method handles the standard parsing logic:
-    /// 1. Extracts the field value from multipart data
-    /// 2. Trims whitespace
-    /// 3. Handles empty values (returns error)
-    /// 4. Attempts to parse using `FromStr`
-    /// 5. Provides detailed error messages on failure
-    fn parse_from_multipart_str(multipart: &Multipart, field: &str) -> MultipartResult<Self>;
+    /// 1. Trims whitespace
+    /// 2. Handles empty values (returns error)
+    /// 3. Attempts to parse using `FromStr`
+    /// 4. Provides detailed error messages on failure
+    fn parse_str(field: &str, value: &str) -> MultipartResult<Self>;
+
+    /// Parse a value from multipart data, reading the first value of `field`
+    fn parse_from_multipart_str(multipart: &Multipart, field: &str) -> MultipartResult<Self> {
+        let data_input = multipart.first_data_required(field)?;
+        Self::parse_str(field, &data_input.value)
+    }
 }
 
 /// Sealed module to control which types can implement PostParseableFromStr
@@ -66,33 +70,15 @@ where
 /// Special implementation for Option<T> - returns None for missing or empty fields
 impl<T> PostParseable for Option<T>
 where
-    T: FromStr,
-    T::Err: std::fmt::Display,
+    T: PostParseableFromStr,
 {
     fn parse_from_multipart(multipart: &Multipart, field: &str) -> MultipartResult<Self> {
-        // Check if field exists
-        if let Some(data_input) = multipart.first_data(field) {
-            let value = data_input.value.trim();
-
-            // Return None for empty values
-            if value.is_empty() {
-                return Ok(None);
-            }
-
-            // Try to parse the value
-            match value.parse::<T>() {
-                Ok(parsed_value) => Ok(Some(parsed_value)),
-                Err(e) => Err(MultipartError::ParseError(format!(
-                    "Failed to parse field '{}' with value '{}' as {}: {}",
-                    field,
-                    value,
-                    std::any::type_name::<T>(),
-                    e
-                ))),
+        // Missing and empty fields are None, the rest is parsed as T
+        match multipart.first_data(field) {
+            Some(data_input) if !data_input.value.trim().is_empty() => {
+                T::parse_from_multipart_str(multipart, field).map(Some)
             }
-        } else {
-            // Field doesn't exist, return None
-            Ok(None)
+            _ => Ok(None),
         }
     }
 }
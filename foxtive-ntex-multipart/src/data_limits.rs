@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{OnceLock, RwLock};
+
+static DEFAULT_LIMITS: OnceLock<RwLock<DataLimits>> = OnceLock::new();
+
+/// Limits on the multipart body, enforced chunk by chunk while it is read so an oversized
+/// request is rejected as soon as it crosses one, without reading or buffering the rest.
+///
+/// Text fields are limited by default; the body, file size and file count limits are
+/// unset and fail with [`crate::MultipartError::LimitExceeded`]. Unlike the
+/// [`crate::Validator`] rules, these apply to every field of the request.
+#[derive(Debug, Clone)]
+pub struct DataLimits {
+    /// Maximum size in bytes of a single text field, 1 MiB by default
+    pub max_field_size: usize,
+    /// Maximum size in bytes of all text fields together, 8 MiB by default
+    pub max_total_size: usize,
+    /// Maximum number of text fields, 1000 by default
+    pub max_fields: usize,
+    /// Field-specific size limits overriding `max_field_size`
+    pub field_sizes: HashMap<String, usize>,
+    /// Maximum size in bytes of all files and text fields together
+    pub max_body_size: Option<usize>,
+    /// Maximum size in bytes of a single file
+    pub max_file_size: Option<usize>,
+    /// Maximum number of files
+    pub max_files: Option<usize>,
+}
+
+/// Body limit of the [`DataLimits`] crossed by a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultipartLimit {
+    BodySize,
+    FileSize,
+    Files,
+}
+
+impl MultipartLimit {
+    /// Whether the limit is a size, answered with 413
+    pub fn is_size(&self) -> bool {
+        matches!(self, MultipartLimit::BodySize | MultipartLimit::FileSize)
+    }
+
+    /// Stable label, see [`crate::MultipartError::reason`]
+    pub fn reason(&self) -> &'static str {
+        match self {
+            MultipartLimit::BodySize => "body_too_large",
+            MultipartLimit::FileSize => "file_too_large",
+            MultipartLimit::Files => "too_many_files",
+        }
+    }
+}
+
+impl Display for MultipartLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MultipartLimit::BodySize => write!(f, "request body size"),
+            MultipartLimit::FileSize => write!(f, "file size"),
+            MultipartLimit::Files => write!(f, "number of files"),
+        }
+    }
+}
+
+impl Default for DataLimits {
+    fn default() -> Self {
+        Self {
+            max_field_size: 1024 * 1024,
+            max_total_size: 8 * 1024 * 1024,
+            max_fields: 1000,
+            field_sizes: HashMap::new(),
+            max_body_size: None,
+            max_file_size: None,
+            max_files: None,
+        }
+    }
+}
+
+impl DataLimits {
+    pub fn max_field_size(mut self, bytes: usize) -> Self {
+        self.max_field_size = bytes;
+        self
+    }
+
+    pub fn max_total_size(mut self, bytes: usize) -> Self {
+        self.max_total_size = bytes;
+        self
+    }
+
+    pub fn max_fields(mut self, count: usize) -> Self {
+        self.max_fields = count;
+        self
+    }
+
+    /// Size limit of one field, e.g. a larger one for a `description`
+    pub fn field_size(mut self, field: &str, bytes: usize) -> Self {
+        self.field_sizes.insert(field.to_string(), bytes);
+        self
+    }
+
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = Some(bytes);
+        self
+    }
+
+    pub fn max_file_size(mut self, bytes: usize) -> Self {
+        self.max_file_size = Some(bytes);
+        self
+    }
+
+    pub fn max_files(mut self, count: usize) -> Self {
+        self.max_files = Some(count);
+        self
+    }
+
+    pub fn limit_for(&self, field: &str) -> usize {
+        self.field_sizes
+            .get(field)
+            .copied()
+            .unwrap_or(self.max_field_size)
+    }
+
+    /// Fail when `value` is over the `limit`, if set
+    pub(crate) fn check(
+        limit: MultipartLimit,
+        max: Option<usize>,
+        value: usize,
+    ) -> Result<(), crate::MultipartError> {
+        match max {
+            Some(max) if value > max => Err(crate::MultipartError::LimitExceeded(limit, max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Limits used by `Multipart` instances created from requests, unless some are registered
+    /// as app state
+    pub fn set_default(limits: DataLimits) {
+        let lock = DEFAULT_LIMITS.get_or_init(|| RwLock::new(DataLimits::default()));
+        if let Ok(mut current) = lock.write() {
+            *current = limits;
+        }
+    }
+
+    pub fn current_default() -> DataLimits {
+        DEFAULT_LIMITS
+            .get()
+            .and_then(|lock| lock.read().ok().map(|limits| limits.clone()))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,151 @@
+use crate::contract::{PostParseableFromStr, sealed};
+use crate::{FieldParseError, MultipartError, MultipartResult};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use std::sync::{OnceLock, RwLock};
+
+static DEFAULT_FORMATS: OnceLock<RwLock<DateTimeFormats>> = OnceLock::new();
+
+/// `chrono` formats accepted by `Multipart::post` for dates and times, tried in order after
+/// the ISO 8601 form of the type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateTimeFormats {
+    /// Formats of `NaiveDate`
+    pub date: Vec<String>,
+    /// Formats of `NaiveDateTime`, also read as UTC for `DateTime<Utc>`
+    pub datetime: Vec<String>,
+    /// Formats with an offset (`%z`, `%:z`) of `DateTime<FixedOffset>` and `DateTime<Utc>`
+    pub offset_datetime: Vec<String>,
+}
+
+impl Default for DateTimeFormats {
+    fn default() -> Self {
+        Self {
+            date: vec!["%Y-%m-%d".to_string()],
+            // includes what `<input type="datetime-local">` sends
+            datetime: vec![
+                "%Y-%m-%dT%H:%M:%S%.f".to_string(),
+                "%Y-%m-%d %H:%M:%S%.f".to_string(),
+                "%Y-%m-%dT%H:%M".to_string(),
+                "%Y-%m-%d %H:%M".to_string(),
+            ],
+            offset_datetime: vec!["%Y-%m-%d %H:%M:%S%.f%:z".to_string()],
+        }
+    }
+}
+
+impl DateTimeFormats {
+    pub fn date_formats<I, S>(mut self, formats: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.date = formats.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn datetime_formats<I, S>(mut self, formats: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.datetime = formats.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn offset_datetime_formats<I, S>(mut self, formats: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.offset_datetime = formats.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Formats used by every `Multipart` instance
+    pub fn set_default(formats: DateTimeFormats) {
+        let lock = DEFAULT_FORMATS.get_or_init(|| RwLock::new(DateTimeFormats::default()));
+        if let Ok(mut current) = lock.write() {
+            *current = formats;
+        }
+    }
+
+    pub fn current_default() -> DateTimeFormats {
+        DEFAULT_FORMATS
+            .get()
+            .and_then(|lock| lock.read().ok().map(|formats| formats.clone()))
+            .unwrap_or_default()
+    }
+}
+
+/// Parse `value` with the ISO 8601 form of `T`, then with each of `formats`
+fn parse_with<T: std::str::FromStr>(
+    value: &str,
+    formats: &[String],
+    parse: impl Fn(&str, &str) -> chrono::ParseResult<T>,
+) -> Result<T, String> {
+    if let Ok(parsed) = value.parse::<T>() {
+        return Ok(parsed);
+    }
+
+    formats
+        .iter()
+        .find_map(|format| parse(value, format).ok())
+        .ok_or_else(|| match formats.is_empty() {
+            true => "expected an ISO 8601 value".to_string(),
+            false => format!(
+                "expected an ISO 8601 value or one of the formats: {}",
+                formats.join(", ")
+            ),
+        })
+}
+
+fn parse_naive_datetime(value: &str, formats: &DateTimeFormats) -> Result<NaiveDateTime, String> {
+    parse_with(value, &formats.datetime, NaiveDateTime::parse_from_str)
+}
+
+fn parse_offset_datetime(
+    value: &str,
+    formats: &DateTimeFormats,
+) -> Result<DateTime<FixedOffset>, String> {
+    parse_with(value, &formats.offset_datetime, DateTime::parse_from_str)
+}
+
+/// Implement `PostParseableFromStr` with a parser taking the current [`DateTimeFormats`]
+macro_rules! impl_post_parseable_datetime {
+    ($($t:ty => $parse:expr),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $t {}
+
+            impl PostParseableFromStr for $t {
+                fn parse_str(field: &str, value: &str) -> MultipartResult<Self> {
+                    let value = value.trim();
+
+                    if value.is_empty() {
+                        return Err(MultipartError::ParseError(FieldParseError::empty::<$t>(
+                            field,
+                        )));
+                    }
+
+                    let formats = DateTimeFormats::current_default();
+                    let parse: fn(&str, &DateTimeFormats) -> Result<$t, String> = $parse;
+                    parse(value, &formats).map_err(|e| {
+                        MultipartError::ParseError(FieldParseError::invalid::<$t>(
+                            field, value, e,
+                        ))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_post_parseable_datetime!(
+    NaiveDate => |value, formats| parse_with(value, &formats.date, NaiveDate::parse_from_str),
+    NaiveDateTime => parse_naive_datetime,
+    DateTime<FixedOffset> => parse_offset_datetime,
+    DateTime<Utc> => |value, formats| {
+        parse_offset_datetime(value, formats)
+            .map(|datetime| datetime.with_timezone(&Utc))
+            .or_else(|_| parse_naive_datetime(value, formats).map(|datetime| datetime.and_utc()))
+    },
+);
@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static DEFAULT_POLICY: AtomicU8 = AtomicU8::new(DuplicatePolicy::Collect as u8);
+
+/// What to do when a text field is sent more than once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// keep the first value, ignore the following ones
+    FirstWins,
+    /// keep the last value
+    LastWins,
+    /// keep every value in order, `post()` and `first_data()` read the first one
+    #[default]
+    Collect,
+    /// fail with `MultipartError::DuplicateField`
+    Reject,
+}
+
+impl DuplicatePolicy {
+    /// Policy used by `Multipart` instances created from requests, unless one is registered
+    /// as app state
+    pub fn set_default(policy: DuplicatePolicy) {
+        DEFAULT_POLICY.store(policy as u8, Ordering::Relaxed);
+    }
+
+    pub fn current_default() -> DuplicatePolicy {
+        match DEFAULT_POLICY.load(Ordering::Relaxed) {
+            0 => DuplicatePolicy::FirstWins,
+            1 => DuplicatePolicy::LastWins,
+            3 => DuplicatePolicy::Reject,
+            _ => DuplicatePolicy::Collect,
+        }
+    }
+}
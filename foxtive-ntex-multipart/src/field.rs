@@ -0,0 +1,461 @@
+use crate::content_disposition::ContentDisposition;
+use crate::metrics::{self, UploadEvent};
+use crate::multipart::{ProgressHandler, declared_size};
+use crate::sniff::SNIFF_LEN;
+use crate::ticket::UploadTicket;
+use crate::{
+    DataLimits, FileInput, Multipart, MultipartError, MultipartLimit, MultipartResult, Validator,
+};
+use futures::{Stream, StreamExt};
+use ntex::util::{Bytes, BytesMut};
+use std::cell::Cell;
+use std::fmt;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// Parts handed out by [`Multipart::next_field`] so far
+#[derive(Debug, Default)]
+pub(crate) struct StreamState {
+    fields: usize,
+    files: usize,
+    /// bytes of every part, shared with the parts still being read
+    usage: Rc<StreamUsage>,
+}
+
+#[derive(Debug, Default)]
+struct StreamUsage {
+    /// size of the text fields
+    text: Cell<usize>,
+    /// size of the files
+    files: Cell<usize>,
+}
+
+impl StreamUsage {
+    fn body_size(&self) -> usize {
+        self.text.get() + self.files.get()
+    }
+}
+
+/// Checks of the [`Multipart`] the parts were read from, applied to their chunks
+struct StreamChecks {
+    ticket: Option<UploadTicket>,
+    validator: Option<Arc<Validator>>,
+    max_body_size: Option<usize>,
+    /// budget shared by the text fields
+    max_text_size: usize,
+    content_length: Option<usize>,
+    progress: Option<Rc<ProgressHandler>>,
+    route: Option<String>,
+}
+
+impl StreamChecks {
+    fn observe(&self, event: UploadEvent<'_>) {
+        if let Some(observer) = metrics::observer() {
+            observer.observe(self.route.as_deref().unwrap_or("unknown"), event);
+        }
+    }
+}
+
+impl fmt::Debug for StreamChecks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamChecks")
+            .field("ticket", &self.ticket)
+            .field("max_body_size", &self.max_body_size)
+            .field("max_text_size", &self.max_text_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Part of a multipart body read with [`Multipart::next_field`], its content is only read
+/// as its chunks are polled
+#[derive(Debug)]
+pub enum Field {
+    Text(TextField),
+    File(FileField),
+}
+
+impl Field {
+    /// Name of the form field
+    pub fn name(&self) -> &str {
+        match self {
+            Field::Text(field) => field.name(),
+            Field::File(field) => field.name(),
+        }
+    }
+}
+
+/// Limit on the size of a single part
+#[derive(Debug)]
+enum PartLimit {
+    Multipart(MultipartLimit),
+    /// text field limit of the [`crate::DataLimits`]
+    DataField(String),
+}
+
+impl PartLimit {
+    fn exceeded(&self, max: usize) -> MultipartError {
+        match self {
+            PartLimit::Multipart(limit) => MultipartError::LimitExceeded(*limit, max),
+            PartLimit::DataField(name) => MultipartError::DataFieldTooLarge(name.clone(), max),
+        }
+    }
+}
+
+/// Chunks of a part, checked against the limits, ticket and validator of the
+/// [`Multipart`] it was read from
+#[derive(Debug)]
+struct PartStream {
+    inner: ntex_multipart::Field,
+    name: String,
+    size: usize,
+    max: Option<(usize, PartLimit)>,
+    usage: Rc<StreamUsage>,
+    checks: Rc<StreamChecks>,
+    /// file being read, `None` for text fields
+    file: Option<FileInput>,
+    /// first bytes of the file, sniffed by the validator
+    head: Vec<u8>,
+    done: bool,
+}
+
+impl PartStream {
+    fn take(&mut self, chunk: &[u8]) -> MultipartResult<()> {
+        self.size += chunk.len();
+        let usage = &self.usage;
+        match &self.file {
+            Some(_) => usage.files.set(usage.files.get() + chunk.len()),
+            None => usage.text.set(usage.text.get() + chunk.len()),
+        }
+
+        if let Some(progress) = &self.checks.progress {
+            progress(&self.name, usage.body_size(), self.checks.content_length);
+        }
+
+        if let Some((max, limit)) = &self.max
+            && self.size > *max
+        {
+            return Err(limit.exceeded(*max));
+        }
+
+        DataLimits::check(
+            MultipartLimit::BodySize,
+            self.checks.max_body_size,
+            usage.body_size(),
+        )?;
+
+        let Some(info) = &self.file else {
+            return match usage.text.get() > self.checks.max_text_size {
+                true => Err(MultipartError::DataBudgetExceeded(
+                    self.checks.max_text_size,
+                )),
+                false => Ok(()),
+            };
+        };
+
+        if let Some(ticket) = &self.checks.ticket {
+            ticket.spend(usage.files.get())?;
+        }
+
+        if let Some(validator) = &self.checks.validator {
+            if let Some(max) = validator
+                .rules(&info.field_name)
+                .and_then(|rules| rules.max_size)
+                && self.size > max
+            {
+                return Err(validator.too_large(info, max));
+            }
+
+            if self.head.len() < SNIFF_LEN {
+                let missing = SNIFF_LEN - self.head.len();
+                self.head
+                    .extend_from_slice(&chunk[..chunk.len().min(missing)]);
+                if self.head.len() == SNIFF_LEN {
+                    validator.validate_head(info, &self.head)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks left once the whole part was read
+    fn finish(&mut self) -> MultipartResult<()> {
+        let Some(info) = &self.file else {
+            return Ok(());
+        };
+
+        // files shorter than the sniffed length are checked once complete
+        if let Some(validator) = &self.checks.validator
+            && self.head.len() < SNIFF_LEN
+        {
+            validator.validate_head(info, &self.head)?;
+        }
+
+        self.checks.observe(UploadEvent::File {
+            field: &info.field_name,
+            bytes: self.size,
+        });
+        Ok(())
+    }
+
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<MultipartResult<Bytes>>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let result = match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => Some(self.take(&chunk).map(|_| chunk)),
+            Poll::Ready(Some(Err(err))) => Some(Err(MultipartError::NtexError(err))),
+            Poll::Ready(None) => self.finish().err().map(Err),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        // a rejected part ends there
+        match &result {
+            Some(Err(err)) => {
+                self.done = true;
+                self.checks.observe(UploadEvent::Rejected {
+                    reason: err.reason(),
+                });
+            }
+            None => self.done = true,
+            Some(Ok(_)) => {}
+        }
+        Poll::Ready(result)
+    }
+
+    async fn collect(mut self) -> MultipartResult<Bytes> {
+        let mut content = BytesMut::new();
+        while let Some(chunk) = self.next().await {
+            content.extend_from_slice(&chunk?);
+        }
+        Ok(content.freeze())
+    }
+}
+
+impl Stream for PartStream {
+    type Item = MultipartResult<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_chunk(cx)
+    }
+}
+
+/// Text part, a stream of its chunks
+#[derive(Debug)]
+pub struct TextField {
+    name: String,
+    stream: PartStream,
+}
+
+impl TextField {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Next chunk of the value, `None` once it was all read
+    pub async fn chunk(&mut self) -> MultipartResult<Option<Bytes>> {
+        self.stream.next().await.transpose()
+    }
+
+    /// Read the whole value
+    pub async fn text(self) -> MultipartResult<String> {
+        let content = self.stream.collect().await?;
+        Ok(String::from_utf8_lossy(&content).into_owned())
+    }
+}
+
+impl Stream for TextField {
+    type Item = MultipartResult<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().stream.poll_chunk(cx)
+    }
+}
+
+/// File part, a stream of its chunks
+#[derive(Debug)]
+pub struct FileField {
+    info: FileInput,
+    stream: PartStream,
+}
+
+impl FileField {
+    pub fn name(&self) -> &str {
+        &self.info.field_name
+    }
+
+    /// Name, type and extension of the file, without content
+    pub fn info(&self) -> &FileInput {
+        &self.info
+    }
+
+    /// Bytes read so far
+    pub fn size(&self) -> usize {
+        self.stream.size
+    }
+
+    /// Next chunk of the file, `None` once it was all read
+    pub async fn chunk(&mut self) -> MultipartResult<Option<Bytes>> {
+        self.stream.next().await.transpose()
+    }
+
+    /// Read the whole file into memory
+    pub async fn into_file_input(self) -> MultipartResult<FileInput> {
+        let mut info = self.info;
+        let content = self.stream.collect().await?;
+        info.size = content.len();
+        info.bytes = vec![content];
+        Ok(info)
+    }
+}
+
+impl Stream for FileField {
+    type Item = MultipartResult<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().stream.poll_chunk(cx)
+    }
+}
+
+impl Multipart {
+    /// Read the parts of the body one at a time and in order, instead of buffering them all
+    /// like `process()`. The content of a part is read as its chunks are polled, so a slow
+    /// consumer slows the upload down. Drop a part before asking for the next one, its
+    /// unread chunks are then skipped; asking while it is alive fails.
+    ///
+    /// The checks of `process()` apply as the parts are read: the [`crate::DataLimits`] on the
+    /// body, files and text fields, the upload ticket, and the per-file rules of the
+    /// configured validator (extensions, content types, size, magic bytes). Rules needing
+    /// all the files (required fields, file counts, async rules) are left to the caller,
+    /// the parts are not stored in this instance.
+    ///
+    /// # Example
+    /// ```ignore
+    /// while let Some(field) = multipart.next_field().await? {
+    ///     match field {
+    ///         Field::Text(field) => println!("{} = {}", field.name(), field.text().await?),
+    ///         Field::File(mut file) => {
+    ///             while let Some(chunk) = file.chunk().await? {
+    ///                 writer.write_all(&chunk).await?;
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub async fn next_field(&mut self) -> MultipartResult<Option<Field>> {
+        let result = self.read_next_field().await;
+        self.observe_result(&result);
+        result
+    }
+
+    async fn read_next_field(&mut self) -> MultipartResult<Option<Field>> {
+        // the parser waits for the previous part to be dropped
+        if Rc::strong_count(&self.streamed.usage) > 1 {
+            return Err(MultipartError::NtexError(
+                ntex_multipart::MultipartError::NotConsumed,
+            ));
+        }
+
+        while let Some(item) = self.multipart.next().await {
+            let field = item.map_err(MultipartError::NtexError)?;
+
+            let Some(content_disposition) = field
+                .headers()
+                .get("content-disposition")
+                .and_then(|value| value.to_str().ok())
+            else {
+                continue;
+            };
+
+            let content_disposition = ContentDisposition::create(content_disposition);
+            if !content_disposition.has_name_field() {
+                continue;
+            }
+
+            if content_disposition.is_file_field() {
+                self.streamed.files += 1;
+                DataLimits::check(
+                    MultipartLimit::Files,
+                    self.data_limits.max_files,
+                    self.streamed.files,
+                )?;
+
+                let declared = declared_size(&field);
+                if let Some(declared) = declared {
+                    DataLimits::check(
+                        MultipartLimit::FileSize,
+                        self.data_limits.max_file_size,
+                        declared,
+                    )?;
+                }
+
+                let info = FileInput::create(field.headers(), content_disposition)?;
+                if let Some(ticket) = &self.ticket {
+                    ticket.admit(&info, declared)?;
+                }
+                if let Some(validator) = &self.config.validator {
+                    validator.validate_metadata(&info, declared)?;
+                }
+
+                let max = self
+                    .data_limits
+                    .max_file_size
+                    .map(|max| (max, PartLimit::Multipart(MultipartLimit::FileSize)));
+                let name = info.field_name.clone();
+                let stream = self.part_stream(field, name, max, Some(info.clone()));
+                return Ok(Some(Field::File(FileField { info, stream })));
+            }
+
+            self.streamed.fields += 1;
+            if self.streamed.fields > self.data_limits.max_fields {
+                return Err(MultipartError::TooManyDataFields(
+                    self.data_limits.max_fields,
+                ));
+            }
+
+            let name = content_disposition
+                .get_name()
+                .unwrap_or_default()
+                .to_string();
+            let limit = self.data_limits.limit_for(&name);
+            let max = (limit, PartLimit::DataField(name.clone()));
+            let stream = self.part_stream(field, name.clone(), Some(max), None);
+            return Ok(Some(Field::Text(TextField { name, stream })));
+        }
+
+        Ok(None)
+    }
+
+    fn part_stream(
+        &self,
+        inner: ntex_multipart::Field,
+        name: String,
+        max: Option<(usize, PartLimit)>,
+        file: Option<FileInput>,
+    ) -> PartStream {
+        let checks = StreamChecks {
+            ticket: self.ticket.clone(),
+            validator: self.config.validator.clone(),
+            max_body_size: self.data_limits.max_body_size,
+            max_text_size: self.data_limits.max_total_size,
+            content_length: self.content_length,
+            progress: self.progress.clone(),
+            route: self.route.clone(),
+        };
+
+        PartStream {
+            inner,
+            name,
+            size: 0,
+            max,
+            usage: self.streamed.usage.clone(),
+            checks: Rc::new(checks),
+            file,
+            head: Vec::new(),
+            done: false,
+        }
+    }
+}
@@ -6,17 +6,81 @@ use foxtive::helpers::FileExtHelper;
 use ntex::http::HeaderMap;
 use ntex::util::Bytes;
 use std::collections::HashMap;
-use std::path::Path;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+/// Uploaded file, end struct literals with `..Default::default()` as fields may be added
 #[derive(Debug, Default, Clone)]
 pub struct FileInput {
     pub file_name: String,
     pub field_name: String,
     pub size: usize, // Size in bytes
     pub content_type: String,
+    /// content held in memory, empty when spilled to `temp_file`
     pub bytes: Vec<Bytes>,
     pub extension: Option<String>,
     pub content_disposition: ContentDisposition,
+    /// content spilled to disk, see [`crate::MultipartConfig`]
+    pub temp_file: Option<TempFile>,
+}
+
+/// Temporary file holding the content of a spilled upload, removed when the last clone
+/// is dropped
+#[derive(Debug, Clone)]
+pub struct TempFile {
+    path: Arc<TempPath>,
+}
+
+#[derive(Debug)]
+struct TempPath(PathBuf);
+
+impl TempFile {
+    /// Take ownership of the file at `path`, deleting it on drop
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: Arc::new(TempPath(path.as_ref().to_path_buf())),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.0)
+            && err.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!("failed to remove temp file '{}': {err}", self.0.display());
+        }
+    }
+}
+
+/// Content of a [`FileInput`], see [`FileInput::reader`]
+#[derive(Debug)]
+pub enum FileReader {
+    Memory(Cursor<Bytes>),
+    Disk(std::fs::File),
+}
+
+impl Read for FileReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            FileReader::Memory(cursor) => cursor.read(buf),
+            FileReader::Disk(file) => file.read(buf),
+        }
+    }
+}
+
+impl Seek for FileReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            FileReader::Memory(cursor) => cursor.seek(pos),
+            FileReader::Disk(file) => file.seek(pos),
+        }
+    }
 }
 
 impl FileInput {
@@ -38,9 +102,67 @@ impl FileInput {
             file_name: name,
             field_name: field,
             content_disposition: cd,
+            temp_file: None,
         })
     }
 
+    /// Whether the content was spilled to a temporary file
+    pub fn is_on_disk(&self) -> bool {
+        self.temp_file.is_some()
+    }
+
+    /// Path of the temporary file holding the content, `None` when held in memory
+    pub fn temp_path(&self) -> Option<&Path> {
+        self.temp_file.as_ref().map(TempFile::path)
+    }
+
+    /// Up to `len` first bytes of the content, `None` when it isn't held, e.g. for the files
+    /// streamed to a storage backend
+    pub fn head(&self, len: usize) -> MultipartResult<Option<Vec<u8>>> {
+        if let Some(file) = &self.temp_file {
+            let mut head = Vec::with_capacity(len);
+            std::fs::File::open(file.path())?
+                .take(len as u64)
+                .read_to_end(&mut head)?;
+            return Ok(Some(head));
+        }
+
+        if self.bytes.is_empty() && self.size > 0 {
+            return Ok(None);
+        }
+
+        let mut head = Vec::with_capacity(len);
+        for chunk in &self.bytes {
+            let missing = len - head.len();
+            head.extend_from_slice(&chunk[..chunk.len().min(missing)]);
+            if head.len() == len {
+                break;
+            }
+        }
+        Ok(Some(head))
+    }
+
+    /// The whole content, read from the temporary file when spilled
+    pub async fn read_bytes(&self) -> MultipartResult<Bytes> {
+        match &self.temp_file {
+            Some(file) => Ok(Bytes::from(tokio::fs::read(file.path()).await?)),
+            None => Ok(Bytes::from(self.bytes.concat())),
+        }
+    }
+
+    /// Blocking reader of the content, streamed from the temporary file when spilled
+    pub fn reader(&self) -> MultipartResult<FileReader> {
+        match &self.temp_file {
+            Some(file) => Ok(FileReader::Disk(std::fs::File::open(file.path())?)),
+            None => Ok(FileReader::Memory(Cursor::new(
+                match self.bytes.as_slice() {
+                    [chunk] => chunk.clone(),
+                    chunks => Bytes::from(chunks.concat()),
+                },
+            ))),
+        }
+    }
+
     // Save the file to the specified path
     pub async fn save(&self, path: impl AsRef<Path>) -> MultipartResult<()> {
         Multipart::save_file(self, path).await
@@ -55,9 +177,12 @@ impl FileInput {
             .validate(&files)
     }
 
-    /// Calculate the file size from bytes collected
+    /// Calculate the file size from bytes collected, the recorded size when spilled
     pub fn calculate_size(&self) -> usize {
-        self.bytes.iter().map(|b| b.len()).sum()
+        match self.temp_file {
+            Some(_) => self.size,
+            None => self.bytes.iter().map(|b| b.len()).sum(),
+        }
     }
 
     /// Get the human-readable file size (e.g., "1.2 MB", "300 KB")
@@ -338,6 +463,7 @@ mod tests {
             bytes: vec![Bytes::from_static(&[0; 1024])],
             extension: Some("txt".to_string()),
             content_disposition: create_content_disposition("upload", "test.txt"),
+            temp_file: None,
         };
 
         let cloned = original.clone();
@@ -0,0 +1,52 @@
+use std::fmt::Display;
+
+/// File of a [`FileMapReport`] whose transform failed
+#[derive(Debug)]
+pub struct FileMapFailure<E> {
+    /// position of the file in its field
+    pub index: usize,
+    pub file_name: String,
+    pub error: E,
+}
+
+/// Outcome of [`Multipart::map_files`](crate::Multipart::map_files): the records of the
+/// files transformed successfully, in upload order, and the files that failed
+#[derive(Debug)]
+pub struct FileMapReport<T, E> {
+    pub mapped: Vec<T>,
+    pub failures: Vec<FileMapFailure<E>>,
+}
+
+impl<T, E> Default for FileMapReport<T, E> {
+    fn default() -> Self {
+        Self {
+            mapped: vec![],
+            failures: vec![],
+        }
+    }
+}
+
+impl<T, E> FileMapReport<T, E> {
+    /// Whether every file was transformed
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// The records when every file was transformed, the first failure otherwise
+    pub fn into_result(self) -> Result<Vec<T>, E> {
+        match self.failures.into_iter().next() {
+            Some(failure) => Err(failure.error),
+            None => Ok(self.mapped),
+        }
+    }
+}
+
+impl<T, E: Display> FileMapReport<T, E> {
+    /// `(file name, error message)` of the failed files, e.g. to render in a response
+    pub fn failure_messages(&self) -> Vec<(String, String)> {
+        self.failures
+            .iter()
+            .map(|failure| (failure.file_name.clone(), failure.error.to_string()))
+            .collect()
+    }
+}
@@ -1,13 +1,44 @@
 use crate::result::MultipartResult;
+use crate::sniff::{self, SNIFF_LEN};
 use crate::{FileInput, MultipartError};
 use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct InputError {
     pub name: String,
     pub error: ErrorMessage,
+    /// human-friendly field name registered on the validator
+    pub label: Option<String>,
+    /// message replacing the default one, produced by the validator's message resolver
+    pub message: Option<String>,
 }
 
+impl InputError {
+    pub fn new(name: &str, error: ErrorMessage) -> Self {
+        Self {
+            name: name.to_string(),
+            error,
+            label: None,
+            message: None,
+        }
+    }
+
+    /// The registered label, or the field name with underscores replaced by spaces
+    pub fn label(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => self.name.replace('_', " "),
+        }
+    }
+}
+
+/// Produces a (localized) message for a validation error, `None` keeps the default one
+pub type MessageResolver = fn(&InputError) -> Option<String>;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ErrorMessage {
     NoFiles,
@@ -18,11 +49,32 @@ pub enum ErrorMessage {
     InvalidFileExtension(Option<String>),
     InvalidContentType(String),
     MissingFileExtension(String),
+    /// content not matching the declared or allowed types, with the type recognized
+    /// from its first bytes if any
+    ContentTypeMismatch(Option<String>),
+    /// rejection message of an async rule
+    Custom(String),
+}
+
+type AsyncCheck =
+    dyn Fn(FileInput) -> Pin<Box<dyn Future<Output = Result<(), String>>>> + Send + Sync;
+
+/// Custom check of the files of a field, see [`Validator::add_async_rule`]
+#[derive(Clone)]
+pub struct AsyncRule(Arc<AsyncCheck>);
+
+impl Debug for AsyncRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AsyncRule")
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Validator {
     rules: HashMap<String, FileRules>,
+    async_rules: Vec<(String, AsyncRule)>,
+    labels: HashMap<String, String>,
+    messages: Option<MessageResolver>,
 }
 
 // Struct for File Validation Rules
@@ -51,6 +103,167 @@ pub struct FileRules {
 
     /// Max number of files, this only works when validating through `Multipart` struct
     pub max_files: Option<usize>,
+
+    /// Whether the first bytes of the content must match the declared content type,
+    /// for the types recognized from their signature (images, PDF, archives, media...)
+    pub verify_magic_bytes: bool,
+
+    /// Allowed content types, recognized from the first bytes of the content
+    pub allowed_sniffed_types: Option<Vec<String>>,
+}
+
+impl FileRules {
+    /// Rules of a mandatory field
+    pub fn required() -> Self {
+        Self {
+            required: true,
+            ..Default::default()
+        }
+    }
+
+    /// Rules of an optional field
+    pub fn optional() -> Self {
+        Self::default()
+    }
+
+    pub fn extension_required(mut self) -> Self {
+        self.extension_required = true;
+        self
+    }
+
+    pub fn min_size(mut self, bytes: usize) -> Self {
+        self.min_size = Some(bytes);
+        self
+    }
+
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Allowed extensions, compared case-insensitively
+    pub fn extensions(mut self, extensions: &[&str]) -> Self {
+        self.allowed_extensions = Some(extensions.iter().map(|e| e.to_lowercase()).collect());
+        self
+    }
+
+    /// Allowed content types, compared case-insensitively
+    pub fn content_types(mut self, content_types: &[&str]) -> Self {
+        self.allowed_content_types = Some(content_types.iter().map(|c| c.to_lowercase()).collect());
+        self
+    }
+
+    /// Reject files whose first bytes don't match their declared content type
+    pub fn verify_magic_bytes(mut self) -> Self {
+        self.verify_magic_bytes = true;
+        self
+    }
+
+    /// Allowed content types recognized from the first bytes, e.g. `image/png`; content
+    /// that isn't recognized is rejected
+    pub fn sniffed_types(mut self, content_types: &[&str]) -> Self {
+        self.allowed_sniffed_types = Some(content_types.iter().map(|c| c.to_lowercase()).collect());
+        self
+    }
+
+    pub fn min_files(mut self, count: usize) -> Self {
+        self.min_files = Some(count);
+        self
+    }
+
+    pub fn max_files(mut self, count: usize) -> Self {
+        self.max_files = Some(count);
+        self
+    }
+
+    /// Combine with `other`, whose values take precedence when set.
+    ///
+    /// Useful to derive field rules from a shared base, e.g.
+    /// `images.and(FileRules::required().max_files(1))`.
+    pub fn and(mut self, other: FileRules) -> Self {
+        self.required |= other.required;
+        self.extension_required |= other.extension_required;
+        self.min_size = other.min_size.or(self.min_size);
+        self.max_size = other.max_size.or(self.max_size);
+        self.allowed_extensions = other.allowed_extensions.or(self.allowed_extensions);
+        self.allowed_content_types = other.allowed_content_types.or(self.allowed_content_types);
+        self.min_files = other.min_files.or(self.min_files);
+        self.max_files = other.max_files.or(self.max_files);
+        self.verify_magic_bytes |= other.verify_magic_bytes;
+        self.allowed_sniffed_types = other.allowed_sniffed_types.or(self.allowed_sniffed_types);
+        self
+    }
+}
+
+/// Builds a [`Validator`] once, to be shared by every request of one or several routes
+///
+/// # Example
+/// ```
+/// use foxtive_ntex_multipart::{FileRules, Validator};
+///
+/// let image = FileRules::optional()
+///     .max_size(5 * 1024 * 1024)
+///     .extensions(&["jpg", "png"]);
+///
+/// let validator = Validator::builder()
+///     .rule("avatar", image.clone().and(FileRules::required().max_files(1)))
+///     .rule("gallery", image.max_files(10))
+///     .label("avatar", "Profile photo")
+///     .build();
+///
+/// // `Arc<Validator>`: store it in the app state and pass it to `Multipart::validate`
+/// assert!(validator.validate(&Default::default()).is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ValidatorBuilder {
+    validator: Validator,
+}
+
+impl ValidatorBuilder {
+    pub fn rule(mut self, field: &str, rules: FileRules) -> Self {
+        self.validator.rules.insert(field.to_string(), rules);
+        self
+    }
+
+    pub fn async_rule<F, Fut>(mut self, field: &str, check: F) -> Self
+    where
+        F: Fn(FileInput) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + 'static,
+    {
+        self.validator = self.validator.add_async_rule(field, check);
+        self
+    }
+
+    pub fn label(mut self, field: &str, label: &str) -> Self {
+        self.validator = self.validator.label(field, label);
+        self
+    }
+
+    pub fn labels(mut self, labels: HashMap<String, String>) -> Self {
+        self.validator = self.validator.labels(labels);
+        self
+    }
+
+    pub fn messages(mut self, resolver: MessageResolver) -> Self {
+        self.validator = self.validator.messages(resolver);
+        self
+    }
+
+    /// Add the rules, labels and message resolver of `other`, replacing existing ones
+    pub fn merge(mut self, other: &Validator) -> Self {
+        self.validator = self.validator.merge(other);
+        self
+    }
+
+    pub fn build(self) -> Arc<Validator> {
+        Arc::new(self.validator)
+    }
+}
+
+impl AsRef<Validator> for Validator {
+    fn as_ref(&self) -> &Validator {
+        self
+    }
 }
 
 impl Validator {
@@ -58,22 +271,162 @@ impl Validator {
         Default::default()
     }
 
+    pub fn builder() -> ValidatorBuilder {
+        ValidatorBuilder::default()
+    }
+
+    /// Copy of this validator extended with the rules, labels and message resolver of `other`;
+    /// fields present in both take the values of `other`
+    pub fn merge(&self, other: &Validator) -> Validator {
+        let mut validator = self.clone();
+        validator.rules.extend(other.rules.clone());
+        validator.async_rules.extend(other.async_rules.clone());
+        validator.labels.extend(other.labels.clone());
+        validator.messages = other.messages.or(self.messages);
+        validator
+    }
+
+    /// Rules registered for `field`
+    pub fn rules(&self, field: &str) -> Option<&FileRules> {
+        self.rules.get(field)
+    }
+
+    /// Clones the validator on every call, prefer [`Validator::builder`] when
+    /// adding several rules
     pub fn add_rule(&mut self, field: &str, rules: FileRules) -> Self {
         let mut validator = self.clone();
         validator.rules.insert(field.to_string(), rules);
         validator
     }
 
+    /// Run `check` on every file of `field` after the declarative rules passed, e.g. to
+    /// decode an image header or call an external scanner. The message it fails with is
+    /// reported as a [`MultipartError::ValidationError`], checked by
+    /// [`Validator::validate_async`] and `Multipart::validate`.
+    ///
+    /// # Example
+    /// ```
+    /// use foxtive_ntex_multipart::Validator;
+    ///
+    /// let validator = Validator::builder()
+    ///     .async_rule("avatar", |file| async move {
+    ///         match file.size % 2 {
+    ///             0 => Ok(()),
+    ///             _ => Err(format!("'{}' is not a valid image", file.file_name)),
+    ///         }
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn add_async_rule<F, Fut>(&self, field: &str, check: F) -> Self
+    where
+        F: Fn(FileInput) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + 'static,
+    {
+        let mut validator = self.clone();
+        let check: Arc<AsyncCheck> = Arc::new(move |file| Box::pin(check(file)));
+        validator
+            .async_rules
+            .push((field.to_string(), AsyncRule(check)));
+        validator
+    }
+
+    /// Name the field is referred to in error messages, e.g. "Profile photo" for "profile_photo"
+    pub fn label(mut self, field: &str, label: &str) -> Self {
+        self.labels.insert(field.to_string(), label.to_string());
+        self
+    }
+
+    pub fn labels(mut self, labels: HashMap<String, String>) -> Self {
+        self.labels.extend(labels);
+        self
+    }
+
+    /// Resolve error messages through `resolver`, e.g. to translate them
+    pub fn messages(mut self, resolver: MessageResolver) -> Self {
+        self.messages = Some(resolver);
+        self
+    }
+
     pub fn validate(&self, files: &HashMap<String, Vec<FileInput>>) -> MultipartResult<()> {
         for (field_name, rules) in &self.rules {
             let files = files.get(field_name);
             Self::validate_files(field_name.clone(), files, rules)
-                .map_err(MultipartError::ValidationError)?;
+                .map_err(|err| MultipartError::ValidationError(self.describe(err)))?;
         }
 
         Ok(())
     }
 
+    /// Same as [`Validator::validate`], then run the async rules on the files, one at a time
+    pub async fn validate_async(
+        &self,
+        files: &HashMap<String, Vec<FileInput>>,
+    ) -> MultipartResult<()> {
+        self.validate(files)?;
+
+        for (field_name, AsyncRule(check)) in &self.async_rules {
+            for file in files.get(field_name).into_iter().flatten() {
+                if let Err(message) = check(file.clone()).await {
+                    let err = InputError::new(field_name, ErrorMessage::Custom(message));
+                    return Err(MultipartError::ValidationError(self.describe(err)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check what is known of a file before its content is read: extension, content type
+    /// and the declared size if any
+    pub(crate) fn validate_metadata(
+        &self,
+        file: &FileInput,
+        declared_size: Option<usize>,
+    ) -> MultipartResult<()> {
+        let Some(rules) = self.rules.get(&file.field_name) else {
+            return Ok(());
+        };
+
+        let rules = FileRules {
+            min_size: None,
+            max_size: declared_size.and(rules.max_size),
+            verify_magic_bytes: false,
+            allowed_sniffed_types: None,
+            ..rules.clone()
+        };
+        let file = FileInput {
+            size: declared_size.unwrap_or_default(),
+            ..file.clone()
+        };
+
+        Self::validate_file(rules, &file)
+            .map_err(|err| MultipartError::ValidationError(self.describe(err)))
+    }
+
+    /// Check the first bytes of a file streamed to a storage backend
+    pub(crate) fn validate_head(&self, file: &FileInput, head: &[u8]) -> MultipartResult<()> {
+        let Some(rules) = self.rules.get(&file.field_name) else {
+            return Ok(());
+        };
+
+        Self::validate_sniffed(rules, file, head)
+            .map_err(|err| MultipartError::ValidationError(self.describe(err)))
+    }
+
+    pub(crate) fn too_large(&self, file: &FileInput, max_size: usize) -> MultipartError {
+        let err = InputError::new(&file.field_name, ErrorMessage::FileTooLarge(max_size));
+        MultipartError::ValidationError(self.describe(err))
+    }
+
+    fn describe(&self, mut err: InputError) -> InputError {
+        err.label = self.labels.get(&err.name).cloned();
+        if let Some(resolver) = self.messages {
+            err.message = resolver(&err);
+        }
+
+        err
+    }
+
     fn validate_files(
         field_name: String,
         files: Option<&Vec<FileInput>>,
@@ -81,10 +434,7 @@ impl Validator {
     ) -> Result<(), InputError> {
         if files.is_none() {
             if rules.required {
-                return Err(InputError {
-                    name: field_name,
-                    error: ErrorMessage::NoFiles,
-                });
+                return Err(InputError::new(&field_name, ErrorMessage::NoFiles));
             }
 
             return Ok(());
@@ -95,24 +445,21 @@ impl Validator {
 
         // Validate required
         if rules.required && file_count == 0 {
-            return Err(InputError {
-                name: field_name,
-                error: ErrorMessage::NoFiles,
-            });
+            return Err(InputError::new(&field_name, ErrorMessage::NoFiles));
         }
 
         if file_count < rules.min_files.unwrap_or(0) {
-            return Err(InputError {
-                name: field_name,
-                error: ErrorMessage::TooFewFiles(file_count),
-            });
+            return Err(InputError::new(
+                &field_name,
+                ErrorMessage::TooFewFiles(file_count),
+            ));
         }
 
         if file_count > rules.max_files.unwrap_or(usize::MAX) {
-            return Err(InputError {
-                name: field_name,
-                error: ErrorMessage::TooManyFiles(file_count),
-            });
+            return Err(InputError::new(
+                &field_name,
+                ErrorMessage::TooManyFiles(file_count),
+            ));
         }
 
         for file in files {
@@ -126,45 +473,45 @@ impl Validator {
     fn validate_file(rule: FileRules, file: &FileInput) -> Result<(), InputError> {
         // Validate file extension
         if rule.extension_required && file.extension.is_none() {
-            return Err(InputError {
-                name: file.field_name.to_string(),
-                error: ErrorMessage::MissingFileExtension(file.file_name.clone()),
-            });
+            return Err(InputError::new(
+                &file.field_name,
+                ErrorMessage::MissingFileExtension(file.file_name.clone()),
+            ));
         }
 
         // Validate file size
         if let Some(min_size) = rule.min_size
             && file.size < min_size
         {
-            return Err(InputError {
-                name: file.field_name.to_string(),
-                error: ErrorMessage::FileTooSmall(min_size),
-            });
+            return Err(InputError::new(
+                &file.field_name,
+                ErrorMessage::FileTooSmall(min_size),
+            ));
         }
 
         if let Some(max_size) = rule.max_size
             && file.size > max_size
         {
-            return Err(InputError {
-                name: file.field_name.to_string(),
-                error: ErrorMessage::FileTooLarge(max_size),
-            });
+            return Err(InputError::new(
+                &file.field_name,
+                ErrorMessage::FileTooLarge(max_size),
+            ));
         }
 
         // Validate file extension
         if let Some(allowed_extensions) = &rule.allowed_extensions {
             if let Some(extension) = &file.extension {
                 if !allowed_extensions.contains(&extension.to_lowercase()) {
-                    return Err(InputError {
-                        name: file.field_name.to_string(),
-                        error: ErrorMessage::InvalidFileExtension(file.extension.clone()),
-                    });
+                    return Err(InputError::new(
+                        &file.field_name,
+                        ErrorMessage::InvalidFileExtension(file.extension.clone()),
+                    ));
                 }
             } else {
-                return Err(InputError {
-                    name: file.field_name.to_string(),
-                    error: ErrorMessage::MissingFileExtension(file.file_name.clone()),
-                });
+                return Err(InputError::new(
+                    &file.field_name,
+                    ErrorMessage::MissingFileExtension(file.file_name.clone()),
+                ));
             }
         }
 
@@ -172,12 +519,54 @@ impl Validator {
         if let Some(allowed_content_types) = &rule.allowed_content_types
             && !allowed_content_types.contains(&file.content_type.to_lowercase())
         {
-            return Err(InputError {
-                name: file.field_name.to_string(),
-                error: ErrorMessage::InvalidContentType(format!(
+            return Err(InputError::new(
+                &file.field_name,
+                ErrorMessage::InvalidContentType(format!(
                     "Invalid content type. Allowed content types are: {allowed_content_types:?}"
                 )),
-            });
+            ));
+        }
+
+        if rule.verify_magic_bytes || rule.allowed_sniffed_types.is_some() {
+            let head = file.head(SNIFF_LEN).map_err(|err| {
+                tracing::warn!("[multipart] failed to read '{}': {err}", file.file_name);
+                InputError::new(&file.field_name, ErrorMessage::ContentTypeMismatch(None))
+            })?;
+
+            // the content of streamed files was checked while they were written
+            if let Some(head) = head {
+                Self::validate_sniffed(&rule, file, &head)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compare the type recognized from the first bytes with the declared and allowed ones
+    fn validate_sniffed(rule: &FileRules, file: &FileInput, head: &[u8]) -> Result<(), InputError> {
+        let sniffed = sniff::sniff(head);
+        let mismatch = || {
+            InputError::new(
+                &file.field_name,
+                ErrorMessage::ContentTypeMismatch(sniffed.map(str::to_string)),
+            )
+        };
+
+        if rule.verify_magic_bytes {
+            let matching = match sniffed {
+                Some(sniffed) => sniff::matches(&file.content_type, sniffed),
+                None => !sniff::is_recognizable(&file.content_type),
+            };
+            if !matching {
+                return Err(mismatch());
+            }
+        }
+
+        if let Some(allowed) = &rule.allowed_sniffed_types
+            && !sniffed
+                .is_some_and(|sniffed| allowed.iter().any(|kind| sniff::matches(kind, sniffed)))
+        {
+            return Err(mismatch());
         }
 
         Ok(())
@@ -188,6 +577,7 @@ impl Validator {
 mod tests {
     use super::*;
     use crate::MultipartError;
+    use ntex::util::Bytes;
 
     // Helper function to create a file input
     fn create_file_input(
@@ -429,4 +819,127 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_labels_and_message_resolver() {
+        let rules = FileRules {
+            required: true,
+            ..Default::default()
+        };
+
+        let files = HashMap::new();
+        let validator = Validator::new()
+            .add_rule("profile_photo", rules.clone())
+            .label("profile_photo", "Profile photo");
+
+        let err = validator.validate(&files).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No files were uploaded for field: 'Profile photo'"
+        );
+
+        fn french(err: &InputError) -> Option<String> {
+            match err.error {
+                ErrorMessage::NoFiles => Some(format!("Le champ « {} » est requis", err.label())),
+                _ => None,
+            }
+        }
+
+        let err = validator.messages(french).validate(&files).unwrap_err();
+        assert_eq!(err.to_string(), "Le champ « Profile photo » est requis");
+
+        let err = Validator::new()
+            .add_rule("profile_photo", rules)
+            .validate(&files)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No files were uploaded for field: 'profile photo'"
+        );
+    }
+
+    #[test]
+    fn test_builder_merge_and_rule_composition() {
+        let base = FileRules::optional()
+            .max_size(1024)
+            .extensions(&["JPG", "png"]);
+        let shared = Validator::builder()
+            .rule(
+                "avatar",
+                base.clone().and(FileRules::required().max_size(100)),
+            )
+            .build();
+
+        let avatar = shared.rules("avatar").unwrap();
+        assert!(avatar.required);
+        assert_eq!(avatar.max_size, Some(100));
+        assert_eq!(
+            avatar.allowed_extensions,
+            Some(vec!["jpg".to_string(), "png".to_string()])
+        );
+
+        let extra = Validator::builder()
+            .rule("cover", base)
+            .label("avatar", "Avatar")
+            .build();
+        let merged = Validator::builder().merge(&shared).merge(&extra).build();
+        assert!(merged.rules("avatar").is_some());
+        assert!(merged.rules("cover").is_some());
+
+        let files = HashMap::from([(
+            "avatar".to_string(),
+            vec![create_file_input(
+                "avatar",
+                "a.jpg",
+                500,
+                Some("jpg"),
+                "image/jpeg",
+            )],
+        )]);
+        let err = merged.validate(&files).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "File size is too big for field 'Avatar'. Maximum size is 100 B"
+        );
+    }
+
+    #[test]
+    fn test_validate_magic_bytes() {
+        let file = |content_type: &str, content: &'static [u8]| FileInput {
+            size: content.len(),
+            bytes: vec![Bytes::from_static(content)],
+            ..create_file_input("photo", "photo.png", 0, Some("png"), content_type)
+        };
+        let png: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let exe: &[u8] = b"MZ\x90\0\x03\0\0\0";
+
+        let validate = |rules: FileRules, file: FileInput| {
+            Validator::new()
+                .add_rule("photo", rules)
+                .validate(&HashMap::from([("photo".to_string(), vec![file])]))
+        };
+
+        let verify = FileRules::optional().verify_magic_bytes();
+        assert!(validate(verify.clone(), file("image/png", png)).is_ok());
+        // unrecognized content is fine for types without a signature
+        assert!(validate(verify.clone(), file("text/plain", b"hello")).is_ok());
+
+        let err = validate(verify.clone(), file("image/png", exe)).unwrap_err();
+        match err {
+            MultipartError::ValidationError(InputError { error, .. }) => assert_eq!(
+                error,
+                ErrorMessage::ContentTypeMismatch(Some("application/x-msdownload".to_string()))
+            ),
+            err => panic!("unexpected error: {err}"),
+        }
+        assert!(validate(verify, file("image/png", b"hello")).is_err());
+
+        let images = FileRules::optional().sniffed_types(&["image/png", "image/jpeg"]);
+        assert!(validate(images.clone(), file("application/octet-stream", png)).is_ok());
+        let err = validate(images, file("image/png", b"hello")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The content of the file for field 'photo' does not match its type"
+        );
+    }
 }
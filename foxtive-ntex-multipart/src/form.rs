@@ -0,0 +1,94 @@
+use crate::file_validator::{ErrorMessage, InputError};
+use crate::{FileInput, Multipart, MultipartError, MultipartResult};
+use ntex::http::StatusCode;
+use ntex::web::WebResponseError;
+
+/// Struct built from the fields of a processed [`Multipart`], usually derived with
+/// `#[derive(FromMultipart)]` (`derive` feature), which also makes it a handler argument.
+///
+/// # Example
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # mod example {
+/// use foxtive_ntex_multipart::{FileInput, FromMultipart};
+///
+/// #[derive(FromMultipart)]
+/// struct CreateProduct {
+///     name: String,
+///     price: f64,
+///     description: Option<String>,
+///     image: FileInput,
+///     #[multipart(rename = "gallery[]")]
+///     gallery: Vec<FileInput>,
+/// }
+///
+/// async fn create(product: CreateProduct) -> String {
+///     format!("{} ({} bytes)", product.name, product.image.size)
+/// }
+/// # }
+/// ```
+pub trait FromMultipart: Sized {
+    fn from_multipart(multipart: &Multipart) -> MultipartResult<Self>;
+}
+
+/// File fields of a [`FromMultipart`] struct
+pub trait FormFiles: Sized {
+    fn from_files(multipart: &Multipart, field: &str) -> MultipartResult<Self>;
+}
+
+impl FormFiles for FileInput {
+    fn from_files(multipart: &Multipart, field: &str) -> MultipartResult<Self> {
+        multipart.first_file(field).cloned().ok_or_else(|| {
+            MultipartError::ValidationError(InputError::new(field, ErrorMessage::NoFiles))
+        })
+    }
+}
+
+impl FormFiles for Option<FileInput> {
+    fn from_files(multipart: &Multipart, field: &str) -> MultipartResult<Self> {
+        Ok(multipart.first_file(field).cloned())
+    }
+}
+
+impl FormFiles for Vec<FileInput> {
+    fn from_files(multipart: &Multipart, field: &str) -> MultipartResult<Self> {
+        Ok(multipart.files(field).cloned().unwrap_or_default())
+    }
+}
+
+/// Rendered as plain text when a derived form is extracted with the default error type
+impl WebResponseError for MultipartError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            MultipartError::ValidationError(input) => match input.error {
+                ErrorMessage::InvalidFileExtension(_)
+                | ErrorMessage::InvalidContentType(_)
+                | ErrorMessage::ContentTypeMismatch(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                _ => StatusCode::UNPROCESSABLE_ENTITY,
+            },
+            err if err.is_too_large() => StatusCode::PAYLOAD_TOO_LARGE,
+            MultipartError::UploadSessionNotFound(_) => StatusCode::NOT_FOUND,
+            MultipartError::InvalidUploadTicket(_) => StatusCode::FORBIDDEN,
+            MultipartError::InfectedFile(..) => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl Multipart {
+    /// Read the body of `req` and build `T` from its fields
+    pub async fn extract<T: FromMultipart>(
+        req: &ntex::web::HttpRequest,
+        payload: &mut ntex::http::Payload,
+    ) -> MultipartResult<T> {
+        let mut multipart = Multipart::from_http_request(req, payload).await;
+        multipart.check_content_length()?;
+        multipart.process().await?;
+        T::from_multipart(&multipart)
+    }
+
+    /// Build `T` from the fields of this instance, call after `process()`
+    pub fn parse<T: FromMultipart>(&self) -> MultipartResult<T> {
+        T::from_multipart(self)
+    }
+}
@@ -1,18 +1,65 @@
+mod chunked;
+mod config;
 mod content_disposition;
 mod contract;
 mod data_input;
+mod data_limits;
+#[cfg(feature = "chrono")]
+mod datetime;
+mod duplicate_policy;
+mod field;
 mod file_input;
+mod file_map;
 mod file_validator;
+mod form;
 mod macros;
+mod metrics;
 pub mod multipart;
+mod nested;
 mod result;
+mod scan;
+mod sink;
+mod sniff;
+mod storage;
 #[cfg(test)]
 mod tests;
+mod ticket;
 
+pub use chunked::{
+    ChunkedUploads, CompletedUpload, MemorySessionStore, MultipartUploadBackend, UploadSession,
+    UploadSessionStore, UploadedPart,
+};
+pub use config::MultipartConfig;
 pub use contract::*;
 pub use data_input::DataInput;
-pub use file_input::FileInput;
+pub use data_limits::{DataLimits, MultipartLimit};
+#[cfg(feature = "chrono")]
+pub use datetime::DateTimeFormats;
+pub use duplicate_policy::DuplicatePolicy;
+pub use field::{Field, FileField, TextField};
+pub use file_input::{FileInput, FileReader, TempFile};
+pub use file_map::{FileMapFailure, FileMapReport};
 pub use file_validator::*;
+pub use form::{FormFiles, FromMultipart};
+#[cfg(feature = "derive")]
+pub use foxtive_ntex_multipart_derive::FromMultipart;
+pub use metrics::{UploadEvent, UploadObserver};
 pub use multipart::Multipart;
-pub use result::MultipartError;
+pub use result::{FieldParseError, MultipartError};
+pub use scan::{FileScanner, KvStore, MemoryKvStore, ScanCache, ScanVerdict};
+pub use sink::{FsSink, SunkFile, UploadSink};
+pub use sniff::sniff;
+pub use storage::{FsObject, FsStorage, StorageBackend, StorageObject, StoredFile};
+#[cfg(feature = "tickets")]
+pub use ticket::UploadTickets;
+pub use ticket::{UPLOAD_TICKET_HEADER, UploadTicket};
 pub type MultipartResult<T> = Result<T, MultipartError>;
+
+// lets the code generated by `#[derive(FromMultipart)]` name this crate from within it
+extern crate self as foxtive_ntex_multipart;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use ntex::http::Payload;
+    pub use ntex::web::{FromRequest, HttpRequest};
+}
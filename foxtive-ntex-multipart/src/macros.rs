@@ -8,26 +8,19 @@ macro_rules! impl_post_parseable_from_str {
             impl sealed::Sealed for $t {}
 
             impl PostParseableFromStr for $t {
-                fn parse_from_multipart_str(multipart: &Multipart, field: &str) -> MultipartResult<Self> {
-                    let data_input = multipart.first_data_required(field)?;
-                    let value = data_input.value.trim();
+                fn parse_str(field: &str, value: &str) -> MultipartResult<Self> {
+                    let value = value.trim();
 
                     // Handle empty values
                     if value.is_empty() {
-                        return Err(MultipartError::ParseError(format!(
-                            "Field '{}' is empty and cannot be parsed as {}",
-                            field,
-                            std::any::type_name::<$t>()
-                        )));
+                        return Err(MultipartError::ParseError(
+                            FieldParseError::empty::<$t>(field),
+                        ));
                     }
 
                     value.parse::<$t>().map_err(|e| {
-                        MultipartError::ParseError(format!(
-                            "Failed to parse field '{}' with value '{}' as {}: {}",
-                            field,
-                            value,
-                            std::any::type_name::<$t>(),
-                            e
+                        MultipartError::ParseError(FieldParseError::invalid::<$t>(
+                            field, value, e,
                         ))
                     })
                 }
@@ -86,6 +79,13 @@ impl_post_parseable_from_str!(
 #[cfg(feature = "uuid")]
 impl_post_parseable_from_str!(uuid::Uuid);
 
+// Decimal Support
+#[cfg(feature = "decimal")]
+impl_post_parseable_from_str!(rust_decimal::Decimal);
+
+#[cfg(feature = "bigdecimal")]
+impl_post_parseable_from_str!(bigdecimal::BigDecimal);
+
 /// Helper macro for users to implement PostParseableFromStr for their custom types
 ///
 /// This macro allows users to easily add support for their custom types that implement FromStr.
@@ -164,29 +164,19 @@ macro_rules! impl_post_parseable_for_custom_type {
         impl $crate::sealed::Sealed for $t {}
 
         impl $crate::PostParseableFromStr for $t {
-            fn parse_from_multipart_str(
-                multipart: &$crate::Multipart,
-                field: &str,
-            ) -> $crate::MultipartResult<Self> {
-                let data_input = multipart.first_data_required(field)?;
-                let value = data_input.value.trim();
+            fn parse_str(field: &str, value: &str) -> $crate::MultipartResult<Self> {
+                let value = value.trim();
 
                 // Handle empty values
                 if value.is_empty() {
-                    return Err($crate::MultipartError::ParseError(format!(
-                        "Field '{}' is empty and cannot be parsed as {}",
-                        field,
-                        std::any::type_name::<$t>()
-                    )));
+                    return Err($crate::MultipartError::ParseError(
+                        $crate::FieldParseError::empty::<$t>(field),
+                    ));
                 }
 
                 value.parse::<$t>().map_err(|e| {
-                    $crate::MultipartError::ParseError(format!(
-                        "Failed to parse field '{}' with value '{}' as {}: {}",
-                        field,
-                        value,
-                        std::any::type_name::<$t>(),
-                        e
+                    $crate::MultipartError::ParseError($crate::FieldParseError::invalid::<$t>(
+                        field, value, e,
                     ))
                 })
             }
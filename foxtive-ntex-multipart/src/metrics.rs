@@ -0,0 +1,42 @@
+use ntex::web::HttpRequest;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+static OBSERVER: OnceLock<RwLock<Option<Arc<dyn UploadObserver>>>> = OnceLock::new();
+
+/// Upload activity reported to the [`UploadObserver`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadEvent<'a> {
+    /// a file was received, buffered or streamed to a storage backend
+    File { field: &'a str, bytes: usize },
+    /// the upload was rejected, `reason` is a stable label such as `too_large`, see
+    /// [`MultipartError::reason`](crate::MultipartError::reason)
+    Rejected { reason: &'static str },
+    /// a file was written to a storage backend in `elapsed`
+    Stored { elapsed: Duration },
+}
+
+/// Receiver of the upload activity of every [`Multipart`](crate::Multipart), e.g. to
+/// export metrics. Installed with [`Multipart::set_observer`](crate::Multipart::set_observer).
+pub trait UploadObserver: Send + Sync + 'static {
+    fn observe(&self, route: &str, event: UploadEvent<'_>);
+
+    /// Label of the route targeted by `req`, the path by default; prefer route templates
+    /// to keep the number of labels bounded
+    fn route(&self, req: &HttpRequest) -> String {
+        req.path().to_string()
+    }
+}
+
+pub(crate) fn set_observer(observer: Arc<dyn UploadObserver>) {
+    let lock = OBSERVER.get_or_init(|| RwLock::new(None));
+    if let Ok(mut current) = lock.write() {
+        *current = Some(observer);
+    }
+}
+
+pub(crate) fn observer() -> Option<Arc<dyn UploadObserver>> {
+    OBSERVER
+        .get()
+        .and_then(|lock| lock.read().ok().and_then(|current| current.clone()))
+}
@@ -1,35 +1,98 @@
 use std::collections::HashMap;
-use std::convert::Infallible;
+use std::future::Future;
 use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
 
+use crate::config::MultipartConfig;
 use crate::content_disposition::ContentDisposition;
-use crate::contract::PostParseable;
+use crate::contract::{PostParseable, PostParseableFromStr};
 use crate::data_input::DataInput;
-use crate::file_input::FileInput;
+use crate::data_limits::{DataLimits, MultipartLimit};
+use crate::duplicate_policy::DuplicatePolicy;
+use crate::field::StreamState;
+use crate::file_input::{FileInput, TempFile};
+use crate::file_map::{FileMapFailure, FileMapReport};
 use crate::file_validator::Validator;
-use crate::result::{MultipartError, MultipartResult};
+use crate::metrics::{self, UploadEvent, UploadObserver};
+use crate::result::{FieldParseError, MultipartError, MultipartResult};
+use crate::scan::{FileScanner, ScanVerdict};
+use crate::sink::UploadSink;
+use crate::sniff::SNIFF_LEN;
+use crate::storage::{StorageBackend, StorageObject, StoredFile, unique_id};
+use crate::ticket::UploadTicket;
 use futures::StreamExt;
 use ntex::http::Payload;
+use ntex::http::header::CONTENT_LENGTH;
+use ntex::util::Bytes;
 use ntex::web::{FromRequest, HttpRequest};
 use ntex_multipart::Multipart as NtexMultipart;
-use tokio::fs::File;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use tokio::fs::{File, OpenOptions};
 use tokio::io::AsyncWriteExt;
+use tracing::field::Empty;
+use tracing::{Instrument, debug, info_span, warn};
+
+/// Size a file part declares in its own `Content-Length` header
+pub(crate) fn declared_size(field: &ntex_multipart::Field) -> Option<usize> {
+    field
+        .headers()
+        .get("content-length")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Fields read so far, checked against the data and multipart limits
+#[derive(Default)]
+struct DataUsage {
+    fields: usize,
+    /// size of the text fields
+    size: usize,
+    files: usize,
+    /// size of the files
+    received: usize,
+}
+
+impl DataUsage {
+    fn body_size(&self) -> usize {
+        self.size + self.received
+    }
+}
 
 pub struct Multipart {
     pub(crate) multipart: NtexMultipart,
     pub(crate) file_inputs: HashMap<String, Vec<FileInput>>, // Store multiple files for the same field
     pub(crate) data_inputs: HashMap<String, Vec<DataInput>>, // Store multiple data entries for the same field
+    pub(crate) data_limits: DataLimits,
+    pub(crate) duplicate_policy: DuplicatePolicy,
+    pub(crate) config: MultipartConfig,
+    pub(crate) ticket: Option<UploadTicket>,
+    /// label reported to the upload observer, if any
+    pub(crate) route: Option<String>,
+    /// declared size of the request body, if known
+    pub(crate) content_length: Option<usize>,
+    pub(crate) progress: Option<Rc<ProgressHandler>>,
+    /// parts read with `next_field()`
+    pub(crate) streamed: StreamState,
 }
 
+/// Called with the field being read, the bytes received so far and the size of the request
+pub(crate) type ProgressHandler = dyn Fn(&str, usize, Option<usize>);
+
+/// Rejects the requests declaring a body over [`DataLimits::max_body_size`] before the
+/// handler runs, the other limits are enforced while the body is read
 impl<Err> FromRequest<Err> for Multipart {
-    type Error = Infallible;
+    type Error = MultipartError;
 
     async fn from_request(
         req: &HttpRequest,
         payload: &mut Payload,
-    ) -> Result<Multipart, Infallible> {
-        let multipart = NtexMultipart::new(req.headers(), payload.take());
-        Ok(Multipart::new(multipart).await)
+    ) -> Result<Multipart, MultipartError> {
+        let multipart = Multipart::from_http_request(req, payload).await;
+        multipart.check_content_length()?;
+        Ok(multipart)
     }
 }
 
@@ -39,79 +102,595 @@ impl Multipart {
             multipart,
             file_inputs: Default::default(),
             data_inputs: Default::default(),
+            data_limits: DataLimits::current_default(),
+            duplicate_policy: DuplicatePolicy::current_default(),
+            config: MultipartConfig::current_default(),
+            ticket: None,
+            route: None,
+            content_length: None,
+            progress: None,
+            streamed: Default::default(),
+        }
+    }
+
+    /// Read with the limits and config registered as app state, e.g. by `foxtive-ntex` from
+    /// its `ServerConfig`, the process defaults otherwise
+    pub(crate) async fn from_http_request(req: &HttpRequest, payload: &mut Payload) -> Multipart {
+        let multipart = NtexMultipart::new(req.headers(), payload.take());
+        let mut multipart = Multipart::new(multipart).await;
+        if let Some(limits) = req.app_state::<DataLimits>() {
+            multipart.data_limits = limits.clone();
+        }
+        if let Some(policy) = req.app_state::<DuplicatePolicy>() {
+            multipart.duplicate_policy = *policy;
+        }
+        if let Some(config) = req.app_state::<MultipartConfig>() {
+            multipart.config = config.clone();
+        }
+        multipart.route = metrics::observer().map(|observer| observer.route(req));
+        multipart.content_length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        multipart
+    }
+
+    /// Fail when the declared size of the body is over the body size limit
+    pub(crate) fn check_content_length(&self) -> MultipartResult<()> {
+        match self.content_length {
+            Some(length) => DataLimits::check(
+                MultipartLimit::BodySize,
+                self.data_limits.max_body_size,
+                length,
+            ),
+            None => Ok(()),
         }
     }
 
+    /// How repeated text fields are handled, call before `process()`
+    pub fn duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Replace the body and text field limits of this instance, call before `process()`,
+    /// `next_field()` or `stream_files_to()`
+    pub fn data_limits(mut self, limits: DataLimits) -> Self {
+        self.data_limits = limits;
+        self
+    }
+
+    /// Replace the file buffering config of this instance, e.g. to spill large files to
+    /// disk, call before `process()`
+    pub fn config(mut self, config: MultipartConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Accept only the files allowed by a verified upload ticket: its field, content types
+    /// and size budget are checked before the bytes of each file are read, call before
+    /// `process()` or `stream_files_to()`
+    pub fn ticket(mut self, ticket: UploadTicket) -> Self {
+        self.ticket = Some(ticket);
+        self
+    }
+
+    /// Call `handler` as the chunks of the body arrive with the name of the field being read,
+    /// the bytes of fields and files received so far and the `Content-Length` of the request,
+    /// if known, e.g. to report the upload progress over SSE or a WebSocket. The length
+    /// counts the part headers and boundaries too, so the received bytes end a bit below it.
+    /// Call before `process()`, `process_into()` or `stream_files_to()`
+    pub fn with_progress(mut self, handler: impl Fn(&str, usize, Option<usize>) + 'static) -> Self {
+        self.progress = Some(Rc::new(handler));
+        self
+    }
+
+    /// Record an `extractor` span (size, files, fields, duration, outcome) while
+    /// processing, enabled by default. Changes the default [`MultipartConfig`], the
+    /// config registered as app state decides for the requests of its app
+    pub fn set_tracing(enabled: bool) {
+        MultipartConfig::set_default(MultipartConfig::current_default().tracing(enabled));
+    }
+
+    /// Report the upload activity of every request to `observer`, e.g. to export metrics
+    pub fn set_observer(observer: impl UploadObserver) {
+        metrics::set_observer(Arc::new(observer));
+    }
+
     pub async fn process(&mut self) -> Result<&mut Multipart, MultipartError> {
-        while let Some(item) = self.multipart.next().await {
-            let mut field = item.map_err(MultipartError::NtexError)?;
+        if !self.config.tracing {
+            let result = self.read_and_validate().await;
+            self.observe_result(&result);
+            result?;
+            return Ok(self);
+        }
 
-            if let Some(content_disposition) = field.headers().get("content-disposition") {
-                let content_disposition = content_disposition.to_str().ok();
-                if let Some(content_disposition) = content_disposition {
-                    let content_disposition = ContentDisposition::create(content_disposition);
+        let span = info_span!(
+            "extractor",
+            extractor = "Multipart",
+            size = Empty,
+            files = Empty,
+            fields = Empty,
+            elapsed_ms = Empty,
+            outcome = Empty
+        );
+
+        let started = Instant::now();
+        let result = self.read_and_validate().instrument(span.clone()).await;
+
+        let size: usize = self.file_inputs.values().flatten().map(|f| f.size).sum();
+        span.record("size", size);
+        span.record(
+            "files",
+            self.file_inputs.values().map(Vec::len).sum::<usize>(),
+        );
+        span.record("fields", self.data_inputs.len());
+        span.record("elapsed_ms", started.elapsed().as_secs_f64() * 1000.0);
+        match &result {
+            Ok(_) => span.record("outcome", "ok"),
+            Err(err) => span.record("outcome", tracing::field::display(err)),
+        };
+
+        self.observe_result(&result);
+        result?;
+        Ok(self)
+    }
 
-                    if !content_disposition.has_name_field() {
-                        continue;
-                    }
+    /// Read the body, then check it with the default validator if any
+    async fn read_and_validate(&mut self) -> MultipartResult<()> {
+        self.read_fields().await?;
+        match self.config.validator.clone() {
+            Some(validator) => validator.validate_async(&self.file_inputs).await,
+            None => Ok(()),
+        }
+    }
 
-                    // Process form fields (non-file fields)
-                    if !content_disposition.is_file_field() {
-                        let value = self.collect_data_field_value(&mut field).await;
-                        let field_name =
-                            content_disposition.get_variable("name").unwrap_or_default();
-
-                        // Insert or append to the data_inputs array for this field
-                        self.data_inputs
-                            .entry(field_name.to_string())
-                            .or_default()
-                            .push(DataInput {
-                                value,
-                                name: field_name.to_string(),
-                            });
-
-                        continue;
-                    }
+    async fn read_fields(&mut self) -> Result<(), MultipartError> {
+        let mut usage = DataUsage::default();
 
-                    // Process file fields
-                    let mut info = FileInput::create(field.headers(), content_disposition)?;
-                    let mut total_size = 0;
-                    let mut bytes = Vec::new();
+        while let Some((mut field, mut info)) = self.next_file_part(&mut usage).await? {
+            if let Some(ticket) = &self.ticket {
+                ticket.admit(&info, declared_size(&field))?;
+            }
 
-                    // Collect all file chunks
-                    while let Some(chunk) = field.next().await {
-                        let data = chunk.unwrap();
-                        total_size += data.len();
+            let mut total_size = 0;
+            let mut bytes = Vec::new();
+            let mut spilled: Option<(TempFile, File)> = None;
+
+            // Collect all file chunks, moving them to disk past the memory threshold
+            while let Some(chunk) = field.next().await {
+                let data = chunk.map_err(MultipartError::NtexError)?;
+                total_size += data.len();
+                usage.received += data.len();
+                self.report_progress(&info.field_name, &usage);
+                self.check_file_size(total_size, &usage)?;
+                if let Some(ticket) = &self.ticket {
+                    ticket.spend(usage.received)?;
+                }
+
+                match &mut spilled {
+                    Some((_, file)) => file.write_all(&data).await?,
+                    None if self.config.spills(total_size) => {
                         bytes.push(data);
+                        spilled = Some(self.spill(&bytes).await?);
+                        bytes.clear();
                     }
+                    None => bytes.push(data),
+                }
+            }
+
+            info.size = total_size;
+            info.bytes = bytes;
+            if let Some((temp_file, mut file)) = spilled {
+                file.flush().await?;
+                debug!(
+                    "[multipart] '{}' ({total_size} bytes) spilled to '{}'",
+                    info.file_name,
+                    temp_file.path().display()
+                );
+                info.temp_file = Some(temp_file);
+            }
+            self.observe(UploadEvent::File {
+                field: &info.field_name,
+                bytes: total_size,
+            });
+
+            // Insert or append file input to the corresponding field
+            self.file_inputs
+                .entry(info.field_name.clone())
+                .or_default()
+                .push(info);
+        }
+
+        Ok(())
+    }
+
+    /// Move the chunks buffered so far to a new temporary file
+    async fn spill(&self, chunks: &[Bytes]) -> MultipartResult<(TempFile, File)> {
+        tokio::fs::create_dir_all(&self.config.temp_dir).await?;
+        // named from the id alone, the client picks the file name and extension
+        let path = self.config.temp_dir.join(format!("upload-{}", unique_id()));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(&path).await?;
+        // owned right away so the file is removed if the copy below fails
+        let temp_file = TempFile::new(&path);
+        for chunk in chunks {
+            file.write_all(chunk).await?;
+        }
 
-                    info.size = total_size;
-                    info.bytes = bytes;
+        Ok((temp_file, file))
+    }
+
+    /// Read parts up to the next file, storing the text fields met on the way
+    async fn next_file_part(
+        &mut self,
+        usage: &mut DataUsage,
+    ) -> MultipartResult<Option<(ntex_multipart::Field, FileInput)>> {
+        while let Some(item) = self.multipart.next().await {
+            let mut field = item.map_err(MultipartError::NtexError)?;
+
+            let Some(content_disposition) = field
+                .headers()
+                .get("content-disposition")
+                .and_then(|value| value.to_str().ok())
+            else {
+                continue;
+            };
+
+            let content_disposition = ContentDisposition::create(content_disposition);
+            if !content_disposition.has_name_field() {
+                continue;
+            }
 
-                    // Insert or append file input to the corresponding field
-                    self.file_inputs
-                        .entry(info.field_name.clone())
-                        .or_default()
-                        .push(info);
+            if content_disposition.is_file_field() {
+                usage.files += 1;
+                DataLimits::check(
+                    MultipartLimit::Files,
+                    self.data_limits.max_files,
+                    usage.files,
+                )?;
+                if let Some(declared) = declared_size(&field) {
+                    DataLimits::check(
+                        MultipartLimit::FileSize,
+                        self.data_limits.max_file_size,
+                        declared,
+                    )?;
                 }
+                let info = FileInput::create(field.headers(), content_disposition)?;
+                return Ok(Some((field, info)));
             }
+
+            // Process form fields (non-file fields)
+            let field_name = content_disposition.get_variable("name").unwrap_or_default();
+
+            usage.fields += 1;
+            if usage.fields > self.data_limits.max_fields {
+                return Err(MultipartError::TooManyDataFields(
+                    self.data_limits.max_fields,
+                ));
+            }
+
+            let value = self
+                .collect_data_field_value(&mut field, field_name, usage)
+                .await?;
+
+            self.insert_data(DataInput {
+                value,
+                name: field_name.to_string(),
+            })?;
+        }
+
+        Ok(None)
+    }
+
+    /// Same as [`Multipart::process`], handing the file chunks to `sink` as they are read
+    /// instead of holding them. Afterwards, `files()` and friends describe the files,
+    /// without bytes. On failure, the sink is aborted.
+    ///
+    /// # Example
+    /// ```
+    /// use foxtive_ntex_multipart::{FsSink, Multipart, MultipartResult, SunkFile};
+    ///
+    /// async fn upload(multipart: &mut Multipart) -> MultipartResult<Vec<SunkFile>> {
+    ///     let mut sink = FsSink::new("/var/uploads");
+    ///     multipart.process_into(&mut sink).await?;
+    ///     Ok(sink.into_files())
+    /// }
+    /// ```
+    pub async fn process_into<S: UploadSink>(
+        &mut self,
+        sink: &mut S,
+    ) -> MultipartResult<&mut Multipart> {
+        if let Err(err) = self.sink_parts(sink).await {
+            self.observe(UploadEvent::Rejected {
+                reason: err.reason(),
+            });
+            if let Err(abort_err) = sink.abort().await {
+                warn!("failed to abort the upload sink: {abort_err}");
+            }
+            return Err(err);
         }
 
         Ok(self)
     }
 
-    async fn collect_data_field_value(&self, field: &mut ntex_multipart::Field) -> String {
-        let mut value = String::new();
+    async fn sink_parts<S: UploadSink>(&mut self, sink: &mut S) -> MultipartResult<()> {
+        let mut usage = DataUsage::default();
+
+        while let Some((mut field, mut info)) = self.next_file_part(&mut usage).await? {
+            if let Some(ticket) = &self.ticket {
+                ticket.admit(&info, declared_size(&field))?;
+            }
+
+            while let Some(chunk) = field.next().await {
+                let chunk = chunk.map_err(MultipartError::NtexError)?;
+                info.size += chunk.len();
+                usage.received += chunk.len();
+                self.report_progress(&info.field_name, &usage);
+                self.check_file_size(info.size, &usage)?;
+                if let Some(ticket) = &self.ticket {
+                    ticket.spend(usage.received)?;
+                }
+                sink.write_chunk(&info, &chunk).await?;
+            }
+
+            sink.finalize(&info).await?;
+            self.observe(UploadEvent::File {
+                field: &info.field_name,
+                bytes: info.size,
+            });
+
+            self.file_inputs
+                .entry(info.field_name.clone())
+                .or_default()
+                .push(info);
+        }
+
+        Ok(())
+    }
+
+    /// Stream the files straight to `backend` instead of buffering them, returning where
+    /// they were stored. Text fields are read as with [`Multipart::process`].
+    ///
+    /// Extensions, content types and declared sizes are checked against `validator` before
+    /// anything is written, the size again while streaming; file counts, required fields
+    /// and minimum sizes once every part is read. On failure, the partial object and
+    /// the files already stored by this request are deleted.
+    ///
+    /// After success, `files()` and friends describe the stored files, without bytes.
+    pub async fn stream_files_to<B: StorageBackend>(
+        &mut self,
+        backend: &B,
+        validator: impl AsRef<Validator>,
+    ) -> MultipartResult<Vec<StoredFile>> {
+        let validator = validator.as_ref();
+        let mut stored = Vec::new();
+
+        let result = match self.stream_parts(backend, validator, &mut stored).await {
+            Ok(_) => validator.validate_async(&self.file_inputs).await,
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            self.observe(UploadEvent::Rejected {
+                reason: err.reason(),
+            });
+            for file in &stored {
+                if let Err(delete_err) = backend.delete(&file.key).await {
+                    warn!(
+                        "failed to delete uploaded object '{}': {delete_err}",
+                        file.key
+                    );
+                }
+            }
+            return Err(err);
+        }
+
+        Ok(stored)
+    }
+
+    async fn stream_parts<B: StorageBackend>(
+        &mut self,
+        backend: &B,
+        validator: &Validator,
+        stored: &mut Vec<StoredFile>,
+    ) -> MultipartResult<()> {
+        let mut usage = DataUsage::default();
+
+        while let Some((mut field, mut info)) = self.next_file_part(&mut usage).await? {
+            let declared = declared_size(&field);
+            if let Some(ticket) = &self.ticket {
+                ticket.admit(&info, declared)?;
+            }
+            validator.validate_metadata(&info, declared)?;
+
+            let max_size = validator
+                .rules(&info.field_name)
+                .and_then(|rules| rules.max_size);
+
+            let key = backend.key_for(&info);
+            let started = Instant::now();
+            let mut object = backend.create(&key, &info).await?;
+            let mut hasher = Sha256::new();
+            let mut head = Vec::with_capacity(SNIFF_LEN);
+
+            while let Some(chunk) = field.next().await {
+                let written = match chunk {
+                    Ok(chunk) => {
+                        info.size += chunk.len();
+                        usage.received += chunk.len();
+                        self.report_progress(&info.field_name, &usage);
+                        let spent = match &self.ticket {
+                            Some(ticket) => self
+                                .check_file_size(info.size, &usage)
+                                .and_then(|_| ticket.spend(usage.received)),
+                            None => self.check_file_size(info.size, &usage),
+                        };
+                        match max_size.is_some_and(|max| info.size > max) {
+                            true => Err(validator.too_large(&info, max_size.unwrap_or_default())),
+                            false if spent.is_err() => spent,
+                            false => {
+                                hasher.update(&chunk);
+                                match self.sniff_head(validator, &info, &mut head, &chunk) {
+                                    Ok(_) => object.write(&chunk).await,
+                                    Err(err) => Err(err),
+                                }
+                            }
+                        }
+                    }
+                    Err(err) => Err(MultipartError::NtexError(err)),
+                };
+
+                if let Err(err) = written {
+                    if let Err(abort_err) = object.abort().await {
+                        warn!("failed to abort uploaded object '{key}': {abort_err}");
+                    }
+                    return Err(err);
+                }
+            }
+
+            // files shorter than the sniffed length are checked once complete
+            if head.len() < SNIFF_LEN
+                && let Err(err) = validator.validate_head(&info, &head)
+            {
+                if let Err(abort_err) = object.abort().await {
+                    warn!("failed to abort uploaded object '{key}': {abort_err}");
+                }
+                return Err(err);
+            }
+
+            object.commit().await?;
+            self.observe(UploadEvent::Stored {
+                elapsed: started.elapsed(),
+            });
+            self.observe(UploadEvent::File {
+                field: &info.field_name,
+                bytes: info.size,
+            });
+
+            let sha256 = hasher
+                .finalize()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+
+            stored.push(StoredFile {
+                field_name: info.field_name.clone(),
+                file_name: info.file_name.clone(),
+                key,
+                size: info.size,
+                content_type: info.content_type.clone(),
+                extension: info.extension.clone(),
+                sha256,
+            });
+
+            self.file_inputs
+                .entry(info.field_name.clone())
+                .or_default()
+                .push(info);
+        }
+
+        Ok(())
+    }
+
+    /// Collect the first bytes of a streamed file, checking them once complete
+    fn sniff_head(
+        &self,
+        validator: &Validator,
+        info: &FileInput,
+        head: &mut Vec<u8>,
+        chunk: &[u8],
+    ) -> MultipartResult<()> {
+        if head.len() == SNIFF_LEN {
+            return Ok(());
+        }
+
+        head.extend_from_slice(&chunk[..chunk.len().min(SNIFF_LEN - head.len())]);
+        match head.len() == SNIFF_LEN {
+            true => validator.validate_head(info, head),
+            false => Ok(()),
+        }
+    }
+
+    /// Check a file of `size` bytes so far against the multipart limits
+    fn check_file_size(&self, size: usize, usage: &DataUsage) -> MultipartResult<()> {
+        DataLimits::check(
+            MultipartLimit::FileSize,
+            self.data_limits.max_file_size,
+            size,
+        )?;
+        DataLimits::check(
+            MultipartLimit::BodySize,
+            self.data_limits.max_body_size,
+            usage.body_size(),
+        )
+    }
+
+    /// Store a text field according to the duplicate policy
+    fn insert_data(&mut self, input: DataInput) -> MultipartResult<()> {
+        let inputs = self.data_inputs.entry(input.name.clone()).or_default();
+        if inputs.is_empty() {
+            inputs.push(input);
+            return Ok(());
+        }
+
+        match self.duplicate_policy {
+            DuplicatePolicy::FirstWins => {}
+            DuplicatePolicy::LastWins => inputs[0] = input,
+            DuplicatePolicy::Collect => inputs.push(input),
+            DuplicatePolicy::Reject => return Err(MultipartError::DuplicateField(input.name)),
+        }
+
+        Ok(())
+    }
+
+    /// Read a text field, failing as soon as it exceeds its limit or the
+    /// budget shared by all text fields
+    async fn collect_data_field_value(
+        &self,
+        field: &mut ntex_multipart::Field,
+        name: &str,
+        usage: &mut DataUsage,
+    ) -> MultipartResult<String> {
+        let limit = self.data_limits.limit_for(name);
+        let mut value = Vec::new();
+
         while let Some(chunk) = field.next().await {
             if let Ok(chunk_data) = chunk {
-                value.push_str(&String::from_utf8_lossy(&chunk_data));
+                usage.size += chunk_data.len();
+                self.report_progress(name, usage);
+                DataLimits::check(
+                    MultipartLimit::BodySize,
+                    self.data_limits.max_body_size,
+                    usage.body_size(),
+                )?;
+                if value.len() + chunk_data.len() > limit {
+                    return Err(MultipartError::DataFieldTooLarge(name.to_string(), limit));
+                }
+                if usage.size > self.data_limits.max_total_size {
+                    return Err(MultipartError::DataBudgetExceeded(
+                        self.data_limits.max_total_size,
+                    ));
+                }
+
+                value.extend_from_slice(&chunk_data);
             }
         }
 
-        value
+        Ok(String::from_utf8_lossy(&value).into_owned())
     }
 
     pub async fn save_file(file_input: &FileInput, path: impl AsRef<Path>) -> MultipartResult<()> {
+        if let Some(temp_file) = &file_input.temp_file {
+            tokio::fs::copy(temp_file.path(), path).await?;
+            return Ok(());
+        }
+
         let mut file = File::create(path).await?;
 
         // Write all bytes in a single batch
@@ -151,6 +730,60 @@ impl Multipart {
         self.post(field).ok()
     }
 
+    /// Get every value of a repeated form field, parsed as `T`, e.g. `tags` sent three times.
+    /// A value that can't be parsed fails with a [`MultipartError::ParseError`] holding its
+    /// position. Requires [`DuplicatePolicy::Collect`] (the default) to see all the values.
+    /// Usage: post_vec::<String>("tags"), post_vec::<u32>("ids[]")
+    pub fn post_vec<T>(&self, field: &str) -> MultipartResult<Vec<T>>
+    where
+        T: PostParseableFromStr,
+    {
+        let inputs = self
+            .data_inputs
+            .get(field)
+            .filter(|inputs| !inputs.is_empty())
+            .ok_or(MultipartError::MissingDataField(field.to_string()))?;
+
+        inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| {
+                T::parse_str(field, &input.value).map_err(|err| match err {
+                    MultipartError::ParseError(err) => MultipartError::ParseError(err.at(index)),
+                    err => err,
+                })
+            })
+            .collect()
+    }
+
+    /// Get every value of a repeated form field, `None` if missing or one can't be parsed
+    /// Usage: post_vec_opt::<String>("tags")
+    pub fn post_vec_opt<T>(&self, field: &str) -> Option<Vec<T>>
+    where
+        T: PostParseableFromStr,
+    {
+        self.post_vec(field).ok()
+    }
+
+    /// Get a form field holding JSON, e.g. a `metadata` part sent along the files,
+    /// deserialized as `T`
+    /// Usage: post_json::<Metadata>("metadata"), post_json::<Vec<u32>>("ids")
+    pub fn post_json<T>(&self, field: &str) -> MultipartResult<T>
+    where
+        T: DeserializeOwned,
+    {
+        let value = self.first_data_required(field)?.value.trim();
+        if value.is_empty() {
+            return Err(MultipartError::ParseError(FieldParseError::empty::<T>(
+                field,
+            )));
+        }
+
+        serde_json::from_str(value).map_err(|err| {
+            MultipartError::ParseError(FieldParseError::invalid::<T>(field, value, err))
+        })
+    }
+
     /// Get all data inputs
     pub fn all_data(&self) -> &HashMap<String, Vec<DataInput>> {
         &self.data_inputs
@@ -192,15 +825,117 @@ impl Multipart {
         self.file_inputs.get(field).and_then(|files| files.first())
     }
 
+    fn report_progress(&self, field: &str, usage: &DataUsage) {
+        if let Some(progress) = &self.progress {
+            progress(field, usage.body_size(), self.content_length);
+        }
+    }
+
+    fn observe(&self, event: UploadEvent<'_>) {
+        if let Some(observer) = metrics::observer() {
+            observer.observe(self.route.as_deref().unwrap_or("unknown"), event);
+        }
+    }
+
+    pub(crate) fn observe_result<T>(&self, result: &MultipartResult<T>) {
+        if let Err(err) = result {
+            self.observe(UploadEvent::Rejected {
+                reason: err.reason(),
+            });
+        }
+    }
+
     /// Check if a field has any files
     pub fn has_file(&self, field: &str) -> bool {
         self.file_inputs.contains_key(field)
     }
 
-    /// Validate all files against the provided rules
-    pub async fn validate(&mut self, validator: Validator) -> MultipartResult<&mut Multipart> {
+    /// Validate all files against the provided rules, accepts a `Validator`, a reference
+    /// or the `Arc<Validator>` produced by `Validator::builder()`
+    pub async fn validate(
+        &mut self,
+        validator: impl AsRef<Validator>,
+    ) -> MultipartResult<&mut Multipart> {
         self.process().await?;
-        validator.validate(&self.file_inputs).map(|_| self)
+        let result = validator.as_ref().validate_async(&self.file_inputs).await;
+        self.observe_result(&result);
+        result.map(|_| self)
+    }
+
+    /// Scan all files with `scanner`, rejecting the request with
+    /// [`MultipartError::InfectedFile`] on the first threat found
+    pub async fn scan(&mut self, scanner: &impl FileScanner) -> MultipartResult<&mut Multipart> {
+        self.process().await?;
+        for file in self.file_inputs.values().flatten() {
+            if let ScanVerdict::Infected(threat) = scanner.scan(file).await? {
+                warn!(
+                    "[multipart] '{}' of field '{}' is infected: {threat}",
+                    file.file_name, file.field_name
+                );
+                let err = MultipartError::InfectedFile(file.file_name.clone(), threat);
+                self.observe(UploadEvent::Rejected {
+                    reason: err.reason(),
+                });
+                return Err(err);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Transform every file of `field` with `transform`, e.g. upload it and return the
+    /// database record. Files are handled one at a time in upload order, a failure doesn't
+    /// stop the others: the report lists the records and the failed files.
+    ///
+    /// # Example
+    /// ```
+    /// use foxtive_ntex_multipart::{FileMapReport, Multipart, MultipartResult};
+    ///
+    /// struct Document {
+    ///     name: String,
+    ///     size: usize,
+    /// }
+    ///
+    /// async fn documents(multipart: &mut Multipart) -> MultipartResult<FileMapReport<Document, String>> {
+    ///     multipart
+    ///         .map_files("documents", |file| async move {
+    ///             // upload to the object store...
+    ///             Ok(Document {
+    ///                 name: file.file_name.clone(),
+    ///                 size: file.size,
+    ///             })
+    ///         })
+    ///         .await
+    /// }
+    /// ```
+    pub async fn map_files<T, E, F, Fut>(
+        &mut self,
+        field: &str,
+        mut transform: F,
+    ) -> MultipartResult<FileMapReport<T, E>>
+    where
+        F: FnMut(FileInput) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.process().await?;
+
+        let mut report = FileMapReport::default();
+        let files = self.file_inputs.get(field).cloned().unwrap_or_default();
+        for (index, file) in files.into_iter().enumerate() {
+            let file_name = file.file_name.clone();
+            match transform(file).await {
+                Ok(record) => report.mapped.push(record),
+                Err(error) => {
+                    debug!("[multipart] mapping '{file_name}' of field '{field}' failed");
+                    report.failures.push(FileMapFailure {
+                        index,
+                        file_name,
+                        error,
+                    });
+                }
+            }
+        }
+
+        Ok(report)
     }
 
     /// Add test data to multipart instance (for testing purposes only)
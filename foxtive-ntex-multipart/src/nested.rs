@@ -0,0 +1,283 @@
+use crate::{Multipart, MultipartError, MultipartResult};
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, Deserializer, Error as _, IntoDeserializer, Visitor};
+use serde_json::{Map, Value};
+
+/// Highest index read as an array position, higher ones are object keys so `items[99999]`
+/// can't allocate a huge array
+const MAX_ARRAY_INDEX: usize = 100;
+
+/// Part of a bracketed field name: `items[0][qty]`, `tags[]`
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Push,
+}
+
+impl Multipart {
+    /// Fold the text fields into a tree following their bracket notation: `address[street]`
+    /// becomes `{"address": {"street": ..}}`, `items[0][qty]` an array of objects and
+    /// `tags[]` an array of values. Values are strings, a field sent more than once becomes an
+    /// array. Call after `process()`.
+    ///
+    /// Use explicit indexes for arrays of objects, each `[]` starts a new element.
+    pub fn nested(&self) -> MultipartResult<Value> {
+        let mut names: Vec<_> = self.data_inputs.keys().collect();
+        names.sort();
+
+        let mut root = Value::Object(Map::new());
+        for name in names {
+            let segments = parse_name(name);
+            for input in &self.data_inputs[name] {
+                insert(&mut root, &segments, input.value.clone()).map_err(|_| {
+                    MultipartError::InvalidNestedForm(format!(
+                        "field '{name}' conflicts with another field"
+                    ))
+                })?;
+            }
+        }
+
+        compact(&mut root);
+        Ok(root)
+    }
+
+    /// Deserialize the [`Multipart::nested`] tree of the text fields into `T`, parsing the
+    /// numbers and booleans it expects. Empty values are `None` for `Option` fields.
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[derive(Deserialize)]
+    /// struct Order {
+    ///     address: Address,       // address[street], address[city]
+    ///     items: Vec<Item>,       // items[0][sku], items[0][qty]
+    ///     tags: Vec<String>,      // tags[]
+    /// }
+    ///
+    /// let order: Order = multipart.deserialize_nested()?;
+    /// ```
+    pub fn deserialize_nested<T: DeserializeOwned>(&self) -> MultipartResult<T> {
+        T::deserialize(NestedDeserializer(self.nested()?))
+            .map_err(|err| MultipartError::InvalidNestedForm(err.to_string()))
+    }
+}
+
+/// Split `items[0][qty]` into its root name and segments, names that aren't well formed
+/// are kept as a single key
+fn parse_name(name: &str) -> Vec<Segment> {
+    let Some(open) = name.find('[').filter(|open| *open > 0) else {
+        return vec![Segment::Key(name.to_string())];
+    };
+
+    let mut segments = vec![Segment::Key(name[..open].to_string())];
+    let mut rest = &name[open..];
+    while !rest.is_empty() {
+        let Some(close) = rest.find(']').filter(|_| rest.starts_with('[')) else {
+            return vec![Segment::Key(name.to_string())];
+        };
+
+        let key = &rest[1..close];
+        segments.push(match key.parse::<usize>() {
+            _ if key.is_empty() => Segment::Push,
+            Ok(index) if index <= MAX_ARRAY_INDEX => Segment::Index(index),
+            _ => Segment::Key(key.to_string()),
+        });
+        rest = &rest[close + 1..];
+    }
+
+    segments
+}
+
+/// Put `value` at the path of `segments` under `node`, failing when the path crosses a
+/// value of another kind
+fn insert(node: &mut Value, segments: &[Segment], value: String) -> Result<(), ()> {
+    let Some((segment, rest)) = segments.split_first() else {
+        return match node {
+            Value::Null => {
+                *node = Value::String(value);
+                Ok(())
+            }
+            Value::String(first) => {
+                *node = Value::Array(vec![Value::String(std::mem::take(first)), value.into()]);
+                Ok(())
+            }
+            Value::Array(values) if values.iter().all(Value::is_string) => {
+                values.push(value.into());
+                Ok(())
+            }
+            _ => Err(()),
+        };
+    };
+
+    if node.is_null() {
+        *node = match segment {
+            Segment::Key(_) => Value::Object(Map::new()),
+            Segment::Index(_) | Segment::Push => Value::Array(vec![]),
+        };
+    }
+
+    let child = match (segment, node) {
+        (Segment::Key(key), Value::Object(map)) => map.entry(key.clone()).or_insert(Value::Null),
+        (Segment::Index(index), Value::Object(map)) => {
+            map.entry(index.to_string()).or_insert(Value::Null)
+        }
+        (Segment::Index(index), Value::Array(values)) => {
+            if values.len() <= *index {
+                values.resize(index + 1, Value::Null);
+            }
+            &mut values[*index]
+        }
+        (Segment::Push, Value::Array(values)) => {
+            values.push(Value::Null);
+            values.last_mut().expect("pushed")
+        }
+        _ => return Err(()),
+    };
+
+    insert(child, rest, value)
+}
+
+/// Drop the holes left by missing indexes, `items[2]` alone is the first item
+fn compact(node: &mut Value) {
+    match node {
+        Value::Array(values) => {
+            values.retain(|value| !value.is_null());
+            values.iter_mut().for_each(compact);
+        }
+        Value::Object(map) => map.values_mut().for_each(compact),
+        _ => {}
+    }
+}
+
+/// Deserializer of a nested form, reading the numbers and booleans from their text
+struct NestedDeserializer(Value);
+
+impl<'de> IntoDeserializer<'de, Error> for NestedDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Parse the text of the value with `FromStr` for the given visitor methods
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0 {
+                    Value::String(text) => match text.trim().parse() {
+                        Ok(value) => visitor.$visit(value),
+                        Err(err) => Err(Error::custom(format!("invalid value '{text}': {err}"))),
+                    },
+                    value => NestedDeserializer(value).deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for NestedDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Null => visitor.visit_unit(),
+            Value::String(text) => visitor.visit_string(text),
+            Value::Array(values) => {
+                let values = values.into_iter().map(NestedDeserializer);
+                visitor.visit_seq(SeqDeserializer::new(values))
+            }
+            Value::Object(map) => {
+                let entries = map
+                    .into_iter()
+                    .map(|(key, value)| (key, NestedDeserializer(value)));
+                visitor.visit_map(MapDeserializer::new(entries))
+            }
+            // the tree only holds strings, arrays and objects
+            value => visitor.visit_string(value.to_string()),
+        }
+    }
+
+    deserialize_parsed!(
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    );
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match &self.0 {
+            Value::Null => visitor.visit_none(),
+            Value::String(text) if text.trim().is_empty() => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    /// A field sent once is a sequence of one value
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::String(text) => {
+                let values = std::iter::once(NestedDeserializer(Value::String(text)));
+                visitor.visit_seq(SeqDeserializer::new(values))
+            }
+            value => NestedDeserializer(value).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::String(text) => visitor.visit_enum(text.into_deserializer()),
+            _ => Err(Error::custom(format!("expected a variant of {name}"))),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_name() {
+        let key = |key: &str| Segment::Key(key.to_string());
+
+        assert_eq!(parse_name("name"), [key("name")]);
+        assert_eq!(
+            parse_name("items[0][qty]"),
+            [key("items"), Segment::Index(0), key("qty")]
+        );
+        assert_eq!(parse_name("tags[]"), [key("tags"), Segment::Push]);
+        assert_eq!(parse_name("ids[99999]"), [key("ids"), key("99999")]);
+        assert_eq!(parse_name("a[b"), [key("a[b")]);
+        assert_eq!(parse_name("a[b]c"), [key("a[b]c")]);
+        assert_eq!(parse_name("[a]"), [key("[a]")]);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::FileInput;
+use crate::MultipartLimit;
 use crate::file_validator::{ErrorMessage, InputError};
 use std::fmt::{Display, Formatter};
 use std::io::Error;
@@ -6,16 +7,162 @@ use thiserror::Error;
 
 pub type MultipartResult<T> = Result<T, MultipartError>;
 
+/// Longest part of a rejected value kept in [`FieldParseError::value_preview`]
+const PREVIEW_LEN: usize = 64;
+
+/// A form field whose value could not be parsed into the requested type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldParseError {
+    pub field: String,
+    /// the trimmed value, cut to 64 characters; `None` when the field was empty
+    pub value_preview: Option<String>,
+    /// name of the requested type, e.g. `i32`
+    pub target_type: &'static str,
+    /// parser error, `None` when the field was empty
+    pub source: Option<String>,
+    /// position of the value among the values of a repeated field, see `Multipart::post_vec`
+    pub index: Option<usize>,
+}
+
+impl FieldParseError {
+    pub fn empty<T>(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            value_preview: None,
+            target_type: std::any::type_name::<T>(),
+            source: None,
+            index: None,
+        }
+    }
+
+    pub fn invalid<T>(field: &str, value: &str, source: impl Display) -> Self {
+        let value_preview = match value.char_indices().nth(PREVIEW_LEN) {
+            Some((end, _)) => format!("{}...", &value[..end]),
+            None => value.to_string(),
+        };
+
+        Self {
+            field: field.to_string(),
+            value_preview: Some(value_preview),
+            target_type: std::any::type_name::<T>(),
+            source: Some(source.to_string()),
+            index: None,
+        }
+    }
+
+    /// Set the position of the rejected value
+    pub fn at(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Field name, followed by the position of the value if any, e.g. `tags[2]`
+    fn location(&self) -> String {
+        match self.index {
+            Some(index) => format!("{}[{index}]", self.field),
+            None => self.field.clone(),
+        }
+    }
+}
+
+impl Display for FieldParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match (&self.value_preview, &self.source) {
+            (Some(value), Some(source)) => write!(
+                f,
+                "Failed to parse field '{}' with value '{value}' as {}: {source}",
+                self.location(),
+                self.target_type
+            ),
+            _ => write!(
+                f,
+                "Field '{}' is empty and cannot be parsed as {}",
+                self.location(),
+                self.target_type
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum MultipartError {
     NoFile,
     IoError(Error),
     NoContentType(String),
-    ParseError(String),
+    ParseError(FieldParseError),
     MissingDataField(String),
     InvalidContentDisposition(String),
     NtexError(ntex_multipart::MultipartError),
     ValidationError(InputError),
+    /// text field name and its size limit
+    DataFieldTooLarge(String, usize),
+    /// size budget of all text fields
+    DataBudgetExceeded(usize),
+    TooManyDataFields(usize),
+    /// repeated text field under `DuplicatePolicy::Reject`
+    DuplicateField(String),
+    /// chunked upload session id
+    UploadSessionNotFound(String),
+    /// part number out of range, missing parts or size mismatch of a chunked upload
+    InvalidUploadPart(String),
+    /// size limit of a chunked upload part or object
+    UploadTooLarge(usize),
+    /// missing, tampered, expired or exceeded upload ticket
+    InvalidUploadTicket(String),
+    /// file name and the threat reported by the scanner
+    InfectedFile(String, String),
+    /// body limit of the [`crate::DataLimits`] crossed and its value
+    LimitExceeded(MultipartLimit, usize),
+    /// conflicting bracketed field names, or nested fields not matching the requested type
+    InvalidNestedForm(String),
+}
+
+impl MultipartError {
+    /// Stable label of the rejection, e.g. for metrics: `file_too_large`, `infected`...
+    pub fn reason(&self) -> &'static str {
+        match self {
+            MultipartError::NoFile => "no_file",
+            MultipartError::IoError(_) => "io",
+            MultipartError::NoContentType(_) => "no_content_type",
+            MultipartError::ParseError(_) => "parse",
+            MultipartError::MissingDataField(_) => "missing_field",
+            MultipartError::InvalidContentDisposition(_) => "invalid_content_disposition",
+            MultipartError::NtexError(_) => "malformed",
+            MultipartError::ValidationError(input) => match input.error {
+                ErrorMessage::NoFiles => "no_files",
+                ErrorMessage::FileTooSmall(_) => "file_too_small",
+                ErrorMessage::FileTooLarge(_) => "file_too_large",
+                ErrorMessage::TooFewFiles(_) => "too_few_files",
+                ErrorMessage::TooManyFiles(_) => "too_many_files",
+                ErrorMessage::InvalidFileExtension(_) => "invalid_extension",
+                ErrorMessage::InvalidContentType(_) => "invalid_content_type",
+                ErrorMessage::MissingFileExtension(_) => "missing_extension",
+                ErrorMessage::ContentTypeMismatch(_) => "content_type_mismatch",
+                ErrorMessage::Custom(_) => "custom",
+            },
+            MultipartError::DataFieldTooLarge(..) => "field_too_large",
+            MultipartError::DataBudgetExceeded(_) => "data_budget_exceeded",
+            MultipartError::TooManyDataFields(_) => "too_many_fields",
+            MultipartError::DuplicateField(_) => "duplicate_field",
+            MultipartError::UploadSessionNotFound(_) => "session_not_found",
+            MultipartError::InvalidUploadPart(_) => "invalid_part",
+            MultipartError::UploadTooLarge(_) => "upload_too_large",
+            MultipartError::InvalidUploadTicket(_) => "invalid_ticket",
+            MultipartError::InfectedFile(..) => "infected",
+            MultipartError::LimitExceeded(limit, _) => limit.reason(),
+            MultipartError::InvalidNestedForm(_) => "invalid_nested_form",
+        }
+    }
+
+    /// Whether the request was rejected for its size, answered with 413
+    pub fn is_too_large(&self) -> bool {
+        matches!(
+            self,
+            MultipartError::DataFieldTooLarge(..)
+                | MultipartError::DataBudgetExceeded(_)
+                | MultipartError::UploadTooLarge(_)
+        ) || matches!(self, MultipartError::LimitExceeded(limit, _) if limit.is_size())
+    }
 }
 
 impl From<Error> for MultipartError {
@@ -48,8 +195,62 @@ impl Display for MultipartError {
             MultipartError::NtexError(err) => {
                 write!(f, "{err}")
             }
+            MultipartError::DataFieldTooLarge(field, limit) => {
+                write!(
+                    f,
+                    "Field '{field}' is too large. Maximum size is {}",
+                    FileInput::format_size(*limit)
+                )
+            }
+            MultipartError::DataBudgetExceeded(limit) => {
+                write!(
+                    f,
+                    "Form fields are too large. Maximum total size is {}",
+                    FileInput::format_size(*limit)
+                )
+            }
+            MultipartError::TooManyDataFields(count) => {
+                write!(f, "Too many form fields. Maximum is {count}")
+            }
+            MultipartError::DuplicateField(field) => {
+                write!(f, "Field '{field}' must be sent only once")
+            }
+            MultipartError::UploadSessionNotFound(id) => {
+                write!(f, "Upload '{id}' does not exist or has ended")
+            }
+            MultipartError::InvalidUploadPart(reason) => {
+                write!(f, "Invalid upload: {reason}")
+            }
+            MultipartError::UploadTooLarge(limit) => {
+                write!(
+                    f,
+                    "Upload is too large. Maximum size is {}",
+                    FileInput::format_size(*limit)
+                )
+            }
+            MultipartError::InvalidUploadTicket(reason) => {
+                write!(f, "Upload rejected: {reason}")
+            }
+            MultipartError::InfectedFile(file, _) => {
+                write!(f, "File '{file}' was rejected by the security scan")
+            }
+            MultipartError::LimitExceeded(limit, max) => match limit.is_size() {
+                true => write!(
+                    f,
+                    "The {limit} exceeds the maximum of {}",
+                    FileInput::format_size(*max)
+                ),
+                false => write!(f, "The {limit} exceeds the maximum of {max}"),
+            },
+            MultipartError::InvalidNestedForm(err) => {
+                write!(f, "Invalid nested form fields: {err}")
+            }
             MultipartError::ValidationError(err) => {
-                let field_name = err.name.clone().replace("_", " ");
+                if let Some(message) = &err.message {
+                    return write!(f, "{message}");
+                }
+
+                let field_name = err.label();
                 match err.error.clone() {
                     ErrorMessage::NoFiles => {
                         write!(f, "No files were uploaded for field: '{field_name}'")
@@ -93,6 +294,13 @@ impl Display for MultipartError {
                     ErrorMessage::MissingFileExtension(mime) => {
                         write!(f, "Invalid file, file extension is required: {mime}")
                     }
+                    ErrorMessage::Custom(message) => write!(f, "{message}"),
+                    ErrorMessage::ContentTypeMismatch(_) => {
+                        write!(
+                            f,
+                            "The content of the file for field '{field_name}' does not match its type"
+                        )
+                    }
                 }
             }
         }
@@ -0,0 +1,170 @@
+use crate::FileInput;
+use crate::result::MultipartResult;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tracing::debug;
+
+/// Prefix of the cached scan verdicts in the [`KvStore`]
+const KEY_PREFIX: &str = "upload-scan:";
+
+/// Outcome of scanning an uploaded file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    /// name of the detected threat
+    Infected(String),
+}
+
+impl ScanVerdict {
+    fn encode(&self) -> String {
+        match self {
+            ScanVerdict::Clean => "clean".to_string(),
+            ScanVerdict::Infected(threat) => format!("infected:{threat}"),
+        }
+    }
+
+    fn decode(value: &str) -> Option<ScanVerdict> {
+        match value {
+            "clean" => Some(ScanVerdict::Clean),
+            _ => value
+                .strip_prefix("infected:")
+                .map(|threat| ScanVerdict::Infected(threat.to_string())),
+        }
+    }
+}
+
+/// Scan hook run on uploaded files with [`Multipart::scan`](crate::Multipart::scan),
+/// e.g. over clamd or a scanning API
+#[allow(async_fn_in_trait)]
+pub trait FileScanner {
+    async fn scan(&self, file: &FileInput) -> MultipartResult<ScanVerdict>;
+}
+
+/// Key-value store with expiring entries, in memory by default. Use a shared store
+/// (redis...) so the instances benefit from each other's entries.
+#[allow(async_fn_in_trait)]
+pub trait KvStore {
+    async fn get(&self, key: &str) -> MultipartResult<Option<String>>;
+
+    async fn put(&self, key: &str, value: String, ttl: Duration) -> MultipartResult<()>;
+}
+
+/// In-memory [`KvStore`], expired entries are dropped on access
+#[derive(Debug, Default)]
+pub struct MemoryKvStore {
+    entries: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl KvStore for MemoryKvStore {
+    async fn get(&self, key: &str) -> MultipartResult<Option<String>> {
+        Ok(self.entries.lock().ok().and_then(|mut entries| {
+            let now = Instant::now();
+            entries.retain(|_, (_, expires)| *expires > now);
+            entries.get(key).map(|(value, _)| value.clone())
+        }))
+    }
+
+    async fn put(&self, key: &str, value: String, ttl: Duration) -> MultipartResult<()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.to_string(), (value, Instant::now() + ttl));
+        }
+        Ok(())
+    }
+}
+
+/// [`FileScanner`] remembering the verdicts by content hash, so popular attachments
+/// uploaded again are not re-scanned until the verdict expires.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex_multipart::{
+///     FileInput, FileScanner, MemoryKvStore, MultipartResult, ScanCache, ScanVerdict,
+/// };
+/// use std::time::Duration;
+///
+/// struct Clamd;
+///
+/// impl FileScanner for Clamd {
+///     async fn scan(&self, file: &FileInput) -> MultipartResult<ScanVerdict> {
+///         Ok(ScanVerdict::Clean)
+///     }
+/// }
+///
+/// let scanner = ScanCache::new(Clamd, MemoryKvStore::default(), Duration::from_secs(3600));
+/// ```
+pub struct ScanCache<S, K> {
+    scanner: S,
+    store: K,
+    ttl: Duration,
+    bypass: bool,
+}
+
+impl<S: FileScanner, K: KvStore> ScanCache<S, K> {
+    pub fn new(scanner: S, store: K, ttl: Duration) -> Self {
+        Self {
+            scanner,
+            store,
+            ttl,
+            bypass: false,
+        }
+    }
+
+    /// Scan every file again while still recording the verdicts, e.g. after the
+    /// scanner signatures were updated
+    pub fn bypass(mut self, bypass: bool) -> Self {
+        self.bypass = bypass;
+        self
+    }
+
+    /// Scan `file` ignoring the cached verdict, and refresh it
+    pub async fn rescan(&self, file: &FileInput) -> MultipartResult<ScanVerdict> {
+        let verdict = self.scanner.scan(file).await?;
+        self.store
+            .put(&cache_key(file).await?, verdict.encode(), self.ttl)
+            .await?;
+        Ok(verdict)
+    }
+}
+
+impl<S: FileScanner, K: KvStore> FileScanner for ScanCache<S, K> {
+    async fn scan(&self, file: &FileInput) -> MultipartResult<ScanVerdict> {
+        if !self.bypass
+            && let Some(verdict) = self.store.get(&cache_key(file).await?).await?
+            && let Some(verdict) = ScanVerdict::decode(&verdict)
+        {
+            debug!("[multipart] reusing scan verdict of '{}'", file.file_name);
+            return Ok(verdict);
+        }
+
+        self.rescan(file).await
+    }
+}
+
+async fn cache_key(file: &FileInput) -> MultipartResult<String> {
+    let mut hasher = Sha256::new();
+    match file.temp_path() {
+        Some(path) => {
+            let mut content = tokio::fs::File::open(path).await?;
+            let mut buf = vec![0; 64 * 1024];
+            loop {
+                let read = content.read(&mut buf).await?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buf[..read]);
+            }
+        }
+        None => file.bytes.iter().for_each(|chunk| hasher.update(chunk)),
+    }
+
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    Ok(format!("{KEY_PREFIX}{hash}"))
+}
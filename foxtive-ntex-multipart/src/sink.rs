@@ -0,0 +1,156 @@
+use crate::FileInput;
+use crate::result::MultipartResult;
+use crate::storage::unique_id;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+/// Receives the files of a request as [`crate::Multipart::process_into`] reads them, so they
+/// are never held in memory. Files arrive one after the other: every chunk of a file, then
+/// its `finalize`, then the next file.
+///
+/// Unlike a [`crate::StorageBackend`], one sink is used per request and keeps its own state,
+/// e.g. the multipart upload in progress.
+///
+/// # Plugging in an object store
+/// Start an upload (e.g. an S3 `CreateMultipartUpload`) on the first chunk of a file,
+/// buffer the chunks up to the part size of the store and upload each part, complete the
+/// upload in `finalize` and cancel the pending ones in `abort`:
+///
+/// ```
+/// use foxtive_ntex_multipart::{FileInput, MultipartResult, UploadSink};
+///
+/// #[derive(Default)]
+/// struct ObjectStoreSink {
+///     part: Vec<u8>,
+///     uploaded: Vec<String>,
+/// }
+///
+/// impl UploadSink for ObjectStoreSink {
+///     async fn write_chunk(&mut self, file: &FileInput, chunk: &[u8]) -> MultipartResult<()> {
+///         self.part.extend_from_slice(chunk);
+///         if self.part.len() >= 5 * 1024 * 1024 {
+///             // upload_part(&file.file_name, &self.part).await?;
+///             self.part.clear();
+///         }
+///         Ok(())
+///     }
+///
+///     async fn finalize(&mut self, file: &FileInput) -> MultipartResult<()> {
+///         // upload_part(&file.file_name, &self.part).await?;
+///         // complete_upload(&file.file_name).await?;
+///         self.part.clear();
+///         self.uploaded.push(file.file_name.clone());
+///         Ok(())
+///     }
+///
+///     async fn abort(&mut self) -> MultipartResult<()> {
+///         // abort the upload in progress and delete `self.uploaded`
+///         Ok(())
+///     }
+/// }
+/// ```
+#[allow(async_fn_in_trait)]
+pub trait UploadSink {
+    /// Receive the next chunk of `file`
+    async fn write_chunk(&mut self, file: &FileInput, chunk: &[u8]) -> MultipartResult<()>;
+
+    /// Every chunk of `file` was received
+    async fn finalize(&mut self, file: &FileInput) -> MultipartResult<()>;
+
+    /// The request was rejected, discard the file in progress and the finalized ones
+    async fn abort(&mut self) -> MultipartResult<()> {
+        Ok(())
+    }
+}
+
+/// File written by an [`FsSink`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SunkFile {
+    pub field_name: String,
+    pub file_name: String,
+    pub path: PathBuf,
+    pub size: usize,
+}
+
+/// Writes the files of a request under a directory, with unique names keeping their
+/// extension
+#[derive(Debug)]
+pub struct FsSink {
+    dir: PathBuf,
+    current: Option<(PathBuf, File)>,
+    files: Vec<SunkFile>,
+}
+
+impl FsSink {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            current: None,
+            files: vec![],
+        }
+    }
+
+    /// Files written so far
+    pub fn files(&self) -> &[SunkFile] {
+        &self.files
+    }
+
+    pub fn into_files(self) -> Vec<SunkFile> {
+        self.files
+    }
+
+    async fn create(&self, file: &FileInput) -> MultipartResult<(PathBuf, File)> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let name = match &file.extension {
+            Some(extension) => format!("{}.{extension}", unique_id()),
+            None => unique_id(),
+        };
+
+        let path = self.dir.join(name);
+        let handle = File::create(&path).await?;
+        Ok((path, handle))
+    }
+}
+
+impl UploadSink for FsSink {
+    async fn write_chunk(&mut self, file: &FileInput, chunk: &[u8]) -> MultipartResult<()> {
+        if self.current.is_none() {
+            self.current = Some(self.create(file).await?);
+        }
+
+        match &mut self.current {
+            Some((_, handle)) => Ok(handle.write_all(chunk).await?),
+            None => Ok(()),
+        }
+    }
+
+    async fn finalize(&mut self, file: &FileInput) -> MultipartResult<()> {
+        // empty files get no chunk
+        let (path, mut handle) = match self.current.take() {
+            Some(current) => current,
+            None => self.create(file).await?,
+        };
+        handle.flush().await?;
+
+        self.files.push(SunkFile {
+            field_name: file.field_name.clone(),
+            file_name: file.file_name.clone(),
+            path,
+            size: file.size,
+        });
+        Ok(())
+    }
+
+    async fn abort(&mut self) -> MultipartResult<()> {
+        let current = self.current.take().map(|(path, _)| path);
+        let finalized = self.files.drain(..).map(|file| file.path);
+        for path in current.into_iter().chain(finalized) {
+            if let Err(err) = tokio::fs::remove_file(&path).await {
+                warn!("failed to remove uploaded file '{}': {err}", path.display());
+            }
+        }
+        Ok(())
+    }
+}
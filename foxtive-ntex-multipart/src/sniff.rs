@@ -0,0 +1,176 @@
+/// Bytes of a file read to recognize its type
+pub(crate) const SNIFF_LEN: usize = 32;
+
+/// Content types stored in a zip archive, reported as `application/zip` by [`sniff`]
+const ZIP_CONTAINERS: &[&str] = &[
+    "application/zip",
+    "application/x-zip-compressed",
+    "application/java-archive",
+    "application/epub+zip",
+    "application/vnd.android.package-archive",
+    "application/vnd.oasis.opendocument.text",
+    "application/vnd.oasis.opendocument.spreadsheet",
+    "application/vnd.oasis.opendocument.presentation",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+];
+
+/// Types recognized from their RIFF or ISO media container
+const CONTAINER_KINDS: &[&str] = &[
+    "image/webp",
+    "audio/wav",
+    "video/x-msvideo",
+    "video/mp4",
+    "image/avif",
+    "image/heic",
+];
+
+/// Leading bytes of the recognized types
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"II*\x00", "image/tiff"),
+    (b"MM\x00*", "image/tiff"),
+    (b"\x00\x00\x01\x00", "image/x-icon"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"PK\x05\x06", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (b"Rar!\x1a\x07", "application/vnd.rar"),
+    (
+        b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1",
+        "application/x-ole-storage",
+    ),
+    (b"ID3", "audio/mpeg"),
+    (b"\xff\xfb", "audio/mpeg"),
+    (b"\xff\xf3", "audio/mpeg"),
+    (b"OggS", "audio/ogg"),
+    (b"fLaC", "audio/flac"),
+    (b"\x1a\x45\xdf\xa3", "video/webm"),
+    (b"\x7fELF", "application/x-executable"),
+    (b"MZ", "application/x-msdownload"),
+];
+
+/// Content type recognized from the first bytes of a file, `None` when unknown
+pub fn sniff(head: &[u8]) -> Option<&'static str> {
+    if let Some(kind) = sniff_riff(head).or_else(|| sniff_iso_media(head)) {
+        return Some(kind);
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| head.starts_with(signature))
+        .map(|(_, kind)| *kind)
+}
+
+/// Whether content sniffed as `sniffed` can be sent as `declared`
+pub(crate) fn matches(declared: &str, sniffed: &str) -> bool {
+    let declared = essence(declared);
+    match sniffed {
+        "application/zip" => ZIP_CONTAINERS.contains(&declared.as_str()),
+        "image/jpeg" => matches!(
+            declared.as_str(),
+            "image/jpeg" | "image/jpg" | "image/pjpeg"
+        ),
+        "image/x-icon" => matches!(
+            declared.as_str(),
+            "image/x-icon" | "image/vnd.microsoft.icon"
+        ),
+        "audio/mpeg" => matches!(declared.as_str(), "audio/mpeg" | "audio/mp3"),
+        "video/webm" => matches!(
+            declared.as_str(),
+            "video/webm" | "audio/webm" | "video/x-matroska"
+        ),
+        "video/mp4" => matches!(
+            declared.as_str(),
+            "video/mp4" | "audio/mp4" | "video/quicktime" | "audio/x-m4a" | "video/3gpp"
+        ),
+        "application/x-ole-storage" => matches!(
+            declared.as_str(),
+            "application/msword" | "application/vnd.ms-excel" | "application/vnd.ms-powerpoint"
+        ),
+        sniffed => declared == sniffed,
+    }
+}
+
+/// Whether a file declared as `declared` must carry a recognizable signature
+pub(crate) fn is_recognizable(declared: &str) -> bool {
+    SIGNATURES
+        .iter()
+        .map(|(_, kind)| *kind)
+        .chain(CONTAINER_KINDS.iter().copied())
+        .any(|kind| matches(declared, kind))
+}
+
+/// Content type without its parameters, lowercased
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// RIFF containers: WebP, WAV and AVI
+fn sniff_riff(head: &[u8]) -> Option<&'static str> {
+    if !head.starts_with(b"RIFF") || head.len() < 12 {
+        return None;
+    }
+
+    match &head[8..12] {
+        b"WEBP" => Some("image/webp"),
+        b"WAVE" => Some("audio/wav"),
+        b"AVI " => Some("video/x-msvideo"),
+        _ => None,
+    }
+}
+
+/// ISO base media files, identified by the brand of their `ftyp` box
+fn sniff_iso_media(head: &[u8]) -> Option<&'static str> {
+    if head.len() < 12 || &head[4..8] != b"ftyp" {
+        return None;
+    }
+
+    match &head[8..12] {
+        b"avif" | b"avis" => Some("image/avif"),
+        b"heic" | b"heix" | b"mif1" => Some("image/heic"),
+        _ => Some("video/mp4"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_known_signatures() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+        assert_eq!(sniff(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"\0\0\0\x20ftypisom"), Some("video/mp4"));
+        assert_eq!(sniff(b"%PDF-1.7"), Some("application/pdf"));
+        assert_eq!(sniff(b"hello world"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn test_matches_declared_type() {
+        assert!(matches("image/jpg", "image/jpeg"));
+        assert!(matches("IMAGE/PNG; charset=binary", "image/png"));
+        assert!(matches(
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "application/zip"
+        ));
+        assert!(!matches("image/png", "application/x-msdownload"));
+
+        assert!(is_recognizable("image/png"));
+        assert!(is_recognizable("image/webp"));
+        assert!(!is_recognizable("text/plain"));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::FileInput;
+use crate::result::MultipartResult;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Identifier unique to this process, ordered by creation time
+pub(crate) fn unique_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+
+    format!("{nanos:x}{sequence:x}")
+}
+
+/// Where [`crate::Multipart::stream_files_to`] writes uploaded files
+#[allow(async_fn_in_trait)]
+pub trait StorageBackend {
+    type Object: StorageObject;
+
+    /// Key of a new object, unique and keeping the file extension by default
+    fn key_for(&self, file: &FileInput) -> String {
+        match &file.extension {
+            Some(extension) => format!("{}.{extension}", unique_id()),
+            None => unique_id(),
+        }
+    }
+
+    /// Start writing an object
+    async fn create(&self, key: &str, file: &FileInput) -> MultipartResult<Self::Object>;
+
+    /// Remove a committed object, used when a later part of the request is rejected
+    async fn delete(&self, key: &str) -> MultipartResult<()>;
+}
+
+/// Object being written to a [`StorageBackend`]
+#[allow(async_fn_in_trait)]
+pub trait StorageObject {
+    async fn write(&mut self, chunk: &[u8]) -> MultipartResult<()>;
+
+    async fn commit(self) -> MultipartResult<()>;
+
+    /// Discard what was written so far
+    async fn abort(self) -> MultipartResult<()>;
+}
+
+/// File uploaded to a [`StorageBackend`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredFile {
+    pub field_name: String,
+    pub file_name: String,
+    pub key: String,
+    pub size: usize,
+    pub content_type: String,
+    pub extension: Option<String>,
+    /// hex SHA-256 of the content
+    pub sha256: String,
+}
+
+/// Stores objects as files under a directory
+#[derive(Debug, Clone)]
+pub struct FsStorage {
+    root: PathBuf,
+}
+
+impl FsStorage {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path_of(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+pub struct FsObject {
+    path: PathBuf,
+    file: File,
+}
+
+impl StorageBackend for FsStorage {
+    type Object = FsObject;
+
+    async fn create(&self, key: &str, _file: &FileInput) -> MultipartResult<FsObject> {
+        let path = self.path_of(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        Ok(FsObject {
+            file: File::create(&path).await?,
+            path,
+        })
+    }
+
+    async fn delete(&self, key: &str) -> MultipartResult<()> {
+        Ok(tokio::fs::remove_file(self.path_of(key)).await?)
+    }
+}
+
+impl StorageObject for FsObject {
+    async fn write(&mut self, chunk: &[u8]) -> MultipartResult<()> {
+        Ok(self.file.write_all(chunk).await?)
+    }
+
+    async fn commit(mut self) -> MultipartResult<()> {
+        Ok(self.file.flush().await?)
+    }
+
+    async fn abort(self) -> MultipartResult<()> {
+        drop(self.file);
+        Ok(tokio::fs::remove_file(self.path).await?)
+    }
+}
@@ -33,6 +33,7 @@ mod test {
             bytes: vec![Bytes::from("Hello World")],
             extension: None,
             content_disposition: Default::default(),
+            temp_file: None,
         };
 
         let path = "test_output.txt";
@@ -98,6 +99,7 @@ mod test {
                 bytes: vec![Bytes::from("File 1 Content")],
                 extension: None,
                 content_disposition: Default::default(),
+                temp_file: None,
             });
 
         multipart_instance
@@ -112,6 +114,7 @@ mod test {
                 bytes: vec![Bytes::from("File 2 Content")],
                 extension: None,
                 content_disposition: Default::default(),
+                temp_file: None,
             });
 
         // Verify multiple files for the same field
@@ -171,6 +174,7 @@ mod test {
                 bytes: vec![Bytes::from("File 1 Content")],
                 extension: None,
                 content_disposition: Default::default(),
+                temp_file: None,
             });
 
         // Test first data input
@@ -845,4 +849,1332 @@ mod test {
         // In a real scenario, attempting to use uuid::Uuid without the feature would cause a compile error
         println!("✅ UUID feature properly gated - not available without 'uuid' feature flag");
     }
+
+    fn form_body(fields: &[(&str, &str)]) -> Multipart {
+        form_with_files(fields, &[])
+    }
+
+    /// Multipart body with text fields and `(field, file name, content)` files
+    fn form_with_files(fields: &[(&str, &str)], files: &[(&str, &str, &str)]) -> Multipart {
+        let mut body = String::new();
+        for (name, value) in fields {
+            body.push_str(&format!(
+                "--x\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            ));
+        }
+        for (name, file_name, content) in files {
+            body.push_str(&format!(
+                "--x\r\nContent-Disposition: form-data; name=\"{name}\"; filename=\"{file_name}\"\r\nContent-Type: text/plain\r\n\r\n{content}\r\n"
+            ));
+        }
+        body.push_str("--x--\r\n");
+        let content_length = body.len();
+
+        let (req, payload) = ntex::web::test::TestRequest::default()
+            .header("content-type", "multipart/form-data; boundary=x")
+            .set_payload(body)
+            .to_http_parts();
+
+        Multipart {
+            multipart: NtexMultipart::new(req.headers(), payload),
+            file_inputs: Default::default(),
+            data_inputs: Default::default(),
+            data_limits: Default::default(),
+            duplicate_policy: Default::default(),
+            config: Default::default(),
+            ticket: None,
+            route: None,
+            content_length: Some(content_length),
+            progress: None,
+            streamed: Default::default(),
+        }
+    }
+
+    // Test 19: Text fields are limited per field, in total and in count
+    #[tokio::test]
+    async fn test_data_field_limits() {
+        use crate::{DataLimits, MultipartError};
+
+        let limits = DataLimits::default()
+            .max_field_size(8)
+            .field_size("bio", 32)
+            .max_total_size(40);
+
+        let mut multipart = form_body(&[("name", "ada"), ("bio", "a longer biography")])
+            .data_limits(limits.clone());
+        multipart.process().await.unwrap();
+        assert_eq!(
+            multipart.post::<String>("bio").unwrap(),
+            "a longer biography"
+        );
+
+        let mut multipart = form_body(&[("name", "a name too long")]).data_limits(limits.clone());
+        let err = multipart.process().await.err().unwrap();
+        assert!(matches!(&err, MultipartError::DataFieldTooLarge(field, 8) if field == "name"));
+        assert!(err.is_too_large());
+
+        let mut multipart = form_body(&[
+            ("bio", "a longer biography"),
+            ("bio", "yet another long biography"),
+        ])
+        .data_limits(limits.clone());
+        let err = multipart.process().await.err().unwrap();
+        assert!(matches!(err, MultipartError::DataBudgetExceeded(40)));
+
+        let mut multipart =
+            form_body(&[("a", "1"), ("b", "2"), ("c", "3")]).data_limits(limits.max_fields(2));
+        let err = multipart.process().await.err().unwrap();
+        assert!(matches!(err, MultipartError::TooManyDataFields(2)));
+        assert!(!err.is_too_large());
+    }
+
+    // Test 20: Repeated text fields follow the duplicate policy
+    #[tokio::test]
+    async fn test_duplicate_policy() {
+        use crate::{DuplicatePolicy, MultipartError};
+
+        let fields = [("role", "user"), ("role", "admin")];
+        for (policy, expected) in [
+            (DuplicatePolicy::FirstWins, vec!["user"]),
+            (DuplicatePolicy::LastWins, vec!["admin"]),
+            (DuplicatePolicy::Collect, vec!["user", "admin"]),
+        ] {
+            let mut multipart = form_body(&fields).duplicate_policy(policy);
+            multipart.process().await.unwrap();
+
+            let values: Vec<_> = multipart
+                .data("role")
+                .unwrap()
+                .iter()
+                .map(|input| input.value.as_str())
+                .collect();
+            assert_eq!(values, expected, "{policy:?}");
+            assert_eq!(multipart.post::<String>("role").unwrap(), expected[0]);
+        }
+
+        let mut multipart = form_body(&fields).duplicate_policy(DuplicatePolicy::Reject);
+        let err = multipart.process().await.err().unwrap();
+        assert!(matches!(&err, MultipartError::DuplicateField(field) if field == "role"));
+        assert_eq!(err.to_string(), "Field 'role' must be sent only once");
+    }
+
+    // Test 21: Files are streamed to storage, and removed when the request is rejected
+    #[tokio::test]
+    async fn test_stream_files_to_storage() {
+        use crate::{FsStorage, MultipartError};
+
+        let dir = std::env::temp_dir().join(format!("fx-multipart-{}", std::process::id()));
+        let storage = FsStorage::new(&dir);
+        let validator = Validator::builder()
+            .rule(
+                "doc",
+                FileRules::required().max_size(16).extensions(&["txt"]),
+            )
+            .build();
+
+        let mut multipart = form_with_files(&[("title", "notes")], &[("doc", "a.txt", "hello")]);
+        let stored = multipart
+            .stream_files_to(&storage, &validator)
+            .await
+            .unwrap();
+
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].size, 5);
+        assert_eq!(
+            stored[0].sha256,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(
+            fs::read_to_string(storage.path_of(&stored[0].key))
+                .await
+                .unwrap(),
+            "hello"
+        );
+        assert_eq!(multipart.post::<String>("title").unwrap(), "notes");
+        assert_eq!(multipart.first_file("doc").unwrap().size, 5);
+
+        // too large while streaming: the partial object is deleted
+        let mut multipart =
+            form_with_files(&[], &[("doc", "b.txt", "far more than sixteen bytes")]);
+        let err = multipart
+            .stream_files_to(&storage, &validator)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MultipartError::ValidationError(_)));
+
+        // rejected extension: nothing is written
+        let mut multipart = form_with_files(&[], &[("doc", "c.exe", "MZ")]);
+        assert!(
+            multipart
+                .stream_files_to(&storage, &validator)
+                .await
+                .is_err()
+        );
+
+        // second file over the count limit: the first one is deleted too
+        let validator = Validator::builder()
+            .rule("doc", FileRules::required().max_files(1))
+            .build();
+        let mut multipart =
+            form_with_files(&[], &[("doc", "d.txt", "one"), ("doc", "e.txt", "two")]);
+        assert!(
+            multipart
+                .stream_files_to(&storage, &validator)
+                .await
+                .is_err()
+        );
+
+        let mut remaining = fs::read_dir(&dir).await.unwrap();
+        let mut count = 0;
+        while remaining.next_entry().await.unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 1);
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    // Test 22: Parse errors tell which field failed, with what value and target type
+    #[tokio::test]
+    async fn test_structured_parse_errors() {
+        use crate::{FieldParseError, MultipartError};
+
+        let mut multipart = form_body(&[]);
+        multipart.add_test_data("age", "forty");
+        multipart.add_test_data("name", "  ");
+
+        let Err(MultipartError::ParseError(err)) = multipart.post::<u8>("age") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err.field, "age");
+        assert_eq!(err.value_preview.as_deref(), Some("forty"));
+        assert_eq!(err.target_type, "u8");
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse field 'age' with value 'forty' as u8: invalid digit found in string"
+        );
+
+        let Err(MultipartError::ParseError(err)) = multipart.post::<Option<u8>>("age") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err.field, "age");
+
+        let Err(MultipartError::ParseError(err)) = multipart.post::<i32>("name") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err, FieldParseError::empty::<i32>("name"));
+        assert_eq!(
+            MultipartError::ParseError(err).to_string(),
+            "Failed to parse post data: Field 'name' is empty and cannot be parsed as i32"
+        );
+
+        let long = "x".repeat(100);
+        let err = FieldParseError::invalid::<u8>("bio", &long, "too long");
+        assert_eq!(err.value_preview.unwrap().len(), 64 + 3);
+    }
+
+    // Test 23: Chunked uploads stream parts to the backend and assemble them in order
+    #[tokio::test]
+    async fn test_chunked_uploads() {
+        use crate::{ChunkedUploads, FsStorage, MemorySessionStore, MultipartError};
+        use futures::stream;
+
+        fn body(
+            content: &'static str,
+        ) -> impl futures::Stream<Item = Result<Bytes, ntex::http::error::PayloadError>> + Unpin
+        {
+            stream::iter(
+                content
+                    .as_bytes()
+                    .chunks(3)
+                    .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                    .collect::<Vec<_>>(),
+            )
+        }
+
+        let dir = std::env::temp_dir().join(format!("fx-chunked-{}", std::process::id()));
+        let uploads = ChunkedUploads::new(FsStorage::new(&dir), MemorySessionStore::default())
+            .max_part_size(8)
+            .max_object_size(20);
+
+        let session = uploads
+            .initiate("Video.MP4", "video/mp4", Some(13))
+            .await
+            .unwrap();
+        assert!(session.key.ends_with(".mp4"));
+
+        // parts arrive out of order, part 2 is sent twice
+        uploads
+            .upload_part(&session.id, 2, body("xxxx"))
+            .await
+            .unwrap();
+        uploads
+            .upload_part(&session.id, 3, body("world!"))
+            .await
+            .unwrap();
+        let part = uploads
+            .upload_part(&session.id, 2, body(", "))
+            .await
+            .unwrap();
+        assert_eq!(part.size, 2);
+
+        let err = uploads.complete(&session.id).await.unwrap_err();
+        assert_eq!(err.to_string(), "Invalid upload: part 1 is missing");
+
+        let err = uploads
+            .upload_part(&session.id, 1, body("too long a part"))
+            .await
+            .unwrap_err();
+        assert!(err.is_too_large());
+
+        uploads
+            .upload_part(&session.id, 1, body("hello"))
+            .await
+            .unwrap();
+        let completed = uploads.complete(&session.id).await.unwrap();
+        assert_eq!(completed.size, 13);
+        assert_eq!(completed.parts, 3);
+        assert_eq!(
+            fs::read_to_string(uploads.backend().path_of(&completed.key))
+                .await
+                .unwrap(),
+            "hello, world!"
+        );
+
+        // the session ended with the upload
+        let err = uploads.complete(&session.id).await.unwrap_err();
+        assert!(matches!(err, MultipartError::UploadSessionNotFound(_)));
+
+        // aborting removes the parts
+        let session = uploads
+            .initiate("big.bin", "application/octet-stream", None)
+            .await
+            .unwrap();
+        uploads
+            .upload_part(&session.id, 1, body("abc"))
+            .await
+            .unwrap();
+        uploads.abort(&session.id).await.unwrap();
+        assert!(!dir.join(".uploads").join(&session.upload_id).exists());
+
+        assert!(
+            uploads
+                .initiate("huge.bin", "", Some(21))
+                .await
+                .unwrap_err()
+                .is_too_large()
+        );
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    // Test 24: Upload tickets restrict the field, content types and size of the files
+    #[tokio::test]
+    async fn test_upload_ticket_constraints() {
+        use crate::{MultipartError, UploadTicket};
+
+        let mut ticket = UploadTicket::new("doc", 8).content_types(&["text/plain"]);
+        ticket.expires_at = u64::MAX;
+
+        let mut multipart = form_with_files(&[("title", "notes")], &[("doc", "a.txt", "hello")])
+            .ticket(ticket.clone());
+        multipart.process().await.unwrap();
+        assert_eq!(multipart.first_file("doc").unwrap().size, 5);
+
+        let mut multipart =
+            form_with_files(&[], &[("avatar", "a.txt", "hello")]).ticket(ticket.clone());
+        let err = multipart.process().await.err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Upload rejected: field 'avatar' is not covered by the ticket"
+        );
+
+        // the budget is shared by all the files of the request
+        let mut multipart =
+            form_with_files(&[], &[("doc", "a.txt", "hello"), ("doc", "b.txt", "world")])
+                .ticket(ticket.clone());
+        let err = multipart.process().await.err().unwrap();
+        assert!(matches!(err, MultipartError::UploadTooLarge(8)));
+
+        let mut multipart = form_with_files(&[], &[("doc", "a.txt", "hello")])
+            .ticket(ticket.clone().content_types(&["image/png"]));
+        let err = multipart.process().await.err().unwrap();
+        assert!(matches!(err, MultipartError::ValidationError(_)));
+
+        ticket.expires_at = 0;
+        let mut multipart = form_with_files(&[], &[("doc", "a.txt", "hello")]).ticket(ticket);
+        let err = multipart.process().await.err().unwrap();
+        assert!(matches!(err, MultipartError::InvalidUploadTicket(_)));
+    }
+
+    // Test 25: Upload tickets are signed, bound to their subject and read from requests
+    #[cfg(feature = "tickets")]
+    #[test]
+    fn test_signed_upload_tickets() {
+        use crate::{MultipartError, UPLOAD_TICKET_HEADER, UploadTicket, UploadTickets};
+        use std::time::Duration;
+
+        let tickets = UploadTickets::new("secret", Duration::from_secs(60));
+        let issued = UploadTicket::new("avatar", 1024)
+            .content_types(&["image/png", "image/jpeg"])
+            .subject("session-1");
+        let token = tickets.issue(issued.clone()).unwrap();
+
+        let ticket = tickets.verify(&token, Some("session-1")).unwrap();
+        assert_eq!(ticket.id, issued.id);
+        assert_eq!(ticket.content_types, ["image/png", "image/jpeg"]);
+        assert_eq!(ticket.max_size, 1024);
+        assert!(!ticket.is_expired());
+
+        let err = tickets.verify(&token, Some("session-2")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Upload rejected: ticket was issued to another session"
+        );
+
+        let (payload, signature) = token.split_once('.').unwrap();
+        let forged = tickets
+            .issue(UploadTicket::new("avatar", usize::MAX).subject("session-1"))
+            .unwrap();
+        let forged = format!("{}.{signature}", forged.split_once('.').unwrap().0);
+        assert!(matches!(
+            tickets.verify(&forged, Some("session-1")),
+            Err(MultipartError::InvalidUploadTicket(_))
+        ));
+        let other = UploadTickets::new("other", Duration::from_secs(60));
+        assert!(other.verify(&token, Some("session-1")).is_err());
+        assert!(tickets.verify(payload, Some("session-1")).is_err());
+
+        let expired = UploadTickets::new("secret", Duration::ZERO)
+            .issue(UploadTicket::new("avatar", 1024))
+            .unwrap();
+        assert_eq!(
+            tickets.verify(&expired, None).unwrap_err().to_string(),
+            "Upload rejected: ticket has expired"
+        );
+
+        let req = ntex::web::test::TestRequest::default()
+            .header(UPLOAD_TICKET_HEADER, token.as_str())
+            .to_http_request();
+        assert!(tickets.from_request(&req, Some("session-1")).is_ok());
+
+        // query parameters leak into logs, only the header is read
+        let req = ntex::web::test::TestRequest::with_uri(&format!("/upload?ticket={token}"))
+            .to_http_request();
+        assert!(tickets.from_request(&req, Some("session-1")).is_err());
+
+        // subjects may hold the characters that used to separate the fields
+        let issued = UploadTicket::new("avatar", 1024).subject("session\n1,2");
+        let token = tickets.issue(issued.clone()).unwrap();
+        let ticket = tickets.verify(&token, Some("session\n1,2")).unwrap();
+        assert_eq!(ticket.subject, issued.subject);
+
+        let req = ntex::web::test::TestRequest::default().to_http_request();
+        assert_eq!(
+            tickets.from_request(&req, None).unwrap_err().to_string(),
+            "Upload rejected: ticket is missing"
+        );
+    }
+
+    // Test 26: Test scan verdicts being cached by content hash
+    #[tokio::test]
+    async fn test_scan_cache() {
+        use crate::{FileScanner, MemoryKvStore, MultipartError, ScanCache, ScanVerdict};
+        use std::cell::Cell;
+        use std::time::Duration;
+
+        #[derive(Default)]
+        struct Counting {
+            scans: Cell<usize>,
+        }
+
+        impl FileScanner for &Counting {
+            async fn scan(&self, file: &FileInput) -> crate::MultipartResult<ScanVerdict> {
+                self.scans.set(self.scans.get() + 1);
+                match file.bytes.iter().any(|chunk| chunk.starts_with(b"EICAR")) {
+                    true => Ok(ScanVerdict::Infected("EICAR-Test".to_string())),
+                    false => Ok(ScanVerdict::Clean),
+                }
+            }
+        }
+
+        let counting = Counting::default();
+        let cache = ScanCache::new(&counting, MemoryKvStore::default(), Duration::from_secs(60));
+
+        let mut multipart =
+            form_with_files(&[], &[("doc", "a.txt", "hello"), ("doc", "b.txt", "hello")]);
+        assert!(multipart.scan(&cache).await.is_ok());
+        assert_eq!(counting.scans.get(), 1);
+
+        let mut multipart = form_with_files(&[], &[("doc", "c.txt", "EICAR payload")]);
+        let err = multipart.scan(&cache).await.err().unwrap();
+        assert!(
+            matches!(&err, MultipartError::InfectedFile(file, threat) if file == "c.txt" && threat == "EICAR-Test")
+        );
+        assert_eq!(counting.scans.get(), 2);
+
+        // the infected verdict is cached too
+        let mut multipart = form_with_files(&[], &[("doc", "d.txt", "EICAR payload")]);
+        assert!(multipart.scan(&cache).await.is_err());
+        assert_eq!(counting.scans.get(), 2);
+
+        let cache = cache.bypass(true);
+        let mut multipart = form_with_files(&[], &[("doc", "a.txt", "hello")]);
+        assert!(multipart.scan(&cache).await.is_ok());
+        assert_eq!(counting.scans.get(), 3);
+    }
+
+    // Test 27: Test mapping the files of a field with partial failures
+    #[tokio::test]
+    async fn test_map_files() {
+        let mut multipart = form_with_files(
+            &[],
+            &[
+                ("documents", "a.txt", "alpha"),
+                ("documents", "b.txt", ""),
+                ("documents", "c.txt", "gamma"),
+                ("avatar", "me.png", "png"),
+            ],
+        );
+
+        let report = multipart
+            .map_files("documents", |file| async move {
+                match file.size {
+                    0 => Err(format!("{} is empty", file.file_name)),
+                    size => Ok((file.file_name, size)),
+                }
+            })
+            .await
+            .unwrap();
+
+        assert!(!report.is_complete());
+        assert_eq!(
+            report.mapped,
+            [("a.txt".to_string(), 5), ("c.txt".to_string(), 5)]
+        );
+        assert_eq!(report.failures[0].index, 1);
+        assert_eq!(
+            report.failure_messages(),
+            [("b.txt".to_string(), "b.txt is empty".to_string())]
+        );
+        assert_eq!(report.into_result().unwrap_err(), "b.txt is empty");
+
+        let report = multipart
+            .map_files("missing", |file| async move { Ok::<_, String>(file.size) })
+            .await
+            .unwrap();
+        assert!(report.is_complete());
+        assert!(report.into_result().unwrap().is_empty());
+    }
+
+    // Test 28: Test upload activity being reported to the observer, labeled by route
+    #[tokio::test]
+    async fn test_upload_observer() {
+        use crate::{UploadEvent, UploadObserver};
+        use std::sync::Mutex;
+
+        static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        struct Recorder;
+
+        impl UploadObserver for Recorder {
+            fn observe(&self, route: &str, event: UploadEvent<'_>) {
+                // the observer is global, ignore the uploads of the other tests
+                if route != "/test-28" {
+                    return;
+                }
+                let event = match event {
+                    UploadEvent::File { field, bytes } => format!("file {field} {bytes}"),
+                    UploadEvent::Rejected { reason } => format!("rejected {reason}"),
+                    UploadEvent::Stored { .. } => "stored".to_string(),
+                };
+                EVENTS.lock().unwrap().push(event);
+            }
+        }
+
+        Multipart::set_observer(Recorder);
+
+        let mut multipart = form_with_files(
+            &[],
+            &[("photos", "a.txt", "alpha"), ("photos", "b.txt", "beta")],
+        );
+        multipart.route = Some("/test-28".to_string());
+
+        let validator = Validator::new().add_rule(
+            "photos",
+            FileRules {
+                max_files: Some(1),
+                ..Default::default()
+            },
+        );
+        assert!(multipart.validate(validator).await.is_err());
+
+        assert_eq!(
+            *EVENTS.lock().unwrap(),
+            ["file photos 5", "file photos 4", "rejected too_many_files"]
+        );
+    }
+
+    // Test 29: Test files past the memory threshold being spilled to a temp file
+    #[tokio::test]
+    async fn test_spill_large_files_to_disk() {
+        use crate::MultipartConfig;
+
+        let temp_dir = std::env::temp_dir().join("foxtive-multipart-test-29");
+        let config = MultipartConfig::default()
+            .memory_threshold(8)
+            .temp_dir(&temp_dir);
+
+        let mut multipart = form_with_files(
+            &[],
+            &[
+                ("docs", "small.txt", "tiny"),
+                ("docs", "large.txt", "larger than eight"),
+            ],
+        )
+        .config(config);
+        multipart.process().await.unwrap();
+
+        let files = multipart.files("docs").unwrap();
+        assert!(!files[0].is_on_disk());
+        assert_eq!(files[0].read_bytes().await.unwrap(), "tiny");
+
+        let large = files[1].clone();
+        let path = large.temp_path().unwrap().to_path_buf();
+        assert!(large.bytes.is_empty());
+        assert!(path.starts_with(&temp_dir));
+        // named without the client's extension, readable by the owner only
+        assert!(path.extension().is_none());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_eq!(large.size, 17);
+        assert_eq!(large.calculate_size(), 17);
+        assert_eq!(large.read_bytes().await.unwrap(), "larger than eight");
+        let mut streamed = String::new();
+        std::io::Read::read_to_string(&mut large.reader().unwrap(), &mut streamed).unwrap();
+        assert_eq!(streamed, "larger than eight");
+
+        let saved = temp_dir.join("saved.txt");
+        large.save(&saved).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(&saved).await.unwrap(),
+            "larger than eight"
+        );
+        fs::remove_file(&saved).await.unwrap();
+
+        // removed with the last clone
+        drop(multipart);
+        assert!(path.exists());
+        drop(large);
+        assert!(!path.exists());
+    }
+
+    // Test 30: Test body limits rejecting the request as soon as one is crossed
+    #[tokio::test]
+    async fn test_multipart_limits() {
+        use crate::{DataLimits, MultipartError, MultipartLimit};
+
+        let limited = |limits: DataLimits| {
+            form_with_files(
+                &[("title", "holiday"), ("notes", "sunny")],
+                &[("photos", "a.txt", "alpha"), ("photos", "b.txt", "beta")],
+            )
+            .data_limits(limits)
+        };
+
+        let cases = [
+            (DataLimits::default().max_files(1), MultipartLimit::Files),
+            (
+                DataLimits::default().max_file_size(4),
+                MultipartLimit::FileSize,
+            ),
+            (
+                DataLimits::default().max_body_size(20),
+                MultipartLimit::BodySize,
+            ),
+        ];
+        for (limits, expected) in cases {
+            match limited(limits).process().await {
+                Err(MultipartError::LimitExceeded(limit, _)) => assert_eq!(limit, expected),
+                other => panic!(
+                    "expected {expected:?} to be exceeded, got {:?}",
+                    other.err()
+                ),
+            }
+        }
+
+        // the text field limits answer with a single error each
+        let err = limited(DataLimits::default().max_fields(1))
+            .process()
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, MultipartError::TooManyDataFields(1)));
+        let err = limited(DataLimits::default().max_field_size(6))
+            .process()
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, MultipartError::DataFieldTooLarge(field, 6) if field == "title"));
+
+        // the second file is never read once the file count is crossed
+        let mut multipart = limited(DataLimits::default().max_files(1));
+        let err = multipart.process().await.err().unwrap();
+        assert_eq!(multipart.files("photos").unwrap().len(), 1);
+        assert_eq!(err.reason(), "too_many_files");
+        assert_eq!(
+            err.to_string(),
+            "The number of files exceeds the maximum of 1"
+        );
+
+        let err = limited(DataLimits::default().max_body_size(20))
+            .process()
+            .await
+            .err()
+            .unwrap();
+        assert!(err.is_too_large());
+
+        let limits = DataLimits::default()
+            .max_body_size(21)
+            .max_fields(2)
+            .max_files(2)
+            .max_file_size(5)
+            .max_field_size(7);
+        assert!(limited(limits).process().await.is_ok());
+    }
+
+    // Test 31: Test typed forms derived with `FromMultipart`
+    #[cfg(feature = "derive")]
+    #[tokio::test]
+    async fn test_derive_from_multipart() {
+        use crate::{FromMultipart, MultipartError};
+        use ntex::web::{DefaultError, FromRequest, WebResponseError};
+
+        #[derive(FromMultipart)]
+        struct CreateProduct {
+            name: String,
+            price: f64,
+            description: Option<String>,
+            image: FileInput,
+            thumbnail: Option<FileInput>,
+            #[multipart(rename = "gallery[]")]
+            gallery: Vec<FileInput>,
+        }
+
+        #[derive(Debug)]
+        struct FormRejected(String);
+
+        impl From<MultipartError> for FormRejected {
+            fn from(err: MultipartError) -> Self {
+                FormRejected(err.reason().to_string())
+            }
+        }
+
+        #[derive(FromMultipart)]
+        #[multipart(error = "FormRejected")]
+        #[allow(dead_code)]
+        struct Avatar {
+            avatar: FileInput,
+        }
+
+        let mut multipart = form_with_files(
+            &[("name", "Lamp"), ("price", "12.5")],
+            &[
+                ("image", "lamp.txt", "lamp"),
+                ("gallery[]", "a.txt", "a"),
+                ("gallery[]", "b.txt", "b"),
+            ],
+        );
+        multipart.process().await.unwrap();
+        let product: CreateProduct = multipart.parse().unwrap();
+        assert_eq!((product.name.as_str(), product.price), ("Lamp", 12.5));
+        assert_eq!(product.description, None);
+        assert_eq!(product.image.file_name, "lamp.txt");
+        assert!(product.thumbnail.is_none());
+        assert_eq!(product.gallery.len(), 2);
+
+        // taken as a handler argument
+        let body = "--x\r\nContent-Disposition: form-data; name=\"name\"\r\n\r\nLamp\r\n--x--\r\n";
+        let request = || {
+            ntex::web::test::TestRequest::default()
+                .header("content-type", "multipart/form-data; boundary=x")
+                .set_payload(body)
+                .to_http_parts()
+        };
+
+        let (req, mut payload) = request();
+        let err = <CreateProduct as FromRequest<DefaultError>>::from_request(&req, &mut payload)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.reason(), "missing_field");
+        assert_eq!(
+            WebResponseError::<DefaultError>::status_code(&err),
+            ntex::http::StatusCode::BAD_REQUEST
+        );
+
+        let (req, mut payload) = request();
+        let err = <Avatar as FromRequest<DefaultError>>::from_request(&req, &mut payload)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.0, "no_files");
+    }
+
+    // Test 32: Test the first bytes of streamed files being checked before commit
+    #[tokio::test]
+    async fn test_stream_files_magic_bytes() {
+        use crate::{ErrorMessage, FsStorage, MultipartError};
+
+        let dir = std::env::temp_dir().join(format!("fx-multipart-32-{}", std::process::id()));
+        let storage = FsStorage::new(&dir);
+        let validator = Validator::builder()
+            .rule(
+                "doc",
+                FileRules::required().sniffed_types(&["application/pdf"]),
+            )
+            .build();
+
+        let mut multipart = form_with_files(&[], &[("doc", "a.pdf", "%PDF-1.7 short")]);
+        let stored = multipart
+            .stream_files_to(&storage, &validator)
+            .await
+            .unwrap();
+        assert_eq!(stored.len(), 1);
+
+        let mut multipart = form_with_files(&[], &[("doc", "b.pdf", "MZ pretending to be a pdf")]);
+        let err = multipart
+            .stream_files_to(&storage, &validator)
+            .await
+            .unwrap_err();
+        match err {
+            MultipartError::ValidationError(input) => {
+                assert!(matches!(input.error, ErrorMessage::ContentTypeMismatch(_)))
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+
+        let mut entries = fs::read_dir(&dir).await.unwrap();
+        let mut count = 0;
+        while entries.next_entry().await.unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 1);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    // Test 33: Test files being handed to an upload sink instead of buffered
+    #[tokio::test]
+    async fn test_process_into_sink() {
+        use crate::{DataLimits, FsSink};
+
+        let dir = std::env::temp_dir().join(format!("fx-multipart-33-{}", std::process::id()));
+
+        let mut sink = FsSink::new(&dir);
+        let mut multipart = form_with_files(
+            &[("title", "trip")],
+            &[("photos", "a.txt", "alpha"), ("photos", "b.txt", "beta")],
+        );
+        multipart.process_into(&mut sink).await.unwrap();
+
+        assert_eq!(multipart.post::<String>("title").unwrap(), "trip");
+        let photos = multipart.files("photos").unwrap();
+        assert!(photos.iter().all(|photo| photo.bytes.is_empty()));
+        assert_eq!(photos[1].size, 4);
+
+        let files = sink.into_files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].file_name, "a.txt");
+        assert_eq!(fs::read_to_string(&files[0].path).await.unwrap(), "alpha");
+        assert_eq!(fs::read_to_string(&files[1].path).await.unwrap(), "beta");
+
+        // a rejected request leaves nothing behind
+        let mut sink = FsSink::new(&dir);
+        let mut multipart = form_with_files(
+            &[],
+            &[
+                ("photos", "c.txt", "gamma"),
+                ("photos", "d.txt", "far too long"),
+            ],
+        )
+        .data_limits(DataLimits::default().max_file_size(8));
+        assert!(multipart.process_into(&mut sink).await.is_err());
+        assert!(sink.files().is_empty());
+
+        let mut entries = fs::read_dir(&dir).await.unwrap();
+        let mut count = 0;
+        while entries.next_entry().await.unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 2);
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    // Test 34: Test async rules running after the declarative ones
+    #[tokio::test]
+    async fn test_async_validator_rule() {
+        use crate::{ErrorMessage, MultipartError};
+
+        let validator = Validator::builder()
+            .rule("avatar", FileRules::default().max_size(64))
+            .async_rule("avatar", |file| async move {
+                let content = file.read_bytes().await.map_err(|err| err.to_string())?;
+                match content.starts_with(b"GIF") {
+                    true => Ok(()),
+                    false => Err(format!("'{}' is not a GIF", file.file_name)),
+                }
+            })
+            .build();
+
+        let mut multipart = form_with_files(&[], &[("avatar", "me.gif", "GIF89a")]);
+        assert!(multipart.validate(&validator).await.is_ok());
+
+        let mut multipart = form_with_files(&[], &[("avatar", "me.gif", "PNG")]);
+        let err = multipart.validate(&validator).await.err().unwrap();
+        match err {
+            MultipartError::ValidationError(input) => {
+                assert_eq!(
+                    input.error,
+                    ErrorMessage::Custom("'me.gif' is not a GIF".into())
+                );
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    // Test 35: Test parsing chrono dates and times with the accepted formats
+    #[cfg(feature = "chrono")]
+    #[tokio::test]
+    async fn test_post_chrono_types() {
+        use crate::{DateTimeFormats, MultipartError};
+        use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+
+        let mut multipart = form_with_files(
+            &[
+                ("dob", "1990-05-17"),
+                ("local", "2024-03-01T09:30"),
+                ("at", "2024-03-01T09:30:00+01:00"),
+                ("naive_at", "2024-03-01 09:30:00"),
+                ("dmy", "17/05/1990"),
+                ("empty", " "),
+            ],
+            &[],
+        );
+        multipart.process().await.unwrap();
+
+        let dob = NaiveDate::from_ymd_opt(1990, 5, 17).unwrap();
+        assert_eq!(multipart.post::<NaiveDate>("dob").unwrap(), dob);
+        assert_eq!(
+            multipart.post::<NaiveDateTime>("local").unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 1)
+                .unwrap()
+                .and_hms_opt(9, 30, 0)
+                .unwrap()
+        );
+        let at = multipart.post::<DateTime<FixedOffset>>("at").unwrap();
+        assert_eq!(at.offset().local_minus_utc(), 3600);
+        assert_eq!(
+            multipart.post::<DateTime<Utc>>("at").unwrap().to_rfc3339(),
+            "2024-03-01T08:30:00+00:00"
+        );
+        assert_eq!(
+            multipart
+                .post::<DateTime<Utc>>("naive_at")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-03-01T09:30:00+00:00"
+        );
+        assert_eq!(multipart.post::<Option<NaiveDate>>("empty").unwrap(), None);
+        assert_eq!(
+            multipart.post::<Option<NaiveDate>>("missing").unwrap(),
+            None
+        );
+
+        let Err(MultipartError::ParseError(err)) = multipart.post::<NaiveDate>("dmy") else {
+            panic!("expected a parse error");
+        };
+        assert!(err.source.unwrap().contains("%Y-%m-%d"));
+
+        DateTimeFormats::set_default(
+            DateTimeFormats::default().date_formats(["%Y-%m-%d", "%d/%m/%Y"]),
+        );
+        let parsed = multipart.post::<Option<NaiveDate>>("dmy");
+        DateTimeFormats::set_default(DateTimeFormats::default());
+        assert_eq!(parsed.unwrap(), Some(dob));
+    }
+
+    // Test 36: Test parsing money fields without losing precision
+    #[cfg(all(feature = "decimal", feature = "bigdecimal"))]
+    #[tokio::test]
+    async fn test_post_decimal_types() {
+        use bigdecimal::BigDecimal;
+        use rust_decimal::Decimal;
+        use std::str::FromStr;
+
+        let mut multipart = form_with_files(
+            &[
+                ("price", " 19.99 "),
+                ("total", "12345678901234567890.0001"),
+                ("discount", "ten"),
+            ],
+            &[],
+        );
+        multipart.process().await.unwrap();
+
+        let price = multipart.post::<Decimal>("price").unwrap();
+        assert_eq!(
+            price * Decimal::from(3),
+            Decimal::from_str("59.97").unwrap()
+        );
+        assert_eq!(
+            multipart.post::<BigDecimal>("total").unwrap().to_string(),
+            "12345678901234567890.0001"
+        );
+        assert_eq!(multipart.post::<Option<Decimal>>("tip").unwrap(), None);
+        assert!(multipart.post::<Decimal>("discount").is_err());
+    }
+
+    // Test 37: Test parsing every value of a repeated field
+    #[tokio::test]
+    async fn test_post_vec() {
+        use crate::MultipartError;
+
+        let mut multipart = form_with_files(
+            &[
+                ("tags", "rust"),
+                ("tags", "web"),
+                ("ids", "1"),
+                ("ids", " 2 "),
+                ("ids", "three"),
+            ],
+            &[],
+        );
+        multipart.process().await.unwrap();
+
+        assert_eq!(
+            multipart.post_vec::<String>("tags").unwrap(),
+            ["rust", "web"]
+        );
+        assert_eq!(multipart.post::<String>("tags").unwrap(), "rust");
+        assert!(matches!(
+            multipart.post_vec::<u32>("missing"),
+            Err(MultipartError::MissingDataField(_))
+        ));
+        assert_eq!(multipart.post_vec_opt::<u32>("missing"), None);
+
+        let Err(MultipartError::ParseError(err)) = multipart.post_vec::<u32>("ids") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err.index, Some(2));
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse field 'ids[2]' with value 'three' as u32: invalid digit found in string"
+        );
+        assert_eq!(multipart.post_vec_opt::<u32>("ids"), None);
+    }
+
+    // Test 38: Test folding bracketed field names into nested values
+    #[tokio::test]
+    async fn test_nested_fields() {
+        use crate::MultipartError;
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Item {
+            sku: String,
+            qty: u32,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Order {
+            name: String,
+            address: std::collections::HashMap<String, String>,
+            items: Vec<Item>,
+            tags: Vec<String>,
+            gift: bool,
+            note: Option<String>,
+        }
+
+        let mut multipart = form_with_files(
+            &[
+                ("name", "Ada"),
+                ("address[street]", "1 Main St"),
+                ("address[city]", "Lagos"),
+                ("items[1][sku]", "B-2"),
+                ("items[1][qty]", "2"),
+                ("items[0][sku]", "A-1"),
+                ("items[0][qty]", " 10 "),
+                ("tags[]", "new"),
+                ("tags[]", "gift"),
+                ("gift", "true"),
+                ("note", ""),
+            ],
+            &[],
+        );
+        multipart.process().await.unwrap();
+
+        let tree = multipart.nested().unwrap();
+        assert_eq!(tree["address"]["city"], "Lagos");
+        assert_eq!(tree["items"][1]["qty"], "2");
+        assert_eq!(tree["tags"], serde_json::json!(["new", "gift"]));
+
+        let order = multipart.deserialize_nested::<Order>().unwrap();
+        assert_eq!(order.name, "Ada");
+        assert_eq!(order.address["street"], "1 Main St");
+        assert_eq!(
+            order.items,
+            [
+                Item {
+                    sku: "A-1".into(),
+                    qty: 10
+                },
+                Item {
+                    sku: "B-2".into(),
+                    qty: 2
+                },
+            ]
+        );
+        assert_eq!(order.tags, ["new", "gift"]);
+        assert!(order.gift);
+        assert_eq!(order.note, None);
+
+        // a value and an object under the same name
+        let mut multipart = form_with_files(&[("user", "ada"), ("user[id]", "1")], &[]);
+        multipart.process().await.unwrap();
+        assert!(matches!(
+            multipart.nested(),
+            Err(MultipartError::InvalidNestedForm(_))
+        ));
+
+        let mut multipart = form_with_files(&[("items[0][qty]", "many")], &[]);
+        multipart.process().await.unwrap();
+        let err = multipart.deserialize_nested::<Order>().err().unwrap();
+        assert_eq!(err.reason(), "invalid_nested_form");
+    }
+
+    // Test 39: Test deserializing a text field holding JSON
+    #[tokio::test]
+    async fn test_post_json() {
+        use crate::MultipartError;
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Metadata {
+            title: String,
+            tags: Vec<String>,
+        }
+
+        let mut multipart = form_with_files(
+            &[
+                ("metadata", r#"{"title": "Trip", "tags": ["beach"]}"#),
+                ("broken", "{\"title\": 1}"),
+            ],
+            &[("photo", "a.jpg", "jpeg")],
+        );
+        multipart.process().await.unwrap();
+
+        let metadata = multipart.post_json::<Metadata>("metadata").unwrap();
+        assert_eq!(metadata.title, "Trip");
+        assert_eq!(metadata.tags, ["beach"]);
+
+        let Err(MultipartError::ParseError(err)) = multipart.post_json::<Metadata>("broken") else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err.field, "broken");
+        assert!(err.target_type.ends_with("Metadata"));
+        assert!(err.source.unwrap().contains("invalid type"));
+
+        assert!(matches!(
+            multipart.post_json::<Metadata>("missing"),
+            Err(MultipartError::MissingDataField(_))
+        ));
+    }
+
+    // Test 40: Test progress being reported as the chunks arrive
+    #[tokio::test]
+    async fn test_upload_progress() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let seen = reports.clone();
+        let mut multipart = form_with_files(
+            &[("title", "trip")],
+            &[("photo", "a.txt", "alpha"), ("video", "b.txt", "beta")],
+        )
+        .with_progress(move |field, received, total| {
+            seen.borrow_mut().push((field.to_string(), received, total));
+        });
+        multipart.process().await.unwrap();
+
+        let reports = reports.borrow();
+        let fields: Vec<_> = reports.iter().map(|(field, ..)| field.as_str()).collect();
+        assert_eq!(fields, ["title", "photo", "video"]);
+        assert_eq!(
+            reports.last().unwrap().1,
+            "trip".len() + "alpha".len() + "beta".len()
+        );
+        assert!(reports.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+        let total = reports[0].2.unwrap();
+        assert!(
+            reports
+                .iter()
+                .all(|(_, received, hint)| *hint == Some(total) && *received < total)
+        );
+    }
+
+    // Test 41: Test the app-level config rejecting requests without handler code
+    #[tokio::test]
+    async fn test_app_level_config() {
+        use crate::{
+            DataLimits, ErrorMessage, FileRules, MultipartConfig, MultipartError, MultipartLimit,
+            Validator,
+        };
+        use ntex::web::FromRequest;
+
+        // oversized bodies are rejected by the extractor, before anything is read
+        let (req, mut payload) = ntex::web::test::TestRequest::default()
+            .header("content-type", "multipart/form-data; boundary=x")
+            .header("content-length", "4096")
+            .state(DataLimits::default().max_body_size(1024))
+            .to_http_parts();
+        let result =
+            <Multipart as FromRequest<ntex::web::DefaultError>>::from_request(&req, &mut payload)
+                .await;
+        assert!(matches!(
+            result.err(),
+            Some(MultipartError::LimitExceeded(
+                MultipartLimit::BodySize,
+                1024
+            ))
+        ));
+
+        // the default validator checks every processed request
+        let validator = Validator::builder()
+            .rule("photo", FileRules::default().max_size(3))
+            .build();
+        let config = MultipartConfig::default().validator(validator);
+
+        let mut multipart =
+            form_with_files(&[], &[("photo", "a.txt", "alpha")]).config(config.clone());
+        let err = multipart.process().await.err().unwrap();
+        assert!(matches!(
+            err,
+            MultipartError::ValidationError(input) if matches!(input.error, ErrorMessage::FileTooLarge(_))
+        ));
+
+        let mut multipart = form_with_files(&[], &[("avatar", "a.txt", "alpha")]).config(config);
+        assert!(multipart.process().await.is_ok());
+    }
+
+    // Test 42: Test reading the parts one at a time
+    #[tokio::test]
+    async fn test_next_field() {
+        use crate::{DataLimits, Field, MultipartError, MultipartLimit};
+
+        let mut multipart = form_with_files(
+            &[("title", "trip"), ("note", "skipped")],
+            &[("photo", "a.jpg", "alpha"), ("video", "b.mp4", "beta")],
+        );
+
+        let Some(Field::Text(title)) = multipart.next_field().await.unwrap() else {
+            panic!("expected a text field");
+        };
+        assert_eq!(title.name(), "title");
+        assert_eq!(title.text().await.unwrap(), "trip");
+
+        // dropped without being read
+        let note = multipart.next_field().await.unwrap().unwrap();
+        assert_eq!(note.name(), "note");
+        drop(note);
+
+        let Some(Field::File(mut photo)) = multipart.next_field().await.unwrap() else {
+            panic!("expected a file field");
+        };
+        assert_eq!(photo.info().file_name, "a.jpg");
+        let mut content = Vec::new();
+        while let Some(chunk) = photo.chunk().await.unwrap() {
+            content.extend_from_slice(&chunk);
+        }
+        assert_eq!(content, b"alpha");
+        assert_eq!(photo.size(), 5);
+
+        // the previous part must be dropped first
+        assert!(multipart.next_field().await.is_err());
+        drop(photo);
+
+        let Some(Field::File(video)) = multipart.next_field().await.unwrap() else {
+            panic!("expected a file field");
+        };
+        let video = video.into_file_input().await.unwrap();
+        assert_eq!((video.field_name.as_str(), video.size), ("video", 4));
+
+        assert!(multipart.next_field().await.unwrap().is_none());
+        assert!(multipart.all_files().is_empty());
+
+        // limits apply to the chunks as they are read
+        let mut multipart = form_with_files(&[], &[("photo", "a.jpg", "far too long")])
+            .data_limits(DataLimits::default().max_file_size(4));
+        let Some(Field::File(photo)) = multipart.next_field().await.unwrap() else {
+            panic!("expected a file field");
+        };
+        assert!(matches!(
+            photo.into_file_input().await.err(),
+            Some(MultipartError::LimitExceeded(MultipartLimit::FileSize, 4))
+        ));
+    }
+
+    // Test 43: Test reading the parts one at a time applies the checks of `process()`
+    #[tokio::test]
+    async fn test_next_field_checks() {
+        use crate::{
+            DataLimits, Field, FileRules, MultipartConfig, MultipartError, UploadTicket, Validator,
+        };
+
+        let mut ticket = UploadTicket::new("doc", 8).content_types(&["image/png"]);
+        ticket.expires_at = u64::MAX;
+
+        // a content type the ticket doesn't allow is refused before the file is read
+        let mut multipart =
+            form_with_files(&[], &[("doc", "a.txt", "hello")]).ticket(ticket.clone());
+        let err = multipart.next_field().await.err().unwrap();
+        assert!(matches!(err, MultipartError::ValidationError(_)));
+
+        // the ticket budget is spent as the chunks are read
+        let mut multipart = form_with_files(&[], &[("doc", "a.txt", "far too long")])
+            .ticket(ticket.content_types(&["text/plain"]));
+        let Some(Field::File(doc)) = multipart.next_field().await.unwrap() else {
+            panic!("expected a file field");
+        };
+        let err = doc.into_file_input().await.err().unwrap();
+        assert!(matches!(err, MultipartError::UploadTooLarge(8)));
+
+        // the text fields share the budget of the data limits
+        let mut multipart = form_with_files(&[("a", "12345"), ("b", "12345")], &[])
+            .data_limits(DataLimits::default().max_total_size(8));
+        let Some(Field::Text(a)) = multipart.next_field().await.unwrap() else {
+            panic!("expected a text field");
+        };
+        assert_eq!(a.text().await.unwrap(), "12345");
+        let Some(Field::Text(b)) = multipart.next_field().await.unwrap() else {
+            panic!("expected a text field");
+        };
+        let err = b.text().await.err().unwrap();
+        assert!(matches!(err, MultipartError::DataBudgetExceeded(8)));
+
+        // and the per-file rules of the configured validator
+        let validator = Validator::new().add_rule("photo", FileRules::optional().max_size(4));
+        let mut multipart = form_with_files(&[], &[("photo", "a.jpg", "far too long")])
+            .config(MultipartConfig::default().validator(validator));
+        let Some(Field::File(photo)) = multipart.next_field().await.unwrap() else {
+            panic!("expected a file field");
+        };
+        let err = photo.into_file_input().await.err().unwrap();
+        assert!(matches!(err, MultipartError::ValidationError(_)));
+    }
 }
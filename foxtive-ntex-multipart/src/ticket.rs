@@ -0,0 +1,261 @@
+use crate::file_input::FileInput;
+use crate::file_validator::{ErrorMessage, InputError};
+use crate::result::{MultipartError, MultipartResult};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Header carrying the signed ticket of an upload request
+pub const UPLOAD_TICKET_HEADER: &str = "x-upload-ticket";
+
+/// Constraints of a single upload, handed to the browser as a short-lived signed token
+/// (see `UploadTickets`, behind the `tickets` feature) and enforced by [`crate::Multipart`]
+/// before any byte of a file is accepted.
+///
+/// The ticket id doubles as the progress token the page polls while uploading.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadTicket {
+    pub id: String,
+    /// the only file field accepted
+    pub field: String,
+    /// size budget in bytes of all the files of the request
+    pub max_size: usize,
+    /// accepted content types, any when empty
+    pub content_types: Vec<String>,
+    /// unix timestamp in seconds
+    pub expires_at: u64,
+    /// session or user the ticket was issued to, checked on redemption against CSRF
+    pub subject: Option<String>,
+}
+
+impl UploadTicket {
+    pub fn new(field: &str, max_size: usize) -> Self {
+        Self {
+            id: crate::storage::unique_id(),
+            field: field.to_string(),
+            max_size,
+            content_types: vec![],
+            expires_at: 0,
+            subject: None,
+        }
+    }
+
+    pub fn content_types(mut self, content_types: &[&str]) -> Self {
+        self.content_types = content_types.iter().map(|ct| ct.to_string()).collect();
+        self
+    }
+
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_string());
+        self
+    }
+
+    pub fn is_expired(&self) -> bool {
+        now_secs() >= self.expires_at
+    }
+
+    /// Check a file part against the ticket, before reading its content
+    pub(crate) fn admit(
+        &self,
+        file: &FileInput,
+        declared_size: Option<usize>,
+    ) -> MultipartResult<()> {
+        if self.is_expired() {
+            return Err(MultipartError::InvalidUploadTicket(
+                "ticket has expired".to_string(),
+            ));
+        }
+
+        if file.field_name != self.field {
+            return Err(MultipartError::InvalidUploadTicket(format!(
+                "field '{}' is not covered by the ticket",
+                file.field_name
+            )));
+        }
+
+        if !self.content_types.is_empty()
+            && !self
+                .content_types
+                .iter()
+                .any(|ct| ct.eq_ignore_ascii_case(&file.content_type))
+        {
+            return Err(MultipartError::ValidationError(InputError::new(
+                &file.field_name,
+                ErrorMessage::InvalidContentType(self.content_types.join(", ")),
+            )));
+        }
+
+        if declared_size.is_some_and(|size| size > self.max_size) {
+            return Err(MultipartError::UploadTooLarge(self.max_size));
+        }
+
+        Ok(())
+    }
+
+    /// Check the bytes received so far for the request against the budget
+    pub(crate) fn spend(&self, received: usize) -> MultipartResult<()> {
+        match received > self.max_size {
+            true => Err(MultipartError::UploadTooLarge(self.max_size)),
+            false => Ok(()),
+        }
+    }
+
+    /// Fields encoded as a JSON array, so values may hold any character
+    #[cfg(feature = "tickets")]
+    fn to_payload(&self) -> MultipartResult<String> {
+        let fields = (
+            &self.id,
+            &self.field,
+            self.max_size,
+            self.expires_at,
+            &self.subject,
+            &self.content_types,
+        );
+        serde_json::to_string(&fields)
+            .map_err(|err| MultipartError::InvalidUploadTicket(err.to_string()))
+    }
+
+    #[cfg(feature = "tickets")]
+    fn from_payload(payload: &str) -> Option<Self> {
+        let (id, field, max_size, expires_at, subject, content_types) =
+            serde_json::from_str(payload).ok()?;
+
+        Some(Self {
+            id,
+            field,
+            max_size,
+            content_types,
+            expires_at,
+            subject,
+        })
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(feature = "tickets")]
+pub use signer::UploadTickets;
+
+#[cfg(feature = "tickets")]
+mod signer {
+    use super::*;
+    use foxtive::helpers::base64::Base64;
+    use foxtive::helpers::hmac::{HashFunc, Hmac};
+    use ntex::web::HttpRequest;
+    use std::time::Duration;
+
+    /// Issues and redeems [`UploadTicket`]s, signed with HMAC-SHA256 so the browser can
+    /// neither forge nor widen them.
+    ///
+    /// # Example
+    /// ```
+    /// use foxtive_ntex_multipart::{UploadTicket, UploadTickets};
+    /// use std::time::Duration;
+    ///
+    /// let tickets = UploadTickets::new("secret", Duration::from_secs(300));
+    /// let token = tickets
+    ///     .issue(UploadTicket::new("avatar", 2 * 1024 * 1024).content_types(&["image/png"]))
+    ///     .unwrap();
+    ///
+    /// let ticket = tickets.verify(&token, None).unwrap();
+    /// assert_eq!(ticket.field, "avatar");
+    /// ```
+    #[derive(Clone)]
+    pub struct UploadTickets {
+        hmac: Hmac,
+        ttl: Duration,
+    }
+
+    impl UploadTickets {
+        pub fn new(secret: &str, ttl: Duration) -> Self {
+            Self {
+                hmac: Hmac::new(secret, HashFunc::Sha256),
+                ttl,
+            }
+        }
+
+        /// Sign `ticket`, expiring after the configured ttl
+        pub fn issue(&self, mut ticket: UploadTicket) -> MultipartResult<String> {
+            ticket.expires_at = now_secs() + self.ttl.as_secs();
+
+            let payload = Base64::encode(&ticket.to_payload()?)
+                .map_err(|err| MultipartError::InvalidUploadTicket(err.to_string()))?
+                .trim_end_matches('=')
+                .replace('+', "-")
+                .replace('/', "_");
+            let signature = self
+                .hmac
+                .hash(&payload)
+                .map_err(|err| MultipartError::InvalidUploadTicket(err.to_string()))?;
+
+            Ok(format!("{payload}.{signature}"))
+        }
+
+        /// Decode a token, rejecting tampered or expired tickets and the ones issued to
+        /// another subject than `subject`
+        pub fn verify(&self, token: &str, subject: Option<&str>) -> MultipartResult<UploadTicket> {
+            let invalid = || MultipartError::InvalidUploadTicket("ticket is invalid".to_string());
+
+            let (payload, signature) = token.split_once('.').ok_or_else(invalid)?;
+            let expected = self
+                .hmac
+                .hash(&payload.to_string())
+                .map_err(|_| invalid())?;
+            if !secure_eq(&expected, signature) {
+                return Err(invalid());
+            }
+
+            let mut padded = payload.replace('-', "+").replace('_', "/");
+            while padded.len() % 4 != 0 {
+                padded.push('=');
+            }
+
+            let payload = Base64::decode(&padded).map_err(|_| invalid())?;
+            let ticket = UploadTicket::from_payload(&payload).ok_or_else(invalid)?;
+
+            if ticket.is_expired() {
+                return Err(MultipartError::InvalidUploadTicket(
+                    "ticket has expired".to_string(),
+                ));
+            }
+            if ticket.subject.as_deref() != subject {
+                return Err(MultipartError::InvalidUploadTicket(
+                    "ticket was issued to another session".to_string(),
+                ));
+            }
+
+            Ok(ticket)
+        }
+
+        /// Verify the ticket sent in the [`UPLOAD_TICKET_HEADER`] header of `req`. Query
+        /// parameters are not read, URLs end up in logs and browser history
+        pub fn from_request(
+            &self,
+            req: &HttpRequest,
+            subject: Option<&str>,
+        ) -> MultipartResult<UploadTicket> {
+            let token = req
+                .headers()
+                .get(UPLOAD_TICKET_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| {
+                    MultipartError::InvalidUploadTicket("ticket is missing".to_string())
+                })?;
+
+            self.verify(token, subject)
+        }
+    }
+
+    /// Compare signatures in time independent of where they first differ
+    fn secure_eq(expected: &str, received: &str) -> bool {
+        expected.len() == received.len()
+            && expected
+                .bytes()
+                .zip(received.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
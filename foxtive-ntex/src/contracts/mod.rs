@@ -1,3 +1,5 @@
+mod request_dto;
 mod response_code_contract;
 
+pub use request_dto::{DtoErrors, RequestDto};
 pub use response_code_contract::ResponseCodeContract;
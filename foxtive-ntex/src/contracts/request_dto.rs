@@ -0,0 +1,111 @@
+use foxtive::prelude::AppResult;
+use ntex::web::HttpRequest;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// Field errors collected while validating a request DTO, rendered as a 400 response
+/// listing every failing field
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct DtoErrors {
+    fields: BTreeMap<String, Vec<String>>,
+}
+
+impl DtoErrors {
+    pub fn add(&mut self, field: &str, message: &str) {
+        self.fields
+            .entry(field.to_string())
+            .or_default()
+            .push(message.to_string());
+    }
+
+    /// Record `message` for `field` when `failed` is true
+    pub fn check(&mut self, failed: bool, field: &str, message: &str) {
+        if failed {
+            self.add(field, message);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn fields(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.fields
+    }
+}
+
+impl Display for DtoErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<_> = self.fields.keys().map(String::as_str).collect();
+        write!(f, "invalid fields: {}", fields.join(", "))
+    }
+}
+
+#[cfg(feature = "validator")]
+impl From<validator::ValidationErrors> for DtoErrors {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        let mut dto_errors = DtoErrors::default();
+        for (field, errors) in errors.field_errors() {
+            for error in errors {
+                let message = match &error.message {
+                    Some(message) => message.to_string(),
+                    None => error.code.to_string(),
+                };
+                dto_errors.add(field.as_ref(), &message);
+            }
+        }
+
+        dto_errors
+    }
+}
+
+/// Request body going through the usual handler preamble, in order:
+/// 1. `sanitize`: normalize the input (trim, lowercase...)
+/// 2. `validate`: record every invalid field, any error rejects the request with 400
+/// 3. `authorize`: check the caller may perform the request, typically failing with 403
+///
+/// Use it through the `Dto<T>` extractor.
+///
+/// # Example
+/// ```
+/// use foxtive::prelude::{AppMessage, AppResult};
+/// use foxtive_ntex::contracts::{DtoErrors, RequestDto};
+/// use ntex::web::HttpRequest;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct InviteMember {
+///     email: String,
+///     role: String,
+/// }
+///
+/// impl RequestDto for InviteMember {
+///     fn sanitize(&mut self) {
+///         self.email = self.email.trim().to_lowercase();
+///     }
+///
+///     fn validate(&self, errors: &mut DtoErrors) {
+///         errors.check(!self.email.contains('@'), "email", "must be an email address");
+///         errors.check(self.role.is_empty(), "role", "is required");
+///     }
+///
+///     fn authorize(&self, req: &HttpRequest) -> AppResult<()> {
+///         match self.role == "owner" && !req.headers().contains_key("x-owner") {
+///             true => AppMessage::Forbidden.ar(),
+///             false => Ok(()),
+///         }
+///     }
+/// }
+/// ```
+pub trait RequestDto: DeserializeOwned {
+    fn sanitize(&mut self) {}
+
+    fn validate(&self, _errors: &mut DtoErrors) {}
+
+    fn authorize(&self, _req: &HttpRequest) -> AppResult<()> {
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use ntex::http::StatusCode;
+use ntex::http::header::{HeaderName, HeaderValue};
 
 pub trait ResponseCodeContract: Clone {
     fn code(&self) -> &str;
@@ -10,6 +11,12 @@ pub trait ResponseCodeContract: Clone {
         (200..300).contains(&code)
     }
 
+    /// Extra headers sent along with this code, e.g. `WWW-Authenticate` for 401.
+    /// Applied by `Responder` and the error renderer unless the response already has them.
+    fn headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+        vec![]
+    }
+
     fn from_code(code: &str) -> Self;
 
     fn from_status(status: StatusCode) -> Self;
@@ -1,5 +1,12 @@
 use crate::contracts::ResponseCodeContract;
+use crate::http::server::ServerSettings;
 use ntex::http::StatusCode;
+use ntex::http::header::{self, HeaderName, HeaderValue};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Seconds advertised in `Retry-After` for 429 and 503 responses, outside of the servers
+/// started with a `ServerConfig::retry_after`
+static RETRY_AFTER_SECS: AtomicU64 = AtomicU64::new(60);
 
 #[derive(Clone)]
 pub enum ResponseCode {
@@ -7,6 +14,7 @@ pub enum ResponseCode {
     Created,
     Accepted,
     NoContent,
+    MultiStatus,
     BadRequest,
     Unauthorized,
     PaymentRequired,
@@ -16,6 +24,58 @@ pub enum ResponseCode {
     InternalServerError,
     ServiceUnavailable,
     NotImplemented,
+    UnsupportedMediaType,
+    UnprocessableEntity,
+    MethodNotAllowed,
+    Gone,
+    PayloadTooLarge,
+    TooManyRequests,
+    GatewayTimeout,
+}
+
+impl ResponseCode {
+    /// Change the `Retry-After` delay sent with `TooManyRequests` and `ServiceUnavailable`
+    /// outside of the servers started with a `ServerConfig::retry_after`
+    pub fn set_retry_after(seconds: u64) {
+        RETRY_AFTER_SECS.store(seconds, Ordering::Relaxed);
+    }
+
+    /// Delay of the server handling the current request, the process default otherwise
+    fn retry_after() -> u64 {
+        ServerSettings::read(|settings| settings.retry_after)
+            .flatten()
+            .unwrap_or_else(|| RETRY_AFTER_SECS.load(Ordering::Relaxed))
+    }
+
+    /// Code of `status`, `None` for the statuses without one
+    pub fn try_from_status(status: StatusCode) -> Option<Self> {
+        let code = match status {
+            StatusCode::OK => ResponseCode::Ok,
+            StatusCode::CREATED => ResponseCode::Created,
+            StatusCode::ACCEPTED => ResponseCode::Accepted,
+            StatusCode::NO_CONTENT => ResponseCode::NoContent,
+            StatusCode::BAD_REQUEST => ResponseCode::BadRequest,
+            StatusCode::UNAUTHORIZED => ResponseCode::Unauthorized,
+            StatusCode::PAYMENT_REQUIRED => ResponseCode::PaymentRequired,
+            StatusCode::FORBIDDEN => ResponseCode::Forbidden,
+            StatusCode::NOT_FOUND => ResponseCode::NotFound,
+            StatusCode::CONFLICT => ResponseCode::Conflict,
+            StatusCode::INTERNAL_SERVER_ERROR => ResponseCode::InternalServerError,
+            StatusCode::SERVICE_UNAVAILABLE => ResponseCode::ServiceUnavailable,
+            StatusCode::NOT_IMPLEMENTED => ResponseCode::NotImplemented,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => ResponseCode::UnsupportedMediaType,
+            StatusCode::PAYLOAD_TOO_LARGE => ResponseCode::PayloadTooLarge,
+            StatusCode::TOO_MANY_REQUESTS => ResponseCode::TooManyRequests,
+            StatusCode::GATEWAY_TIMEOUT => ResponseCode::GatewayTimeout,
+            StatusCode::MULTI_STATUS => ResponseCode::MultiStatus,
+            StatusCode::UNPROCESSABLE_ENTITY => ResponseCode::UnprocessableEntity,
+            StatusCode::METHOD_NOT_ALLOWED => ResponseCode::MethodNotAllowed,
+            StatusCode::GONE => ResponseCode::Gone,
+            _ => return None,
+        };
+
+        Some(code)
+    }
 }
 
 impl ResponseCodeContract for ResponseCode {
@@ -34,6 +94,14 @@ impl ResponseCodeContract for ResponseCode {
             ResponseCode::InternalServerError => "010",
             ResponseCode::ServiceUnavailable => "011",
             ResponseCode::NotImplemented => "012",
+            ResponseCode::UnsupportedMediaType => "013",
+            ResponseCode::PayloadTooLarge => "014",
+            ResponseCode::TooManyRequests => "015",
+            ResponseCode::GatewayTimeout => "016",
+            ResponseCode::MultiStatus => "017",
+            ResponseCode::UnprocessableEntity => "018",
+            ResponseCode::MethodNotAllowed => "019",
+            ResponseCode::Gone => "020",
         }
     }
 
@@ -52,6 +120,26 @@ impl ResponseCodeContract for ResponseCode {
             ResponseCode::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             ResponseCode::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
             ResponseCode::NotImplemented => StatusCode::NOT_IMPLEMENTED,
+            ResponseCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ResponseCode::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ResponseCode::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            ResponseCode::GatewayTimeout => StatusCode::GATEWAY_TIMEOUT,
+            ResponseCode::MultiStatus => StatusCode::MULTI_STATUS,
+            ResponseCode::UnprocessableEntity => StatusCode::UNPROCESSABLE_ENTITY,
+            ResponseCode::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            ResponseCode::Gone => StatusCode::GONE,
+        }
+    }
+
+    fn headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+        match self {
+            ResponseCode::Unauthorized => {
+                vec![(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))]
+            }
+            ResponseCode::TooManyRequests | ResponseCode::ServiceUnavailable => {
+                vec![(header::RETRY_AFTER, HeaderValue::from(Self::retry_after()))]
+            }
+            _ => vec![],
         }
     }
 
@@ -70,26 +158,19 @@ impl ResponseCodeContract for ResponseCode {
             "010" => ResponseCode::InternalServerError,
             "011" => ResponseCode::ServiceUnavailable,
             "012" => ResponseCode::NotImplemented,
+            "013" => ResponseCode::UnsupportedMediaType,
+            "014" => ResponseCode::PayloadTooLarge,
+            "015" => ResponseCode::TooManyRequests,
+            "016" => ResponseCode::GatewayTimeout,
+            "017" => ResponseCode::MultiStatus,
+            "018" => ResponseCode::UnprocessableEntity,
+            "019" => ResponseCode::MethodNotAllowed,
+            "020" => ResponseCode::Gone,
             _ => panic!("Invalid response code"),
         }
     }
 
     fn from_status(status: StatusCode) -> Self {
-        match status {
-            StatusCode::OK => ResponseCode::Ok,
-            StatusCode::CREATED => ResponseCode::Created,
-            StatusCode::ACCEPTED => ResponseCode::Accepted,
-            StatusCode::NO_CONTENT => ResponseCode::NoContent,
-            StatusCode::BAD_REQUEST => ResponseCode::BadRequest,
-            StatusCode::UNAUTHORIZED => ResponseCode::Unauthorized,
-            StatusCode::PAYMENT_REQUIRED => ResponseCode::PaymentRequired,
-            StatusCode::FORBIDDEN => ResponseCode::Forbidden,
-            StatusCode::NOT_FOUND => ResponseCode::NotFound,
-            StatusCode::CONFLICT => ResponseCode::Conflict,
-            StatusCode::INTERNAL_SERVER_ERROR => ResponseCode::InternalServerError,
-            StatusCode::SERVICE_UNAVAILABLE => ResponseCode::ServiceUnavailable,
-            StatusCode::NOT_IMPLEMENTED => ResponseCode::NotImplemented,
-            _ => panic!("Invalid status code"),
-        }
+        ResponseCode::try_from_status(status).unwrap_or_else(|| panic!("Invalid status code"))
     }
 }
@@ -1,5 +1,7 @@
+use crate::contracts::DtoErrors;
 use crate::error::helpers::make_http_error_response;
 use crate::http::response::anyhow::helpers::make_status_code;
+use crate::http::response::status::{ErrorKind, StatusOverrides};
 use foxtive::Error;
 use foxtive::prelude::AppMessage;
 #[cfg(feature = "multipart")]
@@ -26,6 +28,8 @@ pub enum HttpError {
     #[cfg(feature = "validator")]
     #[error("Validation Error: {0}")]
     ValidationError(#[from] validator::ValidationErrors),
+    #[error("Validation Error: {0}")]
+    DtoError(DtoErrors),
     #[cfg(feature = "multipart")]
     #[error("Multipart Error: {0}")]
     MultipartError(#[from] MultipartError),
@@ -35,6 +39,75 @@ impl HttpError {
     pub fn into_app_error(self) -> foxtive::Error {
         foxtive::Error::from(self)
     }
+
+    /// Kind consulted in [`StatusOverrides`], messages and app errors are resolved
+    /// on their own
+    pub(crate) fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            #[cfg(feature = "validator")]
+            HttpError::ValidationError(_) => Some(ErrorKind::Validation),
+            HttpError::DtoError(_) => Some(ErrorKind::Validation),
+            HttpError::PayloadError(_) => Some(ErrorKind::Payload),
+            #[cfg(feature = "multipart")]
+            HttpError::MultipartError(MultipartError::ParseError(_)) => Some(ErrorKind::Validation),
+            #[cfg(feature = "multipart")]
+            HttpError::MultipartError(MultipartError::ValidationError(e))
+                if !is_media_type_error(e) =>
+            {
+                Some(ErrorKind::Validation)
+            }
+            #[cfg(feature = "multipart")]
+            HttpError::MultipartError(_) => Some(ErrorKind::Multipart),
+            _ => None,
+        }
+    }
+
+    /// Status before [`StatusOverrides`] are applied
+    fn default_status_code(&self) -> StatusCode {
+        match self {
+            HttpError::AppMessage(m) => StatusOverrides::message_status(m),
+            HttpError::AppError(e) => make_status_code(e),
+            #[cfg(feature = "validator")]
+            HttpError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            HttpError::PayloadError(PayloadError::Overflow) => StatusCode::PAYLOAD_TOO_LARGE,
+            HttpError::PayloadError(_) => StatusCode::BAD_REQUEST,
+            HttpError::DtoError(_) => StatusCode::BAD_REQUEST,
+            #[cfg(feature = "multipart")]
+            HttpError::MultipartError(err) => match err {
+                MultipartError::ValidationError(err) => match is_media_type_error(err) {
+                    true => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    false => StatusCode::UNPROCESSABLE_ENTITY,
+                },
+                err if err.is_too_large() => StatusCode::PAYLOAD_TOO_LARGE,
+                MultipartError::UploadSessionNotFound(_) => StatusCode::NOT_FOUND,
+                MultipartError::InvalidUploadTicket(_) => StatusCode::FORBIDDEN,
+                MultipartError::InfectedFile(..) => StatusCode::UNPROCESSABLE_ENTITY,
+                _ => StatusCode::BAD_REQUEST,
+            },
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Overridden status of the errors answered with `default`, only 400s of
+    /// multipart errors can be overridden
+    fn resolve_status(&self, default: StatusCode) -> StatusCode {
+        match self.kind() {
+            Some(ErrorKind::Multipart) if default != StatusCode::BAD_REQUEST => default,
+            Some(kind) => StatusOverrides::resolve(kind, default),
+            None => default,
+        }
+    }
+}
+
+/// Files rejected for their extension or content type, answered with 415
+#[cfg(feature = "multipart")]
+fn is_media_type_error(err: &foxtive_ntex_multipart::InputError) -> bool {
+    matches!(
+        err.error,
+        MultipartErrorMessage::InvalidFileExtension(_)
+            | MultipartErrorMessage::InvalidContentType(_)
+            | MultipartErrorMessage::ContentTypeMismatch(_)
+    )
 }
 
 impl From<Box<dyn std::error::Error + Send + Sync>> for HttpError {
@@ -54,25 +127,7 @@ impl From<BlockingError<Error>> for HttpError {
 
 impl WebResponseError for HttpError {
     fn status_code(&self) -> StatusCode {
-        match self {
-            HttpError::AppMessage(m) => m.status_code(),
-            HttpError::AppError(e) => make_status_code(e),
-            #[cfg(feature = "validator")]
-            HttpError::ValidationError(_) => StatusCode::BAD_REQUEST,
-            HttpError::PayloadError(_) => StatusCode::BAD_REQUEST,
-            #[cfg(feature = "multipart")]
-            HttpError::MultipartError(err) => match err {
-                MultipartError::ValidationError(err) => match err.error {
-                    MultipartErrorMessage::InvalidFileExtension(_)
-                    | MultipartErrorMessage::InvalidContentType(_) => {
-                        StatusCode::UNSUPPORTED_MEDIA_TYPE
-                    }
-                    _ => StatusCode::BAD_REQUEST,
-                },
-                _ => StatusCode::BAD_REQUEST,
-            },
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
-        }
+        self.resolve_status(self.default_status_code())
     }
 
     fn error_response(&self, _: &HttpRequest) -> HttpResponse {
@@ -81,13 +136,16 @@ impl WebResponseError for HttpError {
 }
 
 pub(crate) mod helpers {
+    #[cfg(feature = "multipart")]
+    use crate::contracts::DtoErrors;
+    use crate::contracts::ResponseCodeContract;
     use crate::enums::ResponseCode;
     use crate::helpers::responder::Responder;
     use crate::http::HttpError;
     use crate::http::response::anyhow::helpers::make_response;
     use foxtive::prelude::AppMessage;
-    use ntex::web::HttpResponse;
-    use tracing::error;
+    use ntex::web::{HttpResponse, WebResponseError};
+    use tracing::{debug, error};
 
     pub(crate) fn make_http_error_response(err: &HttpError) -> HttpResponse {
         match err {
@@ -96,20 +154,42 @@ pub(crate) mod helpers {
             #[cfg(feature = "validator")]
             HttpError::ValidationError(e) => {
                 error!("Validation Error: {e}");
-                Responder::send_msg(e.errors(), ResponseCode::BadRequest, "Validation Error")
+                let code = ResponseCode::from_status(err.status_code());
+                Responder::send_msg(e.errors(), code, "Validation Error")
+            }
+            HttpError::DtoError(e) => {
+                debug!("Validation Error: {e}");
+                let code = ResponseCode::from_status(err.status_code());
+                Responder::send_msg(e, code, "Validation Error")
             }
             HttpError::PayloadError(e) => {
                 error!("Payload Error: {e}");
-                Responder::send_msg(e.to_string(), ResponseCode::BadRequest, "Payload Error")
+                let code = ResponseCode::from_status(err.status_code());
+                Responder::send_msg(e.to_string(), code, "Payload Error")
             }
             #[cfg(feature = "multipart")]
-            HttpError::MultipartError(err) => {
-                error!("Multipart Error: {err}");
-                Responder::send_msg(
-                    err.to_string(),
-                    ResponseCode::BadRequest,
-                    "File Upload Error",
-                )
+            HttpError::MultipartError(foxtive_ntex_multipart::MultipartError::ParseError(e)) => {
+                debug!("Multipart Error: {e}");
+                let mut errors = DtoErrors::default();
+                errors.add(&e.field, &e.to_string());
+                let code = ResponseCode::from_status(err.status_code());
+                Responder::send_msg(errors, code, "Validation Error")
+            }
+            #[cfg(feature = "multipart")]
+            HttpError::MultipartError(
+                e @ foxtive_ntex_multipart::MultipartError::ValidationError(input),
+            ) => {
+                debug!("Multipart Error: {e}");
+                let mut errors = DtoErrors::default();
+                errors.add(&input.name, &e.to_string());
+                let code = ResponseCode::from_status(err.status_code());
+                Responder::send_msg(errors, code, "Validation Error")
+            }
+            #[cfg(feature = "multipart")]
+            HttpError::MultipartError(e) => {
+                error!("Multipart Error: {e}");
+                let code = ResponseCode::from_status(err.status_code());
+                Responder::send_msg(e.to_string(), code, "File Upload Error")
             }
             _ => {
                 error!("Error: {err}");
@@ -124,6 +204,13 @@ mod tests {
     use super::*;
     use foxtive::Error;
 
+    #[test]
+    fn test_unauthorized_has_www_authenticate() {
+        let error = HttpError::AppMessage(AppMessage::Unauthorized);
+        let response = make_http_error_response(&error);
+        assert!(response.headers().contains_key("www-authenticate"));
+    }
+
     #[test]
     fn test_app_error() {
         let error = HttpError::AppError(Error::from(AppMessage::InternalServerError));
@@ -149,11 +236,32 @@ mod tests {
         assert_eq!(app_error.status(), 500);
     }
 
+    #[test]
+    fn test_status_override_kinds() {
+        assert_eq!(
+            HttpError::DtoError(DtoErrors::default()).kind(),
+            Some(ErrorKind::Validation)
+        );
+        assert_eq!(
+            HttpError::PayloadError(PayloadError::Overflow).kind(),
+            Some(ErrorKind::Payload)
+        );
+        // messages are resolved through their own kind
+        assert_eq!(
+            HttpError::AppMessage(AppMessage::WarningMessage("bad")).kind(),
+            None
+        );
+    }
+
     #[test]
     fn test_payload_error() {
-        let error = HttpError::PayloadError(PayloadError::Overflow);
+        let error = HttpError::PayloadError(PayloadError::EncodingCorrupted);
         let app_error = make_http_error_response(&error);
         assert_eq!(app_error.status(), 400);
+
+        let error = HttpError::PayloadError(PayloadError::Overflow);
+        let app_error = make_http_error_response(&error);
+        assert_eq!(app_error.status(), 413);
     }
 
     #[cfg(feature = "validator")]
@@ -169,13 +277,84 @@ mod tests {
     fn test_multipart_error() {
         use foxtive_ntex_multipart::InputError;
 
-        let error = HttpError::MultipartError(MultipartError::ValidationError(InputError {
-            error: MultipartErrorMessage::InvalidFileExtension(Some("mp4".to_string())),
-            name: "image".to_string(),
-        }));
+        let error = HttpError::MultipartError(MultipartError::ValidationError(InputError::new(
+            "image",
+            MultipartErrorMessage::InvalidFileExtension(Some("mp4".to_string())),
+        )));
 
         let app_error = make_http_error_response(&error);
 
-        assert_eq!(app_error.status(), 400);
+        assert_eq!(app_error.status(), 415);
+        assert_eq!(error.kind(), Some(ErrorKind::Multipart));
+
+        let error = HttpError::MultipartError(MultipartError::ValidationError(InputError::new(
+            "image",
+            MultipartErrorMessage::FileTooLarge(1024),
+        )));
+        assert_eq!(error.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error.kind(), Some(ErrorKind::Validation));
+        assert_eq!(make_http_error_response(&error).status(), 422);
+    }
+
+    #[cfg(feature = "multipart")]
+    #[ntex::test]
+    async fn test_form_parse_error_per_field() {
+        use foxtive_ntex_multipart::FieldParseError;
+        use ntex::web::WebResponse;
+        use ntex::web::test::{TestRequest, read_body};
+
+        let err = FieldParseError::invalid::<u8>("age", "forty", "invalid digit found in string");
+        let resp =
+            make_http_error_response(&HttpError::MultipartError(MultipartError::ParseError(err)));
+        assert_eq!(resp.status(), 400);
+
+        let resp = WebResponse::new(resp, TestRequest::default().to_http_request());
+        let body: serde_json::Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["message"], "Validation Error");
+        assert_eq!(
+            body["data"]["age"][0],
+            "Failed to parse field 'age' with value 'forty' as u8: invalid digit found in string"
+        );
+    }
+
+    #[cfg(feature = "multipart")]
+    #[test]
+    fn test_oversized_form_field() {
+        let error =
+            HttpError::MultipartError(MultipartError::DataFieldTooLarge("bio".to_string(), 1024));
+        assert_eq!(error.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(make_http_error_response(&error).status(), 413);
+
+        let error = HttpError::MultipartError(MultipartError::TooManyDataFields(10));
+        assert_eq!(make_http_error_response(&error).status(), 400);
+    }
+
+    #[cfg(feature = "multipart")]
+    #[ntex::test]
+    async fn test_multipart_error_uses_field_label() {
+        use foxtive_ntex_multipart::{FileRules, Validator};
+        use ntex::web::WebResponse;
+        use ntex::web::test::{TestRequest, read_body};
+        use std::collections::HashMap;
+
+        let rules = FileRules {
+            required: true,
+            ..Default::default()
+        };
+        let err = Validator::new()
+            .add_rule("profile_photo", rules)
+            .label("profile_photo", "Profile photo")
+            .validate(&HashMap::new())
+            .unwrap_err();
+
+        let resp = make_http_error_response(&HttpError::MultipartError(err));
+        let resp = WebResponse::new(resp, TestRequest::default().to_http_request());
+        let body: serde_json::Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["code"], "018");
+        assert_eq!(body["message"], "Validation Error");
+        assert_eq!(
+            body["data"]["profile_photo"][0],
+            "No files were uploaded for field: 'Profile photo'"
+        );
     }
 }
@@ -0,0 +1,224 @@
+use foxtive::prelude::AppResult;
+use ntex::web::HttpRequest;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Header carrying the session/connection id on follow-up HTTP requests
+pub const AFFINITY_HEADER: &str = "x-affinity-id";
+
+pub type AffinityFuture<T> = Pin<Box<dyn Future<Output = AppResult<T>>>>;
+
+/// Backend of [`SessionAffinity`], values are serialized JSON.
+///
+/// The default store lives in memory and is shared by the workers of one instance,
+/// implement this over Redis or similar when requests can land on another instance.
+pub trait AffinityStore: Send + Sync + 'static {
+    fn get(&self, key: &str) -> AffinityFuture<Option<String>>;
+
+    fn put(&self, key: &str, value: String, ttl: Duration) -> AffinityFuture<()>;
+
+    fn remove(&self, key: &str) -> AffinityFuture<()>;
+}
+
+/// In-memory [`AffinityStore`], expired entries are dropped on access
+#[derive(Default)]
+pub struct MemoryAffinityStore {
+    entries: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl AffinityStore for MemoryAffinityStore {
+    fn get(&self, key: &str) -> AffinityFuture<Option<String>> {
+        let value = self.entries.lock().ok().and_then(|mut entries| {
+            let now = Instant::now();
+            entries.retain(|_, (_, expires)| *expires > now);
+            entries.get(key).map(|(value, _)| value.clone())
+        });
+
+        Box::pin(async move { Ok(value) })
+    }
+
+    fn put(&self, key: &str, value: String, ttl: Duration) -> AffinityFuture<()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key.to_string(), (value, Instant::now() + ttl));
+        }
+
+        Box::pin(async { Ok(()) })
+    }
+
+    fn remove(&self, key: &str) -> AffinityFuture<()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(key);
+        }
+
+        Box::pin(async { Ok(()) })
+    }
+}
+
+struct Inner {
+    store: RwLock<Arc<dyn AffinityStore>>,
+    ttl: Duration,
+}
+
+/// State of long-lived connections (WebSocket, SSE) made available to the HTTP requests
+/// of the same client, keyed by session/connection id.
+///
+/// Entries expire after 30 minutes without being attached or touched.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::affinity::SessionAffinity;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Cursor {
+///     channel: String,
+///     last_seen: u64,
+/// }
+///
+/// # async fn example(affinity: SessionAffinity) -> foxtive::prelude::AppResult<()> {
+/// // in the WebSocket handler
+/// affinity.attach("conn-42", &Cursor { channel: "orders".into(), last_seen: 10 }).await?;
+///
+/// // in a later HTTP request sending `X-Affinity-Id: conn-42`
+/// let cursor = affinity.get::<Cursor>("conn-42").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SessionAffinity {
+    inner: Arc<Inner>,
+}
+
+impl Default for SessionAffinity {
+    fn default() -> Self {
+        Self::new(MemoryAffinityStore::default(), Duration::from_secs(30 * 60))
+    }
+}
+
+impl SessionAffinity {
+    pub fn new(store: impl AffinityStore, ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                store: RwLock::new(Arc::new(store)),
+                ttl,
+            }),
+        }
+    }
+
+    /// Replace the store, e.g. with a distributed one once its connection is set up
+    pub fn use_store(&self, store: impl AffinityStore) {
+        self.replace_store(Arc::new(store));
+    }
+
+    pub(crate) fn replace_store(&self, store: Arc<dyn AffinityStore>) {
+        if let Ok(mut current) = self.inner.store.write() {
+            *current = store;
+        }
+    }
+
+    /// Id sent by the client in the `X-Affinity-Id` header
+    pub fn id_from_request(req: &HttpRequest) -> Option<String> {
+        req.headers()
+            .get(AFFINITY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Store the state of a connection, replacing the previous one
+    pub async fn attach<T: Serialize>(&self, id: &str, state: &T) -> AppResult<()> {
+        let value = serde_json::to_string(state)?;
+        self.store().put(id, value, self.inner.ttl).await
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, id: &str) -> AppResult<Option<T>> {
+        match self.store().get(id).await? {
+            None => Ok(None),
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+        }
+    }
+
+    /// State of the connection named by the request's `X-Affinity-Id` header
+    pub async fn get_for_request<T: DeserializeOwned>(
+        &self,
+        req: &HttpRequest,
+    ) -> AppResult<Option<T>> {
+        match Self::id_from_request(req) {
+            Some(id) => self.get(&id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Push back the expiration of a connection still alive, e.g. on WebSocket pings
+    pub async fn touch(&self, id: &str) -> AppResult<()> {
+        let store = self.store();
+        if let Some(value) = store.get(id).await? {
+            store.put(id, value, self.inner.ttl).await?;
+        }
+        Ok(())
+    }
+
+    /// Forget a connection, usually when it closes
+    pub async fn detach(&self, id: &str) -> AppResult<()> {
+        self.store().remove(id).await
+    }
+
+    fn store(&self) -> Arc<dyn AffinityStore> {
+        match self.inner.store.read() {
+            Ok(store) => store.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::test::TestRequest;
+
+    #[ntex::test]
+    async fn test_state_shared_with_followup_requests() {
+        let affinity = SessionAffinity::default();
+        affinity.attach("conn-1", &vec!["orders"]).await.unwrap();
+
+        let req = TestRequest::default()
+            .header(AFFINITY_HEADER, "conn-1")
+            .to_http_request();
+        let channels: Option<Vec<String>> = affinity.get_for_request(&req).await.unwrap();
+        assert_eq!(channels.unwrap(), vec!["orders"]);
+
+        let anonymous = TestRequest::default().to_http_request();
+        let none: Option<Vec<String>> = affinity.get_for_request(&anonymous).await.unwrap();
+        assert!(none.is_none());
+
+        affinity.detach("conn-1").await.unwrap();
+        assert!(
+            affinity
+                .get::<Vec<String>>("conn-1")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[ntex::test]
+    async fn test_entries_expire_unless_touched() {
+        let affinity =
+            SessionAffinity::new(MemoryAffinityStore::default(), Duration::from_millis(100));
+        affinity.attach("idle", &1).await.unwrap();
+        affinity.attach("active", &2).await.unwrap();
+
+        std::thread::sleep(Duration::from_millis(60));
+        affinity.touch("active").await.unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(affinity.get::<i32>("idle").await.unwrap().is_none());
+        assert_eq!(affinity.get::<i32>("active").await.unwrap(), Some(2));
+    }
+}
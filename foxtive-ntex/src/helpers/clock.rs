@@ -0,0 +1,233 @@
+use crate::FoxtiveNtexState;
+use crate::error::HttpError;
+use chrono::{DateTime, Utc};
+use ntex::http::Payload;
+use ntex::web::{FromRequest, HttpRequest};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Time source of the application, available on the state as `state.clock` and as an
+/// extractor. Reads the system clock unless frozen, which makes time-dependent behavior
+/// (expirations, signatures, timestamps) testable.
+///
+/// Clones share the frozen time, moving the clock of the state moves it for every handler.
+///
+/// # Example
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use foxtive_ntex::helpers::clock::RequestClock;
+/// use std::time::Duration;
+///
+/// let clock = RequestClock::frozen(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+/// clock.advance(Duration::from_secs(90));
+/// assert_eq!(clock.now().to_rfc3339(), "2026-01-01T00:01:30+00:00");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestClock {
+    frozen: Option<Arc<Mutex<DateTime<Utc>>>>,
+}
+
+impl RequestClock {
+    pub fn system() -> Self {
+        Self::default()
+    }
+
+    /// Clock standing still at `at` until moved with `set` or `advance`
+    pub fn frozen(at: DateTime<Utc>) -> Self {
+        Self {
+            frozen: Some(Arc::new(Mutex::new(at))),
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        match &self.frozen {
+            Some(at) => at.lock().map(|at| *at).unwrap_or_else(|e| *e.into_inner()),
+            None => Utc::now(),
+        }
+    }
+
+    pub fn unix_secs(&self) -> u64 {
+        self.now().timestamp().max(0) as u64
+    }
+
+    pub fn unix_millis(&self) -> u64 {
+        self.now().timestamp_millis().max(0) as u64
+    }
+
+    /// Move a frozen clock to `at`, no effect on the system clock
+    pub fn set(&self, at: DateTime<Utc>) {
+        if let Some(frozen) = &self.frozen {
+            match frozen.lock() {
+                Ok(mut frozen) => *frozen = at,
+                Err(poisoned) => *poisoned.into_inner() = at,
+            }
+        }
+    }
+
+    /// Move a frozen clock forward, no effect on the system clock
+    pub fn advance(&self, by: Duration) {
+        if let Ok(by) = chrono::Duration::from_std(by) {
+            self.set(self.now() + by);
+        }
+    }
+}
+
+/// Source of randomness of the application, available on the state as `state.rng` and as
+/// an extractor. Random by default, a seeded generator replays the same sequence, which
+/// makes random-dependent behavior (identifiers, sampling, experiment buckets) testable.
+///
+/// Not meant for secrets: use a cryptographic generator for keys and passwords.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::clock::RequestRng;
+///
+/// let (first, second) = (RequestRng::seeded(7), RequestRng::seeded(7));
+/// assert_eq!(first.token(12), second.token(12));
+///
+/// let bucket = first.below(100);
+/// assert!(bucket < 100);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestRng {
+    seeded: Option<Arc<Mutex<u64>>>,
+}
+
+impl RequestRng {
+    pub fn system() -> Self {
+        Self::default()
+    }
+
+    /// Deterministic generator, clones share the sequence
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            seeded: Some(Arc::new(Mutex::new(seed))),
+        }
+    }
+
+    pub fn is_seeded(&self) -> bool {
+        self.seeded.is_some()
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        match &self.seeded {
+            Some(state) => {
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                splitmix(*state)
+            }
+            None => RandomState::new().build_hasher().finish(),
+        }
+    }
+
+    /// Number in `0..bound`, 0 when `bound` is 0
+    pub fn below(&self, bound: u64) -> u64 {
+        match bound {
+            0 => 0,
+            _ => self.next_u64() % bound,
+        }
+    }
+
+    /// `true` with the given probability, from 0.0 to 1.0
+    pub fn chance(&self, probability: f64) -> bool {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < probability
+    }
+
+    pub fn pick<'a, T>(&self, items: &'a [T]) -> Option<&'a T> {
+        items.get(self.below(items.len() as u64) as usize)
+    }
+
+    pub fn fill(&self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            let random = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    }
+
+    /// Random alphanumeric string
+    pub fn token(&self, len: usize) -> String {
+        const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+        (0..len)
+            .map(|_| CHARSET[self.below(CHARSET.len() as u64) as usize] as char)
+            .collect()
+    }
+}
+
+fn splitmix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+impl<Err> FromRequest<Err> for RequestClock {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(req
+            .app_state::<FoxtiveNtexState>()
+            .map(|state| state.clock.clone())
+            .unwrap_or_default())
+    }
+}
+
+impl<Err> FromRequest<Err> for RequestRng {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(req
+            .app_state::<FoxtiveNtexState>()
+            .map(|state| state.rng.clone())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_frozen_clock_is_shared_by_clones() {
+        let at = Utc.with_ymd_and_hms(2026, 5, 1, 12, 0, 0).unwrap();
+        let clock = RequestClock::frozen(at);
+        let handler = clock.clone();
+
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(handler.now(), at + chrono::Duration::hours(1));
+        assert_eq!(handler.unix_secs(), at.timestamp() as u64 + 3600);
+
+        // the system clock can't be moved
+        let system = RequestClock::system();
+        system.set(at);
+        assert!(system.now() > at);
+    }
+
+    #[test]
+    fn test_seeded_rng_replays() {
+        let rng = RequestRng::seeded(42);
+        let replay = RequestRng::seeded(42);
+        let values: Vec<u64> = (0..5).map(|_| rng.next_u64()).collect();
+        assert_eq!(
+            values,
+            (0..5).map(|_| replay.next_u64()).collect::<Vec<_>>()
+        );
+        assert_ne!(values[0], values[1]);
+
+        let mut bytes = [0u8; 11];
+        RequestRng::seeded(1).fill(&mut bytes);
+        assert_ne!(bytes, [0u8; 11]);
+
+        let token = RequestRng::seeded(3).token(16);
+        assert_eq!(token.len(), 16);
+        assert!(token.chars().all(|char| char.is_ascii_alphanumeric()));
+        assert_eq!(RequestRng::system().below(0), 0);
+        assert!(!rng.chance(0.0) && rng.chance(1.0));
+    }
+}
@@ -0,0 +1,461 @@
+use crate::helpers::probes::{DependencyProbe, DependencyStatus, Probes};
+use foxtive::prelude::AppResult;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Time given to a component to stop when none is set
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+type ShutdownFn = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = AppResult<()>> + Send>> + Send>;
+
+/// Background subsystem (scheduler, queue consumer, notifier...) stopped during graceful
+/// shutdown
+pub struct Component {
+    name: String,
+    priority: i32,
+    timeout: Duration,
+    critical: bool,
+    stale_after: Option<Duration>,
+    shutdown: ShutdownFn,
+}
+
+impl Component {
+    pub fn new<F, Fut>(name: &str, shutdown: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = AppResult<()>> + Send + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            priority: 0,
+            timeout: DEFAULT_TIMEOUT,
+            critical: false,
+            stale_after: None,
+            shutdown: Box::new(move || Box::pin(shutdown())),
+        }
+    }
+
+    /// Components with a lower priority are stopped first, 0 by default
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Time after which the shutdown is abandoned, 10 seconds by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The application is not ready while this component is down or not ready
+    pub fn critical(mut self) -> Self {
+        self.critical = true;
+        self
+    }
+
+    /// Consider the component dead when it hasn't reported for this long, e.g. a
+    /// scheduler calling [`HealthReporter::beat`] on every tick
+    pub fn stale_after(mut self, duration: Duration) -> Self {
+        self.stale_after = Some(duration);
+        self
+    }
+}
+
+struct HealthEntry {
+    critical: bool,
+    stale_after: Option<Duration>,
+    live: bool,
+    ready: bool,
+    detail: Option<String>,
+    reported_at: Instant,
+}
+
+impl HealthEntry {
+    fn status(&self, name: &str) -> ComponentStatus {
+        let since = self.reported_at.elapsed();
+        let stale = self.stale_after.is_some_and(|limit| since > limit);
+
+        ComponentStatus {
+            name: name.to_string(),
+            critical: self.critical,
+            live: self.live && !stale,
+            ready: self.ready && self.live && !stale,
+            detail: match stale {
+                true => Some(format!("no report for {}s", since.as_secs())),
+                false => self.detail.clone(),
+            },
+            last_report_secs: since.as_secs(),
+        }
+    }
+}
+
+type HealthEntries = Arc<Mutex<BTreeMap<String, HealthEntry>>>;
+
+/// Handle a component uses to report its own liveness and readiness
+#[derive(Clone)]
+pub struct HealthReporter {
+    name: String,
+    entries: HealthEntries,
+}
+
+impl HealthReporter {
+    /// Running and able to do its work
+    pub fn ready(&self) {
+        self.report(true, true, None);
+    }
+
+    /// Running but unable to do its work for now, e.g. reconnecting to the broker
+    pub fn not_ready(&self, reason: &str) {
+        self.report(true, false, Some(reason));
+    }
+
+    /// Stopped or crashed
+    pub fn down(&self, reason: &str) {
+        self.report(false, false, Some(reason));
+    }
+
+    /// Refresh the last report without changing the status
+    pub fn beat(&self) {
+        if let Ok(mut entries) = self.entries.lock()
+            && let Some(entry) = entries.get_mut(&self.name)
+        {
+            entry.reported_at = Instant::now();
+        }
+    }
+
+    fn report(&self, live: bool, ready: bool, detail: Option<&str>) {
+        if let Ok(mut entries) = self.entries.lock()
+            && let Some(entry) = entries.get_mut(&self.name)
+        {
+            if entry.live != live || entry.ready != ready {
+                info!(
+                    "[components] '{}' reported live: {live}, ready: {ready} ({})",
+                    self.name,
+                    detail.unwrap_or("-")
+                );
+            }
+
+            entry.live = live;
+            entry.ready = ready;
+            entry.detail = detail.map(str::to_string);
+            entry.reported_at = Instant::now();
+        }
+    }
+}
+
+/// Last status reported by a component
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentStatus {
+    pub name: String,
+    pub critical: bool,
+    pub live: bool,
+    pub ready: bool,
+    pub detail: Option<String>,
+    pub last_report_secs: u64,
+}
+
+/// Aggregated health of the components and dependencies, only critical ones decide
+/// `live` and `ready`; dependencies only affect `ready`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HealthReport {
+    pub live: bool,
+    pub ready: bool,
+    pub components: Vec<ComponentStatus>,
+    pub dependencies: Vec<DependencyStatus>,
+}
+
+/// How a component's shutdown went
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShutdownOutcome {
+    Stopped,
+    Failed(String),
+    TimedOut,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentShutdown {
+    pub name: String,
+    pub outcome: ShutdownOutcome,
+    pub took: Duration,
+}
+
+/// Components attached to the server, drained by `start_ntex_server` once it stops.
+///
+/// Components are stopped one at a time, by ascending priority; components sharing a
+/// priority are stopped in reverse registration order, so dependents go first.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::components::{Component, ComponentRegistry};
+/// use std::time::Duration;
+///
+/// let registry = ComponentRegistry::default();
+/// registry.register(
+///     Component::new("email-consumer", || async {
+///         // stop consuming, flush in-flight messages...
+///         Ok(())
+///     })
+///     .priority(-10)
+///     .timeout(Duration::from_secs(5)),
+/// );
+/// ```
+#[derive(Clone, Default)]
+pub struct ComponentRegistry {
+    components: Arc<Mutex<Vec<Component>>>,
+    health: HealthEntries,
+    probes: Probes,
+}
+
+impl ComponentRegistry {
+    /// Attach a component, the returned reporter feeds [`ComponentRegistry::health`]
+    pub fn register(&self, component: Component) -> HealthReporter {
+        info!("[components] registered '{}'", component.name);
+
+        if let Ok(mut health) = self.health.lock() {
+            health.insert(
+                component.name.clone(),
+                HealthEntry {
+                    critical: component.critical,
+                    stale_after: component.stale_after,
+                    live: true,
+                    ready: true,
+                    detail: None,
+                    reported_at: Instant::now(),
+                },
+            );
+        }
+
+        let reporter = HealthReporter {
+            name: component.name.clone(),
+            entries: self.health.clone(),
+        };
+
+        if let Ok(mut components) = self.components.lock() {
+            components.push(component);
+        }
+
+        reporter
+    }
+
+    /// Check an upstream dependency when reporting health, see [`DependencyProbe`]
+    pub fn probe(&self, probe: DependencyProbe) {
+        self.probes.add(probe);
+    }
+
+    /// Status reported by every component, with the cached result of every probe
+    pub fn health(&self) -> HealthReport {
+        let components: Vec<_> = match self.health.lock() {
+            Ok(entries) => entries
+                .iter()
+                .map(|(name, entry)| entry.status(name))
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        let dependencies = self.probes.statuses();
+        let critical = || components.iter().filter(|c| c.critical);
+        HealthReport {
+            live: critical().all(|c| c.live),
+            ready: critical().all(|c| c.ready)
+                && dependencies.iter().filter(|d| d.critical).all(|d| d.up),
+            components,
+            dependencies,
+        }
+    }
+
+    /// Same as [`ComponentRegistry::health`], checking the probes whose result expired first
+    pub async fn checked_health(&self) -> HealthReport {
+        self.probes.refresh_expired().await;
+        self.health()
+    }
+
+    /// Spawn the background refresh of the probes, stopped with the other components
+    pub(crate) fn start_probes(&self) {
+        if !self.probes.start() {
+            return;
+        }
+
+        let probes = self.probes.clone();
+        self.register(Component::new("health-probes", move || async move {
+            probes.stop();
+            Ok(())
+        }));
+    }
+
+    /// Names of the registered components, in shutdown order
+    pub fn names(&self) -> Vec<String> {
+        match self.components.lock() {
+            Ok(components) => Self::ordered(components.iter().collect(), |c| c.priority)
+                .into_iter()
+                .map(|c| c.name.clone())
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// Stop every registered component, leaving the registry empty
+    pub async fn shutdown(&self) -> Vec<ComponentShutdown> {
+        let components = match self.components.lock() {
+            Ok(mut components) => std::mem::take(&mut *components),
+            Err(_) => return vec![],
+        };
+
+        if let Ok(mut entries) = self.health.lock() {
+            for entry in entries.values_mut() {
+                entry.ready = false;
+                entry.detail = Some("shutting down".to_string());
+            }
+        }
+
+        let mut report = vec![];
+        for component in Self::ordered(components, |c| c.priority) {
+            info!("[components] stopping '{}'", component.name);
+            let started = Instant::now();
+
+            let outcome = match ntex::time::timeout(component.timeout, (component.shutdown)()).await
+            {
+                Ok(Ok(_)) => ShutdownOutcome::Stopped,
+                Ok(Err(err)) => {
+                    error!("[components] '{}' failed to stop: {err:?}", component.name);
+                    ShutdownOutcome::Failed(err.to_string())
+                }
+                Err(_) => {
+                    warn!(
+                        "[components] '{}' did not stop within {:?}",
+                        component.name, component.timeout
+                    );
+                    ShutdownOutcome::TimedOut
+                }
+            };
+
+            HealthReporter {
+                name: component.name.clone(),
+                entries: self.health.clone(),
+            }
+            .down("stopped");
+
+            report.push(ComponentShutdown {
+                name: component.name,
+                outcome,
+                took: started.elapsed(),
+            });
+        }
+
+        report
+    }
+
+    fn ordered<T>(mut components: Vec<T>, priority: impl Fn(&T) -> i32) -> Vec<T> {
+        components.reverse();
+        // stable sort keeps the reversed registration order within a priority
+        components.sort_by_key(priority);
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxtive::prelude::AppMessage;
+
+    fn recorder(log: &Arc<Mutex<Vec<&'static str>>>, name: &'static str) -> Component {
+        let log = log.clone();
+        Component::new(name, move || async move {
+            log.lock().unwrap().push(name);
+            Ok(())
+        })
+    }
+
+    #[ntex::test]
+    async fn test_drains_by_priority_then_reverse_registration() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let registry = ComponentRegistry::default();
+        registry.register(recorder(&log, "db-pool").priority(10));
+        registry.register(recorder(&log, "scheduler"));
+        registry.register(recorder(&log, "consumer"));
+        registry.register(recorder(&log, "http-clients").priority(-1));
+
+        assert_eq!(
+            registry.names(),
+            vec!["http-clients", "consumer", "scheduler", "db-pool"]
+        );
+
+        let report = registry.shutdown().await;
+        assert!(report.iter().all(|c| c.outcome == ShutdownOutcome::Stopped));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["http-clients", "consumer", "scheduler", "db-pool"]
+        );
+        assert!(registry.names().is_empty());
+    }
+
+    #[ntex::test]
+    async fn test_failures_and_timeouts_do_not_stop_the_drain() {
+        let registry = ComponentRegistry::default();
+        registry.register(
+            Component::new("stuck", || async {
+                ntex::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .timeout(Duration::from_millis(20)),
+        );
+        registry.register(Component::new("broken", || async {
+            AppMessage::InternalServerError.ar()
+        }));
+        registry.register(Component::new("fine", || async { Ok(()) }).priority(1));
+
+        let outcomes: Vec<_> = registry
+            .shutdown()
+            .await
+            .into_iter()
+            .map(|c| (c.name, c.outcome))
+            .collect();
+
+        assert_eq!(outcomes[0].0, "broken");
+        assert!(matches!(outcomes[0].1, ShutdownOutcome::Failed(_)));
+        assert_eq!(
+            outcomes[1],
+            ("stuck".to_string(), ShutdownOutcome::TimedOut)
+        );
+        assert_eq!(outcomes[2], ("fine".to_string(), ShutdownOutcome::Stopped));
+    }
+
+    #[ntex::test]
+    async fn test_health_aggregates_critical_components() {
+        let registry = ComponentRegistry::default();
+        let consumer =
+            registry.register(Component::new("consumer", || async { Ok(()) }).critical());
+        let notifier = registry.register(Component::new("notifier", || async { Ok(()) }));
+        registry.register(
+            Component::new("scheduler", || async { Ok(()) }).stale_after(Duration::from_millis(10)),
+        );
+
+        notifier.down("smtp unreachable");
+        let health = registry.health();
+        assert!(health.live && health.ready);
+
+        consumer.not_ready("reconnecting to broker");
+        let health = registry.health();
+        assert!(health.live);
+        assert!(!health.ready);
+        assert_eq!(
+            health.components[0].detail.as_deref(),
+            Some("reconnecting to broker")
+        );
+
+        ntex::time::sleep(Duration::from_millis(20)).await;
+        let scheduler = registry.health().components.pop().unwrap();
+        assert_eq!(scheduler.name, "scheduler");
+        assert!(!scheduler.live);
+
+        consumer.ready();
+        assert!(registry.health().ready);
+
+        registry.shutdown().await;
+        assert!(!registry.health().ready);
+    }
+}
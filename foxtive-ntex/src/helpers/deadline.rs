@@ -0,0 +1,171 @@
+use crate::contracts::ResponseCodeContract;
+use crate::helpers::json_message::JsonMessage;
+use crate::helpers::responder::Responder;
+use foxtive::prelude::AppResult;
+use futures_util::future::join_all;
+use ntex::http::Response;
+use ntex::time;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::{error, warn};
+
+type SubQuery<T> = Pin<Box<dyn Future<Output = AppResult<T>>>>;
+
+/// Runs several sub-queries under a shared deadline, keeping whatever completed in time.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::deadline::SoftDeadline;
+/// use std::time::Duration;
+///
+/// async fn search(term: String) -> ntex::http::Response {
+///     let results = SoftDeadline::new(Duration::from_millis(300))
+///         .query("products", async { Ok(vec!["pen"]) })
+///         .query("articles", async { Ok(vec!["how to hold a pen"]) })
+///         .run()
+///         .await;
+///
+///     results.respond(foxtive_ntex::enums::ResponseCode::Ok)
+/// }
+/// ```
+pub struct SoftDeadline<T> {
+    timeout: Duration,
+    queries: Vec<(String, SubQuery<T>)>,
+}
+
+/// Results of a [`SoftDeadline`] run, keyed by source name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PartialResults<T> {
+    pub results: BTreeMap<String, T>,
+    /// sources that didn't complete before the deadline
+    pub timed_out: Vec<String>,
+    /// sources that completed with an error
+    pub failed: Vec<String>,
+}
+
+enum Outcome<T> {
+    Done(T),
+    TimedOut,
+    Failed,
+}
+
+impl<T: 'static> SoftDeadline<T> {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            queries: vec![],
+        }
+    }
+
+    pub fn query<F>(mut self, source: &str, query: F) -> Self
+    where
+        F: Future<Output = AppResult<T>> + 'static,
+    {
+        self.queries.push((source.to_string(), Box::pin(query)));
+        self
+    }
+
+    /// Run all sub-queries concurrently, those still running at the deadline are dropped
+    pub async fn run(self) -> PartialResults<T> {
+        let timeout = self.timeout;
+        let outcomes = join_all(self.queries.into_iter().map(|(source, query)| async move {
+            let outcome = match time::timeout(timeout, query).await {
+                Ok(Ok(value)) => Outcome::Done(value),
+                Ok(Err(err)) => {
+                    error!("[soft-deadline] source '{source}' failed: {err:?}");
+                    Outcome::Failed
+                }
+                Err(_) => {
+                    warn!("[soft-deadline] source '{source}' timed out after {timeout:?}");
+                    Outcome::TimedOut
+                }
+            };
+
+            (source, outcome)
+        }))
+        .await;
+
+        let mut partial = PartialResults {
+            results: BTreeMap::new(),
+            timed_out: vec![],
+            failed: vec![],
+        };
+
+        for (source, outcome) in outcomes {
+            match outcome {
+                Outcome::Done(value) => {
+                    partial.results.insert(source, value);
+                }
+                Outcome::TimedOut => partial.timed_out.push(source),
+                Outcome::Failed => partial.failed.push(source),
+            }
+        }
+
+        partial
+    }
+}
+
+impl<T> PartialResults<T> {
+    /// Whether some sources are missing from the results
+    pub fn is_partial(&self) -> bool {
+        !self.timed_out.is_empty() || !self.failed.is_empty()
+    }
+}
+
+impl<T: Serialize> PartialResults<T> {
+    /// Standard envelope with the results as `data`, plus `partial`, `timed_out` and `failed`
+    pub fn respond<C: ResponseCodeContract>(self, code: C) -> Response {
+        let partial = self.is_partial();
+        let envelope = JsonMessage::make(&self.results, code.code(), code.success(), None);
+
+        match serde_json::to_value(envelope) {
+            Ok(mut envelope) => {
+                envelope["partial"] = partial.into();
+                envelope["timed_out"] = self.timed_out.into();
+                envelope["failed"] = self.failed.into();
+                Responder::with_code_headers(Responder::respond(envelope, code.status()), &code)
+            }
+            Err(err) => {
+                error!("[soft-deadline] failed to serialize results: {err}");
+                Responder::internal_server_error()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::ResponseCode;
+    use foxtive::prelude::AppMessage;
+    use ntex::web::test::read_body;
+    use ntex::web::{HttpResponse, WebResponse, test::TestRequest};
+
+    #[ntex::test]
+    async fn test_keeps_results_completed_in_time() {
+        let results = SoftDeadline::new(Duration::from_millis(50))
+            .query("fast", async { Ok(1) })
+            .query("slow", async {
+                time::sleep(Duration::from_secs(5)).await;
+                Ok(2)
+            })
+            .query("broken", async { AppMessage::InternalServerError.ar() })
+            .run()
+            .await;
+
+        assert!(results.is_partial());
+        assert_eq!(results.results.get("fast"), Some(&1));
+        assert_eq!(results.timed_out, vec!["slow"]);
+        assert_eq!(results.failed, vec!["broken"]);
+
+        let resp: HttpResponse = results.respond(ResponseCode::Ok);
+        let resp = WebResponse::new(resp, TestRequest::default().to_http_request());
+        let body: serde_json::Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["partial"], true);
+        assert_eq!(body["data"]["fast"], 1);
+        assert_eq!(body["timed_out"][0], "slow");
+    }
+}
@@ -0,0 +1,331 @@
+use crate::helpers::clock::RequestRng;
+use foxtive::prelude::AppResult;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// How long [`DistributedLocks::lock`] waits before trying a held lock again
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+pub type LockFuture<T> = Pin<Box<dyn Future<Output = AppResult<T>>>>;
+
+/// Backend of [`DistributedLocks`], `token` identifies the holder so a lock taken over
+/// after expiring is never released by its previous holder.
+///
+/// The default provider lives in memory and only coordinates the workers of one instance,
+/// use [`RedisLockProvider`] (feature `redis`) or similar when instances must coordinate.
+pub trait LockProvider: Send + Sync + 'static {
+    /// Take `key` for `ttl` unless someone else holds it, `true` when taken
+    fn try_acquire(&self, key: &str, token: &str, ttl: Duration) -> LockFuture<bool>;
+
+    /// Release `key` if still held with `token`
+    fn release(&self, key: &str, token: &str) -> LockFuture<()>;
+}
+
+/// In-memory [`LockProvider`], expired locks can be taken again
+#[derive(Default)]
+pub struct MemoryLockProvider {
+    locks: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl LockProvider for MemoryLockProvider {
+    fn try_acquire(&self, key: &str, token: &str, ttl: Duration) -> LockFuture<bool> {
+        let acquired = self.locks.lock().is_ok_and(|mut locks| {
+            let now = Instant::now();
+            match locks.get(key) {
+                Some((_, expires)) if *expires > now => false,
+                _ => {
+                    locks.insert(key.to_string(), (token.to_string(), now + ttl));
+                    true
+                }
+            }
+        });
+
+        Box::pin(async move { Ok(acquired) })
+    }
+
+    fn release(&self, key: &str, token: &str) -> LockFuture<()> {
+        if let Ok(mut locks) = self.locks.lock()
+            && locks.get(key).is_some_and(|(holder, _)| holder == token)
+        {
+            locks.remove(key);
+        }
+
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// [`LockProvider`] over the foxtive Redis connection (feature `redis`), locks are
+/// `SET NX PX` keys released with a compare-and-delete script
+#[cfg(feature = "redis")]
+pub struct RedisLockProvider {
+    redis: Arc<foxtive::redis::Redis>,
+    prefix: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisLockProvider {
+    pub fn new(redis: Arc<foxtive::redis::Redis>) -> Self {
+        Self {
+            redis,
+            prefix: "lock:".to_string(),
+        }
+    }
+
+    /// Prefix of the lock keys, `lock:` by default
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+}
+
+#[cfg(feature = "redis")]
+impl LockProvider for RedisLockProvider {
+    fn try_acquire(&self, key: &str, token: &str, ttl: Duration) -> LockFuture<bool> {
+        let (redis, key, token) = (
+            self.redis.clone(),
+            format!("{}{key}", self.prefix),
+            token.to_string(),
+        );
+
+        Box::pin(async move {
+            let mut conn = redis.redis().await?;
+            let reply: Option<String> = deadpool_redis::redis::cmd("SET")
+                .arg(&key)
+                .arg(&token)
+                .arg("NX")
+                .arg("PX")
+                .arg(ttl.as_millis().max(1) as u64)
+                .query_async(&mut conn)
+                .await
+                .map_err(foxtive::Error::from)?;
+            Ok(reply.is_some())
+        })
+    }
+
+    fn release(&self, key: &str, token: &str) -> LockFuture<()> {
+        const RELEASE: &str = r#"
+            if redis.call("GET", KEYS[1]) == ARGV[1] then
+                return redis.call("DEL", KEYS[1])
+            end
+            return 0
+        "#;
+
+        let (redis, key, token) = (
+            self.redis.clone(),
+            format!("{}{key}", self.prefix),
+            token.to_string(),
+        );
+
+        Box::pin(async move {
+            let mut conn = redis.redis().await?;
+            let _: i32 = deadpool_redis::redis::cmd("EVAL")
+                .arg(RELEASE)
+                .arg(1)
+                .arg(&key)
+                .arg(&token)
+                .query_async(&mut conn)
+                .await
+                .map_err(foxtive::Error::from)?;
+            Ok(())
+        })
+    }
+}
+
+/// Locks shared by the instances of an application, for handlers that must not run
+/// concurrently anywhere (cron-like endpoints, migration triggers). Locks expire after
+/// their ttl, so a crashed holder never blocks the others for good.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::FoxtiveNtexState;
+/// use std::time::Duration;
+///
+/// # async fn example(state: FoxtiveNtexState) -> foxtive::prelude::AppResult<()> {
+/// let guard = state.lock("invoice:123", Duration::from_secs(30)).await?;
+/// // ... only one instance gets here at a time
+/// guard.release().await?;
+///
+/// // skip the work instead of waiting when someone else is on it
+/// if let Some(_guard) = state.try_lock("nightly-report", Duration::from_secs(600)).await? {
+///     // ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct DistributedLocks {
+    provider: Arc<RwLock<Arc<dyn LockProvider>>>,
+}
+
+impl Default for DistributedLocks {
+    fn default() -> Self {
+        Self::new(MemoryLockProvider::default())
+    }
+}
+
+impl DistributedLocks {
+    pub fn new(provider: impl LockProvider) -> Self {
+        Self {
+            provider: Arc::new(RwLock::new(Arc::new(provider))),
+        }
+    }
+
+    /// Replace the provider, e.g. with a distributed one once its connection is set up
+    pub fn use_provider(&self, provider: impl LockProvider) {
+        self.replace_provider(Arc::new(provider));
+    }
+
+    pub(crate) fn replace_provider(&self, provider: Arc<dyn LockProvider>) {
+        if let Ok(mut current) = self.provider.write() {
+            *current = provider;
+        }
+    }
+
+    /// Wait until `key` is free, then hold it for at most `ttl`
+    pub async fn lock(&self, key: &str, ttl: Duration) -> AppResult<DistributedLockGuard> {
+        loop {
+            if let Some(guard) = self.try_lock(key, ttl).await? {
+                return Ok(guard);
+            }
+            ntex::time::sleep(RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Hold `key` for at most `ttl`, `None` when someone else holds it
+    pub async fn try_lock(
+        &self,
+        key: &str,
+        ttl: Duration,
+    ) -> AppResult<Option<DistributedLockGuard>> {
+        let provider = self.provider();
+        let token = RequestRng::system().token(32);
+
+        match provider.try_acquire(key, &token, ttl).await? {
+            false => Ok(None),
+            true => {
+                debug!("[distributed-lock] acquired '{key}'");
+                Ok(Some(DistributedLockGuard {
+                    key: key.to_string(),
+                    token,
+                    provider: Some(provider),
+                }))
+            }
+        }
+    }
+
+    fn provider(&self) -> Arc<dyn LockProvider> {
+        match self.provider.read() {
+            Ok(provider) => provider.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+/// Held distributed lock, released with [`DistributedLockGuard::release`] or in the
+/// background when dropped
+pub struct DistributedLockGuard {
+    key: String,
+    token: String,
+    provider: Option<Arc<dyn LockProvider>>,
+}
+
+impl DistributedLockGuard {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub async fn release(mut self) -> AppResult<()> {
+        match self.provider.take() {
+            Some(provider) => provider.release(&self.key, &self.token).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for DistributedLockGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            let (key, token) = (self.key.clone(), self.token.clone());
+            ntex::rt::spawn(async move {
+                if let Err(err) = provider.release(&key, &token).await {
+                    warn!("[distributed-lock] failed to release '{key}': {err}");
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[ntex::test]
+    async fn test_locks_exclude_and_expire() {
+        let locks = DistributedLocks::default();
+
+        let guard = locks
+            .lock("invoice:1", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(
+            locks
+                .try_lock("invoice:1", Duration::from_secs(5))
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            locks
+                .try_lock("invoice:2", Duration::from_secs(5))
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        guard.release().await.unwrap();
+        let _guard = locks
+            .try_lock("invoice:1", Duration::from_millis(10))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // expired locks are taken over
+        ntex::time::sleep(Duration::from_millis(20)).await;
+        assert!(
+            locks
+                .try_lock("invoice:1", Duration::from_secs(5))
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[ntex::test]
+    async fn test_stale_holder_cannot_release() {
+        let provider = MemoryLockProvider::default();
+        assert!(
+            provider
+                .try_acquire("job", "a", Duration::ZERO)
+                .await
+                .unwrap()
+        );
+        assert!(
+            provider
+                .try_acquire("job", "b", Duration::from_secs(5))
+                .await
+                .unwrap()
+        );
+
+        provider.release("job", "a").await.unwrap();
+        assert!(
+            !provider
+                .try_acquire("job", "c", Duration::from_secs(5))
+                .await
+                .unwrap()
+        );
+    }
+}
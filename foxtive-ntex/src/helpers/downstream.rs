@@ -0,0 +1,284 @@
+use crate::enums::ResponseCode;
+use foxtive::prelude::{AppMessage, AppResult};
+use ntex::http::client::Client;
+use ntex::http::{Method, StatusCode};
+use ntex::util::Bytes;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+pub type DownstreamFuture<T> = Pin<Box<dyn Future<Output = AppResult<T>>>>;
+
+/// Largest downstream response body read by [`NtexDownstreamTransport`]
+const RESPONSE_LIMIT: usize = 4 * 1024 * 1024;
+
+thread_local! {
+    // ntex clients can't leave their thread, the calls of a thread share its client
+    // and its connection pool
+    static CLIENT: Client = Client::new();
+}
+
+/// Sends requests to other services, returning the response status and body
+pub trait DownstreamTransport: Send + Sync + 'static {
+    fn send(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<Bytes>,
+    ) -> DownstreamFuture<(StatusCode, Bytes)>;
+}
+
+/// [`DownstreamTransport`] using the ntex HTTP client, `https` URLs need one of the TLS
+/// features of ntex (enabled by the `webhooks` feature)
+pub struct NtexDownstreamTransport {
+    pub timeout: Duration,
+}
+
+impl Default for NtexDownstreamTransport {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl DownstreamTransport for NtexDownstreamTransport {
+    fn send(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<Bytes>,
+    ) -> DownstreamFuture<(StatusCode, Bytes)> {
+        let request = CLIENT
+            .with(|client| client.request(method, url))
+            .timeout(self.timeout);
+
+        Box::pin(async move {
+            let sent = match body {
+                Some(body) => request.content_type("application/json").send_body(body),
+                None => request.send(),
+            };
+            let mut response = sent
+                .await
+                .map_err(|err| downstream_unavailable(&err.to_string()).ae())?;
+            let body = response
+                .body()
+                .limit(RESPONSE_LIMIT)
+                .await
+                .map_err(|err| downstream_unavailable(&err.to_string()).ae())?;
+
+            Ok((response.status(), body))
+        })
+    }
+}
+
+/// Client of the other services, turning their error responses into [`AppMessage`]s with
+/// [`check_downstream`]. Available on the server state as `state.downstream`, the transport
+/// can be replaced, e.g. by a stub in tests.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::downstream::Downstream;
+/// use serde_json::Value;
+///
+/// # async fn example(downstream: Downstream) -> foxtive::prelude::AppResult<()> {
+/// let user: Value = downstream.get("http://users.internal/api/v1/users/5").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Downstream {
+    transport: Arc<RwLock<Arc<dyn DownstreamTransport>>>,
+}
+
+impl Default for Downstream {
+    fn default() -> Self {
+        Self::new(NtexDownstreamTransport::default())
+    }
+}
+
+impl Downstream {
+    pub fn new(transport: impl DownstreamTransport) -> Self {
+        Self {
+            transport: Arc::new(RwLock::new(Arc::new(transport))),
+        }
+    }
+
+    pub fn use_transport(&self, transport: impl DownstreamTransport) {
+        if let Ok(mut current) = self.transport.write() {
+            *current = Arc::new(transport);
+        }
+    }
+
+    /// Body of a successful response, the failures as errors carrying the downstream status
+    pub async fn send(&self, method: Method, url: &str, body: Option<Bytes>) -> AppResult<Bytes> {
+        let transport = match self.transport.read() {
+            Ok(transport) => transport.clone(),
+            Err(_) => return Err(downstream_unavailable("transport lock poisoned").ae()),
+        };
+
+        let (status, body) = transport.send(method, url, body).await?;
+        check_downstream(status, body)
+    }
+
+    /// `GET` a JSON resource
+    pub async fn get<T: DeserializeOwned>(&self, url: &str) -> AppResult<T> {
+        let body = self.send(Method::GET, url, None).await?;
+        parse(&body)
+    }
+
+    /// Send `body` as JSON, deserializing the JSON response
+    pub async fn json<B, T>(&self, method: Method, url: &str, body: &B) -> AppResult<T>
+    where
+        B: Serialize,
+        T: DeserializeOwned,
+    {
+        let body = serde_json::to_vec(body)?;
+        let body = self.send(method, url, Some(Bytes::from(body))).await?;
+        parse(&body)
+    }
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> AppResult<T> {
+    serde_json::from_slice(body)
+        .map_err(|err| downstream_unavailable(&format!("invalid response body: {err}")).ae())
+}
+
+fn downstream_unavailable(reason: &str) -> AppMessage {
+    AppMessage::ErrorMessage(
+        format!("Downstream service unavailable: {reason}"),
+        StatusCode::SERVICE_UNAVAILABLE,
+    )
+}
+
+/// Convert an error response of another service into an [`AppMessage`] keeping its status.
+/// Statuses without a [`ResponseCode`] take the nearest one, e.g. 502 becomes 503 and
+/// 408 becomes 504, so the message can be rendered.
+///
+/// Understands RFC 7807 `application/problem+json` bodies (`detail`, falling back to `title`)
+/// and foxtive envelopes (`message`), other bodies fall back to the status reason.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::downstream::downstream_error;
+/// use ntex::http::StatusCode;
+///
+/// let body = br#"{"type":"about:blank","title":"Not Found","status":404,"detail":"No such user"}"#;
+/// let message = downstream_error(StatusCode::NOT_FOUND, body);
+///
+/// assert_eq!(message.status_code(), StatusCode::NOT_FOUND);
+/// assert_eq!(message.message(), "No such user");
+/// ```
+pub fn downstream_error(status: StatusCode, body: &[u8]) -> AppMessage {
+    let message = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|body| extract_message(&body))
+        .unwrap_or_else(|| {
+            status
+                .canonical_reason()
+                .unwrap_or("Downstream service error")
+                .to_string()
+        });
+
+    AppMessage::ErrorMessage(message, nearest_status(status))
+}
+
+/// Pass successful downstream bodies through, turn failures into an error carrying
+/// the downstream status so gateway handlers can use `?`
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::downstream::check_downstream;
+/// use ntex::http::StatusCode;
+///
+/// let body = br#"{"success":false,"code":"007","message":"Forbidden"}"#.to_vec();
+/// let err = check_downstream(StatusCode::FORBIDDEN, body).unwrap_err();
+/// ```
+pub fn check_downstream<B: AsRef<[u8]>>(status: StatusCode, body: B) -> AppResult<B> {
+    match status.is_success() {
+        true => Ok(body),
+        false => Err(downstream_error(status, body.as_ref()).ae()),
+    }
+}
+
+/// `status` when it has a [`ResponseCode`], else the closest status having one
+fn nearest_status(status: StatusCode) -> StatusCode {
+    if ResponseCode::try_from_status(status).is_some() {
+        return status;
+    }
+
+    match status {
+        StatusCode::REQUEST_TIMEOUT => StatusCode::GATEWAY_TIMEOUT,
+        StatusCode::PRECONDITION_FAILED | StatusCode::PRECONDITION_REQUIRED => StatusCode::CONFLICT,
+        status if status.is_server_error() => StatusCode::SERVICE_UNAVAILABLE,
+        status if status.is_client_error() => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn extract_message(body: &Value) -> Option<String> {
+    let text = |key: &str| {
+        body.get(key)
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    };
+
+    // problem details have "type"/"title", foxtive envelopes have "code"/"success"
+    text("detail")
+        .or_else(|| text("message"))
+        .or_else(|| text("title"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::response::anyhow::helpers::{make_response, make_status_code};
+
+    #[test]
+    fn test_envelope_and_problem_details() {
+        let body = br#"{"success":false,"code":"009","message":"Email taken","data":{}}"#;
+        let message = downstream_error(StatusCode::CONFLICT, body);
+        assert_eq!(message.message(), "Email taken");
+        assert_eq!(message.status_code(), StatusCode::CONFLICT);
+
+        let body = br#"{"title":"Too Many Requests","status":429}"#;
+        let message = downstream_error(StatusCode::TOO_MANY_REQUESTS, body);
+        assert_eq!(message.message(), "Too Many Requests");
+    }
+
+    #[test]
+    fn test_status_preserved_through_question_mark() {
+        let err = check_downstream(StatusCode::FORBIDDEN, "{}").unwrap_err();
+        assert_eq!(make_status_code(&err), StatusCode::FORBIDDEN);
+
+        assert!(check_downstream(StatusCode::OK, "{}").is_ok());
+    }
+
+    #[test]
+    fn test_unmapped_statuses_render() {
+        let cases = [
+            (StatusCode::BAD_GATEWAY, StatusCode::SERVICE_UNAVAILABLE),
+            (StatusCode::REQUEST_TIMEOUT, StatusCode::GATEWAY_TIMEOUT),
+            (StatusCode::PRECONDITION_FAILED, StatusCode::CONFLICT),
+            (
+                StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (StatusCode::IM_A_TEAPOT, StatusCode::BAD_REQUEST),
+            (StatusCode::NOT_MODIFIED, StatusCode::INTERNAL_SERVER_ERROR),
+        ];
+
+        for (downstream, expected) in cases {
+            let err = check_downstream(downstream, "<html>oops</html>").unwrap_err();
+            assert_eq!(make_response(&err).status(), expected);
+        }
+
+        let err = check_downstream(StatusCode::BAD_GATEWAY, "<html>oops</html>").unwrap_err();
+        assert_eq!(err.to_string(), "Bad Gateway");
+    }
+}
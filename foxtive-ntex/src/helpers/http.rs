@@ -3,10 +3,23 @@ use chrono::NaiveDateTime;
 use ntex::web::types::Query;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "cursor")]
+use crate::helpers::secure_compare::secure_eq;
 use foxtive::enums::app_message::AppMessage;
+#[cfg(feature = "cursor")]
+use foxtive::helpers::{
+    base64::Base64,
+    hmac::{HashFunc, Hmac},
+};
+#[cfg(feature = "cursor")]
+use foxtive::prelude::AppResult;
+#[cfg(feature = "cursor")]
+use serde::de::DeserializeOwned;
 
 pub type TheQueryParams = Query<QueryParams>;
 
+pub type TheCursorParams = Query<CursorParams>;
+
 /// Represents common query parameters used for filtering, pagination, and sorting in API requests.
 #[derive(Deserialize, Clone, Default)]
 pub struct QueryParams {
@@ -87,6 +100,173 @@ impl QueryParams {
     }
 }
 
+/// Which side of the cursor to read
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorDirection {
+    /// items after the cursor
+    #[default]
+    Next,
+    /// items before the cursor
+    Prev,
+}
+
+/// Query parameters of cursor-paginated endpoints.
+///
+/// Example: `?cursor=eyJpZCI6NDJ9.5f2b...&limit=20&direction=next`
+#[derive(Deserialize, Clone, Default, Debug)]
+pub struct CursorParams {
+    /// Opaque cursor returned by the previous page, absent for the first page
+    pub cursor: Option<String>,
+
+    /// The maximum number of results to return.
+    pub limit: Option<i64>,
+
+    /// `next` (default) or `prev`
+    pub direction: Option<CursorDirection>,
+}
+
+impl CursorParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(10).clamp(1, 150)
+    }
+
+    pub fn direction(&self) -> CursorDirection {
+        self.direction.unwrap_or_default()
+    }
+
+    /// Position encoded in the cursor, `None` on the first page
+    #[cfg(feature = "cursor")]
+    pub fn position<T: DeserializeOwned>(&self, codec: &CursorCodec) -> AppResult<Option<T>> {
+        match self.cursor.as_deref().filter(|cursor| !cursor.is_empty()) {
+            Some(cursor) => codec.decode(cursor).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A page of a cursor-paginated listing
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    /// cursor of the following page, `None` on the last one
+    pub next_cursor: Option<String>,
+    /// cursor of the preceding page, `None` on the first one
+    pub prev_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+impl<T> CursorPage<T> {
+    pub fn new(items: Vec<T>, next_cursor: Option<String>, prev_cursor: Option<String>) -> Self {
+        Self {
+            has_more: next_cursor.is_some(),
+            items,
+            next_cursor,
+            prev_cursor,
+        }
+    }
+
+    /// Build the page from up to `limit + 1` fetched items, the extra one telling
+    /// whether another page exists. `position` extracts what the cursors encode
+    /// (e.g. the id, or the sort column and id).
+    ///
+    /// With `direction=prev`, fetch the items before the cursor in reverse order, closest
+    /// to the cursor first (`WHERE id < $cursor ORDER BY id DESC LIMIT $limit + 1`), they
+    /// are put back in the listing order. `has_more` then tells whether earlier items exist.
+    #[cfg(feature = "cursor")]
+    pub fn from_fetched<P, F>(
+        mut items: Vec<T>,
+        params: &CursorParams,
+        codec: &CursorCodec,
+        position: F,
+    ) -> AppResult<Self>
+    where
+        P: Serialize,
+        F: Fn(&T) -> P,
+    {
+        let limit = params.limit() as usize;
+        let has_more = items.len() > limit;
+        items.truncate(limit);
+
+        let encode = |item: Option<&T>| -> AppResult<Option<String>> {
+            item.map(|item| codec.encode(&position(item))).transpose()
+        };
+
+        // the page the cursor came from is on the other side
+        let from_cursor = params.cursor.is_some();
+        let (has_next, has_prev) = match params.direction() {
+            CursorDirection::Next => (has_more, from_cursor),
+            CursorDirection::Prev => {
+                items.reverse();
+                (from_cursor, has_more)
+            }
+        };
+
+        let next_cursor = match has_next {
+            true => encode(items.last())?,
+            false => None,
+        };
+        let prev_cursor = match has_prev {
+            true => encode(items.first())?,
+            false => None,
+        };
+
+        Ok(Self {
+            items,
+            next_cursor,
+            prev_cursor,
+            has_more,
+        })
+    }
+}
+
+/// Encodes positions into opaque cursors (URL-safe base64 JSON) signed with HMAC-SHA256,
+/// so clients can't forge or alter them
+#[cfg(feature = "cursor")]
+#[derive(Clone)]
+pub struct CursorCodec {
+    hmac: Hmac,
+}
+
+#[cfg(feature = "cursor")]
+impl CursorCodec {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            hmac: Hmac::new(secret, HashFunc::Sha256),
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, position: &T) -> AppResult<String> {
+        let json = serde_json::to_string(position)?;
+        let payload = Base64::encode(&json)?
+            .trim_end_matches('=')
+            .replace('+', "-")
+            .replace('/', "_");
+
+        let signature = self.hmac.hash(&payload)?;
+        Ok(format!("{payload}.{signature}"))
+    }
+
+    /// Fails with 400 when the cursor is malformed or was tampered with
+    pub fn decode<T: DeserializeOwned>(&self, cursor: &str) -> AppResult<T> {
+        let invalid = || AppMessage::WarningMessage("Invalid pagination cursor").ae();
+
+        let (payload, signature) = cursor.split_once('.').ok_or_else(invalid)?;
+        let expected = self.hmac.hash(&payload.to_string())?;
+        if !secure_eq(&expected, signature) {
+            return Err(invalid());
+        }
+
+        let mut padded = payload.replace('-', "+").replace('_', "/");
+        while padded.len() % 4 != 0 {
+            padded.push('=');
+        }
+
+        let json = Base64::decode(&padded).map_err(|_| invalid())?;
+        serde_json::from_str(&json).map_err(|_| invalid())
+    }
+}
+
 #[allow(dead_code)]
 pub fn date_from_unsafe_input(date: &str, field_name: &str) -> Result<NaiveDateTime, AppMessage> {
     NaiveDateTime::parse_from_str(format!("{date} 00:00:00").as_str(), "%Y-%m-%d %H:%M:%S").map_err(
@@ -103,3 +283,106 @@ pub struct HttpHeaderItem {
     pub name: String,
     pub value: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_params_defaults() {
+        let params = CursorParams::default();
+        assert_eq!(params.limit(), 10);
+        assert_eq!(params.direction(), CursorDirection::Next);
+
+        let params = CursorParams {
+            limit: Some(1_000),
+            ..Default::default()
+        };
+        assert_eq!(params.limit(), 150);
+    }
+
+    #[cfg(feature = "cursor")]
+    #[test]
+    fn test_signed_cursors() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Position {
+            created_at: i64,
+            id: u64,
+        }
+
+        let codec = CursorCodec::new("secret");
+        let position = Position {
+            created_at: 1_700_000_000,
+            id: 42,
+        };
+
+        let cursor = codec.encode(&position).unwrap();
+        assert!(!cursor.contains(['+', '/', '=']));
+        assert_eq!(codec.decode::<Position>(&cursor).unwrap(), position);
+
+        // tampered payload or signature, other secret
+        let (payload, signature) = cursor.split_once('.').unwrap();
+        let forged = codec
+            .encode(&Position {
+                created_at: 0,
+                id: 1,
+            })
+            .unwrap();
+        let forged_payload = forged.split_once('.').unwrap().0;
+        assert!(
+            codec
+                .decode::<Position>(&format!("{forged_payload}.{signature}"))
+                .is_err()
+        );
+        assert!(codec.decode::<Position>(&format!("{payload}.00")).is_err());
+        assert!(
+            CursorCodec::new("other")
+                .decode::<Position>(&cursor)
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "cursor")]
+    #[test]
+    fn test_page_from_fetched_items() {
+        let codec = CursorCodec::new("secret");
+        let params = CursorParams {
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        let page = CursorPage::from_fetched(vec![1, 2, 3], &params, &codec, |id| *id).unwrap();
+        assert_eq!(page.items, vec![1, 2]);
+        assert!(page.has_more);
+        assert!(page.prev_cursor.is_none());
+
+        let params = CursorParams {
+            cursor: page.next_cursor,
+            ..params
+        };
+        assert_eq!(params.position::<i32>(&codec).unwrap(), Some(2));
+
+        let page = CursorPage::from_fetched(vec![3], &params, &codec, |id| *id).unwrap();
+        assert!(!page.has_more);
+        assert!(page.next_cursor.is_none());
+        assert!(page.prev_cursor.is_some());
+
+        // going back from 3: items before it, fetched closest first
+        let params = CursorParams {
+            cursor: page.prev_cursor,
+            direction: Some(CursorDirection::Prev),
+            ..params
+        };
+        assert_eq!(params.position::<i32>(&codec).unwrap(), Some(3));
+
+        let page = CursorPage::from_fetched(vec![2, 1], &params, &codec, |id| *id).unwrap();
+        assert_eq!(page.items, vec![1, 2]);
+        assert!(!page.has_more);
+        assert!(page.prev_cursor.is_none());
+        let next = CursorParams {
+            cursor: page.next_cursor,
+            ..Default::default()
+        };
+        assert_eq!(next.position::<i32>(&codec).unwrap(), Some(2));
+    }
+}
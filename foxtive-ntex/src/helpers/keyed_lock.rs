@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Unused keys are forgotten after this long
+const DEFAULT_IDLE_TTL: Duration = Duration::from_secs(60);
+
+/// Idle keys are only looked for once this many are tracked
+const SWEEP_THRESHOLD: usize = 1024;
+
+/// Counters describing how much serialization is happening
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct KeyedLockStats {
+    /// total number of acquired locks
+    pub acquisitions: u64,
+    /// acquisitions that had to wait for another holder
+    pub contended: u64,
+    /// keys currently tracked (held or recently used)
+    pub keys: usize,
+}
+
+struct Slot {
+    lock: Arc<AsyncMutex<()>>,
+    last_used: Instant,
+}
+
+/// Tracked keys, idle ones are swept once their number doubled since the last sweep
+#[derive(Default)]
+struct Slots {
+    entries: HashMap<String, Slot>,
+    /// number of keys left by the last sweep
+    swept: usize,
+}
+
+struct Inner {
+    slots: Mutex<Slots>,
+    idle_ttl: Duration,
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+}
+
+/// Registry of async mutexes keyed by resource, used to serialize conflicting
+/// requests (e.g. updates of the same wallet) within the process.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::keyed_lock::KeyedLocks;
+///
+/// # async fn example() {
+/// let locks = KeyedLocks::default();
+///
+/// let _guard = locks.serialize_on("wallet:42").await;
+/// // other requests for "wallet:42" wait until the guard is dropped
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct KeyedLocks {
+    inner: Arc<Inner>,
+}
+
+/// Held lock, released when dropped
+pub struct KeyedGuard {
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Default for KeyedLocks {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDLE_TTL)
+    }
+}
+
+impl KeyedLocks {
+    /// Create a registry forgetting keys unused for `idle_ttl`, looked for once the number
+    /// of tracked keys doubled since the last sweep
+    pub fn new(idle_ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                slots: Mutex::new(Slots::default()),
+                idle_ttl,
+                acquisitions: AtomicU64::new(0),
+                contended: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Wait until no other holder of `key` remains, then hold it until the guard is dropped
+    pub async fn serialize_on(&self, key: &str) -> KeyedGuard {
+        let lock = self.slot(key);
+
+        let guard = match lock.clone().try_lock_owned() {
+            Ok(guard) => guard,
+            Err(_) => {
+                self.inner.contended.fetch_add(1, Ordering::Relaxed);
+                lock.lock_owned().await
+            }
+        };
+
+        self.inner.acquisitions.fetch_add(1, Ordering::Relaxed);
+        KeyedGuard { _guard: guard }
+    }
+
+    pub fn stats(&self) -> KeyedLockStats {
+        KeyedLockStats {
+            acquisitions: self.inner.acquisitions.load(Ordering::Relaxed),
+            contended: self.inner.contended.load(Ordering::Relaxed),
+            keys: self
+                .inner
+                .slots
+                .lock()
+                .map(|slots| slots.entries.len())
+                .unwrap_or(0),
+        }
+    }
+
+    fn slot(&self, key: &str) -> Arc<AsyncMutex<()>> {
+        let now = Instant::now();
+        let mut slots = self.inner.slots.lock().unwrap();
+
+        // expired keys are dropped unless someone still holds or waits on them
+        if slots.entries.len() >= (slots.swept * 2).max(SWEEP_THRESHOLD) {
+            slots.entries.retain(|_, slot| {
+                Arc::strong_count(&slot.lock) > 1
+                    || now.duration_since(slot.last_used) < self.inner.idle_ttl
+            });
+            slots.swept = slots.entries.len();
+        }
+
+        let slot = slots
+            .entries
+            .entry(key.to_string())
+            .or_insert_with(|| Slot {
+                lock: Arc::new(AsyncMutex::new(())),
+                last_used: now,
+            });
+
+        slot.last_used = now;
+        slot.lock.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_serializes_same_key_only() {
+        let locks = KeyedLocks::default();
+
+        let first = locks.serialize_on("wallet:1").await;
+        // other keys are not affected
+        let _other = locks.serialize_on("wallet:2").await;
+
+        let waiting = tokio::spawn({
+            let locks = locks.clone();
+            async move {
+                let _guard = locks.serialize_on("wallet:1").await;
+            }
+        });
+
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        waiting.await.unwrap();
+
+        let stats = locks.stats();
+        assert_eq!((stats.acquisitions, stats.contended), (3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_forgets_idle_keys() {
+        let locks = KeyedLocks::new(Duration::ZERO);
+
+        let held = locks.serialize_on("held").await;
+        for key in 1..SWEEP_THRESHOLD {
+            drop(locks.serialize_on(&key.to_string()).await);
+        }
+        assert_eq!(locks.stats().keys, SWEEP_THRESHOLD);
+
+        // the idle keys expired, "held" is still held
+        let _last = locks.serialize_on("last").await;
+        assert_eq!(locks.stats().keys, 2);
+        drop(held);
+    }
+}
@@ -0,0 +1,125 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::time::Instant;
+
+struct Entry<V> {
+    value: V,
+    expires: Instant,
+    used: u64,
+}
+
+/// Bounded map dropping the least recently used entry once full, entries also expire
+/// on their own deadline and are dropped lazily when looked up or evicted.
+///
+/// Lookups and inserts are `O(log n)`, recency is tracked in an ordered index instead
+/// of scanning the entries.
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, Entry<V>>,
+    recency: BTreeMap<u64, K>,
+    uses: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            uses: 0,
+        }
+    }
+
+    /// Live entry of `key`, marked as the most recently used
+    pub(crate) fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let expired = self.entries.get(key)?.expires <= Instant::now();
+        if expired {
+            self.remove(key);
+            return None;
+        }
+
+        self.uses += 1;
+        let uses = self.uses;
+        let entry = self.entries.get_mut(key)?;
+        let owned = self.recency.remove(&entry.used)?;
+        entry.used = uses;
+        self.recency.insert(uses, owned);
+        Some(&entry.value)
+    }
+
+    /// Store `value` until `expires`, evicting the least recently used entry when full
+    pub(crate) fn insert(&mut self, key: K, value: V, expires: Instant) {
+        self.remove(&key);
+
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        self.uses += 1;
+        self.recency.insert(self.uses, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                value,
+                expires,
+                used: self.uses,
+            },
+        );
+    }
+
+    pub(crate) fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.used);
+        Some(entry.value)
+    }
+
+    #[cfg(feature = "jwt")]
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_evicts_least_recently_used_and_expired() {
+        let later = Instant::now() + Duration::from_secs(60);
+        let mut cache = LruCache::new(2);
+
+        cache.insert("a", 1, later);
+        cache.insert("b", 2, later);
+        assert_eq!(cache.get("a"), Some(&1));
+
+        // "b" is the least recently used
+        cache.insert("c", 3, later);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(&1));
+        assert_eq!(cache.get("c"), Some(&3));
+
+        cache.insert("a", 4, Instant::now());
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.len(), 1);
+    }
+}
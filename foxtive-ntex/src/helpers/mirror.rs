@@ -0,0 +1,411 @@
+use chrono::{DateTime, Utc};
+use ntex::http::HeaderMap;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// What [`RequestMirror`] keeps and hides, see `ServerConfig::request_mirror`
+#[derive(Debug, Clone)]
+pub struct MirrorConfig {
+    /// number of exchanges kept, the oldest are dropped first
+    pub capacity: usize,
+    /// bytes of each body kept, the rest is cut
+    pub max_body: usize,
+    /// headers whose value is hidden, compared case-insensitively
+    pub redacted_headers: Vec<String>,
+    /// JSON keys and query parameters whose value is hidden, compared case-insensitively
+    pub redacted_fields: Vec<String>,
+}
+
+impl Default for MirrorConfig {
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl MirrorConfig {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            max_body: 4096,
+            redacted_headers: [
+                "authorization",
+                "proxy-authorization",
+                "cookie",
+                "set-cookie",
+            ]
+            .map(String::from)
+            .to_vec(),
+            redacted_fields: [
+                "password",
+                "token",
+                "access_token",
+                "refresh_token",
+                "secret",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+
+    pub fn max_body(mut self, bytes: usize) -> Self {
+        self.max_body = bytes;
+        self
+    }
+
+    pub fn redact_header(mut self, name: &str) -> Self {
+        self.redacted_headers.push(name.to_lowercase());
+        self
+    }
+
+    pub fn redact_field(mut self, name: &str) -> Self {
+        self.redacted_fields.push(name.to_lowercase());
+        self
+    }
+
+    fn hides_header(&self, name: &str) -> bool {
+        self.redacted_headers
+            .iter()
+            .any(|hidden| hidden.eq_ignore_ascii_case(name))
+    }
+
+    fn hides_field(&self, name: &str) -> bool {
+        self.redacted_fields
+            .iter()
+            .any(|hidden| hidden.eq_ignore_ascii_case(name))
+    }
+
+    pub(crate) fn headers(&self, headers: &HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = match self.hides_header(name.as_str()) {
+                    true => REDACTED.to_string(),
+                    false => String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                };
+                (name.to_string(), value)
+            })
+            .collect()
+    }
+
+    pub(crate) fn query(&self, query: &str) -> String {
+        query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if self.hides_field(name) => format!("{name}={REDACTED}"),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    /// Body cut to `max_body` with the redacted fields hidden, cut JSON bodies are redacted
+    /// as text and the other ones as urlencoded forms
+    pub(crate) fn body(&self, bytes: &[u8], total: usize) -> MirroredBody {
+        let truncated = total > bytes.len() || bytes.len() > self.max_body;
+        let kept = &bytes[..bytes.len().min(self.max_body)];
+
+        let content = match serde_json::from_slice::<Value>(kept) {
+            Ok(mut json) if !truncated => {
+                self.redact_json(&mut json);
+                json.to_string()
+            }
+            _ => {
+                let text = String::from_utf8_lossy(kept);
+                match text.trim_start().starts_with(['{', '[']) {
+                    true => self.redact_text(&text),
+                    false => self.query(&text),
+                }
+            }
+        };
+
+        MirroredBody {
+            content,
+            size: total,
+            truncated,
+        }
+    }
+
+    /// Hide the values of `"field": value` pairs of JSON that can't be parsed
+    fn redact_text(&self, text: &str) -> String {
+        let bytes = text.as_bytes();
+        let skip_spaces = |mut at: usize| {
+            while bytes.get(at).is_some_and(u8::is_ascii_whitespace) {
+                at += 1;
+            }
+            at
+        };
+
+        let mut redacted = String::with_capacity(text.len());
+        let (mut copied, mut at) = (0, 0);
+        while let Some(offset) = text[at..].find('"') {
+            let key_start = at + offset + 1;
+            let Some(key_len) = text[key_start..].find('"') else {
+                break;
+            };
+
+            at = key_start + key_len + 1;
+            let colon = skip_spaces(at);
+            if bytes.get(colon) != Some(&b':') || !self.hides_field(&text[key_start..at - 1]) {
+                continue;
+            }
+
+            let value_start = skip_spaces(colon + 1);
+            let mut value_end = value_start;
+            match bytes.get(value_start) {
+                Some(b'"') => {
+                    value_end += 1;
+                    while value_end < bytes.len() && bytes[value_end] != b'"' {
+                        value_end += if bytes[value_end] == b'\\' { 2 } else { 1 };
+                    }
+                    value_end += 1;
+                }
+                _ => {
+                    while value_end < bytes.len() && !b",}]".contains(&bytes[value_end]) {
+                        value_end += 1;
+                    }
+                }
+            }
+
+            value_end = value_end.min(bytes.len());
+            while !text.is_char_boundary(value_end) {
+                value_end += 1;
+            }
+            redacted.push_str(&text[copied..value_start]);
+            redacted.push_str(&format!("\"{REDACTED}\""));
+            copied = value_end;
+            at = value_end;
+        }
+
+        redacted.push_str(&text[copied..]);
+        redacted
+    }
+
+    fn redact_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match self.hides_field(key) {
+                        true => *value = Value::String(REDACTED.to_string()),
+                        false => self.redact_json(value),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_json(item)),
+            _ => {}
+        }
+    }
+}
+
+/// Body of a mirrored request or response
+#[derive(Debug, Clone, Serialize)]
+pub struct MirroredBody {
+    pub content: String,
+    /// bytes seen, including the ones cut
+    pub size: usize,
+    pub truncated: bool,
+}
+
+/// A request and its response, as kept by [`RequestMirror`]
+#[derive(Debug, Clone, Serialize)]
+pub struct MirroredExchange {
+    pub id: u64,
+    pub at: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub status: u16,
+    pub elapsed_ms: f64,
+    pub request_headers: Vec<(String, String)>,
+    /// `None` when the handler did not read the body
+    pub request_body: Option<MirroredBody>,
+    pub response_headers: Vec<(String, String)>,
+    /// `None` for streamed responses
+    pub response_body: Option<MirroredBody>,
+}
+
+struct Ring {
+    config: MirrorConfig,
+    exchanges: VecDeque<MirroredExchange>,
+}
+
+/// In-memory ring buffer of the last requests and responses, with truncated and redacted
+/// bodies, for live debugging of staging servers without full logging (feature `dev-tools`).
+///
+/// Filled once enabled with `ServerConfig::request_mirror`, never in production, and
+/// inspected through [`admin::mirror`](crate::http::admin::mirror).
+#[derive(Clone)]
+pub struct RequestMirror {
+    enabled: Arc<AtomicBool>,
+    sequence: Arc<AtomicU64>,
+    ring: Arc<Mutex<Ring>>,
+}
+
+impl Default for RequestMirror {
+    fn default() -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            sequence: Arc::new(AtomicU64::new(0)),
+            ring: Arc::new(Mutex::new(Ring {
+                config: MirrorConfig::default(),
+                exchanges: VecDeque::new(),
+            })),
+        }
+    }
+}
+
+impl RequestMirror {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Start mirroring with `config`, or stop and forget the kept exchanges with `None`
+    pub(crate) fn configure(&self, config: Option<MirrorConfig>) {
+        self.enabled.store(config.is_some(), Ordering::Relaxed);
+        if let Ok(mut ring) = self.ring.lock() {
+            ring.exchanges.clear();
+            if let Some(config) = config {
+                ring.config = config;
+            }
+        }
+    }
+
+    pub(crate) fn config(&self) -> MirrorConfig {
+        self.ring
+            .lock()
+            .map(|ring| ring.config.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn next_id(&self) -> u64 {
+        self.sequence.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub(crate) fn record(&self, exchange: MirroredExchange) {
+        if let Ok(mut ring) = self.ring.lock() {
+            while ring.exchanges.len() >= ring.config.capacity.max(1) {
+                ring.exchanges.pop_front();
+            }
+            ring.exchanges.push_back(exchange);
+        }
+    }
+
+    /// Kept exchanges, newest first, optionally only the paths starting with `path`
+    pub fn recent(&self, limit: usize, path: Option<&str>) -> Vec<MirroredExchange> {
+        let Ok(ring) = self.ring.lock() else {
+            return vec![];
+        };
+
+        ring.exchanges
+            .iter()
+            .rev()
+            .filter(|exchange| path.is_none_or(|path| exchange.path.starts_with(path)))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<MirroredExchange> {
+        self.ring.lock().ok().and_then(|ring| {
+            ring.exchanges
+                .iter()
+                .find(|exchange| exchange.id == id)
+                .cloned()
+        })
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut ring) = self.ring.lock() {
+            ring.exchanges.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::http::header::{AUTHORIZATION, CONTENT_TYPE, HeaderValue};
+
+    fn exchange(mirror: &RequestMirror, path: &str) -> MirroredExchange {
+        MirroredExchange {
+            id: mirror.next_id(),
+            at: Utc::now(),
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query: String::new(),
+            status: 200,
+            elapsed_ms: 1.0,
+            request_headers: vec![],
+            request_body: None,
+            response_headers: vec![],
+            response_body: None,
+        }
+    }
+
+    #[test]
+    fn test_ring_keeps_the_last_exchanges() {
+        let mirror = RequestMirror::default();
+        mirror.configure(Some(MirrorConfig::new(2)));
+        for path in ["/a", "/b", "/users/1"] {
+            mirror.record(exchange(&mirror, path));
+        }
+
+        let recent = mirror.recent(10, None);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].path, "/users/1");
+        assert_eq!(recent[1].path, "/b");
+        assert_eq!(mirror.recent(10, Some("/users")).len(), 1);
+        assert_eq!(mirror.get(2).unwrap().path, "/b");
+        assert!(mirror.get(1).is_none());
+
+        mirror.configure(None);
+        assert!(!mirror.is_enabled());
+        assert!(mirror.recent(10, None).is_empty());
+    }
+
+    #[test]
+    fn test_redaction() {
+        let config = MirrorConfig::new(10).max_body(64).redact_field("pin");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let headers = config.headers(&headers);
+        assert!(headers.contains(&("authorization".to_string(), REDACTED.to_string())));
+        assert!(headers.contains(&("content-type".to_string(), "application/json".to_string())));
+
+        assert_eq!(config.query("page=2&token=abc"), "page=2&token=[REDACTED]");
+
+        let body = br#"{"user":{"name":"ann","Password":"x"},"pins":[{"pin":"1234"}]}"#;
+        let mirrored = config.body(body, body.len());
+        assert!(!mirrored.truncated);
+        let json: Value = serde_json::from_str(&mirrored.content).unwrap();
+        assert_eq!(json["user"]["Password"], REDACTED);
+        assert_eq!(json["user"]["name"], "ann");
+        assert_eq!(json["pins"][0]["pin"], REDACTED);
+
+        let body = br#"{"password": "hunter2", "name": "ann", "bio": "a very long biography"}"#;
+        let mirrored = config.body(body, body.len());
+        assert!(mirrored.truncated);
+        assert_eq!(
+            mirrored.content,
+            r#"{"password": "[REDACTED]", "name": "ann", "bio": "a very long biogr"#
+        );
+
+        let body = b"name=ann&password=hunter2";
+        assert_eq!(
+            config.body(body, body.len()).content,
+            "name=ann&password=[REDACTED]"
+        );
+
+        let body = "x".repeat(100);
+        let mirrored = config.body(body.as_bytes(), 100);
+        assert!(mirrored.truncated);
+        assert_eq!(mirrored.content.len(), 64);
+        assert_eq!(mirrored.size, 100);
+    }
+}
@@ -1,6 +1,31 @@
+pub mod affinity;
+pub mod clock;
+pub mod components;
+pub mod deadline;
+pub mod distributed_lock;
+pub mod downstream;
 pub mod form;
 pub mod http;
 pub mod json_message;
+pub mod keyed_lock;
+pub(crate) mod lru;
+#[cfg(feature = "dev-tools")]
+pub mod mirror;
 pub(crate) mod once_lock;
+pub mod pool;
+pub mod probes;
+pub mod pubsub;
+pub mod query_filter;
+#[cfg(feature = "remember-me")]
+pub mod remember_me;
 pub mod request;
 pub mod responder;
+#[cfg(any(feature = "cursor", feature = "webhooks"))]
+pub(crate) mod secure_compare;
+pub mod single_flight;
+pub mod stats;
+#[cfg(feature = "metrics")]
+pub mod upload_metrics;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+pub mod worker_pool;
@@ -0,0 +1,189 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters describing how well a pool is reused
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PoolStats {
+    /// acquisitions served from the pool
+    pub hits: u64,
+    /// acquisitions that had to create a new object
+    pub misses: u64,
+    /// objects dropped instead of returned (pool full or object too big)
+    pub discarded: u64,
+}
+
+impl PoolStats {
+    /// Fraction of acquisitions served from the pool (0.0 - 1.0)
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// Simple thread-safe pool of reusable objects.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::pool::ObjectPool;
+///
+/// let pool = ObjectPool::new(16, String::new, |s: &mut String| s.clear());
+///
+/// let mut buffer = pool.acquire();
+/// buffer.push_str("hello");
+/// drop(buffer); // cleared and returned to the pool
+///
+/// assert_eq!(pool.acquire().len(), 0);
+/// assert_eq!(pool.stats().hits, 1);
+/// ```
+pub struct ObjectPool<T> {
+    items: Mutex<Vec<T>>,
+    max_items: usize,
+    create: fn() -> T,
+    reset: fn(&mut T),
+    retain: fn(&T) -> bool,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    discarded: AtomicU64,
+}
+
+impl<T> ObjectPool<T> {
+    /// Create a pool keeping at most `max_items` idle objects, `reset` is called
+    /// before an object is returned to the pool
+    pub fn new(max_items: usize, create: fn() -> T, reset: fn(&mut T)) -> Self {
+        Self {
+            items: Mutex::new(Vec::new()),
+            max_items,
+            create,
+            reset,
+            retain: |_| true,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            discarded: AtomicU64::new(0),
+        }
+    }
+
+    /// Only return objects for which `retain` returns true, e.g. to avoid keeping huge buffers
+    pub fn retain_if(mut self, retain: fn(&T) -> bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    pub fn acquire(&self) -> Pooled<'_, T> {
+        let item = self.items.lock().ok().and_then(|mut items| items.pop());
+
+        let item = match item {
+            Some(item) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                item
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                (self.create)()
+            }
+        };
+
+        Pooled {
+            pool: self,
+            item: Some(item),
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            discarded: self.discarded.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Number of idle objects currently in the pool
+    pub fn idle(&self) -> usize {
+        self.items.lock().map(|items| items.len()).unwrap_or(0)
+    }
+
+    fn release(&self, mut item: T) {
+        if (self.retain)(&item) {
+            (self.reset)(&mut item);
+
+            if let Ok(mut items) = self.items.lock()
+                && items.len() < self.max_items
+            {
+                items.push(item);
+                return;
+            }
+        }
+
+        self.discarded.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Object borrowed from an [`ObjectPool`], returned to it when dropped
+pub struct Pooled<'a, T> {
+    pool: &'a ObjectPool<T>,
+    item: Option<T>,
+}
+
+impl<T> Deref for Pooled<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.item.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for Pooled<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.item.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for Pooled<'_, T> {
+    fn drop(&mut self) {
+        if let Some(item) = self.item.take() {
+            self.pool.release(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reuses_released_objects() {
+        let pool = ObjectPool::new(2, Vec::<u8>::new, Vec::clear);
+
+        let mut buffer = pool.acquire();
+        buffer.extend_from_slice(b"hello");
+        drop(buffer);
+
+        let buffer = pool.acquire();
+        assert!(buffer.is_empty());
+        assert!(buffer.capacity() >= 5);
+
+        let stats = pool.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.hit_rate(), 0.5);
+    }
+
+    #[test]
+    fn test_discards_when_full_or_not_retained() {
+        let pool = ObjectPool::new(1, String::new, String::clear).retain_if(|s| s.capacity() < 8);
+
+        let first = pool.acquire();
+        let second = pool.acquire();
+        drop(first);
+        drop(second);
+        assert_eq!(pool.idle(), 1);
+
+        let mut big = pool.acquire();
+        big.push_str("way too long to keep");
+        drop(big);
+
+        assert_eq!(pool.idle(), 0);
+        assert_eq!(pool.stats().discarded, 2);
+    }
+}
@@ -0,0 +1,351 @@
+use foxtive::prelude::AppResult;
+use futures_util::future::join_all;
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+type CheckFn = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = AppResult<()>> + Send>> + Send + Sync>;
+
+/// Active check of an upstream dependency (database, broker, other API), registered with
+/// `ComponentRegistry::probe` and reported by the health endpoints.
+///
+/// Results are cached for `cache_for` so readiness calls don't ping the dependency each
+/// time, and a check already in flight is never started twice. With `refresh_every`,
+/// a background task keeps the cache warm, each run delayed by a random jitter so
+/// instances don't check in lockstep.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::probes::DependencyProbe;
+/// use std::time::Duration;
+///
+/// let probe = DependencyProbe::new("postgres", || async {
+///     // SELECT 1
+///     Ok(())
+/// })
+/// .critical()
+/// .cache_for(Duration::from_secs(10))
+/// .refresh_every(Duration::from_secs(15));
+/// ```
+#[derive(Clone)]
+pub struct DependencyProbe {
+    name: String,
+    critical: bool,
+    cache_for: Duration,
+    timeout: Duration,
+    refresh_every: Option<Duration>,
+    jitter: Option<Duration>,
+    check: CheckFn,
+}
+
+impl DependencyProbe {
+    pub fn new<F, Fut>(name: &str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = AppResult<()>> + Send + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            critical: false,
+            cache_for: Duration::from_secs(5),
+            timeout: Duration::from_secs(2),
+            refresh_every: None,
+            jitter: None,
+            check: Arc::new(move || Box::pin(check())),
+        }
+    }
+
+    /// The application is not ready while this dependency is down, otherwise it is only
+    /// informational
+    pub fn critical(mut self) -> Self {
+        self.critical = true;
+        self
+    }
+
+    /// How long a result is reused, 5 seconds by default
+    pub fn cache_for(mut self, duration: Duration) -> Self {
+        self.cache_for = duration;
+        self
+    }
+
+    /// Time after which the check counts as failed, 2 seconds by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Check in the background at this interval instead of on demand
+    pub fn refresh_every(mut self, interval: Duration) -> Self {
+        self.refresh_every = Some(interval);
+        self
+    }
+
+    /// Maximum random delay added to each background refresh, 10% of the interval by default
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+}
+
+/// Last result of a [`DependencyProbe`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DependencyStatus {
+    pub name: String,
+    pub critical: bool,
+    pub up: bool,
+    pub detail: Option<String>,
+    pub latency_ms: Option<u64>,
+    /// seconds since the last check, `None` when never checked
+    pub checked_secs: Option<u64>,
+}
+
+struct ProbeResult {
+    up: bool,
+    detail: Option<String>,
+    latency: Duration,
+    checked_at: Instant,
+}
+
+struct ProbeState {
+    probe: DependencyProbe,
+    last: Mutex<Option<ProbeResult>>,
+    running: AtomicBool,
+}
+
+impl ProbeState {
+    fn is_fresh(&self) -> bool {
+        self.last.lock().ok().is_some_and(|last| {
+            last.as_ref()
+                .is_some_and(|result| result.checked_at.elapsed() < self.probe.cache_for)
+        })
+    }
+
+    async fn refresh(&self) {
+        // concurrent callers keep the cached result instead of checking again
+        if self.running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let started = Instant::now();
+        let (up, detail) = match ntex::time::timeout(self.probe.timeout, (self.probe.check)()).await
+        {
+            Ok(Ok(_)) => (true, None),
+            Ok(Err(err)) => (false, Some(err.to_string())),
+            Err(_) => (
+                false,
+                Some(format!("timed out after {:?}", self.probe.timeout)),
+            ),
+        };
+
+        if let Ok(mut last) = self.last.lock() {
+            let was_up = last.as_ref().map(|result| result.up);
+            if was_up != Some(up) {
+                info!(
+                    "[probes] '{}' is {} ({})",
+                    self.probe.name,
+                    if up { "up" } else { "down" },
+                    detail.as_deref().unwrap_or("-")
+                );
+            }
+
+            *last = Some(ProbeResult {
+                up,
+                detail,
+                latency: started.elapsed(),
+                checked_at: Instant::now(),
+            });
+        }
+
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    fn status(&self) -> DependencyStatus {
+        let last = self.last.lock().ok();
+        let last = last.as_ref().and_then(|last| last.as_ref());
+
+        DependencyStatus {
+            name: self.probe.name.clone(),
+            critical: self.probe.critical,
+            up: last.is_some_and(|result| result.up),
+            detail: match last {
+                None => Some("not checked yet".to_string()),
+                Some(result) => result.detail.clone(),
+            },
+            latency_ms: last.map(|result| result.latency.as_millis() as u64),
+            checked_secs: last.map(|result| result.checked_at.elapsed().as_secs()),
+        }
+    }
+
+    /// Next background refresh delay, `interval` plus a random share of the jitter
+    fn next_delay(&self, interval: Duration) -> Duration {
+        let jitter = self.probe.jitter.unwrap_or(interval / 10);
+        let random = RandomState::new().build_hasher().finish();
+        let nanos = jitter.as_nanos() as u64;
+
+        match nanos {
+            0 => interval,
+            _ => interval + Duration::from_nanos(random % nanos),
+        }
+    }
+}
+
+/// Probes of a `ComponentRegistry`
+#[derive(Clone, Default)]
+pub(crate) struct Probes {
+    probes: Arc<Mutex<Vec<Arc<ProbeState>>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Probes {
+    pub(crate) fn add(&self, probe: DependencyProbe) {
+        info!("[probes] registered '{}'", probe.name);
+        if let Ok(mut probes) = self.probes.lock() {
+            probes.push(Arc::new(ProbeState {
+                probe,
+                last: Mutex::new(None),
+                running: AtomicBool::new(false),
+            }));
+        }
+    }
+
+    fn all(&self) -> Vec<Arc<ProbeState>> {
+        self.probes
+            .lock()
+            .map(|probes| probes.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn statuses(&self) -> Vec<DependencyStatus> {
+        self.all().iter().map(|state| state.status()).collect()
+    }
+
+    /// Check the probes whose cached result expired
+    pub(crate) async fn refresh_expired(&self) {
+        let expired: Vec<_> = self
+            .all()
+            .into_iter()
+            .filter(|state| !state.is_fresh())
+            .collect();
+
+        join_all(expired.iter().map(|state| state.refresh())).await;
+    }
+
+    /// Spawn the background refresh of the probes having an interval, returns whether
+    /// there was any
+    pub(crate) fn start(&self) -> bool {
+        let mut started = false;
+        for state in self.all() {
+            let Some(interval) = state.probe.refresh_every else {
+                continue;
+            };
+
+            let stopped = self.stopped.clone();
+            ntex::rt::spawn(async move {
+                while !stopped.load(Ordering::SeqCst) {
+                    state.refresh().await;
+                    ntex::time::sleep(state.next_delay(interval)).await;
+                }
+                debug!("[probes] '{}' refresh stopped", state.probe.name);
+            });
+            started = true;
+        }
+
+        started
+    }
+
+    pub(crate) fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxtive::prelude::AppMessage;
+    use std::sync::atomic::AtomicUsize;
+
+    fn counting(calls: &Arc<AtomicUsize>, up: bool) -> DependencyProbe {
+        let calls = calls.clone();
+        DependencyProbe::new("postgres", move || {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                ntex::time::sleep(Duration::from_millis(5)).await;
+                match up {
+                    true => Ok(()),
+                    false => AppMessage::WarningMessage("connection refused").ar(),
+                }
+            }
+        })
+    }
+
+    #[ntex::test]
+    async fn test_results_are_cached() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let probes = Probes::default();
+        probes.add(counting(&calls, true).critical());
+
+        assert_eq!(
+            probes.statuses()[0].detail.as_deref(),
+            Some("not checked yet")
+        );
+
+        probes.refresh_expired().await;
+        probes.refresh_expired().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let status = &probes.statuses()[0];
+        assert!(status.up && status.critical);
+        assert_eq!(status.checked_secs, Some(0));
+    }
+
+    #[ntex::test]
+    async fn test_concurrent_checks_are_not_duplicated() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let probes = Probes::default();
+        probes.add(counting(&calls, false).cache_for(Duration::ZERO));
+
+        futures_util::future::join(probes.refresh_expired(), probes.refresh_expired()).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let status = &probes.statuses()[0];
+        assert!(!status.up);
+        assert_eq!(status.detail.as_deref(), Some("connection refused"));
+    }
+
+    #[ntex::test]
+    async fn test_timeouts_count_as_down() {
+        let probes = Probes::default();
+        probes.add(
+            DependencyProbe::new("broker", || async {
+                ntex::time::sleep(Duration::from_secs(5)).await;
+                Ok(())
+            })
+            .timeout(Duration::from_millis(10)),
+        );
+
+        probes.refresh_expired().await;
+        assert!(!probes.statuses()[0].up);
+    }
+
+    #[test]
+    fn test_jittered_delay_stays_in_range() {
+        let state = ProbeState {
+            probe: DependencyProbe::new("cache", || async { Ok(()) })
+                .jitter(Duration::from_secs(2)),
+            last: Mutex::new(None),
+            running: AtomicBool::new(false),
+        };
+
+        for _ in 0..20 {
+            let delay = state.next_delay(Duration::from_secs(10));
+            assert!(delay >= Duration::from_secs(10) && delay < Duration::from_secs(12));
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use crate::helpers::responder::Responder;
+use futures_util::stream;
+use ntex::http::Response;
+use ntex::util::Bytes;
+use serde::Serialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+use tracing::{debug, warn};
+
+/// Message received from the messaging layer, forwarded to matching connections
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BridgeMessage {
+    pub topic: String,
+    pub payload: Value,
+    /// deliver only to connections of this user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// deliver only to connections of this tenant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+impl BridgeMessage {
+    pub fn new(topic: &str, payload: Value) -> Self {
+        Self {
+            topic: topic.to_string(),
+            payload,
+            user_id: None,
+            tenant_id: None,
+        }
+    }
+
+    pub fn for_user(mut self, user_id: &str) -> Self {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    pub fn for_tenant(mut self, tenant_id: &str) -> Self {
+        self.tenant_id = Some(tenant_id.to_string());
+        self
+    }
+}
+
+/// What a connection wants to receive. Topics match exactly, or by prefix when
+/// ending with `*` (e.g. `orders.*`).
+#[derive(Debug, Clone, Default)]
+pub struct BridgeFilter {
+    pub topics: Vec<String>,
+    pub user_id: Option<String>,
+    pub tenant_id: Option<String>,
+}
+
+impl BridgeFilter {
+    pub fn topic(mut self, topic: &str) -> Self {
+        self.topics.push(topic.to_string());
+        self
+    }
+
+    pub fn user(mut self, user_id: &str) -> Self {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    pub fn tenant(mut self, tenant_id: &str) -> Self {
+        self.tenant_id = Some(tenant_id.to_string());
+        self
+    }
+
+    fn matches(&self, message: &BridgeMessage) -> bool {
+        let topic_matches = self
+            .topics
+            .iter()
+            .any(|topic| match topic.strip_suffix('*') {
+                Some(prefix) => message.topic.starts_with(prefix),
+                None => *topic == message.topic,
+            });
+
+        // targeted messages only reach connections of the same user/tenant
+        let targets = |target: &Option<String>, own: &Option<String>| match target {
+            None => true,
+            Some(target) => own.as_ref() == Some(target),
+        };
+
+        topic_matches
+            && targets(&message.user_id, &self.user_id)
+            && targets(&message.tenant_id, &self.tenant_id)
+    }
+}
+
+/// Delivery counters of the bridge
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BridgeStats {
+    /// messages handed to the bridge
+    pub published: u64,
+    /// copies queued to connections
+    pub delivered: u64,
+    /// copies dropped because the connection's queue was full
+    pub dropped: u64,
+    /// currently connected subscribers
+    pub subscribers: usize,
+}
+
+struct Subscriber {
+    filter: BridgeFilter,
+    sender: Sender<Arc<BridgeMessage>>,
+}
+
+#[derive(Default)]
+struct Inner {
+    subscribers: Mutex<Vec<Subscriber>>,
+    published: AtomicU64,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Fan-out of queue messages to connected WebSocket/SSE clients.
+///
+/// The queue consumer (RabbitMQ, Redis...) calls [`PubSubBridge::publish`], each connection
+/// holds a [`BridgeReceiver`] with a bounded queue; slow clients lose messages instead of
+/// slowing everyone down.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::pubsub::{BridgeFilter, BridgeMessage, PubSubBridge};
+/// use serde_json::json;
+///
+/// let bridge = PubSubBridge::default();
+/// let mut receiver = bridge.subscribe(BridgeFilter::default().topic("orders.*").user("42"), 16);
+///
+/// bridge.publish(BridgeMessage::new("orders.shipped", json!({"id": 1})).for_user("42"));
+/// assert_eq!(receiver.try_recv().unwrap().topic, "orders.shipped");
+/// ```
+#[derive(Clone, Default)]
+pub struct PubSubBridge {
+    inner: Arc<Inner>,
+}
+
+impl PubSubBridge {
+    /// Register a connection, `capacity` is the number of messages buffered for it
+    pub fn subscribe(&self, filter: BridgeFilter, capacity: usize) -> BridgeReceiver {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        if let Ok(mut subscribers) = self.inner.subscribers.lock() {
+            subscribers.push(Subscriber { filter, sender });
+        }
+
+        BridgeReceiver { receiver }
+    }
+
+    /// Forward the message to every matching connection, returns the number of deliveries
+    pub fn publish(&self, message: BridgeMessage) -> usize {
+        self.inner.published.fetch_add(1, Ordering::Relaxed);
+
+        let message = Arc::new(message);
+        let mut delivered = 0;
+        let mut subscribers = match self.inner.subscribers.lock() {
+            Ok(subscribers) => subscribers,
+            Err(_) => return 0,
+        };
+
+        subscribers.retain(|subscriber| {
+            if !subscriber.filter.matches(&message) {
+                return !subscriber.sender.is_closed();
+            }
+
+            match subscriber.sender.try_send(message.clone()) {
+                Ok(_) => {
+                    delivered += 1;
+                    true
+                }
+                Err(TrySendError::Full(_)) => {
+                    warn!(
+                        "[pubsub] subscriber queue full, dropping '{}'",
+                        message.topic
+                    );
+                    self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                    true
+                }
+                Err(TrySendError::Closed(_)) => {
+                    debug!("[pubsub] removing disconnected subscriber");
+                    false
+                }
+            }
+        });
+
+        self.inner
+            .delivered
+            .fetch_add(delivered as u64, Ordering::Relaxed);
+        delivered
+    }
+
+    pub fn stats(&self) -> BridgeStats {
+        BridgeStats {
+            published: self.inner.published.load(Ordering::Relaxed),
+            delivered: self.inner.delivered.load(Ordering::Relaxed),
+            dropped: self.inner.dropped.load(Ordering::Relaxed),
+            subscribers: self.inner.subscribers.lock().map(|s| s.len()).unwrap_or(0),
+        }
+    }
+}
+
+/// Messages delivered to one connection, dropping it unsubscribes
+pub struct BridgeReceiver {
+    receiver: Receiver<Arc<BridgeMessage>>,
+}
+
+impl BridgeReceiver {
+    pub async fn recv(&mut self) -> Option<Arc<BridgeMessage>> {
+        self.receiver.recv().await
+    }
+
+    pub fn try_recv(&mut self) -> Option<Arc<BridgeMessage>> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Stream the messages as server-sent events, using the topic as event name
+    pub fn into_sse_response(self) -> Response {
+        let events = stream::unfold(self, |mut receiver| async move {
+            let message = receiver.recv().await?;
+            let data = serde_json::to_string(&message.payload).unwrap_or_default();
+            let event = format!("event: {}\ndata: {data}\n\n", message.topic);
+            Some((Ok::<_, Infallible>(Bytes::from(event)), receiver))
+        });
+
+        let mut resp = Responder::send_stream(Box::pin(events), "text/event-stream");
+        resp.headers_mut().insert(
+            ntex::http::header::CACHE_CONTROL,
+            ntex::http::header::HeaderValue::from_static("no-cache"),
+        );
+        resp
+    }
+
+    /// Send the messages to a WebSocket connection as JSON text frames until either side closes
+    #[cfg(feature = "ws")]
+    pub async fn forward_to(mut self, session: crate::http::ws::WsSession) {
+        use ntex::web::ws::Message;
+
+        while let Some(message) = self.recv().await {
+            let text = match serde_json::to_string(&*message) {
+                Ok(text) => text,
+                Err(_) => continue,
+            };
+
+            if session.send(Message::Text(text.into())).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_filters_by_topic_and_target() {
+        let bridge = PubSubBridge::default();
+        let mut alice = bridge.subscribe(BridgeFilter::default().topic("chat.*").user("alice"), 4);
+        let mut bob = bridge.subscribe(BridgeFilter::default().topic("chat.room").user("bob"), 4);
+
+        assert_eq!(bridge.publish(BridgeMessage::new("chat.room", json!(1))), 2);
+        assert_eq!(
+            bridge.publish(BridgeMessage::new("chat.dm", json!(2)).for_user("alice")),
+            1
+        );
+        assert_eq!(bridge.publish(BridgeMessage::new("orders", json!(3))), 0);
+
+        assert_eq!(alice.try_recv().unwrap().payload, json!(1));
+        assert_eq!(alice.try_recv().unwrap().payload, json!(2));
+        assert_eq!(bob.try_recv().unwrap().payload, json!(1));
+        assert!(bob.try_recv().is_none());
+    }
+
+    #[test]
+    fn test_drops_for_slow_and_forgets_closed_subscribers() {
+        let bridge = PubSubBridge::default();
+        let _slow = bridge.subscribe(BridgeFilter::default().topic("*"), 1);
+        let gone = bridge.subscribe(BridgeFilter::default().topic("*"), 1);
+        drop(gone);
+
+        bridge.publish(BridgeMessage::new("a", json!(null)));
+        bridge.publish(BridgeMessage::new("b", json!(null)));
+
+        let stats = bridge.stats();
+        assert_eq!((stats.published, stats.delivered, stats.dropped), (2, 1, 1));
+        assert_eq!(stats.subscribers, 1);
+    }
+}
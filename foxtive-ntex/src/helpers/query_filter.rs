@@ -0,0 +1,462 @@
+use foxtive::prelude::{AppMessage, AppResult};
+use ntex::web::types::Query;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+pub type TheFilterParams = Query<FilterParams>;
+
+/// Raw `filter` and `sort` query parameters, parsed with [`FilterRules::parse`].
+///
+/// Example: `?filter=status:eq:active,amount:gte:100&sort=-created_at,name`
+#[derive(Deserialize, Clone, Default, Debug)]
+pub struct FilterParams {
+    /// Comma-separated `field:operator:value` expressions, all of which must match.
+    /// `in`/`nin` take `|`-separated values, `\` escapes `,`, `|` and itself.
+    pub filter: Option<String>,
+
+    /// Comma-separated fields, `-` prefixed for descending order
+    pub sort: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// substring match, left to the data layer (e.g. `ILIKE '%value%'`)
+    Like,
+    In,
+    NotIn,
+    /// `field:null:true` / `field:null:false`
+    IsNull,
+}
+
+impl FilterOp {
+    pub const ALL: [FilterOp; 10] = [
+        FilterOp::Eq,
+        FilterOp::Ne,
+        FilterOp::Gt,
+        FilterOp::Gte,
+        FilterOp::Lt,
+        FilterOp::Lte,
+        FilterOp::Like,
+        FilterOp::In,
+        FilterOp::NotIn,
+        FilterOp::IsNull,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterOp::Eq => "eq",
+            FilterOp::Ne => "ne",
+            FilterOp::Gt => "gt",
+            FilterOp::Gte => "gte",
+            FilterOp::Lt => "lt",
+            FilterOp::Lte => "lte",
+            FilterOp::Like => "like",
+            FilterOp::In => "in",
+            FilterOp::NotIn => "nin",
+            FilterOp::IsNull => "null",
+        }
+    }
+}
+
+impl Display for FilterOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FilterOp {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FilterOp::ALL
+            .into_iter()
+            .find(|op| op.as_str() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+    Single(String),
+    /// values of `in` and `nin`
+    List(Vec<String>),
+    /// value of `null`
+    Bool(bool),
+}
+
+/// One `field:operator:value` expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub field: String,
+    pub op: FilterOp,
+    pub value: FilterValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sort {
+    pub field: String,
+    pub direction: SortDirection,
+}
+
+/// Parsed and validated filter and sort expressions, in query-string order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterQuery {
+    pub filters: Vec<Filter>,
+    pub sort: Vec<Sort>,
+}
+
+impl FilterQuery {
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty() && self.sort.is_empty()
+    }
+
+    /// Filters applied to `field`
+    pub fn filters_on<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a Filter> + 'a {
+        self.filters
+            .iter()
+            .filter(move |filter| filter.field == field)
+    }
+}
+
+/// Fields a route lets clients filter and sort on. Anything else is rejected with a 400,
+/// so field names can be mapped to columns without further checks.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::query_filter::{FilterOp, FilterParams, FilterRules};
+///
+/// let rules = FilterRules::new()
+///     .filterable("status", &[FilterOp::Eq, FilterOp::In])
+///     .filterable("amount", &[FilterOp::Gte, FilterOp::Lte])
+///     .sortable("created_at")
+///     .default_sort("-created_at");
+///
+/// let params = FilterParams {
+///     filter: Some("status:in:active|pending,amount:gte:100".into()),
+///     sort: None,
+/// };
+///
+/// let query = rules.parse(&params).unwrap();
+/// assert_eq!(query.filters.len(), 2);
+/// assert_eq!(query.sort[0].field, "created_at");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FilterRules {
+    filterable: HashMap<String, Vec<FilterOp>>,
+    sortable: Vec<String>,
+    default_sort: Option<String>,
+    max_filters: usize,
+}
+
+impl Default for FilterRules {
+    fn default() -> Self {
+        Self {
+            filterable: HashMap::new(),
+            sortable: vec![],
+            default_sort: None,
+            max_filters: 20,
+        }
+    }
+}
+
+impl FilterRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow filtering `field` with the given operators
+    pub fn filterable(mut self, field: &str, ops: &[FilterOp]) -> Self {
+        self.filterable.insert(field.to_string(), ops.to_vec());
+        self
+    }
+
+    pub fn sortable(mut self, field: &str) -> Self {
+        self.sortable.push(field.to_string());
+        self
+    }
+
+    /// Sort expression used when the client sends none, e.g. `-created_at`
+    pub fn default_sort(mut self, sort: &str) -> Self {
+        self.default_sort = Some(sort.to_string());
+        self
+    }
+
+    /// Maximum number of filter expressions, 20 by default
+    pub fn max_filters(mut self, max: usize) -> Self {
+        self.max_filters = max;
+        self
+    }
+
+    pub fn parse(&self, params: &FilterParams) -> AppResult<FilterQuery> {
+        let filters = match params.filter.as_deref().map(str::trim) {
+            Some(filter) if !filter.is_empty() => self.parse_filters(filter)?,
+            _ => vec![],
+        };
+
+        let sort = match params.sort.as_deref().map(str::trim) {
+            Some(sort) if !sort.is_empty() => self.parse_sort(sort)?,
+            _ => match &self.default_sort {
+                Some(sort) => self.parse_sort(sort)?,
+                None => vec![],
+            },
+        };
+
+        Ok(FilterQuery { filters, sort })
+    }
+
+    fn parse_filters(&self, raw: &str) -> AppResult<Vec<Filter>> {
+        let expressions = split_escaped(raw, ',');
+        if expressions.len() > self.max_filters {
+            return Err(invalid(format!(
+                "too many filters, at most {} are allowed",
+                self.max_filters
+            )));
+        }
+
+        expressions
+            .iter()
+            .map(|expression| self.parse_filter(expression))
+            .collect()
+    }
+
+    fn parse_filter(&self, expression: &str) -> AppResult<Filter> {
+        let mut parts = expression.splitn(3, ':');
+        let (Some(field), Some(op), Some(value)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid(format!(
+                "'{}' is not a field:operator:value expression",
+                unescape(expression)
+            )));
+        };
+
+        let field = field.trim();
+        let Some(allowed) = self.filterable.get(field) else {
+            return Err(invalid(format!("filtering on '{field}' is not allowed")));
+        };
+
+        let op = op
+            .trim()
+            .parse::<FilterOp>()
+            .map_err(|_| invalid(format!("unknown filter operator '{}'", op.trim())))?;
+
+        if !allowed.contains(&op) {
+            return Err(invalid(format!(
+                "operator '{op}' is not allowed on '{field}'"
+            )));
+        }
+
+        let value = match op {
+            FilterOp::In | FilterOp::NotIn => {
+                let values: Vec<String> = split_escaped(value, '|')
+                    .iter()
+                    .map(|value| unescape(value))
+                    .collect();
+
+                if values.iter().any(String::is_empty) {
+                    return Err(invalid(format!("empty value in '{field}:{op}' filter")));
+                }
+                FilterValue::List(values)
+            }
+            FilterOp::IsNull => match value {
+                "true" => FilterValue::Bool(true),
+                "false" => FilterValue::Bool(false),
+                _ => {
+                    return Err(invalid(format!("'{field}:null' expects true or false")));
+                }
+            },
+            _ => {
+                let value = unescape(value);
+                if value.is_empty() {
+                    return Err(invalid(format!("empty value in '{field}:{op}' filter")));
+                }
+                FilterValue::Single(value)
+            }
+        };
+
+        Ok(Filter {
+            field: field.to_string(),
+            op,
+            value,
+        })
+    }
+
+    fn parse_sort(&self, raw: &str) -> AppResult<Vec<Sort>> {
+        raw.split(',')
+            .map(str::trim)
+            .map(|field| {
+                let (field, direction) = match field.strip_prefix('-') {
+                    Some(field) => (field, SortDirection::Desc),
+                    None => (field.trim_start_matches('+'), SortDirection::Asc),
+                };
+
+                if !self.sortable.iter().any(|sortable| sortable == field) {
+                    return Err(invalid(format!("sorting on '{field}' is not allowed")));
+                }
+
+                Ok(Sort {
+                    field: field.to_string(),
+                    direction,
+                })
+            })
+            .collect()
+    }
+}
+
+fn invalid(reason: String) -> foxtive::Error {
+    AppMessage::WarningMessageString(format!("Invalid filter: {reason}")).ae()
+}
+
+/// Split on `separator` unless escaped with `\`, escapes are kept for [`unescape`]
+fn split_escaped(raw: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (index, char) in raw.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if char == separator => {
+                parts.push(&raw[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&raw[start..]);
+    parts
+}
+
+fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => value.extend(chars.next()),
+            _ => value.push(char),
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> FilterRules {
+        FilterRules::new()
+            .filterable("status", &[FilterOp::Eq, FilterOp::In])
+            .filterable("amount", &[FilterOp::Gte, FilterOp::Lt])
+            .filterable("name", &[FilterOp::Like, FilterOp::IsNull])
+            .sortable("created_at")
+            .sortable("name")
+    }
+
+    fn parse(filter: &str, sort: &str) -> AppResult<FilterQuery> {
+        rules().parse(&FilterParams {
+            filter: Some(filter.to_string()),
+            sort: Some(sort.to_string()),
+        })
+    }
+
+    #[test]
+    fn test_parses_filters_and_sort() {
+        let query = parse(
+            r"status:in:active|on\|hold,amount:gte:100,name:like:a\,b:c,name:null:false",
+            "-created_at,name",
+        )
+        .unwrap();
+
+        assert_eq!(
+            query.filters,
+            vec![
+                Filter {
+                    field: "status".into(),
+                    op: FilterOp::In,
+                    value: FilterValue::List(vec!["active".into(), "on|hold".into()]),
+                },
+                Filter {
+                    field: "amount".into(),
+                    op: FilterOp::Gte,
+                    value: FilterValue::Single("100".into()),
+                },
+                Filter {
+                    field: "name".into(),
+                    op: FilterOp::Like,
+                    value: FilterValue::Single("a,b:c".into()),
+                },
+                Filter {
+                    field: "name".into(),
+                    op: FilterOp::IsNull,
+                    value: FilterValue::Bool(false),
+                },
+            ]
+        );
+        assert_eq!(query.filters_on("name").count(), 2);
+        assert_eq!(
+            query.sort,
+            vec![
+                Sort {
+                    field: "created_at".into(),
+                    direction: SortDirection::Desc
+                },
+                Sort {
+                    field: "name".into(),
+                    direction: SortDirection::Asc
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_expressions() {
+        for (filter, sort) in [
+            ("password:eq:x", ""),
+            ("status:gt:1", ""),
+            ("status:between:1", ""),
+            ("status", ""),
+            ("amount:gte:", ""),
+            ("name:null:maybe", ""),
+            ("", "password"),
+        ] {
+            let err = parse(filter, sort).unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<AppMessage>(),
+                    Some(AppMessage::WarningMessageString(message)) if message.starts_with("Invalid filter")
+                ),
+                "{filter} / {sort}"
+            );
+        }
+
+        let rules = rules().max_filters(1);
+        let params = FilterParams {
+            filter: Some("status:eq:a,status:eq:b".into()),
+            sort: None,
+        };
+        assert!(rules.parse(&params).is_err());
+    }
+
+    #[test]
+    fn test_default_sort() {
+        let rules = rules().default_sort("-created_at");
+        let query = rules.parse(&FilterParams::default()).unwrap();
+        assert!(query.filters.is_empty());
+        assert_eq!(query.sort[0].direction, SortDirection::Desc);
+    }
+}
@@ -0,0 +1,771 @@
+use crate::http::HttpError;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use foxtive::helpers::hmac::{HashFunc, Hmac};
+use foxtive::prelude::{AppMessage, AppResult};
+use ntex::http::{Payload, header};
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, FromRequest, HttpRequest, HttpResponse, WebRequest, WebResponse};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+pub type RememberMeFuture<T> = Pin<Box<dyn Future<Output = AppResult<T>>>>;
+
+/// Remember-me series as kept by a [`RememberMeStore`], tokens are stored hashed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RememberedSeries {
+    pub series: String,
+    pub user_id: String,
+    /// hash of the token the cookie currently carries
+    pub token: String,
+    /// hash of the token replaced by the last rotation, with when it was replaced
+    pub previous: Option<(String, u64)>,
+}
+
+/// Storage of the remember-me series, one per remembered device.
+///
+/// The default store lives in memory, so series are lost on restart and not shared by the
+/// instances; implement it over the database or redis in production.
+pub trait RememberMeStore: Send + Sync + 'static {
+    /// Series `series`, `None` once revoked or expired
+    fn find(&self, series: &str) -> RememberMeFuture<Option<RememberedSeries>>;
+
+    /// Insert or replace the series, expiring after `ttl`
+    fn save(&self, series: RememberedSeries, ttl: Duration) -> RememberMeFuture<()>;
+
+    /// Replace the series only while its current token hash is still `expected`, returns
+    /// whether it was replaced. Must be atomic, e.g. a conditional `UPDATE` or a redis
+    /// script, so that one of two requests rotating the same token wins.
+    fn rotate(
+        &self,
+        series: RememberedSeries,
+        expected: &str,
+        ttl: Duration,
+    ) -> RememberMeFuture<bool>;
+
+    fn revoke(&self, series: &str) -> RememberMeFuture<()>;
+
+    /// Revoke every series of `user_id` (password change, logout everywhere)
+    fn revoke_user(&self, user_id: &str) -> RememberMeFuture<()>;
+}
+
+/// In-memory [`RememberMeStore`], an expired series is dropped when looked up, and the
+/// ones never looked up again once the store has doubled in size since the last sweep
+#[derive(Default)]
+pub struct MemoryRememberMeStore {
+    series: Mutex<MemorySeries>,
+}
+
+#[derive(Default)]
+struct MemorySeries {
+    entries: HashMap<String, (RememberedSeries, Instant)>,
+    /// number of series left by the last sweep
+    swept: usize,
+}
+
+impl MemorySeries {
+    fn insert(&mut self, series: RememberedSeries, ttl: Duration) {
+        let now = Instant::now();
+        if self.entries.len() >= (self.swept * 2).max(1024) {
+            self.entries.retain(|_, (_, expires)| *expires > now);
+            self.swept = self.entries.len();
+        }
+
+        self.entries
+            .insert(series.series.clone(), (series, now + ttl));
+    }
+}
+
+impl RememberMeStore for MemoryRememberMeStore {
+    fn find(&self, series: &str) -> RememberMeFuture<Option<RememberedSeries>> {
+        let found = self.series.lock().ok().and_then(|mut stored| {
+            let (found, expires) = stored.entries.get(series)?;
+            if *expires > Instant::now() {
+                return Some(found.clone());
+            }
+
+            stored.entries.remove(series);
+            None
+        });
+
+        Box::pin(async move { Ok(found) })
+    }
+
+    fn save(&self, series: RememberedSeries, ttl: Duration) -> RememberMeFuture<()> {
+        if let Ok(mut stored) = self.series.lock() {
+            stored.insert(series, ttl);
+        }
+
+        Box::pin(async { Ok(()) })
+    }
+
+    fn rotate(
+        &self,
+        series: RememberedSeries,
+        expected: &str,
+        ttl: Duration,
+    ) -> RememberMeFuture<bool> {
+        let rotated = self.series.lock().is_ok_and(|mut stored| {
+            let current = stored
+                .entries
+                .get(&series.series)
+                .filter(|(_, expires)| *expires > Instant::now());
+            match current {
+                Some((current, _)) if current.token == expected => {
+                    stored.insert(series, ttl);
+                    true
+                }
+                _ => false,
+            }
+        });
+
+        Box::pin(async move { Ok(rotated) })
+    }
+
+    fn revoke(&self, series: &str) -> RememberMeFuture<()> {
+        if let Ok(mut stored) = self.series.lock() {
+            stored.entries.remove(series);
+        }
+
+        Box::pin(async { Ok(()) })
+    }
+
+    fn revoke_user(&self, user_id: &str) -> RememberMeFuture<()> {
+        if let Ok(mut stored) = self.series.lock() {
+            stored
+                .entries
+                .retain(|_, (series, _)| series.user_id != user_id);
+        }
+
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Content of the cookie, encrypted and authenticated with XChaCha20-Poly1305
+#[derive(Serialize, Deserialize)]
+struct CookiePayload {
+    user: String,
+    series: String,
+    token: String,
+    device: String,
+    expires: u64,
+}
+
+/// Long-lived login cookies (feature `remember-me`), bound to the user and to the device
+/// they were issued to.
+///
+/// Cookies are encrypted, so they reveal nothing about the user, and carry a token
+/// rotated on every use. Presenting a token that was already rotated means the cookie was
+/// copied: the whole series is revoked, logging out both the thief and the victim.
+/// Requests sent in parallel with the same cookie are tolerated for a short grace period.
+///
+/// Register it as app state to use the [`RememberedUser`] extractor. The rotated cookie
+/// is sent back by [`RememberMeCookies`], wrapped around the apps started by the server,
+/// on every response including the error ones.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::remember_me::{RememberMe, RememberedUser};
+/// use ntex::web::{HttpRequest, HttpResponse};
+/// use ntex::web::types::State;
+/// use std::time::Duration;
+///
+/// let remember_me = RememberMe::new("cookie-secret")
+///     .unwrap()
+///     .ttl(Duration::from_secs(14 * 86400));
+/// // App::new().state(remember_me)
+///
+/// async fn login(req: HttpRequest, remember_me: State<RememberMe>) -> HttpResponse {
+///     // ... after checking the credentials
+///     let cookie = remember_me.issue("user-42", &req).await.unwrap();
+///     let mut resp = HttpResponse::Ok().finish();
+///     remember_me.set_cookie(&mut resp, &cookie);
+///     resp
+/// }
+///
+/// async fn dashboard(user: RememberedUser) -> HttpResponse {
+///     HttpResponse::Ok().body(format!("welcome back {}", user.user_id))
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RememberMe {
+    cipher: XChaCha20Poly1305,
+    hmac: Hmac,
+    store: Arc<dyn RememberMeStore>,
+    ttl: Duration,
+    grace: Duration,
+    cookie_name: String,
+}
+
+impl RememberMe {
+    /// Fails when no encryption key can be derived from `secret`
+    pub fn new(secret: &str) -> AppResult<Self> {
+        let hmac = Hmac::new(secret, HashFunc::Sha256);
+        let cipher = hmac
+            .hash(&"remember-me:encryption".to_string())
+            .ok()
+            .and_then(|hash| from_hex(&hash))
+            .and_then(|key| XChaCha20Poly1305::new_from_slice(&key).ok())
+            .ok_or_else(|| {
+                AppMessage::InternalServerErrorMessage(
+                    "Failed to derive the remember-me encryption key",
+                )
+                .ae()
+            })?;
+
+        Ok(Self {
+            cipher,
+            hmac,
+            store: Arc::new(MemoryRememberMeStore::default()),
+            ttl: Duration::from_secs(30 * 24 * 3600),
+            grace: Duration::from_secs(10),
+            cookie_name: "remember_me".to_string(),
+        })
+    }
+
+    pub fn store(mut self, store: impl RememberMeStore) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Lifetime of the cookies and series, 30 days by default
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// How long a rotated token is still accepted, 10 seconds by default
+    pub fn rotation_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Name of the cookie, `remember_me` by default
+    pub fn cookie_name(mut self, name: &str) -> Self {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    /// Start a new series for `user_id` on the device of `req`, returns the cookie value
+    pub async fn issue(&self, user_id: &str, req: &HttpRequest) -> AppResult<String> {
+        let series = random_token(12);
+        debug!("[remember-me] issuing series {series} to {user_id}");
+        let (stored, cookie) = self.next_token(user_id, &series, &self.device(req)?, None)?;
+        self.store.save(stored, self.ttl).await?;
+        Ok(cookie)
+    }
+
+    /// Verify the cookie of `req` and rotate its token
+    pub async fn verify(&self, req: &HttpRequest) -> AppResult<RememberedUser> {
+        let cookie = self
+            .cookie_of(req)
+            .ok_or_else(|| AppMessage::UnAuthorizedMessage("Missing remember-me cookie").ae())?;
+        let payload = self.decrypt(&cookie).ok_or_else(invalid)?;
+
+        if payload.expires <= now_secs() || payload.device != self.device(req)? {
+            return Err(invalid());
+        }
+
+        let stored = self
+            .store
+            .find(&payload.series)
+            .await?
+            .filter(|stored| stored.user_id == payload.user)
+            .ok_or_else(invalid)?;
+
+        let token = self.hash(&payload.token)?;
+        let cookie = match stored.token == token {
+            true => self.rotate(&payload, &token).await?,
+            // a parallel request with the cookie being replaced
+            false if self.within_grace(&stored, &token) => None,
+            false => {
+                warn!(
+                    "[remember-me] rotated token replayed, revoking series {} of {}",
+                    payload.series, payload.user
+                );
+                self.store.revoke(&payload.series).await?;
+                return Err(invalid());
+            }
+        };
+
+        Ok(RememberedUser {
+            user_id: payload.user,
+            series: payload.series,
+            cookie_header: cookie
+                .as_deref()
+                .map(|cookie| self.cookie_header(cookie))
+                .unwrap_or_default(),
+            cookie,
+        })
+    }
+
+    pub async fn revoke(&self, series: &str) -> AppResult<()> {
+        self.store.revoke(series).await
+    }
+
+    pub async fn revoke_user(&self, user_id: &str) -> AppResult<()> {
+        self.store.revoke_user(user_id).await
+    }
+
+    /// Revoke the series of the cookie sent with `req`, e.g. on logout
+    pub async fn forget(&self, req: &HttpRequest) -> AppResult<()> {
+        match self.cookie_of(req).and_then(|cookie| self.decrypt(&cookie)) {
+            Some(payload) => self.store.revoke(&payload.series).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Add the `Set-Cookie` header carrying `cookie` to `resp`
+    pub fn set_cookie(&self, resp: &mut HttpResponse, cookie: &str) {
+        if let Ok(value) = header::HeaderValue::from_str(&self.cookie_header(cookie)) {
+            resp.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+
+    /// Add the `Set-Cookie` header removing the cookie to `resp`
+    pub fn clear_cookie(&self, resp: &mut HttpResponse) {
+        let clear = format!(
+            "{}=; Max-Age=0; Path=/; HttpOnly; Secure; SameSite=Lax",
+            self.cookie_name
+        );
+        if let Ok(value) = header::HeaderValue::from_str(&clear) {
+            resp.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+
+    /// Replace the current token `token` of the series of `payload`, `None` when a parallel
+    /// request replaced it first and the token is within its grace period
+    async fn rotate(&self, payload: &CookiePayload, token: &str) -> AppResult<Option<String>> {
+        let (next, cookie) = self.next_token(
+            &payload.user,
+            &payload.series,
+            &payload.device,
+            Some(token.to_string()),
+        )?;
+        if self.store.rotate(next, token, self.ttl).await? {
+            return Ok(Some(cookie));
+        }
+
+        let stored = self.store.find(&payload.series).await?;
+        match stored.is_some_and(|stored| self.within_grace(&stored, token)) {
+            true => Ok(None),
+            false => Err(invalid()),
+        }
+    }
+
+    /// Whether `token` was replaced by the last rotation of `stored` less than the grace
+    /// period ago
+    fn within_grace(&self, stored: &RememberedSeries, token: &str) -> bool {
+        stored
+            .previous
+            .as_ref()
+            .is_some_and(|(previous, rotated_at)| {
+                previous == token && now_secs() < rotated_at + self.grace.as_secs()
+            })
+    }
+
+    /// New token of the series replacing `previous`, with the cookie carrying it
+    fn next_token(
+        &self,
+        user_id: &str,
+        series: &str,
+        device: &str,
+        previous: Option<String>,
+    ) -> AppResult<(RememberedSeries, String)> {
+        let token = random_token(32);
+        let stored = RememberedSeries {
+            series: series.to_string(),
+            user_id: user_id.to_string(),
+            token: self.hash(&token)?,
+            previous: previous.map(|previous| (previous, now_secs())),
+        };
+
+        let cookie = self.encrypt(&CookiePayload {
+            user: user_id.to_string(),
+            series: series.to_string(),
+            token,
+            device: device.to_string(),
+            expires: now_secs() + self.ttl.as_secs(),
+        })?;
+        Ok((stored, cookie))
+    }
+
+    fn encrypt(&self, payload: &CookiePayload) -> AppResult<String> {
+        let plain = serde_json::to_vec(payload)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = self
+            .cipher
+            .encrypt(&nonce, plain.as_slice())
+            .map_err(|_| AppMessage::InternalServerErrorMessage("Failed to seal cookie").ae())?;
+
+        Ok(to_hex(nonce.iter().chain(sealed.iter())))
+    }
+
+    fn decrypt(&self, cookie: &str) -> Option<CookiePayload> {
+        let bytes = from_hex(cookie)?;
+        if bytes.len() < 24 {
+            return None;
+        }
+
+        let (nonce, sealed) = bytes.split_at(24);
+        let plain = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), sealed)
+            .ok()?;
+        serde_json::from_slice(&plain).ok()
+    }
+
+    /// Fingerprint of the device sending `req`
+    fn device(&self, req: &HttpRequest) -> AppResult<String> {
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+        };
+        self.hash(&format!(
+            "{}|{}",
+            header(header::USER_AGENT),
+            header(header::ACCEPT_LANGUAGE)
+        ))
+    }
+
+    fn hash(&self, value: &str) -> AppResult<String> {
+        self.hmac.hash(&value.to_string())
+    }
+
+    fn cookie_of(&self, req: &HttpRequest) -> Option<String> {
+        req.headers()
+            .get_all(header::COOKIE)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == self.cookie_name)
+            .map(|(_, value)| value.to_string())
+    }
+
+    fn cookie_header(&self, cookie: &str) -> String {
+        format!(
+            "{}={cookie}; Max-Age={}; Path=/; HttpOnly; Secure; SameSite=Lax",
+            self.cookie_name,
+            self.ttl.as_secs()
+        )
+    }
+}
+
+/// User recognized by its remember-me cookie, extracted with the [`RememberMe`] app state.
+/// The token was rotated, [`RememberMeCookies`] sends the new cookie back.
+#[derive(Debug, Clone)]
+pub struct RememberedUser {
+    pub user_id: String,
+    pub series: String,
+    /// value of the rotated cookie, `None` when the request used a token within its
+    /// rotation grace period
+    pub cookie: Option<String>,
+    cookie_header: String,
+}
+
+impl RememberedUser {
+    /// Add the `Set-Cookie` header of the rotated cookie to `resp`, only needed for the
+    /// responses not going through [`RememberMeCookies`]
+    pub fn apply(&self, resp: &mut HttpResponse) {
+        if let Some(value) = self.set_cookie() {
+            append_once(resp.headers_mut(), value);
+        }
+    }
+
+    fn set_cookie(&self) -> Option<header::HeaderValue> {
+        self.cookie.as_ref()?;
+        header::HeaderValue::from_str(&self.cookie_header).ok()
+    }
+}
+
+/// `Set-Cookie` of a token rotated while extracting [`RememberedUser`]
+struct RotatedCookie(header::HeaderValue);
+
+fn append_once(headers: &mut header::HeaderMap, value: header::HeaderValue) {
+    if !headers
+        .get_all(header::SET_COOKIE)
+        .any(|sent| *sent == value)
+    {
+        headers.append(header::SET_COOKIE, value);
+    }
+}
+
+/// Middleware sending the cookie rotated by the [`RememberedUser`] extractor with the
+/// response, whatever the handler returned. Otherwise the client would keep the replaced
+/// token, and its next request past the grace period would revoke the series.
+///
+/// Wrapped around the apps started by the server, wrap it yourself in other apps.
+#[derive(Clone)]
+pub struct RememberMeCookies;
+
+impl<S> ServiceMiddleware<S> for RememberMeCookies {
+    type Service = RememberMeCookiesService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RememberMeCookiesService { service }
+    }
+}
+
+pub struct RememberMeCookiesService<S> {
+    service: S,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for RememberMeCookiesService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let mut resp = ctx.call(&self.service, req).await?;
+
+        let rotated = resp.request().extensions_mut().remove::<RotatedCookie>();
+        if let Some(RotatedCookie(value)) = rotated {
+            append_once(resp.headers_mut(), value);
+        }
+
+        Ok(resp)
+    }
+}
+
+impl<Err> FromRequest<Err> for RememberedUser {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        let remember_me = req.app_state::<RememberMe>().cloned().ok_or_else(|| {
+            HttpError::AppMessage(AppMessage::InternalServerErrorMessage(
+                "RememberMe is not registered as app state",
+            ))
+        })?;
+
+        let user = remember_me.verify(req).await?;
+        if let Some(value) = user.set_cookie() {
+            req.extensions_mut().insert(RotatedCookie(value));
+        }
+        Ok(user)
+    }
+}
+
+fn invalid() -> foxtive::Error {
+    AppMessage::UnAuthorizedMessage("Remember-me cookie is invalid").ae()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Hex of `len` bytes from the OS generator
+fn random_token(len: usize) -> String {
+    let mut bytes = vec![0; len];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(bytes.iter())
+}
+
+fn to_hex<'a>(bytes: impl Iterator<Item = &'a u8>) -> String {
+    bytes.map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(value.get(at..at + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::test::TestRequest;
+
+    fn request(cookie: Option<&str>) -> HttpRequest {
+        let mut req = TestRequest::default().header(header::USER_AGENT, "firefox");
+        if let Some(cookie) = cookie {
+            req = req.header(header::COOKIE, format!("theme=dark; remember_me={cookie}"));
+        }
+        req.to_http_request()
+    }
+
+    #[ntex::test]
+    async fn test_tokens_rotate_and_replays_revoke() {
+        let remember_me = RememberMe::new("secret")
+            .unwrap()
+            .rotation_grace(Duration::ZERO);
+        let cookie = remember_me.issue("user-1", &request(None)).await.unwrap();
+
+        let user = remember_me.verify(&request(Some(&cookie))).await.unwrap();
+        assert_eq!(user.user_id, "user-1");
+        let rotated = user.cookie.unwrap();
+        assert_ne!(rotated, cookie);
+
+        // the stolen copy of the first cookie kills the series
+        assert!(remember_me.verify(&request(Some(&cookie))).await.is_err());
+        assert!(remember_me.verify(&request(Some(&rotated))).await.is_err());
+    }
+
+    #[ntex::test]
+    async fn test_rotated_cookie_is_sent_on_every_response() {
+        use crate::http::HttpResult;
+        use ntex::web::App;
+        use ntex::web::test::{call_service, init_service};
+
+        async fn failing(_user: RememberedUser) -> HttpResult {
+            Err(HttpError::AppMessage(AppMessage::WarningMessage("nope")))
+        }
+
+        async fn applying(user: RememberedUser) -> HttpResponse {
+            let mut resp = HttpResponse::Ok().finish();
+            user.apply(&mut resp);
+            resp
+        }
+
+        let remember_me = RememberMe::new("secret").unwrap();
+        let app = init_service(
+            App::new()
+                .state(remember_me.clone())
+                .route("/failing", web::get().to(failing))
+                .route("/applying", web::get().to(applying))
+                .wrap(RememberMeCookies),
+        )
+        .await;
+
+        let mut cookie = remember_me.issue("user-1", &request(None)).await.unwrap();
+        for path in ["/failing", "/applying"] {
+            let req = TestRequest::with_uri(path)
+                .header(header::USER_AGENT, "firefox")
+                .header(header::COOKIE, format!("remember_me={cookie}"))
+                .to_request();
+            let resp = call_service(&app, req).await;
+
+            let set_cookies: Vec<_> = resp.headers().get_all(header::SET_COOKIE).collect();
+            assert_eq!(set_cookies.len(), 1, "{path}");
+            let sent = set_cookies[0].to_str().unwrap();
+            let rotated = sent.strip_prefix("remember_me=").unwrap();
+            let rotated = rotated.split(';').next().unwrap().to_string();
+            assert_ne!(rotated, cookie);
+            cookie = rotated;
+        }
+    }
+
+    #[ntex::test]
+    async fn test_cookies_are_bound_to_the_device() {
+        let remember_me = RememberMe::new("secret").unwrap();
+        let cookie = remember_me.issue("user-1", &request(None)).await.unwrap();
+
+        let other_device = TestRequest::default()
+            .header(header::USER_AGENT, "curl")
+            .header(header::COOKIE, format!("remember_me={cookie}"))
+            .to_http_request();
+        assert!(remember_me.verify(&other_device).await.is_err());
+
+        // other secrets can't read the cookie
+        let other = RememberMe::new("other")
+            .unwrap()
+            .store(MemoryRememberMeStore::default());
+        assert!(other.verify(&request(Some(&cookie))).await.is_err());
+
+        remember_me.revoke_user("user-1").await.unwrap();
+        assert!(remember_me.verify(&request(Some(&cookie))).await.is_err());
+    }
+
+    #[ntex::test]
+    async fn test_memory_store_expires_series() {
+        let store = MemoryRememberMeStore::default();
+        let series = |name: &str| RememberedSeries {
+            series: name.to_string(),
+            user_id: "user-1".to_string(),
+            token: "hash".to_string(),
+            previous: None,
+        };
+
+        store
+            .save(series("live"), Duration::from_secs(60))
+            .await
+            .unwrap();
+        store.save(series("expired"), Duration::ZERO).await.unwrap();
+
+        assert_eq!(store.find("live").await.unwrap(), Some(series("live")));
+        assert_eq!(store.find("expired").await.unwrap(), None);
+        assert_eq!(store.series.lock().unwrap().entries.len(), 1);
+    }
+
+    /// Store letting parallel requests interleave between reading and rotating a series
+    #[derive(Clone, Default)]
+    struct YieldingStore(Arc<MemoryRememberMeStore>);
+
+    impl RememberMeStore for YieldingStore {
+        fn find(&self, series: &str) -> RememberMeFuture<Option<RememberedSeries>> {
+            let found = self.0.find(series);
+            Box::pin(async move {
+                let found = found.await;
+                tokio::task::yield_now().await;
+                found
+            })
+        }
+
+        fn save(&self, series: RememberedSeries, ttl: Duration) -> RememberMeFuture<()> {
+            self.0.save(series, ttl)
+        }
+
+        fn rotate(
+            &self,
+            series: RememberedSeries,
+            expected: &str,
+            ttl: Duration,
+        ) -> RememberMeFuture<bool> {
+            self.0.rotate(series, expected, ttl)
+        }
+
+        fn revoke(&self, series: &str) -> RememberMeFuture<()> {
+            self.0.revoke(series)
+        }
+
+        fn revoke_user(&self, user_id: &str) -> RememberMeFuture<()> {
+            self.0.revoke_user(user_id)
+        }
+    }
+
+    #[ntex::test]
+    async fn test_parallel_requests_keep_the_series() {
+        let remember_me = RememberMe::new("secret")
+            .unwrap()
+            .store(YieldingStore::default());
+        let cookie = remember_me.issue("user-1", &request(None)).await.unwrap();
+
+        let (first, second) = futures_util::future::join(
+            remember_me.verify(&request(Some(&cookie))),
+            remember_me.verify(&request(Some(&cookie))),
+        )
+        .await;
+        let (first, second) = (first.unwrap(), second.unwrap());
+
+        // only one of them rotated the token, its cookie keeps working
+        let rotated = match (first.cookie, second.cookie) {
+            (Some(rotated), None) | (None, Some(rotated)) => rotated,
+            cookies => panic!("expected a single rotation, got {cookies:?}"),
+        };
+        assert!(remember_me.verify(&request(Some(&rotated))).await.is_ok());
+    }
+}
@@ -1,10 +1,21 @@
 use crate::contracts::ResponseCodeContract;
 use crate::enums::ResponseCode;
 use crate::helpers::json_message::JsonMessage;
+use crate::http::response::serializer::SerializerConfig;
+use crate::http::server::ServerSettings;
 use foxtive::helpers::json::json_empty;
-use ntex::http::{Response, StatusCode};
+use futures_util::Stream;
+use ntex::http::{Response, StatusCode, header};
+use ntex::util::Bytes;
 use ntex::web::HttpResponse;
 use serde::Serialize;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static NO_CONTENT_FOR_EMPTY: AtomicBool = AtomicBool::new(false);
+
+/// Initial capacity of the JSON response buffers, enough for the envelope of small payloads
+const JSON_BUFFER_CAPACITY: usize = 256;
 
 pub struct Responder;
 
@@ -14,10 +25,12 @@ impl Responder {
         C: ResponseCodeContract,
         D: Serialize,
     {
-        Self::respond(
+        let resp = Self::respond(
             JsonMessage::make(data, code.code(), code.success(), Some(msg.to_string())),
             code.status(),
-        )
+        );
+
+        Self::with_code_headers(resp, &code)
     }
 
     pub fn send<C, D>(data: D, code: C) -> Response
@@ -25,10 +38,55 @@ impl Responder {
         C: ResponseCodeContract,
         D: Serialize,
     {
-        Self::respond(
+        let no_content_for_empty = ServerSettings::read(|settings| settings.no_content_for_empty)
+            .unwrap_or_else(|| NO_CONTENT_FOR_EMPTY.load(Ordering::Relaxed));
+        if no_content_for_empty {
+            return Self::send_or_no_content(data, code);
+        }
+
+        let resp = Self::respond(
+            JsonMessage::make(data, code.code(), code.success(), None),
+            code.status(),
+        );
+
+        Self::with_code_headers(resp, &code)
+    }
+
+    /// Same as [`Responder::send`], but successful `()`/`None` data results in `204 No Content`
+    pub fn send_or_no_content<C, D>(data: D, code: C) -> Response
+    where
+        C: ResponseCodeContract,
+        D: Serialize,
+    {
+        let data = match serde_json::to_value(data) {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::error!("[responder] failed to serialize response data: {err}");
+                return Self::internal_server_error();
+            }
+        };
+
+        if data.is_null() && code.success() {
+            return Self::no_content();
+        }
+
+        let resp = Self::respond(
             JsonMessage::make(data, code.code(), code.success(), None),
             code.status(),
-        )
+        );
+
+        Self::with_code_headers(resp, &code)
+    }
+
+    pub fn no_content() -> Response {
+        HttpResponse::NoContent().finish()
+    }
+
+    /// Make [`Responder::send`] respond with `204 No Content` for successful `()`/`None` data
+    /// outside of the servers started with a `ServerConfig`, which use their
+    /// `ServerConfig::no_content_for_empty`
+    pub fn set_no_content_for_empty(enabled: bool) {
+        NO_CONTENT_FOR_EMPTY.store(enabled, Ordering::Relaxed);
     }
 
     pub fn ok_message(msg: &str) -> Response {
@@ -76,7 +134,18 @@ impl Responder {
             Some(msg.to_owned()),
         );
 
-        Self::respond(message, code.status())
+        Self::with_code_headers(Self::respond(message, code.status()), &code)
+    }
+
+    /// Add the headers required by the response code, keeping the ones already set
+    pub fn with_code_headers<C: ResponseCodeContract>(mut resp: Response, code: &C) -> Response {
+        for (name, value) in code.headers() {
+            if !resp.headers().contains_key(&name) {
+                resp.headers_mut().insert(name, value);
+            }
+        }
+
+        resp
     }
 
     /// Send a response without the standard response wrapper
@@ -92,6 +161,71 @@ impl Responder {
         Self::make_response(data, status)
     }
 
+    /// Send raw bytes as-is, bypassing the JSON envelope (images, PDFs, proxied files...)
+    pub fn send_bytes(bytes: impl Into<Bytes>, content_type: &str) -> Response {
+        Self::send_bytes_with_status(bytes, content_type, StatusCode::OK)
+    }
+
+    pub fn send_bytes_with_status(
+        bytes: impl Into<Bytes>,
+        content_type: &str,
+        status: StatusCode,
+    ) -> Response {
+        HttpResponse::build(status)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(bytes.into())
+    }
+
+    /// Stream a raw body without buffering it, bypassing the JSON envelope
+    pub fn send_stream<S, E>(stream: S, content_type: &str) -> Response
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Error + 'static,
+    {
+        Self::send_stream_with_status(stream, content_type, StatusCode::OK)
+    }
+
+    pub fn send_stream_with_status<S, E>(
+        stream: S,
+        content_type: &str,
+        status: StatusCode,
+    ) -> Response
+    where
+        S: Stream<Item = Result<Bytes, E>> + Unpin + 'static,
+        E: Error + 'static,
+    {
+        HttpResponse::build(status)
+            .header(header::CONTENT_TYPE, content_type)
+            .streaming(stream)
+    }
+
+    /// `201 Created` with the resource in the envelope and a `Location` header built from
+    /// `location`, its `{field}` placeholders being filled with the top-level fields of the
+    /// serialized resource, e.g. `/api/v1/users/{id}`
+    pub fn created_at<D: Serialize>(location: &str, data: D) -> Response {
+        let value = match serde_json::to_value(data) {
+            Ok(value) => value,
+            Err(err) => {
+                tracing::error!("[responder] failed to serialize response data: {err}");
+                return Self::internal_server_error();
+            }
+        };
+
+        let Some(location) = expand_location(location, &value) else {
+            tracing::error!("[responder] cannot fill location '{location}' from the resource");
+            return Self::internal_server_error();
+        };
+
+        let mut resp = Self::send(value, ResponseCode::Created);
+        match header::HeaderValue::from_str(&location) {
+            Ok(location) => {
+                resp.headers_mut().insert(header::LOCATION, location);
+                resp
+            }
+            Err(_) => Self::internal_server_error(),
+        }
+    }
+
     pub fn redirect(url: &'static str) -> Response {
         HttpResponse::Found()
             .header(ntex::http::header::LOCATION, url)
@@ -100,8 +234,46 @@ impl Responder {
     }
 
     fn make_response<T: Serialize>(data: T, status: StatusCode) -> Response {
-        HttpResponse::build(status).json(&data)
+        let mut buffer = Vec::with_capacity(JSON_BUFFER_CAPACITY);
+        match SerializerConfig::current().write(&mut buffer, &data) {
+            // the body takes the buffer over without copying it
+            Ok(_) => HttpResponse::build(status)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Bytes::from(buffer)),
+            Err(err) => err.into(),
+        }
+    }
+}
+
+/// Fill the `{field}` placeholders of `template` with the fields of `resource`,
+/// percent-encoded; `None` when a field is missing or isn't a string or number
+fn expand_location(template: &str, resource: &serde_json::Value) -> Option<String> {
+    let mut location = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        location.push_str(&rest[..start]);
+
+        let value = match resource.get(&rest[start + 1..end])? {
+            serde_json::Value::String(value) => value.clone(),
+            serde_json::Value::Number(value) => value.to_string(),
+            _ => return None,
+        };
+        for byte in value.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    location.push(byte as char)
+                }
+                _ => location.push_str(&format!("%{byte:02X}")),
+            }
+        }
+
+        rest = &rest[end + 1..];
     }
+
+    location.push_str(rest);
+    Some(location)
 }
 
 #[cfg(test)]
@@ -190,6 +362,53 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_send_or_no_content() {
+        let response = Responder::send_or_no_content((), ResponseCode::Ok);
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = Responder::send_or_no_content(None::<u8>, ResponseCode::NotFound);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = Responder::send_or_no_content(Some(1), ResponseCode::Ok);
+        let body: serde_json::Value =
+            serde_json::from_str(&collect_raw_body(response).await).unwrap();
+        assert_eq!(body["data"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_bytes() {
+        let bytes = vec![0x89, 0x50, 0x4e, 0x47, 0xff];
+        let mut response = Responder::send_bytes_with_status(
+            bytes.clone(),
+            "image/png",
+            StatusCode::PARTIAL_CONTENT,
+        );
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers().get("Content-Type").unwrap(), "image/png");
+
+        let mut body = response.take_body();
+        let mut collected = BytesMut::new();
+        while let Some(chunk) = body.next().await {
+            collected.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(collected.as_ref(), bytes.as_slice());
+    }
+
+    #[tokio::test]
+    async fn test_send_stream() {
+        let chunks: Vec<Result<Bytes, std::io::Error>> = vec![
+            Ok(Bytes::from_static(b"%PDF-")),
+            Ok(Bytes::from_static(b"1.7")),
+        ];
+        let response =
+            Responder::send_stream(futures_util::stream::iter(chunks), "application/pdf");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(collect_raw_body(response).await, "%PDF-1.7");
+    }
+
     #[tokio::test]
     async fn test_internal_server_error() {
         let response = Responder::internal_server_error();
@@ -202,4 +421,32 @@ mod tests {
         assert_eq!(body["message"], "Internal Server Error");
         assert_eq!(body["data"], serde_json::to_value(json_empty()).unwrap()); // assuming `json_empty()` returns an empty object
     }
+
+    #[test]
+    fn test_code_headers() {
+        let response = Responder::message("Login required", ResponseCode::Unauthorized);
+        assert_eq!(
+            response.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer"
+        );
+
+        let mut response = HttpResponse::TooManyRequests().finish();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, 5u64.into());
+        let response = Responder::with_code_headers(response, &ResponseCode::TooManyRequests);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "5");
+    }
+
+    #[test]
+    fn test_expand_location() {
+        let user = json!({"id": "a b/c", "org": 7, "tags": []});
+        assert_eq!(
+            expand_location("/orgs/{org}/users/{id}", &user).as_deref(),
+            Some("/orgs/7/users/a%20b%2Fc")
+        );
+        assert_eq!(expand_location("/users", &user).as_deref(), Some("/users"));
+        assert_eq!(expand_location("/users/{missing}", &user), None);
+        assert_eq!(expand_location("/users/{tags}", &user), None);
+    }
 }
@@ -0,0 +1,22 @@
+/// Compare a secret (signature, token) with what was received in time independent of
+/// where they first differ, so the comparison doesn't leak how much of a guess was right
+pub(crate) fn secure_eq(expected: &str, received: &str) -> bool {
+    expected.len() == received.len()
+        && expected
+            .bytes()
+            .zip(received.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secure_eq() {
+        assert!(secure_eq("5f2b", "5f2b"));
+        assert!(!secure_eq("5f2b", "5f2c"));
+        assert!(!secure_eq("5f2b", "5f2"));
+    }
+}
@@ -0,0 +1,262 @@
+use foxtive::prelude::AppResult;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+enum Slot<T> {
+    /// computation running, the receiver yields its result
+    InFlight(u64, watch::Receiver<Option<T>>),
+    /// result reused until the instant
+    Ready(T, Instant),
+}
+
+/// What a caller does with a key
+enum Joined<T> {
+    Cached(T),
+    /// another caller is computing it
+    Wait(watch::Receiver<Option<T>>),
+    /// the caller computes it
+    Lead(u64, watch::Sender<Option<T>>),
+}
+
+struct Inner<T> {
+    slots: Mutex<HashMap<String, Slot<T>>>,
+    ttl: Option<Duration>,
+    next_id: AtomicU64,
+}
+
+/// Coalesces concurrent computations of the same key: the first caller runs it, callers
+/// arriving meanwhile wait and receive a clone of its result.
+///
+/// Errors are not shared, callers that were waiting on a failed (or cancelled)
+/// computation run it again. With [`SingleFlight::with_ttl`], successful results are also
+/// reused for that long.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::single_flight::SingleFlight;
+/// use std::time::Duration;
+///
+/// # async fn example() -> foxtive::prelude::AppResult<()> {
+/// let leaderboard = SingleFlight::<Vec<String>>::with_ttl(Duration::from_secs(5));
+///
+/// // every request hitting the endpoint at the same time shares one query
+/// let top = leaderboard.run("top:weekly", || async {
+///     Ok(vec!["ada".to_string()])
+/// }).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SingleFlight<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Clone for SingleFlight<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Removes the in-flight slot when the leader returns without a result (error, panic
+/// or cancellation), waiters then retry
+struct LeaderGuard<'a, T> {
+    inner: &'a Inner<T>,
+    key: &'a str,
+    id: u64,
+}
+
+impl<T> Drop for LeaderGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Ok(mut slots) = self.inner.slots.lock()
+            && matches!(slots.get(self.key), Some(Slot::InFlight(id, _)) if *id == self.id)
+        {
+            slots.remove(self.key);
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    /// `ttl` is how long successful results are reused, `None` only coalesces
+    /// concurrent calls
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                slots: Mutex::new(HashMap::new()),
+                ttl,
+                next_id: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self::new(Some(ttl))
+    }
+
+    pub async fn run<F, Fut>(&self, key: &str, compute: F) -> AppResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AppResult<T>>,
+    {
+        loop {
+            let mut receiver = match self.join(key) {
+                Joined::Cached(value) => return Ok(value),
+                Joined::Wait(receiver) => receiver,
+                Joined::Lead(id, sender) => return self.lead(key, id, sender, compute).await,
+            };
+
+            // resolves once the leader publishes, fails when it gave up
+            if let Ok(value) = receiver.wait_for(Option::is_some).await
+                && let Some(value) = value.clone()
+            {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn join(&self, key: &str) -> Joined<T> {
+        let mut slots = self
+            .inner
+            .slots
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match slots.get(key) {
+            Some(Slot::Ready(value, expires)) if *expires > Instant::now() => {
+                Joined::Cached(value.clone())
+            }
+            Some(Slot::InFlight(_, receiver)) => Joined::Wait(receiver.clone()),
+            _ => {
+                let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+                let (sender, receiver) = watch::channel(None);
+                slots.retain(|_, slot| match slot {
+                    Slot::Ready(_, expires) => *expires > Instant::now(),
+                    Slot::InFlight(..) => true,
+                });
+                slots.insert(key.to_string(), Slot::InFlight(id, receiver));
+                Joined::Lead(id, sender)
+            }
+        }
+    }
+
+    async fn lead<F, Fut>(
+        &self,
+        key: &str,
+        id: u64,
+        sender: watch::Sender<Option<T>>,
+        compute: F,
+    ) -> AppResult<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AppResult<T>>,
+    {
+        let guard = LeaderGuard {
+            inner: &self.inner,
+            key,
+            id,
+        };
+
+        let value = compute().await?;
+
+        if let Ok(mut slots) = self.inner.slots.lock() {
+            match self.inner.ttl {
+                Some(ttl) => {
+                    let expires = Instant::now() + ttl;
+                    slots.insert(key.to_string(), Slot::Ready(value.clone(), expires));
+                }
+                None => {
+                    slots.remove(key);
+                }
+            }
+        }
+
+        std::mem::forget(guard);
+        let _ = sender.send(Some(value.clone()));
+        Ok(value)
+    }
+
+    /// Drop the cached result of `key`
+    pub fn forget(&self, key: &str) {
+        if let Ok(mut slots) = self.inner.slots.lock()
+            && matches!(slots.get(key), Some(Slot::Ready(..)))
+        {
+            slots.remove(key);
+        }
+    }
+
+    /// Number of computations currently running
+    pub fn in_flight(&self) -> usize {
+        match self.inner.slots.lock() {
+            Ok(slots) => slots
+                .values()
+                .filter(|slot| matches!(slot, Slot::InFlight(..)))
+                .count(),
+            Err(_) => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxtive::prelude::AppMessage;
+    use futures_util::future::join_all;
+    use std::sync::atomic::AtomicUsize;
+
+    #[ntex::test]
+    async fn test_concurrent_callers_share_one_computation() {
+        let flight = SingleFlight::<usize>::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let results = join_all((0..5).map(|_| {
+            let calls = calls.clone();
+            flight.run("hot", move || async move {
+                ntex::time::sleep(Duration::from_millis(20)).await;
+                Ok(calls.fetch_add(1, Ordering::SeqCst) + 42)
+            })
+        }))
+        .await;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(results.into_iter().all(|r| r.unwrap() == 42));
+        assert_eq!(flight.in_flight(), 0);
+
+        // nothing is kept without a ttl
+        let again = flight.run("hot", || async { Ok(7) }).await.unwrap();
+        assert_eq!(again, 7);
+    }
+
+    #[ntex::test]
+    async fn test_waiters_retry_after_failure_and_ttl_reuse() {
+        let flight = SingleFlight::<&'static str>::with_ttl(Duration::from_secs(60));
+
+        let (failed, retried) = futures_util::future::join(
+            flight.run("key", || async {
+                ntex::time::sleep(Duration::from_millis(20)).await;
+                AppMessage::InternalServerError.ar()
+            }),
+            flight.run("key", || async { Ok("second") }),
+        )
+        .await;
+
+        assert!(failed.is_err());
+        assert_eq!(retried.unwrap(), "second");
+
+        let cached = flight.run("key", || async { Ok("third") }).await.unwrap();
+        assert_eq!(cached, "second");
+
+        flight.forget("key");
+        let fresh = flight.run("key", || async { Ok("third") }).await.unwrap();
+        assert_eq!(fresh, "third");
+    }
+}
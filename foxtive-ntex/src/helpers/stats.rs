@@ -0,0 +1,462 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Number of buckets a window is divided in, older buckets expire one at a time
+const BUCKETS: u32 = 12;
+
+/// Power of two histogram, `counts[n]` holds the values below `2^n` (and at least `2^(n-1)`)
+#[derive(Clone, Default)]
+struct Histogram {
+    counts: [u64; 32],
+    max: u64,
+}
+
+impl Histogram {
+    fn record(&mut self, value: u64) {
+        let slot = (u64::BITS - value.leading_zeros()) as usize;
+        self.counts[slot.min(self.counts.len() - 1)] += 1;
+        self.max = self.max.max(value);
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other;
+        }
+        self.max = self.max.max(other.max);
+    }
+
+    /// Upper bound of the slot holding the percentile, capped by the max value seen
+    fn percentile(&self, percentile: f64) -> u64 {
+        let total: u64 = self.counts.iter().sum();
+        if total == 0 {
+            return 0;
+        }
+
+        let rank = ((total as f64) * percentile).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (slot, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return ((1u64 << slot) - 1).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+#[derive(Clone, Default)]
+struct RouteCounters {
+    requests: u64,
+    errors: u64,
+    latency_ms: Histogram,
+    total_ms: u64,
+    phases: HashMap<String, PhaseCounters>,
+}
+
+#[derive(Clone, Default)]
+struct PhaseCounters {
+    count: u64,
+    duration_ms: Histogram,
+    total_ms: u64,
+}
+
+impl PhaseCounters {
+    fn record(&mut self, elapsed_ms: u64) {
+        self.count += 1;
+        self.duration_ms.record(elapsed_ms);
+        self.total_ms += elapsed_ms;
+    }
+
+    fn merge(&mut self, other: &PhaseCounters) {
+        self.count += other.count;
+        self.duration_ms.merge(&other.duration_ms);
+        self.total_ms += other.total_ms;
+    }
+}
+
+#[derive(Default)]
+struct Bucket {
+    index: u64,
+    routes: HashMap<String, RouteCounters>,
+    request_size: Histogram,
+    response_size: Histogram,
+}
+
+struct Window {
+    started: Instant,
+    bucket: Duration,
+    buckets: VecDeque<Bucket>,
+}
+
+impl Window {
+    fn new(window: Duration) -> Self {
+        Self {
+            started: Instant::now(),
+            bucket: (window / BUCKETS).max(Duration::from_millis(1)),
+            buckets: VecDeque::new(),
+        }
+    }
+
+    fn index(&self) -> u64 {
+        (self.started.elapsed().as_nanos() / self.bucket.as_nanos().max(1)) as u64
+    }
+
+    /// Bucket of the current instant, dropping the expired ones
+    fn current(&mut self) -> &mut Bucket {
+        let index = self.index();
+        self.expire(index);
+        if self
+            .buckets
+            .back()
+            .is_none_or(|bucket| bucket.index != index)
+        {
+            self.buckets.push_back(Bucket {
+                index,
+                ..Default::default()
+            });
+        }
+        self.buckets.back_mut().expect("bucket was just pushed")
+    }
+
+    fn expire(&mut self, index: u64) {
+        while self
+            .buckets
+            .front()
+            .is_some_and(|bucket| bucket.index + (BUCKETS as u64) <= index)
+        {
+            self.buckets.pop_front();
+        }
+    }
+}
+
+/// Completed request, as recorded by the stats middleware
+pub(crate) struct RequestSample {
+    pub(crate) route: String,
+    pub(crate) status: u16,
+    pub(crate) elapsed: Duration,
+    pub(crate) request_size: Option<u64>,
+    pub(crate) response_size: Option<u64>,
+    /// Phases recorded through [`Timings`](crate::http::extractors::Timings)
+    pub(crate) phases: Vec<(String, Duration)>,
+}
+
+/// Rolling-window request counters, maintained by the server when enabled with
+/// `ServerConfig::request_stats` and exposed by [`admin::stats`](crate::http::admin::stats).
+///
+/// A dependency-free mini dashboard for environments without Prometheus: traffic, latency
+/// and error rate per route template, in-flight requests and body size percentiles.
+/// Phases recorded through [`Timings`](crate::http::extractors::Timings) are broken down
+/// per route as well.
+/// Latencies and sizes are kept in power of two histograms, percentiles are approximate.
+#[derive(Clone)]
+pub struct RequestStats {
+    enabled: Arc<AtomicBool>,
+    active: Arc<AtomicUsize>,
+    window: Arc<Mutex<Window>>,
+}
+
+impl Default for RequestStats {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300))
+    }
+}
+
+impl RequestStats {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            active: Arc::new(AtomicUsize::new(0)),
+            window: Arc::new(Mutex::new(Window::new(window))),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Start collecting over `window`, or stop with `None`
+    pub(crate) fn configure(&self, window: Option<Duration>) {
+        if let Some(window) = window
+            && let Ok(mut current) = self.window.lock()
+        {
+            *current = Window::new(window);
+        }
+        self.enabled.store(window.is_some(), Ordering::Relaxed);
+    }
+
+    /// Count a request in flight until the returned guard is dropped
+    pub(crate) fn track(&self) -> ActiveRequest {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        ActiveRequest(self.active.clone())
+    }
+
+    pub(crate) fn record(&self, sample: RequestSample) {
+        let Ok(mut window) = self.window.lock() else {
+            return;
+        };
+
+        let bucket = window.current();
+        let elapsed_ms = sample.elapsed.as_millis() as u64;
+        let route = bucket.routes.entry(sample.route).or_default();
+        route.requests += 1;
+        route.errors += u64::from(sample.status >= 500);
+        route.latency_ms.record(elapsed_ms);
+        route.total_ms += elapsed_ms;
+        for (name, elapsed) in sample.phases {
+            let phase = route.phases.entry(name).or_default();
+            phase.record(elapsed.as_millis() as u64);
+        }
+
+        if let Some(size) = sample.request_size {
+            bucket.request_size.record(size);
+        }
+        if let Some(size) = sample.response_size {
+            bucket.response_size.record(size);
+        }
+    }
+
+    /// Counters of the current window, routes ranked by traffic, latency and error rate
+    pub fn snapshot(&self, top: usize) -> StatsSnapshot {
+        let mut routes: HashMap<String, RouteCounters> = HashMap::new();
+        let mut request_size = Histogram::default();
+        let mut response_size = Histogram::default();
+        let mut window_secs = 0;
+
+        if let Ok(mut window) = self.window.lock() {
+            let index = window.index();
+            window.expire(index);
+            window_secs = (window.bucket * BUCKETS).as_secs();
+
+            for bucket in &window.buckets {
+                for (route, counters) in &bucket.routes {
+                    let merged = routes.entry(route.clone()).or_default();
+                    merged.requests += counters.requests;
+                    merged.errors += counters.errors;
+                    merged.total_ms += counters.total_ms;
+                    merged.latency_ms.merge(&counters.latency_ms);
+                    for (name, phase) in &counters.phases {
+                        merged.phases.entry(name.clone()).or_default().merge(phase);
+                    }
+                }
+                request_size.merge(&bucket.request_size);
+                response_size.merge(&bucket.response_size);
+            }
+        }
+
+        let mut routes: Vec<RouteStats> = routes
+            .into_iter()
+            .map(|(route, counters)| RouteStats::new(route, &counters))
+            .collect();
+        let requests = routes.iter().map(|route| route.requests).sum();
+        let errors = routes.iter().map(|route| route.errors).sum();
+
+        let mut ranked = |key: fn(&RouteStats) -> f64| {
+            routes.sort_by(|a, b| key(b).total_cmp(&key(a)).then(a.route.cmp(&b.route)));
+            routes.iter().take(top).cloned().collect::<Vec<_>>()
+        };
+
+        StatsSnapshot {
+            enabled: self.is_enabled(),
+            window_secs,
+            requests,
+            errors,
+            error_rate: ratio(errors, requests),
+            requests_per_sec: match window_secs {
+                0 => 0.0,
+                secs => requests as f64 / secs as f64,
+            },
+            active_requests: self.active.load(Ordering::Relaxed),
+            request_size: SizePercentiles::from(&request_size),
+            response_size: SizePercentiles::from(&response_size),
+            top_by_traffic: ranked(|route| route.requests as f64),
+            top_by_latency: ranked(|route| route.p95_ms as f64),
+            top_by_error_rate: ranked(|route| route.error_rate),
+        }
+    }
+}
+
+fn ratio(part: u64, total: u64) -> f64 {
+    match total {
+        0 => 0.0,
+        total => part as f64 / total as f64,
+    }
+}
+
+/// Decrements the in-flight requests when dropped
+pub(crate) struct ActiveRequest(Arc<AtomicUsize>);
+
+impl Drop for ActiveRequest {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counters of a route template over the window, errors being 5xx responses
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteStats {
+    pub route: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub avg_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+    pub phases: Vec<PhaseStats>,
+}
+
+impl RouteStats {
+    fn new(route: String, counters: &RouteCounters) -> Self {
+        Self {
+            route,
+            requests: counters.requests,
+            errors: counters.errors,
+            error_rate: ratio(counters.errors, counters.requests),
+            avg_ms: counters.total_ms / counters.requests.max(1),
+            p95_ms: counters.latency_ms.percentile(0.95),
+            max_ms: counters.latency_ms.max,
+            phases: PhaseStats::sorted(&counters.phases),
+        }
+    }
+}
+
+/// Durations of a [`Timings`](crate::http::extractors::Timings) phase within a route
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseStats {
+    pub name: String,
+    pub count: u64,
+    pub avg_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+impl PhaseStats {
+    fn sorted(phases: &HashMap<String, PhaseCounters>) -> Vec<Self> {
+        let mut phases: Vec<Self> = phases
+            .iter()
+            .map(|(name, counters)| Self {
+                name: name.clone(),
+                count: counters.count,
+                avg_ms: counters.total_ms / counters.count.max(1),
+                p95_ms: counters.duration_ms.percentile(0.95),
+                max_ms: counters.duration_ms.max,
+            })
+            .collect();
+        phases.sort_by(|a, b| a.name.cmp(&b.name));
+        phases
+    }
+}
+
+/// Body sizes in bytes, requests without a known size are left out
+#[derive(Debug, Clone, Serialize)]
+pub struct SizePercentiles {
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl From<&Histogram> for SizePercentiles {
+    fn from(histogram: &Histogram) -> Self {
+        Self {
+            p50: histogram.percentile(0.5),
+            p90: histogram.percentile(0.9),
+            p99: histogram.percentile(0.99),
+            max: histogram.max,
+        }
+    }
+}
+
+/// Rendering of [`RequestStats`]
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsSnapshot {
+    pub enabled: bool,
+    pub window_secs: u64,
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub requests_per_sec: f64,
+    pub active_requests: usize,
+    pub request_size: SizePercentiles,
+    pub response_size: SizePercentiles,
+    pub top_by_traffic: Vec<RouteStats>,
+    pub top_by_latency: Vec<RouteStats>,
+    pub top_by_error_rate: Vec<RouteStats>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(route: &str, status: u16, elapsed_ms: u64, size: u64) -> RequestSample {
+        RequestSample {
+            route: route.to_string(),
+            status,
+            elapsed: Duration::from_millis(elapsed_ms),
+            request_size: Some(size),
+            response_size: None,
+            phases: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ranks_routes() {
+        let stats = RequestStats::default();
+        for _ in 0..8 {
+            stats.record(sample("GET /users", 200, 5, 100));
+        }
+        stats.record(sample("POST /orders", 500, 300, 2000));
+        stats.record(sample("POST /orders", 201, 100, 2000));
+
+        let snapshot = stats.snapshot(1);
+        assert_eq!(snapshot.requests, 10);
+        assert_eq!(snapshot.errors, 1);
+        assert_eq!(snapshot.window_secs, 300);
+        assert_eq!(snapshot.top_by_traffic[0].route, "GET /users");
+        assert_eq!(snapshot.top_by_latency[0].route, "POST /orders");
+        assert_eq!(snapshot.top_by_latency[0].max_ms, 300);
+        assert_eq!(snapshot.top_by_latency[0].avg_ms, 200);
+        assert_eq!(snapshot.top_by_error_rate[0].error_rate, 0.5);
+        assert!(snapshot.request_size.p50 >= 100 && snapshot.request_size.p50 < 128);
+        assert_eq!(snapshot.request_size.max, 2000);
+        assert_eq!(snapshot.response_size.max, 0);
+    }
+
+    #[test]
+    fn test_breaks_down_phases() {
+        let stats = RequestStats::default();
+        for elapsed_ms in [4, 8] {
+            let mut sample = sample("GET /users", 200, 20, 0);
+            sample.phases = vec![
+                ("db".to_string(), Duration::from_millis(elapsed_ms)),
+                ("cache".to_string(), Duration::from_millis(1)),
+            ];
+            stats.record(sample);
+        }
+
+        let route = &stats.snapshot(1).top_by_traffic[0];
+        assert_eq!(route.phases.len(), 2);
+        assert_eq!(route.phases[0].name, "cache");
+        assert_eq!(route.phases[1].name, "db");
+        assert_eq!(route.phases[1].count, 2);
+        assert_eq!(route.phases[1].avg_ms, 6);
+        assert_eq!(route.phases[1].max_ms, 8);
+    }
+
+    #[test]
+    fn test_window_expires() {
+        let stats = RequestStats::new(Duration::from_millis(24));
+        stats.record(sample("GET /users", 200, 1, 10));
+        assert_eq!(stats.snapshot(5).requests, 1);
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(stats.snapshot(5).requests, 0);
+
+        let active = stats.track();
+        assert_eq!(stats.snapshot(5).active_requests, 1);
+        drop(active);
+        assert_eq!(stats.snapshot(5).active_requests, 0);
+    }
+}
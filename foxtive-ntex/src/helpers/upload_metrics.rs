@@ -0,0 +1,235 @@
+use crate::http::middlewares::MatchedRoute;
+use foxtive_ntex_multipart::{UploadEvent, UploadObserver};
+use ntex::web::HttpRequest;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Upper bounds of the storage latency histogram, in seconds
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static GLOBAL: OnceLock<UploadMetrics> = OnceLock::new();
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+#[derive(Default)]
+struct Registry {
+    bytes: BTreeMap<String, u64>,
+    files: BTreeMap<String, u64>,
+    rejections: BTreeMap<(String, &'static str), u64>,
+    storage: BTreeMap<String, Histogram>,
+}
+
+/// Prometheus metrics of the multipart uploads, labeled by route template:
+///
+/// - `upload_bytes_total{route}`
+/// - `upload_files_total{route}`
+/// - `upload_rejections_total{route, reason}`
+/// - `upload_storage_seconds{route}`, histogram of the time spent writing files to a
+///   storage backend
+///
+/// The server records into [`UploadMetrics::global`], render it with the
+/// `admin::upload_metrics` controller or [`UploadMetrics::render`].
+#[derive(Clone, Default)]
+pub struct UploadMetrics {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl UploadMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry fed by every `Multipart` extracted by the server
+    pub fn global() -> &'static UploadMetrics {
+        GLOBAL.get_or_init(UploadMetrics::new)
+    }
+
+    pub fn record(&self, route: &str, event: UploadEvent<'_>) {
+        let Ok(mut registry) = self.registry.lock() else {
+            return;
+        };
+
+        match event {
+            UploadEvent::File { bytes, .. } => {
+                *registry.bytes.entry(route.to_string()).or_default() += bytes as u64;
+                *registry.files.entry(route.to_string()).or_default() += 1;
+            }
+            UploadEvent::Rejected { reason } => {
+                *registry
+                    .rejections
+                    .entry((route.to_string(), reason))
+                    .or_default() += 1;
+            }
+            UploadEvent::Stored { elapsed } => {
+                let seconds = elapsed.as_secs_f64();
+                let histogram = registry.storage.entry(route.to_string()).or_default();
+                for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+                    if seconds <= bound {
+                        *bucket += 1;
+                    }
+                }
+                histogram.sum += seconds;
+                histogram.count += 1;
+            }
+        }
+    }
+
+    /// Metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let Ok(registry) = self.registry.lock() else {
+            return String::new();
+        };
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP upload_bytes_total Bytes of the uploaded files."
+        );
+        let _ = writeln!(out, "# TYPE upload_bytes_total counter");
+        for (route, value) in &registry.bytes {
+            let _ = writeln!(
+                out,
+                "upload_bytes_total{{route=\"{}\"}} {value}",
+                escape(route)
+            );
+        }
+
+        let _ = writeln!(out, "# HELP upload_files_total Number of uploaded files.");
+        let _ = writeln!(out, "# TYPE upload_files_total counter");
+        for (route, value) in &registry.files {
+            let _ = writeln!(
+                out,
+                "upload_files_total{{route=\"{}\"}} {value}",
+                escape(route)
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP upload_rejections_total Rejected uploads by reason."
+        );
+        let _ = writeln!(out, "# TYPE upload_rejections_total counter");
+        for ((route, reason), value) in &registry.rejections {
+            let _ = writeln!(
+                out,
+                "upload_rejections_total{{route=\"{}\",reason=\"{reason}\"}} {value}",
+                escape(route)
+            );
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP upload_storage_seconds Time spent storing uploaded files."
+        );
+        let _ = writeln!(out, "# TYPE upload_storage_seconds histogram");
+        for (route, histogram) in &registry.storage {
+            let route = escape(route);
+            for (count, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(
+                    out,
+                    "upload_storage_seconds_bucket{{route=\"{route}\",le=\"{bound}\"}} {count}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "upload_storage_seconds_bucket{{route=\"{route}\",le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "upload_storage_seconds_sum{{route=\"{route}\"}} {}",
+                histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "upload_storage_seconds_count{{route=\"{route}\"}} {}",
+                histogram.count
+            );
+        }
+
+        out
+    }
+}
+
+impl UploadObserver for UploadMetrics {
+    fn observe(&self, route: &str, event: UploadEvent<'_>) {
+        self.record(route, event);
+    }
+
+    /// The route template when resolved, keeping the label set bounded
+    fn route(&self, req: &HttpRequest) -> String {
+        match MatchedRoute::from_http_request(req) {
+            Some(route) => route.template().to_string(),
+            None => req.path().to_string(),
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render_prometheus_text() {
+        let metrics = UploadMetrics::new();
+        let route = "/users/{id}/avatar";
+        metrics.record(
+            route,
+            UploadEvent::File {
+                field: "avatar",
+                bytes: 512,
+            },
+        );
+        metrics.record(
+            route,
+            UploadEvent::File {
+                field: "avatar",
+                bytes: 256,
+            },
+        );
+        metrics.record(
+            route,
+            UploadEvent::Rejected {
+                reason: "file_too_large",
+            },
+        );
+        metrics.record(
+            route,
+            UploadEvent::Stored {
+                elapsed: Duration::from_millis(30),
+            },
+        );
+
+        let text = metrics.render();
+        assert!(text.contains("upload_bytes_total{route=\"/users/{id}/avatar\"} 768"));
+        assert!(text.contains("upload_files_total{route=\"/users/{id}/avatar\"} 2"));
+        assert!(text.contains(
+            "upload_rejections_total{route=\"/users/{id}/avatar\",reason=\"file_too_large\"} 1"
+        ));
+        assert!(text.contains(
+            "upload_storage_seconds_bucket{route=\"/users/{id}/avatar\",le=\"0.025\"} 0"
+        ));
+        assert!(
+            text.contains(
+                "upload_storage_seconds_bucket{route=\"/users/{id}/avatar\",le=\"0.05\"} 1"
+            )
+        );
+        assert!(text.contains("upload_storage_seconds_count{route=\"/users/{id}/avatar\"} 1"));
+    }
+}
@@ -0,0 +1,941 @@
+use crate::helpers::clock::RequestClock;
+use crate::helpers::components::{Component, ComponentRegistry};
+use crate::helpers::secure_compare::secure_eq;
+use foxtive::helpers::hmac::{HashFunc, Hmac};
+use foxtive::prelude::{AppMessage, AppResult};
+use futures_util::future::select;
+use futures_util::{StreamExt, stream};
+use ntex::http::client::Client;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::{Pin, pin};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+use tracing::{debug, warn};
+
+pub const WEBHOOK_ID_HEADER: &str = "x-webhook-id";
+pub const WEBHOOK_EVENT_HEADER: &str = "x-webhook-event";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+/// `v1=<hex HMAC-SHA256 of "{timestamp}.{body}">`
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+pub type WebhookFuture<T> = Pin<Box<dyn Future<Output = AppResult<T>>>>;
+
+/// Deliveries attempted at the same time by default
+const DELIVERY_CONCURRENCY: usize = 16;
+
+thread_local! {
+    // ntex clients can't leave their thread, the deliveries of a thread share its client
+    // and its connection pool
+    static CLIENT: Client = Client::new();
+}
+
+/// Sends signed webhook requests, returning the response status
+pub trait WebhookTransport: Send + Sync + 'static {
+    fn post(
+        &self,
+        url: &str,
+        headers: Vec<(&'static str, String)>,
+        body: String,
+    ) -> WebhookFuture<u16>;
+}
+
+/// [`WebhookTransport`] using the ntex HTTP client, `https` endpoints go through rustls
+/// with the webpki root certificates
+pub struct NtexWebhookTransport {
+    pub timeout: Duration,
+}
+
+impl Default for NtexWebhookTransport {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl WebhookTransport for NtexWebhookTransport {
+    fn post(
+        &self,
+        url: &str,
+        headers: Vec<(&'static str, String)>,
+        body: String,
+    ) -> WebhookFuture<u16> {
+        let url = url.to_string();
+        let timeout = self.timeout;
+
+        Box::pin(async move {
+            let mut request = CLIENT
+                .with(|client| client.post(url))
+                .timeout(timeout)
+                .content_type("application/json");
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+
+            let response = request
+                .send_body(body)
+                .await
+                .map_err(|err| AppMessage::WarningMessageString(err.to_string()).ae())?;
+            Ok(response.status().as_u16())
+        })
+    }
+}
+
+/// Signature of outbound payloads, and its verification for the receiving side
+pub struct WebhookSignature;
+
+impl WebhookSignature {
+    pub fn sign(secret: &str, timestamp: u64, body: &str) -> AppResult<String> {
+        let hash = Hmac::new(secret, HashFunc::Sha256).hash(&format!("{timestamp}.{body}"))?;
+        Ok(format!("v1={hash}"))
+    }
+
+    /// Check a signature and reject timestamps further than `tolerance` from now
+    pub fn verify(
+        secret: &str,
+        timestamp: u64,
+        signature: &str,
+        body: &str,
+        tolerance: Duration,
+    ) -> bool {
+        if now_secs().abs_diff(timestamp) > tolerance.as_secs() {
+            return false;
+        }
+
+        match Self::sign(secret, timestamp, body) {
+            Ok(expected) => secure_eq(&expected, signature),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Receiver of a tenant's webhooks
+#[derive(Clone, Serialize)]
+pub struct WebhookEndpoint {
+    pub id: String,
+    pub tenant: String,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// events delivered to this endpoint, all when empty
+    pub events: Vec<String>,
+}
+
+impl WebhookEndpoint {
+    pub fn new(id: &str, tenant: &str, url: &str, secret: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            tenant: tenant.to_string(),
+            url: url.to_string(),
+            secret: secret.to_string(),
+            events: vec![],
+        }
+    }
+
+    pub fn events(mut self, events: &[&str]) -> Self {
+        self.events = events.iter().map(|event| event.to_string()).collect();
+        self
+    }
+
+    fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|wanted| wanted == event)
+    }
+}
+
+impl Debug for WebhookEndpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookEndpoint")
+            .field("id", &self.id)
+            .field("tenant", &self.tenant)
+            .field("url", &self.url)
+            .field("secret", &"<redacted>")
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+/// Attempts and exponential backoff between them
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// attempts before a delivery is dead-lettered, 5 by default
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the attempt following `attempts` failed ones
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    /// failed at least once, another attempt is scheduled
+    Retrying,
+    Delivered,
+    /// gave up after the last attempt, can be redelivered manually
+    DeadLettered,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    pub id: String,
+    pub endpoint_id: String,
+    pub tenant: String,
+    pub event: String,
+    pub payload: Value,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_status_code: Option<u16>,
+    pub last_error: Option<String>,
+    /// unix timestamps
+    pub created_at: u64,
+    pub last_attempt_at: Option<u64>,
+    /// `None` while an attempt is in flight or once the delivery is settled
+    #[serde(skip)]
+    next_attempt: Option<Instant>,
+}
+
+/// Settled deliveries kept for lookups by default, the oldest are forgotten first
+const DELIVERY_HISTORY: usize = 10_000;
+
+/// Deliveries in creation order, indexed by id
+#[derive(Default)]
+struct DeliveryLog {
+    entries: BTreeMap<u64, Delivery>,
+    keys: HashMap<String, u64>,
+    /// keys of the settled deliveries, oldest first
+    settled: VecDeque<u64>,
+    sequence: u64,
+}
+
+impl DeliveryLog {
+    fn push(&mut self, delivery: Delivery) {
+        self.sequence += 1;
+        self.keys.insert(delivery.id.clone(), self.sequence);
+        self.entries.insert(self.sequence, delivery);
+    }
+
+    fn get(&self, id: &str) -> Option<&Delivery> {
+        self.entries.get(self.keys.get(id)?)
+    }
+
+    fn get_mut(&mut self, id: &str) -> Option<&mut Delivery> {
+        self.entries.get_mut(self.keys.get(id)?)
+    }
+
+    /// Record that the delivery `id` settled, forgetting the oldest settled deliveries
+    /// past `history`
+    fn settle(&mut self, id: &str, history: usize) {
+        if let Some(key) = self.keys.get(id) {
+            self.settled.push_back(*key);
+        }
+
+        while self.settled.len() > history {
+            let Some(key) = self.settled.pop_front() else {
+                break;
+            };
+            // redelivered dead letters are pending again
+            if self.entries.get(&key).is_some_and(Delivery::is_settled) {
+                self.remove(key);
+            }
+        }
+    }
+
+    fn remove(&mut self, key: u64) {
+        if let Some(delivery) = self.entries.remove(&key) {
+            self.keys.remove(&delivery.id);
+        }
+    }
+}
+
+impl Delivery {
+    fn is_settled(&self) -> bool {
+        matches!(
+            self.status,
+            DeliveryStatus::Delivered | DeliveryStatus::DeadLettered
+        )
+    }
+}
+
+struct Inner {
+    endpoints: RwLock<HashMap<String, WebhookEndpoint>>,
+    deliveries: Mutex<DeliveryLog>,
+    history: AtomicUsize,
+    concurrency: AtomicUsize,
+    transport: RwLock<Arc<dyn WebhookTransport>>,
+    policy: RwLock<RetryPolicy>,
+    clock: RwLock<RequestClock>,
+    sequence: AtomicU64,
+    wake: Notify,
+    stopped: AtomicBool,
+}
+
+/// Outbound webhooks: per-tenant endpoints, signed deliveries retried with backoff by a
+/// background task, dead letters and delivery status lookups.
+///
+/// Available on the server state as `state.webhooks`; the delivery task starts with the
+/// server and stops during graceful shutdown.
+///
+/// Deliveries are kept in memory: the pending and retrying ones are lost on restart, and
+/// only the last 10 000 settled ones can be looked up. Stage the events in a durable
+/// outbox first when they must survive a restart.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::helpers::webhooks::{WebhookEndpoint, Webhooks};
+/// use serde_json::json;
+///
+/// # fn example(webhooks: Webhooks) -> foxtive::prelude::AppResult<()> {
+/// webhooks.register_endpoint(
+///     WebhookEndpoint::new("ep-1", "acme", "https://acme.test/hooks", "whsec_123")
+///         .events(&["invoice.paid"]),
+/// );
+///
+/// let ids = webhooks.dispatch("acme", "invoice.paid", &json!({"invoice": 42}))?;
+/// let delivery = webhooks.delivery(&ids[0]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Webhooks {
+    inner: Arc<Inner>,
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self::new(NtexWebhookTransport::default())
+    }
+}
+
+impl Webhooks {
+    pub fn new(transport: impl WebhookTransport) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                endpoints: RwLock::new(HashMap::new()),
+                deliveries: Mutex::new(DeliveryLog::default()),
+                history: AtomicUsize::new(DELIVERY_HISTORY),
+                concurrency: AtomicUsize::new(DELIVERY_CONCURRENCY),
+                transport: RwLock::new(Arc::new(transport)),
+                policy: RwLock::new(RetryPolicy::default()),
+                clock: RwLock::new(RequestClock::system()),
+                sequence: AtomicU64::new(0),
+                wake: Notify::new(),
+                stopped: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn use_transport(&self, transport: impl WebhookTransport) {
+        if let Ok(mut current) = self.inner.transport.write() {
+            *current = Arc::new(transport);
+        }
+    }
+
+    /// Clock of delivery timestamps and signatures, usually `state.clock`
+    pub fn use_clock(&self, clock: RequestClock) {
+        if let Ok(mut current) = self.inner.clock.write() {
+            *current = clock;
+        }
+    }
+
+    pub fn set_retry_policy(&self, policy: RetryPolicy) {
+        if let Ok(mut current) = self.inner.policy.write() {
+            *current = policy;
+        }
+    }
+
+    /// How many settled (delivered or dead-lettered) deliveries are kept for lookups and
+    /// redelivery, 10 000 by default
+    pub fn set_history_limit(&self, limit: usize) {
+        self.inner.history.store(limit, Ordering::Relaxed);
+    }
+
+    /// How many deliveries are attempted at the same time, 16 by default
+    pub fn set_concurrency(&self, limit: usize) {
+        self.inner
+            .concurrency
+            .store(limit.max(1), Ordering::Relaxed);
+    }
+
+    /// Add an endpoint, replacing the one with the same id
+    pub fn register_endpoint(&self, endpoint: WebhookEndpoint) {
+        if let Ok(mut endpoints) = self.inner.endpoints.write() {
+            endpoints.insert(endpoint.id.clone(), endpoint);
+        }
+    }
+
+    pub fn remove_endpoint(&self, id: &str) -> Option<WebhookEndpoint> {
+        self.inner.endpoints.write().ok()?.remove(id)
+    }
+
+    pub fn endpoints(&self, tenant: &str) -> Vec<WebhookEndpoint> {
+        self.inner
+            .endpoints
+            .read()
+            .map(|endpoints| {
+                endpoints
+                    .values()
+                    .filter(|endpoint| endpoint.tenant == tenant)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Queue `payload` for every endpoint of `tenant` subscribed to `event`,
+    /// returning the ids of the created deliveries
+    pub fn dispatch<T: Serialize>(
+        &self,
+        tenant: &str,
+        event: &str,
+        payload: &T,
+    ) -> AppResult<Vec<String>> {
+        let payload = serde_json::to_value(payload)?;
+        let now = Instant::now();
+
+        let deliveries: Vec<Delivery> = self
+            .endpoints(tenant)
+            .into_iter()
+            .filter(|endpoint| endpoint.wants(event))
+            .map(|endpoint| Delivery {
+                id: self.next_id(),
+                endpoint_id: endpoint.id,
+                tenant: tenant.to_string(),
+                event: event.to_string(),
+                payload: payload.clone(),
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                last_status_code: None,
+                last_error: None,
+                created_at: self.clock().unix_secs(),
+                last_attempt_at: None,
+                next_attempt: Some(now),
+            })
+            .collect();
+
+        let ids = deliveries
+            .iter()
+            .map(|delivery| delivery.id.clone())
+            .collect();
+        if let Ok(mut log) = self.inner.deliveries.lock() {
+            for delivery in deliveries {
+                log.push(delivery);
+            }
+        }
+
+        self.inner.wake.notify_one();
+        Ok(ids)
+    }
+
+    pub fn delivery(&self, id: &str) -> Option<Delivery> {
+        self.inner.deliveries.lock().ok()?.get(id).cloned()
+    }
+
+    /// Deliveries of a tenant, optionally only those in `status`
+    pub fn deliveries(&self, tenant: &str, status: Option<DeliveryStatus>) -> Vec<Delivery> {
+        self.find(|delivery| {
+            delivery.tenant == tenant && status.is_none_or(|status| delivery.status == status)
+        })
+    }
+
+    pub fn dead_letters(&self, tenant: &str) -> Vec<Delivery> {
+        self.deliveries(tenant, Some(DeliveryStatus::DeadLettered))
+    }
+
+    /// Schedule a dead-lettered delivery again, with a fresh set of attempts
+    pub fn redeliver(&self, id: &str) -> AppResult<()> {
+        let mut deliveries = self
+            .inner
+            .deliveries
+            .lock()
+            .map_err(|_| AppMessage::InternalServerError.ae())?;
+
+        match deliveries.get_mut(id) {
+            Some(delivery) if delivery.status == DeliveryStatus::DeadLettered => {
+                delivery.status = DeliveryStatus::Retrying;
+                delivery.attempts = 0;
+                delivery.next_attempt = Some(Instant::now());
+            }
+            Some(_) => return AppMessage::WarningMessage("Delivery is not dead-lettered").ar(),
+            None => return AppMessage::EntityNotFound("delivery".to_string()).ar(),
+        }
+
+        drop(deliveries);
+        self.inner.wake.notify_one();
+        Ok(())
+    }
+
+    /// Forget settled deliveries, returns how many were removed
+    pub fn purge_delivered(&self) -> usize {
+        match self.inner.deliveries.lock() {
+            Ok(mut log) => {
+                let delivered: Vec<u64> = log
+                    .entries
+                    .iter()
+                    .filter(|(_, delivery)| delivery.status == DeliveryStatus::Delivered)
+                    .map(|(key, _)| *key)
+                    .collect();
+                for key in &delivered {
+                    log.remove(*key);
+                }
+                delivered.len()
+            }
+            Err(_) => 0,
+        }
+    }
+
+    /// Attempt every due delivery, returns how many were attempted.
+    /// Called by the background task, useful on its own in tests and jobs.
+    pub async fn run_pending(&self) -> usize {
+        let now = Instant::now();
+        let due: Vec<Delivery> = match self.inner.deliveries.lock() {
+            Ok(mut log) => log
+                .entries
+                .values_mut()
+                .filter(|delivery| delivery.next_attempt.is_some_and(|at| at <= now))
+                .map(|delivery| {
+                    delivery.next_attempt = None;
+                    delivery.clone()
+                })
+                .collect(),
+            Err(_) => return 0,
+        };
+
+        let attempted = due.len();
+        let concurrency = self.inner.concurrency.load(Ordering::Relaxed);
+        let outcomes: Vec<_> = stream::iter(due.iter().map(|delivery| self.attempt(delivery)))
+            .buffered(concurrency)
+            .collect()
+            .await;
+
+        let policy = self.policy();
+        let now = self.clock().unix_secs();
+        let history = self.inner.history.load(Ordering::Relaxed);
+        if let Ok(mut log) = self.inner.deliveries.lock() {
+            for (delivery, outcome) in due.into_iter().zip(outcomes) {
+                if let Some(current) = log.get_mut(&delivery.id) {
+                    Self::settle(current, outcome, &policy, now);
+                    if current.is_settled() {
+                        log.settle(&delivery.id, history);
+                    }
+                }
+            }
+        }
+
+        attempted
+    }
+
+    /// Spawn the delivery task on the current runtime, stopped with the other components
+    pub(crate) fn start(&self, components: &ComponentRegistry, poll_interval: Duration) {
+        let inner = self.inner.clone();
+        let reporter = components.register(Component::new("webhooks", move || async move {
+            inner.stopped.store(true, Ordering::SeqCst);
+            inner.wake.notify_one();
+            Ok(())
+        }));
+
+        let webhooks = self.clone();
+        ntex::rt::spawn(async move {
+            while !webhooks.inner.stopped.load(Ordering::SeqCst) {
+                webhooks.run_pending().await;
+                reporter.beat();
+
+                // woken early by new dispatches and shutdown
+                let wake = webhooks.inner.wake.notified();
+                select(pin!(wake), pin!(ntex::time::sleep(poll_interval))).await;
+            }
+            debug!("[webhooks] delivery task stopped");
+        });
+    }
+
+    async fn attempt(&self, delivery: &Delivery) -> Result<u16, String> {
+        let endpoint = self
+            .inner
+            .endpoints
+            .read()
+            .ok()
+            .and_then(|endpoints| endpoints.get(&delivery.endpoint_id).cloned())
+            .ok_or_else(|| "endpoint removed".to_string())?;
+
+        let timestamp = self.clock().unix_secs();
+        let body = json!({
+            "id": delivery.id,
+            "event": delivery.event,
+            "tenant": delivery.tenant,
+            "created_at": delivery.created_at,
+            "data": delivery.payload,
+        })
+        .to_string();
+
+        let signature = WebhookSignature::sign(&endpoint.secret, timestamp, &body)
+            .map_err(|e| e.to_string())?;
+        let headers = vec![
+            (WEBHOOK_ID_HEADER, delivery.id.clone()),
+            (WEBHOOK_EVENT_HEADER, delivery.event.clone()),
+            (WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string()),
+            (WEBHOOK_SIGNATURE_HEADER, signature),
+        ];
+
+        let transport = self.transport();
+        transport
+            .post(&endpoint.url, headers, body)
+            .await
+            .map_err(|err| err.to_string())
+    }
+
+    fn settle(
+        delivery: &mut Delivery,
+        outcome: Result<u16, String>,
+        policy: &RetryPolicy,
+        now: u64,
+    ) {
+        delivery.attempts += 1;
+        delivery.last_attempt_at = Some(now);
+
+        let error = match outcome {
+            Ok(status) if (200..300).contains(&status) => {
+                delivery.last_status_code = Some(status);
+                delivery.last_error = None;
+                delivery.status = DeliveryStatus::Delivered;
+                return;
+            }
+            Ok(status) => {
+                delivery.last_status_code = Some(status);
+                format!("endpoint responded with {status}")
+            }
+            Err(error) => error,
+        };
+
+        delivery.last_error = Some(error);
+        match delivery.attempts >= policy.max_attempts {
+            true => {
+                warn!(
+                    "[webhooks] delivery {} to '{}' dead-lettered after {} attempts: {:?}",
+                    delivery.id, delivery.endpoint_id, delivery.attempts, delivery.last_error
+                );
+                delivery.status = DeliveryStatus::DeadLettered;
+            }
+            false => {
+                delivery.status = DeliveryStatus::Retrying;
+                delivery.next_attempt = Some(Instant::now() + policy.backoff(delivery.attempts));
+            }
+        }
+    }
+
+    fn find(&self, matches: impl Fn(&Delivery) -> bool) -> Vec<Delivery> {
+        self.inner
+            .deliveries
+            .lock()
+            .map(|log| {
+                log.entries
+                    .values()
+                    .filter(|delivery| matches(delivery))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn next_id(&self) -> String {
+        let sequence = self.inner.sequence.fetch_add(1, Ordering::Relaxed);
+        let millis = self.clock().unix_millis();
+        format!("whd_{millis:x}{sequence:04x}")
+    }
+
+    fn clock(&self) -> RequestClock {
+        match self.inner.clock.read() {
+            Ok(clock) => clock.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn transport(&self) -> Arc<dyn WebhookTransport> {
+        match self.inner.transport.read() {
+            Ok(transport) => transport.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn policy(&self) -> RetryPolicy {
+        self.inner
+            .policy
+            .read()
+            .map(|policy| policy.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Sent = Arc<Mutex<Vec<(String, Vec<(&'static str, String)>, String)>>>;
+
+    /// Answers with the scripted statuses in turn, 200 once they run out
+    struct Scripted {
+        statuses: Mutex<Vec<u16>>,
+        sent: Sent,
+    }
+
+    impl WebhookTransport for Scripted {
+        fn post(
+            &self,
+            url: &str,
+            headers: Vec<(&'static str, String)>,
+            body: String,
+        ) -> WebhookFuture<u16> {
+            self.sent
+                .lock()
+                .unwrap()
+                .push((url.to_string(), headers, body));
+            let status = self.statuses.lock().unwrap().pop().unwrap_or(200);
+            Box::pin(async move { Ok(status) })
+        }
+    }
+
+    fn webhooks(mut statuses: Vec<u16>) -> (Webhooks, Sent) {
+        statuses.reverse();
+        let sent = Sent::default();
+        let webhooks = Webhooks::new(Scripted {
+            statuses: Mutex::new(statuses),
+            sent: sent.clone(),
+        });
+
+        webhooks.set_retry_policy(RetryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        });
+        webhooks.register_endpoint(
+            WebhookEndpoint::new("ep-1", "acme", "http://acme.test/hooks", "secret")
+                .events(&["invoice.paid"]),
+        );
+        webhooks.register_endpoint(WebhookEndpoint::new(
+            "ep-2",
+            "globex",
+            "http://globex.test/hooks",
+            "other",
+        ));
+
+        (webhooks, sent)
+    }
+
+    #[ntex::test]
+    async fn test_signed_delivery_to_tenant_endpoints() {
+        let (webhooks, sent) = webhooks(vec![]);
+
+        assert!(
+            webhooks
+                .dispatch("acme", "invoice.created", &json!({}))
+                .unwrap()
+                .is_empty()
+        );
+        let ids = webhooks
+            .dispatch("acme", "invoice.paid", &json!({"invoice": 42}))
+            .unwrap();
+        assert_eq!(webhooks.run_pending().await, 1);
+
+        let (url, headers, body) = sent.lock().unwrap().pop().unwrap();
+        assert_eq!(url, "http://acme.test/hooks");
+
+        let header = |name| headers.iter().find(|(n, _)| *n == name).unwrap().1.clone();
+        let timestamp: u64 = header(WEBHOOK_TIMESTAMP_HEADER).parse().unwrap();
+        let signature = header(WEBHOOK_SIGNATURE_HEADER);
+        let tolerance = Duration::from_secs(300);
+        assert!(WebhookSignature::verify(
+            "secret", timestamp, &signature, &body, tolerance
+        ));
+        assert!(!WebhookSignature::verify(
+            "other", timestamp, &signature, &body, tolerance
+        ));
+        assert!(!WebhookSignature::verify(
+            "secret", timestamp, &signature, "{}", tolerance
+        ));
+
+        let delivery = webhooks.delivery(&ids[0]).unwrap();
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(delivery.last_status_code, Some(200));
+        assert_eq!(webhooks.purge_delivered(), 1);
+    }
+
+    #[ntex::test]
+    async fn test_retries_then_dead_letters() {
+        let (webhooks, sent) = webhooks(vec![500, 503, 200]);
+        let id = webhooks
+            .dispatch("acme", "invoice.paid", &json!({}))
+            .unwrap()
+            .remove(0);
+
+        webhooks.run_pending().await;
+        assert_eq!(
+            webhooks.delivery(&id).unwrap().status,
+            DeliveryStatus::Retrying
+        );
+
+        webhooks.run_pending().await;
+        let delivery = webhooks.delivery(&id).unwrap();
+        assert_eq!(delivery.status, DeliveryStatus::DeadLettered);
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.last_status_code, Some(503));
+        assert_eq!(webhooks.dead_letters("acme").len(), 1);
+        assert!(webhooks.dead_letters("globex").is_empty());
+
+        // nothing is due until redelivered
+        assert_eq!(webhooks.run_pending().await, 0);
+        webhooks.redeliver(&id).unwrap();
+        webhooks.run_pending().await;
+        assert_eq!(
+            webhooks.delivery(&id).unwrap().status,
+            DeliveryStatus::Delivered
+        );
+        assert_eq!(sent.lock().unwrap().len(), 3);
+    }
+
+    #[ntex::test]
+    async fn test_settled_history_is_capped() {
+        let (webhooks, _) = webhooks(vec![]);
+        webhooks.set_history_limit(2);
+
+        let mut ids = vec![];
+        for invoice in 0..3 {
+            ids.extend(
+                webhooks
+                    .dispatch("acme", "invoice.paid", &json!({"invoice": invoice}))
+                    .unwrap(),
+            );
+        }
+        assert_eq!(webhooks.run_pending().await, 3);
+
+        // the oldest settled delivery is forgotten, the others stay in order
+        assert!(webhooks.delivery(&ids[0]).is_none());
+        let kept: Vec<String> = webhooks
+            .deliveries("acme", None)
+            .into_iter()
+            .map(|delivery| delivery.id)
+            .collect();
+        assert_eq!(kept, ids[1..]);
+    }
+
+    /// Tracks how many posts are in flight at once
+    #[derive(Default)]
+    struct Slow {
+        in_flight: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    }
+
+    impl WebhookTransport for Slow {
+        fn post(&self, _: &str, _: Vec<(&'static str, String)>, _: String) -> WebhookFuture<u16> {
+            let (in_flight, peak) = (self.in_flight.clone(), self.peak.clone());
+            Box::pin(async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(current, Ordering::SeqCst);
+                ntex::time::sleep(Duration::from_millis(5)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(200)
+            })
+        }
+    }
+
+    #[ntex::test]
+    async fn test_deliveries_are_attempted_with_bounded_concurrency() {
+        let transport = Slow::default();
+        let peak = transport.peak.clone();
+        let webhooks = Webhooks::new(transport);
+        webhooks.set_concurrency(3);
+        webhooks.register_endpoint(WebhookEndpoint::new(
+            "ep-1",
+            "acme",
+            "http://acme.test/hooks",
+            "secret",
+        ));
+
+        for invoice in 0..10 {
+            webhooks
+                .dispatch("acme", "invoice.paid", &json!({"invoice": invoice}))
+                .unwrap();
+        }
+        assert_eq!(webhooks.run_pending().await, 10);
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            webhooks
+                .deliveries("acme", Some(DeliveryStatus::Delivered))
+                .len(),
+            10
+        );
+    }
+
+    #[ntex::test]
+    async fn test_transport_supports_https() {
+        let err = NtexWebhookTransport::default()
+            .post("https://127.0.0.1:1/hooks", vec![], "{}".to_string())
+            .await
+            .unwrap_err();
+        assert!(!err.to_string().contains("SSL is not supported"), "{err}");
+    }
+
+    #[test]
+    fn test_endpoint_debug_hides_the_secret() {
+        let endpoint = WebhookEndpoint::new("ep-1", "acme", "https://acme.test", "whsec_123");
+        assert!(!format!("{endpoint:?}").contains("whsec_123"));
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_secs(5));
+        assert_eq!(policy.backoff(3), Duration::from_secs(20));
+        assert_eq!(policy.backoff(30), Duration::from_secs(30 * 60));
+    }
+
+    #[ntex::test]
+    async fn test_timestamps_follow_the_clock() {
+        let (webhooks, sent) = webhooks(vec![]);
+        let clock =
+            RequestClock::frozen(chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+        webhooks.use_clock(clock.clone());
+
+        let ids = webhooks
+            .dispatch("acme", "invoice.paid", &json!({}))
+            .unwrap();
+        clock.advance(Duration::from_secs(30));
+        webhooks.run_pending().await;
+
+        let delivery = webhooks.delivery(&ids[0]).unwrap();
+        assert_eq!(delivery.created_at, 1_700_000_000);
+        assert_eq!(delivery.last_attempt_at, Some(1_700_000_030));
+
+        let (_, headers, _) = sent.lock().unwrap().pop().unwrap();
+        assert!(headers.contains(&(WEBHOOK_TIMESTAMP_HEADER, "1700000030".to_string())));
+    }
+}
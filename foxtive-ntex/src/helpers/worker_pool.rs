@@ -0,0 +1,220 @@
+use crate::FOXTIVE_NTEX;
+use foxtive::prelude::{AppMessage, AppResult};
+use ntex::http::StatusCode;
+use std::collections::HashMap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::sync::oneshot;
+use tracing::{debug, error, warn};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Jobs a pool queues per thread by default, before rejecting new ones
+const QUEUED_JOBS_PER_THREAD: usize = 64;
+
+/// Dedicated set of threads for blocking work, isolating expensive endpoints
+/// (reports, exports...) from the shared blocking pool.
+///
+/// Jobs wait in a bounded queue, once it is full new jobs are rejected with
+/// `503 Service Unavailable` instead of piling up.
+#[derive(Clone)]
+pub struct WorkerPool {
+    name: Arc<str>,
+    sender: SyncSender<Job>,
+}
+
+impl WorkerPool {
+    /// Start `threads` threads named `{name}-{index}`, queueing up to 64 jobs per thread
+    pub fn new(name: &str, threads: usize) -> AppResult<Self> {
+        let threads = threads.max(1);
+        Self::with_queue(name, threads, threads * QUEUED_JOBS_PER_THREAD)
+    }
+
+    /// Start `threads` threads named `{name}-{index}`, queueing up to `queue` jobs
+    pub fn with_queue(name: &str, threads: usize, queue: usize) -> AppResult<Self> {
+        let (sender, receiver) = sync_channel::<Job>(queue);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for index in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("{name}-{index}"))
+                .spawn(move || Self::work(receiver))?;
+        }
+
+        debug!("[worker-pool] started '{name}' with {threads} thread(s)");
+
+        Ok(Self {
+            name: Arc::from(name),
+            sender,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Run the function on one of the pool threads and await its result
+    pub async fn run<F, T>(&self, func: F) -> AppResult<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = tx.send(catch_unwind(AssertUnwindSafe(func)));
+        });
+
+        match self.sender.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                warn!("[worker-pool] queue of pool '{}' is full", self.name);
+                return Err(AppMessage::ErrorMessage(
+                    format!("Worker pool '{}' is busy, try again later", self.name),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                )
+                .ae());
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                error!("[worker-pool] pool '{}' is not accepting jobs", self.name);
+                return AppMessage::InternalServerError.ar();
+            }
+        }
+
+        match rx.await {
+            Ok(Ok(value)) => Ok(value),
+            _ => {
+                error!("[worker-pool] job panicked on pool '{}'", self.name);
+                AppMessage::InternalServerError.ar()
+            }
+        }
+    }
+
+    fn work(receiver: Arc<Mutex<Receiver<Job>>>) {
+        loop {
+            let job = match receiver.lock() {
+                Ok(receiver) => receiver.recv(),
+                Err(_) => return,
+            };
+
+            match job {
+                Ok(job) => job(),
+                // every sender is gone, the pool was dropped
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+/// Named worker pools, configured through `ServerConfig::worker_pool`
+#[derive(Clone, Default)]
+pub struct WorkerPools {
+    pools: Arc<HashMap<String, WorkerPool>>,
+}
+
+impl WorkerPools {
+    pub fn new(pools: Vec<WorkerPool>) -> Self {
+        let pools = pools
+            .into_iter()
+            .map(|pool| (pool.name().to_string(), pool))
+            .collect();
+
+        Self {
+            pools: Arc::new(pools),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WorkerPool> {
+        self.pools.get(name)
+    }
+
+    /// Run the function on the named pool, fails when no such pool is configured
+    pub async fn spawn_on<F, T>(&self, name: &str, func: F) -> AppResult<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        match self.get(name) {
+            Some(pool) => pool.run(func).await,
+            None => {
+                error!("[worker-pool] no worker pool named '{name}'");
+                AppMessage::InternalServerErrorMessage("unknown worker pool").ar()
+            }
+        }
+    }
+}
+
+/// Run the function on a named pool of the running server
+///
+/// # Example
+/// ```no_run
+/// use foxtive_ntex::helpers::worker_pool::spawn_on;
+///
+/// async fn export() -> foxtive::prelude::AppResult<usize> {
+///     spawn_on("reports", || 42).await
+/// }
+/// ```
+pub async fn spawn_on<F, T>(name: &str, func: F) -> AppResult<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match FOXTIVE_NTEX.get() {
+        Some(state) => state.worker_pools.spawn_on(name, func).await,
+        None => AppMessage::InternalServerErrorMessage("foxtive-ntex is not set up").ar(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runs_on_named_thread() {
+        let pools = WorkerPools::new(vec![WorkerPool::new("reports", 2).unwrap()]);
+        let thread_name = pools
+            .spawn_on("reports", || thread::current().name().unwrap().to_string())
+            .await
+            .unwrap();
+
+        assert!(thread_name.starts_with("reports-"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_pool_fails() {
+        let pools = WorkerPools::default();
+        assert!(pools.spawn_on("reports", || 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_jobs_when_the_queue_is_full() {
+        let pool = WorkerPool::with_queue("busy", 1, 1).unwrap();
+        let (release, blocked) = std::sync::mpsc::channel::<()>();
+        let (started, running) = std::sync::mpsc::channel::<()>();
+
+        pool.sender
+            .try_send(Box::new(move || {
+                started.send(()).unwrap();
+                blocked.recv().unwrap();
+            }))
+            .unwrap();
+        running.recv().unwrap();
+
+        // the thread is busy and the queue holds one job
+        pool.sender.try_send(Box::new(|| {})).unwrap();
+        let err = pool.run(|| 3).await.unwrap_err();
+        assert!(err.to_string().contains("busy"));
+
+        release.send(()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_panicking_job_does_not_kill_pool() {
+        let pool = WorkerPool::new("panics", 1).unwrap();
+
+        assert!(pool.run(|| -> i32 { panic!("boom") }).await.is_err());
+        assert_eq!(pool.run(|| 7).await.unwrap(), 7);
+    }
+}
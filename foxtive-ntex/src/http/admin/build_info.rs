@@ -0,0 +1,38 @@
+use crate::FoxtiveNtexState;
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use ntex::web;
+use ntex::web::{HttpResponse, ServiceConfig};
+use serde::Serialize;
+
+/// What the running binary was built with
+#[derive(Debug, Serialize)]
+struct BuildInfo {
+    /// version of foxtive-ntex
+    framework_version: &'static str,
+    features: Vec<&'static str>,
+    pid: u32,
+}
+
+/// Registers `GET` rendering the foxtive-ntex version and the cargo features the binary
+/// was compiled with, so operators can check a deployment matches expectations.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::admin::build_info;
+/// use foxtive_ntex::http::kernel::Controller;
+///
+/// let controller = Controller::new("/system/build-info", build_info::register);
+/// ```
+pub fn register(cfg: &mut ServiceConfig) {
+    cfg.route("", web::get().to(show));
+}
+
+async fn show(state: web::types::State<FoxtiveNtexState>) -> HttpResponse {
+    let info = BuildInfo {
+        framework_version: env!("CARGO_PKG_VERSION"),
+        features: state.features(),
+        pid: std::process::id(),
+    };
+    Responder::send(info, ResponseCode::Ok)
+}
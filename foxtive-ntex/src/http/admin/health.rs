@@ -0,0 +1,139 @@
+use crate::FoxtiveNtexState;
+use crate::enums::ResponseCode;
+use crate::helpers::components::HealthReport;
+use crate::helpers::responder::Responder;
+use ntex::web;
+use ntex::web::{HttpResponse, ServiceConfig};
+
+/// Registers the aggregated health report (`GET`), plus `GET /live` and `GET /ready`
+/// probes answering 503 when a critical component is down or not ready, or when a
+/// critical dependency is down (readiness only).
+///
+/// Components report through the [`HealthReporter`](crate::helpers::components::HealthReporter)
+/// returned when registering them on `FoxtiveNtexState::components`, dependencies are
+/// checked by the [`DependencyProbe`](crate::helpers::probes::DependencyProbe)s registered
+/// there, with cached results.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::admin::health;
+/// use foxtive_ntex::http::kernel::Controller;
+///
+/// let controller = Controller::new("/system/health-check", health::register);
+/// ```
+pub fn register(cfg: &mut ServiceConfig) {
+    cfg.route("", web::get().to(report))
+        .route("/live", web::get().to(live))
+        .route("/ready", web::get().to(ready));
+}
+
+async fn report(state: web::types::State<FoxtiveNtexState>) -> HttpResponse {
+    let health = state.components.checked_health().await;
+    let healthy = health.ready;
+    respond(health, healthy)
+}
+
+async fn live(state: web::types::State<FoxtiveNtexState>) -> HttpResponse {
+    let health = state.components.health();
+    let live = health.live;
+    respond(health, live)
+}
+
+async fn ready(state: web::types::State<FoxtiveNtexState>) -> HttpResponse {
+    let health = state.components.checked_health().await;
+    let ready = health.ready;
+    respond(health, ready)
+}
+
+fn respond(health: HealthReport, healthy: bool) -> HttpResponse {
+    match healthy {
+        true => Responder::send(health, ResponseCode::Ok),
+        false => Responder::send(health, ResponseCode::ServiceUnavailable),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::components::Component;
+    use crate::helpers::probes::DependencyProbe;
+    use crate::http::Method;
+    use foxtive::prelude::AppMessage;
+    use ntex::http::StatusCode;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+
+    #[ntex::test]
+    async fn test_probes_follow_critical_components() {
+        let state = FoxtiveNtexState {
+            allowed_methods: vec![Method::GET],
+            ..FoxtiveNtexState::for_tests()
+        };
+        let consumer = state
+            .components
+            .register(Component::new("consumer", || async { Ok(()) }).critical());
+
+        let app = init_service(
+            App::new()
+                .state(state)
+                .service(web::scope("/health").configure(register)),
+        )
+        .await;
+        let status = |path: &'static str| {
+            let app = &app;
+            async move {
+                call_service(app, TestRequest::with_uri(path).to_request())
+                    .await
+                    .status()
+            }
+        };
+
+        assert_eq!(status("/health/ready").await, StatusCode::OK);
+
+        consumer.not_ready("broker unreachable");
+        assert_eq!(status("/health").await, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            status("/health/ready").await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(status("/health/live").await, StatusCode::OK);
+    }
+
+    #[ntex::test]
+    async fn test_readiness_follows_critical_dependencies() {
+        let state = FoxtiveNtexState {
+            allowed_methods: vec![Method::GET],
+            ..FoxtiveNtexState::for_tests()
+        };
+        state
+            .components
+            .probe(DependencyProbe::new("search", || async {
+                AppMessage::WarningMessage("unreachable").ar()
+            }));
+        state.components.probe(
+            DependencyProbe::new("postgres", || async {
+                AppMessage::WarningMessage("connection refused").ar()
+            })
+            .critical(),
+        );
+
+        let app = init_service(
+            App::new()
+                .state(state)
+                .service(web::scope("/health").configure(register)),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::with_uri("/health/ready").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["data"]["dependencies"][1]["name"], "postgres");
+        assert_eq!(
+            body["data"]["dependencies"][1]["detail"],
+            "connection refused"
+        );
+
+        let resp = call_service(&app, TestRequest::with_uri("/health/live").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use crate::http::middlewares::MiddlewareChains;
+use ntex::web;
+use ntex::web::{HttpResponse, ServiceConfig};
+
+/// Registers `GET` rendering the middleware and policy chain of every route group in
+/// execution order, with per-layer timings when `ServerConfig::middleware_timings` is
+/// enabled. Layers declared but never wrapped around their group are flagged `skipped`.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::admin::middleware_chains;
+/// use foxtive_ntex::http::kernel::Controller;
+///
+/// let controller = Controller::new("/system/middleware-chains", middleware_chains::register);
+/// ```
+pub fn register(cfg: &mut ServiceConfig) {
+    cfg.route("", web::get().to(show));
+}
+
+async fn show() -> HttpResponse {
+    Responder::send(MiddlewareChains::snapshot(), ResponseCode::Ok)
+}
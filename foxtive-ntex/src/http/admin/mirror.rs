@@ -0,0 +1,70 @@
+use crate::FoxtiveNtexState;
+use crate::http::HttpResult;
+use crate::http::response::ext::StructResponseExt;
+use foxtive::prelude::AppMessage;
+use ntex::http::StatusCode;
+use ntex::web;
+use ntex::web::ServiceConfig;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct MirrorQuery {
+    limit: Option<usize>,
+    path: Option<String>,
+}
+
+/// Registers `GET` listing the exchanges kept by the
+/// [`RequestMirror`](crate::helpers::mirror::RequestMirror), newest first (`?limit=`, 50 by
+/// default, and `?path=` prefix filter), `GET /{id}` rendering one and `DELETE` forgetting
+/// them. Answers 404 unless `ServerConfig::request_mirror` is enabled, which it never is in
+/// production; the bodies may still hold personal data, mount it behind authentication.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::admin::mirror;
+/// use foxtive_ntex::http::kernel::Controller;
+///
+/// let controller = Controller::new("/system/mirror", mirror::register);
+/// ```
+pub fn register(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .route(web::get().to(list))
+            .route(web::delete().to(clear)),
+    )
+    .route("/{id}", web::get().to(show));
+}
+
+fn ensure_enabled(state: &FoxtiveNtexState) -> Result<(), AppMessage> {
+    match state.mirror.is_enabled() {
+        true => Ok(()),
+        false => Err(AppMessage::ErrorMessage(
+            "Request mirroring is disabled".to_string(),
+            StatusCode::NOT_FOUND,
+        )),
+    }
+}
+
+async fn list(
+    state: web::types::State<FoxtiveNtexState>,
+    query: web::types::Query<MirrorQuery>,
+) -> HttpResult {
+    ensure_enabled(&state)?;
+    let limit = query.limit.unwrap_or(50).min(500);
+    let exchanges = state.mirror.recent(limit, query.path.as_deref());
+    exchanges.respond()
+}
+
+async fn show(state: web::types::State<FoxtiveNtexState>, id: web::types::Path<u64>) -> HttpResult {
+    ensure_enabled(&state)?;
+    match state.mirror.get(id.into_inner()) {
+        Some(exchange) => exchange.respond(),
+        None => Err(AppMessage::EntityNotFound("exchange".to_string()).into()),
+    }
+}
+
+async fn clear(state: web::types::State<FoxtiveNtexState>) -> HttpResult {
+    ensure_enabled(&state)?;
+    state.mirror.clear();
+    ().respond_msg("Mirrored requests cleared")
+}
@@ -0,0 +1,14 @@
+//! Controllers for administrative endpoints.
+//!
+//! These endpoints are not registered automatically, mount them in a route group
+//! guarded by your own authentication middlewares.
+
+pub mod build_info;
+pub mod health;
+pub mod middleware_chains;
+#[cfg(feature = "dev-tools")]
+pub mod mirror;
+pub mod runtime_settings;
+pub mod stats;
+#[cfg(feature = "metrics")]
+pub mod upload_metrics;
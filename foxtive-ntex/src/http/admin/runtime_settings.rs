@@ -0,0 +1,86 @@
+use crate::FoxtiveNtexState;
+use crate::http::HttpResult;
+use crate::http::extractors::JsonBody;
+use crate::http::response::ext::StructResponseExt;
+use crate::setup::runtime_settings::Settings;
+use foxtive::prelude::AppMessage;
+use ntex::web;
+use ntex::web::ServiceConfig;
+use tracing::info;
+
+/// Registers `GET` (read) and `PUT` (replace) endpoints for the runtime settings store.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::admin::runtime_settings;
+/// use foxtive_ntex::http::kernel::Controller;
+///
+/// let controller = Controller::new("/settings", runtime_settings::register);
+/// ```
+pub fn register(cfg: &mut ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .route(web::get().to(show))
+            .route(web::put().to(update)),
+    );
+}
+
+async fn show(state: web::types::State<FoxtiveNtexState>) -> HttpResult {
+    Settings::clone(&state.runtime_settings.current()).respond()
+}
+
+async fn update(state: web::types::State<FoxtiveNtexState>, body: JsonBody) -> HttpResult {
+    let settings = body.deserialize::<Settings>().map_err(|err| {
+        AppMessage::WarningMessageString(format!("Invalid settings payload: {err}"))
+    })?;
+
+    if !(0.0..=1.0).contains(&settings.log_sample_rate) {
+        return Err(AppMessage::WarningMessageString(format!(
+            "Invalid settings payload: log_sample_rate must be between 0.0 and 1.0, got {}",
+            settings.log_sample_rate
+        ))
+        .into());
+    }
+
+    info!("[runtime-settings] updating settings: {settings:?}");
+    state.runtime_settings.replace(settings.clone());
+
+    settings.respond_msg("Settings updated")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::http::StatusCode;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+
+    #[ntex::test]
+    async fn test_update_rejects_out_of_range_log_sample_rate() {
+        let state = FoxtiveNtexState::for_tests();
+        let app = init_service(
+            App::new()
+                .state(state.clone())
+                .service(web::scope("/settings").configure(register)),
+        )
+        .await;
+
+        let put = |rate: f64| {
+            TestRequest::put()
+                .uri("/settings")
+                .header("content-type", "application/json")
+                .set_payload(format!("{{\"log_sample_rate\": {rate}}}"))
+                .to_request()
+        };
+
+        let resp = call_service(&app, put(1.5)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let resp = call_service(&app, put(-0.1)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(state.runtime_settings.current().log_sample_rate, 1.0);
+
+        let resp = call_service(&app, put(0.25)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(state.runtime_settings.current().log_sample_rate, 0.25);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::FoxtiveNtexState;
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use ntex::web;
+use ntex::web::{HttpResponse, ServiceConfig};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct StatsQuery {
+    top: Option<usize>,
+}
+
+/// Registers `GET` rendering the rolling-window [`RequestStats`](crate::helpers::stats::RequestStats)
+/// collected when `ServerConfig::request_stats` is enabled, `?top=` sets how many routes
+/// each ranking lists (10 by default).
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::admin::stats;
+/// use foxtive_ntex::http::kernel::Controller;
+///
+/// let controller = Controller::new("/system/stats", stats::register);
+/// ```
+pub fn register(cfg: &mut ServiceConfig) {
+    cfg.route("", web::get().to(show));
+}
+
+async fn show(
+    state: web::types::State<FoxtiveNtexState>,
+    query: web::types::Query<StatsQuery>,
+) -> HttpResponse {
+    let top = query.top.unwrap_or(10).min(100);
+    Responder::send(state.stats.snapshot(top), ResponseCode::Ok)
+}
@@ -0,0 +1,23 @@
+use crate::helpers::upload_metrics::UploadMetrics;
+use ntex::web;
+use ntex::web::{HttpResponse, ServiceConfig};
+
+/// Registers `GET` rendering the multipart upload metrics in the Prometheus text format,
+/// for a scraper to collect.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::admin::upload_metrics;
+/// use foxtive_ntex::http::kernel::Controller;
+///
+/// let controller = Controller::new("/system/metrics/uploads", upload_metrics::register);
+/// ```
+pub fn register(cfg: &mut ServiceConfig) {
+    cfg.route("", web::get().to(show));
+}
+
+async fn show() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(UploadMetrics::global().render())
+}
@@ -0,0 +1,410 @@
+use ntex::http::header::{self, HeaderValue};
+use ntex::http::{Method, StatusCode};
+use ntex::util::Bytes;
+use ntex::web;
+use ntex::web::{HttpRequest, HttpResponse, ServiceConfig};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Content encodings an asset can be stored in, in order of preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetEncoding {
+    Brotli,
+    Gzip,
+}
+
+impl AssetEncoding {
+    fn token(&self) -> &'static str {
+        match self {
+            AssetEncoding::Brotli => "br",
+            AssetEncoding::Gzip => "gzip",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct EmbeddedFile {
+    content_type: String,
+    identity: Variant,
+    encoded: Vec<(AssetEncoding, Variant)>,
+}
+
+/// Stored representation of an asset, shared by the responses without copying
+#[derive(Clone)]
+struct Variant {
+    content: Bytes,
+    etag: HeaderValue,
+}
+
+impl Variant {
+    fn new(content: Cow<'static, [u8]>, encoding: Option<AssetEncoding>) -> Self {
+        let content = match content {
+            Cow::Borrowed(content) => Bytes::from_static(content),
+            Cow::Owned(content) => Bytes::from(content),
+        };
+
+        Self {
+            etag: etag(&content, encoding),
+            content,
+        }
+    }
+}
+
+/// Assets compiled into the binary (`include_bytes!`, rust-embed...), served under a path
+/// like [`StaticFileConfig`](crate::http::server::StaticFileConfig) does for a directory.
+///
+/// Responses carry an `ETag` derived from the content (`If-None-Match` gets a
+/// `304 Not Modified`), stable across builds and restarts. Precompressed variants
+/// registered with [`EmbeddedAssets::encoded`] are sent to clients accepting them,
+/// each with its own `ETag`.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::assets::{AssetEncoding, EmbeddedAssets};
+///
+/// let admin = EmbeddedAssets::new("/admin")
+///     .file("index.html", b"<html>...</html>".as_slice())
+///     .file("app.js", b"console.log('admin')".as_slice())
+///     // e.g. produced by the build script
+///     .encoded("app.js", AssetEncoding::Gzip, b"\x1f\x8b...".as_slice())
+///     .spa_fallback(true);
+/// ```
+///
+/// With rust-embed, loop over `Asset::iter()` and pass `Asset::get(name).data` to `file()`.
+#[derive(Clone)]
+pub struct EmbeddedAssets {
+    path: String,
+    files: Arc<HashMap<String, EmbeddedFile>>,
+    index: String,
+    spa_fallback: bool,
+    cache_control: String,
+}
+
+impl EmbeddedAssets {
+    /// Serve the assets under `path`, e.g. `/admin`
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: format!("/{}", path.trim_matches('/')),
+            files: Arc::new(HashMap::new()),
+            index: "index.html".to_string(),
+            spa_fallback: false,
+            cache_control: "no-cache".to_string(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Add an asset, `name` is relative to the mount path (`css/app.css`)
+    pub fn file(mut self, name: &str, content: impl Into<Cow<'static, [u8]>>) -> Self {
+        let name = normalize(name);
+
+        let file = EmbeddedFile {
+            content_type: content_type(&name).to_string(),
+            identity: Variant::new(content.into(), None),
+            encoded: vec![],
+        };
+
+        self.files_mut().insert(name, file);
+        self
+    }
+
+    /// Add the precompressed variant of an asset added with [`EmbeddedAssets::file`]
+    pub fn encoded(
+        mut self,
+        name: &str,
+        encoding: AssetEncoding,
+        content: impl Into<Cow<'static, [u8]>>,
+    ) -> Self {
+        if let Some(file) = self.files_mut().get_mut(&normalize(name)) {
+            file.encoded.retain(|(existing, _)| *existing != encoding);
+            file.encoded
+                .push((encoding, Variant::new(content.into(), Some(encoding))));
+            file.encoded.sort_by_key(|(encoding, _)| *encoding as u8);
+        }
+        self
+    }
+
+    /// File served for the mount path itself and directories, `index.html` by default
+    pub fn index(mut self, name: &str) -> Self {
+        self.index = normalize(name);
+        self
+    }
+
+    /// Serve the index for unknown paths instead of 404, for client-side routed UIs
+    pub fn spa_fallback(mut self, enabled: bool) -> Self {
+        self.spa_fallback = enabled;
+        self
+    }
+
+    /// `Cache-Control` of the responses, `no-cache` (always revalidate) by default
+    pub fn cache_control(mut self, value: &str) -> Self {
+        self.cache_control = value.to_string();
+        self
+    }
+
+    /// Names of the embedded assets
+    pub fn names(&self) -> Vec<&str> {
+        self.files.keys().map(String::as_str).collect()
+    }
+
+    pub fn register(self, cfg: &mut ServiceConfig) {
+        let path = self.path.clone();
+        let assets = Arc::new(self);
+
+        cfg.service(
+            web::scope(&path).default_service(web::to(move |req: HttpRequest| {
+                let assets = assets.clone();
+                async move { assets.respond(&req) }
+            })),
+        );
+    }
+
+    fn files_mut(&mut self) -> &mut HashMap<String, EmbeddedFile> {
+        Arc::make_mut(&mut self.files)
+    }
+
+    fn lookup(&self, path: &str) -> Option<&EmbeddedFile> {
+        let name = normalize(path.strip_prefix(self.path.as_str()).unwrap_or(path));
+
+        let found = match name.is_empty() || name.ends_with('/') {
+            true => self.files.get(&format!("{name}{}", self.index)),
+            false => self.files.get(&name),
+        };
+
+        match found {
+            Some(file) => Some(file),
+            // assets (with an extension) are never substituted
+            None if self.spa_fallback && !name.rsplit('/').next()?.contains('.') => {
+                self.files.get(&self.index)
+            }
+            None => None,
+        }
+    }
+
+    fn respond(&self, req: &HttpRequest) -> HttpResponse {
+        if req.method() != Method::GET && req.method() != Method::HEAD {
+            return HttpResponse::MethodNotAllowed()
+                .header(header::ALLOW, "GET, HEAD")
+                .finish();
+        }
+
+        let file = match self.lookup(req.path()) {
+            Some(file) => file,
+            None => return HttpResponse::NotFound().finish(),
+        };
+
+        let accepted = accepted_encodings(req);
+        let (variant, encoding) = file
+            .encoded
+            .iter()
+            .find(|(encoding, _)| accepted.contains(&encoding.token()))
+            .map(|(encoding, variant)| (variant, Some(encoding)))
+            .unwrap_or((&file.identity, None));
+
+        let mut builder = HttpResponse::build(StatusCode::OK);
+        builder
+            .header(header::ETAG, variant.etag.clone())
+            .header(header::CACHE_CONTROL, self.cache_control.as_str());
+
+        if !file.encoded.is_empty() {
+            builder.header(header::VARY, "Accept-Encoding");
+        }
+
+        let not_modified = req
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|tags| {
+                tags.split(',')
+                    .any(|tag| tag.trim() == "*" || tag.trim() == variant.etag)
+            });
+
+        if not_modified {
+            return builder.status(StatusCode::NOT_MODIFIED).finish();
+        }
+
+        builder.header(header::CONTENT_TYPE, file.content_type.as_str());
+
+        if let Some(encoding) = encoding {
+            builder.header(header::CONTENT_ENCODING, encoding.token());
+        }
+
+        // the body of HEAD responses is dropped by the server
+        builder.body(variant.content.clone())
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim_start_matches('/').to_string()
+}
+
+/// Strong validator from the content hash and length, suffixed with the encoding since
+/// each variant is a different representation
+fn etag(content: &[u8], encoding: Option<AssetEncoding>) -> HeaderValue {
+    let tag = match encoding {
+        Some(encoding) => format!(
+            "\"{:016x}-{:x}-{}\"",
+            fnv1a(content),
+            content.len(),
+            encoding.token()
+        ),
+        None => format!("\"{:016x}-{:x}\"", fnv1a(content), content.len()),
+    };
+    HeaderValue::from_str(&tag).expect("hex etag is a valid header value")
+}
+
+/// 64-bit FNV-1a, unlike `DefaultHasher` its output doesn't change between Rust releases
+fn fnv1a(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Encodings accepted by the client, excluding the ones refused with `q=0`
+fn accepted_encodings(req: &HttpRequest) -> Vec<&str> {
+    req.headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| {
+            value
+                .split(',')
+                .filter_map(|item| {
+                    let mut parts = item.split(';').map(str::trim);
+                    let token = parts.next()?;
+                    let refused = parts.any(|param| {
+                        param
+                            .strip_prefix("q=")
+                            .and_then(|q| q.parse::<f32>().ok())
+                            .is_some_and(|q| q == 0.0)
+                    });
+                    (!refused).then_some(token)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn content_type(name: &str) -> &'static str {
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+
+    fn assets() -> EmbeddedAssets {
+        EmbeddedAssets::new("/admin")
+            .file("index.html", b"<h1>admin</h1>".as_slice())
+            .file("js/app.js", b"console.log(1)".as_slice())
+            .encoded("js/app.js", AssetEncoding::Gzip, b"gzipped".as_slice())
+            .spa_fallback(true)
+    }
+
+    #[ntex::test]
+    async fn test_serves_assets_with_etag_and_encoding() {
+        let assets = assets();
+        let app = init_service(App::new().configure(|cfg| assets.register(cfg))).await;
+
+        let resp = call_service(&app, TestRequest::with_uri("/admin").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(read_body(resp).await, "<h1>admin</h1>");
+
+        let resp = call_service(
+            &app,
+            TestRequest::with_uri("/admin/")
+                .header("if-none-match", etag.to_str().unwrap())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let resp = call_service(
+            &app,
+            TestRequest::with_uri("/admin/js/app.js")
+                .header("accept-encoding", "br;q=0, gzip")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "gzip"
+        );
+        let gzip_etag = resp.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(read_body(resp).await, "gzipped");
+
+        let resp = call_service(&app, TestRequest::with_uri("/admin/js/app.js").to_request()).await;
+        assert!(!resp.headers().contains_key(header::CONTENT_ENCODING));
+        assert_ne!(resp.headers().get(header::ETAG).unwrap(), &gzip_etag);
+        assert_eq!(read_body(resp).await, "console.log(1)");
+
+        // the validator of one representation doesn't match the other
+        let resp = call_service(
+            &app,
+            TestRequest::with_uri("/admin/js/app.js")
+                .header("if-none-match", gzip_etag.to_str().unwrap())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_etag_is_stable() {
+        // reference vectors of the FNV-1a spec
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(etag(b"a", None), "\"af63dc4c8601ec8c-1\"");
+        assert_eq!(
+            etag(b"gzipped", Some(AssetEncoding::Gzip)),
+            format!("\"{:016x}-7-gzip\"", fnv1a(b"gzipped"))
+        );
+    }
+
+    #[ntex::test]
+    async fn test_spa_fallback_skips_missing_assets() {
+        let assets = assets();
+        let app = init_service(App::new().configure(|cfg| assets.register(cfg))).await;
+
+        let resp = call_service(&app, TestRequest::with_uri("/admin/users/42").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, "<h1>admin</h1>");
+
+        let resp = call_service(
+            &app,
+            TestRequest::with_uri("/admin/missing.css").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,407 @@
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use crate::http::middlewares::MatchedRoute;
+use crate::http::{HttpResult, Method};
+use futures_util::StreamExt;
+use ntex::http::Uri;
+use ntex::router::{Path, Router};
+use ntex::util::{Bytes, BytesMut};
+use ntex::web::types::Payload;
+use ntex::web::{HttpRequest, HttpResponse, WebResponseError};
+use std::collections::{BTreeSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use tracing::info;
+
+/// Handler of a runtime-registered route, receives the request and its collected body
+pub type DynamicHandler =
+    Arc<dyn Fn(HttpRequest, Bytes) -> Pin<Box<dyn Future<Output = HttpResult>>> + Send + Sync>;
+
+/// Number of table changes kept by [`DynamicRoutes::history`]
+const MAX_HISTORY: usize = 50;
+
+#[derive(Clone)]
+struct DynamicEndpoint {
+    method: Method,
+    pattern: String,
+    handler: DynamicHandler,
+}
+
+/// Routes added and removed together, e.g. the endpoints of a plugin
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::enums::ResponseCode;
+/// use foxtive_ntex::helpers::responder::Responder;
+/// use foxtive_ntex::http::Method;
+/// use foxtive_ntex::http::dynamic::RouteGroup;
+/// use foxtive_ntex::http::middlewares::MatchedRoute;
+///
+/// let group = RouteGroup::new("billing", "/plugins/billing").route(
+///     Method::GET,
+///     "/invoices/{id}",
+///     |req, _body| async move {
+///         let route = MatchedRoute::from_http_request(&req).unwrap();
+///         let id = route.param("id").unwrap_or_default().to_string();
+///         Ok(Responder::send(id, ResponseCode::Ok))
+///     },
+/// );
+/// ```
+#[derive(Clone)]
+pub struct RouteGroup {
+    name: String,
+    prefix: String,
+    body_limit: usize,
+    endpoints: Vec<DynamicEndpoint>,
+}
+
+impl RouteGroup {
+    pub fn new(name: &str, prefix: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            prefix: prefix.to_string(),
+            body_limit: 256 * 1024,
+            endpoints: vec![],
+        }
+    }
+
+    pub fn route<F, Fut>(mut self, method: Method, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(HttpRequest, Bytes) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HttpResult> + 'static,
+    {
+        self.endpoints.push(DynamicEndpoint {
+            method,
+            pattern: format!("{}{pattern}", self.prefix),
+            handler: Arc::new(move |req, body| Box::pin(handler(req, body))),
+        });
+        self
+    }
+
+    /// Largest body accepted by the group's handlers, 256KiB by default
+    pub fn body_limit(mut self, bytes: usize) -> Self {
+        self.body_limit = bytes;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// `METHOD /full/pattern` of every endpoint
+    pub fn signatures(&self) -> Vec<String> {
+        self.endpoints
+            .iter()
+            .map(|e| format!("{} {}", e.method, e.pattern))
+            .collect()
+    }
+}
+
+/// Change applied to the route table, with the endpoints it added and removed
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteTableChange {
+    pub version: u64,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Immutable snapshot of the dynamic routes, replaced as a whole on every change
+#[derive(Default)]
+pub struct RouteTable {
+    version: u64,
+    groups: Vec<RouteGroup>,
+    router: Option<Router<(usize, usize), Method>>,
+}
+
+impl RouteTable {
+    fn new(version: u64, groups: Vec<RouteGroup>) -> Self {
+        let mut router = Router::build();
+        for (g, group) in groups.iter().enumerate() {
+            for (e, endpoint) in group.endpoints.iter().enumerate() {
+                router.path(endpoint.pattern.as_str(), (g, e)).2 = Some(endpoint.method.clone());
+            }
+        }
+
+        let router = match groups.iter().all(|g| g.endpoints.is_empty()) {
+            true => None,
+            false => Some(router.finish()),
+        };
+
+        Self {
+            version,
+            groups,
+            router,
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn group_names(&self) -> Vec<&str> {
+        self.groups.iter().map(|g| g.name.as_str()).collect()
+    }
+
+    fn signatures(&self) -> BTreeSet<String> {
+        self.groups.iter().flat_map(|g| g.signatures()).collect()
+    }
+
+    /// Find the endpoint serving `method` on `uri`
+    fn resolve(
+        &self,
+        method: &Method,
+        uri: &Uri,
+    ) -> Option<(&RouteGroup, &DynamicEndpoint, MatchedRoute)> {
+        let mut path = Path::new(uri.clone());
+        let ((g, e), _) = self
+            .router
+            .as_ref()?
+            .recognize_checked(&mut path, |_, m| m == Some(method))?;
+
+        let group = &self.groups[*g];
+        let endpoint = &group.endpoints[*e];
+
+        let params = path
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        let route = MatchedRoute::new(&endpoint.pattern, params);
+        Some((group, endpoint, route))
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    table: RwLock<Arc<RouteTable>>,
+    history: RwLock<VecDeque<RouteTableChange>>,
+}
+
+/// Route groups added and removed while the server is running, e.g. by plugins loaded at
+/// runtime.
+///
+/// Requests not matched by the static routes fall through to the default service, which
+/// looks them up here. Every change builds a new [`RouteTable`] swapped in atomically:
+/// in-flight requests keep the snapshot they started with.
+#[derive(Clone, Default)]
+pub struct DynamicRoutes {
+    inner: Arc<Inner>,
+}
+
+impl DynamicRoutes {
+    /// Current snapshot of the table
+    pub fn load(&self) -> Arc<RouteTable> {
+        match self.inner.table.read() {
+            Ok(table) => table.clone(),
+            Err(_) => Arc::new(RouteTable::default()),
+        }
+    }
+
+    /// Add a group, replacing the one registered under the same name
+    pub fn add_group(&self, group: RouteGroup) -> RouteTableChange {
+        self.update(|groups| {
+            groups.retain(|g| g.name != group.name);
+            groups.push(group);
+        })
+    }
+
+    /// Remove a group by name, `None` when no such group is registered
+    pub fn remove_group(&self, name: &str) -> Option<RouteTableChange> {
+        if !self.load().groups.iter().any(|g| g.name == name) {
+            return None;
+        }
+
+        Some(self.update(|groups| groups.retain(|g| g.name != name)))
+    }
+
+    /// Replace every group at once
+    pub fn swap(&self, groups: Vec<RouteGroup>) -> RouteTableChange {
+        self.update(|current| *current = groups)
+    }
+
+    /// Most recent changes, oldest first
+    pub fn history(&self) -> Vec<RouteTableChange> {
+        match self.inner.history.read() {
+            Ok(history) => history.iter().cloned().collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn update<F>(&self, apply: F) -> RouteTableChange
+    where
+        F: FnOnce(&mut Vec<RouteGroup>),
+    {
+        let mut table = self
+            .inner
+            .table
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let mut groups = table.groups.clone();
+        apply(&mut groups);
+
+        let next = RouteTable::new(table.version + 1, groups);
+        let (before, after) = (table.signatures(), next.signatures());
+        let change = RouteTableChange {
+            version: next.version,
+            added: after.difference(&before).cloned().collect(),
+            removed: before.difference(&after).cloned().collect(),
+        };
+
+        info!(
+            "[dynamic-routes] v{}: +{:?} -{:?}",
+            change.version, change.added, change.removed
+        );
+
+        *table = Arc::new(next);
+        drop(table);
+
+        if let Ok(mut history) = self.inner.history.write() {
+            if history.len() == MAX_HISTORY {
+                history.pop_front();
+            }
+            history.push_back(change.clone());
+        }
+
+        change
+    }
+
+    /// Serve the request if a dynamic route matches it, the request is handed back otherwise
+    pub async fn dispatch(
+        &self,
+        req: HttpRequest,
+        mut payload: Payload,
+    ) -> Result<HttpResponse, HttpRequest> {
+        let table = self.load();
+        let (group, endpoint, route) = match table.resolve(req.method(), req.uri()) {
+            None => return Err(req),
+            Some(found) => found,
+        };
+
+        let mut body = BytesMut::new();
+        while let Some(chunk) = payload.next().await {
+            match chunk {
+                Ok(chunk) if body.len() + chunk.len() <= group.body_limit => {
+                    body.extend_from_slice(&chunk)
+                }
+                Ok(_) => {
+                    return Ok(Responder::message(
+                        "Request body is too large",
+                        ResponseCode::PayloadTooLarge,
+                    ));
+                }
+                Err(err) => return Ok(crate::http::HttpError::from(err).error_response(&req)),
+            }
+        }
+
+        req.extensions_mut().insert(route);
+        let handler = endpoint.handler.clone();
+        match handler(req.clone(), body.freeze()).await {
+            Ok(resp) => Ok(resp),
+            Err(err) => Ok(err.error_response(&req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FoxtiveNtexState;
+    use crate::http::kernel::{not_found_service, ntex_default_service};
+    use ntex::http::StatusCode;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+
+    fn plugin(version: &'static str) -> RouteGroup {
+        RouteGroup::new("greeter", "/plugins/greeter")
+            .route(Method::GET, "/{name}", move |req, _| async move {
+                let route = MatchedRoute::from_http_request(&req).unwrap();
+                let name = route.param("name").unwrap_or_default().to_string();
+                Ok(HttpResponse::Ok().body(format!("{version}: hello {name}")))
+            })
+            .route(Method::POST, "/echo", |_, body| async move {
+                Ok(HttpResponse::Ok().body(body))
+            })
+            .body_limit(8)
+    }
+
+    #[ntex::test]
+    async fn test_groups_swapped_at_runtime() {
+        let state = FoxtiveNtexState::for_tests();
+        let routes = state.dynamic_routes.clone();
+        let app = init_service(
+            App::new()
+                .state(state)
+                .default_service(ntex_default_service()),
+        )
+        .await;
+
+        let get = || TestRequest::with_uri("/plugins/greeter/ada").to_request();
+        assert_eq!(
+            call_service(&app, get()).await.status(),
+            StatusCode::NOT_FOUND
+        );
+
+        routes.add_group(plugin("v1"));
+        assert_eq!(
+            read_body(call_service(&app, get()).await).await,
+            "v1: hello ada"
+        );
+
+        let echo = |body: &'static str| {
+            TestRequest::with_uri("/plugins/greeter/echo")
+                .method(Method::POST)
+                .set_payload(body)
+                .to_request()
+        };
+        assert_eq!(read_body(call_service(&app, echo("hi")).await).await, "hi");
+        let resp = call_service(&app, echo("far too long")).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        routes.add_group(plugin("v2"));
+        assert_eq!(
+            read_body(call_service(&app, get()).await).await,
+            "v2: hello ada"
+        );
+
+        assert!(routes.remove_group("greeter").is_some());
+        assert!(routes.remove_group("greeter").is_none());
+        assert_eq!(
+            call_service(&app, get()).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[ntex::test]
+    async fn test_additional_servers_dont_dispatch_groups() {
+        let state = FoxtiveNtexState::for_tests();
+        state.dynamic_routes.add_group(plugin("v1"));
+        let app = init_service(App::new().state(state).default_service(not_found_service())).await;
+
+        let req = TestRequest::with_uri("/plugins/greeter/ada").to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_history_records_diff() {
+        let routes = DynamicRoutes::default();
+        routes.add_group(plugin("v1"));
+        routes.swap(vec![RouteGroup::new("other", "/other").route(
+            Method::GET,
+            "",
+            |_, _| async { Ok(HttpResponse::Ok().finish()) },
+        )]);
+
+        let history = routes.history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].added.len(), 2);
+        assert_eq!(history[1].added, vec!["GET /other"]);
+        assert_eq!(history[1].removed.len(), 2);
+        assert_eq!(routes.load().version(), 2);
+        assert_eq!(routes.load().group_names(), vec!["other"]);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::FoxtiveNtexState;
+use crate::error::HttpError;
+use crate::helpers::worker_pool::WorkerPool;
+use crate::http::kernel::RouteWorkerPool;
+use foxtive::prelude::{AppMessage, AppResult};
+use ntex::http::Payload;
+use ntex::web;
+use ntex::web::error::BlockingError;
+use ntex::web::{FromRequest, HttpRequest};
+use std::convert::Infallible;
+use std::sync::{Mutex, PoisonError};
+use tracing::warn;
+
+/// Runs blocking sections on the worker pool attached to the current controller,
+/// falling back to the shared blocking pool when the controller has none.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::extractors::BlockingPool;
+/// use foxtive_ntex::http::kernel::Controller;
+///
+/// async fn export(pool: BlockingPool) -> String {
+///     let rows = pool.run(|| 42).await.unwrap_or_default();
+///     format!("exported {rows} rows")
+/// }
+///
+/// let controller = Controller::new("/reports", |cfg| {
+///     cfg.route("/export", ntex::web::get().to(export));
+/// })
+/// .worker_pool("reports");
+/// ```
+pub struct BlockingPool {
+    pool: Option<WorkerPool>,
+}
+
+impl BlockingPool {
+    pub fn from_http_request(req: &HttpRequest) -> Self {
+        let name = match req.app_state::<RouteWorkerPool>() {
+            Some(name) => name,
+            None => return Self { pool: None },
+        };
+
+        let pool = req
+            .app_state::<FoxtiveNtexState>()
+            .and_then(|state| state.worker_pools.get(&name.0).cloned());
+
+        if pool.is_none() {
+            warn!(
+                "[blocking-pool] worker pool '{}' is not configured, using the shared pool",
+                name.0
+            );
+        }
+
+        Self { pool }
+    }
+
+    /// Name of the dedicated pool, `None` when using the shared blocking pool
+    pub fn name(&self) -> Option<&str> {
+        self.pool.as_ref().map(|pool| pool.name())
+    }
+
+    pub async fn run<F, T>(&self, func: F) -> AppResult<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        match &self.pool {
+            Some(pool) => pool.run(func).await,
+            // the shared pool wants a `Sync` function, which the mutex makes of any `Send` one
+            None => {
+                let func = Mutex::new(func);
+                web::block(move || {
+                    let func = func.into_inner().unwrap_or_else(PoisonError::into_inner);
+                    Ok::<_, Infallible>(func())
+                })
+                .await
+                .map_err(|err| match err {
+                    BlockingError::Error(never) => match never {},
+                    BlockingError::Canceled => AppMessage::InternalServerError.ae(),
+                })
+            }
+        }
+    }
+}
+
+impl<Err> FromRequest<Err> for BlockingPool {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(BlockingPool::from_http_request(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::worker_pool::WorkerPools;
+    use ntex::web::test::TestRequest;
+
+    fn make_state() -> FoxtiveNtexState {
+        FoxtiveNtexState {
+            worker_pools: WorkerPools::new(vec![WorkerPool::new("reports", 1).unwrap()]),
+            ..FoxtiveNtexState::for_tests()
+        }
+    }
+
+    #[ntex::test]
+    async fn test_uses_route_pool() {
+        let req = TestRequest::default()
+            .state(make_state())
+            .state(RouteWorkerPool("reports".to_string()))
+            .to_http_request();
+
+        let pool = BlockingPool::from_http_request(&req);
+        assert_eq!(pool.name(), Some("reports"));
+
+        let thread_name = pool
+            .run(|| std::thread::current().name().unwrap().to_string())
+            .await
+            .unwrap();
+        assert_eq!(thread_name, "reports-0");
+    }
+
+    #[ntex::test]
+    async fn test_runs_functions_that_arent_sync() {
+        let req = TestRequest::default().state(make_state()).to_http_request();
+        let cell = std::cell::Cell::new(5);
+
+        let pool = BlockingPool::from_http_request(&req);
+        assert_eq!(pool.run(move || cell.get()).await.unwrap(), 5);
+    }
+
+    #[ntex::test]
+    async fn test_falls_back_to_shared_pool() {
+        let req = TestRequest::default().state(make_state()).to_http_request();
+
+        let pool = BlockingPool::from_http_request(&req);
+        assert!(pool.name().is_none());
+        assert_eq!(pool.run(|| 5).await.unwrap(), 5);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::http::server::ServerSettings;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+type Transform = Arc<dyn Fn(&mut Value) + Send + Sync>;
+
+static POLICY: RwLock<BodyNormalization> = RwLock::new(BodyNormalization::new());
+
+/// Normalization applied to JSON bodies before `JsonBody::deserialize`, `DeJsonBody` and
+/// `Dto` deserialize them, configured through `ServerConfig::body_normalization`. Nothing
+/// is normalized by default.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::extractors::BodyNormalization;
+///
+/// let policy = BodyNormalization::new()
+///     .trim()
+///     .empty_as_null()
+///     .transform(|value| {
+///         if let Some(email) = value.get("email").and_then(|email| email.as_str()) {
+///             let email = email.to_lowercase();
+///             value["email"] = email.into();
+///         }
+///     });
+/// ```
+#[derive(Clone, Default)]
+pub struct BodyNormalization {
+    trim: bool,
+    empty_as_null: bool,
+    #[cfg(feature = "unicode")]
+    nfc: bool,
+    transform: Option<Transform>,
+}
+
+impl fmt::Debug for BodyNormalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("BodyNormalization");
+        debug
+            .field("trim", &self.trim)
+            .field("empty_as_null", &self.empty_as_null);
+        #[cfg(feature = "unicode")]
+        debug.field("nfc", &self.nfc);
+        debug.field("transform", &self.transform.is_some()).finish()
+    }
+}
+
+impl BodyNormalization {
+    pub const fn new() -> Self {
+        Self {
+            trim: false,
+            empty_as_null: false,
+            #[cfg(feature = "unicode")]
+            nfc: false,
+            transform: None,
+        }
+    }
+
+    /// Trim the whitespace around every string
+    pub fn trim(mut self) -> Self {
+        self.trim = true;
+        self
+    }
+
+    /// Turn empty strings, after trimming, into `null`, so optional fields read as `None`
+    pub fn empty_as_null(mut self) -> Self {
+        self.empty_as_null = true;
+        self
+    }
+
+    /// Normalize every string to the Unicode normalization form C
+    #[cfg(feature = "unicode")]
+    pub fn nfc(mut self) -> Self {
+        self.nfc = true;
+        self
+    }
+
+    /// Run `transform` on the whole body once the strings are normalized
+    pub fn transform<F>(mut self, transform: F) -> Self
+    where
+        F: Fn(&mut Value) + Send + Sync + 'static,
+    {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+
+    /// Process default, for the apps not started with a `ServerConfig`
+    pub fn set(policy: BodyNormalization) {
+        if let Ok(mut current) = POLICY.write() {
+            *current = policy;
+        }
+    }
+
+    /// Policy of the server receiving the current request, the process default otherwise
+    pub fn current() -> BodyNormalization {
+        if let Some(policy) = ServerSettings::read(|settings| settings.body_normalization.clone()) {
+            return policy;
+        }
+
+        POLICY
+            .read()
+            .map(|policy| policy.clone())
+            .unwrap_or_default()
+    }
+
+    fn normalizes_strings(&self) -> bool {
+        #[cfg(feature = "unicode")]
+        if self.nfc {
+            return true;
+        }
+        self.trim || self.empty_as_null
+    }
+
+    fn is_active(&self) -> bool {
+        self.normalizes_strings() || self.transform.is_some()
+    }
+
+    /// Normalize `value` in place
+    pub fn apply(&self, value: &mut Value) {
+        if self.normalizes_strings() {
+            self.normalize(value);
+        }
+        if let Some(transform) = &self.transform {
+            transform(value);
+        }
+    }
+
+    fn normalize(&self, value: &mut Value) {
+        match value {
+            Value::String(string) => {
+                if self.trim && string.trim().len() != string.len() {
+                    *string = string.trim().to_string();
+                }
+                #[cfg(feature = "unicode")]
+                if self.nfc {
+                    use unicode_normalization::UnicodeNormalization;
+                    *string = string.nfc().collect();
+                }
+                if self.empty_as_null && string.is_empty() {
+                    *value = Value::Null;
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.normalize(item)),
+            Value::Object(fields) => fields.values_mut().for_each(|field| self.normalize(field)),
+            _ => {}
+        }
+    }
+}
+
+/// Deserialize `json` once normalized with the current policy
+pub(crate) fn from_json_str<T: DeserializeOwned>(json: &str) -> serde_json::Result<T> {
+    let policy = BodyNormalization::current();
+    if !policy.is_active() {
+        return serde_json::from_str(json);
+    }
+
+    let mut value = serde_json::from_str(json)?;
+    policy.apply(&mut value);
+    serde_json::from_value(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize_strings() {
+        let mut body = json!({
+            "name": "  Ada ",
+            "nickname": "   ",
+            "tags": [" a ", ""],
+            "age": 36,
+        });
+
+        BodyNormalization::new()
+            .trim()
+            .empty_as_null()
+            .transform(|value| value["normalized"] = true.into())
+            .apply(&mut body);
+
+        assert_eq!(
+            body,
+            json!({
+                "name": "Ada",
+                "nickname": null,
+                "tags": ["a", null],
+                "age": 36,
+                "normalized": true,
+            })
+        );
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_nfc() {
+        let mut body = json!({"name": "Zoe\u{0301}"});
+        BodyNormalization::new().nfc().apply(&mut body);
+        assert_eq!(body["name"], "Zo\u{00e9}");
+    }
+}
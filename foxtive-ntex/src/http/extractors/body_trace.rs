@@ -0,0 +1,109 @@
+use crate::error::HttpError;
+use crate::http::server::ServerSettings;
+use ntex::http::Payload;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Instant;
+use tracing::field::Empty;
+use tracing::{Instrument, Span, info_span, trace};
+
+/// How much the built-in body extractors (`JsonBody`, `DeJsonBody`, `StringBody`,
+/// `ByteBody`, `Multipart`) trace, configured through `ServerConfig::extractor_tracing`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ExtractorTracing {
+    Off,
+    /// a child span per extraction, recording `size`, `elapsed_ms` and `outcome`
+    #[default]
+    Spans,
+    /// spans plus an event per body chunk received
+    Chunks,
+}
+
+static TRACING: AtomicU8 = AtomicU8::new(ExtractorTracing::Spans as u8);
+
+impl ExtractorTracing {
+    /// Process default, for the apps not started with a `ServerConfig`
+    pub fn set(level: ExtractorTracing) {
+        TRACING.store(level as u8, Ordering::Relaxed);
+
+        #[cfg(feature = "multipart")]
+        foxtive_ntex_multipart::Multipart::set_tracing(level != ExtractorTracing::Off);
+    }
+
+    /// Level of the server handling the current request, the process default otherwise
+    pub fn current() -> ExtractorTracing {
+        if let Some(level) = ServerSettings::read(|settings| settings.extractor_tracing) {
+            return level;
+        }
+
+        match TRACING.load(Ordering::Relaxed) {
+            0 => ExtractorTracing::Off,
+            1 => ExtractorTracing::Spans,
+            _ => ExtractorTracing::Chunks,
+        }
+    }
+}
+
+/// Run an extraction inside an `extractor` span, recording how long it took and how it ended
+pub(crate) async fn traced<T, E, F>(extractor: &'static str, extraction: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    if ExtractorTracing::current() == ExtractorTracing::Off {
+        return extraction.await;
+    }
+
+    let span = info_span!(
+        "extractor",
+        extractor,
+        size = Empty,
+        elapsed_ms = Empty,
+        outcome = Empty
+    );
+
+    let started = Instant::now();
+    let result = extraction.instrument(span.clone()).await;
+
+    span.record("elapsed_ms", started.elapsed().as_secs_f64() * 1000.0);
+    match &result {
+        Ok(_) => span.record("outcome", "ok"),
+        Err(err) => span.record("outcome", tracing::field::display(err)),
+    };
+
+    result
+}
+
+/// Read the whole payload, recording its size on the current extractor span
+pub(crate) async fn read_payload(payload: &mut Payload) -> Result<Vec<u8>, HttpError> {
+    let chunks = ExtractorTracing::current() == ExtractorTracing::Chunks;
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = ntex::util::stream_recv(payload).await {
+        let chunk = chunk?;
+        if chunks {
+            trace!(len = chunk.len(), "body chunk");
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Span::current().record("size", bytes.len());
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::test::TestRequest;
+
+    #[ntex::test]
+    async fn test_reads_payload_inside_span() {
+        let (_, mut payload) = TestRequest::default()
+            .set_payload("hello world")
+            .to_http_parts();
+
+        let body = traced("test", read_payload(&mut payload)).await.unwrap();
+        assert_eq!(body, b"hello world");
+    }
+}
@@ -1,7 +1,7 @@
 use crate::error::HttpError;
+use crate::http::extractors::body_trace::{read_payload, traced};
 use foxtive::prelude::{AppMessage, AppResult};
 use ntex::http::Payload;
-use ntex::util::BytesMut;
 use ntex::web::{FromRequest, HttpRequest};
 use tracing::debug;
 
@@ -66,15 +66,10 @@ impl<Err> FromRequest<Err> for ByteBody {
     type Error = HttpError;
 
     async fn from_request(_req: &HttpRequest, payload: &mut Payload) -> Result<Self, Self::Error> {
-        let mut bytes = BytesMut::new();
-        while let Some(chunk) = ntex::util::stream_recv(payload).await {
-            bytes.extend_from_slice(&chunk?);
-        }
+        let bytes = traced("ByteBody", read_payload(payload)).await?;
 
         debug!("[byte-body] {} bytes", bytes.len());
-        Ok(Self {
-            bytes: bytes.to_vec(),
-        })
+        Ok(Self { bytes })
     }
 }
 
@@ -0,0 +1,129 @@
+use crate::error::HttpError;
+use futures_util::future::{Either, select};
+use ntex::http::Payload;
+use ntex::web::{FromRequest, HttpRequest};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Cancelled when the client goes away before the response is sent.
+///
+/// Handler futures are dropped on disconnect, but work they handed off (blocking pools,
+/// spawned tasks, downstream calls) keeps running unless it watches this token.
+/// The token is attached by the server to every request; outside of it (tests), the
+/// extracted token is simply never cancelled.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::extractors::CancellationToken;
+///
+/// async fn report(token: CancellationToken) -> String {
+///     let rows = token
+///         .run_until_cancelled(async { vec![1, 2, 3] /* expensive query */ })
+///         .await
+///         .unwrap_or_default();
+///
+///     format!("{} rows", rows.len())
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the token attached to the request, creating one if it does not exist yet
+    pub fn from_http_request(req: &HttpRequest) -> Self {
+        if let Some(token) = req.extensions().get::<CancellationToken>() {
+            return token.clone();
+        }
+
+        let token = CancellationToken::new();
+        req.extensions_mut().insert(token.clone());
+        token
+    }
+
+    pub fn cancel(&self) {
+        if !self.state.cancelled.swap(true, Ordering::SeqCst) {
+            self.state.notify.notify_waiters();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the token is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Run `fut` unless the token gets cancelled first, `None` when it does
+    pub async fn run_until_cancelled<F: Future>(&self, fut: F) -> Option<F::Output> {
+        if self.is_cancelled() {
+            return None;
+        }
+
+        match select(pin!(fut), pin!(self.cancelled())).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+impl<Err> FromRequest<Err> for CancellationToken {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(CancellationToken::from_http_request(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[ntex::test]
+    async fn test_cancel_wakes_waiters() {
+        let token = CancellationToken::new();
+        let waiter = token.clone();
+
+        let (output, _) = futures_util::future::join(
+            waiter.run_until_cancelled(ntex::time::sleep(Duration::from_secs(5))),
+            async {
+                ntex::time::sleep(Duration::from_millis(10)).await;
+                token.cancel();
+            },
+        )
+        .await;
+
+        assert!(output.is_none());
+        assert!(waiter.is_cancelled());
+        assert_eq!(waiter.run_until_cancelled(async { 1 }).await, None);
+        assert_eq!(
+            CancellationToken::new()
+                .run_until_cancelled(async { 1 })
+                .await,
+            Some(1)
+        );
+    }
+}
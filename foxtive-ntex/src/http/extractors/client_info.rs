@@ -1,8 +1,10 @@
 use crate::error::HttpError;
 use crate::helpers::request::RequestHelper;
+use crate::http::extractors::RequestMemo;
 use ntex::http::Payload;
 use ntex::web::{FromRequest, HttpRequest};
 
+#[derive(Debug, Clone)]
 pub struct ClientInfo {
     pub ip: Option<String>,
     pub ua: Option<String>,
@@ -18,9 +20,9 @@ impl<Err> FromRequest<Err> for ClientInfo {
     type Error = HttpError;
 
     async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
-        Ok(ClientInfo {
+        Ok(req.memoized_or_insert_with(|| ClientInfo {
             ip: req.ip(),
             ua: req.user_agent(),
-        })
+        }))
     }
 }
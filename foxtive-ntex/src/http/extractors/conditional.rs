@@ -0,0 +1,258 @@
+use crate::enums::ResponseCode;
+use crate::error::HttpError;
+use crate::helpers::responder::Responder;
+use crate::http::HttpResult;
+use chrono::{DateTime, Utc};
+use ntex::http::header::{self, HeaderValue};
+use ntex::http::{Method, Payload};
+use ntex::web::{FromRequest, HttpRequest, HttpResponse};
+use serde::Serialize;
+use std::fmt::Display;
+
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Version of a resource (revision number, hash, update time...) from which the `ETag`
+/// and `Last-Modified` validators of its responses are derived
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceVersion {
+    tag: String,
+    modified: Option<DateTime<Utc>>,
+}
+
+impl ResourceVersion {
+    pub fn tag(version: impl Display) -> Self {
+        Self {
+            tag: version.to_string(),
+            modified: None,
+        }
+    }
+
+    /// Update time, used for both validators
+    pub fn modified(at: DateTime<Utc>) -> Self {
+        Self {
+            tag: at.timestamp_micros().to_string(),
+            modified: Some(at),
+        }
+    }
+
+    /// Also send `Last-Modified`
+    pub fn last_modified(mut self, at: DateTime<Utc>) -> Self {
+        self.modified = Some(at);
+        self
+    }
+
+    /// Weak validator, the JSON rendering of a version may vary (envelope, serializer)
+    pub fn etag(&self) -> String {
+        let tag: String = self
+            .tag
+            .chars()
+            .filter(|char| char.is_ascii_graphic() && *char != '"')
+            .collect();
+        format!("W/\"{tag}\"")
+    }
+
+    fn headers(&self) -> Vec<(header::HeaderName, HeaderValue)> {
+        let mut headers = vec![];
+        if let Ok(etag) = HeaderValue::from_str(&self.etag()) {
+            headers.push((header::ETAG, etag));
+        }
+        if let Some(modified) = self.modified
+            && let Ok(value) = HeaderValue::from_str(&modified.format(HTTP_DATE).to_string())
+        {
+            headers.push((header::LAST_MODIFIED, value));
+        }
+        headers
+    }
+}
+
+macro_rules! version_from {
+    ($($ty:ty),*) => {
+        $(impl From<$ty> for ResourceVersion {
+            fn from(version: $ty) -> Self {
+                ResourceVersion::tag(version)
+            }
+        })*
+    };
+}
+
+version_from!(i32, i64, u32, u64, usize, &str, String);
+
+impl From<DateTime<Utc>> for ResourceVersion {
+    fn from(at: DateTime<Utc>) -> Self {
+        ResourceVersion::modified(at)
+    }
+}
+
+/// Conditional request headers (`If-None-Match`, `If-Modified-Since`), answering with
+/// `304 Not Modified` when the client's copy is still current.
+///
+/// A lighter alternative to response caching for endpoints knowing the version of what
+/// they return. Only `GET` and `HEAD` requests are answered with 304.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::HttpResult;
+/// use foxtive_ntex::http::extractors::Conditional;
+/// # struct Article { revision: u64 }
+/// # fn load_revision(_id: u64) -> u64 { 3 }
+/// # fn load(_id: u64) -> Article { Article { revision: 3 } }
+///
+/// async fn show(conditional: Conditional) -> HttpResult {
+///     // cheap check first, the article is only loaded when the client needs it
+///     if let Some(not_modified) = conditional.not_modified(load_revision(1)) {
+///         return Ok(not_modified);
+///     }
+///
+///     let article = load(1);
+///     conditional.respond_versioned(article.revision, article.revision)
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Conditional {
+    if_none_match: Option<String>,
+    if_modified_since: Option<DateTime<Utc>>,
+    cacheable: bool,
+}
+
+impl Conditional {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+
+        Self {
+            if_none_match: header(header::IF_NONE_MATCH),
+            if_modified_since: header(header::IF_MODIFIED_SINCE)
+                .and_then(|date| DateTime::parse_from_rfc2822(&date).ok())
+                .map(|date| date.with_timezone(&Utc)),
+            cacheable: matches!(*req.method(), Method::GET | Method::HEAD),
+        }
+    }
+
+    /// Whether the client already has `version`
+    pub fn is_current(&self, version: &ResourceVersion) -> bool {
+        if !self.cacheable {
+            return false;
+        }
+
+        // If-Modified-Since is ignored when If-None-Match is sent
+        if let Some(tags) = &self.if_none_match {
+            let etag = version.etag();
+            let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+            return tags
+                .split(',')
+                .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(&etag));
+        }
+
+        match (self.if_modified_since, version.modified) {
+            (Some(since), Some(modified)) => modified.timestamp() <= since.timestamp(),
+            _ => false,
+        }
+    }
+
+    /// `304 Not Modified` when the client already has `version`
+    pub fn not_modified(&self, version: impl Into<ResourceVersion>) -> Option<HttpResponse> {
+        let version = version.into();
+        if !self.is_current(&version) {
+            return None;
+        }
+
+        let mut builder = HttpResponse::NotModified();
+        for (name, value) in version.headers() {
+            builder.header(name, value);
+        }
+        Some(builder.finish())
+    }
+
+    /// Send `data` with its validators, or `304 Not Modified` when the client has it
+    pub fn respond_versioned<T: Serialize>(
+        &self,
+        data: T,
+        version: impl Into<ResourceVersion>,
+    ) -> HttpResult {
+        let version = version.into();
+        if let Some(not_modified) = self.not_modified(version.clone()) {
+            return Ok(not_modified);
+        }
+
+        let mut response = Responder::send(data, ResponseCode::Ok);
+        for (name, value) in version.headers() {
+            response.headers_mut().insert(name, value);
+        }
+        Ok(response)
+    }
+}
+
+impl<Err> FromRequest<Err> for Conditional {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(Conditional::from_request(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use ntex::http::StatusCode;
+    use ntex::web::test::TestRequest;
+
+    fn conditional(headers: &[(&'static str, &str)]) -> Conditional {
+        let mut req = TestRequest::default();
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        Conditional::from_request(&req.to_http_request())
+    }
+
+    #[test]
+    fn test_etag_match() {
+        let fresh = conditional(&[("if-none-match", "\"1\", W/\"7\"")]);
+        let response = fresh.respond_versioned("article", 7u64).unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(header::ETAG).unwrap(), "W/\"7\"");
+
+        let response = fresh.respond_versioned("article", 8u64).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(header::ETAG).unwrap(), "W/\"8\"");
+
+        let post = Conditional::from_request(
+            &TestRequest::default()
+                .method(Method::POST)
+                .header("if-none-match", "*")
+                .to_http_request(),
+        );
+        assert!(post.not_modified(7u64).is_none());
+    }
+
+    #[test]
+    fn test_modified_since() {
+        let updated = Utc.with_ymd_and_hms(2026, 3, 1, 10, 30, 0).unwrap();
+        let version = ResourceVersion::modified(updated);
+
+        let response = conditional(&[])
+            .respond_versioned("doc", version.clone())
+            .unwrap();
+        assert_eq!(
+            response.headers().get(header::LAST_MODIFIED).unwrap(),
+            "Sun, 01 Mar 2026 10:30:00 GMT"
+        );
+
+        let since = conditional(&[("if-modified-since", "Sun, 01 Mar 2026 10:30:00 GMT")]);
+        assert!(since.is_current(&version));
+
+        let older = conditional(&[("if-modified-since", "Sat, 28 Feb 2026 10:30:00 GMT")]);
+        assert!(!older.is_current(&version));
+
+        // If-None-Match wins over If-Modified-Since
+        let both = conditional(&[
+            ("if-none-match", "W/\"other\""),
+            ("if-modified-since", "Sun, 01 Mar 2026 10:30:00 GMT"),
+        ]);
+        assert!(!both.is_current(&version));
+    }
+}
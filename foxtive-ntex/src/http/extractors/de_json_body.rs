@@ -1,7 +1,8 @@
 use crate::error::HttpError;
+use crate::http::extractors::body_normalization::from_json_str;
+use crate::http::extractors::body_trace::{read_payload, traced};
 use foxtive::prelude::AppMessage;
 use ntex::http::Payload;
-use ntex::util::BytesMut;
 use ntex::web::{FromRequest, HttpRequest};
 use serde::de::DeserializeOwned;
 use std::ops;
@@ -25,7 +26,7 @@ impl<T: DeserializeOwned> DeJsonBody<T> {
     /// # Errors
     /// Returns an error if the JSON string cannot be deserialized into the target type T.
     pub fn new(json: String) -> Result<DeJsonBody<T>, HttpError> {
-        let t = serde_json::from_str::<T>(&json)
+        let t = from_json_str::<T>(&json)
             .map_err(|e| AppMessage::WarningMessageString(e.to_string()))?;
 
         Ok(DeJsonBody(json, t))
@@ -94,16 +95,12 @@ impl<T: DeserializeOwned, Err> FromRequest<Err> for DeJsonBody<T> {
         _req: &HttpRequest,
         payload: &mut Payload,
     ) -> Result<DeJsonBody<T>, Self::Error> {
-        let mut bytes = BytesMut::new();
-        while let Some(item) = ntex::util::stream_recv(payload).await {
-            bytes.extend_from_slice(&item?);
-        }
-
-        let raw = String::from_utf8(bytes.to_vec())?;
-
-        debug!("[json-body] {raw}");
-
-        Self::new(raw)
+        traced("DeJsonBody", async {
+            let raw = String::from_utf8(read_payload(payload).await?)?;
+            debug!("[json-body] {raw}");
+            Self::new(raw)
+        })
+        .await
     }
 }
 
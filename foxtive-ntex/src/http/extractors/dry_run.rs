@@ -0,0 +1,202 @@
+use crate::contracts::ResponseCodeContract;
+use crate::error::HttpError;
+use crate::helpers::json_message::JsonMessage;
+use crate::helpers::responder::Responder;
+use foxtive::prelude::AppMessage;
+use ntex::http::header::{HeaderName, HeaderValue};
+use ntex::http::{Payload, Response};
+use ntex::web::{FromRequest, HttpRequest};
+use serde::Serialize;
+use std::future::Future;
+
+/// Query parameter asking for a dry run
+pub const DRY_RUN_PARAM: &str = "dry_run";
+
+/// Header asking for a dry run, echoed on the responses sent through [`DryRun::send`]
+pub const DRY_RUN_HEADER: &str = "x-dry-run";
+
+/// Whether the client asked destructive endpoints to validate and report what would
+/// happen without committing, through `?dry_run=true` or `X-Dry-Run: true`.
+///
+/// Other values than `true`/`1`/`yes` and `false`/`0`/`no` are answered with 400.
+///
+/// # Example
+/// ```
+/// use foxtive::prelude::AppResult;
+/// use foxtive_ntex::enums::ResponseCode;
+/// use foxtive_ntex::http::extractors::DryRun;
+/// use ntex::http::Response;
+///
+/// async fn begin() -> AppResult<()> { Ok(()) }
+/// async fn delete_users() -> AppResult<Vec<u64>> { Ok(vec![4, 8]) }
+/// async fn commit() -> AppResult<()> { Ok(()) }
+/// async fn rollback() -> AppResult<()> { Ok(()) }
+///
+/// async fn purge(dry_run: DryRun) -> AppResult<Response> {
+///     begin().await?;
+///     let deleted = delete_users().await?;
+///     dry_run.settle(commit(), rollback()).await?;
+///     Ok(dry_run.send(deleted, ResponseCode::Ok))
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DryRun(bool);
+
+impl DryRun {
+    pub fn new(active: bool) -> Self {
+        Self(active)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.0
+    }
+
+    /// Read the dry-run flag of `req`, the query parameter taking precedence over the header
+    pub fn from_http_request(req: &HttpRequest) -> Result<DryRun, HttpError> {
+        if let Some(dry_run) = req.extensions().get::<DryRun>() {
+            return Ok(*dry_run);
+        }
+
+        let from_query = req.query_string().split('&').find_map(|pair| {
+            match pair.split_once('=').unwrap_or((pair, "true")) {
+                (DRY_RUN_PARAM, value) => Some(value.to_string()),
+                _ => None,
+            }
+        });
+        let requested = from_query.or_else(|| {
+            req.headers()
+                .get(DRY_RUN_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        });
+
+        let dry_run = match requested.as_deref().map(str::trim) {
+            None => DryRun(false),
+            Some("true" | "1" | "yes") => DryRun(true),
+            Some("false" | "0" | "no") => DryRun(false),
+            Some(value) => {
+                return Err(AppMessage::WarningMessageString(format!(
+                    "Invalid dry run flag '{value}', expected true or false"
+                ))
+                .into());
+            }
+        };
+
+        req.extensions_mut().insert(dry_run);
+        Ok(dry_run)
+    }
+
+    /// Run `rollback` during a dry run, `commit` otherwise
+    pub async fn settle<T, E>(
+        &self,
+        commit: impl Future<Output = Result<T, E>>,
+        rollback: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        match self.0 {
+            true => rollback.await,
+            false => commit.await,
+        }
+    }
+
+    /// Same as `Responder::send`, marking the envelope with `"dry_run": true` and the
+    /// response with `X-Dry-Run: true` during a dry run
+    pub fn send<C, D>(&self, data: D, code: C) -> Response
+    where
+        C: ResponseCodeContract,
+        D: Serialize,
+    {
+        if !self.0 {
+            return Responder::send(data, code);
+        }
+
+        let envelope = JsonMessage::make(data, code.code(), code.success(), None);
+        let mut envelope = match serde_json::to_value(envelope) {
+            Ok(envelope) => envelope,
+            Err(err) => {
+                tracing::error!("[dry-run] failed to serialize response data: {err}");
+                return Responder::internal_server_error();
+            }
+        };
+        if let Some(fields) = envelope.as_object_mut() {
+            fields.insert(DRY_RUN_PARAM.to_string(), true.into());
+        }
+
+        let mut resp =
+            Responder::with_code_headers(Responder::respond(envelope, code.status()), &code);
+        resp.headers_mut().insert(
+            HeaderName::from_static(DRY_RUN_HEADER),
+            HeaderValue::from_static("true"),
+        );
+        resp
+    }
+}
+
+impl<Err> FromRequest<Err> for DryRun {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _: &mut Payload) -> Result<DryRun, HttpError> {
+        DryRun::from_http_request(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::ResponseCode;
+    use ntex::http::StatusCode;
+    use ntex::http::body::{Body, ResponseBody};
+    use ntex::web::WebResponseError;
+    use ntex::web::test::TestRequest;
+
+    #[test]
+    fn test_flag_from_query_or_header() {
+        let dry_run = |req: TestRequest| DryRun::from_http_request(&req.to_http_request());
+
+        assert!(
+            dry_run(TestRequest::with_uri("/?dry_run=true"))
+                .unwrap()
+                .is_active()
+        );
+        assert!(
+            dry_run(TestRequest::with_uri("/?page=2&dry_run"))
+                .unwrap()
+                .is_active()
+        );
+        assert!(
+            !dry_run(TestRequest::with_uri("/?dry_run=0"))
+                .unwrap()
+                .is_active()
+        );
+        assert!(!dry_run(TestRequest::with_uri("/")).unwrap().is_active());
+        assert!(
+            dry_run(TestRequest::with_uri("/").header(DRY_RUN_HEADER, "yes"))
+                .unwrap()
+                .is_active()
+        );
+
+        let err = dry_run(TestRequest::with_uri("/?dry_run=maybe")).unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[ntex::test]
+    async fn test_settle_and_mark() {
+        let commit = async { Ok::<_, ()>("committed") };
+        let rollback = async { Ok::<_, ()>("rolled back") };
+        assert_eq!(
+            DryRun::new(true).settle(commit, rollback).await,
+            Ok("rolled back")
+        );
+
+        let resp = DryRun::new(true).send(vec![4, 8], ResponseCode::Ok);
+        assert_eq!(resp.headers().get(DRY_RUN_HEADER).unwrap(), "true");
+        let ResponseBody::Body(Body::Bytes(bytes)) = resp.body() else {
+            panic!("expected a buffered body");
+        };
+        let body: serde_json::Value = serde_json::from_slice(bytes).unwrap();
+        assert_eq!(body["dry_run"], true);
+        assert_eq!(body["data"], serde_json::json!([4, 8]));
+
+        let resp = DryRun::new(false).send(vec![4, 8], ResponseCode::Ok);
+        assert!(!resp.headers().contains_key(DRY_RUN_HEADER));
+    }
+}
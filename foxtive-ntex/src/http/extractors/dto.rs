@@ -0,0 +1,118 @@
+use crate::contracts::{DtoErrors, RequestDto};
+use crate::error::HttpError;
+use crate::http::extractors::DeJsonBody;
+use ntex::http::Payload;
+use ntex::web::{FromRequest, HttpRequest};
+use std::ops;
+
+/// JSON body sanitized, validated and authorized through its [`RequestDto`] implementation
+/// before reaching the handler.
+///
+/// Validation failures are answered with 400 and the errors of every field, authorization
+/// failures with the error returned by `authorize`.
+pub struct Dto<T: RequestDto>(pub T);
+
+impl<T: RequestDto> Dto<T> {
+    /// Run the sanitize, validate and authorize phases on an already parsed value
+    pub fn process(mut value: T, req: &HttpRequest) -> Result<Dto<T>, HttpError> {
+        value.sanitize();
+
+        let mut errors = DtoErrors::default();
+        value.validate(&mut errors);
+        if !errors.is_empty() {
+            return Err(HttpError::DtoError(errors));
+        }
+
+        value.authorize(req)?;
+        Ok(Dto(value))
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: RequestDto, Err> FromRequest<Err> for Dto<T> {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, payload: &mut Payload) -> Result<Dto<T>, HttpError> {
+        let body = <DeJsonBody<T> as FromRequest<Err>>::from_request(req, payload).await?;
+        Self::process(body.into_inner(), req)
+    }
+}
+
+impl<T: RequestDto> ops::Deref for Dto<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxtive::prelude::{AppMessage, AppResult};
+    use ntex::http::StatusCode;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+    use ntex::web::{self, App, HttpResponse};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Invite {
+        email: String,
+        role: String,
+    }
+
+    impl RequestDto for Invite {
+        fn sanitize(&mut self) {
+            self.email = self.email.trim().to_lowercase();
+        }
+
+        fn validate(&self, errors: &mut DtoErrors) {
+            errors.check(!self.email.contains('@'), "email", "must be an email");
+            errors.check(self.role.is_empty(), "role", "is required");
+        }
+
+        fn authorize(&self, req: &HttpRequest) -> AppResult<()> {
+            match self.role == "owner" && !req.headers().contains_key("x-owner") {
+                true => AppMessage::Forbidden.ar(),
+                false => Ok(()),
+            }
+        }
+    }
+
+    async fn invite(dto: Dto<Invite>) -> HttpResponse {
+        HttpResponse::Ok().body(dto.email.clone())
+    }
+
+    #[ntex::test]
+    async fn test_phases_run_in_order() {
+        let app = init_service(App::new().route("/", web::post().to(invite))).await;
+        let req = |body: serde_json::Value| TestRequest::post().uri("/").set_json(&body);
+
+        let resp = call_service(
+            &app,
+            req(serde_json::json!({"email": " Ada@Example.com ", "role": "member"})).to_request(),
+        )
+        .await;
+        assert_eq!(read_body(resp).await, "ada@example.com");
+
+        let resp = call_service(
+            &app,
+            req(serde_json::json!({"email": "nope", "role": ""})).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(body["data"]["email"][0], "must be an email");
+        assert_eq!(body["data"]["role"][0], "is required");
+
+        let resp = call_service(
+            &app,
+            req(serde_json::json!({"email": "a@b.c", "role": "owner"})).to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+}
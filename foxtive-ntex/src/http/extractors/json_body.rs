@@ -1,11 +1,14 @@
 use crate::error::HttpError;
+use crate::http::extractors::RequestMemo;
+use crate::http::extractors::body_normalization::from_json_str;
+use crate::http::extractors::body_trace::{read_payload, traced};
 use foxtive::prelude::{AppMessage, AppResult};
 use ntex::http::Payload;
-use ntex::util::BytesMut;
 use ntex::web::{FromRequest, HttpRequest};
 use serde::de::DeserializeOwned;
 use tracing::{debug, error};
 
+#[derive(Clone)]
 pub struct JsonBody {
     json: String,
 }
@@ -54,7 +57,7 @@ impl JsonBody {
     /// # Errors
     /// Return an error if the JSON string cannot be deserialized to the target type.
     pub fn deserialize<T: DeserializeOwned>(&self) -> AppResult<T> {
-        serde_json::from_str::<T>(&self.json).map_err(|e| {
+        from_json_str::<T>(&self.json).map_err(|e| {
             error!("Error deserializing JSON: {e:?}");
             HttpError::AppMessage(AppMessage::WarningMessageString(e.to_string())).into_app_error()
         })
@@ -104,17 +107,23 @@ impl<Err> FromRequest<Err> for JsonBody {
     type Error = HttpError;
 
     async fn from_request(
-        _req: &HttpRequest,
+        req: &HttpRequest,
         payload: &mut Payload,
     ) -> Result<JsonBody, Self::Error> {
-        let mut bytes = BytesMut::new();
-        while let Some(item) = ntex::util::stream_recv(payload).await {
-            bytes.extend_from_slice(&item?);
+        // the payload is gone once read, later extractions reuse the first one
+        if let Some(body) = req.memoized::<JsonBody>() {
+            return Ok(body);
         }
 
-        let raw = String::from_utf8(bytes.to_vec())?;
+        let raw = traced("JsonBody", async {
+            Ok::<_, HttpError>(String::from_utf8(read_payload(payload).await?)?)
+        })
+        .await?;
+
         debug!("[json-body] {raw}");
-        Ok(JsonBody { json: raw })
+        let body = JsonBody { json: raw };
+        req.memoize(body.clone());
+        Ok(body)
     }
 }
 
@@ -236,4 +245,21 @@ mod tests {
 
         assert_eq!(deserialized, expected);
     }
+
+    #[ntex::test]
+    async fn test_extracted_once_per_request() {
+        let (req, mut payload) = ntex::web::test::TestRequest::default()
+            .set_payload(r#"{"field1": "a", "field2": 1}"#)
+            .to_http_parts();
+
+        let first = <JsonBody as FromRequest<HttpError>>::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+        // the payload is consumed by now
+        let second = <JsonBody as FromRequest<HttpError>>::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+        assert_eq!(first.body(), second.body());
+        assert_eq!(second.deserialize::<TestStruct>().unwrap().field2, 1);
+    }
 }
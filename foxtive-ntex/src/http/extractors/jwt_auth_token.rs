@@ -1,12 +1,124 @@
 use crate::error::HttpError;
+use crate::helpers::lru::LruCache;
+use crate::http::extractors::{RequestMemo, TokenIdentity, TokenRevocations};
 use foxtive::prelude::{AppMessage, AppResult};
 use jsonwebtoken::{DecodingKey, TokenData, Validation, decode};
 use ntex::http::Payload;
 use ntex::http::header;
 use ntex::web::{FromRequest, HttpRequest};
 use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::{debug, error};
 
+/// Tokens kept by [`JwtAuthToken::decode_cached`], the least recently used go first
+const CLAIMS_CACHE_CAPACITY: usize = 10_000;
+
+/// How long claims without `exp` are cached
+const UNBOUNDED_CLAIMS_TTL: Duration = Duration::from_secs(60);
+
+/// Claims decoded by [`JwtAuthToken::decode_once`], with the token they came from
+#[derive(Clone)]
+struct DecodedClaims<T> {
+    token: String,
+    claims: T,
+}
+
+struct CachedClaims {
+    token: String,
+    claims: Value,
+}
+
+/// Bounded LRU of verified claims, keyed by the hash of the token and of what it was
+/// verified with
+struct ClaimsCache {
+    entries: LruCache<u64, CachedClaims>,
+}
+
+impl ClaimsCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: LruCache::new(capacity),
+        }
+    }
+
+    fn get(&mut self, key: u64, token: &str) -> Option<Value> {
+        self.entries
+            .get(&key)
+            .filter(|entry| entry.token == token)
+            .map(|entry| entry.claims.clone())
+    }
+
+    fn insert(&mut self, key: u64, token: &str, claims: Value, expires: Instant) {
+        let entry = CachedClaims {
+            token: token.to_string(),
+            claims,
+        };
+        self.entries.insert(key, entry, expires);
+    }
+}
+
+fn claims_cache() -> &'static Mutex<ClaimsCache> {
+    static CACHE: OnceLock<Mutex<ClaimsCache>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(ClaimsCache::new(CLAIMS_CACHE_CAPACITY)))
+}
+
+/// When cached claims must be verified again: at their `exp`, or after
+/// [`UNBOUNDED_CLAIMS_TTL`] without one
+fn claims_expiry(claims: &Value) -> Instant {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    match claims.get("exp").and_then(Value::as_u64) {
+        Some(exp) => Instant::now() + Duration::from_secs(exp.saturating_sub(now)),
+        None => Instant::now() + UNBOUNDED_CLAIMS_TTL,
+    }
+}
+
+/// Secret and validation used by [`JwtAuthToken::decode_cached`], fingerprinted once
+/// when built instead of on every call, with the revocations checked on every decode,
+/// cached or not.
+///
+/// # Example
+/// ```
+/// use foxtive::helpers::jwt::Algorithm;
+/// use foxtive_ntex::http::extractors::JwtVerifier;
+/// use jsonwebtoken::Validation;
+///
+/// let verifier = JwtVerifier::new("secret", Validation::new(Algorithm::HS256));
+/// ```
+#[derive(Clone)]
+pub struct JwtVerifier {
+    secret: String,
+    validation: Validation,
+    fingerprint: u64,
+    revocations: Option<TokenRevocations>,
+}
+
+impl JwtVerifier {
+    pub fn new(secret: &str, validation: Validation) -> Self {
+        let mut hasher = DefaultHasher::new();
+        (secret, format!("{validation:?}")).hash(&mut hasher);
+
+        Self {
+            secret: secret.to_string(),
+            validation,
+            fingerprint: hasher.finish(),
+            revocations: None,
+        }
+    }
+
+    /// Reject revoked tokens, including the ones whose claims are served from the cache
+    pub fn revocations(mut self, revocations: TokenRevocations) -> Self {
+        self.revocations = Some(revocations);
+        self
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct JwtAuthToken {
     token: String,
@@ -46,6 +158,93 @@ impl JwtAuthToken {
         }
     }
 
+    /// Decode and verify the JWT like [`JwtAuthToken::decode`] once per request, the
+    /// middlewares and handlers decoding it afterward get the same claims for free
+    pub fn decode_once<T: DeserializeOwned + Clone + 'static>(
+        &self,
+        req: &HttpRequest,
+        secret: &str,
+        validation: &Validation,
+    ) -> AppResult<T> {
+        if let Some(decoded) = req.memoized::<DecodedClaims<T>>()
+            && decoded.token == self.token
+        {
+            return Ok(decoded.claims);
+        }
+
+        let claims: T = self.decode(secret, validation)?;
+        req.memoize(DecodedClaims {
+            token: self.token.clone(),
+            claims: claims.clone(),
+        });
+        Ok(claims)
+    }
+
+    /// Decode and verify the JWT like [`JwtAuthToken::decode`], reusing the claims verified
+    /// earlier by the same verifier until the token expires, so keep-alive clients and
+    /// layered middlewares don't pay for the signature check on every call. Revocations
+    /// of the verifier are checked every time.
+    ///
+    /// Claims are kept in a process-wide cache of the 10 000 most recently used tokens.
+    pub async fn decode_cached<T: DeserializeOwned>(&self, verifier: &JwtVerifier) -> AppResult<T> {
+        let mut hasher = DefaultHasher::new();
+        (verifier.fingerprint, &self.token).hash(&mut hasher);
+        let key = hasher.finish();
+
+        let cached = claims_cache()
+            .lock()
+            .ok()
+            .and_then(|mut cache| cache.get(key, &self.token));
+
+        let claims = match cached {
+            Some(claims) => claims,
+            None => {
+                let claims: Value = self.decode(&verifier.secret, &verifier.validation)?;
+                if let Ok(mut cache) = claims_cache().lock() {
+                    cache.insert(key, &self.token, claims.clone(), claims_expiry(&claims));
+                }
+                claims
+            }
+        };
+
+        if let Some(revocations) = &verifier.revocations {
+            Self::check_revocations(&claims, revocations).await?;
+        }
+
+        T::deserialize(claims).map_err(|e| {
+            error!("JWT claims error: {e:?}");
+            HttpError::AppMessage(AppMessage::WarningMessageString(e.to_string())).into_app_error()
+        })
+    }
+
+    /// Decode and verify the JWT like [`JwtAuthToken::decode`], then reject it with 401
+    /// when `revocations` reports its `jti` or `sub` as revoked, or when they can't be
+    /// read from the claims
+    pub async fn decode_unrevoked<T: DeserializeOwned>(
+        &self,
+        secret: &str,
+        validation: &Validation,
+        revocations: &TokenRevocations,
+    ) -> AppResult<T> {
+        let claims: serde_json::Value = self.decode(secret, validation)?;
+
+        Self::check_revocations(&claims, revocations).await?;
+
+        T::deserialize(claims).map_err(|e| {
+            error!("JWT claims error: {e:?}");
+            HttpError::AppMessage(AppMessage::WarningMessageString(e.to_string())).into_app_error()
+        })
+    }
+
+    async fn check_revocations(claims: &Value, revocations: &TokenRevocations) -> AppResult<()> {
+        let identity = TokenIdentity::from_claims(claims).ok_or_else(|| {
+            debug!("[jwt] rejected token with malformed registered claims");
+            HttpError::AppMessage(AppMessage::UnAuthorizedMessage("Invalid token claims"))
+                .into_app_error()
+        })?;
+        revocations.check(&identity).await
+    }
+
     /// Utility: Check if the token seems to be present and nonempty
     pub fn is_empty(&self) -> bool {
         self.token.is_empty()
@@ -70,6 +269,10 @@ impl<Err> FromRequest<Err> for JwtAuthToken {
     type Error = HttpError;
 
     async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        if let Some(token) = req.memoized::<JwtAuthToken>() {
+            return Ok(token);
+        }
+
         let token = req
             .headers()
             .get(header::AUTHORIZATION)
@@ -88,20 +291,25 @@ impl<Err> FromRequest<Err> for JwtAuthToken {
 
         debug!("[jwt-auth-token] extracted {token}");
 
-        Ok(JwtAuthToken {
+        let token = JwtAuthToken {
             token: token.to_string(),
-        })
+        };
+        req.memoize(token.clone());
+        Ok(token)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::http::extractors::{RevocationFuture, TokenRevocationStore};
     use foxtive::helpers::jwt::Algorithm;
     use jsonwebtoken::{EncodingKey, Header, encode};
     use ntex::http::{Payload, header};
     use ntex::web::test::TestRequest;
     use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
 
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
     struct TestClaims {
@@ -169,6 +377,87 @@ mod tests {
         assert!(token.is_err());
     }
 
+    #[tokio::test]
+    async fn test_decode_unrevoked() {
+        struct Revoked;
+
+        impl TokenRevocationStore for Revoked {
+            fn is_revoked(&self, jti: &str) -> RevocationFuture<bool> {
+                let revoked = jti == "revoked";
+                Box::pin(async move { Ok(revoked) })
+            }
+        }
+
+        let secret = "my-secret";
+        let revocations = TokenRevocations::new(Revoked);
+        let validation = Validation::new(Algorithm::HS256);
+        let sign = |claims: serde_json::Value| {
+            let jwt = encode(
+                &Header::default(),
+                &claims,
+                &EncodingKey::from_secret(secret.as_bytes()),
+            )
+            .unwrap();
+            JwtAuthToken::from(jwt)
+        };
+        let token =
+            |jti: &str| sign(serde_json::json!({"sub": "me", "jti": jti, "exp": 2000000000}));
+
+        let claims: serde_json::Value = token("valid")
+            .decode_unrevoked(secret, &validation, &revocations)
+            .await
+            .unwrap();
+        assert_eq!(claims["sub"], "me");
+
+        let revoked = token("revoked")
+            .decode_unrevoked::<serde_json::Value>(secret, &validation, &revocations)
+            .await;
+        assert!(revoked.is_err());
+
+        // an identity that can't be read is not waved through
+        let malformed = sign(serde_json::json!({"sub": "me", "jti": 42, "exp": 2000000000}))
+            .decode_unrevoked::<serde_json::Value>(secret, &validation, &revocations)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            malformed.downcast_ref::<HttpError>(),
+            Some(HttpError::AppMessage(AppMessage::UnAuthorizedMessage(
+                "Invalid token claims"
+            )))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_decode_once_per_request() {
+        let claims = TestClaims {
+            sub: "me".to_string(),
+            company: "Acme".to_string(),
+            exp: 2000000000,
+        };
+        let jwt = create_jwt("my-secret", &claims);
+        let req = jwt_req_with_header(&jwt);
+        let validation = Validation::new(Algorithm::HS256);
+
+        let token =
+            <JwtAuthToken as FromRequest<HttpError>>::from_request(&req, &mut Payload::None)
+                .await
+                .unwrap();
+        let decoded: TestClaims = token.decode_once(&req, "my-secret", &validation).unwrap();
+        assert_eq!(decoded, claims);
+
+        // memoized: the secret isn't checked again within the request
+        let again: TestClaims = token.decode_once(&req, "other", &validation).unwrap();
+        assert_eq!(again, claims);
+
+        // another token isn't served the memoized claims
+        let other = JwtAuthToken::from(create_jwt("other", &claims));
+        assert!(
+            other
+                .decode_once::<TestClaims>(&req, "my-secret", &validation)
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_utilities() {
         let token = JwtAuthToken::from("abc.def.ghi");
@@ -176,4 +465,75 @@ mod tests {
         assert!(!token.is_empty());
         assert_eq!(token.clone().into_token(), "abc.def.ghi".to_string());
     }
+
+    #[tokio::test]
+    async fn test_decode_cached() {
+        let claims = TestClaims {
+            sub: "me".to_string(),
+            company: "Acme".to_string(),
+            exp: 2000000000,
+        };
+        let token = JwtAuthToken::from(create_jwt("cached-secret", &claims));
+        let verifier = JwtVerifier::new("cached-secret", Validation::new(Algorithm::HS256));
+
+        for _ in 0..2 {
+            let decoded: TestClaims = token.decode_cached(&verifier).await.unwrap();
+            assert_eq!(decoded, claims);
+        }
+
+        // claims verified with another secret are not reused
+        let other = JwtVerifier::new("other-secret", Validation::new(Algorithm::HS256));
+        assert!(token.decode_cached::<TestClaims>(&other).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_decode_cached_checks_revocations_on_hits() {
+        struct Revoked(Arc<AtomicBool>);
+
+        impl TokenRevocationStore for Revoked {
+            fn is_revoked(&self, _jti: &str) -> RevocationFuture<bool> {
+                let revoked = self.0.load(Ordering::SeqCst);
+                Box::pin(async move { Ok(revoked) })
+            }
+        }
+
+        let revoked = Arc::new(AtomicBool::new(false));
+        let revocations = TokenRevocations::new(Revoked(revoked.clone())).cache_ttl(Duration::ZERO);
+        let verifier = JwtVerifier::new("revoked-secret", Validation::new(Algorithm::HS256))
+            .revocations(revocations);
+
+        let jwt = encode(
+            &Header::default(),
+            &serde_json::json!({"sub": "me", "jti": "cached", "exp": 2000000000}),
+            &EncodingKey::from_secret(b"revoked-secret"),
+        )
+        .unwrap();
+        let token = JwtAuthToken::from(jwt);
+
+        assert!(token.decode_cached::<Value>(&verifier).await.is_ok());
+
+        // the claims are cached now, the revocation still applies
+        revoked.store(true, Ordering::SeqCst);
+        assert!(token.decode_cached::<Value>(&verifier).await.is_err());
+    }
+
+    #[test]
+    fn test_claims_cache_evicts_least_recently_used() {
+        let mut cache = ClaimsCache::new(2);
+        let later = Instant::now() + Duration::from_secs(60);
+
+        cache.insert(1, "a", Value::from(1), later);
+        cache.insert(2, "b", Value::from(2), later);
+        assert_eq!(cache.get(1, "a"), Some(Value::from(1)));
+
+        cache.insert(3, "c", Value::from(3), later);
+        assert_eq!(cache.get(2, "b"), None);
+        assert_eq!(cache.get(1, "a"), Some(Value::from(1)));
+        assert_eq!(cache.get(3, "c"), Some(Value::from(3)));
+
+        // expired entries and other tokens with the same key are misses
+        cache.insert(4, "d", Value::from(4), Instant::now());
+        assert_eq!(cache.get(4, "d"), None);
+        assert_eq!(cache.get(1, "not-a"), None);
+    }
 }
@@ -0,0 +1,268 @@
+use crate::helpers::lru::LruCache;
+use foxtive::prelude::{AppMessage, AppResult};
+use serde::Deserialize;
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Lookups kept by [`TokenRevocations`], the least recently used go first
+const DECISIONS_CAPACITY: usize = 10_000;
+
+pub type RevocationFuture<T> = Pin<Box<dyn Future<Output = AppResult<T>>>>;
+
+/// Registered claims identifying a token, read from the verified claims
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TokenIdentity {
+    pub jti: Option<String>,
+    pub sub: Option<String>,
+    pub iat: Option<u64>,
+}
+
+impl TokenIdentity {
+    /// Identity of verified claims, `None` when `jti`, `sub` or `iat` is present but
+    /// not of the registered type, such tokens can't be checked against revocations
+    pub fn from_claims(claims: &Value) -> Option<Self> {
+        fn string(claims: &Value, name: &str) -> Option<Option<String>> {
+            match claims.get(name) {
+                None | Some(Value::Null) => Some(None),
+                Some(Value::String(value)) => Some(Some(value.clone())),
+                Some(_) => None,
+            }
+        }
+
+        let iat = match claims.get("iat") {
+            None | Some(Value::Null) => None,
+            // some issuers write fractional timestamps
+            Some(Value::Number(iat)) => Some(
+                iat.as_u64()
+                    .or_else(|| iat.as_f64().filter(|iat| *iat >= 0.0).map(|iat| iat as u64))?,
+            ),
+            Some(_) => return None,
+        };
+
+        Some(Self {
+            jti: string(claims, "jti")?,
+            sub: string(claims, "sub")?,
+            iat,
+        })
+    }
+}
+
+/// Lookup of revoked tokens, consulted after the signature has been verified.
+///
+/// Implement the lookups your revocation model supports, the others default to
+/// "not revoked".
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::extractors::{RevocationFuture, TokenRevocationStore};
+///
+/// struct LoggedOutSessions;
+///
+/// impl TokenRevocationStore for LoggedOutSessions {
+///     fn is_revoked(&self, jti: &str) -> RevocationFuture<bool> {
+///         let jti = jti.to_string();
+///         // SELECT 1 FROM revoked_tokens WHERE jti = $1
+///         Box::pin(async move { Ok(jti == "stolen") })
+///     }
+/// }
+/// ```
+pub trait TokenRevocationStore: Send + Sync + 'static {
+    /// Whether the token with this `jti` was revoked (logout, compromised token)
+    fn is_revoked(&self, _jti: &str) -> RevocationFuture<bool> {
+        Box::pin(async { Ok(false) })
+    }
+
+    /// Unix timestamp before which every token of `sub` is revoked (logout everywhere)
+    fn revoked_before(&self, _sub: &str) -> RevocationFuture<Option<u64>> {
+        Box::pin(async { Ok(None) })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Decision {
+    Jti(bool),
+    Sub(Option<u64>),
+}
+
+/// Cached access to a [`TokenRevocationStore`], cheap to clone and share between workers.
+///
+/// Lookups are cached for 30 seconds by default, which is how long a revocation
+/// can take to apply, and only the 10 000 most recently used are kept. Store failures
+/// are not cached.
+#[derive(Clone)]
+pub struct TokenRevocations {
+    store: Arc<dyn TokenRevocationStore>,
+    ttl: Duration,
+    decisions: Arc<Mutex<LruCache<String, Decision>>>,
+}
+
+impl TokenRevocations {
+    pub fn new(store: impl TokenRevocationStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            ttl: Duration::from_secs(30),
+            decisions: Arc::new(Mutex::new(LruCache::new(DECISIONS_CAPACITY))),
+        }
+    }
+
+    /// How long lookups are reused, `Duration::ZERO` disables caching
+    pub fn cache_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Fails with 401 when the token is revoked by id or by subject
+    pub async fn check(&self, identity: &TokenIdentity) -> AppResult<()> {
+        if let Some(jti) = &identity.jti
+            && let Decision::Jti(true) = self.lookup(Key::Jti(jti)).await?
+        {
+            return Self::revoked(identity);
+        }
+
+        if let Some(sub) = &identity.sub
+            && let Decision::Sub(Some(before)) = self.lookup(Key::Sub(sub)).await?
+            // tokens without `iat` can't prove they were issued afterwards
+            && identity.iat.is_none_or(|iat| iat < before)
+        {
+            return Self::revoked(identity);
+        }
+
+        Ok(())
+    }
+
+    /// Forget the cached lookups, e.g. right after revoking a token on this instance
+    pub fn clear_cache(&self) {
+        if let Ok(mut decisions) = self.decisions.lock() {
+            decisions.clear();
+        }
+    }
+
+    fn revoked(identity: &TokenIdentity) -> AppResult<()> {
+        debug!(
+            "[jwt] rejected revoked token (jti: {:?}, sub: {:?})",
+            identity.jti, identity.sub
+        );
+        AppMessage::UnAuthorizedMessage("Token has been revoked").ar()
+    }
+
+    async fn lookup(&self, key: Key<'_>) -> AppResult<Decision> {
+        let cache_key = key.cache_key();
+
+        if let Ok(mut decisions) = self.decisions.lock()
+            && let Some(decision) = decisions.get(&cache_key)
+        {
+            return Ok(*decision);
+        }
+
+        let decision = match key {
+            Key::Jti(jti) => Decision::Jti(self.store.is_revoked(jti).await?),
+            Key::Sub(sub) => Decision::Sub(self.store.revoked_before(sub).await?),
+        };
+
+        if !self.ttl.is_zero()
+            && let Ok(mut decisions) = self.decisions.lock()
+        {
+            decisions.insert(cache_key, decision, Instant::now() + self.ttl);
+        }
+
+        Ok(decision)
+    }
+}
+
+enum Key<'a> {
+    Jti(&'a str),
+    Sub(&'a str),
+}
+
+impl Key<'_> {
+    fn cache_key(&self) -> String {
+        match self {
+            Key::Jti(jti) => format!("jti:{jti}"),
+            Key::Sub(sub) => format!("sub:{sub}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Store(Arc<AtomicUsize>);
+
+    impl TokenRevocationStore for Store {
+        fn is_revoked(&self, jti: &str) -> RevocationFuture<bool> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let revoked = jti == "stolen";
+            Box::pin(async move { Ok(revoked) })
+        }
+
+        fn revoked_before(&self, sub: &str) -> RevocationFuture<Option<u64>> {
+            let before = (sub == "ada").then_some(1_000);
+            Box::pin(async move { Ok(before) })
+        }
+    }
+
+    fn identity(jti: &str, sub: &str, iat: Option<u64>) -> TokenIdentity {
+        TokenIdentity {
+            jti: Some(jti.to_string()),
+            sub: Some(sub.to_string()),
+            iat,
+        }
+    }
+
+    #[ntex::test]
+    async fn test_revoked_by_jti_and_subject() {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let revocations = TokenRevocations::new(Store(lookups.clone()));
+
+        assert!(
+            revocations
+                .check(&identity("ok", "bob", None))
+                .await
+                .is_ok()
+        );
+        assert!(
+            revocations
+                .check(&identity("stolen", "bob", None))
+                .await
+                .is_err()
+        );
+
+        // logout everywhere only hits tokens issued before the cutoff
+        assert!(
+            revocations
+                .check(&identity("a", "ada", Some(999)))
+                .await
+                .is_err()
+        );
+        assert!(
+            revocations
+                .check(&identity("b", "ada", None))
+                .await
+                .is_err()
+        );
+        assert!(
+            revocations
+                .check(&identity("c", "ada", Some(1_000)))
+                .await
+                .is_ok()
+        );
+
+        let err = revocations
+            .check(&identity("stolen", "bob", None))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<AppMessage>(),
+            Some(AppMessage::UnAuthorizedMessage("Token has been revoked"))
+        ));
+
+        // "ok" and "stolen" were cached
+        assert_eq!(lookups.load(Ordering::SeqCst), 5);
+    }
+}
@@ -0,0 +1,98 @@
+use ntex::web::{HttpRequest, WebRequest};
+
+/// Slot of a memoized value, keeps memoized types apart from other request extensions
+struct Memo<T>(T);
+
+/// Once-per-request memoization of expensive extractions, keyed by type and kept in the
+/// request extensions, so a middleware and a handler (or two extractors) needing the same
+/// parsed value share it instead of parsing twice.
+///
+/// `JwtAuthToken`, `JsonBody` and `ClientInfo` memoize themselves.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::extractors::RequestMemo;
+/// use ntex::web::HttpRequest;
+///
+/// #[derive(Clone)]
+/// struct Tenant(String);
+///
+/// fn tenant(req: &HttpRequest) -> Tenant {
+///     req.memoized_or_insert_with(|| {
+///         // expensive lookup, done once per request
+///         Tenant("acme".to_string())
+///     })
+/// }
+/// ```
+pub trait RequestMemo {
+    /// Value memoized for this request
+    fn memoized<T: Clone + 'static>(&self) -> Option<T>;
+
+    /// Memoize `value` for the rest of the request, replacing the previous one
+    fn memoize<T: 'static>(&self, value: T);
+
+    fn memoized_or_insert_with<T: Clone + 'static>(&self, init: impl FnOnce() -> T) -> T {
+        if let Some(value) = self.memoized::<T>() {
+            return value;
+        }
+
+        let value = init();
+        self.memoize(value.clone());
+        value
+    }
+}
+
+impl RequestMemo for HttpRequest {
+    fn memoized<T: Clone + 'static>(&self) -> Option<T> {
+        self.extensions()
+            .get::<Memo<T>>()
+            .map(|memo| memo.0.clone())
+    }
+
+    fn memoize<T: 'static>(&self, value: T) {
+        self.extensions_mut().insert(Memo(value));
+    }
+}
+
+impl<Err> RequestMemo for WebRequest<Err> {
+    fn memoized<T: Clone + 'static>(&self) -> Option<T> {
+        self.extensions()
+            .get::<Memo<T>>()
+            .map(|memo| memo.0.clone())
+    }
+
+    fn memoize<T: 'static>(&self, value: T) {
+        self.extensions_mut().insert(Memo(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::test::TestRequest;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_initialized_once_per_request() {
+        let calls = Cell::new(0);
+        let init = || {
+            calls.set(calls.get() + 1);
+            "parsed".to_string()
+        };
+
+        let req = TestRequest::default().to_http_request();
+        assert!(req.memoized::<String>().is_none());
+        assert_eq!(req.memoized_or_insert_with(init), "parsed");
+        assert_eq!(req.memoized_or_insert_with(init), "parsed");
+        assert_eq!(calls.get(), 1);
+
+        // middlewares memoize on their view of the request
+        let web = TestRequest::default().to_srv_request();
+        web.memoize(7u32);
+        assert_eq!(web.memoized::<u32>(), Some(7));
+        assert!(web.memoized::<u64>().is_none());
+
+        let other = TestRequest::default().to_http_request();
+        assert!(other.memoized::<String>().is_none());
+    }
+}
@@ -1,15 +1,41 @@
+mod blocking_pool;
+mod body_normalization;
+mod body_trace;
 mod byte_body;
+mod cancellation;
 mod client_info;
+mod conditional;
 mod de_json_body;
+mod dry_run;
+mod dto;
 mod json_body;
 #[cfg(feature = "jwt")]
 mod jwt_auth_token;
+#[cfg(feature = "jwt")]
+mod jwt_revocation;
+mod memo;
+mod outbox;
+mod route_template;
 mod string_body;
+mod timings;
 
+pub use blocking_pool::BlockingPool;
+pub use body_normalization::BodyNormalization;
+pub use body_trace::ExtractorTracing;
 pub use byte_body::ByteBody;
+pub use cancellation::CancellationToken;
 pub use client_info::ClientInfo;
+pub use conditional::{Conditional, ResourceVersion};
 pub use de_json_body::DeJsonBody;
+pub use dry_run::{DRY_RUN_HEADER, DRY_RUN_PARAM, DryRun};
+pub use dto::Dto;
 pub use json_body::JsonBody;
 #[cfg(feature = "jwt")]
-pub use jwt_auth_token::JwtAuthToken;
-pub use string_body::StringBody;
+pub use jwt_auth_token::{JwtAuthToken, JwtVerifier};
+#[cfg(feature = "jwt")]
+pub use jwt_revocation::{RevocationFuture, TokenIdentity, TokenRevocationStore, TokenRevocations};
+pub use memo::RequestMemo;
+pub use outbox::{Outbox, OutboxEvent};
+pub use route_template::RouteTemplate;
+pub use string_body::{BodyCharset, StringBody};
+pub use timings::{TimingGuard, TimingPhase, Timings};
@@ -0,0 +1,80 @@
+use crate::error::HttpError;
+use foxtive::prelude::AppResult;
+use ntex::http::Payload;
+use ntex::web::{FromRequest, HttpRequest};
+use serde::Serialize;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Domain event staged during request handling
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutboxEvent {
+    pub topic: String,
+    pub payload: Value,
+}
+
+/// Events staged by the handler, published by the `publish_outbox` middleware
+/// once the response turns out successful.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::extractors::Outbox;
+/// use foxtive_ntex::http::HttpResult;
+/// use foxtive_ntex::helpers::responder::Responder;
+/// use serde_json::json;
+///
+/// async fn create_order(outbox: Outbox) -> HttpResult {
+///     // ...persist the order
+///     outbox.publish_later("orders.created", &json!({"id": 1}))?;
+///     Ok(Responder::ok_message("Order created"))
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Outbox {
+    events: Rc<RefCell<Vec<OutboxEvent>>>,
+}
+
+impl Outbox {
+    /// Get the outbox of the request, creating it on first use
+    pub fn from_http_request(req: &HttpRequest) -> Self {
+        if let Some(outbox) = req.extensions().get::<Outbox>() {
+            return outbox.clone();
+        }
+
+        let outbox = Outbox::default();
+        req.extensions_mut().insert(outbox.clone());
+        outbox
+    }
+
+    /// Stage an event, it is dropped if the request fails
+    pub fn publish_later<T: Serialize>(&self, topic: &str, event: &T) -> AppResult<()> {
+        self.events.borrow_mut().push(OutboxEvent {
+            topic: topic.to_string(),
+            payload: serde_json::to_value(event)?,
+        });
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.borrow().is_empty()
+    }
+
+    /// Remove and return the staged events
+    pub fn take(&self) -> Vec<OutboxEvent> {
+        self.events.take()
+    }
+}
+
+impl<Err> FromRequest<Err> for Outbox {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(Outbox::from_http_request(req))
+    }
+}
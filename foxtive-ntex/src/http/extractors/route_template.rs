@@ -0,0 +1,87 @@
+use crate::error::HttpError;
+use crate::http::middlewares::MatchedRoute;
+use ntex::http::Payload;
+use ntex::web::{FromRequest, HttpRequest};
+use std::fmt::{Display, Formatter};
+
+/// Route pattern matched for the request, e.g. `/api/v1/users/{id}`.
+///
+/// Unlike the raw path it has a bounded number of values, which makes it suitable
+/// as a metrics or analytics label. Routes registered outside `register_routes`
+/// resolve to `UNMATCHED`.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::extractors::RouteTemplate;
+///
+/// async fn handler(route: RouteTemplate) -> String {
+///     format!("served by {route}")
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteTemplate(pub String);
+
+impl RouteTemplate {
+    pub const UNMATCHED: &'static str = "<unmatched>";
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn from_http_request(req: &HttpRequest) -> Self {
+        match MatchedRoute::from_http_request(req) {
+            Some(route) => RouteTemplate(route.template().to_string()),
+            None => RouteTemplate(Self::UNMATCHED.to_string()),
+        }
+    }
+}
+
+impl Display for RouteTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<Err> FromRequest<Err> for RouteTemplate {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(RouteTemplate::from_http_request(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::kernel::{Controller, Route, register_routes};
+    use ntex::web::test::{TestRequest, init_service, read_response};
+    use ntex::web::{self, App, ServiceConfig};
+
+    fn users(cfg: &mut ServiceConfig) {
+        cfg.route(
+            "/{id}",
+            web::get().to(|route: RouteTemplate| async move { route.0 }),
+        );
+    }
+
+    #[ntex::test]
+    async fn test_extracts_template_without_middlewares() {
+        let routes = vec![
+            Route::new("/api/v1").controller(Controller::new("/users", users).pattern("/{id}")),
+        ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let req = TestRequest::with_uri("/api/v1/users/42").to_request();
+        let body = read_response(&app, req).await;
+        assert_eq!(body, "/api/v1/users/{id}");
+    }
+
+    #[test]
+    fn test_unmatched_outside_registered_routes() {
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(
+            RouteTemplate::from_http_request(&req).as_str(),
+            RouteTemplate::UNMATCHED
+        );
+    }
+}
@@ -1,11 +1,117 @@
 use crate::error::HttpError;
+use crate::http::extractors::body_trace::{read_payload, traced};
+use crate::http::server::ServerSettings;
 use foxtive::prelude::{AppMessage, AppResult};
-use ntex::http::Payload;
-use ntex::util::BytesMut;
+use ntex::http::{Payload, StatusCode, header};
 use ntex::web::{FromRequest, HttpRequest};
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::debug;
 
-/// Extractor for reading the request body as a plain UTF-8 string.
+/// How `StringBody` handles the charset of request bodies, configured through
+/// `ServerConfig::body_charset`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BodyCharset {
+    /// Charset taken from a byte order mark or the `Content-Type` charset parameter, other
+    /// charsets than UTF-8 are transcoded with the `encoding` feature and answered with
+    /// `415 Unsupported Media Type` without it
+    #[default]
+    Detect,
+    /// Only UTF-8 is accepted, any other declared charset is answered with 415
+    StrictUtf8,
+}
+
+static STRICT_UTF8: AtomicBool = AtomicBool::new(false);
+
+impl BodyCharset {
+    /// Process default, for the apps not started with a `ServerConfig`
+    pub fn set(charset: BodyCharset) {
+        STRICT_UTF8.store(charset == BodyCharset::StrictUtf8, Ordering::Relaxed);
+    }
+
+    /// Handling of the server receiving the current request, the process default otherwise
+    pub fn current() -> BodyCharset {
+        if let Some(charset) = ServerSettings::read(|settings| settings.body_charset) {
+            return charset;
+        }
+
+        match STRICT_UTF8.load(Ordering::Relaxed) {
+            true => BodyCharset::StrictUtf8,
+            false => BodyCharset::Detect,
+        }
+    }
+
+    /// Decode `bytes` into UTF-8, `content_type` being the request `Content-Type` header.
+    /// A UTF-8 byte order mark is dropped.
+    pub fn decode(self, bytes: Vec<u8>, content_type: Option<&str>) -> Result<String, HttpError> {
+        let declared = content_type.and_then(charset_param);
+        let (label, bom_len) = match bytes.as_slice() {
+            [0xEF, 0xBB, 0xBF, ..] => ("utf-8".to_string(), 3),
+            [0xFF, 0xFE, ..] => ("utf-16le".to_string(), 2),
+            [0xFE, 0xFF, ..] => ("utf-16be".to_string(), 2),
+            _ => (declared.unwrap_or_else(|| "utf-8".to_string()), 0),
+        };
+
+        if matches!(label.as_str(), "utf-8" | "utf8" | "us-ascii") {
+            let mut bytes = bytes;
+            bytes.drain(..bom_len);
+            return String::from_utf8(bytes).map_err(|err| {
+                unreadable(format!(
+                    "request body is not valid UTF-8 (invalid byte at offset {})",
+                    err.utf8_error().valid_up_to() + bom_len
+                ))
+            });
+        }
+
+        if self == BodyCharset::StrictUtf8 {
+            return Err(unsupported(format!(
+                "request body charset '{label}' is not accepted, send UTF-8"
+            )));
+        }
+
+        transcode(&label, &bytes[bom_len..])
+    }
+}
+
+fn charset_param(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"').to_ascii_lowercase())
+    })
+}
+
+#[cfg(feature = "encoding")]
+fn transcode(label: &str, bytes: &[u8]) -> Result<String, HttpError> {
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| unsupported(format!("unknown request body charset '{label}'")))?;
+
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|text| text.into_owned())
+        .ok_or_else(|| unreadable(format!("request body is not valid {}", encoding.name())))
+}
+
+#[cfg(not(feature = "encoding"))]
+fn transcode(label: &str, _bytes: &[u8]) -> Result<String, HttpError> {
+    Err(unsupported(format!(
+        "request body charset '{label}' is not supported, send UTF-8"
+    )))
+}
+
+fn unsupported(message: String) -> HttpError {
+    HttpError::AppMessage(AppMessage::ErrorMessage(
+        message,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+    ))
+}
+
+fn unreadable(message: String) -> HttpError {
+    HttpError::AppMessage(AppMessage::ErrorMessage(message, StatusCode::BAD_REQUEST))
+}
+
+/// Extractor for reading the request body as a UTF-8 string, transcoded from the charset
+/// sent by the client according to the [`BodyCharset`] policy.
 ///
 /// # Example
 /// ```
@@ -69,13 +175,17 @@ impl From<&str> for StringBody {
 impl<Err> FromRequest<Err> for StringBody {
     type Error = HttpError;
 
-    async fn from_request(_req: &HttpRequest, payload: &mut Payload) -> Result<Self, Self::Error> {
-        let mut bytes = BytesMut::new();
-        while let Some(chunk) = ntex::util::stream_recv(payload).await {
-            bytes.extend_from_slice(&chunk?);
-        }
+    async fn from_request(req: &HttpRequest, payload: &mut Payload) -> Result<Self, Self::Error> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+
+        let raw = traced("StringBody", async {
+            BodyCharset::current().decode(read_payload(payload).await?, content_type)
+        })
+        .await?;
 
-        let raw = String::from_utf8(bytes.to_vec())?;
         debug!("[string-body] {raw}");
         Ok(Self { body: raw })
     }
@@ -140,4 +250,41 @@ mod tests {
         let sb = StringBody::from(data.clone());
         assert_eq!(sb.body(), &data);
     }
+
+    #[test]
+    fn test_charset_decoding() {
+        let detect = BodyCharset::Detect;
+        let text = detect
+            .decode(b"\xEF\xBB\xBFcaf\xC3\xA9".to_vec(), Some("text/plain"))
+            .unwrap();
+        assert_eq!(text, "café");
+
+        let err = detect.decode(vec![b'a', 0xFF], None).unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        assert!(err.to_string().contains("offset 1"));
+
+        let err = BodyCharset::StrictUtf8
+            .decode(b"caf\xE9".to_vec(), Some("text/plain; charset=ISO-8859-1"))
+            .unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(err.to_string().contains("iso-8859-1"));
+    }
+
+    #[test]
+    fn test_utf16_bodies() {
+        // "hé" as UTF-16LE with its byte order mark
+        let body = vec![0xFF, 0xFE, b'h', 0x00, 0xE9, 0x00];
+        let decoded = BodyCharset::Detect.decode(body.clone(), None);
+
+        #[cfg(feature = "encoding")]
+        assert_eq!(decoded.unwrap(), "hé");
+        #[cfg(not(feature = "encoding"))]
+        assert_eq!(
+            decoded.unwrap_err().status_code(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let strict = BodyCharset::StrictUtf8.decode(body, None).unwrap_err();
+        assert_eq!(strict.status_code(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
 }
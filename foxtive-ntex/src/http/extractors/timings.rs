@@ -0,0 +1,223 @@
+use crate::error::HttpError;
+use ntex::http::Payload;
+use ntex::web::{FromRequest, HttpRequest};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A single named phase recorded during request handling
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingPhase {
+    pub name: String,
+    pub duration: Duration,
+    pub description: Option<String>,
+}
+
+/// Per-request handle for recording named phases (db, cache, downstream calls...).
+///
+/// The handle is shared through request extensions, so every extraction within the same
+/// request records into the same list. Use the `server_timing` after-middleware to render
+/// the phases into the `Server-Timing` response header.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::extractors::Timings;
+///
+/// async fn handler(timings: Timings) -> String {
+///     let users = timings.measure("db", async { vec!["john"] }).await;
+///     format!("{} users", users.len())
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct Timings {
+    phases: Arc<Mutex<Vec<TimingPhase>>>,
+}
+
+/// Records the elapsed time of a phase when dropped
+pub struct TimingGuard {
+    timings: Timings,
+    name: String,
+    started_at: Instant,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the handle attached to the request, creating one if it does not exist yet
+    pub fn from_http_request(req: &HttpRequest) -> Self {
+        if let Some(timings) = req.extensions().get::<Timings>() {
+            return timings.clone();
+        }
+
+        let timings = Timings::new();
+        req.extensions_mut().insert(timings.clone());
+        timings
+    }
+
+    /// Record a phase with a known duration
+    pub fn record(&self, name: &str, duration: Duration) {
+        self.push(TimingPhase {
+            name: name.to_string(),
+            duration,
+            description: None,
+        });
+    }
+
+    /// Record a phase with a known duration and a human-readable description
+    pub fn record_with_desc(&self, name: &str, duration: Duration, description: &str) {
+        self.push(TimingPhase {
+            name: name.to_string(),
+            duration,
+            description: Some(description.to_string()),
+        });
+    }
+
+    /// Start timing a phase, the phase is recorded when the returned guard is dropped
+    pub fn start(&self, name: &str) -> TimingGuard {
+        TimingGuard {
+            timings: self.clone(),
+            name: name.to_string(),
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Await the future while recording its duration under the given name
+    pub async fn measure<F, T>(&self, name: &str, fut: F) -> T
+    where
+        F: Future<Output = T>,
+    {
+        let _guard = self.start(name);
+        fut.await
+    }
+
+    /// Returns the phases recorded so far
+    pub fn phases(&self) -> Vec<TimingPhase> {
+        self.phases.lock().unwrap().clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phases.lock().unwrap().is_empty()
+    }
+
+    /// Render the recorded phases as a `Server-Timing` header value
+    pub fn header_value(&self) -> String {
+        self.phases
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|phase| {
+                let name = sanitize_token(&phase.name);
+                let duration = phase.duration.as_secs_f64() * 1000.0;
+                match &phase.description {
+                    None => format!("{name};dur={duration:.3}"),
+                    Some(desc) => {
+                        let desc = quote_description(desc);
+                        format!("{name};dur={duration:.3};desc={desc}")
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn push(&self, phase: TimingPhase) {
+        self.phases.lock().unwrap().push(phase);
+    }
+}
+
+impl Drop for TimingGuard {
+    fn drop(&mut self) {
+        self.timings.record(&self.name, self.started_at.elapsed());
+    }
+}
+
+/// Server-Timing metric names must be valid header tokens
+fn sanitize_token(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() || "-_.".contains(c) {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+/// Descriptions are quoted strings, characters a header value can't hold are replaced
+fn quote_description(desc: &str) -> String {
+    let mut quoted = String::with_capacity(desc.len() + 2);
+    quoted.push('"');
+    for c in desc.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            ' ' | '\t' => quoted.push(c),
+            c if c.is_ascii_graphic() => quoted.push(c),
+            _ => quoted.push('?'),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl<Err> FromRequest<Err> for Timings {
+    type Error = HttpError;
+
+    async fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Result<Self, Self::Error> {
+        Ok(Timings::from_http_request(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::test::TestRequest;
+
+    #[test]
+    fn test_header_value() {
+        let timings = Timings::new();
+        timings.record("db", Duration::from_millis(12));
+        timings.record_with_desc("cache hit", Duration::from_micros(500), "redis");
+
+        assert_eq!(
+            timings.header_value(),
+            "db;dur=12.000, cache_hit;dur=0.500;desc=\"redis\""
+        );
+    }
+
+    #[test]
+    fn test_header_value_escapes_descriptions() {
+        let timings = Timings::new();
+        timings.record_with_desc("db", Duration::from_millis(1), "say \"hi\" \\ bye");
+        timings.record_with_desc("cache", Duration::from_millis(1), "caf\u{e9}\r\nhit");
+
+        let value = timings.header_value();
+        assert_eq!(
+            value,
+            "db;dur=1.000;desc=\"say \\\"hi\\\" \\\\ bye\", cache;dur=1.000;desc=\"caf???hit\""
+        );
+        assert!(ntex::http::header::HeaderValue::from_str(&value).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_measure_records_phase() {
+        let timings = Timings::new();
+        let value = timings.measure("compute", async { 42 }).await;
+
+        assert_eq!(value, 42);
+        assert_eq!(timings.phases().len(), 1);
+        assert_eq!(timings.phases()[0].name, "compute");
+    }
+
+    #[test]
+    fn test_shared_through_request_extensions() {
+        let req = TestRequest::default().to_http_request();
+
+        Timings::from_http_request(&req).record("first", Duration::from_millis(1));
+        Timings::from_http_request(&req).record("second", Duration::from_millis(1));
+
+        assert_eq!(Timings::from_http_request(&req).phases().len(), 2);
+    }
+}
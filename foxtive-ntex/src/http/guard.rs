@@ -0,0 +1,243 @@
+use crate::FoxtiveNtexState;
+use chrono::{DateTime, Datelike, NaiveTime, Utc, Weekday};
+use ntex::http::Method;
+use ntex::http::header;
+use ntex::web::HttpRequest;
+use std::fmt::{Debug, Formatter};
+use std::ops::Not;
+use std::sync::Arc;
+
+type Check = dyn Fn(&HttpRequest) -> bool + Send + Sync;
+
+/// Access predicate declared on route groups and controllers with `RoutePolicies::guard`,
+/// requests it refuses are answered with 403 before reaching the handler.
+///
+/// Guards compose with [`Guard::and`], [`Guard::or`], `!` ([`Not`]), [`Guard::any_of`] and
+/// [`Guard::all_of`], so access policies are declared instead of written as middlewares.
+/// Time based guards read the [`RequestClock`](crate::helpers::clock::RequestClock)
+/// of the state, in UTC.
+///
+/// # Example
+/// ```
+/// use chrono::{NaiveTime, Weekday};
+/// use foxtive_ntex::http::Method;
+/// use foxtive_ntex::http::guard::Guard;
+///
+/// let office_hours = Guard::weekdays(&[Weekday::Mon, Weekday::Tue, Weekday::Wed])
+///     .and(Guard::time_window(
+///         NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+///         NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+///     ));
+///
+/// let guard = Guard::method(Method::GET).or(office_hours.and(!Guard::header("x-dry-run", "1")));
+/// assert_eq!(
+///     format!("{guard:?}"),
+///     "(method(GET) or ((weekdays(Mon, Tue, Wed) and time(08:00:00..18:00:00)) and not(header(x-dry-run=1))))"
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Guard {
+    name: String,
+    check: Arc<Check>,
+}
+
+impl Debug for Guard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl Guard {
+    /// Custom guard, `name` describes it in logs
+    pub fn new<F>(name: &str, check: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            check: Arc::new(check),
+        }
+    }
+
+    pub fn method(method: Method) -> Self {
+        Self::new(&format!("method({method})"), move |req| {
+            *req.method() == method
+        })
+    }
+
+    /// Header sent with exactly `value`
+    pub fn header(name: &str, value: &str) -> Self {
+        let (header, value) = (name.to_string(), value.to_string());
+        Self::new(&format!("header({name}={value})"), move |req| {
+            req.headers()
+                .get_all(header.as_str())
+                .any(|sent| sent.as_bytes() == value.as_bytes())
+        })
+    }
+
+    /// Content type without its parameters (e.g. `application/json`), compared
+    /// case-insensitively
+    pub fn content_type(essence: &str) -> Self {
+        let essence = essence.to_ascii_lowercase();
+        Self::new(&format!("content_type({essence})"), move |req| {
+            req.headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(';').next())
+                .is_some_and(|sent| sent.trim().eq_ignore_ascii_case(&essence))
+        })
+    }
+
+    pub fn weekdays(days: &[Weekday]) -> Self {
+        let days = days.to_vec();
+        let names: Vec<String> = days.iter().map(ToString::to_string).collect();
+        Self::new(&format!("weekdays({})", names.join(", ")), move |req| {
+            days.contains(&now(req).weekday())
+        })
+    }
+
+    /// Time of day between `start` (included) and `end` (excluded), a window ending before
+    /// it starts spans midnight, e.g. 22:00 to 06:00
+    pub fn time_window(start: NaiveTime, end: NaiveTime) -> Self {
+        Self::new(&format!("time({start}..{end})"), move |req| {
+            in_window(start, end, now(req).time())
+        })
+    }
+
+    /// Passes when both guards pass
+    pub fn and(self, other: Guard) -> Self {
+        Self::new(&format!("({:?} and {other:?})", self), move |req| {
+            self.check(req) && other.check(req)
+        })
+    }
+
+    /// Passes when either guard passes
+    pub fn or(self, other: Guard) -> Self {
+        Self::new(&format!("({:?} or {other:?})", self), move |req| {
+            self.check(req) || other.check(req)
+        })
+    }
+
+    /// Passes when one of `guards` passes, never when empty
+    pub fn any_of(guards: impl IntoIterator<Item = Guard>) -> Self {
+        let guards: Vec<Guard> = guards.into_iter().collect();
+        Self::new(&format!("any_of{}", list(&guards)), move |req| {
+            guards.iter().any(|guard| guard.check(req))
+        })
+    }
+
+    /// Passes when all of `guards` pass, always when empty
+    pub fn all_of(guards: impl IntoIterator<Item = Guard>) -> Self {
+        let guards: Vec<Guard> = guards.into_iter().collect();
+        Self::new(&format!("all_of{}", list(&guards)), move |req| {
+            guards.iter().all(|guard| guard.check(req))
+        })
+    }
+
+    pub fn check(&self, req: &HttpRequest) -> bool {
+        (self.check)(req)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Not for Guard {
+    type Output = Guard;
+
+    /// Passes when the guard refuses
+    fn not(self) -> Self::Output {
+        Self::new(&format!("not({:?})", self), move |req| !self.check(req))
+    }
+}
+
+fn list(guards: &[Guard]) -> String {
+    let names: Vec<&str> = guards.iter().map(Guard::name).collect();
+    format!("({})", names.join(", "))
+}
+
+fn now(req: &HttpRequest) -> DateTime<Utc> {
+    req.app_state::<FoxtiveNtexState>()
+        .map(|state| state.clock.now())
+        .unwrap_or_else(Utc::now)
+}
+
+fn in_window(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    match start <= end {
+        true => start <= time && time < end,
+        false => time >= start || time < end,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::test::TestRequest;
+
+    fn at(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_primitives() {
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .header("content-type", "Application/JSON; charset=utf-8")
+            .header("x-env", "staging")
+            .to_http_request();
+
+        assert!(Guard::method(Method::POST).check(&req));
+        assert!(!Guard::method(Method::GET).check(&req));
+        assert!(Guard::header("x-env", "staging").check(&req));
+        assert!(!Guard::header("x-env", "prod").check(&req));
+        assert!(Guard::content_type("application/json").check(&req));
+        assert!(!Guard::content_type("text/plain").check(&req));
+
+        let all_days = [
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ];
+        assert!(Guard::weekdays(&all_days).check(&req));
+        assert!(!Guard::weekdays(&[]).check(&req));
+    }
+
+    #[test]
+    fn test_time_windows() {
+        assert!(in_window(at(8), at(18), at(8)));
+        assert!(!in_window(at(8), at(18), at(18)));
+        // spanning midnight
+        assert!(in_window(at(22), at(6), at(23)));
+        assert!(in_window(at(22), at(6), at(2)));
+        assert!(!in_window(at(22), at(6), at(12)));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let req = TestRequest::default()
+            .header("x-env", "staging")
+            .to_http_request();
+        let get = Guard::method(Method::GET);
+        let post = Guard::method(Method::POST);
+        let staging = Guard::header("x-env", "staging");
+
+        assert!(get.clone().and(staging.clone()).check(&req));
+        assert!(!post.clone().and(staging.clone()).check(&req));
+        assert!(post.clone().or(staging.clone()).check(&req));
+        assert!((!post.clone()).check(&req));
+        assert!(Guard::any_of([post.clone(), get.clone()]).check(&req));
+        assert!(!Guard::any_of([]).check(&req));
+        assert!(!Guard::all_of([get.clone(), post.clone()]).check(&req));
+        assert!(Guard::all_of([]).check(&req));
+
+        assert_eq!(
+            Guard::any_of([post, !get]).name(),
+            "any_of(method(POST), not(method(GET)))"
+        );
+    }
+}
@@ -1,25 +1,155 @@
+use crate::FoxtiveNtexState;
 use crate::enums::ResponseCode;
 use crate::helpers::responder::Responder;
 use crate::http::Method;
+use crate::http::manifest::RouteExample;
 use crate::http::middlewares::Middleware;
+use crate::http::middlewares::{
+    BodyParser, HeaderPolicy, MiddlewareChains, RouteLayer, RouteMatcher, RouteMeta, RoutePolicies,
+    Sunset,
+};
+use crate::http::well_known::{FAVICON_PATH, WellKnownConfig};
 use ntex::http::header;
-use ntex::web::ServiceConfig;
+use ntex::service::Identity;
 use ntex::web::middleware::Logger;
+use ntex::web::stack::WebStack;
+use ntex::web::types::Payload;
+use ntex::web::{DefaultError, HttpRequest, HttpResponse, Scope, ServiceConfig};
 use ntex::{web, web::Route as NtexRoute};
 use ntex_cors::Cors;
+use std::sync::Arc;
 use tracing::info;
 
+/// Handlers mounted under a path, built with [`Controller::new`] and its builder methods;
+/// non-exhaustive so new settings don't break callers
 #[derive(Clone)]
+#[non_exhaustive]
 pub struct Controller {
     pub path: String,
     pub handler: fn(cfg: &mut ServiceConfig),
+    /// Example requests/responses, used for the route manifest and contract tests
+    pub examples: Vec<RouteExample>,
+    /// Worker pool running the blocking sections of this controller's handlers
+    pub worker_pool: Option<String>,
+    /// Patterns registered by `handler`, relative to `path`, exposed to middlewares
+    /// through `MatchedRoute`
+    pub patterns: Vec<String>,
+    /// Request bodies accepted by this controller, others are rejected with 415 before
+    /// being read. `None` accepts any body.
+    pub body_parsers: Option<Vec<BodyParser>>,
+    /// Rate limit, timeout, body limit and auth of this controller, overriding the route's
+    pub policies: RoutePolicies,
+    /// Methods served on each pattern, other methods are answered with 405 and `Allow`
+    pub methods: Vec<(String, Vec<Method>)>,
 }
 
+/// Name of the worker pool attached to a controller, stored as scope state
+#[derive(Debug, Clone)]
+pub struct RouteWorkerPool(pub String);
+
+impl Controller {
+    pub fn new(path: &str, handler: fn(cfg: &mut ServiceConfig)) -> Self {
+        Self {
+            path: path.to_string(),
+            handler,
+            examples: vec![],
+            worker_pool: None,
+            patterns: vec![],
+            body_parsers: None,
+            policies: RoutePolicies::default(),
+            methods: vec![],
+        }
+    }
+
+    pub fn example(mut self, example: RouteExample) -> Self {
+        self.examples.push(example);
+        self
+    }
+
+    /// Run blocking sections (through the `BlockingPool` extractor) on the named worker pool
+    pub fn worker_pool(mut self, name: &str) -> Self {
+        self.worker_pool = Some(name.to_string());
+        self
+    }
+
+    /// Declare a pattern registered by the handler, so middlewares can read its params
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        self.patterns.push(pattern.to_string());
+        self
+    }
+
+    /// Restrict the request bodies this controller accepts, e.g. JSON-only API groups
+    pub fn body_parsers(mut self, parsers: &[BodyParser]) -> Self {
+        self.body_parsers = Some(parsers.to_vec());
+        self
+    }
+
+    pub fn policies(mut self, policies: RoutePolicies) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Declare the methods the handler serves on `pattern` (relative to the controller
+    /// path, `""` for the path itself). Other methods are answered with 405 and an `Allow`
+    /// header, `OPTIONS` with 204 and the same header; the pattern is declared as with
+    /// [`Controller::pattern`].
+    pub fn methods(mut self, pattern: &str, methods: &[Method]) -> Self {
+        if !self.patterns.iter().any(|known| known == pattern) {
+            self.patterns.push(pattern.to_string());
+        }
+        self.methods.push((pattern.to_string(), methods.to_vec()));
+        self
+    }
+}
+
+/// Controllers sharing a prefix, middlewares and policies. Start from [`Route::new`],
+/// fields keep being added so the struct can't be built literally
 #[derive(Clone)]
+#[non_exhaustive]
 pub struct Route {
     pub prefix: String,
     pub middlewares: Vec<Middleware>,
     pub controllers: Vec<Controller>,
+    /// Policies applied to every controller of this route
+    pub policies: RoutePolicies,
+}
+
+impl Route {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            middlewares: vec![],
+            controllers: vec![],
+            policies: RoutePolicies::default(),
+        }
+    }
+
+    pub fn middleware(mut self, middleware: Middleware) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    pub fn controller(mut self, controller: Controller) -> Self {
+        self.controllers.push(controller);
+        self
+    }
+
+    pub fn policies(mut self, policies: RoutePolicies) -> Self {
+        self.policies = policies;
+        self
+    }
+
+    /// Headers added to every response of this route, see [`HeaderPolicy`]
+    pub fn headers(mut self, headers: HeaderPolicy) -> Self {
+        self.policies.headers = Some(headers);
+        self
+    }
+
+    /// Retire this route on a schedule, see [`Sunset`]
+    pub fn sunset(mut self, sunset: Sunset) -> Self {
+        self.policies.sunset = Some(sunset);
+        self
+    }
 }
 
 pub fn register_routes(config: &mut ServiceConfig, routes: Vec<Route>) {
@@ -33,32 +163,68 @@ pub fn register_routes(config: &mut ServiceConfig, routes: Vec<Route>) {
                 if path.is_empty() { "/" } else { path.as_str() }
             );
 
+            let matcher = RouteMatcher::new(&path, &controller.patterns);
+            register_chain(&path, &route, controller);
+
             if path.is_empty() {
-                config.service(web::scope("").configure(controller.handler));
+                config.service(make_scope("", &route, controller, matcher));
             } else if !route.middlewares.is_empty() {
                 let total = route.middlewares.len();
 
                 if total == 1 {
-                    let scope = web::scope(path.as_str())
-                        .wrap(route.middlewares.first().unwrap().middleware())
-                        .configure(controller.handler);
+                    let scope = make_scope(path.as_str(), &route, controller, matcher.clone())
+                        .wrap(
+                            route
+                                .middlewares
+                                .first()
+                                .unwrap()
+                                .route_middleware(matcher.clone(), 0),
+                        );
                     config.service(scope);
                 } else if total == 2 {
-                    let scope = web::scope(path.as_str())
-                        .wrap(route.middlewares.first().unwrap().middleware())
-                        .wrap(route.middlewares.last().unwrap().middleware())
-                        .configure(controller.handler);
+                    let scope = make_scope(path.as_str(), &route, controller, matcher.clone())
+                        .wrap(
+                            route
+                                .middlewares
+                                .first()
+                                .unwrap()
+                                .route_middleware(matcher.clone(), 0),
+                        )
+                        .wrap(
+                            route
+                                .middlewares
+                                .last()
+                                .unwrap()
+                                .route_middleware(matcher.clone(), total - 1),
+                        );
                     config.service(scope);
                 } else {
-                    let scope = web::scope(path.as_str())
-                        .wrap(route.middlewares.first().unwrap().middleware())
-                        .wrap(route.middlewares.get(1).unwrap().middleware())
-                        .wrap(route.middlewares.last().unwrap().middleware())
-                        .configure(controller.handler);
+                    let scope = make_scope(path.as_str(), &route, controller, matcher.clone())
+                        .wrap(
+                            route
+                                .middlewares
+                                .first()
+                                .unwrap()
+                                .route_middleware(matcher.clone(), 0),
+                        )
+                        .wrap(
+                            route
+                                .middlewares
+                                .get(1)
+                                .unwrap()
+                                .route_middleware(matcher.clone(), 1),
+                        )
+                        .wrap(
+                            route
+                                .middlewares
+                                .last()
+                                .unwrap()
+                                .route_middleware(matcher.clone(), total - 1),
+                        );
                     config.service(scope);
                 }
             } else {
-                config.service(web::scope(path.as_str()).configure(controller.handler));
+                config.service(make_scope(path.as_str(), &route, controller, matcher));
             }
         }
     }
@@ -66,13 +232,95 @@ pub fn register_routes(config: &mut ServiceConfig, routes: Vec<Route>) {
     tracing::debug!("route discovery finished :)");
 }
 
+/// Record the layers of a route group for [`MiddlewareChains`], mirroring how
+/// `register_routes` wraps the scope: the last wrapped middleware runs first
+fn register_chain(path: &str, route: &Route, controller: &Controller) {
+    let total = route.middlewares.len();
+    let wrapped: Vec<usize> = match total {
+        _ if path.is_empty() => vec![],
+        0..=2 => (0..total).collect(),
+        _ => vec![0, 1, total - 1],
+    };
+
+    let mut layers: Vec<(String, &'static str, bool)> = wrapped
+        .iter()
+        .rev()
+        .map(|&index| {
+            let kind = route.middlewares[index].kind();
+            (format!("middleware#{index}"), kind, false)
+        })
+        .collect();
+    layers.extend(
+        route
+            .middlewares
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !wrapped.contains(index))
+            .map(|(index, middleware)| (format!("middleware#{index}"), middleware.kind(), true)),
+    );
+
+    let policies = route.policies.merge(&controller.policies);
+    let checks = [
+        ("sunset", policies.sunset.is_some()),
+        ("body_parsers", controller.body_parsers.is_some()),
+        ("body_limit", policies.body_limit.is_some()),
+        ("rate_limit", policies.rate_limit.is_some()),
+        ("guard", policies.guard.is_some()),
+        ("auth", policies.auth.is_some()),
+        ("timeout", policies.timeout.is_some()),
+    ];
+    for (name, enabled) in checks {
+        if enabled {
+            layers.push((name.to_string(), "policy", false));
+        }
+    }
+    layers.push(("handler".to_string(), "handler", false));
+
+    MiddlewareChains::register(path, layers);
+}
+
+fn make_scope(
+    path: &str,
+    route: &Route,
+    controller: &Controller,
+    matcher: Arc<RouteMatcher>,
+) -> Scope<DefaultError, WebStack<Identity, RouteLayer, DefaultError>> {
+    let mut scope = web::scope(path).configure(controller.handler);
+
+    if let Some(pool) = &controller.worker_pool {
+        scope = scope.state(RouteWorkerPool(pool.clone()));
+    }
+
+    scope.wrap(RouteLayer::new(
+        RouteMeta {
+            scope: path.to_string(),
+            body_parsers: controller.body_parsers.clone(),
+            policies: route.policies.merge(&controller.policies),
+            methods: controller
+                .methods
+                .iter()
+                .map(|(pattern, methods)| (format!("{path}{pattern}"), methods.clone()))
+                .collect(),
+        },
+        matcher,
+    ))
+}
+
 pub fn setup_logger() -> Logger {
     Logger::default()
-        .exclude("/favicon.ico")
+        .exclude(FAVICON_PATH)
         .exclude("/system/health-check")
         .exclude("/api/v1/admin/health-check")
 }
 
+/// [`setup_logger`] also leaving out the well-known paths registered by the config
+pub fn setup_logger_with_well_known(config: &WellKnownConfig) -> Logger {
+    config
+        .paths()
+        .into_iter()
+        .fold(setup_logger(), Logger::exclude)
+}
+
 pub fn setup_cors(origins: Vec<String>, methods: Vec<Method>) -> Cors {
     let mut cors = Cors::new().send_wildcard();
 
@@ -88,7 +336,29 @@ pub fn setup_cors(origins: Vec<String>, methods: Vec<Method>) -> Cors {
         cors = cors.allowed_origin(origin.as_str());
     }
 
-    let allowed_methods = match methods.is_empty() {
+    cors.allowed_methods(cors_methods(methods))
+        .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])
+        .allowed_header(header::CONTENT_TYPE)
+        .max_age(3600)
+}
+
+/// Paths of the controllers whose policies turn CORS off
+pub(crate) fn cors_free_prefixes(routes: &[Route]) -> Vec<String> {
+    routes
+        .iter()
+        .flat_map(|route| {
+            route
+                .controllers
+                .iter()
+                .filter(|controller| route.policies.merge(&controller.policies).cors == Some(false))
+                .map(|controller| format!("{}{}", route.prefix, controller.path))
+        })
+        .collect()
+}
+
+/// Methods allowed by CORS, a standard set when none is configured
+pub(crate) fn cors_methods(methods: Vec<Method>) -> Vec<Method> {
+    match methods.is_empty() {
         false => methods,
         true => vec![
             Method::GET,
@@ -98,20 +368,32 @@ pub fn setup_cors(origins: Vec<String>, methods: Vec<Method>) -> Cors {
             Method::DELETE,
             Method::OPTIONS,
         ],
-    };
-
-    cors.allowed_methods(allowed_methods)
-        .allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])
-        .allowed_header(header::CONTENT_TYPE)
-        .max_age(3600)
+    }
 }
 
+/// Serves the dynamic routes (see `FoxtiveNtexState::dynamic_routes`), responds
+/// with 404 to anything else
 pub fn ntex_default_service() -> NtexRoute {
-    web::to(|| async {
-        Responder::message("Requested Resource(s) Not Found", ResponseCode::NotFound)
+    web::to(|req: HttpRequest, payload: Payload| async move {
+        if let Some(state) = req.app_state::<FoxtiveNtexState>().cloned()
+            && let Ok(resp) = state.dynamic_routes.dispatch(req, payload).await
+        {
+            return resp;
+        }
+
+        not_found()
     })
 }
 
+/// Default service of the additional servers, which don't dispatch the dynamic routes
+pub(crate) fn not_found_service() -> NtexRoute {
+    web::to(|| async { not_found() })
+}
+
+fn not_found() -> HttpResponse {
+    Responder::message("Requested Resource(s) Not Found", ResponseCode::NotFound)
+}
+
 pub fn register_middlewares(_config: &mut ServiceConfig) {
     // for middleware in middlewares() {
     // }
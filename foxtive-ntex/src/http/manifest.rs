@@ -0,0 +1,328 @@
+use crate::http::Method;
+use crate::http::kernel::Route;
+use ntex::http::StatusCode;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value, json};
+
+#[cfg(any(test, feature = "dev-tools"))]
+pub use replay::{ExampleOutcome, assert_examples, replay_examples};
+
+/// An example request along with the response it is expected to produce.
+///
+/// Examples are attached to controllers, listed in the [`RouteManifest`] and can be
+/// replayed against an in-memory app with `replay_examples` (feature `dev-tools`) to keep
+/// docs honest.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::Method;
+/// use foxtive_ntex::http::manifest::RouteExample;
+/// use ntex::http::StatusCode;
+///
+/// let example = RouteExample::new("fetch user", Method::GET, "/1")
+///     .pattern("/{id}")
+///     .responds_with(StatusCode::OK)
+///     .response_json(serde_json::json!({"success": true}));
+/// ```
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteExample {
+    pub name: String,
+    #[serde(serialize_with = "serialize_method")]
+    pub method: Method,
+    /// Concrete request path, relative to the controller
+    pub path: String,
+    /// Route pattern used in the OpenAPI output, defaults to `path`
+    pub pattern: String,
+    pub headers: Vec<(String, String)>,
+    pub request_body: Option<Value>,
+    pub status: u16,
+    /// Expected response body, compared as a subset of the actual JSON body
+    pub response_body: Option<Value>,
+}
+
+impl RouteExample {
+    pub fn new(name: &str, method: Method, path: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            method,
+            path: path.to_string(),
+            pattern: path.to_string(),
+            headers: vec![],
+            request_body: None,
+            status: StatusCode::OK.as_u16(),
+            response_body: None,
+        }
+    }
+
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        self.pattern = pattern.to_string();
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn request_json(mut self, body: Value) -> Self {
+        self.request_body = Some(body);
+        self
+    }
+
+    pub fn responds_with(mut self, status: StatusCode) -> Self {
+        self.status = status.as_u16();
+        self
+    }
+
+    pub fn response_json(mut self, body: Value) -> Self {
+        self.response_body = Some(body);
+        self
+    }
+}
+
+fn serialize_method<S: Serializer>(method: &Method, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(method.as_str())
+}
+
+/// A controller as seen from outside: its full path and attached examples
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub middlewares: usize,
+    pub examples: Vec<RouteExample>,
+}
+
+/// Listing of registered controllers, built from the same routes passed to the server
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteManifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl RouteManifest {
+    pub fn from_routes(routes: &[Route]) -> Self {
+        let entries = routes
+            .iter()
+            .flat_map(|route| {
+                route.controllers.iter().map(|controller| ManifestEntry {
+                    path: format!("{}{}", route.prefix, controller.path),
+                    middlewares: route.middlewares.len(),
+                    examples: controller.examples.clone(),
+                })
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    /// Render the examples as an OpenAPI 3 `paths` object
+    pub fn openapi_paths(&self) -> Value {
+        let mut paths = Map::new();
+
+        for entry in &self.entries {
+            for example in &entry.examples {
+                let path = format!("{}{}", entry.path, example.pattern);
+                let operation = paths
+                    .entry(path)
+                    .or_insert_with(|| json!({}))
+                    .as_object_mut()
+                    .unwrap()
+                    .entry(example.method.as_str().to_lowercase())
+                    .or_insert_with(|| json!({"responses": {}}));
+
+                if let Some(body) = &example.request_body {
+                    operation["requestBody"]["content"]["application/json"]["examples"]
+                        [&example.name] = json!({ "value": body });
+                }
+
+                let response = &mut operation["responses"][example.status.to_string()];
+                if response.get("description").is_none() {
+                    let reason = StatusCode::from_u16(example.status)
+                        .ok()
+                        .and_then(|s| s.canonical_reason())
+                        .unwrap_or_default();
+                    response["description"] = json!(reason);
+                }
+
+                if let Some(body) = &example.response_body {
+                    response["content"]["application/json"]["examples"][&example.name] =
+                        json!({ "value": body });
+                }
+            }
+        }
+
+        Value::Object(paths)
+    }
+}
+
+/// Replay of the examples against an in-memory app, built on `ntex::web::test` so only
+/// compiled for tests and with the `dev-tools` feature
+#[cfg(any(test, feature = "dev-tools"))]
+mod replay {
+    use super::*;
+    use crate::http::kernel::register_routes;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+    use ntex::web::{App, ServiceConfig};
+
+    /// Outcome of replaying a single example
+    #[derive(Debug, Clone)]
+    pub struct ExampleOutcome {
+        /// `METHOD /full/path (example name)`
+        pub example: String,
+        /// Mismatch description, `None` when the response matched the example
+        pub failure: Option<String>,
+    }
+
+    impl ExampleOutcome {
+        pub fn passed(&self) -> bool {
+            self.failure.is_none()
+        }
+    }
+
+    /// Replay every example against an in-memory app built from `routes`.
+    ///
+    /// `configure` is applied to the app before the routes, use it to register state the
+    /// handlers depend on. Expected response bodies are matched as a subset of the actual
+    /// body, so volatile fields like `timestamp` can be left out of examples.
+    pub async fn replay_examples<F>(routes: Vec<Route>, configure: F) -> Vec<ExampleOutcome>
+    where
+        F: FnOnce(&mut ServiceConfig),
+    {
+        let manifest = RouteManifest::from_routes(&routes);
+        let app = init_service(
+            App::new()
+                .configure(configure)
+                .configure(|cfg| register_routes(cfg, routes)),
+        )
+        .await;
+
+        let mut outcomes = vec![];
+        for entry in manifest.entries {
+            for example in entry.examples {
+                let uri = format!("{}{}", entry.path, example.path);
+                let mut req = TestRequest::with_uri(&uri).method(example.method.clone());
+                for (name, value) in &example.headers {
+                    req = req.header(name.as_str(), value.as_str());
+                }
+
+                if let Some(body) = &example.request_body {
+                    req = req.set_json(body);
+                }
+
+                let resp = call_service(&app, req.to_request()).await;
+                let status = resp.status().as_u16();
+                let body = read_body(resp).await;
+
+                let failure = if status != example.status {
+                    Some(format!("expected status {}, got {status}", example.status))
+                } else {
+                    match &example.response_body {
+                        None => None,
+                        Some(expected) => match serde_json::from_slice::<Value>(&body) {
+                            Err(err) => Some(format!("response body is not valid json: {err}")),
+                            Ok(actual) if !json_contains(&actual, expected) => {
+                                Some(format!("expected body to contain {expected}, got {actual}"))
+                            }
+                            Ok(_) => None,
+                        },
+                    }
+                };
+
+                outcomes.push(ExampleOutcome {
+                    example: format!("{} {uri} ({})", example.method, example.name),
+                    failure,
+                });
+            }
+        }
+
+        outcomes
+    }
+
+    /// Replay the examples and panic with a report listing every mismatch
+    pub async fn assert_examples<F>(routes: Vec<Route>, configure: F)
+    where
+        F: FnOnce(&mut ServiceConfig),
+    {
+        let failures: Vec<_> = replay_examples(routes, configure)
+            .await
+            .into_iter()
+            .filter_map(|o| o.failure.map(|f| format!("{}: {f}", o.example)))
+            .collect();
+
+        assert!(
+            failures.is_empty(),
+            "route examples out of date:\n{}",
+            failures.join("\n")
+        );
+    }
+
+    fn json_contains(actual: &Value, expected: &Value) -> bool {
+        match (actual, expected) {
+            (Value::Object(actual), Value::Object(expected)) => {
+                expected.iter().all(|(key, value)| {
+                    actual
+                        .get(key)
+                        .is_some_and(|actual| json_contains(actual, value))
+                })
+            }
+            (Value::Array(actual), Value::Array(expected)) => {
+                actual.len() == expected.len()
+                    && actual
+                        .iter()
+                        .zip(expected)
+                        .all(|(a, e)| json_contains(a, e))
+            }
+            _ => actual == expected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::ResponseCode;
+    use crate::helpers::responder::Responder;
+    use crate::http::kernel::Controller;
+    use ntex::web;
+    use ntex::web::ServiceConfig;
+
+    fn users(cfg: &mut ServiceConfig) {
+        cfg.route(
+            "/{id}",
+            web::get().to(|| async { Responder::send(json!({"id": 1}), ResponseCode::Ok) }),
+        );
+    }
+
+    fn routes(expected_id: i32) -> Vec<Route> {
+        vec![
+            Route::new("/api").controller(
+                Controller::new("/users", users).example(
+                    RouteExample::new("fetch user", Method::GET, "/1")
+                        .pattern("/{id}")
+                        .response_json(json!({"success": true, "data": {"id": expected_id}})),
+                ),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_openapi_paths() {
+        let paths = RouteManifest::from_routes(&routes(1)).openapi_paths();
+        let response = &paths["/api/users/{id}"]["get"]["responses"]["200"];
+
+        assert_eq!(response["description"], "OK");
+        assert_eq!(
+            response["content"]["application/json"]["examples"]["fetch user"]["value"]["data"]["id"],
+            1
+        );
+    }
+
+    #[ntex::test]
+    async fn test_replay_examples() {
+        let outcomes = replay_examples(routes(1), |_| {}).await;
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].passed(), "{:?}", outcomes[0].failure);
+
+        let outcomes = replay_examples(routes(2), |_| {}).await;
+        assert!(!outcomes[0].passed());
+    }
+}
@@ -0,0 +1,320 @@
+use ntex::http::header::{self, HeaderMap, HeaderValue};
+use ntex::http::{StatusCode, Uri};
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use std::sync::Arc;
+use tracing::{debug, error};
+
+/// How a request hitting an [`Alias`] reaches its new path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliasMode {
+    /// `301 Moved Permanently` to the new path
+    Permanent,
+    /// `307 Temporary Redirect`, keeping the method and body
+    Temporary,
+    /// Served by the new path's controller without a round trip
+    Rewrite,
+}
+
+/// Old path kept working after an API reorganization, registered with `ServerConfig::alias`.
+///
+/// The old path matches itself and everything below it, `/old/users` also covers
+/// `/old/users/5`, which becomes `/api/v1/users/5`; an alias of `/` only covers `/`.
+/// The query string is preserved.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::middlewares::Alias;
+///
+/// let alias = Alias::rewrite("/old/users", "/api/v1/users")
+///     .deprecated()
+///     .sunset("Sat, 01 Aug 2026 00:00:00 GMT");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Alias {
+    pub(crate) from: String,
+    pub(crate) to: String,
+    pub(crate) mode: AliasMode,
+    pub(crate) deprecated: bool,
+    pub(crate) sunset: Option<String>,
+}
+
+impl Alias {
+    pub fn new(from: &str, to: &str, mode: AliasMode) -> Self {
+        Self {
+            from: normalize(from),
+            to: normalize(to),
+            mode,
+            deprecated: false,
+            sunset: None,
+        }
+    }
+
+    pub fn permanent(from: &str, to: &str) -> Self {
+        Self::new(from, to, AliasMode::Permanent)
+    }
+
+    pub fn temporary(from: &str, to: &str) -> Self {
+        Self::new(from, to, AliasMode::Temporary)
+    }
+
+    pub fn rewrite(from: &str, to: &str) -> Self {
+        Self::new(from, to, AliasMode::Rewrite)
+    }
+
+    /// Send `Deprecation: true` and a `Link` to the new path as `successor-version`
+    pub fn deprecated(mut self) -> Self {
+        self.deprecated = true;
+        self
+    }
+
+    /// Send a `Sunset` header with the HTTP date the old path goes away, implies `deprecated`
+    pub fn sunset(mut self, http_date: &str) -> Self {
+        self.deprecated = true;
+        self.sunset = Some(http_date.to_string());
+        self
+    }
+
+    /// New path of `path`, `None` when the alias doesn't cover it
+    fn target(&self, path: &str) -> Option<String> {
+        let rest = match self.from.as_str() {
+            "/" => (path == "/").then_some("")?,
+            from => path.strip_prefix(from)?,
+        };
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+
+        let target = match self.to.as_str() {
+            "/" => rest,
+            to => &format!("{to}{rest}"),
+        };
+        // `//host` would be a protocol-relative redirect to another site
+        Some(format!("/{}", target.trim_start_matches('/')))
+    }
+
+    fn decorate(&self, headers: &mut HeaderMap) {
+        if !self.deprecated {
+            return;
+        }
+
+        headers.insert(
+            header::HeaderName::from_static("deprecation"),
+            HeaderValue::from_static("true"),
+        );
+        if let Ok(link) =
+            HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", self.to))
+        {
+            headers.append(header::LINK, link);
+        }
+        if let Some(sunset) = &self.sunset
+            && let Ok(value) = HeaderValue::from_str(sunset)
+        {
+            headers.insert(header::HeaderName::from_static("sunset"), value);
+        }
+    }
+}
+
+/// `/` for the root, without the trailing slash otherwise
+fn normalize(path: &str) -> String {
+    format!("/{}", path.trim_matches('/'))
+}
+
+/// Middleware applying the [`Alias`] table before routing, the first matching alias wins
+#[derive(Clone)]
+pub(crate) struct AliasTable {
+    aliases: Arc<Vec<Alias>>,
+}
+
+impl AliasTable {
+    pub(crate) fn new(aliases: Vec<Alias>) -> Self {
+        Self {
+            aliases: Arc::new(aliases),
+        }
+    }
+
+    fn resolve(&self, path: &str) -> Option<(&Alias, String)> {
+        self.aliases
+            .iter()
+            .find_map(|alias| alias.target(path).map(|target| (alias, target)))
+    }
+}
+
+impl<S> ServiceMiddleware<S> for AliasTable {
+    type Service = AliasTableService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        AliasTableService {
+            service,
+            table: self.clone(),
+        }
+    }
+}
+
+pub(crate) struct AliasTableService<S> {
+    service: S,
+    table: AliasTable,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for AliasTableService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        mut req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let (alias, location) = match self.table.resolve(req.path()) {
+            None => return ctx.call(&self.service, req).await,
+            Some((alias, target)) => match req.uri().query() {
+                Some(query) => (alias, format!("{target}?{query}")),
+                None => (alias, target),
+            },
+        };
+
+        debug!("[alias] {} -> {location} ({:?})", req.path(), alias.mode);
+
+        let status = match alias.mode {
+            AliasMode::Permanent => StatusCode::MOVED_PERMANENTLY,
+            AliasMode::Temporary => StatusCode::TEMPORARY_REDIRECT,
+            AliasMode::Rewrite => {
+                match Uri::try_from(location.as_str()) {
+                    Ok(uri) => {
+                        req.head_mut().uri = uri.clone();
+                        req.match_info_mut().set(uri);
+                    }
+                    Err(err) => error!("[alias] invalid rewritten uri '{location}': {err}"),
+                }
+
+                let mut resp = ctx.call(&self.service, req).await?;
+                alias.decorate(resp.headers_mut());
+                return Ok(resp);
+            }
+        };
+
+        let mut resp = HttpResponse::build(status)
+            .header(header::LOCATION, location)
+            .finish();
+        alias.decorate(resp.headers_mut());
+        Ok(req.into_response(resp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+    use ntex::web::{App, HttpRequest};
+
+    async fn users(req: HttpRequest) -> String {
+        format!("users {}", req.uri())
+    }
+
+    fn table() -> AliasTable {
+        AliasTable::new(vec![
+            Alias::rewrite("/old/users", "/api/v1/users").sunset("Sat, 01 Aug 2026 00:00:00 GMT"),
+            Alias::permanent("/legacy/", "/api/v1"),
+            Alias::temporary("/tmp", "/api/v1/users"),
+        ])
+    }
+
+    #[test]
+    fn test_target_matches_segments() {
+        let alias = Alias::permanent("/old/users", "/api/v1/users");
+
+        assert_eq!(alias.target("/old/users").unwrap(), "/api/v1/users");
+        assert_eq!(alias.target("/old/users/5").unwrap(), "/api/v1/users/5");
+        assert!(alias.target("/old/usersx").is_none());
+        assert!(alias.target("/other").is_none());
+        assert_eq!(Alias::rewrite("/old", "/").target("/old").unwrap(), "/");
+    }
+
+    #[test]
+    fn test_targets_stay_on_the_site() {
+        let alias = Alias::permanent("/old", "/");
+        assert_eq!(alias.target("/old//evil.com").unwrap(), "/evil.com");
+        assert_eq!(alias.target("/old/users").unwrap(), "/users");
+
+        let alias = Alias::permanent("/old", "/api");
+        assert_eq!(alias.target("/old//evil.com").unwrap(), "/api//evil.com");
+    }
+
+    #[test]
+    fn test_root_alias_only_covers_the_root() {
+        let alias = Alias::permanent("/", "/home");
+        assert_eq!(alias.target("/").unwrap(), "/home");
+        assert!(alias.target("/users").is_none());
+        assert!(alias.target("//evil.com").is_none());
+    }
+
+    #[ntex::test]
+    async fn test_redirect_to_root_is_not_protocol_relative() {
+        let app = init_service(
+            App::new()
+                .wrap(AliasTable::new(vec![Alias::permanent("/old", "/")]))
+                .route("/", web::get().to(users)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/old//evil.com").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(resp.headers().get(header::LOCATION).unwrap(), "/evil.com");
+    }
+
+    #[ntex::test]
+    async fn test_rewrites_before_routing() {
+        let app = init_service(
+            App::new()
+                .wrap(table())
+                .route("/api/v1/users", web::get().to(users))
+                .route("/api/v1/users/{id}", web::get().to(users)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/old/users/5?page=2").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("deprecation").unwrap(), "true");
+        assert_eq!(
+            resp.headers().get(header::LINK).unwrap(),
+            "</api/v1/users>; rel=\"successor-version\""
+        );
+        assert!(resp.headers().contains_key("sunset"));
+        assert_eq!(read_body(resp).await, "users /api/v1/users/5?page=2");
+    }
+
+    #[ntex::test]
+    async fn test_redirects() {
+        let app = init_service(
+            App::new()
+                .wrap(table())
+                .route("/api/v1/users", web::get().to(users)),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/legacy/users?q=1").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "/api/v1/users?q=1"
+        );
+        assert!(!resp.headers().contains_key("deprecation"));
+
+        let req = TestRequest::with_uri("/tmp").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::TEMPORARY_REDIRECT);
+
+        let req = TestRequest::with_uri("/api/v1/users").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(read_body(resp).await, "users /api/v1/users");
+    }
+}
@@ -0,0 +1,120 @@
+use crate::http::extractors::CancellationToken;
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, WebRequest, WebResponse};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::debug;
+
+static CANCELLED_REQUESTS: AtomicU64 = AtomicU64::new(0);
+
+/// Number of requests abandoned by their client before the response was ready
+pub fn cancelled_requests() -> u64 {
+    CANCELLED_REQUESTS.load(Ordering::Relaxed)
+}
+
+/// Cancels the request's token when the call is dropped before completing, which is
+/// what the server does with in-flight requests of a closed connection
+struct DisconnectGuard {
+    token: CancellationToken,
+    path: String,
+    completed: bool,
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        if !self.completed {
+            CANCELLED_REQUESTS.fetch_add(1, Ordering::Relaxed);
+            debug!("[cancellation] client went away during {}", self.path);
+            self.token.cancel();
+        }
+    }
+}
+
+/// Attaches a [`CancellationToken`] to every request
+#[derive(Clone)]
+pub(crate) struct RequestCancellation;
+
+impl<S> ServiceMiddleware<S> for RequestCancellation {
+    type Service = RequestCancellationService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RequestCancellationService { service }
+    }
+}
+
+pub(crate) struct RequestCancellationService<S> {
+    service: S,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for RequestCancellationService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let token = CancellationToken::new();
+        req.extensions_mut().insert(token.clone());
+
+        let mut guard = DisconnectGuard {
+            token,
+            path: req.path().to_string(),
+            completed: false,
+        };
+
+        let result = ctx.call(&self.service, req).await;
+        guard.completed = true;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use ntex::web::{App, HttpResponse};
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    #[ntex::test]
+    async fn test_dropped_requests_cancel_their_token() {
+        static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+        let app = init_service(
+            App::new()
+                .route(
+                    "/slow",
+                    web::get().to(|token: CancellationToken| async move {
+                        // hand the token over, as a spawned job would
+                        let _ = TOKEN.set(token);
+                        ntex::time::sleep(Duration::from_secs(5)).await;
+                        HttpResponse::Ok().finish()
+                    }),
+                )
+                .route(
+                    "/fast",
+                    web::get().to(|| async { HttpResponse::Ok().finish() }),
+                )
+                .wrap(RequestCancellation),
+        )
+        .await;
+
+        let before = cancelled_requests();
+        call_service(&app, TestRequest::with_uri("/fast").to_request()).await;
+        assert_eq!(cancelled_requests(), before);
+
+        // the client disconnecting drops the in-flight call
+        let call = call_service(&app, TestRequest::with_uri("/slow").to_request());
+        let _ = ntex::time::timeout(Duration::from_millis(20), call).await;
+
+        assert!(TOKEN.get().unwrap().is_cancelled());
+        assert_eq!(cancelled_requests(), before + 1);
+    }
+}
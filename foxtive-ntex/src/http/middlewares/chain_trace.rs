@@ -0,0 +1,253 @@
+use crate::http::server::ServerSettings;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// Timings kept per layer, older ones are dropped
+const SAMPLES: usize = 100;
+
+static CHAINS: OnceLock<Mutex<BTreeMap<String, GroupChain>>> = OnceLock::new();
+static TIMINGS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+struct GroupChain {
+    layers: Vec<(String, &'static str, bool)>,
+    timings: HashMap<String, VecDeque<Duration>>,
+}
+
+/// Timing of a layer over the recent requests
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerTiming {
+    pub samples: usize,
+    pub avg_us: u64,
+    pub max_us: u64,
+}
+
+/// Layer a request of a route group goes through
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainLayer {
+    /// e.g. `middleware#1`, `guard`, `handler`
+    pub name: String,
+    /// `before`, `after`, `policy` or `handler`
+    pub kind: &'static str,
+    /// declared on the route but never wrapped around the group
+    pub skipped: bool,
+    pub timing: Option<LayerTiming>,
+}
+
+/// Layers of a route group in execution order
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteChain {
+    pub group: String,
+    pub layers: Vec<ChainLayer>,
+}
+
+/// Middleware and policy chain resolved for every route group by `register_routes`, in a
+/// registry shared by all the servers of the process, with
+/// the timing of each layer over the recent requests when enabled through
+/// `ServerConfig::middleware_timings`. Rendered by the `admin::middleware_chains`
+/// controller.
+///
+/// Middlewares are listed in execution order, outermost first: an `after` middleware
+/// listed first sees the response last.
+pub struct MiddlewareChains;
+
+impl MiddlewareChains {
+    /// Process default, for the apps not started with a `ServerConfig`
+    pub fn set_timings(enabled: bool) {
+        TIMINGS.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether the server handling the current request records timings, the process
+    /// default otherwise
+    pub fn timings_enabled() -> bool {
+        ServerSettings::read(|settings| settings.middleware_timings)
+            .unwrap_or_else(|| TIMINGS.load(Ordering::Relaxed))
+    }
+
+    /// Chains of every registered route group, sorted by group
+    pub fn snapshot() -> Vec<RouteChain> {
+        let Ok(chains) = chains().lock() else {
+            return vec![];
+        };
+
+        chains
+            .iter()
+            .map(|(group, chain)| RouteChain {
+                group: group.clone(),
+                layers: chain
+                    .layers
+                    .iter()
+                    .map(|(name, kind, skipped)| ChainLayer {
+                        name: name.clone(),
+                        kind,
+                        skipped: *skipped,
+                        timing: chain.timings.get(name).map(timing),
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Chain of `group`, `None` when not registered
+    pub fn group(group: &str) -> Option<RouteChain> {
+        Self::snapshot()
+            .into_iter()
+            .find(|chain| chain.group == group)
+    }
+
+    /// Record the layers of `group`, `(name, kind, skipped)` in execution order
+    pub(crate) fn register(group: &str, layers: Vec<(String, &'static str, bool)>) {
+        if let Ok(mut chains) = chains().lock() {
+            chains.insert(
+                group_label(group),
+                GroupChain {
+                    layers,
+                    timings: HashMap::new(),
+                },
+            );
+        }
+    }
+
+    pub(crate) fn record(group: &str, layer: &str, elapsed: Duration) {
+        if !Self::timings_enabled() {
+            return;
+        }
+
+        if let Ok(mut chains) = chains().lock()
+            && let Some(chain) = chains.get_mut(&group_label(group))
+        {
+            let samples = chain.timings.entry(layer.to_string()).or_default();
+            if samples.len() == SAMPLES {
+                samples.pop_front();
+            }
+            samples.push_back(elapsed);
+        }
+    }
+}
+
+fn chains() -> &'static Mutex<BTreeMap<String, GroupChain>> {
+    CHAINS.get_or_init(Default::default)
+}
+
+fn group_label(group: &str) -> String {
+    match group.is_empty() {
+        true => "/".to_string(),
+        false => group.to_string(),
+    }
+}
+
+fn timing(samples: &VecDeque<Duration>) -> LayerTiming {
+    let total: Duration = samples.iter().sum();
+    LayerTiming {
+        samples: samples.len(),
+        avg_us: (total / samples.len().max(1) as u32).as_micros() as u64,
+        max_us: samples
+            .iter()
+            .max()
+            .copied()
+            .unwrap_or_default()
+            .as_micros() as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::kernel::{Controller, Route, register_routes};
+    use crate::http::middlewares::{Middleware, RoutePolicies};
+    use foxtive::prelude::AppResult;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use ntex::web::{self, App, HttpRequest, HttpResponse, ServiceConfig};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    type Before = Pin<Box<dyn Future<Output = AppResult<HttpRequest>>>>;
+
+    static CALLS: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+    fn visit(req: HttpRequest, name: &'static str) -> Before {
+        CALLS.lock().unwrap().push(name);
+        Box::pin(async move { Ok(req) })
+    }
+
+    fn first(req: HttpRequest) -> Before {
+        visit(req, "middleware#0")
+    }
+
+    fn second(req: HttpRequest) -> Before {
+        visit(req, "middleware#1")
+    }
+
+    fn third(req: HttpRequest) -> Before {
+        visit(req, "middleware#2")
+    }
+
+    fn fourth(req: HttpRequest) -> Before {
+        visit(req, "middleware#3")
+    }
+
+    fn ping(cfg: &mut ServiceConfig) {
+        cfg.route("", web::get().to(|| async { HttpResponse::Ok() }));
+    }
+
+    #[ntex::test]
+    async fn test_chain_follows_execution_order() {
+        let routes = vec![
+            Route::new("/chain-trace")
+                .middleware(Middleware::Before(first))
+                .middleware(Middleware::Before(second))
+                .middleware(Middleware::Before(third))
+                .middleware(Middleware::Before(fourth))
+                .policies(RoutePolicies::default().body_limit(1024))
+                .controller(Controller::new("/ping", ping)),
+        ];
+        let settings = ServerSettings {
+            middleware_timings: true,
+            ..Default::default()
+        };
+        let app = init_service(
+            App::new()
+                .state(std::sync::Arc::new(settings))
+                .configure(|cfg| register_routes(cfg, routes))
+                .wrap(crate::http::server::SettingsScope),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/chain-trace/ping").to_request();
+        assert!(call_service(&app, req).await.status().is_success());
+
+        let chain = MiddlewareChains::group("/chain-trace/ping").unwrap();
+        let executed: Vec<&str> = chain
+            .layers
+            .iter()
+            .filter(|layer| layer.kind == "before" && !layer.skipped)
+            .map(|layer| layer.name.as_str())
+            .collect();
+        assert_eq!(executed, *CALLS.lock().unwrap());
+
+        let names: Vec<(&str, bool)> = chain
+            .layers
+            .iter()
+            .map(|layer| (layer.name.as_str(), layer.skipped))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("middleware#3", false),
+                ("middleware#1", false),
+                ("middleware#0", false),
+                ("middleware#2", true),
+                ("body_limit", false),
+                ("handler", false),
+            ]
+        );
+
+        let handler = chain.layers.last().unwrap();
+        assert_eq!(handler.timing.as_ref().unwrap().samples, 1);
+        assert_eq!(chain.layers[0].timing.as_ref().unwrap().samples, 1);
+        assert!(chain.layers[3].timing.is_none());
+    }
+}
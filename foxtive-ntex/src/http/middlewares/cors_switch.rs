@@ -0,0 +1,166 @@
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, WebRequest, WebResponse};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Service shared by the CORS middleware and the requests bypassing it
+pub(crate) struct Shared<S>(Rc<S>);
+
+impl<S, Req> Service<Req> for Shared<S>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn ready(&self, ctx: ServiceCtx<'_, Self>) -> Result<(), Self::Error> {
+        ctx.ready(&*self.0).await
+    }
+
+    async fn call(
+        &self,
+        req: Req,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        ctx.call(&*self.0, req).await
+    }
+}
+
+/// Applies a CORS middleware unless CORS is off for the whole server (`None`) or for the
+/// route groups under the bypassed prefixes (`RoutePolicies::cors(false)`), whose requests
+/// reach the app without any CORS processing
+#[derive(Clone)]
+pub(crate) struct CorsSwitch<M> {
+    cors: Option<M>,
+    bypass: Arc<Vec<String>>,
+}
+
+impl<M> CorsSwitch<M> {
+    pub(crate) fn new(cors: Option<M>, bypass: Arc<Vec<String>>) -> Self {
+        Self { cors, bypass }
+    }
+}
+
+impl<S, M> ServiceMiddleware<S> for CorsSwitch<M>
+where
+    M: ServiceMiddleware<Shared<S>>,
+{
+    type Service = CorsSwitchService<S, M::Service>;
+
+    fn create(&self, service: S) -> Self::Service {
+        let service = Rc::new(service);
+        CorsSwitchService {
+            cors: self
+                .cors
+                .as_ref()
+                .map(|cors| cors.create(Shared(service.clone()))),
+            service,
+            bypass: self.bypass.clone(),
+        }
+    }
+}
+
+pub(crate) struct CorsSwitchService<S, C> {
+    service: Rc<S>,
+    cors: Option<C>,
+    bypass: Arc<Vec<String>>,
+}
+
+impl<S, C> CorsSwitchService<S, C> {
+    fn bypassed(&self, path: &str) -> bool {
+        self.bypass.iter().any(|prefix| {
+            path.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+impl<S, C, Err> Service<WebRequest<Err>> for CorsSwitchService<S, C>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    C: Service<WebRequest<Err>, Response = WebResponse, Error = S::Error>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    async fn ready(&self, ctx: ServiceCtx<'_, Self>) -> Result<(), Self::Error> {
+        ctx.ready(&*self.service).await
+    }
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        match &self.cors {
+            Some(cors) if !self.bypassed(req.path()) => ctx.call(cors, req).await,
+            _ => ctx.call(&*self.service, req).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::kernel::setup_cors;
+    use ntex::http::{Method, StatusCode, header};
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+
+    fn preflight(path: &str) -> ntex::http::Request {
+        TestRequest::with_uri(path)
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://app.example")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .to_request()
+    }
+
+    #[ntex::test]
+    async fn test_bypassed_prefixes_skip_cors() {
+        let cors = setup_cors(vec!["https://app.example".to_string()], vec![Method::GET]);
+        let bypass = Arc::new(vec!["/internal".to_string()]);
+        let app = init_service(
+            App::new()
+                .wrap(CorsSwitch::new(Some(cors.finish()), bypass))
+                .route("/public", web::to(|| async { "public" }))
+                .route("/internal/jobs", web::to(|| async { "jobs" })),
+        )
+        .await;
+
+        let resp = call_service(&app, preflight("/public")).await;
+        assert!(
+            resp.headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+
+        // reaches the handler, no preflight answer
+        let resp = call_service(&app, preflight("/internal/jobs")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(
+            !resp
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+    }
+
+    #[ntex::test]
+    async fn test_disabled_for_the_server() {
+        let app = init_service(
+            App::new()
+                .wrap(CorsSwitch::new(
+                    None::<ntex_cors::CorsFactory<web::DefaultError>>,
+                    Arc::default(),
+                ))
+                .route("/public", web::to(|| async { "public" })),
+        )
+        .await;
+
+        let resp = call_service(&app, preflight("/public")).await;
+        assert!(
+            !resp
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+    }
+}
@@ -1,22 +1,46 @@
-use crate::http::middlewares::Middleware;
-use crate::http::response::anyhow::ResponseError;
+use crate::helpers::clock::RequestRng;
+use crate::http::middlewares::matched_route::{MatchedRoute, RouteMatcher};
+use crate::http::middlewares::{Middleware, MiddlewareChains};
+use crate::http::response::anyhow::{ErrorContext, ErrorPhase, ResponseError};
 use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
 use ntex::web;
 use ntex::web::{Error, WebRequest};
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use std::time::Instant;
+use tracing::{Instrument, Span, debug, info, info_span};
 
 #[derive(Clone)]
 pub struct MiddlewareExecutor {
-    handler: Arc<Middleware>,
+    handlers: Arc<[Middleware]>,
+    matcher: Option<Arc<RouteMatcher>>,
+    /// position in the middlewares of the route, reported by `MiddlewareChains`
+    position: Option<usize>,
 }
 
 impl MiddlewareExecutor {
     pub fn new(handler: Middleware) -> Self {
+        Self::chain(vec![handler])
+    }
+
+    /// Run several middlewares as one: the before ones in order, the handler, then the
+    /// after ones in order
+    pub fn chain(handlers: Vec<Middleware>) -> Self {
         MiddlewareExecutor {
-            handler: Arc::new(handler),
+            handlers: handlers.into(),
+            matcher: None,
+            position: None,
         }
     }
+
+    pub(crate) fn matcher(mut self, matcher: Arc<RouteMatcher>) -> Self {
+        self.matcher = Some(matcher);
+        self
+    }
+
+    pub(crate) fn position(mut self, position: usize) -> Self {
+        self.position = Some(position);
+        self
+    }
 }
 
 impl<S> ServiceMiddleware<S> for MiddlewareExecutor {
@@ -25,14 +49,18 @@ impl<S> ServiceMiddleware<S> for MiddlewareExecutor {
     fn create(&self, service: S) -> Self::Service {
         ExecutorMiddlewareInternal {
             service,
-            middleware: self.handler.clone(),
+            middlewares: self.handlers.clone(),
+            matcher: self.matcher.clone(),
+            position: self.position,
         }
     }
 }
 
 pub struct ExecutorMiddlewareInternal<S> {
     service: S,
-    middleware: Arc<Middleware>,
+    middlewares: Arc<[Middleware]>,
+    matcher: Option<Arc<RouteMatcher>>,
+    position: Option<usize>,
 }
 
 impl<S, Err> Service<web::WebRequest<Err>> for ExecutorMiddlewareInternal<S>
@@ -53,35 +81,150 @@ where
         let (req, payload) = request.into_parts();
         info!("{} {}", req.method(), req.path());
 
-        match *self.middleware {
+        if let Some(matcher) = &self.matcher
+            && req.extensions().get::<MatchedRoute>().is_none()
+        {
+            let route = matcher.resolve(&req);
+            req.extensions_mut().insert(route);
+        }
+
+        let mut req = req;
+        let befores = self
+            .middlewares
+            .iter()
+            .filter_map(|middleware| match middleware {
+                Middleware::Before(mid) => Some(mid),
+                Middleware::After(_) => None,
+            });
+        for (index, mid) in befores.enumerate() {
             // execute before calling handler
-            Middleware::Before(ref mid) => match mid(req).await {
-                Ok(req) => {
-                    let request = WebRequest::from_parts(req, payload).unwrap();
-                    debug!("calling http controller -> method...");
-                    ctx.call(&self.service, request).await
-                }
-                Err(err) => Err(Error::from(ResponseError::new(err))),
-            },
-
-            // execute after executing handler
-            Middleware::After(ref mid) => {
-                let request = WebRequest::from_parts(req, payload).unwrap();
-                match ctx.call(&self.service, request).await {
-                    Ok(resp) => match mid(resp).await {
-                        Ok(resp) => Ok(resp),
-                        // log error and return response generated from controller
-                        Err(err) => {
-                            error!("[middleware-level-error][post-exec] {err:?}");
-                            Err(Error::from(ResponseError::new(err)))
-                        }
-                    },
-                    Err(err) => {
-                        error!("[middleware-level-error][post-exec] {err:?}");
-                        Err(err)
-                    }
-                }
+            let context = req.clone();
+            let started = Instant::now();
+            let result = mid(req).instrument(self.span("before", index)).await;
+            self.record(index, started);
+            req = result.map_err(|err| {
+                let context =
+                    ErrorContext::new(&context, &self.name("before", index), ErrorPhase::Before);
+                Error::from(ResponseError::in_context(err, context))
+            })?;
+        }
+
+        // the request can't be kept around while the handler runs
+        let request_id = ErrorContext::sent_request_id(&req);
+        let request = WebRequest::from_parts(req, payload).unwrap();
+        debug!("calling http controller -> method...");
+        let mut resp = ctx.call(&self.service, request).await.inspect_err(|err| {
+            if self.has_after() {
+                let request_id = request_id.unwrap_or_else(|| RequestRng::system().token(16));
+                ErrorContext::with_request_id(
+                    request_id,
+                    &self.name("handler", 0),
+                    ErrorPhase::Handler,
+                )
+                .log(err);
             }
+        })?;
+
+        // execute after executing handler
+        let afters = self
+            .middlewares
+            .iter()
+            .filter_map(|middleware| match middleware {
+                Middleware::After(mid) => Some(mid),
+                Middleware::Before(_) => None,
+            });
+        for (index, mid) in afters.enumerate() {
+            let req = resp.request().clone();
+            let started = Instant::now();
+            let result = mid(resp).instrument(self.span("after", index)).await;
+            self.record(index, started);
+            resp = result.map_err(|err| {
+                let context =
+                    ErrorContext::new(&req, &self.name("after", index), ErrorPhase::After);
+                Error::from(ResponseError::in_context(err, context))
+            })?;
         }
+
+        Ok(resp)
+    }
+}
+
+impl<S> ExecutorMiddlewareInternal<S> {
+    /// Label of a middleware in error contexts, e.g. `/api/users before#0`
+    fn name(&self, kind: &str, index: usize) -> String {
+        match &self.matcher {
+            Some(matcher) => format!("{} {kind}#{index}", matcher.scope()),
+            None => format!("{kind}#{index}"),
+        }
+    }
+
+    /// Name of a middleware in `MiddlewareChains`, e.g. `middleware#2`
+    fn layer(&self, index: usize) -> String {
+        format!("middleware#{}", self.position.unwrap_or(index))
+    }
+
+    fn span(&self, kind: &'static str, index: usize) -> Span {
+        let group = self.matcher.as_ref().map(|matcher| matcher.scope());
+        info_span!("middleware", group, layer = %self.layer(index), kind)
+    }
+
+    fn record(&self, index: usize, started: Instant) {
+        if let Some(matcher) = &self.matcher {
+            MiddlewareChains::record(matcher.scope(), &self.layer(index), started.elapsed());
+        }
+    }
+
+    fn has_after(&self) -> bool {
+        self.middlewares
+            .iter()
+            .any(|m| matches!(m, Middleware::After(_)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxtive::prelude::{AppMessage, AppResult};
+    use ntex::http::StatusCode;
+    use ntex::web::test::{TestRequest, init_service};
+    use ntex::web::{App, HttpRequest};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    fn pass(req: HttpRequest) -> Pin<Box<dyn Future<Output = AppResult<HttpRequest>>>> {
+        Box::pin(async move { Ok(req) })
+    }
+
+    fn reject(_req: HttpRequest) -> Pin<Box<dyn Future<Output = AppResult<HttpRequest>>>> {
+        Box::pin(async move { AppMessage::Unauthorized.ar() })
+    }
+
+    #[ntex::test]
+    async fn test_before_errors_carry_their_context() {
+        let app = init_service(
+            App::new()
+                .wrap(Middleware::chain(vec![
+                    Middleware::Before(pass),
+                    Middleware::Before(reject),
+                ]))
+                .route("/", web::get().to(|| async { "handled" })),
+        )
+        .await;
+
+        let req = TestRequest::with_uri("/")
+            .header("x-request-id", "req-1")
+            .to_request();
+        let err = app.call(req).await.unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+        // the context travels with the error to the response rendering
+        let rendered = format!("{err:?}");
+        assert!(rendered.contains("req-1") && rendered.contains("before#1"));
+
+        let executor = Middleware::chain(vec![]).matcher(RouteMatcher::new("/api", &[]));
+        let service = executor.create(());
+        assert_eq!(service.name("before", 1), "/api before#1");
     }
 }
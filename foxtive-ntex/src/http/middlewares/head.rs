@@ -0,0 +1,110 @@
+use foxtive::prelude::AppResult;
+use ntex::http::Method;
+use ntex::http::body::{Body, BodySize, MessageBody, ResponseBody};
+use ntex::http::header::{CONTENT_LENGTH, HeaderValue};
+use ntex::util::Bytes;
+use ntex::web::WebResponse;
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+/// After middleware dropping response bodies of `HEAD` requests.
+///
+/// ntex already omits the body on the wire, this makes the response body-less as soon as the
+/// handler returns, so the remaining middlewares (and in-process test clients) don't carry it.
+/// The `Content-Length` of the body a `GET` would have returned is kept.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::middlewares::{Middleware, head_without_body};
+///
+/// let middleware = Middleware::After(head_without_body);
+/// ```
+pub fn head_without_body(
+    resp: WebResponse,
+) -> Pin<Box<dyn Future<Output = AppResult<WebResponse>>>> {
+    Box::pin(async move {
+        if resp.request().method() != Method::HEAD {
+            return Ok(resp);
+        }
+
+        Ok(resp.map_body(|head, body| {
+            let size = body.size();
+            if let BodySize::Sized(len) = size
+                && !head.headers.contains_key(CONTENT_LENGTH)
+            {
+                head.headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+            }
+            ResponseBody::Other(Body::from_message(HeadBody(size)))
+        }))
+    })
+}
+
+/// No content, but the size of the dropped body, which ntex sends as `Content-Length`
+struct HeadBody(BodySize);
+
+impl MessageBody for HeadBody {
+    fn size(&self) -> BodySize {
+        self.0
+    }
+
+    fn poll_next_chunk(
+        &mut self,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Rc<dyn Error>>>> {
+        Poll::Ready(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::HttpResponse;
+    use ntex::web::test::{TestRequest, read_body};
+
+    #[tokio::test]
+    async fn test_strips_head_body() {
+        let req = TestRequest::default()
+            .method(Method::HEAD)
+            .to_http_request();
+        let resp = WebResponse::new(HttpResponse::Ok().body("hello"), req);
+        let resp = head_without_body(resp).await.unwrap();
+
+        assert_eq!(resp.headers().get(CONTENT_LENGTH).unwrap(), "5");
+        assert_eq!(resp.response().body().size(), BodySize::Sized(5));
+        assert!(read_body(resp).await.is_empty());
+    }
+
+    #[ntex::test]
+    async fn test_content_length_reaches_the_client() {
+        use crate::http::middlewares::Middleware;
+        use ntex::web::{self, App};
+
+        let srv = web::test::server(|| {
+            App::new()
+                .wrap(Middleware::After(head_without_body).middleware())
+                .route(
+                    "/",
+                    web::route().to(|| async { HttpResponse::Ok().body("hello") }),
+                )
+        });
+
+        let resp = srv
+            .request(Method::HEAD, srv.url("/"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.headers().get(CONTENT_LENGTH).unwrap(), "5");
+    }
+
+    #[tokio::test]
+    async fn test_keeps_get_body() {
+        let req = TestRequest::default().to_http_request();
+        let resp = WebResponse::new(HttpResponse::Ok().body("hello"), req);
+        let resp = head_without_body(resp).await.unwrap();
+
+        assert_eq!(read_body(resp).await, "hello");
+    }
+}
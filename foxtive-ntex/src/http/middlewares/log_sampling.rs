@@ -0,0 +1,132 @@
+use crate::FoxtiveNtexState;
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::middleware::Logger;
+use ntex::web::{self, WebRequest, WebResponse};
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Wraps the access [`Logger`], logging only the `log_sample_rate` fraction of the requests
+/// set in the runtime settings, the others skip the logger entirely
+pub(crate) struct LogSampling {
+    logger: Logger,
+}
+
+impl LogSampling {
+    pub(crate) fn new(logger: Logger) -> Self {
+        Self { logger }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for LogSampling {
+    type Service = LogSamplingService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        let service = Rc::new(service);
+        LogSamplingService {
+            logged: self.logger.create(Shared(service.clone())),
+            service,
+            requests: Cell::new(0),
+        }
+    }
+}
+
+pub(crate) struct LogSamplingService<S> {
+    logged: <Logger as ServiceMiddleware<Shared<S>>>::Service,
+    service: Rc<S>,
+    requests: Cell<u64>,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for LogSamplingService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    // the logger only forwards readiness to the shared service
+    async fn ready(&self, ctx: ServiceCtx<'_, Self>) -> Result<(), Self::Error> {
+        ctx.ready(self.service.as_ref()).await
+    }
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let rate = req.app_state::<FoxtiveNtexState>().map_or(1.0, |state| {
+            state.runtime_settings.current().log_sample_rate
+        });
+
+        if sampled(&self.requests, rate) {
+            ctx.call(&self.logged, req).await
+        } else {
+            ctx.call(self.service.as_ref(), req).await
+        }
+    }
+}
+
+/// Spreads the logged requests evenly, e.g. every fourth one at `0.25`
+fn sampled(requests: &Cell<u64>, rate: f64) -> bool {
+    let rate = rate.clamp(0.0, 1.0);
+    let n = requests.get();
+    requests.set(n.wrapping_add(1));
+    ((n + 1) as f64 * rate).floor() > (n as f64 * rate).floor()
+}
+
+/// The wrapped service, shared by the logged and the unlogged path
+pub(crate) struct Shared<S>(Rc<S>);
+
+impl<S, Req> Service<Req> for Shared<S>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+
+    async fn ready(&self, ctx: ServiceCtx<'_, Self>) -> Result<(), Self::Error> {
+        ctx.ready(self.0.as_ref()).await
+    }
+
+    async fn call(&self, req: Req, ctx: ServiceCtx<'_, Self>) -> Result<S::Response, S::Error> {
+        ctx.call(self.0.as_ref(), req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::http::StatusCode;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use ntex::web::{App, HttpResponse};
+
+    #[test]
+    fn test_sampling_spreads_requests() {
+        let requests = Cell::new(0);
+        let logged = |rate| (0..100).filter(|_| sampled(&requests, rate)).count();
+
+        assert_eq!(logged(1.0), 100);
+        assert_eq!(logged(0.25), 25);
+        assert_eq!(logged(0.0), 0);
+        assert_eq!(logged(f64::NAN), 0);
+    }
+
+    #[ntex::test]
+    async fn test_unsampled_requests_still_reach_the_service() {
+        let state = FoxtiveNtexState::for_tests();
+        state.runtime_settings.update(|s| s.log_sample_rate = 0.5);
+
+        let app = init_service(
+            App::new()
+                .state(state)
+                .wrap(LogSampling::new(Logger::default()))
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        for _ in 0..4 {
+            let resp = call_service(&app, TestRequest::with_uri("/").to_request()).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::FoxtiveNtexState;
+use foxtive::prelude::{AppMessage, AppResult};
+use ntex::http::StatusCode;
+use ntex::web::HttpRequest;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Before middleware rejecting requests with 503 while maintenance mode is enabled
+/// in the runtime settings store.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::middlewares::{Middleware, maintenance_mode};
+///
+/// let middleware = Middleware::Before(maintenance_mode);
+/// ```
+pub fn maintenance_mode(req: HttpRequest) -> Pin<Box<dyn Future<Output = AppResult<HttpRequest>>>> {
+    Box::pin(async move {
+        let settings = match req.app_state::<FoxtiveNtexState>() {
+            Some(state) => state.runtime_settings.current(),
+            None => return Ok(req),
+        };
+
+        if settings.maintenance_mode {
+            let message = settings
+                .maintenance_message
+                .clone()
+                .unwrap_or_else(|| "Service is under maintenance".to_string());
+
+            return Err(AppMessage::ErrorMessage(message, StatusCode::SERVICE_UNAVAILABLE).ae());
+        }
+
+        Ok(req)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::setup::runtime_settings::{RuntimeSettings, Settings};
+    use ntex::web::WebResponseError;
+    use ntex::web::test::TestRequest;
+
+    fn make_state(maintenance_mode: bool) -> FoxtiveNtexState {
+        FoxtiveNtexState {
+            runtime_settings: RuntimeSettings::new(Settings {
+                maintenance_mode,
+                ..Default::default()
+            }),
+            ..FoxtiveNtexState::for_tests()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_passes_when_not_in_maintenance() {
+        let req = TestRequest::default()
+            .state(make_state(false))
+            .to_http_request();
+
+        assert!(maintenance_mode(req).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_when_in_maintenance() {
+        let req = TestRequest::default()
+            .state(make_state(true))
+            .to_http_request();
+
+        let err = maintenance_mode(req).await.unwrap_err();
+        let err = crate::http::response::anyhow::ResponseError::new(err);
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
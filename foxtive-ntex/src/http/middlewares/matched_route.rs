@@ -0,0 +1,151 @@
+use ntex::http::Uri;
+use ntex::router::{Path, Router};
+use ntex::web::HttpRequest;
+use std::sync::Arc;
+
+/// Route template and parameters matched for the current request.
+///
+/// ntex matches resources registered inside a controller only after the controller's
+/// middlewares have run, so before-middlewares would otherwise only see the raw path.
+/// Declaring the controller's patterns with `Controller::pattern` lets the middleware
+/// executor resolve them upfront.
+///
+/// # Example
+/// ```
+/// use foxtive::prelude::{AppMessage, AppResult};
+/// use foxtive_ntex::http::middlewares::MatchedRoute;
+/// use ntex::web::HttpRequest;
+///
+/// fn ensure_tenant(req: &HttpRequest) -> AppResult<()> {
+///     let route = MatchedRoute::from_http_request(req);
+///     match route.and_then(|r| r.param("tenant_id").map(str::to_owned)) {
+///         Some(tenant) if tenant == "acme" => Ok(()),
+///         _ => AppMessage::Forbidden.ar(),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedRoute {
+    template: String,
+    params: Vec<(String, String)>,
+}
+
+impl MatchedRoute {
+    pub fn new(template: &str, params: Vec<(String, String)>) -> Self {
+        Self {
+            template: template.to_string(),
+            params,
+        }
+    }
+
+    /// Get the route matched by the middleware executor, `None` outside registered routes
+    pub fn from_http_request(req: &HttpRequest) -> Option<MatchedRoute> {
+        req.extensions().get::<MatchedRoute>().cloned()
+    }
+
+    /// Full route template, e.g. `/api/tenants/{tenant_id}/users/{id}`
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+}
+
+/// Resolves [`MatchedRoute`] for requests entering a controller scope
+pub(crate) struct RouteMatcher {
+    scope: String,
+    router: Router<String>,
+}
+
+impl RouteMatcher {
+    pub(crate) fn scope(&self) -> &str {
+        &self.scope
+    }
+
+    pub(crate) fn new(scope: &str, patterns: &[String]) -> Arc<Self> {
+        let mut router = Router::build();
+        for pattern in patterns {
+            router.path(pattern.as_str(), format!("{scope}{pattern}"));
+        }
+
+        Arc::new(Self {
+            scope: scope.to_string(),
+            router: router.finish(),
+        })
+    }
+
+    pub(crate) fn resolve(&self, req: &HttpRequest) -> MatchedRoute {
+        let mut path: Path<Uri> = req.match_info().clone();
+
+        let template = match self.router.recognize(&mut path) {
+            Some((template, _)) => template.clone(),
+            None => {
+                path = req.match_info().clone();
+                self.scope.clone()
+            }
+        };
+
+        let params = path
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        MatchedRoute { template, params }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::kernel::{Controller, Route, register_routes};
+    use crate::http::middlewares::Middleware;
+    use foxtive::prelude::{AppMessage, AppResult};
+    use ntex::http::StatusCode;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use ntex::web::{self, App, HttpResponse, ServiceConfig};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    fn only_acme(req: HttpRequest) -> Pin<Box<dyn Future<Output = AppResult<HttpRequest>>>> {
+        Box::pin(async move {
+            let route = MatchedRoute::from_http_request(&req).unwrap();
+            assert_eq!(route.template(), "/tenants/{tenant_id}/users/{id}");
+
+            match (route.param("tenant_id"), route.param("id")) {
+                (Some("acme"), Some(_)) => Ok(req),
+                _ => AppMessage::Forbidden.ar(),
+            }
+        })
+    }
+
+    fn users(cfg: &mut ServiceConfig) {
+        cfg.route("/{id}", web::get().to(|| async { HttpResponse::Ok() }));
+    }
+
+    #[ntex::test]
+    async fn test_params_visible_to_before_middleware() {
+        let routes = vec![
+            Route::new("/tenants/{tenant_id}")
+                .middleware(Middleware::Before(only_acme))
+                .controller(Controller::new("/users", users).pattern("/{id}")),
+        ];
+
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let req = TestRequest::with_uri("/tenants/acme/users/1").to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        // middleware errors are rendered by the server, the service itself fails
+        let req = TestRequest::with_uri("/tenants/umbrella/users/1").to_request();
+        assert!(app.call(req).await.is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::helpers::mirror::{MirrorConfig, MirroredExchange, RequestMirror};
+use chrono::Utc;
+use futures_util::StreamExt;
+use ntex::http::Payload;
+use ntex::http::body::{Body, ResponseBody};
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, WebRequest, WebResponse};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// Request body bytes kept while the handler reads the payload
+#[derive(Default)]
+struct Captured {
+    bytes: Vec<u8>,
+    size: usize,
+    read: bool,
+}
+
+/// Middleware copying the exchanges into the [`RequestMirror`] (feature `dev-tools`),
+/// request bodies are copied while the handler reads them, never buffered ahead
+#[derive(Clone)]
+pub(crate) struct RequestMirroring {
+    mirror: RequestMirror,
+}
+
+impl RequestMirroring {
+    pub(crate) fn new(mirror: RequestMirror) -> Self {
+        Self { mirror }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for RequestMirroring {
+    type Service = RequestMirroringService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RequestMirroringService {
+            service,
+            mirror: self.mirror.clone(),
+        }
+    }
+}
+
+pub(crate) struct RequestMirroringService<S> {
+    service: S,
+    mirror: RequestMirror,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for RequestMirroringService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        mut req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if !self.mirror.is_enabled() {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let config = self.mirror.config();
+        let started = Instant::now();
+        let at = Utc::now();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let query = config.query(req.query_string());
+        let request_headers = config.headers(req.headers());
+
+        let captured = Rc::new(RefCell::new(Captured::default()));
+        let payload = req.take_payload();
+        req.set_payload(tee(payload, captured.clone(), config.max_body));
+
+        let resp = ctx.call(&self.service, req).await?;
+
+        let captured = captured.borrow();
+        let request_body = captured
+            .read
+            .then(|| config.body(&captured.bytes, captured.size));
+        let response_body = match resp.response().body() {
+            ResponseBody::Body(Body::Bytes(bytes)) | ResponseBody::Other(Body::Bytes(bytes)) => {
+                Some(config.body(bytes, bytes.len()))
+            }
+            ResponseBody::Body(Body::None | Body::Empty)
+            | ResponseBody::Other(Body::None | Body::Empty) => Some(config.body(&[], 0)),
+            _ => None,
+        };
+
+        self.mirror.record(MirroredExchange {
+            id: self.mirror.next_id(),
+            at,
+            method,
+            path,
+            query,
+            status: resp.status().as_u16(),
+            elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+            request_headers,
+            request_body,
+            response_headers: config.headers(resp.headers()),
+            response_body,
+        });
+
+        Ok(resp)
+    }
+}
+
+/// Payload passing the chunks through, keeping a copy of the first `max_body` bytes
+fn tee(payload: Payload, captured: Rc<RefCell<Captured>>, max_body: usize) -> Payload {
+    Payload::from_stream(payload.map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            let mut captured = captured.borrow_mut();
+            captured.read = true;
+            captured.size += chunk.len();
+            let room = max_body.saturating_sub(captured.bytes.len());
+            captured
+                .bytes
+                .extend_from_slice(&chunk[..chunk.len().min(room)]);
+        }
+        chunk
+    }))
+}
+
+/// Mirroring is off unless configured, and never happens in production
+pub(crate) fn mirror_config(
+    config: Option<MirrorConfig>,
+    allows_debug: bool,
+) -> Option<MirrorConfig> {
+    match (config, allows_debug) {
+        (Some(_), false) => {
+            tracing::warn!("request mirroring is ignored in production");
+            None
+        }
+        (config, _) => config,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+
+    #[ntex::test]
+    async fn test_mirrors_exchanges() {
+        let mirror = RequestMirror::default();
+        let app = init_service(
+            App::new()
+                .wrap(RequestMirroring::new(mirror.clone()))
+                .route(
+                    "/login",
+                    web::post().to(|body: String| async move { format!("hello {}", body.len()) }),
+                )
+                .route("/ping", web::get().to(|| async { "pong" })),
+        )
+        .await;
+
+        // nothing kept until enabled
+        call_service(&app, TestRequest::with_uri("/ping").to_request()).await;
+        assert!(mirror.recent(10, None).is_empty());
+
+        mirror.configure(Some(MirrorConfig::new(10).max_body(16)));
+        let req = TestRequest::with_uri("/login?token=abc")
+            .method(ntex::http::Method::POST)
+            .header("authorization", "Bearer abc")
+            .set_payload(r#"{"user":"ann","password":"secret"}"#)
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(read_body(resp).await, "hello 34");
+        call_service(&app, TestRequest::with_uri("/ping").to_request()).await;
+
+        let recent = mirror.recent(10, None);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].path, "/ping");
+        assert!(recent[0].request_body.is_none());
+        assert_eq!(recent[0].response_body.as_ref().unwrap().content, "pong");
+
+        let login = &recent[1];
+        assert_eq!(login.method, "POST");
+        assert_eq!(login.query, "token=[REDACTED]");
+        assert!(
+            login
+                .request_headers
+                .contains(&("authorization".to_string(), "[REDACTED]".to_string()))
+        );
+        let body = login.request_body.as_ref().unwrap();
+        assert!(body.truncated);
+        assert_eq!(body.size, 34);
+        assert_eq!(body.content, r#"{"user":"ann","p"#);
+    }
+
+    #[test]
+    fn test_never_in_production() {
+        assert!(mirror_config(Some(MirrorConfig::default()), false).is_none());
+        assert!(mirror_config(Some(MirrorConfig::default()), true).is_some());
+    }
+}
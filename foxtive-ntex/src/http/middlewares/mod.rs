@@ -3,8 +3,54 @@ use foxtive::prelude::AppResult;
 use ntex::web::{HttpRequest, WebResponse};
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 
+mod alias;
+mod cancellation;
+mod chain_trace;
+mod cors_switch;
 mod executor;
+mod head;
+mod log_sampling;
+mod maintenance;
+mod matched_route;
+#[cfg(feature = "dev-tools")]
+mod mirror;
+mod origin_cors;
+mod outbox;
+mod response_cache;
+mod route_layer;
+mod server_timing;
+mod size_guard;
+mod stats;
+mod strict_length;
+mod sunset;
+
+pub(crate) use alias::AliasTable;
+pub use alias::{Alias, AliasMode};
+pub(crate) use cancellation::RequestCancellation;
+pub use cancellation::cancelled_requests;
+pub use chain_trace::{ChainLayer, LayerTiming, MiddlewareChains, RouteChain};
+pub(crate) use cors_switch::CorsSwitch;
+pub use head::head_without_body;
+pub(crate) use log_sampling::LogSampling;
+pub use maintenance::maintenance_mode;
+pub use matched_route::MatchedRoute;
+pub(crate) use matched_route::RouteMatcher;
+#[cfg(feature = "dev-tools")]
+pub(crate) use mirror::{RequestMirroring, mirror_config};
+pub(crate) use origin_cors::OriginCors;
+pub use origin_cors::OriginResolver;
+pub use outbox::{OutboxPublisher, publish_outbox, set_outbox_publisher};
+pub use response_cache::{CACHE_STATUS_HEADER, CachePolicy, CachedRoutes};
+pub use route_layer::{BodyParser, HeaderPolicy, RateLimit, RoutePolicies};
+pub(crate) use route_layer::{RateLimiters, RouteLayer, RouteMeta};
+pub use server_timing::server_timing;
+pub(crate) use size_guard::ResponseSizeLimit;
+pub use size_guard::{OversizeMode, ResponseSizeGuard, TRUNCATED_HEADER};
+pub(crate) use stats::StatsRecorder;
+pub(crate) use strict_length::StrictContentLength;
+pub use sunset::Sunset;
 
 pub type BeforeMiddlewareHandler =
     fn(HttpRequest) -> Pin<Box<dyn Future<Output = AppResult<HttpRequest>>>>;
@@ -24,4 +70,29 @@ impl Middleware {
     pub fn middleware(&self) -> MiddlewareExecutor {
         MiddlewareExecutor::new(self.clone())
     }
+
+    /// Single executor running every middleware of the list, e.g. to wrap a whole app
+    pub fn chain(middlewares: Vec<Middleware>) -> MiddlewareExecutor {
+        MiddlewareExecutor::chain(middlewares)
+    }
+
+    /// Executor resolving [`MatchedRoute`] before running the middleware, `position` being
+    /// its index in the middlewares of the route
+    pub(crate) fn route_middleware(
+        &self,
+        matcher: Arc<RouteMatcher>,
+        position: usize,
+    ) -> MiddlewareExecutor {
+        MiddlewareExecutor::new(self.clone())
+            .matcher(matcher)
+            .position(position)
+    }
+
+    /// `before` or `after`
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Middleware::Before(_) => "before",
+            Middleware::After(_) => "after",
+        }
+    }
 }
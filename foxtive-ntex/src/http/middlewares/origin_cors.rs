@@ -0,0 +1,272 @@
+use foxtive::prelude::AppResult;
+use ntex::http::Method;
+use ntex::http::header::{self, HeaderValue};
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, HttpResponse, WebRequest, WebResponse};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error};
+
+/// Decides at runtime whether an origin missing from the static CORS list is allowed,
+/// e.g. by looking up customer domains in the database.
+///
+/// Decisions are cached (see `ServerConfig::origin_cache_ttl`), failures are not.
+///
+/// # Example
+/// ```
+/// use foxtive::prelude::AppResult;
+/// use foxtive_ntex::http::middlewares::OriginResolver;
+/// use std::future::Future;
+/// use std::pin::Pin;
+///
+/// struct CustomerDomains;
+///
+/// impl OriginResolver for CustomerDomains {
+///     fn is_allowed(&self, origin: &str) -> Pin<Box<dyn Future<Output = AppResult<bool>>>> {
+///         let origin = origin.to_string();
+///         Box::pin(async move {
+///             // SELECT 1 FROM custom_domains WHERE origin = $1
+///             Ok(origin.ends_with(".customer.example"))
+///         })
+///     }
+/// }
+/// ```
+pub trait OriginResolver: Send + Sync + 'static {
+    fn is_allowed(&self, origin: &str) -> Pin<Box<dyn Future<Output = AppResult<bool>>>>;
+}
+
+/// Cached resolver decisions, shared by every worker
+struct OriginCache {
+    resolver: Arc<dyn OriginResolver>,
+    ttl: Duration,
+    decisions: Mutex<HashMap<String, (bool, Instant)>>,
+}
+
+impl OriginCache {
+    async fn is_allowed(&self, origin: &str) -> bool {
+        if let Ok(decisions) = self.decisions.lock()
+            && let Some((allowed, expires)) = decisions.get(origin)
+            && *expires > Instant::now()
+        {
+            return *allowed;
+        }
+
+        match self.resolver.is_allowed(origin).await {
+            Ok(allowed) => {
+                debug!("[cors] resolved origin '{origin}': {allowed}");
+                if let Ok(mut decisions) = self.decisions.lock() {
+                    let now = Instant::now();
+                    decisions.retain(|_, (_, expires)| *expires > now);
+                    decisions.insert(origin.to_string(), (allowed, now + self.ttl));
+                }
+                allowed
+            }
+            Err(err) => {
+                error!("[cors] failed to resolve origin '{origin}': {err:?}");
+                false
+            }
+        }
+    }
+}
+
+/// Middleware answering CORS for origins accepted by an [`OriginResolver`].
+///
+/// It wraps the static CORS middleware: statically listed origins and requests without
+/// origin go through untouched, resolved origins are answered here.
+#[derive(Clone)]
+pub(crate) struct OriginCors {
+    cache: Option<Arc<OriginCache>>,
+    static_origins: Arc<Vec<String>>,
+    methods: HeaderValue,
+}
+
+impl OriginCors {
+    pub(crate) fn new(
+        resolver: Option<Arc<dyn OriginResolver>>,
+        ttl: Duration,
+        static_origins: Vec<String>,
+        methods: &[Method],
+    ) -> Self {
+        let methods: Vec<_> = methods.iter().map(Method::as_str).collect();
+
+        Self {
+            cache: resolver.map(|resolver| {
+                Arc::new(OriginCache {
+                    resolver,
+                    ttl,
+                    decisions: Mutex::new(HashMap::new()),
+                })
+            }),
+            static_origins: Arc::new(static_origins),
+            methods: HeaderValue::from_str(&methods.join(", "))
+                .unwrap_or(HeaderValue::from_static("GET")),
+        }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for OriginCors {
+    type Service = OriginCorsService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        OriginCorsService {
+            service,
+            cors: self.clone(),
+        }
+    }
+}
+
+pub(crate) struct OriginCorsService<S> {
+    service: S,
+    cors: OriginCors,
+}
+
+impl<S> OriginCorsService<S> {
+    /// Origin to answer here, `None` when the request is left to the static CORS middleware
+    async fn resolved_origin<Err>(&self, req: &WebRequest<Err>) -> Option<HeaderValue> {
+        let cache = self.cors.cache.as_ref()?;
+        let value = req.headers().get(header::ORIGIN)?.clone();
+        let origin = value.to_str().ok()?;
+
+        let listed = self
+            .cors
+            .static_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == origin);
+
+        match !listed && cache.is_allowed(origin).await {
+            true => Some(value),
+            false => None,
+        }
+    }
+}
+
+impl<S, Err> Service<WebRequest<Err>> for OriginCorsService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        mut req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let origin = match self.resolved_origin(&req).await {
+            None => return ctx.call(&self.service, req).await,
+            Some(origin) => origin,
+        };
+
+        let preflight = req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+        if preflight {
+            let resp = HttpResponse::Ok()
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+                .header(
+                    header::ACCESS_CONTROL_ALLOW_METHODS,
+                    self.cors.methods.clone(),
+                )
+                .header(
+                    header::ACCESS_CONTROL_ALLOW_HEADERS,
+                    "authorization, accept, content-type",
+                )
+                .header(header::ACCESS_CONTROL_MAX_AGE, "3600")
+                .header(header::VARY, "Origin")
+                .finish();
+            return Ok(req.into_response(resp));
+        }
+
+        // the static middleware would reject the origin it doesn't know about
+        req.headers_mut().remove(header::ORIGIN);
+        let mut resp = ctx.call(&self.service, req).await?;
+
+        let headers = resp.headers_mut();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::kernel::{cors_methods, setup_cors};
+    use ntex::http::StatusCode;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Customers;
+
+    impl OriginResolver for Customers {
+        fn is_allowed(&self, origin: &str) -> Pin<Box<dyn Future<Output = AppResult<bool>>>> {
+            LOOKUPS.fetch_add(1, Ordering::SeqCst);
+            let allowed = origin == "https://shop.customer.test";
+            Box::pin(async move { Ok(allowed) })
+        }
+    }
+
+    #[ntex::test]
+    async fn test_resolved_origins_are_allowed() {
+        let origins = vec!["https://app.test".to_string()];
+        let app = init_service(
+            App::new()
+                .route("/", web::get().to(|| async { HttpResponse::Ok().finish() }))
+                .wrap(setup_cors(origins.clone(), vec![]).finish())
+                .wrap(OriginCors::new(
+                    Some(Arc::new(Customers)),
+                    Duration::from_secs(60),
+                    origins,
+                    &cors_methods(vec![]),
+                )),
+        )
+        .await;
+
+        let get = |origin: &str| {
+            TestRequest::with_uri("/")
+                .header("origin", origin)
+                .to_request()
+        };
+        let allow_origin = |resp: &WebResponse| {
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+
+        let resp = call_service(&app, get("https://shop.customer.test")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(allow_origin(&resp).unwrap(), "https://shop.customer.test");
+
+        let preflight = TestRequest::with_uri("/")
+            .method(Method::OPTIONS)
+            .header("origin", "https://shop.customer.test")
+            .header("access-control-request-method", "POST")
+            .to_request();
+        let resp = call_service(&app, preflight).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(
+            resp.headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_METHODS)
+        );
+
+        // decisions are cached
+        assert_eq!(LOOKUPS.load(Ordering::SeqCst), 1);
+
+        let resp = call_service(&app, get("https://app.test")).await;
+        assert_eq!(allow_origin(&resp).unwrap(), "https://app.test");
+
+        let resp = call_service(&app, get("https://evil.test")).await;
+        assert_ne!(allow_origin(&resp).as_deref(), Some("https://evil.test"));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::http::extractors::{Outbox, OutboxEvent};
+use crate::http::server::ServerSettings;
+use foxtive::prelude::AppResult;
+use ntex::web::WebResponse;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::OnceLock;
+use tracing::{error, warn};
+
+/// Delivers staged events to the message bus (RabbitMQ, Redis streams...)
+pub type OutboxPublisher = fn(Vec<OutboxEvent>) -> Pin<Box<dyn Future<Output = AppResult<()>>>>;
+
+static PUBLISHER: OnceLock<OutboxPublisher> = OnceLock::new();
+
+/// Register the publisher used by [`publish_outbox`] outside of the servers started with a
+/// `ServerConfig::outbox_publisher`, which use their own. Only the first registration is kept.
+pub fn set_outbox_publisher(publisher: OutboxPublisher) {
+    if PUBLISHER.set(publisher).is_err() {
+        warn!("[outbox] publisher is already set, ignoring");
+    }
+}
+
+/// After middleware publishing the events staged through the `Outbox` extractor,
+/// only when the response is successful. Publishing failures are logged, the
+/// response is sent regardless.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::middlewares::{Middleware, publish_outbox};
+///
+/// let middleware = Middleware::After(publish_outbox);
+/// ```
+pub fn publish_outbox(resp: WebResponse) -> Pin<Box<dyn Future<Output = AppResult<WebResponse>>>> {
+    Box::pin(async move {
+        let outbox = resp.request().extensions().get::<Outbox>().cloned();
+        let events = match outbox {
+            Some(outbox) => outbox.take(),
+            None => return Ok(resp),
+        };
+
+        if events.is_empty() {
+            return Ok(resp);
+        }
+
+        if !resp.status().is_success() {
+            warn!(
+                "[outbox] discarding {} event(s) of failed request ({})",
+                events.len(),
+                resp.status()
+            );
+            return Ok(resp);
+        }
+
+        let publisher = ServerSettings::read(|settings| settings.outbox_publisher)
+            .flatten()
+            .or_else(|| PUBLISHER.get().copied());
+        match publisher {
+            None => error!("[outbox] no publisher set, {} event(s) lost", events.len()),
+            Some(publisher) => {
+                if let Err(err) = publisher(events).await {
+                    error!("[outbox] failed to publish events: {err:?}");
+                }
+            }
+        }
+
+        Ok(resp)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::HttpResponse;
+    use ntex::web::test::TestRequest;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    static PUBLISHED: Mutex<Vec<OutboxEvent>> = Mutex::new(Vec::new());
+
+    fn record(events: Vec<OutboxEvent>) -> Pin<Box<dyn Future<Output = AppResult<()>>>> {
+        Box::pin(async move {
+            PUBLISHED.lock().unwrap().extend(events);
+            Ok(())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_publishes_only_successful_responses() {
+        set_outbox_publisher(record);
+
+        let req = TestRequest::default().to_http_request();
+        let outbox = Outbox::from_http_request(&req);
+        outbox
+            .publish_later("orders.failed", &json!({"id": 1}))
+            .unwrap();
+        let resp = WebResponse::new(HttpResponse::BadRequest().finish(), req);
+        publish_outbox(resp).await.unwrap();
+        assert!(PUBLISHED.lock().unwrap().is_empty());
+
+        let req = TestRequest::default().to_http_request();
+        let outbox = Outbox::from_http_request(&req);
+        outbox
+            .publish_later("orders.created", &json!({"id": 2}))
+            .unwrap();
+        let resp = WebResponse::new(HttpResponse::Ok().finish(), req);
+        publish_outbox(resp).await.unwrap();
+
+        let published = PUBLISHED.lock().unwrap();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].topic, "orders.created");
+        assert!(outbox.is_empty());
+    }
+
+    static SERVER_PUBLISHED: Mutex<Vec<OutboxEvent>> = Mutex::new(Vec::new());
+
+    fn record_on_server(events: Vec<OutboxEvent>) -> Pin<Box<dyn Future<Output = AppResult<()>>>> {
+        Box::pin(async move {
+            SERVER_PUBLISHED.lock().unwrap().extend(events);
+            Ok(())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_server_publisher_takes_precedence() {
+        let settings = ServerSettings {
+            outbox_publisher: Some(record_on_server),
+            ..Default::default()
+        };
+
+        let req = TestRequest::default().to_http_request();
+        let outbox = Outbox::from_http_request(&req);
+        outbox
+            .publish_later("invoices.paid", &json!({"id": 3}))
+            .unwrap();
+        let resp = WebResponse::new(HttpResponse::Ok().finish(), req);
+        ServerSettings::scope(std::sync::Arc::new(settings), publish_outbox(resp))
+            .await
+            .unwrap();
+
+        let published = SERVER_PUBLISHED.lock().unwrap();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].topic, "invoices.paid");
+    }
+}
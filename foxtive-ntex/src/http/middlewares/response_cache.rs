@@ -0,0 +1,382 @@
+use crate::helpers::lru::LruCache;
+use ntex::http::body::{Body, ResponseBody};
+use ntex::http::header::{self, HeaderName, HeaderValue};
+use ntex::http::{Method, StatusCode};
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{
+    self, DefaultError, FromRequest, Handler, HttpResponse, Resource, ServiceConfig, WebRequest,
+    WebResponse,
+};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Header telling whether the response was served from the cache, `HIT` or `MISS`
+pub const CACHE_STATUS_HEADER: &str = "x-cache";
+
+/// Caching of a route declared next to its handler with [`CachedRoutes`]
+///
+/// Successful `GET` responses with an in-memory body are kept for `ttl` per path, query
+/// string and values of the `vary` headers, up to `max_entries` per route and worker.
+/// Clients sending `Cache-Control: no-cache` skip the cached response and refresh it.
+///
+/// Responses setting cookies or marked `Cache-Control: private` or `no-store` are never
+/// kept, so one client's session is not replayed to another.
+#[derive(Debug, Clone)]
+pub struct CachePolicy {
+    pub ttl: Duration,
+    /// request headers whose values make separate cache entries, e.g. `Authorization`
+    pub vary: Vec<HeaderName>,
+    /// responses kept at most, the least recently used are dropped first, 1024 by default
+    pub max_entries: usize,
+}
+
+impl CachePolicy {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            vary: vec![],
+            max_entries: 1024,
+        }
+    }
+
+    pub fn vary(mut self, header: HeaderName) -> Self {
+        self.vary.push(header);
+        self
+    }
+
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+}
+
+/// Routes registered with their cache policy, so caching is configured where the handler
+/// is rather than in a distant middleware block.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::middlewares::{CachePolicy, CachedRoutes};
+/// use ntex::http::header;
+/// use ntex::web::ServiceConfig;
+/// use std::time::Duration;
+///
+/// async fn catalog() -> &'static str {
+///     "[]"
+/// }
+///
+/// fn products(cfg: &mut ServiceConfig) {
+///     cfg.get_cached(
+///         "/catalog",
+///         catalog,
+///         CachePolicy::new(Duration::from_secs(60)).vary(header::AUTHORIZATION),
+///     );
+/// }
+/// ```
+pub trait CachedRoutes {
+    fn get_cached<F, Args>(&mut self, path: &str, handler: F, policy: CachePolicy) -> &mut Self
+    where
+        F: Handler<Args, DefaultError> + 'static,
+        Args: FromRequest<DefaultError> + 'static,
+        Args::Error: Into<web::Error>;
+}
+
+impl CachedRoutes for ServiceConfig {
+    fn get_cached<F, Args>(&mut self, path: &str, handler: F, policy: CachePolicy) -> &mut Self
+    where
+        F: Handler<Args, DefaultError> + 'static,
+        Args: FromRequest<DefaultError> + 'static,
+        Args::Error: Into<web::Error>,
+    {
+        self.service(
+            Resource::new(path)
+                .wrap(ResponseCache::new(policy))
+                .route(web::get().to(handler)),
+        )
+    }
+}
+
+/// Connection-level headers, meaningful for a single hop only and never replayed
+const HOP_BY_HOP: [HeaderName; 8] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+#[derive(Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    body: Bytes,
+}
+
+type Entries = Arc<Mutex<LruCache<String, CachedResponse>>>;
+
+/// Middleware serving the responses kept by [`CachePolicy`], each instance has its own
+/// bounded set of entries
+#[derive(Clone)]
+pub(crate) struct ResponseCache {
+    policy: CachePolicy,
+    entries: Entries,
+}
+
+impl ResponseCache {
+    pub(crate) fn new(policy: CachePolicy) -> Self {
+        let entries = Arc::new(Mutex::new(LruCache::new(policy.max_entries)));
+        Self { policy, entries }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for ResponseCache {
+    type Service = ResponseCacheService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ResponseCacheService {
+            service,
+            policy: self.policy.clone(),
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+pub(crate) struct ResponseCacheService<S> {
+    service: S,
+    policy: CachePolicy,
+    entries: Entries,
+}
+
+/// Whether the response may be replayed to other clients
+fn is_shareable(resp: &WebResponse) -> bool {
+    if resp.status() != StatusCode::OK || resp.headers().contains_key(header::SET_COOKIE) {
+        return false;
+    }
+
+    !resp
+        .headers()
+        .get_all(header::CACHE_CONTROL)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|directive| directive.trim().to_ascii_lowercase())
+        .any(|directive| directive == "private" || directive == "no-store")
+}
+
+impl<S> ResponseCacheService<S> {
+    fn key<Err>(&self, req: &WebRequest<Err>) -> String {
+        let mut key = format!("{}?{}", req.path(), req.query_string());
+        for name in &self.policy.vary {
+            let value = req
+                .headers()
+                .get(name)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+            key.push_str(&format!("|{name}={}", value.unwrap_or_default()));
+        }
+        key
+    }
+
+    fn vary(&self) -> Option<HeaderValue> {
+        let names: Vec<&str> = self.policy.vary.iter().map(HeaderName::as_str).collect();
+        match names.is_empty() {
+            true => None,
+            false => HeaderValue::from_str(&names.join(", ")).ok(),
+        }
+    }
+}
+
+impl<S, Err> Service<WebRequest<Err>> for ResponseCacheService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if req.method() != Method::GET {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let key = self.key(&req);
+        let refresh = req
+            .headers()
+            .get(header::CACHE_CONTROL)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("no-cache"));
+
+        let cached = match refresh {
+            true => None,
+            false => self
+                .entries
+                .lock()
+                .ok()
+                .and_then(|mut entries| entries.get(&key).cloned()),
+        };
+
+        if let Some(cached) = cached {
+            debug!("[response-cache] serving {key} from the cache");
+            let mut resp = HttpResponse::build(cached.status).body(cached.body);
+            for (name, value) in cached.headers {
+                resp.headers_mut().append(name, value);
+            }
+            resp.headers_mut().insert(
+                HeaderName::from_static(CACHE_STATUS_HEADER),
+                HeaderValue::from_static("HIT"),
+            );
+            return Ok(req.into_response(resp));
+        }
+
+        let mut resp = ctx.call(&self.service, req).await?;
+
+        if let Some(vary) = self.vary() {
+            resp.headers_mut().insert(header::VARY, vary);
+        }
+
+        let body = match resp.response().body() {
+            ResponseBody::Body(Body::Bytes(bytes)) | ResponseBody::Other(Body::Bytes(bytes)) => {
+                Some(bytes.clone())
+            }
+            _ => None,
+        };
+
+        if is_shareable(&resp)
+            && let Some(body) = body
+            && let Ok(mut entries) = self.entries.lock()
+        {
+            let cached = CachedResponse {
+                status: resp.status(),
+                headers: resp
+                    .headers()
+                    .iter()
+                    .filter(|(name, _)| !HOP_BY_HOP.contains(name))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect(),
+                body,
+            };
+            entries.insert(key, cached, Instant::now() + self.policy.ttl);
+        }
+
+        resp.headers_mut().insert(
+            HeaderName::from_static(CACHE_STATUS_HEADER),
+            HeaderValue::from_static("MISS"),
+        );
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    async fn catalog(req: ntex::web::HttpRequest) -> String {
+        let calls = CALLS.fetch_add(1, Ordering::SeqCst) + 1;
+        let user = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("guest");
+        format!("{user}:{calls}")
+    }
+
+    fn routes(cfg: &mut ServiceConfig) {
+        cfg.get_cached(
+            "/cached-catalog",
+            catalog,
+            CachePolicy::new(Duration::from_secs(60)).vary(header::AUTHORIZATION),
+        );
+    }
+
+    #[ntex::test]
+    async fn test_cached_route() {
+        let app = init_service(App::new().configure(routes)).await;
+        let get = |token: &str| {
+            TestRequest::with_uri("/cached-catalog")
+                .header("authorization", token)
+                .to_request()
+        };
+
+        let resp = call_service(&app, get("ann")).await;
+        assert_eq!(resp.headers().get(CACHE_STATUS_HEADER).unwrap(), "MISS");
+        assert_eq!(resp.headers().get(header::VARY).unwrap(), "authorization");
+        assert_eq!(read_body(resp).await, "ann:1");
+
+        let resp = call_service(&app, get("ann")).await;
+        assert_eq!(resp.headers().get(CACHE_STATUS_HEADER).unwrap(), "HIT");
+        assert_eq!(read_body(resp).await, "ann:1");
+
+        // other values of the vary headers have their own entry
+        let resp = call_service(&app, get("bob")).await;
+        assert_eq!(read_body(resp).await, "bob:2");
+
+        let req = TestRequest::with_uri("/cached-catalog")
+            .header("authorization", "ann")
+            .header("cache-control", "no-cache")
+            .to_request();
+        assert_eq!(read_body(call_service(&app, req).await).await, "ann:3");
+        assert_eq!(
+            read_body(call_service(&app, get("ann")).await).await,
+            "ann:3"
+        );
+    }
+
+    static SESSION_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    async fn session(req: ntex::web::HttpRequest) -> HttpResponse {
+        let calls = SESSION_CALLS.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut resp = HttpResponse::Ok();
+        match req.query_string() {
+            "cookie" => resp.header(header::SET_COOKIE, format!("session={calls}")),
+            "private" => resp.header(header::CACHE_CONTROL, "max-age=60, private"),
+            _ => resp.header("keep-alive", "timeout=5"),
+        };
+        resp.body(calls.to_string())
+    }
+
+    #[ntex::test]
+    async fn test_private_responses_are_not_cached() {
+        let app = init_service(App::new().configure(|cfg| {
+            cfg.get_cached(
+                "/cached-session",
+                session,
+                CachePolicy::new(Duration::from_secs(60)).max_entries(1),
+            );
+        }))
+        .await;
+        let get =
+            |query: &str| TestRequest::with_uri(&format!("/cached-session?{query}")).to_request();
+
+        for query in ["cookie", "private"] {
+            call_service(&app, get(query)).await;
+            let resp = call_service(&app, get(query)).await;
+            assert_eq!(resp.headers().get(CACHE_STATUS_HEADER).unwrap(), "MISS");
+        }
+
+        let resp = call_service(&app, get("a")).await;
+        assert!(resp.headers().contains_key("keep-alive"));
+        let first = read_body(resp).await;
+        let resp = call_service(&app, get("a")).await;
+        assert_eq!(resp.headers().get(CACHE_STATUS_HEADER).unwrap(), "HIT");
+        assert!(!resp.headers().contains_key("keep-alive"));
+        assert_eq!(read_body(resp).await, first);
+
+        // a single entry is kept, "b" evicts "a"
+        call_service(&app, get("b")).await;
+        let resp = call_service(&app, get("a")).await;
+        assert_eq!(resp.headers().get(CACHE_STATUS_HEADER).unwrap(), "MISS");
+    }
+}
@@ -0,0 +1,1268 @@
+use crate::enums::ResponseCode;
+use crate::helpers::lru::LruCache;
+use crate::helpers::responder::Responder;
+use crate::http::guard::Guard;
+use crate::http::middlewares::{
+    BeforeMiddlewareHandler, MatchedRoute, MiddlewareChains, RouteMatcher, Sunset,
+};
+use crate::http::response::anyhow::{ErrorContext, ErrorPhase, ResponseError};
+use crate::http::response::envelope::RouteEnvelopeVersion;
+use crate::setup::state::FoxtiveNtexState;
+use ntex::http::error::PayloadError;
+use ntex::http::header::{self, HeaderName, HeaderValue};
+use ntex::http::{HeaderMap, Method, Payload, StatusCode};
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::time;
+use ntex::util::{Bytes, Stream};
+use ntex::web;
+use ntex::web::error::{InternalError, WebResponseError};
+use ntex::web::{HttpRequest, HttpResponse, WebRequest, WebResponse};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tracing::{Instrument, debug, info_span, warn};
+
+/// Kind of request body a controller is willing to parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyParser {
+    /// `application/json` and `application/*+json`
+    Json,
+    /// `application/x-www-form-urlencoded`
+    Form,
+    /// `multipart/form-data`
+    Multipart,
+    /// `text/*`
+    Text,
+    /// any content type
+    Bytes,
+}
+
+impl BodyParser {
+    pub fn accepts(&self, content_type: &str) -> bool {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        match self {
+            BodyParser::Json => {
+                essence == "application/json"
+                    || (essence.starts_with("application/") && essence.ends_with("+json"))
+            }
+            BodyParser::Form => essence == "application/x-www-form-urlencoded",
+            BodyParser::Multipart => essence == "multipart/form-data",
+            BodyParser::Text => essence.starts_with("text/"),
+            BodyParser::Bytes => true,
+        }
+    }
+}
+
+/// Declarative policies of a route group or controller, translated into checks
+/// by `register_routes`. Controller policies take precedence over route policies.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::middlewares::{RateLimit, RoutePolicies};
+/// use std::time::Duration;
+///
+/// let policies = RoutePolicies::default()
+///     .rate_limit(RateLimit::per_minute(60))
+///     .timeout(Duration::from_secs(10))
+///     .body_limit(1024 * 1024);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RoutePolicies {
+    /// maximum requests per client
+    pub rate_limit: Option<RateLimit>,
+    /// maximum handling time, requests taking longer are answered with 504
+    pub timeout: Option<Duration>,
+    /// maximum body size in bytes, larger bodies are rejected with 413 up front when their
+    /// `Content-Length` declares it, else once the bytes read cross it
+    pub body_limit: Option<usize>,
+    /// check executed before the handler, e.g. token verification
+    pub auth: Option<BeforeMiddlewareHandler>,
+    /// response envelope used when the client doesn't ask for a version
+    pub envelope_version: Option<String>,
+    /// `Some(false)` for server-to-server groups skipping CORS processing entirely
+    pub cors: Option<bool>,
+    /// access policy checked before the handler, refused requests are answered with 403
+    pub guard: Option<Guard>,
+    /// headers added to every response
+    pub headers: Option<HeaderPolicy>,
+    /// retirement schedule, past it requests are answered with 410
+    pub sunset: Option<Sunset>,
+}
+
+impl RoutePolicies {
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn body_limit(mut self, bytes: usize) -> Self {
+        self.body_limit = Some(bytes);
+        self
+    }
+
+    pub fn auth(mut self, handler: BeforeMiddlewareHandler) -> Self {
+        self.auth = Some(handler);
+        self
+    }
+
+    /// Envelope version for clients not sending `X-Envelope-Version`, e.g. `v2` on new
+    /// API groups while older ones keep the legacy envelope
+    pub fn envelope_version(mut self, version: &str) -> Self {
+        self.envelope_version = Some(version.to_string());
+        self
+    }
+
+    /// Whether CORS applies, turning it off for purely server-to-server groups avoids the
+    /// CORS work and conflicts with gateways handling it
+    pub fn cors(mut self, enabled: bool) -> Self {
+        self.cors = Some(enabled);
+        self
+    }
+
+    /// Access policy composed from [`Guard`] primitives, e.g. office hours only
+    pub fn guard(mut self, guard: Guard) -> Self {
+        self.guard = Some(guard);
+        self
+    }
+
+    /// Headers added to every response, e.g. `no-store` on authentication endpoints
+    pub fn headers(mut self, headers: HeaderPolicy) -> Self {
+        self.headers = Some(headers);
+        self
+    }
+
+    /// Retire the routes on a schedule, see [`Sunset`]
+    pub fn sunset(mut self, sunset: Sunset) -> Self {
+        self.sunset = Some(sunset);
+        self
+    }
+
+    /// Policies of `self`, overridden by the ones set in `other`; guards are not overridden
+    /// but combined, requests have to pass both, and header policies apply one after the
+    /// other
+    pub fn merge(&self, other: &RoutePolicies) -> RoutePolicies {
+        RoutePolicies {
+            rate_limit: other.rate_limit.or(self.rate_limit),
+            timeout: other.timeout.or(self.timeout),
+            body_limit: other.body_limit.or(self.body_limit),
+            auth: other.auth.or(self.auth),
+            envelope_version: other
+                .envelope_version
+                .clone()
+                .or_else(|| self.envelope_version.clone()),
+            cors: other.cors.or(self.cors),
+            guard: match (&self.guard, &other.guard) {
+                (Some(route), Some(controller)) => Some(route.clone().and(controller.clone())),
+                (route, controller) => controller.clone().or_else(|| route.clone()),
+            },
+            headers: match (&self.headers, &other.headers) {
+                (Some(route), Some(controller)) => Some(route.clone().then(controller)),
+                (route, controller) => controller.clone().or_else(|| route.clone()),
+            },
+            sunset: other.sunset.clone().or_else(|| self.sunset.clone()),
+        }
+    }
+}
+
+/// Fixed-window limit of requests per client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub window: Duration,
+}
+
+impl RateLimit {
+    pub fn new(requests: u32, window: Duration) -> Self {
+        Self { requests, window }
+    }
+
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HeaderRule {
+    Set(HeaderName, HeaderValue),
+    Append(HeaderName, HeaderValue),
+    Remove(HeaderName),
+}
+
+/// Headers applied to the responses of a route group, in the order they were declared,
+/// so caching and security headers are set in one place rather than in each handler.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::kernel::Route;
+/// use foxtive_ntex::http::middlewares::HeaderPolicy;
+/// use ntex::http::header::{HeaderName, HeaderValue};
+///
+/// let auth = Route::new("/auth").headers(HeaderPolicy::no_store());
+///
+/// let catalog = Route::new("/catalog").headers(
+///     HeaderPolicy::public_cache(300).set(
+///         HeaderName::from_static("x-data-source"),
+///         HeaderValue::from_static("catalog"),
+///     ),
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderPolicy {
+    rules: Vec<HeaderRule>,
+}
+
+impl HeaderPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `Cache-Control: no-store`, for responses carrying credentials or personal data
+    pub fn no_store() -> Self {
+        Self::new().set(header::CACHE_CONTROL, HeaderValue::from_static("no-store"))
+    }
+
+    /// `Cache-Control: public, max-age=<seconds>`, for data that rarely changes
+    pub fn public_cache(max_age: u64) -> Self {
+        let value = HeaderValue::from_str(&format!("public, max-age={max_age}"))
+            .expect("cache-control value is valid");
+        Self::new().set(header::CACHE_CONTROL, value)
+    }
+
+    /// Set `name`, replacing the value given by the handler
+    pub fn set(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.rules.push(HeaderRule::Set(name, value));
+        self
+    }
+
+    /// Add a value to `name`, keeping the ones given by the handler
+    pub fn append(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.rules.push(HeaderRule::Append(name, value));
+        self
+    }
+
+    pub fn remove(mut self, name: HeaderName) -> Self {
+        self.rules.push(HeaderRule::Remove(name));
+        self
+    }
+
+    /// Rules of `self` followed by the ones of `other`
+    pub fn then(mut self, other: &HeaderPolicy) -> Self {
+        self.rules.extend(other.rules.iter().cloned());
+        self
+    }
+
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for rule in &self.rules {
+            match rule {
+                HeaderRule::Set(name, value) => headers.insert(name.clone(), value.clone()),
+                HeaderRule::Append(name, value) => headers.append(name.clone(), value.clone()),
+                HeaderRule::Remove(name) => headers.remove(name),
+            }
+        }
+    }
+}
+
+/// Counters of a rate-limited scope
+struct RateLimiter {
+    limit: RateLimit,
+    clients: Mutex<LruCache<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            clients: Mutex::new(LruCache::new(MAX_TRACKED_CLIENTS)),
+        }
+    }
+
+    /// Records a hit, returns the time left in the window when the client is over the limit
+    fn hit(&self, client: IpAddr) -> Option<Duration> {
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap();
+
+        // windows are stored until they end, a missing one starts over
+        let (started_at, count) = match clients.get(&client) {
+            Some(&(started_at, count)) => (started_at, count + 1),
+            None => (now, 1),
+        };
+        clients.insert(client, (started_at, count), started_at + self.limit.window);
+
+        match count > self.limit.requests {
+            true => Some(self.limit.window - now.duration_since(started_at)),
+            false => None,
+        }
+    }
+}
+
+/// Clients tracked per scope, the least recently seen are forgotten first
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Rate limit counters of one server, keyed by scope path so that every worker shares
+/// the same counters, and the proxies allowed to report the client address
+#[derive(Clone, Default)]
+pub(crate) struct RateLimiters {
+    scopes: Arc<Mutex<HashMap<String, Arc<RateLimiter>>>>,
+    trusted_proxies: Arc<Vec<IpAddr>>,
+}
+
+impl RateLimiters {
+    pub(crate) fn new(trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            scopes: Default::default(),
+            trusted_proxies: Arc::new(trusted_proxies),
+        }
+    }
+
+    fn limiter_for(&self, scope: &str, limit: RateLimit) -> Arc<RateLimiter> {
+        let mut scopes = self.scopes.lock().unwrap();
+        let limiter = scopes
+            .entry(scope.to_string())
+            .or_insert_with(|| Arc::new(RateLimiter::new(limit)));
+
+        if limiter.limit != limit {
+            *limiter = Arc::new(RateLimiter::new(limit));
+        }
+
+        limiter.clone()
+    }
+
+    /// Address of the client, the peer unless it is a trusted proxy, in which case the
+    /// last `X-Forwarded-For` hop not added by a trusted proxy. Connections without a
+    /// peer address, e.g. over unix sockets, share the unspecified address
+    fn client(&self, req: &HttpRequest) -> IpAddr {
+        self.resolve_client(req.peer_addr().map(|peer| peer.ip()), req.headers())
+    }
+
+    fn resolve_client(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> IpAddr {
+        let peer = match peer {
+            Some(peer) => peer,
+            None => return IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        };
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+
+        let forwarded: Vec<IpAddr> = headers
+            .get_all(X_FORWARDED_FOR)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|hop| hop.trim().parse::<IpAddr>().ok())
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+
+        forwarded
+            .into_iter()
+            .rev()
+            .find(|hop| !self.trusted_proxies.contains(hop))
+            .unwrap_or(peer)
+    }
+}
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// Metadata enforced for every request entering a controller scope
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RouteMeta {
+    /// full path of the controller scope
+    pub(crate) scope: String,
+    /// accepted request bodies, `None` accepts everything
+    pub(crate) body_parsers: Option<Vec<BodyParser>>,
+    pub(crate) policies: RoutePolicies,
+    /// methods declared per route template with `Controller::methods`
+    pub(crate) methods: Vec<(String, Vec<Method>)>,
+}
+
+struct Rejection {
+    message: String,
+    code: ResponseCode,
+    retry_after: Option<Duration>,
+    allow: Option<HeaderValue>,
+}
+
+impl Rejection {
+    fn new(message: String, code: ResponseCode) -> Self {
+        Self {
+            message,
+            code,
+            retry_after: None,
+            allow: None,
+        }
+    }
+
+    fn into_response(self, req: HttpRequest) -> WebResponse {
+        let mut resp = Responder::message(&self.message, self.code);
+        if let Some(retry_after) = self.retry_after {
+            let seconds = retry_after.as_secs().max(1);
+            resp.headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        if let Some(allow) = self.allow {
+            resp.headers_mut().insert(header::ALLOW, allow);
+        }
+
+        WebResponse::new(resp, req)
+    }
+}
+
+impl RouteMeta {
+    /// `Allow` header of `template` when `method` is not declared for it, `None` when
+    /// allowed or when the methods of the template are unknown
+    fn disallowed(&self, template: &str, method: &Method) -> Option<HeaderValue> {
+        let (_, methods) = self.methods.iter().find(|(known, _)| known == template)?;
+        if methods.contains(method) {
+            return None;
+        }
+
+        let mut names: Vec<&str> = methods.iter().map(Method::as_str).collect();
+        if !methods.contains(&Method::OPTIONS) {
+            names.push(Method::OPTIONS.as_str());
+        }
+        HeaderValue::from_str(&names.join(", ")).ok()
+    }
+
+    /// Checks the request before its payload is consumed, returning the rejection if any
+    fn check(&self, headers: &HeaderMap) -> Option<Rejection> {
+        if let Some(parsers) = &self.body_parsers
+            && has_body(headers)
+        {
+            let content_type = headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+
+            if !parsers.iter().any(|parser| parser.accepts(content_type)) {
+                debug!("[route-layer] rejecting body with content type '{content_type}'");
+                return Some(Rejection::new(
+                    format!("Unsupported content type '{content_type}'"),
+                    ResponseCode::UnsupportedMediaType,
+                ));
+            }
+        }
+
+        if let Some(limit) = self.policies.body_limit
+            && content_length(headers).is_some_and(|length| length > limit as u64)
+        {
+            return Some(Rejection::new(
+                format!("Request body exceeds the limit of {limit} bytes"),
+                ResponseCode::PayloadTooLarge,
+            ));
+        }
+
+        None
+    }
+
+    fn check_guard(&self, req: &HttpRequest) -> Option<Rejection> {
+        let guard = self.policies.guard.as_ref()?;
+        match guard.check(req) {
+            true => None,
+            false => {
+                debug!(
+                    "[route-layer] {guard:?} refused {} on {}",
+                    client_key(req),
+                    self.scope
+                );
+                Some(Rejection::new(
+                    "Access denied".to_string(),
+                    ResponseCode::Forbidden,
+                ))
+            }
+        }
+    }
+
+    /// The limit of the route policies, or the `rate_limit_per_minute` of the runtime
+    /// settings shared by the routes without one
+    fn check_rate_limit(&self, req: &HttpRequest) -> Option<Rejection> {
+        let (scope, limit) = match self.policies.rate_limit {
+            Some(limit) => (self.scope.as_str(), limit),
+            None => (RUNTIME_RATE_LIMIT_SCOPE, runtime_rate_limit(req)?),
+        };
+        let limiters = rate_limiters(req);
+        let client = limiters.client(req);
+
+        limiters
+            .limiter_for(scope, limit)
+            .hit(client)
+            .map(|retry_after| {
+                debug!(
+                    "[route-layer] rate limit exceeded by {client} on {}",
+                    self.scope
+                );
+                Rejection {
+                    retry_after: Some(retry_after),
+                    ..Rejection::new(
+                        "Too many requests, please try again later".to_string(),
+                        ResponseCode::TooManyRequests,
+                    )
+                }
+            })
+    }
+}
+
+/// Counters of the runtime settings limit, not a valid scope path
+const RUNTIME_RATE_LIMIT_SCOPE: &str = "*";
+
+fn runtime_rate_limit(req: &HttpRequest) -> Option<RateLimit> {
+    let state = req.app_state::<FoxtiveNtexState>()?;
+    let per_minute = state.runtime_settings.current().rate_limit_per_minute?;
+    Some(RateLimit::per_minute(per_minute))
+}
+
+fn client_key(req: &HttpRequest) -> String {
+    rate_limiters(req).client(req).to_string()
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+}
+
+fn has_body(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::TRANSFER_ENCODING)
+        || content_length(headers).is_some_and(|length| length > 0)
+}
+
+/// Payload failing with [`PayloadError::Overflow`], answered with 413, once more than
+/// `limit` bytes were read, for the bodies whose size isn't declared up front
+struct LimitedPayload {
+    payload: Payload,
+    limit: usize,
+    read: usize,
+}
+
+impl LimitedPayload {
+    fn new(payload: Payload, limit: usize) -> Self {
+        Self {
+            payload,
+            limit,
+            read: 0,
+        }
+    }
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.read > this.limit {
+            return Poll::Ready(None);
+        }
+
+        match this.payload.poll_recv(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                this.read += chunk.len();
+                match this.read > this.limit {
+                    true => {
+                        debug!(
+                            "[route-layer] body exceeds the limit of {} bytes",
+                            this.limit
+                        );
+                        Poll::Ready(Some(Err(PayloadError::Overflow)))
+                    }
+                    false => Poll::Ready(Some(Ok(chunk))),
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+/// Middleware applied by `register_routes` to each controller scope
+#[derive(Clone)]
+pub(crate) struct RouteLayer {
+    meta: Arc<RouteMeta>,
+    matcher: Arc<RouteMatcher>,
+}
+
+impl RouteLayer {
+    pub(crate) fn new(meta: RouteMeta, matcher: Arc<RouteMatcher>) -> Self {
+        Self {
+            meta: Arc::new(meta),
+            matcher,
+        }
+    }
+}
+
+/// Counters of apps built without the server's [`RateLimiters`], e.g. in tests
+fn fallback_limiters() -> &'static RateLimiters {
+    static LIMITERS: OnceLock<RateLimiters> = OnceLock::new();
+    LIMITERS.get_or_init(RateLimiters::default)
+}
+
+fn rate_limiters(req: &HttpRequest) -> &RateLimiters {
+    req.app_state::<RateLimiters>()
+        .unwrap_or_else(|| fallback_limiters())
+}
+
+impl<S> ServiceMiddleware<S> for RouteLayer {
+    type Service = RouteLayerService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        RouteLayerService {
+            service,
+            meta: self.meta.clone(),
+            matcher: self.matcher.clone(),
+        }
+    }
+}
+
+pub(crate) struct RouteLayerService<S> {
+    service: S,
+    meta: Arc<RouteMeta>,
+    matcher: Arc<RouteMatcher>,
+}
+
+impl<S> RouteLayerService<S> {
+    async fn handle<Err>(
+        &self,
+        req: HttpRequest,
+        payload: Payload,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<WebResponse, web::Error>
+    where
+        S: Service<WebRequest<Err>, Response = WebResponse, Error = web::Error>,
+        Err: web::ErrorRenderer,
+    {
+        let rejection = self
+            .meta
+            .check(req.headers())
+            .or_else(|| self.meta.check_rate_limit(&req))
+            .or_else(|| self.meta.check_guard(&req));
+
+        if let Some(rejection) = rejection {
+            return Ok(rejection.into_response(req));
+        }
+
+        let req = match self.meta.policies.auth {
+            None => req,
+            Some(auth) => {
+                let context = req.clone();
+                let started = Instant::now();
+                let result = auth(req)
+                    .instrument(info_span!("middleware", layer = "auth", kind = "policy"))
+                    .await;
+                MiddlewareChains::record(&self.meta.scope, "auth", started.elapsed());
+                result.map_err(|err| {
+                    let name = format!("{} auth", self.meta.scope);
+                    let context = ErrorContext::new(&context, &name, ErrorPhase::Before);
+                    web::Error::from(ResponseError::in_context(err, context))
+                })?
+            }
+        };
+
+        let payload = match self.meta.policies.body_limit {
+            Some(limit) => Payload::from_stream(LimitedPayload::new(payload, limit)),
+            None => payload,
+        };
+        let request = WebRequest::<Err>::from_parts(req, payload).map_err(|_| {
+            warn!(
+                "[route-layer] request of {} is still borrowed",
+                self.meta.scope
+            );
+            let resp = Responder::internal_server_error();
+            web::Error::from(InternalError::<_, web::DefaultError>::from_response(
+                "request still borrowed",
+                resp,
+            ))
+        })?;
+        let started = Instant::now();
+        let result = match self.meta.policies.timeout {
+            None => ctx.call(&self.service, request).await,
+            Some(timeout) => Self::with_timeout(timeout, ctx.call(&self.service, request)).await,
+        };
+        MiddlewareChains::record(&self.meta.scope, "handler", started.elapsed());
+        result
+    }
+
+    async fn with_timeout(
+        timeout: Duration,
+        handler: impl Future<Output = Result<WebResponse, web::Error>>,
+    ) -> Result<WebResponse, web::Error> {
+        match time::timeout(timeout, handler).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("[route-layer] request timed out after {timeout:?}");
+                // the request went down with the cancelled handler, so the response is
+                // carried by the error instead
+                let resp = Responder::message(
+                    "Request took too long to process",
+                    ResponseCode::GatewayTimeout,
+                );
+                Err(InternalError::<_, web::DefaultError>::from_response("timeout", resp).into())
+            }
+        }
+    }
+}
+
+impl<S, Err> Service<WebRequest<Err>> for RouteLayerService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse, Error = web::Error>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = web::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        request: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let (req, payload) = request.into_parts();
+
+        let route = match MatchedRoute::from_http_request(&req) {
+            Some(route) => route,
+            None => {
+                let route = self.matcher.resolve(&req);
+                req.extensions_mut().insert(route.clone());
+                route
+            }
+        };
+
+        if let Some(version) = &self.meta.policies.envelope_version {
+            req.extensions_mut()
+                .insert(RouteEnvelopeVersion(version.clone()));
+        }
+
+        let method = req.method().clone();
+        let sunset = self.meta.policies.sunset.as_ref();
+        let retired = sunset.filter(|sunset| sunset.is_retired(&req));
+        let result = match (retired, self.meta.disallowed(route.template(), &method)) {
+            (Some(sunset), _) => {
+                debug!("[route-layer] {} is retired", route.template());
+                Ok(WebResponse::new(sunset.gone(), req))
+            }
+            (None, Some(allow)) if method == Method::OPTIONS => {
+                let resp = HttpResponse::NoContent()
+                    .header(header::ALLOW, allow)
+                    .finish();
+                Ok(WebResponse::new(resp, req))
+            }
+            (None, Some(allow)) => {
+                debug!(
+                    "[route-layer] {method} is not allowed on {}",
+                    route.template()
+                );
+                let rejection = Rejection {
+                    allow: Some(allow),
+                    ..Rejection::new(
+                        format!("Method {method} is not allowed on this resource"),
+                        ResponseCode::MethodNotAllowed,
+                    )
+                };
+                Ok(rejection.into_response(req))
+            }
+            (None, None) => {
+                let span = info_span!("route", method = %method, template = route.template());
+                self.handle(req, payload, ctx).instrument(span).await
+            }
+        };
+
+        let mut resp = match result {
+            Ok(resp) => resp,
+            // errors are rendered further up, the headers have to travel with them
+            Err(error) => {
+                return Err(web::Error::new(DecoratedError {
+                    error,
+                    headers: self.meta.policies.headers.clone(),
+                    sunset: sunset.cloned(),
+                }));
+            }
+        };
+
+        decorate(
+            resp.headers_mut(),
+            self.meta.policies.headers.as_ref(),
+            sunset,
+        );
+        debug!(
+            target: "foxtive_ntex::access",
+            "{method} {} {}",
+            route.template(),
+            resp.status().as_u16()
+        );
+
+        Ok(resp)
+    }
+}
+
+fn decorate(headers: &mut HeaderMap, policy: Option<&HeaderPolicy>, sunset: Option<&Sunset>) {
+    if let Some(policy) = policy {
+        policy.apply(headers);
+    }
+    if let Some(sunset) = sunset {
+        sunset.decorate(headers);
+    }
+}
+
+/// Error of a route, decorated with its header policy and sunset headers once rendered
+struct DecoratedError {
+    error: web::Error,
+    headers: Option<HeaderPolicy>,
+    sunset: Option<Sunset>,
+}
+
+impl fmt::Display for DecoratedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl fmt::Debug for DecoratedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+impl WebResponseError for DecoratedError {
+    fn status_code(&self) -> StatusCode {
+        self.error.as_response_error().status_code()
+    }
+
+    fn error_response(&self, req: &HttpRequest) -> HttpResponse {
+        let mut resp = self.error.as_response_error().error_response(req);
+        decorate(
+            resp.headers_mut(),
+            self.headers.as_ref(),
+            self.sunset.as_ref(),
+        );
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::kernel::{Controller, Route, register_routes};
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use ntex::web::{App, HttpResponse, ServiceConfig};
+
+    #[test]
+    fn test_body_parser_accepts() {
+        assert!(BodyParser::Json.accepts("application/json; charset=utf-8"));
+        assert!(BodyParser::Json.accepts("application/problem+json"));
+        assert!(!BodyParser::Json.accepts("multipart/form-data; boundary=x"));
+        assert!(BodyParser::Multipart.accepts("multipart/form-data; boundary=x"));
+        assert!(BodyParser::Text.accepts("text/csv"));
+    }
+
+    fn items(cfg: &mut ServiceConfig) {
+        cfg.route("", web::post().to(|| async { HttpResponse::Ok() }));
+    }
+
+    #[ntex::test]
+    async fn test_rejects_unexpected_body_with_415() {
+        let routes = vec![
+            Route::new("/api")
+                .controller(Controller::new("/items", items).body_parsers(&[BodyParser::Json])),
+        ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let req = TestRequest::post()
+            .uri("/api/items")
+            .header("content-type", "multipart/form-data; boundary=x")
+            .header("content-length", "5")
+            .set_payload("--x--")
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let req = TestRequest::post()
+            .uri("/api/items")
+            .header("content-length", "14")
+            .set_json(&serde_json::json!({"name": "pen"}))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        // body-less requests are not affected
+        let req = TestRequest::post().uri("/api/items").to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[ntex::test]
+    async fn test_body_limit_and_rate_limit() {
+        let routes = vec![
+            Route::new("/limited")
+                .policies(RoutePolicies::default().rate_limit(RateLimit::per_minute(2)))
+                .controller(
+                    Controller::new("/items", items)
+                        .policies(RoutePolicies::default().body_limit(4)),
+                ),
+        ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let req = TestRequest::post()
+            .uri("/limited/items")
+            .header("content-length", "5")
+            .set_payload("hello")
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // controller policies are merged with the route's, the rate limit still applies
+        for _ in 0..2 {
+            let req = TestRequest::post().uri("/limited/items").to_request();
+            assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+        }
+
+        let req = TestRequest::post().uri("/limited/items").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(resp.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[ntex::test]
+    async fn test_body_limit_applies_to_undeclared_bodies() {
+        fn uploads(cfg: &mut ServiceConfig) {
+            cfg.route(
+                "",
+                web::post().to(|body: crate::http::extractors::ByteBody| async move {
+                    HttpResponse::Ok().body(body.into_bytes())
+                }),
+            );
+        }
+
+        let routes =
+            vec![
+                Route::new("/streamed").controller(
+                    Controller::new("/uploads", uploads)
+                        .policies(RoutePolicies::default().body_limit(4)),
+                ),
+            ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let request = |body: &'static str| {
+            TestRequest::post()
+                .uri("/streamed/uploads")
+                .header("transfer-encoding", "chunked")
+                .set_payload(body)
+                .to_request()
+        };
+
+        let resp = call_service(&app, request("hello")).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let resp = call_service(&app, request("hey")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[ntex::test]
+    async fn test_rate_limit_ignores_forwarded_for_from_untrusted_peers() {
+        let routes = vec![
+            Route::new("/keyed")
+                .policies(RoutePolicies::default().rate_limit(RateLimit::per_minute(1)))
+                .controller(Controller::new("/items", items)),
+        ];
+        let app = init_service(
+            App::new()
+                .state(RateLimiters::new(vec![]))
+                .configure(|cfg| register_routes(cfg, routes)),
+        )
+        .await;
+
+        let request = |forwarded: &str| {
+            TestRequest::post()
+                .uri("/keyed/items")
+                .header("x-forwarded-for", forwarded)
+                .to_request()
+        };
+
+        // a client can't dodge the limit by making up forwarded addresses
+        let first = call_service(&app, request("198.51.100.1")).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        let spoofed = call_service(&app, request("198.51.100.2")).await;
+        assert_eq!(spoofed.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[ntex::test]
+    async fn test_runtime_settings_rate_limit_covers_routes_without_one() {
+        let state = FoxtiveNtexState::for_tests();
+        state
+            .runtime_settings
+            .update(|s| s.rate_limit_per_minute = Some(1));
+
+        let routes = vec![
+            Route::new("/open").controller(Controller::new("/items", items)),
+            Route::new("/other").controller(Controller::new("/items", items)),
+        ];
+        let app = init_service(
+            App::new()
+                .state(state.clone())
+                .state(RateLimiters::new(vec![]))
+                .configure(|cfg| register_routes(cfg, routes)),
+        )
+        .await;
+
+        let request = |uri: &str| TestRequest::post().uri(uri).to_request();
+
+        let first = call_service(&app, request("/open/items")).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        // the runtime limit is shared by all routes without their own
+        let second = call_service(&app, request("/other/items")).await;
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        state
+            .runtime_settings
+            .update(|s| s.rate_limit_per_minute = None);
+        let unlimited = call_service(&app, request("/other/items")).await;
+        assert_eq!(unlimited.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_client_address_behind_trusted_proxies() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let direct: IpAddr = "203.0.113.7".parse().unwrap();
+        let limiters = RateLimiters::new(vec![proxy]);
+
+        let forwarded = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(X_FORWARDED_FOR, HeaderValue::from_str(value).unwrap());
+            headers
+        };
+
+        let headers = forwarded("198.51.100.1");
+        assert_eq!(limiters.resolve_client(Some(direct), &headers), direct);
+        assert_eq!(
+            limiters.resolve_client(Some(proxy), &headers),
+            "198.51.100.1".parse::<IpAddr>().unwrap()
+        );
+
+        // hops added by trusted proxies are skipped, the ones before can be forged
+        let headers = forwarded("1.1.1.1, 198.51.100.2, 10.0.0.1");
+        assert_eq!(
+            limiters.resolve_client(Some(proxy), &headers),
+            "198.51.100.2".parse::<IpAddr>().unwrap()
+        );
+
+        // unreadable headers fall back to the peer
+        let headers = forwarded("not-an-ip");
+        assert_eq!(limiters.resolve_client(Some(proxy), &headers), proxy);
+        assert_eq!(
+            limiters.resolve_client(None, &headers),
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        );
+    }
+
+    fn orders(cfg: &mut ServiceConfig) {
+        cfg.route("", web::get().to(|| async { HttpResponse::Ok() }))
+            .route("", web::post().to(|| async { HttpResponse::Created() }))
+            .route("/{id}", web::get().to(|| async { HttpResponse::Ok() }));
+    }
+
+    #[ntex::test]
+    async fn test_declared_methods_answer_405_with_allow() {
+        let routes = vec![
+            Route::new("/api").controller(
+                Controller::new("/orders", orders)
+                    .methods("", &[Method::GET, Method::POST])
+                    .methods("/{id}", &[Method::GET]),
+            ),
+        ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let req = TestRequest::delete().uri("/api/orders/7").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(resp.headers().get(header::ALLOW).unwrap(), "GET, OPTIONS");
+
+        let req = TestRequest::default()
+            .method(Method::OPTIONS)
+            .uri("/api/orders")
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            resp.headers().get(header::ALLOW).unwrap(),
+            "GET, POST, OPTIONS"
+        );
+
+        let req = TestRequest::post().uri("/api/orders").to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::CREATED);
+    }
+
+    #[ntex::test]
+    async fn test_guards_of_route_and_controller_are_combined() {
+        let routes = vec![
+            Route::new("/internal")
+                .policies(RoutePolicies::default().guard(Guard::header("x-env", "staging")))
+                .controller(Controller::new("/items", items).policies(
+                    RoutePolicies::default().guard(!Guard::content_type("multipart/form-data")),
+                )),
+        ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let req = TestRequest::post().uri("/internal/items").to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let req = TestRequest::post()
+            .uri("/internal/items")
+            .header("x-env", "staging")
+            .header("content-type", "multipart/form-data; boundary=x")
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::FORBIDDEN
+        );
+
+        let req = TestRequest::post()
+            .uri("/internal/items")
+            .header("x-env", "staging")
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    fn cached(cfg: &mut ServiceConfig) {
+        cfg.route(
+            "",
+            web::get().to(|| async {
+                HttpResponse::Ok()
+                    .header(header::CACHE_CONTROL, "max-age=5")
+                    .header("x-trace", "handler")
+                    .finish()
+            }),
+        );
+    }
+
+    #[ntex::test]
+    async fn test_header_policies_apply_to_every_response() {
+        let routes = vec![
+            Route::new("/auth")
+                .headers(HeaderPolicy::no_store().append(
+                    HeaderName::from_static("x-trace"),
+                    HeaderValue::from_static("policy"),
+                ))
+                .controller(
+                    Controller::new("/token", cached)
+                        .methods("", &[Method::GET])
+                        .policies(RoutePolicies::default().headers(
+                            HeaderPolicy::new().remove(HeaderName::from_static("x-powered-by")),
+                        )),
+                ),
+        ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let resp = call_service(&app, TestRequest::with_uri("/auth/token").to_request()).await;
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        let traces: Vec<_> = resp.headers().get_all("x-trace").collect();
+        assert_eq!(traces, ["handler", "policy"]);
+
+        // rejections carry the headers too
+        let req = TestRequest::delete().uri("/auth/token").to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+    }
+
+    #[ntex::test]
+    async fn test_sunset_retires_routes_past_the_grace_period() {
+        use chrono::{Duration as Days, Utc};
+
+        let retired = Sunset::at(Utc::now() - Days::days(10))
+            .grace(Duration::from_secs(86400))
+            .successor("/api/v2/orders")
+            .exempt_key("partner");
+        let in_grace = Sunset::at(Utc::now() - Days::days(1)).grace(Duration::from_secs(7 * 86400));
+
+        let routes = vec![
+            Route::new("/v1").controller(
+                Controller::new("/orders", items)
+                    .policies(RoutePolicies::default().sunset(retired)),
+            ),
+            Route::new("/v1/legacy")
+                .sunset(in_grace)
+                .controller(Controller::new("/items", items)),
+        ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let resp = call_service(&app, TestRequest::post().uri("/v1/orders").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::GONE);
+        assert_eq!(resp.headers().get("deprecation").unwrap(), "true");
+        let body = ntex::web::test::read_body(resp).await;
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["successor"], "/api/v2/orders");
+
+        let req = TestRequest::post()
+            .uri("/v1/orders")
+            .header("x-api-key", "partner")
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().contains_key("sunset"));
+
+        let resp = call_service(
+            &app,
+            TestRequest::post().uri("/v1/legacy/items").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("deprecation").unwrap(), "true");
+    }
+
+    fn slow(cfg: &mut ServiceConfig) {
+        cfg.route(
+            "",
+            web::get().to(|| async {
+                ntex::time::sleep(Duration::from_millis(200)).await;
+                HttpResponse::Ok()
+            }),
+        );
+    }
+
+    #[ntex::test]
+    async fn test_timeout_responds_with_504() {
+        let sunset = Sunset::at(chrono::Utc::now() + chrono::Duration::days(30));
+        let routes = vec![
+            Route::new("/slow")
+                .headers(HeaderPolicy::no_store())
+                .sunset(sunset)
+                .controller(
+                    Controller::new("", slow)
+                        .policies(RoutePolicies::default().timeout(Duration::from_millis(20))),
+                ),
+        ];
+        let app = init_service(App::new().configure(|cfg| register_routes(cfg, routes))).await;
+
+        let req = TestRequest::with_uri("/slow").to_request();
+        let err = app.call(req).await.err().unwrap();
+        let resp = err
+            .as_response_error()
+            .error_response(&TestRequest::default().to_http_request());
+        assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+        // errors carry the route headers too
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-store"
+        );
+        assert_eq!(resp.headers().get("deprecation").unwrap(), "true");
+    }
+}
@@ -0,0 +1,79 @@
+use crate::http::extractors::Timings;
+use foxtive::prelude::AppResult;
+use ntex::http::header::{HeaderName, HeaderValue};
+use ntex::web::WebResponse;
+use std::future::Future;
+use std::pin::Pin;
+use tracing::debug;
+
+/// After middleware rendering phases recorded through [`Timings`] into the `Server-Timing` header.
+///
+/// The phases are also broken down per route in the request stats, when enabled with
+/// `ServerConfig::request_stats`.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::middlewares::{Middleware, server_timing};
+///
+/// let middleware = Middleware::After(server_timing);
+/// ```
+pub fn server_timing(
+    mut resp: WebResponse,
+) -> Pin<Box<dyn Future<Output = AppResult<WebResponse>>>> {
+    Box::pin(async move {
+        let timings = resp.request().extensions().get::<Timings>().cloned();
+        let timings = match timings {
+            Some(timings) => timings,
+            None => return Ok(resp),
+        };
+
+        if timings.is_empty() {
+            return Ok(resp);
+        }
+
+        for phase in timings.phases() {
+            debug!(
+                "[server-timing] {} {} {}: {:?}",
+                resp.request().method(),
+                resp.request().path(),
+                phase.name,
+                phase.duration
+            );
+        }
+
+        if let Ok(value) = HeaderValue::from_str(&timings.header_value()) {
+            resp.headers_mut()
+                .append(HeaderName::from_static("server-timing"), value);
+        }
+
+        Ok(resp)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::web::HttpResponse;
+    use ntex::web::test::TestRequest;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_renders_header() {
+        let req = TestRequest::default().to_http_request();
+        Timings::from_http_request(&req).record("db", Duration::from_millis(3));
+
+        let resp = WebResponse::new(HttpResponse::Ok().finish(), req);
+        let resp = server_timing(resp).await.unwrap();
+
+        assert_eq!(resp.headers().get("server-timing").unwrap(), "db;dur=3.000");
+    }
+
+    #[tokio::test]
+    async fn test_skips_when_nothing_recorded() {
+        let req = TestRequest::default().to_http_request();
+        let resp = WebResponse::new(HttpResponse::Ok().finish(), req);
+        let resp = server_timing(resp).await.unwrap();
+
+        assert!(resp.headers().get("server-timing").is_none());
+    }
+}
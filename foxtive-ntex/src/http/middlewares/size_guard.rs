@@ -0,0 +1,261 @@
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use ntex::http::body::{Body, ResponseBody};
+use ntex::http::header::{HeaderName, HeaderValue};
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{self, WebRequest, WebResponse};
+use serde_json::Value;
+use tracing::{error, warn};
+
+/// Header of truncated list responses, `<kept>/<total>` items
+pub const TRUNCATED_HEADER: &str = "x-response-truncated";
+
+/// What happens to list responses over the threshold of a [`ResponseSizeGuard`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversizeMode {
+    /// answer with 500 and log the endpoint
+    Reject,
+    /// drop the last items until the response fits, adding the `X-Response-Truncated`
+    /// header and logging a warning
+    Truncate,
+}
+
+/// Limit on the serialized size of list responses, nudging endpoints returning unbounded
+/// lists toward pagination before their payloads overwhelm clients.
+///
+/// List responses are JSON arrays, alone or as the `data` of the response envelope.
+/// Other responses are never affected.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::middlewares::ResponseSizeGuard;
+///
+/// // fail loudly in staging, degrade gracefully in production
+/// let strict = ResponseSizeGuard::reject(2 * 1024 * 1024);
+/// let lenient = ResponseSizeGuard::truncate(2 * 1024 * 1024);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseSizeGuard {
+    pub max_bytes: usize,
+    pub mode: OversizeMode,
+}
+
+impl ResponseSizeGuard {
+    pub fn reject(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            mode: OversizeMode::Reject,
+        }
+    }
+
+    pub fn truncate(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            mode: OversizeMode::Truncate,
+        }
+    }
+}
+
+/// Middleware applying the [`ResponseSizeGuard`], requests pass untouched without one
+#[derive(Clone)]
+pub(crate) struct ResponseSizeLimit {
+    guard: Option<ResponseSizeGuard>,
+}
+
+impl ResponseSizeLimit {
+    pub(crate) fn new(guard: Option<ResponseSizeGuard>) -> Self {
+        Self { guard }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for ResponseSizeLimit {
+    type Service = ResponseSizeLimitService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        ResponseSizeLimitService {
+            service,
+            guard: self.guard,
+        }
+    }
+}
+
+pub(crate) struct ResponseSizeLimitService<S> {
+    service: S,
+    guard: Option<ResponseSizeGuard>,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for ResponseSizeLimitService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let resp = ctx.call(&self.service, req).await?;
+        let Some(guard) = self.guard else {
+            return Ok(resp);
+        };
+
+        let list = match resp.response().body() {
+            ResponseBody::Body(Body::Bytes(bytes)) | ResponseBody::Other(Body::Bytes(bytes))
+                if bytes.len() > guard.max_bytes =>
+            {
+                serde_json::from_slice::<Value>(bytes)
+                    .ok()
+                    .filter(|body| list_of(body).is_some())
+                    .map(|body| (body, bytes.len()))
+            }
+            _ => None,
+        };
+
+        let Some((mut body, size)) = list else {
+            return Ok(resp);
+        };
+
+        let path = resp.request().path().to_string();
+        match guard.mode {
+            OversizeMode::Reject => {
+                error!(
+                    "[size-guard] {path} returned a {size} bytes list, over {} bytes; paginate this endpoint",
+                    guard.max_bytes
+                );
+                let message = Responder::message(
+                    "Response is too large, this endpoint must be paginated",
+                    ResponseCode::InternalServerError,
+                );
+                Ok(resp.into_response(message))
+            }
+            OversizeMode::Truncate => {
+                let (kept, total) = truncate(&mut body, guard.max_bytes);
+                warn!(
+                    "[size-guard] {path} returned a {size} bytes list, truncated to {kept} of {total} items"
+                );
+
+                let bytes = Bytes::from(serde_json::to_vec(&body).unwrap_or_default());
+                let mut resp = resp.map_body(|_, _| ResponseBody::Body(Body::Bytes(bytes)));
+                if let Ok(value) = HeaderValue::from_str(&format!("{kept}/{total}")) {
+                    resp.headers_mut()
+                        .insert(HeaderName::from_static(TRUNCATED_HEADER), value);
+                }
+                Ok(resp)
+            }
+        }
+    }
+}
+
+/// Items of a list response: the body itself or the `data` of the envelope
+fn list_of(body: &Value) -> Option<&Vec<Value>> {
+    match body {
+        Value::Array(items) => Some(items),
+        Value::Object(envelope) => envelope.get("data").and_then(Value::as_array),
+        _ => None,
+    }
+}
+
+fn list_of_mut(body: &mut Value) -> Option<&mut Vec<Value>> {
+    match body {
+        Value::Array(items) => Some(items),
+        Value::Object(envelope) => envelope.get_mut("data").and_then(Value::as_array_mut),
+        _ => None,
+    }
+}
+
+/// Keep the leading items fitting in `max_bytes`, returns the kept and total counts
+fn truncate(body: &mut Value, max_bytes: usize) -> (usize, usize) {
+    let Some(items) = list_of_mut(body) else {
+        return (0, 0);
+    };
+
+    let all = std::mem::take(items);
+    let total = all.len();
+    let mut size = serde_json::to_vec(body)
+        .map(|bytes| bytes.len())
+        .unwrap_or(0);
+
+    let mut kept = Vec::new();
+    for item in all {
+        // the item and its separating comma
+        let item_size = serde_json::to_vec(&item)
+            .map(|bytes| bytes.len())
+            .unwrap_or(0)
+            + 1;
+        if size + item_size > max_bytes {
+            break;
+        }
+        size += item_size;
+        kept.push(item);
+    }
+
+    let count = kept.len();
+    if let Some(items) = list_of_mut(body) {
+        *items = kept;
+    }
+    (count, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::http::StatusCode;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+    use ntex::web::{App, HttpResponse};
+    use serde_json::json;
+
+    async fn list() -> HttpResponse {
+        let items: Vec<_> = (0..20).map(|id| json!({ "id": id })).collect();
+        HttpResponse::Ok().json(&json!({ "success": true, "data": items }))
+    }
+
+    async fn text() -> String {
+        "x".repeat(500)
+    }
+
+    #[ntex::test]
+    async fn test_rejects_oversized_lists() {
+        let app = init_service(
+            App::new()
+                .wrap(ResponseSizeLimit::new(Some(ResponseSizeGuard::reject(100))))
+                .route("/list", web::get().to(list))
+                .route("/text", web::get().to(text)),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::with_uri("/list").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let resp = call_service(&app, TestRequest::with_uri("/text").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[ntex::test]
+    async fn test_truncates_oversized_lists() {
+        let app = init_service(
+            App::new()
+                .wrap(ResponseSizeLimit::new(Some(ResponseSizeGuard::truncate(
+                    100,
+                ))))
+                .route("/list", web::get().to(list)),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::with_uri("/list").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let header = resp.headers().get(TRUNCATED_HEADER).unwrap().clone();
+
+        let body = read_body(resp).await;
+        assert!(body.len() <= 100);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let kept = body["data"].as_array().unwrap().len();
+        assert_eq!(header, format!("{kept}/20").as_str());
+        assert_eq!(body["data"][0]["id"], 0);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::helpers::stats::{RequestSample, RequestStats};
+use crate::http::extractors::{RouteTemplate, Timings};
+use ntex::http::body::{BodySize, MessageBody};
+use ntex::http::header;
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, WebRequest, WebResponse};
+use std::time::Instant;
+
+/// Middleware feeding [`RequestStats`], requests are labelled `{method} {route template}`
+#[derive(Clone)]
+pub(crate) struct StatsRecorder {
+    stats: RequestStats,
+}
+
+impl StatsRecorder {
+    pub(crate) fn new(stats: RequestStats) -> Self {
+        Self { stats }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for StatsRecorder {
+    type Service = StatsRecorderService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        StatsRecorderService {
+            service,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+pub(crate) struct StatsRecorderService<S> {
+    service: S,
+    stats: RequestStats,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for StatsRecorderService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if !self.stats.is_enabled() {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let _active = self.stats.track();
+        let started = Instant::now();
+        let method = req.method().clone();
+        let request_size = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+
+        let resp = ctx.call(&self.service, req).await?;
+
+        // the route is only known once the controller scope matched it
+        let route = RouteTemplate::from_http_request(resp.request());
+        let timings = resp.request().extensions().get::<Timings>().cloned();
+        self.stats.record(RequestSample {
+            route: format!("{method} {route}"),
+            status: resp.status().as_u16(),
+            elapsed: started.elapsed(),
+            request_size,
+            response_size: match resp.response().body().size() {
+                BodySize::Sized(size) => Some(size),
+                BodySize::Empty => Some(0),
+                _ => None,
+            },
+            phases: timings
+                .map(|timings| {
+                    let phases = timings.phases().into_iter();
+                    phases.map(|phase| (phase.name, phase.duration)).collect()
+                })
+                .unwrap_or_default(),
+        });
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::kernel::{Controller, Route, register_routes};
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use ntex::web::{App, HttpResponse, ServiceConfig};
+    use std::time::Duration;
+
+    fn users(cfg: &mut ServiceConfig) {
+        cfg.route(
+            "/{id}",
+            web::get().to(|timings: Timings| async move {
+                timings.record("db", Duration::from_millis(2));
+                "user"
+            }),
+        )
+        .route(
+            "/{id}",
+            web::delete().to(|| async { HttpResponse::InternalServerError().finish() }),
+        );
+    }
+
+    #[ntex::test]
+    async fn test_records_route_templates() {
+        let stats = RequestStats::default();
+        let app = init_service(
+            App::new()
+                .wrap(StatsRecorder::new(stats.clone()))
+                .configure(|cfg| {
+                    register_routes(
+                        cfg,
+                        vec![
+                            Route::new("/api")
+                                .controller(Controller::new("/users", users).pattern("/{id}")),
+                        ],
+                    )
+                }),
+        )
+        .await;
+
+        // not collected until enabled
+        call_service(&app, TestRequest::with_uri("/api/users/1").to_request()).await;
+        assert_eq!(stats.snapshot(5).requests, 0);
+
+        stats.configure(Some(Duration::from_secs(60)));
+        for id in 1..4 {
+            let req = TestRequest::with_uri(&format!("/api/users/{id}")).to_request();
+            call_service(&app, req).await;
+        }
+        let req = TestRequest::with_uri("/api/users/9")
+            .method(ntex::http::Method::DELETE)
+            .to_request();
+        call_service(&app, req).await;
+
+        let snapshot = stats.snapshot(5);
+        assert_eq!(snapshot.requests, 4);
+        assert_eq!(snapshot.top_by_traffic[0].route, "GET /api/users/{id}");
+        assert_eq!(snapshot.top_by_traffic[0].requests, 3);
+        assert_eq!(snapshot.top_by_traffic[0].phases[0].name, "db");
+        assert_eq!(snapshot.top_by_traffic[0].phases[0].count, 3);
+        assert_eq!(
+            snapshot.top_by_error_rate[0].route,
+            "DELETE /api/users/{id}"
+        );
+        assert_eq!(snapshot.response_size.max, 4);
+        assert_eq!(snapshot.active_requests, 0);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use futures_util::StreamExt;
+use ntex::http::Payload;
+use ntex::http::error::PayloadError;
+use ntex::http::header;
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, WebRequest, WebResponse};
+use tracing::debug;
+
+/// Rejects bodies larger than their `Content-Length`, and any body over `max_body`, before
+/// extractors buffer them; declared sizes over `max_body` are answered with 413 right away
+/// while overflowing bodies fail with `PayloadError::Overflow`, also answered with 413.
+/// Requests pass untouched when `max_body` is `None`.
+#[derive(Clone)]
+pub(crate) struct StrictContentLength {
+    max_body: Option<usize>,
+}
+
+impl StrictContentLength {
+    pub(crate) fn new(max_body: Option<usize>) -> Self {
+        Self { max_body }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for StrictContentLength {
+    type Service = StrictContentLengthService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        StrictContentLengthService {
+            service,
+            max_body: self.max_body,
+        }
+    }
+}
+
+pub(crate) struct StrictContentLengthService<S> {
+    service: S,
+    max_body: Option<usize>,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for StrictContentLengthService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        mut req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let Some(max_body) = self.max_body else {
+            return ctx.call(&self.service, req).await;
+        };
+
+        let declared = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<usize>().ok());
+
+        if let Some(declared) = declared
+            && declared > max_body
+        {
+            debug!(
+                "[strict-length] {} declares {declared} bytes, over {max_body}",
+                req.path()
+            );
+            let resp =
+                Responder::message("Request body is too large", ResponseCode::PayloadTooLarge);
+            return Ok(req.into_response(resp));
+        }
+
+        let limit = declared.unwrap_or(max_body);
+        let payload = req.take_payload();
+        req.set_payload(capped(payload, limit));
+
+        ctx.call(&self.service, req).await
+    }
+}
+
+/// Payload failing with `PayloadError::Overflow` once more than `limit` bytes are received
+fn capped(payload: Payload, limit: usize) -> Payload {
+    let mut received = 0usize;
+    Payload::from_stream(payload.map(move |chunk| {
+        let chunk = chunk?;
+        received += chunk.len();
+        match received > limit {
+            true => Err(PayloadError::Overflow),
+            false => Ok(chunk),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpError;
+    use futures_util::StreamExt;
+    use ntex::http::StatusCode;
+    use ntex::util::Bytes;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use ntex::web::{App, HttpResponse};
+
+    async fn read_all(mut payload: ntex::web::types::Payload) -> Result<HttpResponse, HttpError> {
+        let mut size = 0;
+        while let Some(chunk) = payload.next().await {
+            size += chunk?.len();
+        }
+        Ok(HttpResponse::Ok().body(size.to_string()))
+    }
+
+    #[ntex::test]
+    async fn test_strict_content_length() {
+        let app = init_service(
+            App::new()
+                .wrap(StrictContentLength::new(Some(8)))
+                .route("/upload", web::post().to(read_all)),
+        )
+        .await;
+
+        let req = TestRequest::post()
+            .uri("/upload")
+            .header("content-length", "9")
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // body larger than declared
+        let req = TestRequest::post()
+            .uri("/upload")
+            .header("content-length", "2")
+            .set_payload(Bytes::from_static(b"hello"))
+            .to_request();
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        // undeclared bodies are capped
+        let mut req = TestRequest::post()
+            .uri("/upload")
+            .set_payload(Bytes::from_static(b"hello world"))
+            .to_request();
+        req.headers_mut().remove(header::CONTENT_LENGTH);
+        assert_eq!(
+            call_service(&app, req).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        let req = TestRequest::post()
+            .uri("/upload")
+            .set_payload(Bytes::from_static(b"hello"))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+    }
+}
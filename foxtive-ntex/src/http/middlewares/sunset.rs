@@ -0,0 +1,155 @@
+use crate::FoxtiveNtexState;
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use chrono::{DateTime, Utc};
+use ntex::http::Response;
+use ntex::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use ntex::web::HttpRequest;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Retirement schedule of a route, set with `RoutePolicies::sunset` or `Route::sunset`.
+///
+/// Until the sunset date, responses carry `Deprecation`, `Sunset` and a `Link` to the
+/// successor. Once the date and the grace period are past, requests are answered with
+/// `410 Gone` and the migration information, except for the exempted API keys.
+///
+/// The current time is read from the clock of the state when available, so a frozen
+/// `RequestClock` drives the enforcement in tests.
+///
+/// # Example
+/// ```
+/// use chrono::{TimeZone, Utc};
+/// use foxtive_ntex::http::middlewares::Sunset;
+/// use std::time::Duration;
+///
+/// let sunset = Sunset::at(Utc.with_ymd_and_hms(2026, 8, 1, 0, 0, 0).unwrap())
+///     .grace(Duration::from_secs(14 * 86400))
+///     .successor("/api/v2/orders")
+///     .migration_guide("https://docs.example.com/migrations/orders-v2")
+///     .exempt_key("partner-acme");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sunset {
+    at: DateTime<Utc>,
+    grace: Duration,
+    successor: Option<String>,
+    migration_guide: Option<String>,
+    key_header: HeaderName,
+    exempt_keys: HashSet<String>,
+}
+
+/// Body of the `410 Gone` answered for retired routes
+#[derive(Debug, Serialize)]
+struct Retirement<'a> {
+    sunset: String,
+    successor: Option<&'a str>,
+    migration_guide: Option<&'a str>,
+}
+
+impl Sunset {
+    pub fn at(at: DateTime<Utc>) -> Self {
+        Self {
+            at,
+            grace: Duration::ZERO,
+            successor: None,
+            migration_guide: None,
+            key_header: HeaderName::from_static("x-api-key"),
+            exempt_keys: HashSet::new(),
+        }
+    }
+
+    /// Keep serving the route for `grace` after the sunset date, none by default
+    pub fn grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    /// Path replacing the route, advertised as `successor-version`
+    pub fn successor(mut self, path: &str) -> Self {
+        self.successor = Some(path.to_string());
+        self
+    }
+
+    /// Documentation of the migration, advertised as `sunset` link and in the 410 body
+    pub fn migration_guide(mut self, url: &str) -> Self {
+        self.migration_guide = Some(url.to_string());
+        self
+    }
+
+    /// Header carrying the API key of the client, `X-Api-Key` by default
+    pub fn key_header(mut self, name: HeaderName) -> Self {
+        self.key_header = name;
+        self
+    }
+
+    /// Keep serving the client sending `key` past the sunset, e.g. a partner with an
+    /// extended migration deadline
+    pub fn exempt_key(mut self, key: &str) -> Self {
+        self.exempt_keys.insert(key.to_string());
+        self
+    }
+
+    /// Whether the route is retired for `req`
+    pub(crate) fn is_retired(&self, req: &HttpRequest) -> bool {
+        let now = req
+            .app_state::<FoxtiveNtexState>()
+            .map(|state| state.clock.now())
+            .unwrap_or_else(Utc::now);
+
+        let enforced_at = match chrono::Duration::from_std(self.grace) {
+            Ok(grace) => self.at + grace,
+            Err(_) => return false,
+        };
+
+        now >= enforced_at && !self.is_exempt(req)
+    }
+
+    fn is_exempt(&self, req: &HttpRequest) -> bool {
+        req.headers()
+            .get(&self.key_header)
+            .and_then(|key| key.to_str().ok())
+            .is_some_and(|key| self.exempt_keys.contains(key))
+    }
+
+    /// `410 Gone` with the migration information
+    pub(crate) fn gone(&self) -> Response {
+        let retirement = Retirement {
+            sunset: self.at.to_rfc3339(),
+            successor: self.successor.as_deref(),
+            migration_guide: self.migration_guide.as_deref(),
+        };
+
+        Responder::send_msg(
+            retirement,
+            ResponseCode::Gone,
+            "This endpoint has been retired",
+        )
+    }
+
+    /// Add the deprecation headers to a response
+    pub(crate) fn decorate(&self, headers: &mut HeaderMap) {
+        headers.insert(
+            HeaderName::from_static("deprecation"),
+            HeaderValue::from_static("true"),
+        );
+
+        let date = self.at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            headers.insert(HeaderName::from_static("sunset"), value);
+        }
+
+        let links = [
+            (&self.successor, "successor-version"),
+            (&self.migration_guide, "sunset"),
+        ];
+        for (target, rel) in links {
+            if let Some(target) = target
+                && let Ok(link) = HeaderValue::from_str(&format!("<{target}>; rel=\"{rel}\""))
+            {
+                headers.append(header::LINK, link);
+            }
+        }
+    }
+}
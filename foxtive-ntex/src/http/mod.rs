@@ -1,11 +1,22 @@
 use foxtive::prelude::{AppMessage, AppResult};
 use ntex::http::error::BlockingError;
 
+pub mod admin;
+pub mod assets;
+pub mod dynamic;
 pub mod extractors;
+pub mod guard;
 pub mod kernel;
+pub mod manifest;
 pub mod middlewares;
+pub mod plugin;
 pub mod response;
 pub mod server;
+#[cfg(any(test, feature = "dev-tools"))]
+pub mod testing;
+pub mod well_known;
+#[cfg(feature = "ws")]
+pub mod ws;
 
 use crate::enums::ResponseCode;
 use crate::helpers::responder::Responder;
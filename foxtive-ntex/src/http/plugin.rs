@@ -0,0 +1,220 @@
+use crate::FoxtiveNtexState;
+use crate::http::kernel::Route;
+use crate::http::middlewares::Middleware;
+use foxtive::prelude::AppResult;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tracing::{error, info};
+
+pub type PluginFuture = Pin<Box<dyn Future<Output = AppResult<()>>>>;
+
+/// Reusable module (auth, metrics, admin panel...) attached to a server as a single unit
+/// with `ServerConfig::register_plugin`.
+///
+/// Every hook is optional. Hooks run in registration order, `on_shutdown` in reverse order.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::FoxtiveNtexState;
+/// use foxtive_ntex::http::kernel::{Controller, Route};
+/// use foxtive_ntex::http::plugin::{FoxtivePlugin, PluginFuture};
+/// use ntex::web::{self, HttpResponse, ServiceConfig};
+///
+/// struct Metrics;
+///
+/// fn metrics(cfg: &mut ServiceConfig) {
+///     cfg.route("", web::get().to(|| async { HttpResponse::Ok().body("up 1") }));
+/// }
+///
+/// impl FoxtivePlugin for Metrics {
+///     fn name(&self) -> &str {
+///         "metrics"
+///     }
+///
+///     fn routes(&self) -> Vec<Route> {
+///         vec![Route::new("").controller(Controller::new("/metrics", metrics))]
+///     }
+///
+///     fn on_start(&self, _state: FoxtiveNtexState) -> PluginFuture {
+///         Box::pin(async { Ok(()) })
+///     }
+/// }
+/// ```
+pub trait FoxtivePlugin: Send + Sync + 'static {
+    fn name(&self) -> &str;
+
+    /// Called once the state is created, before the app bootstrap callback
+    fn configure_state(&self, _state: &FoxtiveNtexState) -> AppResult<()> {
+        Ok(())
+    }
+
+    /// Routes registered next to the app's own
+    fn routes(&self) -> Vec<Route> {
+        vec![]
+    }
+
+    /// Middlewares wrapping every request of the app
+    fn middlewares(&self) -> Vec<Middleware> {
+        vec![]
+    }
+
+    /// Called before the server starts accepting requests, failing aborts the start
+    fn on_start(&self, _state: FoxtiveNtexState) -> PluginFuture {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Called once the server has stopped
+    fn on_shutdown(&self, _state: FoxtiveNtexState) -> PluginFuture {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Plugins registered on a server
+#[derive(Clone, Default)]
+pub(crate) struct Plugins(Vec<Arc<dyn FoxtivePlugin>>);
+
+impl Plugins {
+    pub(crate) fn push(&mut self, plugin: Arc<dyn FoxtivePlugin>) {
+        self.0.push(plugin);
+    }
+
+    pub(crate) fn configure_state(&self, state: &FoxtiveNtexState) -> AppResult<()> {
+        for plugin in &self.0 {
+            plugin.configure_state(state)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|plugin| plugin.name().to_string())
+            .collect()
+    }
+
+    pub(crate) fn routes(&self) -> Vec<Route> {
+        self.0.iter().flat_map(|plugin| plugin.routes()).collect()
+    }
+
+    pub(crate) fn middlewares(&self) -> Vec<Middleware> {
+        self.0
+            .iter()
+            .flat_map(|plugin| plugin.middlewares())
+            .collect()
+    }
+
+    pub(crate) async fn start(&self, state: &FoxtiveNtexState) -> AppResult<()> {
+        for plugin in &self.0 {
+            info!("[plugin] starting '{}'", plugin.name());
+            plugin.on_start(state.clone()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Shut every plugin down, failures are logged so the others still run
+    pub(crate) async fn shutdown(&self, state: &FoxtiveNtexState) {
+        for plugin in self.0.iter().rev() {
+            info!("[plugin] shutting down '{}'", plugin.name());
+            if let Err(err) = plugin.on_shutdown(state.clone()).await {
+                error!("[plugin] '{}' failed to shut down: {err:?}", plugin.name());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::kernel::{Controller, register_routes};
+    use foxtive::prelude::AppMessage;
+    use ntex::http::StatusCode;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+    use ntex::web::{self, App, HttpRequest, HttpResponse, ServiceConfig, WebResponse};
+    use std::sync::Mutex;
+
+    static EVENTS: Mutex<Vec<String>> = Mutex::new(vec![]);
+
+    struct Audit(&'static str);
+
+    fn ping(cfg: &mut ServiceConfig) {
+        cfg.route("", web::get().to(|| async { HttpResponse::Ok().finish() }));
+    }
+
+    fn deny_banned(req: HttpRequest) -> Pin<Box<dyn Future<Output = AppResult<HttpRequest>>>> {
+        Box::pin(async move {
+            match req.headers().contains_key("x-banned") {
+                true => AppMessage::Forbidden.ar(),
+                false => Ok(req),
+            }
+        })
+    }
+
+    fn tag(mut resp: WebResponse) -> Pin<Box<dyn Future<Output = AppResult<WebResponse>>>> {
+        Box::pin(async move {
+            resp.headers_mut()
+                .insert("x-audited".parse().unwrap(), "1".parse().unwrap());
+            Ok(resp)
+        })
+    }
+
+    impl FoxtivePlugin for Audit {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn routes(&self) -> Vec<Route> {
+            vec![Route::new("/audit").controller(Controller::new("/ping", ping))]
+        }
+
+        fn middlewares(&self) -> Vec<Middleware> {
+            vec![Middleware::Before(deny_banned), Middleware::After(tag)]
+        }
+
+        fn on_shutdown(&self, _state: FoxtiveNtexState) -> PluginFuture {
+            let name = self.0.to_string();
+            Box::pin(async move {
+                EVENTS.lock().unwrap().push(name);
+                Ok(())
+            })
+        }
+    }
+
+    #[ntex::test]
+    async fn test_plugin_routes_and_middlewares() {
+        let mut plugins = Plugins::default();
+        plugins.push(Arc::new(Audit("audit")));
+
+        let routes = plugins.routes();
+        let app = init_service(
+            App::new()
+                .configure(|cfg| register_routes(cfg, routes))
+                .wrap(Middleware::chain(plugins.middlewares())),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::with_uri("/audit/ping").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("x-audited").unwrap(), "1");
+
+        let req = TestRequest::with_uri("/audit/ping")
+            .header("x-banned", "1")
+            .to_request();
+        assert!(app.call(req).await.is_err());
+    }
+
+    #[ntex::test]
+    async fn test_shutdown_runs_in_reverse_order() {
+        let mut plugins = Plugins::default();
+        plugins.push(Arc::new(Audit("first")));
+        plugins.push(Arc::new(Audit("second")));
+
+        let state = FoxtiveNtexState::for_tests();
+
+        plugins.start(&state).await.unwrap();
+        plugins.shutdown(&state).await;
+        assert_eq!(*EVENTS.lock().unwrap(), vec!["second", "first"]);
+    }
+}
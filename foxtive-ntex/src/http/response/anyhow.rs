@@ -1,20 +1,106 @@
+use crate::FoxtiveNtexState;
 use crate::error::HttpError;
 use foxtive::Error;
 use foxtive::prelude::AppMessage;
 use ntex::http::StatusCode;
 use ntex::http::error::BlockingError;
 use ntex::web::{HttpRequest, HttpResponse, WebResponseError};
+use serde::Serialize;
 use std::fmt::{Debug, Display, Formatter};
 use thiserror::Error;
 
+/// Step of the request handling an error came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPhase {
+    Before,
+    Handler,
+    After,
+}
+
+impl Display for ErrorPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ErrorPhase::Before => "before",
+            ErrorPhase::Handler => "handler",
+            ErrorPhase::After => "after",
+        })
+    }
+}
+
+/// Where a middleware error happened, included in its log line and, when error debugging
+/// is on, under `debug.context` of the error envelope, so both can be tied together
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorContext {
+    /// `x-request-id` header, generated when the client sent none
+    pub request_id: String,
+    pub middleware: String,
+    pub phase: ErrorPhase,
+}
+
+impl ErrorContext {
+    pub fn new(req: &HttpRequest, middleware: &str, phase: ErrorPhase) -> Self {
+        let request_id = Self::sent_request_id(req).unwrap_or_else(|| {
+            req.app_state::<FoxtiveNtexState>()
+                .map(|state| state.rng.clone())
+                .unwrap_or_default()
+                .token(16)
+        });
+
+        Self::with_request_id(request_id, middleware, phase)
+    }
+
+    pub fn with_request_id(request_id: String, middleware: &str, phase: ErrorPhase) -> Self {
+        Self {
+            request_id,
+            middleware: middleware.to_string(),
+            phase,
+        }
+    }
+
+    /// `x-request-id` header of the request
+    pub(crate) fn sent_request_id(req: &HttpRequest) -> Option<String> {
+        req.headers()
+            .get("x-request-id")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    }
+
+    /// Log `err` with the context, in a single line
+    pub fn log(&self, err: &dyn Debug) {
+        tracing::error!(
+            request_id = %self.request_id,
+            middleware = %self.middleware,
+            phase = %self.phase,
+            "[middleware-level-error][{}] {} (request {}): {err:?}",
+            self.phase,
+            self.middleware,
+            self.request_id
+        );
+    }
+}
+
 #[derive(Debug, Error)]
 pub struct ResponseError {
     pub error: foxtive::Error,
+    pub context: Option<ErrorContext>,
 }
 
 impl ResponseError {
     pub fn new(error: foxtive::Error) -> Self {
-        Self { error }
+        Self {
+            error,
+            context: None,
+        }
+    }
+
+    /// Error of a middleware, logged right away with its context
+    pub fn in_context(error: foxtive::Error, context: ErrorContext) -> Self {
+        context.log(&error);
+        Self {
+            error,
+            context: Some(context),
+        }
     }
 }
 
@@ -30,7 +116,7 @@ impl WebResponseError for ResponseError {
     }
 
     fn error_response(&self, _: &HttpRequest) -> HttpResponse {
-        helpers::make_response(&self.error)
+        helpers::make_response_in(&self.error, self.context.as_ref())
     }
 }
 
@@ -63,8 +149,13 @@ impl From<BlockingError<foxtive::Error>> for ResponseError {
 pub mod helpers {
     use crate::contracts::ResponseCodeContract;
     use crate::enums::ResponseCode;
+    use crate::helpers::json_message::JsonMessage;
     use crate::helpers::responder::Responder;
     use crate::http::HttpError;
+    use crate::http::response::anyhow::ErrorContext;
+    use crate::http::response::debug::ErrorDebug;
+    use crate::http::response::status::{ErrorKind, StatusOverrides};
+    use foxtive::helpers::json::json_empty;
     use foxtive::prelude::AppMessage;
     use ntex::http::StatusCode;
     use ntex::http::error::BlockingError;
@@ -73,10 +164,10 @@ pub mod helpers {
 
     pub fn make_status_code(err: &foxtive::Error) -> StatusCode {
         match err.downcast_ref::<AppMessage>() {
-            Some(msg) => msg.status_code(),
+            Some(msg) => StatusOverrides::message_status(msg),
             None => match err.downcast_ref::<BlockingError<AppMessage>>() {
                 Some(err) => match err {
-                    BlockingError::Error(msg) => msg.status_code(),
+                    BlockingError::Error(msg) => StatusOverrides::message_status(msg),
                     BlockingError::Canceled => StatusCode::INTERNAL_SERVER_ERROR,
                 },
                 None => match err.downcast_ref::<HttpError>() {
@@ -85,7 +176,7 @@ pub mod helpers {
                         None => StatusCode::INTERNAL_SERVER_ERROR,
                         Some(err) => {
                             error!("Json-Error: {err}");
-                            StatusCode::BAD_REQUEST
+                            StatusOverrides::resolve(ErrorKind::Json, StatusCode::BAD_REQUEST)
                         }
                     },
                 },
@@ -94,22 +185,31 @@ pub mod helpers {
     }
 
     pub fn make_response(err: &foxtive::Error) -> HttpResponse {
+        make_response_in(err, None)
+    }
+
+    /// Error response, with the context of the middleware that failed under `debug`
+    pub fn make_response_in(err: &foxtive::Error, context: Option<&ErrorContext>) -> HttpResponse {
         let status = make_status_code(err);
+        let error_json = |err: &foxtive::Error, body: String, status: StatusCode| -> HttpResponse {
+            make_error_json(err, context, body, status)
+        };
 
         match err.downcast_ref::<AppMessage>() {
             Some(msg) => {
                 msg.log();
-                make_json_response(msg.message(), status)
+                error_json(err, msg.message(), status)
             }
             None => match err.downcast_ref::<BlockingError<AppMessage>>() {
-                Some(err) => match err {
+                Some(blocking) => match blocking {
                     BlockingError::Error(msg) => {
                         error!("Error: {msg}");
-                        make_json_response(msg.message(), status)
+                        error_json(err, msg.message(), status)
                     }
                     BlockingError::Canceled => {
                         error!("Ntex Blocking Error");
-                        make_json_response(
+                        error_json(
+                            err,
                             AppMessage::InternalServerError.message(),
                             StatusCode::INTERNAL_SERVER_ERROR,
                         )
@@ -118,17 +218,19 @@ pub mod helpers {
                 None => match err.downcast_ref::<HttpError>() {
                     Some(err) => crate::error::helpers::make_http_error_response(err),
                     None => match err.downcast_ref::<serde_json::Error>() {
-                        Some(err) => {
-                            error!("Error: {err}");
+                        Some(json_err) => {
+                            error!("Error: {json_err}");
                             // We can't send JSON error as a response, we don't know what may be leaked
-                            make_json_response(
+                            error_json(
+                                err,
                                 "Data processing error".to_string(),
                                 StatusCode::BAD_REQUEST,
                             )
                         }
                         None => {
                             error!("Error: {err}");
-                            make_json_response(
+                            error_json(
+                                err,
                                 AppMessage::InternalServerError.message(),
                                 StatusCode::INTERNAL_SERVER_ERROR,
                             )
@@ -143,4 +245,27 @@ pub mod helpers {
         let code = ResponseCode::from_status(status);
         Responder::message(&body, code)
     }
+
+    /// Error envelope, with the cause chain under `debug` when enabled
+    fn make_error_json(
+        err: &foxtive::Error,
+        context: Option<&ErrorContext>,
+        body: String,
+        status: StatusCode,
+    ) -> HttpResponse {
+        let debug = match ErrorDebug::current().describe(err, context) {
+            None => return make_json_response(body, status),
+            Some(debug) => debug,
+        };
+
+        let code = ResponseCode::from_status(status);
+        let envelope = JsonMessage::make(json_empty(), code.code(), false, Some(body));
+        match serde_json::to_value(envelope) {
+            Ok(mut envelope) => {
+                envelope["debug"] = debug;
+                Responder::with_code_headers(Responder::respond(envelope, status), &code)
+            }
+            Err(_) => make_json_response(AppMessage::InternalServerError.message(), status),
+        }
+    }
 }
@@ -0,0 +1,246 @@
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use crate::http::HttpResult;
+use crate::http::response::anyhow::helpers::make_status_code;
+use foxtive::prelude::{AppMessage, AppResult};
+use futures_util::{StreamExt, stream};
+use ntex::http::error::BlockingError;
+use serde::Serialize;
+use std::future::Future;
+
+/// Outcome of one item of a bulk request
+#[derive(Debug, Serialize)]
+pub struct BulkItemResult<T> {
+    /// position of the item in the request
+    pub index: usize,
+    pub status: u16,
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+/// Per-item outcomes of a bulk request, in request order
+#[derive(Debug, Serialize)]
+pub struct BulkResult<T> {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub items: Vec<BulkItemResult<T>>,
+}
+
+impl<T: Serialize> BulkResult<T> {
+    /// 200 when every item succeeded, 400 when all failed, 207 otherwise
+    pub fn response_code(&self) -> ResponseCode {
+        match (self.succeeded, self.failed) {
+            (_, 0) => ResponseCode::Ok,
+            (0, _) => ResponseCode::BadRequest,
+            _ => ResponseCode::MultiStatus,
+        }
+    }
+
+    /// Send the per-item results in the standard envelope
+    pub fn respond(self) -> HttpResult {
+        let code = self.response_code();
+        Ok(Responder::send(self, code))
+    }
+}
+
+impl<T> FromIterator<AppResult<T>> for BulkResult<T> {
+    fn from_iter<I: IntoIterator<Item = AppResult<T>>>(outcomes: I) -> Self {
+        let items: Vec<_> = outcomes
+            .into_iter()
+            .enumerate()
+            .map(|(index, outcome)| match outcome {
+                Ok(data) => BulkItemResult {
+                    index,
+                    status: 200,
+                    success: true,
+                    data: Some(data),
+                    error: None,
+                },
+                Err(err) => {
+                    let status = make_status_code(&err);
+                    BulkItemResult {
+                        index,
+                        status: status.as_u16(),
+                        success: false,
+                        data: None,
+                        error: Some(error_message(&err, status.canonical_reason())),
+                    }
+                }
+            })
+            .collect();
+
+        let succeeded = items.iter().filter(|item| item.success).count();
+        BulkResult {
+            failed: items.len() - succeeded,
+            succeeded,
+            items,
+        }
+    }
+}
+
+/// Only app messages are meant for clients, other errors are described by their status
+fn error_message(err: &foxtive::Error, reason: Option<&str>) -> String {
+    match err.downcast_ref::<AppMessage>() {
+        Some(msg) => msg.message(),
+        None => match err.downcast_ref::<BlockingError<AppMessage>>() {
+            Some(BlockingError::Error(msg)) => msg.message(),
+            _ => reason.unwrap_or("Error").to_string(),
+        },
+    }
+}
+
+/// Runs a closure over the items of a bulk request, `concurrency` at a time.
+///
+/// # Example
+/// ```
+/// use foxtive::prelude::{AppMessage, AppResult};
+/// use foxtive_ntex::http::HttpResult;
+/// use foxtive_ntex::http::extractors::DeJsonBody;
+/// use foxtive_ntex::http::response::bulk::BulkProcessor;
+///
+/// async fn import(body: DeJsonBody<Vec<String>>) -> HttpResult {
+///     BulkProcessor::new()
+///         .concurrency(4)
+///         .max_items(500)
+///         .run(body.into_inner(), |_index, email| async move {
+///             match email.contains('@') {
+///                 true => Ok(email),
+///                 false => AppMessage::WarningMessage("Invalid email").ar(),
+///             }
+///         })
+///         .await?
+///         .respond()
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BulkProcessor {
+    concurrency: usize,
+    max_items: Option<usize>,
+}
+
+impl Default for BulkProcessor {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            max_items: None,
+        }
+    }
+}
+
+impl BulkProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many items are processed at once, 1 (sequential) by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Reject the whole request with 400 when it holds more items
+    pub fn max_items(mut self, max: usize) -> Self {
+        self.max_items = Some(max);
+        self
+    }
+
+    /// Process every item, failures are recorded per item instead of aborting the batch
+    pub async fn run<T, R, F, Fut>(&self, items: Vec<T>, process: F) -> AppResult<BulkResult<R>>
+    where
+        F: Fn(usize, T) -> Fut,
+        Fut: Future<Output = AppResult<R>>,
+    {
+        if let Some(max) = self.max_items
+            && items.len() > max
+        {
+            return Err(AppMessage::WarningMessageString(format!(
+                "Too many items, at most {max} are allowed per request"
+            ))
+            .ae());
+        }
+
+        let outcomes: Vec<AppResult<R>> = stream::iter(items.into_iter().enumerate())
+            .map(|(index, item)| process(index, item))
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        Ok(outcomes.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::http::StatusCode;
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    #[ntex::test]
+    async fn test_per_item_results_and_status() {
+        let result = BulkProcessor::new()
+            .run(vec![1, -2, 3], |_, n| async move {
+                match n > 0 {
+                    true => Ok(n * 10),
+                    false => AppMessage::WarningMessage("must be positive").ar(),
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!((result.succeeded, result.failed), (2, 1));
+        assert_eq!(result.items[1].index, 1);
+        assert_eq!(result.items[1].status, 400);
+        assert_eq!(result.items[1].error.as_deref(), Some("must be positive"));
+        assert_eq!(result.items[2].data, Some(30));
+        assert_eq!(result.respond().unwrap().status(), StatusCode::MULTI_STATUS);
+
+        let all_ok: BulkResult<i32> = vec![Ok(1)].into_iter().collect();
+        assert_eq!(all_ok.respond().unwrap().status(), StatusCode::OK);
+
+        let all_failed: BulkResult<i32> = vec![Err(foxtive::Error::msg("db down"))]
+            .into_iter()
+            .collect();
+        assert_eq!(all_failed.items[0].status, 500);
+        assert_eq!(
+            all_failed.items[0].error.as_deref(),
+            Some("Internal Server Error")
+        );
+        assert_eq!(
+            all_failed.respond().unwrap().status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[ntex::test]
+    async fn test_concurrency_limit_and_max_items() {
+        let running = Cell::new(0);
+        let peak = Cell::new(0);
+
+        let result = BulkProcessor::new()
+            .concurrency(2)
+            .run((0..6).collect(), |index, _: i32| {
+                let (running, peak) = (&running, &peak);
+                async move {
+                    running.set(running.get() + 1);
+                    peak.set(peak.get().max(running.get()));
+                    ntex::time::sleep(Duration::from_millis(5)).await;
+                    running.set(running.get() - 1);
+                    Ok(index)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(peak.get(), 2);
+        let order: Vec<_> = result.items.iter().map(|item| item.data).collect();
+        assert_eq!(order, (0..6).map(Some).collect::<Vec<_>>());
+
+        let too_many = BulkProcessor::new()
+            .max_items(2)
+            .run(vec![1, 2, 3], |_, n| async move { Ok(n) })
+            .await;
+        assert!(too_many.is_err());
+    }
+}
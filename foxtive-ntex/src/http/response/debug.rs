@@ -0,0 +1,101 @@
+use crate::http::response::anyhow::ErrorContext;
+use crate::http::server::ServerSettings;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::backtrace::BacktraceStatus;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static ERROR_DEBUG: AtomicU8 = AtomicU8::new(ErrorDebug::Off as u8);
+
+/// How much error detail is added to error envelopes under the `debug` key
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorDebug {
+    #[default]
+    Off = 0,
+    /// Include the error cause chain
+    Chain = 1,
+    /// Include the error cause chain and the captured backtrace (requires `RUST_BACKTRACE`)
+    ChainWithBacktrace = 2,
+}
+
+impl ErrorDebug {
+    /// Level of the server handling the current request, configured through
+    /// `ServerConfig::error_debug`, the process default otherwise
+    pub fn current() -> ErrorDebug {
+        if let Some(level) = ServerSettings::read(|settings| settings.error_debug) {
+            return level;
+        }
+
+        match ERROR_DEBUG.load(Ordering::Relaxed) {
+            1 => ErrorDebug::Chain,
+            2 => ErrorDebug::ChainWithBacktrace,
+            _ => ErrorDebug::Off,
+        }
+    }
+
+    /// Process default, for the apps not started with a `ServerConfig`
+    pub fn set(level: ErrorDebug) {
+        ERROR_DEBUG.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// Build the `debug` value for an error, `None` when debugging is off
+    pub(crate) fn describe(
+        &self,
+        err: &foxtive::Error,
+        context: Option<&ErrorContext>,
+    ) -> Option<Value> {
+        let chain: Vec<String> = match self {
+            ErrorDebug::Off => return None,
+            _ => err.chain().map(|cause| cause.to_string()).collect(),
+        };
+
+        let backtrace = match self {
+            ErrorDebug::ChainWithBacktrace => match err.backtrace().status() {
+                BacktraceStatus::Captured => Some(err.backtrace().to_string()),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        Some(json!({
+            "chain": chain,
+            "backtrace": backtrace,
+            "context": context,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_chain() {
+        let err = foxtive::Error::new(std::io::Error::other("connection refused"))
+            .context("failed to load user");
+
+        assert!(ErrorDebug::Off.describe(&err, None).is_none());
+
+        let debug = ErrorDebug::Chain.describe(&err, None).unwrap();
+        assert_eq!(
+            debug["chain"],
+            json!(["failed to load user", "connection refused"])
+        );
+        assert!(debug["backtrace"].is_null());
+    }
+
+    #[test]
+    fn test_describe_middleware_context() {
+        use crate::http::response::anyhow::ErrorPhase;
+
+        let err = foxtive::Error::msg("token expired");
+        let context =
+            ErrorContext::with_request_id("req-7".to_string(), "/api auth", ErrorPhase::Before);
+
+        let debug = ErrorDebug::Chain.describe(&err, Some(&context)).unwrap();
+        assert_eq!(
+            debug["context"],
+            json!({"request_id": "req-7", "middleware": "/api auth", "phase": "before"})
+        );
+    }
+}
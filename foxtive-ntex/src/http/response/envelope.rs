@@ -0,0 +1,275 @@
+use crate::http::response::serializer;
+use ntex::http::StatusCode;
+use ntex::http::body::{Body, ResponseBody};
+use ntex::http::header::{self, HeaderName, HeaderValue};
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::util::Bytes;
+use ntex::web::{self, WebRequest, WebResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Header clients use to pick the envelope version, echoed on the response
+pub const ENVELOPE_VERSION_HEADER: &str = "x-envelope-version";
+
+/// Version of the standard envelope (`code`, `success`, `message`, `timestamp`, `data`)
+pub const LEGACY_ENVELOPE_VERSION: &str = "v1";
+
+/// Standard envelope rendered by `Responder` and the error paths
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub code: String,
+    pub success: bool,
+    pub message: Option<String>,
+    pub timestamp: u64,
+    /// may be absent when the serializer strips nulls
+    #[serde(default)]
+    pub data: Value,
+    /// keys added next to the standard ones, e.g. `debug` on errors
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Renders the standard envelope in another shape, registered under a version with
+/// `ServerConfig::response_formatter`.
+pub trait ResponseFormatter: Send + Sync + 'static {
+    fn format(&self, envelope: Envelope, status: StatusCode) -> Value;
+}
+
+/// Slimmer envelope: the data alone on success, `{"error": {"code", "message"}}` otherwise
+pub struct SlimEnvelope;
+
+impl ResponseFormatter for SlimEnvelope {
+    fn format(&self, envelope: Envelope, _status: StatusCode) -> Value {
+        match envelope.success {
+            true => envelope.data,
+            false => json!({
+                "error": {
+                    "code": envelope.code,
+                    "message": envelope.message,
+                    "details": envelope.data,
+                }
+            }),
+        }
+    }
+}
+
+/// Envelope version a route group or controller defaults to, see `RoutePolicies::envelope_version`
+#[derive(Debug, Clone)]
+pub(crate) struct RouteEnvelopeVersion(pub(crate) String);
+
+/// Registered formatters and the version used when the client doesn't ask for one
+#[derive(Clone)]
+pub struct ResponseFormatters {
+    formatters: Arc<HashMap<String, Arc<dyn ResponseFormatter>>>,
+    default_version: String,
+}
+
+impl Default for ResponseFormatters {
+    fn default() -> Self {
+        Self {
+            formatters: Arc::new(HashMap::new()),
+            default_version: LEGACY_ENVELOPE_VERSION.to_string(),
+        }
+    }
+}
+
+impl ResponseFormatters {
+    pub fn register(&mut self, version: &str, formatter: impl ResponseFormatter) {
+        Arc::make_mut(&mut self.formatters).insert(version.to_string(), Arc::new(formatter));
+    }
+
+    pub fn set_default_version(&mut self, version: &str) {
+        self.default_version = version.to_string();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.formatters.is_empty()
+    }
+
+    fn is_known(&self, version: &str) -> bool {
+        version == LEGACY_ENVELOPE_VERSION || self.formatters.contains_key(version)
+    }
+
+    /// Requested version if known, then the route default, then the global default
+    fn negotiate(&self, requested: Option<&str>, route: Option<&str>) -> String {
+        requested
+            .filter(|version| self.is_known(version))
+            .or(route)
+            .unwrap_or(&self.default_version)
+            .to_string()
+    }
+
+    /// Re-render an envelope body, `None` when it isn't one
+    fn render(&self, version: &str, body: &[u8], status: StatusCode) -> Option<Bytes> {
+        let formatter = self.formatters.get(version)?;
+        let envelope = serde_json::from_slice::<Envelope>(body).ok()?;
+        serializer::to_vec(&formatter.format(envelope, status))
+            .ok()
+            .map(Bytes::from)
+    }
+}
+
+/// Middleware negotiating the envelope version and re-rendering JSON envelopes with
+/// the selected [`ResponseFormatter`]. Streamed and non-JSON bodies are left untouched.
+#[derive(Clone)]
+pub(crate) struct EnvelopeNegotiation {
+    formatters: ResponseFormatters,
+}
+
+impl EnvelopeNegotiation {
+    pub(crate) fn new(formatters: ResponseFormatters) -> Self {
+        Self { formatters }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for EnvelopeNegotiation {
+    type Service = EnvelopeNegotiationService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        EnvelopeNegotiationService {
+            service,
+            formatters: self.formatters.clone(),
+        }
+    }
+}
+
+pub(crate) struct EnvelopeNegotiationService<S> {
+    service: S,
+    formatters: ResponseFormatters,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for EnvelopeNegotiationService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if self.formatters.is_empty() {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let requested = req
+            .headers()
+            .get(ENVELOPE_VERSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string());
+
+        let mut resp = ctx.call(&self.service, req).await?;
+
+        let route = resp
+            .request()
+            .extensions()
+            .get::<RouteEnvelopeVersion>()
+            .map(|version| version.0.clone());
+        let version = self
+            .formatters
+            .negotiate(requested.as_deref(), route.as_deref());
+
+        let headers = resp.headers_mut();
+        headers.append(
+            header::VARY,
+            HeaderValue::from_static(ENVELOPE_VERSION_HEADER),
+        );
+        if let Ok(value) = HeaderValue::from_str(&version) {
+            headers.insert(HeaderName::from_static(ENVELOPE_VERSION_HEADER), value);
+        }
+
+        let is_json = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+
+        if version == LEGACY_ENVELOPE_VERSION || !is_json {
+            return Ok(resp);
+        }
+
+        let status = resp.status();
+        let formatters = self.formatters.clone();
+        Ok(resp.map_body(move |_, body| match body {
+            ResponseBody::Body(Body::Bytes(bytes)) => {
+                match formatters.render(&version, &bytes, status) {
+                    Some(rendered) => ResponseBody::Body(Body::Bytes(rendered)),
+                    None => {
+                        debug!("[envelope] response is not an envelope, sent as-is");
+                        ResponseBody::Body(Body::Bytes(bytes))
+                    }
+                }
+            }
+            body => body,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::ResponseCode;
+    use crate::helpers::responder::Responder;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+
+    #[ntex::test]
+    async fn test_envelope_negotiated_per_client() {
+        let mut formatters = ResponseFormatters::default();
+        formatters.register("v2", SlimEnvelope);
+
+        let app = init_service(
+            App::new()
+                .route(
+                    "/",
+                    web::get().to(|| async { Responder::send(json!({"id": 1}), ResponseCode::Ok) }),
+                )
+                .wrap(EnvelopeNegotiation::new(formatters)),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::with_uri("/").to_request()).await;
+        assert_eq!(resp.headers().get(ENVELOPE_VERSION_HEADER).unwrap(), "v1");
+        let legacy: Value = serde_json::from_slice(&read_body(resp).await).unwrap();
+        assert_eq!(legacy["data"]["id"], 1);
+        assert_eq!(legacy["success"], true);
+
+        let req = TestRequest::with_uri("/")
+            .header(ENVELOPE_VERSION_HEADER, "v2")
+            .to_request();
+        let slim: Value =
+            serde_json::from_slice(&read_body(call_service(&app, req).await).await).unwrap();
+        assert_eq!(slim, json!({"id": 1}));
+
+        // unknown versions fall back to the default
+        let req = TestRequest::with_uri("/missing")
+            .header(ENVELOPE_VERSION_HEADER, "v9")
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.headers().get(ENVELOPE_VERSION_HEADER).unwrap(), "v1");
+    }
+
+    #[test]
+    fn test_slim_errors() {
+        let mut formatters = ResponseFormatters::default();
+        formatters.register("v2", SlimEnvelope);
+
+        let body =
+            br#"{"code":"004","success":false,"message":"Not Found","timestamp":1,"data":null}"#;
+        let rendered = formatters
+            .render("v2", body, StatusCode::NOT_FOUND)
+            .unwrap();
+        let rendered: Value = serde_json::from_slice(&rendered).unwrap();
+
+        assert_eq!(rendered["error"]["code"], "004");
+        assert_eq!(rendered["error"]["message"], "Not Found");
+        assert!(formatters.render("v2", b"[1,2]", StatusCode::OK).is_none());
+    }
+}
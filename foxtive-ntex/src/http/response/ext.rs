@@ -30,6 +30,12 @@ pub trait StructResponseExt: Sized {
     fn respond(self) -> HttpResult;
 }
 
+/// REST creation semantics for handler results, see [`Responder::created_at`](crate::helpers::responder::Responder::created_at)
+pub trait RespondCreatedExt {
+    /// `201 Created` with the resource and its `Location`, e.g. `/api/v1/users/{id}`
+    fn created_at(self, location: &str) -> HttpResult;
+}
+
 pub trait OptionResultResponseExt<T> {
     fn is_empty(&self) -> bool;
 
@@ -0,0 +1,442 @@
+use crate::enums::ResponseCode;
+use crate::helpers::responder::Responder;
+use crate::http::HttpResult;
+use crate::http::response::bulk::BulkResult;
+use foxtive::prelude::{AppMessage, AppResult};
+use foxtive_ntex_multipart::{FileInput, FileReader};
+use futures_util::{StreamExt, stream};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+type ProgressHook = Arc<dyn Fn(&ImportProgress) + Send + Sync>;
+
+/// Spreadsheet formats the [`Importer`] reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Csv,
+    /// requires the `xlsx` feature, the first worksheet is imported
+    Xlsx,
+}
+
+impl ImportFormat {
+    /// Format of `file` from its extension, then its content type
+    pub fn detect(file: &FileInput) -> Option<ImportFormat> {
+        match file.extension.as_deref().map(str::to_lowercase).as_deref() {
+            Some("csv") => return Some(ImportFormat::Csv),
+            Some("xlsx") => return Some(ImportFormat::Xlsx),
+            _ => {}
+        }
+
+        match file.content_type.split(';').next().map(str::trim) {
+            Some("text/csv") => Some(ImportFormat::Csv),
+            Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet") => {
+                Some(ImportFormat::Xlsx)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Data row of an imported file, its cells keyed by the header row
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRow {
+    /// line of the row in the file, the header being line 1
+    pub line: usize,
+    pub cells: HashMap<String, String>,
+}
+
+impl ImportRow {
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.cells.get(column).map(String::as_str)
+    }
+
+    /// Value of a column that must be present and non-empty, a 400 naming the column
+    /// otherwise
+    pub fn required(&self, column: &str) -> AppResult<&str> {
+        match self.get(column).map(str::trim) {
+            Some(value) if !value.is_empty() => Ok(value),
+            _ => AppMessage::WarningMessageString(format!("Column '{column}' is required")).ar(),
+        }
+    }
+}
+
+/// Progress reported after every batch
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportProgress {
+    pub processed: usize,
+    pub total: usize,
+    pub failed: usize,
+}
+
+/// Failed row of an import
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    pub line: usize,
+    pub status: u16,
+    pub error: String,
+}
+
+/// Outcome of an import, rendered in the standard envelope with [`ImportSummary::respond`]
+#[derive(Debug, Serialize)]
+pub struct ImportSummary<T> {
+    pub total: usize,
+    pub imported: usize,
+    pub failed: usize,
+    /// records returned by the row handler, in file order
+    pub records: Vec<T>,
+    pub errors: Vec<RowError>,
+}
+
+impl<T: Serialize> ImportSummary<T> {
+    /// 200 when every row was imported, 400 when all failed, 207 otherwise
+    pub fn response_code(&self) -> ResponseCode {
+        match (self.imported, self.failed) {
+            (_, 0) => ResponseCode::Ok,
+            (0, _) => ResponseCode::BadRequest,
+            _ => ResponseCode::MultiStatus,
+        }
+    }
+
+    /// Send the summary in the standard envelope
+    pub fn respond(self) -> HttpResult {
+        let code = self.response_code();
+        Ok(Responder::send(self, code))
+    }
+}
+
+/// Imports the rows of an uploaded CSV or XLSX file through a row handler, in batches
+/// processed `concurrency` rows at a time. Failures are recorded per row, the import
+/// carries on.
+///
+/// # Example
+/// ```
+/// use foxtive::prelude::{AppMessage, AppResult};
+/// use foxtive_ntex::http::HttpResult;
+/// use foxtive_ntex::http::response::import::{ImportRow, Importer};
+/// use foxtive_ntex_multipart::Multipart;
+///
+/// async fn create_user(row: ImportRow) -> AppResult<String> {
+///     let email = row.required("email")?;
+///     // insert into the database...
+///     Ok(email.to_string())
+/// }
+///
+/// async fn import_users(mut multipart: Multipart) -> HttpResult {
+///     multipart.process().await?;
+///     let file = multipart
+///         .first_file("users")
+///         .cloned()
+///         .ok_or(AppMessage::WarningMessage("Upload the users file"))?;
+///
+///     Importer::new()
+///         .batch_size(200)
+///         .concurrency(8)
+///         .max_rows(10_000)
+///         .on_progress(|progress| tracing::info!("{}/{}", progress.processed, progress.total))
+///         .run(&file, create_user)
+///         .await?
+///         .respond()
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Importer {
+    batch_size: usize,
+    concurrency: usize,
+    max_rows: Option<usize>,
+    format: Option<ImportFormat>,
+    on_progress: Option<ProgressHook>,
+}
+
+impl Default for Importer {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            concurrency: 1,
+            max_rows: None,
+            format: None,
+            on_progress: None,
+        }
+    }
+}
+
+impl Importer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rows per batch, progress is reported after each one; 100 by default
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Rows of a batch processed at once, 1 (sequential) by default
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Reject the whole file with 400 when it holds more data rows
+    pub fn max_rows(mut self, max: usize) -> Self {
+        self.max_rows = Some(max);
+        self
+    }
+
+    /// Read the file as `format` instead of detecting it
+    pub fn format(mut self, format: ImportFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn on_progress(mut self, hook: impl Fn(&ImportProgress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(hook));
+        self
+    }
+
+    /// Import every data row of `file` with `handle`
+    pub async fn run<T, F, Fut>(&self, file: &FileInput, handle: F) -> AppResult<ImportSummary<T>>
+    where
+        F: Fn(ImportRow) -> Fut,
+        Fut: Future<Output = AppResult<T>>,
+    {
+        let format = self
+            .format
+            .or_else(|| ImportFormat::detect(file))
+            .ok_or_else(|| {
+                AppMessage::WarningMessageString(format!(
+                    "Unsupported import file '{}', expected CSV or XLSX",
+                    file.file_name
+                ))
+                .ae()
+            })?;
+
+        // parsed from a reader so spilled uploads aren't loaded into memory whole
+        let content = file.reader()?;
+        let rows = match format {
+            ImportFormat::Csv => read_csv(content)?,
+            ImportFormat::Xlsx => read_xlsx(content)?,
+        };
+
+        if let Some(max) = self.max_rows
+            && rows.len() > max
+        {
+            return AppMessage::WarningMessageString(format!(
+                "Too many rows, at most {max} can be imported at once"
+            ))
+            .ar();
+        }
+
+        let mut summary = ImportSummary {
+            total: rows.len(),
+            imported: 0,
+            failed: 0,
+            records: vec![],
+            errors: vec![],
+        };
+
+        let mut rows = rows.into_iter();
+        loop {
+            let batch: Vec<_> = rows.by_ref().take(self.batch_size).collect();
+            if batch.is_empty() {
+                break;
+            }
+
+            let outcomes: Vec<(usize, AppResult<T>)> = stream::iter(batch)
+                .map(|row| async {
+                    match row {
+                        Ok(row) => (row.line, handle(row).await),
+                        Err((line, err)) => (line, Err(err)),
+                    }
+                })
+                .buffered(self.concurrency)
+                .collect()
+                .await;
+
+            let lines: Vec<usize> = outcomes.iter().map(|(line, _)| *line).collect();
+            let result: BulkResult<T> = outcomes.into_iter().map(|(_, outcome)| outcome).collect();
+            for item in result.items {
+                match item.data {
+                    Some(record) => summary.records.push(record),
+                    None => summary.errors.push(RowError {
+                        line: lines[item.index],
+                        status: item.status,
+                        error: item.error.unwrap_or_default(),
+                    }),
+                }
+            }
+
+            summary.imported = summary.records.len();
+            summary.failed = summary.errors.len();
+            if let Some(hook) = &self.on_progress {
+                hook(&ImportProgress {
+                    processed: summary.imported + summary.failed,
+                    total: summary.total,
+                    failed: summary.failed,
+                });
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Row of the file or the reason it couldn't be read, with its line
+type ParsedRow = Result<ImportRow, (usize, foxtive::Error)>;
+
+fn unreadable(message: String) -> foxtive::Error {
+    AppMessage::WarningMessageString(message).ae()
+}
+
+fn read_csv(content: FileReader) -> AppResult<Vec<ParsedRow>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content);
+
+    let headers = reader
+        .headers()
+        .map_err(|err| unreadable(format!("Unreadable CSV header: {err}")))?
+        .clone();
+
+    Ok(reader
+        .records()
+        .enumerate()
+        .map(|(index, record)| {
+            let line = record
+                .as_ref()
+                .ok()
+                .and_then(|record| record.position())
+                .map(|position| position.line() as usize)
+                .unwrap_or(index + 2);
+
+            match record {
+                Ok(record) => Ok(ImportRow {
+                    line,
+                    cells: headers
+                        .iter()
+                        .zip(record.iter())
+                        .map(|(header, cell)| (header.to_string(), cell.to_string()))
+                        .collect(),
+                }),
+                Err(err) => Err((line, unreadable(format!("Unreadable row: {err}")))),
+            }
+        })
+        .collect())
+}
+
+#[cfg(feature = "xlsx")]
+fn read_xlsx(content: FileReader) -> AppResult<Vec<ParsedRow>> {
+    use calamine::{Reader, Xlsx};
+
+    let mut workbook =
+        Xlsx::new(content).map_err(|err| unreadable(format!("Unreadable XLSX file: {err}")))?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| unreadable("The XLSX file has no worksheet".to_string()))?
+        .map_err(|err| unreadable(format!("Unreadable XLSX worksheet: {err}")))?;
+
+    let mut rows = range.rows();
+    let headers: Vec<String> = match rows.next() {
+        Some(header) => header
+            .iter()
+            .map(|cell| cell.to_string().trim().to_string())
+            .collect(),
+        None => return Ok(vec![]),
+    };
+
+    Ok(rows
+        .enumerate()
+        .map(|(index, row)| {
+            Ok(ImportRow {
+                line: index + 2,
+                cells: headers
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(header, cell)| (header.clone(), cell.to_string().trim().to_string()))
+                    .collect(),
+            })
+        })
+        .collect())
+}
+
+#[cfg(not(feature = "xlsx"))]
+fn read_xlsx(_content: FileReader) -> AppResult<Vec<ParsedRow>> {
+    AppMessage::WarningMessage("XLSX imports are not supported, upload a CSV file").ar()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::http::StatusCode;
+    use ntex::util::Bytes;
+    use std::sync::Mutex;
+
+    fn csv_file(content: &'static str) -> FileInput {
+        FileInput {
+            file_name: "users.csv".to_string(),
+            field_name: "users".to_string(),
+            size: content.len(),
+            content_type: "text/csv".to_string(),
+            bytes: vec![Bytes::from_static(content.as_bytes())],
+            extension: Some("csv".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[ntex::test]
+    async fn test_import_summary_and_progress() {
+        let file = csv_file("email,name\nada@example.com, Ada\n,Nameless\nbob@example.com,Bob\n");
+        let progress = Arc::new(Mutex::new(vec![]));
+        let reported = progress.clone();
+
+        let summary = Importer::new()
+            .batch_size(2)
+            .concurrency(2)
+            .on_progress(move |p| reported.lock().unwrap().push((p.processed, p.failed)))
+            .run(&file, |row| async move {
+                let email = row.required("email")?;
+                Ok(format!("{email}/{}", row.get("name").unwrap_or_default()))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!((summary.total, summary.imported, summary.failed), (3, 2, 1));
+        assert_eq!(
+            summary.records,
+            ["ada@example.com/Ada", "bob@example.com/Bob"]
+        );
+        assert_eq!(summary.errors[0].line, 3);
+        assert_eq!(summary.errors[0].status, 400);
+        assert_eq!(summary.errors[0].error, "Column 'email' is required");
+        assert_eq!(*progress.lock().unwrap(), [(2, 1), (3, 1)]);
+        assert_eq!(
+            summary.respond().unwrap().status(),
+            StatusCode::MULTI_STATUS
+        );
+    }
+
+    #[ntex::test]
+    async fn test_rejected_files() {
+        let handle = |row: ImportRow| async move { Ok(row.line) };
+
+        let file = csv_file("email\na@b.c\nd@e.f\n");
+        let err = Importer::new()
+            .max_rows(1)
+            .run(&file, handle)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("at most 1"));
+
+        let mut file = csv_file("%PDF");
+        file.extension = Some("pdf".to_string());
+        file.content_type = "application/pdf".to_string();
+        assert!(Importer::new().run(&file, handle).await.is_err());
+
+        // rows with a different number of cells than the header are reported, not fatal
+        let file = csv_file("email,name\na@b.c\nd@e.f,D\n");
+        let summary = Importer::new().run(&file, handle).await.unwrap();
+        assert_eq!((summary.imported, summary.failed), (1, 1));
+        assert_eq!(summary.errors[0].line, 2);
+    }
+}
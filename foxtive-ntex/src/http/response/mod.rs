@@ -1,6 +1,14 @@
 pub(crate) mod anyhow;
+pub mod bulk;
+pub mod debug;
+pub mod envelope;
 pub mod ext;
+#[cfg(feature = "import")]
+pub mod import;
 mod message;
 pub mod respond;
 pub mod result;
+pub mod serializer;
+pub mod status;
+pub mod stream;
 pub mod r#struct;
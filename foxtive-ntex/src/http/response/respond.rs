@@ -1,7 +1,7 @@
 use crate::contracts::ResponseCodeContract;
 use crate::enums::ResponseCode;
 use crate::helpers::responder::Responder;
-use crate::http::response::ext::{ResponderExt, ResultResponseExt};
+use crate::http::response::ext::{RespondCreatedExt, ResponderExt, ResultResponseExt};
 use crate::http::{HttpResult, IntoAppResult};
 use foxtive::prelude::{AppMessage, AppResult};
 use ntex::http::error::BlockingError;
@@ -24,6 +24,12 @@ where
     }
 }
 
+impl<T: Serialize> RespondCreatedExt for AppResult<T> {
+    fn created_at(self, location: &str) -> HttpResult {
+        Ok(Responder::created_at(location, self?))
+    }
+}
+
 impl<T> ResponderExt for Result<T, BlockingError<AppMessage>>
 where
     T: Serialize + Sized,
@@ -133,4 +139,20 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_created_at() {
+        let result: AppResult<_> = Ok(json!({"id": 42, "name": "Jane"}));
+
+        let response = result.created_at("/api/v1/users/{id}").unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.headers().get("location").unwrap(),
+            "/api/v1/users/42"
+        );
+
+        let result: AppResult<serde_json::Value> = AppMessage::EntityNotFound("user".into()).ar();
+        let err = result.created_at("/api/v1/users/{id}").unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+    }
 }
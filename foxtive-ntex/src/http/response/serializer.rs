@@ -0,0 +1,220 @@
+use crate::http::server::ServerSettings;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static CONFIG: RwLock<SerializerConfig> = RwLock::new(SerializerConfig::new());
+/// set once a non-default config is installed, keeps the default path lock-free
+static CUSTOMIZED: AtomicBool = AtomicBool::new(false);
+
+/// Casing applied to every object key of JSON responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyCase {
+    /// keys as serialized by the types
+    #[default]
+    AsIs,
+    CamelCase,
+    PascalCase,
+    SnakeCase,
+}
+
+impl KeyCase {
+    pub fn convert(&self, key: &str) -> String {
+        match self {
+            KeyCase::AsIs => key.to_string(),
+            KeyCase::CamelCase => Self::join_words(key, false),
+            KeyCase::PascalCase => Self::join_words(key, true),
+            KeyCase::SnakeCase => {
+                let mut snake = String::with_capacity(key.len() + 4);
+                for (index, char) in key.chars().enumerate() {
+                    match char {
+                        '-' | ' ' => snake.push('_'),
+                        _ if char.is_uppercase() => {
+                            if index > 0 && !snake.ends_with('_') {
+                                snake.push('_');
+                            }
+                            snake.extend(char.to_lowercase());
+                        }
+                        _ => snake.push(char),
+                    }
+                }
+                snake
+            }
+        }
+    }
+
+    fn join_words(key: &str, capitalize_first: bool) -> String {
+        let mut joined = String::with_capacity(key.len());
+        let mut words = key.split(['_', '-', ' ']).filter(|word| !word.is_empty());
+
+        if let Some(first) = words.next() {
+            match capitalize_first {
+                true => Self::push_capitalized(&mut joined, first),
+                false => {
+                    let mut chars = first.chars();
+                    joined.extend(chars.next().into_iter().flat_map(char::to_lowercase));
+                    joined.extend(chars);
+                }
+            }
+        }
+        for word in words {
+            Self::push_capitalized(&mut joined, word);
+        }
+
+        joined
+    }
+
+    fn push_capitalized(target: &mut String, word: &str) {
+        let mut chars = word.chars();
+        target.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+        target.extend(chars);
+    }
+}
+
+/// How response bodies are serialized by `Responder`, error responses and streamed
+/// responders, configured once through `ServerConfig::serializer`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SerializerConfig {
+    /// indent output; only applied in environments allowing debug output
+    pub pretty: bool,
+    pub key_case: KeyCase,
+    /// drop object entries whose value is `null`
+    pub strip_nulls: bool,
+}
+
+impl SerializerConfig {
+    pub const fn new() -> Self {
+        Self {
+            pretty: false,
+            key_case: KeyCase::AsIs,
+            strip_nulls: false,
+        }
+    }
+
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    pub fn key_case(mut self, key_case: KeyCase) -> Self {
+        self.key_case = key_case;
+        self
+    }
+
+    pub fn strip_nulls(mut self, strip_nulls: bool) -> Self {
+        self.strip_nulls = strip_nulls;
+        self
+    }
+
+    /// Process default, for the apps not started with a `ServerConfig`
+    pub fn set(config: SerializerConfig) {
+        CUSTOMIZED.store(config != SerializerConfig::new(), Ordering::Relaxed);
+        if let Ok(mut current) = CONFIG.write() {
+            *current = config;
+        }
+    }
+
+    /// Config of the server handling the current request, the process default otherwise
+    pub fn current() -> SerializerConfig {
+        if let Some(config) = ServerSettings::read(|settings| settings.serializer.clone()) {
+            return config;
+        }
+
+        match CUSTOMIZED.load(Ordering::Relaxed) {
+            false => SerializerConfig::new(),
+            true => CONFIG
+                .read()
+                .map(|config| config.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Serialize `value` into `writer` with this config
+    pub fn write<W: Write, T: Serialize>(&self, writer: W, value: &T) -> serde_json::Result<()> {
+        if self.key_case == KeyCase::AsIs && !self.strip_nulls {
+            return match self.pretty {
+                true => serde_json::to_writer_pretty(writer, value),
+                false => serde_json::to_writer(writer, value),
+            };
+        }
+
+        let value = self.transform(serde_json::to_value(value)?);
+        match self.pretty {
+            true => serde_json::to_writer_pretty(writer, &value),
+            false => serde_json::to_writer(writer, &value),
+        }
+    }
+
+    pub fn to_vec<T: Serialize>(&self, value: &T) -> serde_json::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(128);
+        self.write(&mut bytes, value)?;
+        Ok(bytes)
+    }
+
+    fn transform(&self, value: Value) -> Value {
+        match value {
+            Value::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .filter(|(_, value)| !(self.strip_nulls && value.is_null()))
+                    .map(|(key, value)| (self.key_case.convert(&key), self.transform(value)))
+                    .collect::<Map<_, _>>(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|item| self.transform(item)).collect())
+            }
+            value => value,
+        }
+    }
+}
+
+/// Serialize with the global [`SerializerConfig`]
+pub fn to_vec<T: Serialize>(value: &T) -> serde_json::Result<Vec<u8>> {
+    SerializerConfig::current().to_vec(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_key_cases() {
+        assert_eq!(KeyCase::CamelCase.convert("created_at"), "createdAt");
+        assert_eq!(KeyCase::CamelCase.convert("CreatedAt"), "createdAt");
+        assert_eq!(KeyCase::PascalCase.convert("created_at"), "CreatedAt");
+        assert_eq!(KeyCase::SnakeCase.convert("createdAt"), "created_at");
+        assert_eq!(KeyCase::SnakeCase.convert("Created-At"), "created_at");
+        assert_eq!(KeyCase::AsIs.convert("created_at"), "created_at");
+    }
+
+    #[test]
+    fn test_transforms_nested_values() {
+        let config = SerializerConfig::new()
+            .key_case(KeyCase::CamelCase)
+            .strip_nulls(true);
+
+        let value = json!({
+            "user_id": 1,
+            "deleted_at": null,
+            "line_items": [{"unit_price": 2, "note": null}, null],
+        });
+        let bytes = config.to_vec(&value).unwrap();
+        let value: Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(
+            value,
+            json!({"userId": 1, "lineItems": [{"unitPrice": 2}, null]})
+        );
+
+        let pretty = SerializerConfig::new()
+            .pretty(true)
+            .to_vec(&json!({"a": 1}));
+        assert_eq!(
+            String::from_utf8(pretty.unwrap()).unwrap(),
+            "{\n  \"a\": 1\n}"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use crate::contracts::ResponseCodeContract;
+use crate::enums::ResponseCode;
+use crate::http::server::ServerSettings;
+use foxtive::prelude::AppMessage;
+use ntex::http::StatusCode;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static OVERRIDES: RwLock<StatusOverrides> = RwLock::new(StatusOverrides::new());
+/// set once an override is installed, keeps the default path lock-free
+static CUSTOMIZED: AtomicBool = AtomicBool::new(false);
+
+/// Family of errors whose response status can be overridden with [`StatusOverrides`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// `AppMessage::WarningMessage` and `WarningMessageString`, 400 by default
+    Warning,
+    /// `AppMessage::Unauthorized` and its message variants, 401 by default
+    Unauthorized,
+    /// `AppMessage::Forbidden` and its message variants, 403 by default
+    Forbidden,
+    /// `AppMessage::EntityNotFound`, 404 by default
+    NotFound,
+    /// `AppMessage::InternalServerError` and the other server errors, 500 by default
+    InternalServerError,
+    /// `validator` and `DtoErrors` errors, and unparsable form fields, 400 by default;
+    /// uploaded files failing their rules, 422 by default
+    Validation,
+    /// unreadable request payloads, 400 by default
+    Payload,
+    /// bodies that are not valid JSON for the requested type, 400 by default
+    Json,
+    /// rejected multipart requests answered with 400 by default
+    Multipart,
+}
+
+impl ErrorKind {
+    /// Kind of a message, `None` for the messages carrying their own status or that are
+    /// not errors (`ErrorMessage`, `SuccessMessage`, `Redirect`...)
+    pub fn of_message(msg: &AppMessage) -> Option<ErrorKind> {
+        match msg {
+            AppMessage::WarningMessage(_) | AppMessage::WarningMessageString(_) => {
+                Some(ErrorKind::Warning)
+            }
+            AppMessage::Unauthorized
+            | AppMessage::UnAuthorizedMessage(_)
+            | AppMessage::UnAuthorizedMessageString(_) => Some(ErrorKind::Unauthorized),
+            AppMessage::Forbidden
+            | AppMessage::ForbiddenMessage(_)
+            | AppMessage::ForbiddenMessageString(_) => Some(ErrorKind::Forbidden),
+            AppMessage::EntityNotFound(_) => Some(ErrorKind::NotFound),
+            AppMessage::InternalServerError
+            | AppMessage::InternalServerErrorMessage(_)
+            | AppMessage::MissingEnvironmentVariable(..) => Some(ErrorKind::InternalServerError),
+            _ => None,
+        }
+    }
+}
+
+/// Application-wide status policy, consulted before the default status of each error kind
+/// so handlers don't have to remap errors one by one, configured with
+/// `ServerConfig::status_overrides`.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::response::status::{ErrorKind, StatusOverrides};
+/// use ntex::http::StatusCode;
+///
+/// let overrides = StatusOverrides::new()
+///     .status(ErrorKind::Warning, StatusCode::UNPROCESSABLE_ENTITY)
+///     .status(ErrorKind::Validation, StatusCode::UNPROCESSABLE_ENTITY);
+/// assert_eq!(
+///     overrides.get(ErrorKind::Warning),
+///     Some(StatusCode::UNPROCESSABLE_ENTITY)
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StatusOverrides {
+    statuses: Vec<(ErrorKind, StatusCode)>,
+}
+
+impl StatusOverrides {
+    pub const fn new() -> Self {
+        Self { statuses: vec![] }
+    }
+
+    /// Answer errors of `kind` with `status`
+    ///
+    /// # Panics
+    /// When `status` has no [`ResponseCode`], as the envelope code could not be rendered
+    pub fn status(mut self, kind: ErrorKind, status: StatusCode) -> Self {
+        ResponseCode::from_status(status);
+        self.statuses.retain(|(current, _)| *current != kind);
+        self.statuses.push((kind, status));
+        self
+    }
+
+    pub fn get(&self, kind: ErrorKind) -> Option<StatusCode> {
+        self.statuses
+            .iter()
+            .find(|(current, _)| *current == kind)
+            .map(|(_, status)| *status)
+    }
+
+    /// Process default, for the apps not started with a `ServerConfig`
+    pub fn set(overrides: StatusOverrides) {
+        CUSTOMIZED.store(!overrides.statuses.is_empty(), Ordering::Relaxed);
+        if let Ok(mut current) = OVERRIDES.write() {
+            *current = overrides;
+        }
+    }
+
+    /// Overrides of the server handling the current request, the process default otherwise
+    pub fn current() -> StatusOverrides {
+        if let Some(overrides) = ServerSettings::read(|settings| settings.status_overrides.clone())
+        {
+            return overrides;
+        }
+
+        match CUSTOMIZED.load(Ordering::Relaxed) {
+            false => StatusOverrides::new(),
+            true => OVERRIDES
+                .read()
+                .map(|overrides| overrides.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Status of an error of `kind`, `default` unless overridden
+    pub(crate) fn resolve(kind: ErrorKind, default: StatusCode) -> StatusCode {
+        if let Some(status) = ServerSettings::read(|settings| settings.status_overrides.get(kind)) {
+            return status.unwrap_or(default);
+        }
+
+        if !CUSTOMIZED.load(Ordering::Relaxed) {
+            return default;
+        }
+
+        OVERRIDES
+            .read()
+            .ok()
+            .and_then(|overrides| overrides.get(kind))
+            .unwrap_or(default)
+    }
+
+    /// Status of a message, `msg.status_code()` unless overridden
+    pub(crate) fn message_status(msg: &AppMessage) -> StatusCode {
+        match ErrorKind::of_message(msg) {
+            Some(kind) => Self::resolve(kind, msg.status_code()),
+            None => msg.status_code(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_replace_previous_entries() {
+        let overrides = StatusOverrides::new()
+            .status(ErrorKind::Warning, StatusCode::CONFLICT)
+            .status(ErrorKind::Warning, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            overrides.get(ErrorKind::Warning),
+            Some(StatusCode::UNPROCESSABLE_ENTITY)
+        );
+        assert_eq!(overrides.get(ErrorKind::NotFound), None);
+    }
+
+    #[test]
+    fn test_message_kinds() {
+        assert_eq!(
+            ErrorKind::of_message(&AppMessage::WarningMessage("bad")),
+            Some(ErrorKind::Warning)
+        );
+        assert_eq!(
+            ErrorKind::of_message(&AppMessage::EntityNotFound("user".to_string())),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(
+            ErrorKind::of_message(&AppMessage::ErrorMessage(
+                "gone".to_string(),
+                StatusCode::GONE
+            )),
+            None
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid status code")]
+    fn test_unknown_status_is_rejected() {
+        let _ = StatusOverrides::new().status(ErrorKind::Warning, StatusCode::IM_A_TEAPOT);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::contracts::ResponseCodeContract;
+use crate::enums::ResponseCode;
+use crate::helpers::json_message::JsonMessage;
+use crate::helpers::responder::Responder;
+use crate::http::response::anyhow::helpers::make_status_code;
+use crate::http::response::serializer;
+use crate::http::{HttpError, HttpResult};
+use foxtive::helpers::json::json_empty;
+use foxtive::prelude::AppResult;
+use futures_util::{Stream, StreamExt};
+use ntex::http::header;
+use ntex::time;
+use ntex::util::Bytes;
+use ntex::web::HttpResponse;
+use serde::Serialize;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// Items collected from a stream, along with whether the stream had more to give
+#[derive(Debug, Serialize)]
+pub struct CollectedPage<T> {
+    pub items: Vec<T>,
+    /// whether collection stopped before the stream was exhausted
+    pub has_more: bool,
+}
+
+/// Combinators turning `Stream<Item = AppResult<T>>` (e.g. produced by DB layers) into responses
+#[allow(async_fn_in_trait)]
+pub trait AppResultStreamExt<T>: Stream<Item = AppResult<T>> + Unpin + Sized {
+    /// Collect at most `limit` items, failing on the first error
+    async fn collect_limited(mut self, limit: usize) -> AppResult<CollectedPage<T>> {
+        let mut items = Vec::new();
+
+        while items.len() < limit {
+            match self.next().await {
+                Some(item) => items.push(item?),
+                None => {
+                    return Ok(CollectedPage {
+                        items,
+                        has_more: false,
+                    });
+                }
+            }
+        }
+
+        let has_more = self.next().await.is_some();
+        Ok(CollectedPage { items, has_more })
+    }
+
+    /// Collect at most `limit` items, stopping early once `budget` has elapsed.
+    ///
+    /// Each item is only waited for during the rest of the budget, an item still pending
+    /// when it runs out means the stream has more. A stream ending just then isn't
+    /// reported as having more.
+    async fn collect_within_budget(
+        mut self,
+        limit: usize,
+        budget: Duration,
+    ) -> AppResult<CollectedPage<T>> {
+        let started_at = Instant::now();
+        let mut items = Vec::new();
+
+        loop {
+            let remaining = budget.saturating_sub(started_at.elapsed());
+            // only probing whether the stream has more
+            let done = items.len() == limit || remaining.is_zero();
+            let next = match time::timeout(remaining, self.next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    return Ok(CollectedPage {
+                        items,
+                        has_more: true,
+                    });
+                }
+            };
+
+            match next {
+                None => {
+                    return Ok(CollectedPage {
+                        items,
+                        has_more: false,
+                    });
+                }
+                Some(_) if done => {
+                    return Ok(CollectedPage {
+                        items,
+                        has_more: true,
+                    });
+                }
+                Some(item) => items.push(item?),
+            }
+        }
+    }
+
+    /// Collect at most `limit` items and send them in the standard envelope,
+    /// errors are mapped to the standard error envelope
+    async fn respond_limited(self, limit: usize) -> HttpResult
+    where
+        T: Serialize,
+    {
+        match self.collect_limited(limit).await {
+            Ok(page) => Ok(Responder::send(page, ResponseCode::Ok)),
+            Err(err) => Err(HttpError::AppError(err)),
+        }
+    }
+
+    /// Stream items as newline-delimited JSON without buffering them.
+    ///
+    /// If the stream yields an error, the error is written as a final line using the standard
+    /// error envelope and the response ends.
+    fn into_ndjson_response(self) -> HttpResponse
+    where
+        T: Serialize + 'static,
+        Self: 'static,
+    {
+        HttpResponse::Ok()
+            .header(header::CONTENT_TYPE, "application/x-ndjson")
+            .streaming(NdJsonStream {
+                stream: self,
+                done: false,
+            })
+    }
+}
+
+impl<S, T> AppResultStreamExt<T> for S where S: Stream<Item = AppResult<T>> + Unpin {}
+
+struct NdJsonStream<S> {
+    stream: S,
+    done: bool,
+}
+
+impl<S, T> Stream for NdJsonStream<S>
+where
+    S: Stream<Item = AppResult<T>> + Unpin,
+    T: Serialize,
+{
+    type Item = Result<Bytes, Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        match self.stream.poll_next_unpin(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => {
+                self.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(Ok(item))) => match serializer::to_vec(&item) {
+                Ok(mut line) => {
+                    line.push(b'\n');
+                    Poll::Ready(Some(Ok(Bytes::from(line))))
+                }
+                Err(err) => {
+                    error!("[ndjson-stream] failed to serialize item: {err}");
+                    self.done = true;
+                    Poll::Ready(Some(Ok(error_line(&foxtive::Error::from(err)))))
+                }
+            },
+            Poll::Ready(Some(Err(err))) => {
+                error!("[ndjson-stream] stream error: {err}");
+                self.done = true;
+                Poll::Ready(Some(Ok(error_line(&err))))
+            }
+        }
+    }
+}
+
+fn error_line(err: &foxtive::Error) -> Bytes {
+    let code = ResponseCode::from_status(make_status_code(err));
+    let message = match code.status().is_server_error() {
+        true => "Internal Server Error".to_string(),
+        false => err.to_string(),
+    };
+
+    let envelope = JsonMessage::make(json_empty(), code.code(), false, Some(message));
+    let mut line = serializer::to_vec(&envelope).unwrap_or_default();
+    line.push(b'\n');
+    Bytes::from(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxtive::prelude::AppMessage;
+    use futures_util::stream;
+
+    fn numbers(count: usize) -> impl Stream<Item = AppResult<usize>> + Unpin {
+        stream::iter((0..count).map(Ok))
+    }
+
+    #[tokio::test]
+    async fn test_collect_limited() {
+        let page = numbers(5).collect_limited(3).await.unwrap();
+        assert_eq!(page.items, vec![0, 1, 2]);
+        assert!(page.has_more);
+
+        let page = numbers(2).collect_limited(3).await.unwrap();
+        assert_eq!(page.items, vec![0, 1]);
+        assert!(!page.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_collect_limited_fails_on_error() {
+        let items: Vec<AppResult<usize>> = vec![Ok(1), AppMessage::WarningMessage("bad").ar()];
+        let result = stream::iter(items).collect_limited(10).await;
+        assert!(result.is_err());
+    }
+
+    #[ntex::test]
+    async fn test_collect_within_zero_budget() {
+        let page = numbers(5)
+            .collect_within_budget(10, Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(page.items.is_empty());
+        assert!(page.has_more);
+
+        // an exhausted stream has nothing more, even without budget
+        let page = numbers(0)
+            .collect_within_budget(10, Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(!page.has_more);
+    }
+
+    #[ntex::test]
+    async fn test_collect_within_budget_doesnt_wait_past_it() {
+        let slow_end = stream::iter(0..3).then(|n| async move {
+            if n == 2 {
+                ntex::time::sleep(Duration::from_secs(5)).await;
+            }
+            Ok(n)
+        });
+
+        let started_at = Instant::now();
+        let page = Box::pin(slow_end)
+            .collect_within_budget(10, Duration::from_millis(20))
+            .await
+            .unwrap();
+        assert_eq!(page.items, vec![0, 1]);
+        assert!(page.has_more);
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+
+        // the probe past the limit is bounded too
+        let started_at = Instant::now();
+        let page = numbers(2)
+            .chain(stream::pending())
+            .collect_within_budget(2, Duration::from_millis(20))
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert!(page.has_more);
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_stops_after_error() {
+        let items: Vec<AppResult<usize>> =
+            vec![Ok(1), AppMessage::WarningMessage("bad").ar(), Ok(2)];
+        let lines: Vec<_> = NdJsonStream {
+            stream: stream::iter(items),
+            done: false,
+        }
+        .collect()
+        .await;
+
+        assert_eq!(lines.len(), 2);
+        let error: serde_json::Value = serde_json::from_slice(lines[1].as_ref().unwrap()).unwrap();
+        assert_eq!(error["success"], false);
+        assert_eq!(error["message"], "bad");
+    }
+}
@@ -0,0 +1,241 @@
+use foxtive::prelude::AppResult;
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+/// Middlewares wrapping every app, in the order they see requests
+pub(crate) const FRAMEWORK_MIDDLEWARES: [&str; 8] = [
+    "panic-context",
+    "origin-cors",
+    "cors",
+    "logger",
+    "aliases",
+    "request-stats",
+    "request-cancellation",
+    "envelope-negotiation",
+];
+
+/// Summary of a successful start, enabled with `ServerConfig::boot_report`.
+///
+/// Logged as a single JSON line (`boot report: {...}`) followed by a readable summary,
+/// and optionally written to a file for deploy tooling to diff against expectations.
+#[derive(Debug, Clone, Serialize)]
+pub struct BootReport {
+    pub app: String,
+    /// version of foxtive-ntex
+    pub framework_version: &'static str,
+    pub pid: u32,
+    /// unix timestamp (seconds) of when the server started listening
+    pub booted_at: u64,
+    pub addresses: Vec<String>,
+    pub workers: usize,
+    /// cargo features `foxtive-ntex` was built with
+    pub features: Vec<&'static str>,
+    /// controllers registered on the main server, plugin ones included
+    pub routes: usize,
+    pub middlewares: Vec<String>,
+    pub plugins: Vec<String>,
+    pub additional_servers: Vec<String>,
+    pub tasks: Vec<BootTask>,
+    pub total_ms: u64,
+}
+
+/// Duration of one startup step
+#[derive(Debug, Clone, Serialize)]
+pub struct BootTask {
+    pub name: &'static str,
+    pub took_ms: u64,
+}
+
+impl BootReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn summary(&self) -> String {
+        let tasks = self
+            .tasks
+            .iter()
+            .map(|task| format!("{} {}ms", task.name, task.took_ms))
+            .collect::<Vec<_>>();
+
+        let lines = [
+            format!(
+                "{} (foxtive-ntex v{}, pid {})",
+                self.app, self.framework_version, self.pid
+            ),
+            format!("  listening:   {}", self.addresses.join(", ")),
+            format!("  workers:     {}", self.workers),
+            format!("  routes:      {}", self.routes),
+            format!("  features:    {}", Self::list(&self.features)),
+            format!("  middlewares: {}", Self::list(&self.middlewares)),
+            format!("  plugins:     {}", Self::list(&self.plugins)),
+            format!("  servers:     {}", Self::list(&self.additional_servers)),
+            format!(
+                "  startup:     {} (total {}ms)",
+                tasks.join(", "),
+                self.total_ms
+            ),
+        ];
+
+        lines.join("\n")
+    }
+
+    pub(crate) fn emit(&self, file: Option<&str>) -> AppResult<()> {
+        let json = self.to_json();
+        info!("boot report: {json}");
+        info!("booted\n{}", self.summary());
+
+        if let Some(path) = file {
+            std::fs::write(path, json)?;
+        }
+
+        Ok(())
+    }
+
+    fn list<T: AsRef<str>>(items: &[T]) -> String {
+        match items.is_empty() {
+            true => "-".to_string(),
+            false => items
+                .iter()
+                .map(AsRef::as_ref)
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+/// Records how long each startup step took
+pub(crate) struct BootTimer {
+    started: Instant,
+    last: Instant,
+    tasks: Vec<BootTask>,
+}
+
+impl BootTimer {
+    pub(crate) fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last: now,
+            tasks: vec![],
+        }
+    }
+
+    /// Close the step running since the previous one
+    pub(crate) fn lap(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.tasks.push(BootTask {
+            name,
+            took_ms: millis(now - self.last),
+        });
+        self.last = now;
+    }
+
+    pub(crate) fn finish(self) -> (Vec<BootTask>, u64) {
+        (self.tasks, millis(self.started.elapsed()))
+    }
+}
+
+pub(crate) fn enabled_features() -> Vec<&'static str> {
+    let features = [
+        ("strum", cfg!(feature = "strum")),
+        ("static", cfg!(feature = "static")),
+        ("validator", cfg!(feature = "validator")),
+        ("database", cfg!(feature = "database")),
+        ("jwt", cfg!(feature = "jwt")),
+        ("multipart", cfg!(feature = "multipart")),
+        ("multipart-derive", cfg!(feature = "multipart-derive")),
+        ("multipart-chrono", cfg!(feature = "multipart-chrono")),
+        ("multipart-decimal", cfg!(feature = "multipart-decimal")),
+        (
+            "multipart-bigdecimal",
+            cfg!(feature = "multipart-bigdecimal"),
+        ),
+        ("ws", cfg!(feature = "ws")),
+        ("cursor", cfg!(feature = "cursor")),
+        ("webhooks", cfg!(feature = "webhooks")),
+        ("encoding", cfg!(feature = "encoding")),
+        ("dev-tools", cfg!(feature = "dev-tools")),
+        ("redis", cfg!(feature = "redis")),
+        ("remember-me", cfg!(feature = "remember-me")),
+        ("metrics", cfg!(feature = "metrics")),
+        ("unicode", cfg!(feature = "unicode")),
+        ("import", cfg!(feature = "import")),
+        ("xlsx", cfg!(feature = "xlsx")),
+    ];
+
+    features
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect()
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> BootReport {
+        let mut timer = BootTimer::start();
+        timer.lap("state");
+        timer.lap("bind");
+        let (tasks, total_ms) = timer.finish();
+
+        BootReport {
+            app: "orders".to_string(),
+            framework_version: env!("CARGO_PKG_VERSION"),
+            pid: 42,
+            booted_at: unix_now(),
+            addresses: vec!["0.0.0.0:8080".to_string()],
+            workers: 4,
+            features: enabled_features(),
+            routes: 12,
+            middlewares: FRAMEWORK_MIDDLEWARES.map(String::from).to_vec(),
+            plugins: vec!["metrics".to_string()],
+            additional_servers: vec![],
+            tasks,
+            total_ms,
+        }
+    }
+
+    #[test]
+    fn test_timer_records_tasks_in_order() {
+        let report = report();
+        let names: Vec<_> = report.tasks.iter().map(|task| task.name).collect();
+        assert_eq!(names, ["state", "bind"]);
+    }
+
+    #[test]
+    fn test_json_and_summary() {
+        let report = report();
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["app"], "orders");
+        assert_eq!(json["routes"], 12);
+        assert_eq!(json["plugins"][0], "metrics");
+        assert_eq!(json["tasks"][1]["name"], "bind");
+
+        let summary = report.summary();
+        assert!(summary.contains("listening:   0.0.0.0:8080"));
+        assert!(summary.contains("servers:     -"));
+    }
+
+    #[test]
+    fn test_enabled_features_follow_compilation() {
+        let features = enabled_features();
+        assert_eq!(features.contains(&"jwt"), cfg!(feature = "jwt"));
+        assert_eq!(features.contains(&"multipart"), cfg!(feature = "multipart"));
+        assert_eq!(features.contains(&"static"), cfg!(feature = "static"));
+    }
+}
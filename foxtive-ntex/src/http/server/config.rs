@@ -1,9 +1,26 @@
+use crate::helpers::affinity::AffinityStore;
+use crate::helpers::distributed_lock::LockProvider;
 use crate::http::Method;
+use crate::http::assets::EmbeddedAssets;
+use crate::http::extractors::{BodyCharset, BodyNormalization, ExtractorTracing};
 use crate::http::kernel::Route;
+use crate::http::middlewares::{Alias, OriginResolver, OutboxPublisher, ResponseSizeGuard};
+use crate::http::plugin::{FoxtivePlugin, Plugins};
+use crate::http::response::debug::ErrorDebug;
+use crate::http::response::envelope::{ResponseFormatter, ResponseFormatters};
+use crate::http::response::serializer::SerializerConfig;
+use crate::http::response::status::StatusOverrides;
+use crate::http::server::ListenerSource;
+use crate::http::server::workers::{WorkerPanic, WorkerPanicHook};
+use crate::http::well_known::WellKnownConfig;
+use crate::setup::runtime_settings::Settings;
 use foxtive::setup::FoxtiveSetup;
 use foxtive::setup::trace::Tracing;
 use ntex::http::KeepAlive;
 use ntex::time::Seconds;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[cfg(feature = "static")]
 pub struct StaticFileConfig {
@@ -11,6 +28,39 @@ pub struct StaticFileConfig {
     pub dir: String,
 }
 
+/// Extra HTTP server started alongside the main one (admin, metrics...), sharing
+/// its state but serving its own routes
+#[derive(Clone)]
+pub struct AdditionalServer {
+    pub(crate) name: String,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) workers: usize,
+    pub(crate) routes: Vec<Route>,
+}
+
+impl AdditionalServer {
+    pub fn new(name: &str, host: &str, port: u16) -> Self {
+        Self {
+            name: name.to_string(),
+            host: host.to_string(),
+            port,
+            workers: 1,
+            routes: vec![],
+        }
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    pub fn routes(mut self, routes: Vec<Route>) -> Self {
+        self.routes = routes;
+        self
+    }
+}
+
 pub struct ServerConfig<TB>
 where
     TB: FnOnce() -> Vec<Route> + Send + Copy + 'static,
@@ -39,6 +89,9 @@ where
     #[cfg(feature = "static")]
     pub(crate) static_config: StaticFileConfig,
 
+    /// assets compiled into the binary, each under its own path
+    pub(crate) embedded_assets: Vec<EmbeddedAssets>,
+
     /// whether the app bootstrap has started
     pub(crate) has_started_bootstrap: bool,
 
@@ -50,6 +103,123 @@ where
     /// list of allowed CORS origins
     pub(crate) allowed_methods: Vec<Method>,
 
+    /// whether CORS applies at all, off for purely server-to-server APIs
+    pub(crate) cors: bool,
+
+    /// runtime lookup of origins missing from `allowed_origins`
+    pub(crate) origin_resolver: Option<Arc<dyn OriginResolver>>,
+
+    /// how long origin resolver decisions are reused
+    pub(crate) origin_cache_ttl: Duration,
+
+    /// initial values of the runtime settings store
+    pub(crate) runtime_settings: Settings,
+
+    /// robots.txt, security.txt & favicon endpoints
+    pub(crate) well_known: Option<WellKnownConfig>,
+
+    /// whether successful `()`/`None` responses should be sent as 204 No Content
+    pub(crate) no_content_for_empty: bool,
+
+    /// pretty printing, key casing and null stripping of JSON responses
+    pub(crate) serializer: SerializerConfig,
+
+    /// error details included in error responses outside production
+    pub(crate) error_debug: ErrorDebug,
+
+    /// statuses answered instead of the defaults, per error kind
+    pub(crate) status_overrides: StatusOverrides,
+
+    /// spans recorded by the body extractors
+    pub(crate) extractor_tracing: ExtractorTracing,
+
+    /// charset handling of `StringBody`
+    pub(crate) body_charset: BodyCharset,
+
+    /// normalization of the JSON bodies before deserialization
+    pub(crate) body_normalization: BodyNormalization,
+
+    /// envelope shapes clients can negotiate through `X-Envelope-Version`
+    pub(crate) response_formatters: ResponseFormatters,
+
+    /// name and thread count of dedicated worker pools
+    pub(crate) worker_pools: Vec<(String, usize)>,
+
+    /// publisher of the events staged through the `Outbox` extractor
+    pub(crate) outbox_publisher: Option<OutboxPublisher>,
+
+    /// seconds advertised in `Retry-After` by 429 and 503 responses, `None` for the default
+    pub(crate) retry_after: Option<u64>,
+
+    /// servers started and stopped together with the main one
+    pub(crate) additional_servers: Vec<AdditionalServer>,
+
+    /// old paths redirected or rewritten to their new location before routing
+    pub(crate) aliases: Vec<Alias>,
+
+    /// whether to bind the address or use inherited sockets
+    pub(crate) listener: ListenerSource,
+
+    /// whether connections start with a PROXY protocol preamble
+    pub(crate) proxy_protocol: bool,
+
+    /// peers whose `X-Forwarded-For` is trusted to name the client
+    pub(crate) trusted_proxies: Vec<IpAddr>,
+
+    /// pid file used to hand the port over from a previous instance
+    pub(crate) pid_file: Option<String>,
+
+    /// OS-level name prefix of the worker threads
+    pub(crate) worker_thread_name: Option<String>,
+
+    /// called with the panics of worker threads
+    pub(crate) worker_panic_hook: Option<WorkerPanicHook>,
+
+    /// window of the request stats, `None` when not collected
+    pub(crate) request_stats: Option<Duration>,
+
+    /// record the timing of every middleware layer, see `MiddlewareChains`
+    pub(crate) middleware_timings: bool,
+
+    /// cap on request bodies enforced before extractors, `None` when not enforced
+    pub(crate) strict_content_length: Option<usize>,
+
+    /// limit on the size of list responses, `None` when not enforced
+    pub(crate) response_size_guard: Option<ResponseSizeGuard>,
+
+    /// ring buffer of the last exchanges, `None` when not kept
+    #[cfg(feature = "dev-tools")]
+    pub(crate) request_mirror: Option<crate::helpers::mirror::MirrorConfig>,
+
+    /// whether to log a boot report once listening, and where to write it
+    pub(crate) boot_report: bool,
+    pub(crate) boot_report_file: Option<String>,
+
+    /// modules contributing state, routes, middlewares and lifecycle hooks
+    pub(crate) plugins: Plugins,
+
+    /// backend of the session affinity registry, in memory when `None`
+    pub(crate) affinity_store: Option<Arc<dyn AffinityStore>>,
+
+    /// backend of the distributed locks, in memory when `None`
+    pub(crate) lock_provider: Option<Arc<dyn LockProvider>>,
+
+    /// limits on multipart bodies and text fields, the multipart defaults when `None`
+    #[cfg(feature = "multipart")]
+    pub(crate) multipart_data_limits: Option<foxtive_ntex_multipart::DataLimits>,
+
+    /// handling of repeated multipart text fields
+    #[cfg(feature = "multipart")]
+    pub(crate) multipart_duplicate_policy: foxtive_ntex_multipart::DuplicatePolicy,
+
+    /// buffering and default validator of multipart files, the multipart defaults when `None`
+    #[cfg(feature = "multipart")]
+    pub(crate) multipart_config: Option<foxtive_ntex_multipart::MultipartConfig>,
+
+    /// how often the webhook task looks for due retries
+    #[cfg(feature = "webhooks")]
+    pub(crate) webhook_poll_interval: Duration,
+
     pub(crate) boot_thread: Option<TB>,
 }
 
@@ -72,10 +242,54 @@ where
             foxtive_setup: setup,
             #[cfg(feature = "static")]
             static_config: StaticFileConfig::default(),
+            embedded_assets: vec![],
             has_started_bootstrap: false,
             routes: vec![],
             allowed_origins: vec![],
             allowed_methods: vec![],
+            cors: true,
+            origin_resolver: None,
+            origin_cache_ttl: Duration::from_secs(300),
+            runtime_settings: Settings::default(),
+            well_known: None,
+            no_content_for_empty: false,
+            serializer: SerializerConfig::new(),
+            error_debug: ErrorDebug::Off,
+            status_overrides: StatusOverrides::new(),
+            extractor_tracing: ExtractorTracing::default(),
+            body_charset: BodyCharset::Detect,
+            body_normalization: BodyNormalization::new(),
+            response_formatters: ResponseFormatters::default(),
+            worker_pools: vec![],
+            outbox_publisher: None,
+            retry_after: None,
+            additional_servers: vec![],
+            aliases: vec![],
+            listener: ListenerSource::Bind,
+            proxy_protocol: false,
+            trusted_proxies: vec![],
+            pid_file: None,
+            worker_thread_name: None,
+            worker_panic_hook: None,
+            request_stats: None,
+            middleware_timings: false,
+            strict_content_length: None,
+            response_size_guard: None,
+            #[cfg(feature = "dev-tools")]
+            request_mirror: None,
+            boot_report: false,
+            boot_report_file: None,
+            plugins: Plugins::default(),
+            affinity_store: None,
+            lock_provider: None,
+            #[cfg(feature = "multipart")]
+            multipart_data_limits: None,
+            #[cfg(feature = "multipart")]
+            multipart_duplicate_policy: Default::default(),
+            #[cfg(feature = "multipart")]
+            multipart_config: None,
+            #[cfg(feature = "webhooks")]
+            webhook_poll_interval: Duration::from_secs(1),
             boot_thread: None,
             tracing: None,
         }
@@ -191,6 +405,313 @@ where
         self
     }
 
+    /// Turn CORS off entirely (`false`) for purely server-to-server APIs, requests then
+    /// reach the app without any CORS processing. Route groups can turn it off with
+    /// `RoutePolicies::cors(false)` instead.
+    pub fn cors(mut self, enabled: bool) -> Self {
+        self.cors = enabled;
+        self
+    }
+
+    /// Consult `resolver` for origins missing from `allowed_origins`, e.g. customer domains
+    /// stored in the database
+    pub fn origin_resolver(mut self, resolver: impl OriginResolver) -> Self {
+        self.origin_resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// How long origin resolver decisions are cached, 5 minutes by default
+    pub fn origin_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.origin_cache_ttl = ttl;
+        self
+    }
+
+    /// Set the initial values of the runtime settings store
+    pub fn runtime_settings(mut self, settings: Settings) -> Self {
+        self.runtime_settings = settings;
+        self
+    }
+
+    /// Register the standard well-known endpoints (`/robots.txt`, `/.well-known/security.txt`, `/favicon.ico`)
+    pub fn well_known(mut self, config: WellKnownConfig) -> Self {
+        self.well_known = Some(config);
+        self
+    }
+
+    /// Respond with `204 No Content` instead of an envelope when handlers send `()`/`None`.
+    ///
+    /// Disabled by default, see `Responder::send_or_no_content` for opting-in per call.
+    pub fn no_content_for_empty(mut self, enabled: bool) -> Self {
+        self.no_content_for_empty = enabled;
+        self
+    }
+
+    /// Serialize JSON responses, error envelopes and NDJSON streams with these options,
+    /// e.g. camelCase keys everywhere instead of `#[serde(rename_all)]` on each DTO.
+    ///
+    /// Pretty printing is ignored when the foxtive setup environment is production.
+    pub fn serializer(mut self, config: SerializerConfig) -> Self {
+        self.serializer = config;
+        self
+    }
+
+    /// Include the error cause chain (and optionally the backtrace) in error responses.
+    ///
+    /// Ignored when the foxtive setup environment is production.
+    pub fn error_debug(mut self, level: ErrorDebug) -> Self {
+        self.error_debug = level;
+        self
+    }
+
+    /// Answer errors with application-wide statuses instead of the defaults, e.g.
+    /// 422 rather than 400 for warnings and validation errors
+    pub fn status_overrides(mut self, overrides: StatusOverrides) -> Self {
+        self.status_overrides = overrides;
+        self
+    }
+
+    /// Tracing of the body extractors, a span per extraction by default
+    pub fn extractor_tracing(mut self, level: ExtractorTracing) -> Self {
+        self.extractor_tracing = level;
+        self
+    }
+
+    /// Charset handling of `StringBody`, see [`BodyCharset`]
+    pub fn body_charset(mut self, charset: BodyCharset) -> Self {
+        self.body_charset = charset;
+        self
+    }
+
+    /// Normalization of the JSON bodies before the extractors deserialize them, see
+    /// [`BodyNormalization`]
+    pub fn body_normalization(mut self, policy: BodyNormalization) -> Self {
+        self.body_normalization = policy;
+        self
+    }
+
+    /// Render the envelope with `formatter` for clients sending `X-Envelope-Version: {version}`
+    pub fn response_formatter(mut self, version: &str, formatter: impl ResponseFormatter) -> Self {
+        self.response_formatters.register(version, formatter);
+        self
+    }
+
+    /// Envelope version used when neither the client nor the route picks one,
+    /// the legacy `v1` envelope by default
+    pub fn envelope_version(mut self, version: &str) -> Self {
+        self.response_formatters.set_default_version(version);
+        self
+    }
+
+    /// Add a named pool of dedicated threads for blocking work.
+    ///
+    /// Use `spawn_on(name, f)` or attach the pool to a controller to run blocking sections on it.
+    pub fn worker_pool(mut self, name: &str, threads: usize) -> Self {
+        self.worker_pools.push((name.to_string(), threads));
+        self
+    }
+
+    /// Use the sockets passed by systemd socket activation (`LISTEN_FDS`) instead of binding,
+    /// so restarts don't drop pending connections
+    pub fn listener(mut self, source: ListenerSource) -> Self {
+        self.listener = source;
+        self
+    }
+
+    /// Expect a PROXY protocol (v1 or v2) header on every connection, as sent by HAProxy or
+    /// an AWS NLB with proxy protocol enabled. The client address it carries becomes the
+    /// peer address, so `ClientInfo` and other IP-based features see the real client.
+    /// Connections without a valid header are closed, only enable it behind such a proxy.
+    pub fn proxy_protocol(mut self, enabled: bool) -> Self {
+        self.proxy_protocol = enabled;
+        self
+    }
+
+    /// Load balancers and reverse proxies allowed to report the client address with
+    /// `X-Forwarded-For`. Rate limits key on the peer address otherwise, the header
+    /// being set by anyone reaching the server directly.
+    pub fn trusted_proxies(mut self, proxies: Vec<IpAddr>) -> Self {
+        self.trusted_proxies = proxies;
+        self
+    }
+
+    /// Once listening, record the pid in `path` and send `SIGTERM` to the instance found
+    /// there. Combined with `ListenerSource::ReusePort` this gives blue/green restarts:
+    /// start the new process, the old one stops accepting and drains.
+    pub fn pid_file(mut self, path: &str) -> Self {
+        self.pid_file = Some(path.to_string());
+        self
+    }
+
+    /// Name the worker threads `{prefix}-{index}` (e.g. `api-0`), as seen by `ps`, `top -H`
+    /// and profilers. Linux only, names are truncated to 15 bytes.
+    pub fn worker_thread_name(mut self, prefix: &str) -> Self {
+        self.worker_thread_name = Some(prefix.to_string());
+        self
+    }
+
+    /// Call `hook` when a worker thread panics, with the request being handled when known.
+    /// The previously installed panic hook still runs afterward.
+    ///
+    /// Use `WorkerPanic::log` to log panics as JSON lines:
+    /// ```
+    /// use foxtive_ntex::http::server::{ServerConfig, WorkerPanic};
+    ///
+    /// # fn config(config: ServerConfig<fn() -> Vec<foxtive_ntex::http::kernel::Route>>) {
+    /// let config = config.worker_panic_hook(WorkerPanic::log);
+    /// # }
+    /// ```
+    pub fn worker_panic_hook(
+        mut self,
+        hook: impl Fn(&WorkerPanic) + Send + Sync + 'static,
+    ) -> Self {
+        self.worker_panic_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Collect rolling-window request counters over `window` (traffic, latency and error
+    /// rate per route, body sizes), rendered by `admin::stats`
+    pub fn request_stats(mut self, window: Duration) -> Self {
+        self.request_stats = Some(window);
+        self
+    }
+
+    /// Record how long each middleware, policy and handler of the route groups takes over
+    /// the recent requests, rendered by `admin::middleware_chains`
+    pub fn middleware_timings(mut self, enabled: bool) -> Self {
+        self.middleware_timings = enabled;
+        self
+    }
+
+    /// Reject request bodies over `max_body` bytes or larger than their `Content-Length`
+    /// before extractors buffer them, answered with 413. Declared sizes over the cap are
+    /// refused without reading the body.
+    pub fn strict_content_length(mut self, max_body: usize) -> Self {
+        self.strict_content_length = Some(max_body);
+        self
+    }
+
+    /// Reject or truncate list responses serialized over a size threshold, see
+    /// [`ResponseSizeGuard`]
+    pub fn response_size_guard(mut self, guard: ResponseSizeGuard) -> Self {
+        self.response_size_guard = Some(guard);
+        self
+    }
+
+    /// Keep the last requests and responses, redacted and with truncated bodies, for
+    /// `admin::mirror` to render (feature `dev-tools`).
+    ///
+    /// Ignored when the foxtive setup environment is production.
+    #[cfg(feature = "dev-tools")]
+    pub fn request_mirror(mut self, config: crate::helpers::mirror::MirrorConfig) -> Self {
+        self.request_mirror = Some(config);
+        self
+    }
+
+    /// Log a `BootReport` (bind addresses, workers, features, routes, middlewares, plugins
+    /// and startup step durations) once the server is listening
+    pub fn boot_report(mut self, enabled: bool) -> Self {
+        self.boot_report = enabled;
+        self
+    }
+
+    /// Also write the boot report as JSON to `path`, e.g. for deploy tooling checking
+    /// for configuration drift
+    pub fn boot_report_file(mut self, path: &str) -> Self {
+        self.boot_report = true;
+        self.boot_report_file = Some(path.to_string());
+        self
+    }
+
+    /// Keep an old path working after moving its controller, see [`Alias`]
+    pub fn alias(mut self, alias: Alias) -> Self {
+        self.aliases.push(alias);
+        self
+    }
+
+    /// Start another server with its own routes, e.g. an admin API bound to localhost.
+    /// When any of the servers stops, the others are stopped gracefully.
+    pub fn additional_server(mut self, server: AdditionalServer) -> Self {
+        self.additional_servers.push(server);
+        self
+    }
+
+    /// Keep session affinity state in `store` instead of memory, for deployments running
+    /// several instances
+    pub fn affinity_store(mut self, store: impl AffinityStore) -> Self {
+        self.affinity_store = Some(Arc::new(store));
+        self
+    }
+
+    /// Back `state.lock()` with `provider` instead of memory, so the instances of the
+    /// application coordinate with each other, e.g. `RedisLockProvider` (feature `redis`)
+    pub fn lock_provider(mut self, provider: impl LockProvider) -> Self {
+        self.lock_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Limits on multipart bodies: text field sizes (1 MiB each, 8 MiB together by default)
+    /// and counts, and optionally the total body size, file size and file count. They are
+    /// enforced while the body is read, and requests declaring a larger body are rejected
+    /// by the `Multipart` extractor before the handler runs
+    #[cfg(feature = "multipart")]
+    pub fn multipart_data_limits(mut self, limits: foxtive_ntex_multipart::DataLimits) -> Self {
+        self.multipart_data_limits = Some(limits);
+        self
+    }
+
+    /// How repeated multipart text fields are handled, all values are kept by default
+    #[cfg(feature = "multipart")]
+    pub fn multipart_duplicate_policy(
+        mut self,
+        policy: foxtive_ntex_multipart::DuplicatePolicy,
+    ) -> Self {
+        self.multipart_duplicate_policy = policy;
+        self
+    }
+
+    /// How multipart files are buffered, e.g. spilling the ones above a size to temp
+    /// files instead of holding them in memory, and the validator checking the files of
+    /// every request; all are kept in memory and unchecked by default
+    #[cfg(feature = "multipart")]
+    pub fn multipart_config(mut self, config: foxtive_ntex_multipart::MultipartConfig) -> Self {
+        self.multipart_config = Some(config);
+        self
+    }
+
+    /// How often due webhook retries are looked for, 1 second by default.
+    /// New dispatches are sent right away regardless.
+    #[cfg(feature = "webhooks")]
+    pub fn webhook_poll_interval(mut self, interval: Duration) -> Self {
+        self.webhook_poll_interval = interval;
+        self
+    }
+
+    /// Attach a plugin, see [`FoxtivePlugin`]
+    pub fn register_plugin(mut self, plugin: impl FoxtivePlugin) -> Self {
+        self.plugins.push(Arc::new(plugin));
+        self
+    }
+
+    /// Publisher receiving the events staged by successful requests,
+    /// see the `publish_outbox` middleware
+    pub fn outbox_publisher(mut self, publisher: OutboxPublisher) -> Self {
+        self.outbox_publisher = Some(publisher);
+        self
+    }
+
+    /// Seconds advertised in `Retry-After` by the `TooManyRequests` and `ServiceUnavailable`
+    /// responses of this server, 60 by default
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
+
+    /// Serve assets compiled into the binary, see [`EmbeddedAssets`]
+    pub fn embedded_assets(mut self, assets: EmbeddedAssets) -> Self {
+        self.embedded_assets.push(assets);
+        self
+    }
+
     #[cfg(feature = "static")]
     pub fn static_config(mut self, static_config: StaticFileConfig) -> Self {
         self.static_config = static_config;
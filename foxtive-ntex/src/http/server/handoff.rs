@@ -0,0 +1,173 @@
+use foxtive::prelude::AppResult;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::fs;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Bind with `SO_REUSEPORT`, letting a new instance accept on the same port while the
+/// previous one drains its connections
+pub fn bind_reuse_port(addr: impl ToSocketAddrs, backlog: i32) -> AppResult<Vec<TcpListener>> {
+    let mut listeners = vec![];
+    for addr in addr.to_socket_addrs()? {
+        listeners.push(listener(addr, backlog, true)?);
+    }
+
+    Ok(listeners)
+}
+
+/// Bind `addr` with the given backlog, without `SO_REUSEPORT`
+pub(crate) fn bind_listeners(
+    addr: impl ToSocketAddrs,
+    backlog: i32,
+) -> AppResult<Vec<TcpListener>> {
+    let mut listeners = vec![];
+    for addr in addr.to_socket_addrs()? {
+        listeners.push(listener(addr, backlog, false)?);
+    }
+
+    Ok(listeners)
+}
+
+fn listener(addr: SocketAddr, backlog: i32, reuse_port: bool) -> AppResult<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(target_os = "solaris"), not(target_os = "illumos")))]
+    if reuse_port {
+        socket.set_reuse_port(true)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(backlog)?;
+
+    Ok(socket.into())
+}
+
+/// Pid file of the running instance, removed on drop unless another instance took over
+pub struct PidFile {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl PidFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if read_pid(&self.path) == Some(self.pid) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Record this process in the pid file and ask the previous instance (if any) to stop
+/// gracefully with `SIGTERM`. Call it once the new listeners are bound.
+///
+/// The previous pid is only signalled when it runs an executable of the same name as
+/// this process, so a stale pid file never stops an unrelated process that reused the
+/// pid. Where that can't be checked (no `/proc`), nothing is signalled.
+pub fn take_over(path: impl AsRef<Path>) -> AppResult<PidFile> {
+    let path = path.as_ref().to_path_buf();
+    let pid = std::process::id();
+    let previous = read_pid(&path).filter(|previous| *previous != pid);
+
+    fs::write(&path, pid.to_string())?;
+
+    if let Some(previous) = previous {
+        match is_previous_instance(previous) {
+            true => {
+                info!("[handoff] asking previous instance {previous} to drain");
+                signal_terminate(previous);
+            }
+            false => warn!("[handoff] pid {previous} is not a previous instance, not signalled"),
+        }
+    }
+
+    Ok(PidFile { path, pid })
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether `pid` runs the same executable as this process. Names are compared rather
+/// than paths, deployments commonly start the new version from another directory or
+/// replace the binary the previous instance was started from
+fn is_previous_instance(pid: u32) -> bool {
+    fn executable_name(link: &Path) -> Option<String> {
+        let path = fs::read_link(link).ok()?;
+        let name = path.file_name()?.to_str()?;
+        // the binary the process started from was replaced or removed
+        Some(name.trim_end_matches(" (deleted)").to_string())
+    }
+
+    let previous = executable_name(&Path::new("/proc").join(pid.to_string()).join("exe"));
+    let current = executable_name(Path::new("/proc/self/exe"));
+
+    matches!((previous, current), (Some(previous), Some(current)) if previous == current)
+}
+
+#[cfg(unix)]
+fn signal_terminate(pid: u32) {
+    // SAFETY: kill(2) has no memory safety requirements
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) } != 0 {
+        warn!(
+            "[handoff] failed to signal {pid}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_terminate(pid: u32) {
+    warn!("[handoff] signalling {pid} is only supported on unix");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_listeners_share_port() {
+        let first = bind_reuse_port("127.0.0.1:0", 16).unwrap().remove(0);
+        let addr = first.local_addr().unwrap();
+
+        let second = bind_reuse_port(addr, 16).unwrap().remove(0);
+        assert_eq!(second.local_addr().unwrap(), addr);
+    }
+
+    #[test]
+    fn test_pid_file_is_removed_on_drop() {
+        let path = std::env::temp_dir().join(format!("foxtive-handoff-{}.pid", std::process::id()));
+
+        let pid_file = take_over(&path).unwrap();
+        assert_eq!(read_pid(pid_file.path()), Some(std::process::id()));
+
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_unrelated_pid_is_not_signalled() {
+        let path = std::env::temp_dir().join(format!("foxtive-stale-{}.pid", std::process::id()));
+
+        // a `sleep` child stands for an unrelated process that reused a stale pid
+        let mut unrelated = std::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+        fs::write(&path, unrelated.id().to_string()).unwrap();
+        assert!(!is_previous_instance(unrelated.id()));
+        assert!(is_previous_instance(std::process::id()));
+
+        let pid_file = take_over(&path).unwrap();
+        assert_eq!(read_pid(pid_file.path()), Some(std::process::id()));
+        assert!(unrelated.try_wait().unwrap().is_none());
+
+        unrelated.kill().unwrap();
+        unrelated.wait().unwrap();
+    }
+}
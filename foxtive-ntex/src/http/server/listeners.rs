@@ -0,0 +1,92 @@
+use foxtive::prelude::AppResult;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+
+/// First descriptor passed by systemd, see `sd_listen_fds(3)`
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: i32 = 3;
+
+/// Inherited descriptors can only be owned once
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Where the main server gets its listening sockets from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListenerSource {
+    /// bind the configured host and port
+    #[default]
+    Bind,
+    /// use the sockets passed through `LISTEN_FDS` (systemd socket activation),
+    /// binding the configured address when none were passed
+    Inherited,
+    /// bind with `SO_REUSEPORT`, so a new instance can start while the old one drains
+    ReusePort,
+}
+
+/// Take the TCP listeners inherited from the parent process (`LISTEN_PID`/`LISTEN_FDS`),
+/// empty when none were passed to this process or they were already taken
+pub fn inherited_listeners() -> AppResult<Vec<TcpListener>> {
+    let count = listen_fds_count(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+
+    if count == 0 || TAKEN.swap(true, Ordering::SeqCst) {
+        return Ok(vec![]);
+    }
+
+    from_fds(count)
+}
+
+#[cfg(unix)]
+fn from_fds(count: usize) -> AppResult<Vec<TcpListener>> {
+    use std::os::unix::io::FromRawFd;
+
+    let mut listeners = Vec::with_capacity(count);
+    for fd in SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count as i32 {
+        // SAFETY: systemd passes `count` open sockets starting at fd 3, and `TAKEN`
+        // guarantees they are wrapped only once
+        let listener = unsafe { TcpListener::from_raw_fd(fd) };
+        listener.set_nonblocking(true)?;
+        info!("using inherited listener {:?}", listener.local_addr()?);
+        listeners.push(listener);
+    }
+
+    Ok(listeners)
+}
+
+#[cfg(not(unix))]
+fn from_fds(_count: usize) -> AppResult<Vec<TcpListener>> {
+    warn!("inherited listeners are only supported on unix");
+    Ok(vec![])
+}
+
+/// Number of descriptors meant for this process
+fn listen_fds_count(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> usize {
+    match listen_pid.and_then(|v| v.trim().parse::<u32>().ok()) {
+        Some(target) if target == pid => {}
+        Some(_) => {
+            warn!("LISTEN_FDS is meant for another process, ignoring");
+            return 0;
+        }
+        None => return 0,
+    }
+
+    listen_fds
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listen_fds_count() {
+        assert_eq!(listen_fds_count(Some("42"), Some("2"), 42), 2);
+        assert_eq!(listen_fds_count(Some("41"), Some("2"), 42), 0);
+        assert_eq!(listen_fds_count(None, Some("2"), 42), 0);
+        assert_eq!(listen_fds_count(Some("42"), Some("nope"), 42), 0);
+    }
+}
@@ -1,19 +1,49 @@
+mod boot_report;
 mod config;
+mod handoff;
+mod listeners;
+mod proxy_protocol;
+mod settings;
+mod workers;
 
-pub use config::ServerConfig;
+pub use boot_report::{BootReport, BootTask};
 #[cfg(feature = "static")]
 pub use config::StaticFileConfig;
+pub use config::{AdditionalServer, ServerConfig};
+pub use handoff::{PidFile, bind_reuse_port, take_over};
+pub use listeners::{ListenerSource, inherited_listeners};
+pub(crate) use settings::{ServerSettings, SettingsScope};
+pub use workers::{PanicRequest, WorkerPanic, WorkerPanicHook};
 
 use crate::FoxtiveNtexState;
-use crate::http::kernel::{Route, ntex_default_service, register_routes, setup_cors, setup_logger};
+use crate::http::kernel::{
+    Route, cors_free_prefixes, cors_methods, not_found_service, ntex_default_service,
+    register_routes, setup_cors, setup_logger, setup_logger_with_well_known,
+};
+use crate::http::middlewares::{
+    AliasTable, CorsSwitch, LogSampling, Middleware, OriginCors, RateLimiters, RequestCancellation,
+    ResponseSizeLimit, StatsRecorder, StrictContentLength,
+};
+use crate::http::response::debug::ErrorDebug;
+use crate::http::response::envelope::EnvelopeNegotiation;
+use crate::http::well_known::register_well_known;
 use crate::setup::{FoxtiveNtexSetup, make_ntex_state};
+pub(crate) use boot_report::enabled_features;
+use boot_report::{BootTimer, FRAMEWORK_MIDDLEWARES, unix_now};
 use foxtive::Error;
 use foxtive::prelude::AppResult;
 use foxtive::setup::load_environment_variables;
 use foxtive::setup::trace::Tracing;
+use futures_util::future::select_all;
+use handoff::bind_listeners;
+use ntex::server::Server;
 use ntex::web;
+use proxy_protocol::proxied_server;
 use std::future::Future;
-use tracing::{debug, error};
+use std::net::{IpAddr, TcpListener};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use workers::{PanicContext, enter_worker, install_panic_hook};
 
 pub fn init_bootstrap(service: &str, config: Tracing) -> AppResult<()> {
     foxtive::setup::trace::init_tracing(config)?;
@@ -30,19 +60,76 @@ where
     Fut: Future<Output = AppResult<()>> + Send + 'static,
     TB: FnOnce() -> Vec<Route> + Send + Copy + 'static,
 {
+    let mut timer = BootTimer::start();
     if !config.has_started_bootstrap {
         let t_config = config.tracing.unwrap_or_default();
         debug!("Starting bootstrap");
         init_bootstrap(&config.app, t_config).expect("failed to init bootstrap: ");
+        timer.lap("bootstrap");
     }
 
+    let multipart = MultipartState {
+        #[cfg(feature = "multipart")]
+        data_limits: config.multipart_data_limits,
+        #[cfg(feature = "multipart")]
+        duplicate_policy: config.multipart_duplicate_policy,
+        #[cfg(feature = "multipart")]
+        config: match config.extractor_tracing {
+            crate::http::extractors::ExtractorTracing::Off => Some(
+                config
+                    .multipart_config
+                    .unwrap_or_else(foxtive_ntex_multipart::MultipartConfig::current_default)
+                    .tracing(false),
+            ),
+            _ => config.multipart_config,
+        },
+    };
+    #[cfg(feature = "metrics")]
+    foxtive_ntex_multipart::Multipart::set_observer(
+        crate::helpers::upload_metrics::UploadMetrics::global().clone(),
+    );
+    let pretty = config.serializer.pretty && config.foxtive_setup.env.allows_debug();
+    let settings = Arc::new(ServerSettings {
+        no_content_for_empty: config.no_content_for_empty,
+        extractor_tracing: config.extractor_tracing,
+        body_charset: config.body_charset,
+        body_normalization: config.body_normalization,
+        error_debug: match config.foxtive_setup.env.allows_debug() {
+            true => config.error_debug,
+            false => ErrorDebug::Off,
+        },
+        status_overrides: config.status_overrides,
+        serializer: config.serializer.clone().pretty(pretty),
+        middleware_timings: config.middleware_timings,
+        retry_after: config.retry_after,
+        outbox_publisher: config.outbox_publisher,
+    });
+    #[cfg(feature = "dev-tools")]
+    let request_mirror = crate::http::middlewares::mirror_config(
+        config.request_mirror,
+        config.foxtive_setup.env.allows_debug(),
+    );
+
     debug!("Creating Foxtive-Ntex state");
     let app_state = make_ntex_state(FoxtiveNtexSetup {
         allowed_origins: config.allowed_origins,
         allowed_methods: config.allowed_methods,
+        runtime_settings: config.runtime_settings,
+        worker_pools: config.worker_pools,
         foxtive_setup: config.foxtive_setup,
     })
     .await?;
+    timer.lap("state");
+
+    if let Some(store) = config.affinity_store {
+        app_state.affinity.replace_store(store);
+    }
+    if let Some(provider) = config.lock_provider {
+        app_state.distributed_locks.replace_provider(provider);
+    }
+
+    let plugins = config.plugins;
+    plugins.configure_state(&app_state)?;
 
     debug!("Executing app bootstrap callback");
     match callback(app_state.clone()).await {
@@ -52,27 +139,170 @@ where
             panic!("boostrap failed");
         }
     }
+    timer.lap("app_callback");
+
+    plugins.start(&app_state).await?;
+    app_state.components.start_probes();
+
+    #[cfg(feature = "webhooks")]
+    app_state
+        .webhooks
+        .start(&app_state.components, config.webhook_poll_interval);
+    timer.lap("plugins_start");
 
     let boot = config.boot_thread;
     let alt_routes = config.routes;
+    let well_known = config.well_known;
+    let embedded_assets = config.embedded_assets;
+    let plugin_routes = plugins.routes();
+    let report = config.boot_report.then(|| {
+        let mut routes = match boot {
+            None => alt_routes.clone(),
+            Some(boot) => boot(),
+        };
+        routes.extend(plugin_routes.clone());
+        let mut middlewares = FRAMEWORK_MIDDLEWARES.map(String::from).to_vec();
+        #[cfg(feature = "dev-tools")]
+        if request_mirror.is_some() {
+            middlewares.push("request-mirror".to_string());
+        }
+        let route_middlewares: usize = routes.iter().map(|route| route.middlewares.len()).sum();
+        let plugin_middlewares = plugins.middlewares().len();
+        if plugin_middlewares > 0 {
+            middlewares.push(format!("plugins ({plugin_middlewares})"));
+        }
+        if route_middlewares > 0 {
+            middlewares.push(format!("route-level ({route_middlewares})"));
+        }
 
-    web::HttpServer::new(move || {
-        let routes = match boot {
+        BootReport {
+            app: config.app.clone(),
+            framework_version: env!("CARGO_PKG_VERSION"),
+            pid: std::process::id(),
+            booted_at: 0,
+            addresses: vec![],
+            workers: config.workers,
+            features: enabled_features(),
+            routes: routes.iter().map(|route| route.controllers.len()).sum(),
+            middlewares,
+            plugins: plugins.names(),
+            additional_servers: config
+                .additional_servers
+                .iter()
+                .map(|server| format!("{} {}:{}", server.name, server.host, server.port))
+                .collect(),
+            tasks: vec![],
+            total_ms: 0,
+        }
+    });
+    let plugin_middlewares = Middleware::chain(plugins.middlewares());
+    let envelopes = EnvelopeNegotiation::new(config.response_formatters);
+    let panic_context = PanicContext::new(config.worker_panic_hook.is_some());
+    if let Some(hook) = config.worker_panic_hook {
+        install_panic_hook(hook);
+    }
+    let worker_name = config.worker_thread_name;
+    let aliases = AliasTable::new(config.aliases);
+    app_state.stats.configure(config.request_stats);
+    let stats = StatsRecorder::new(app_state.stats.clone());
+    let strict_length = StrictContentLength::new(config.strict_content_length);
+    let size_limit = ResponseSizeLimit::new(config.response_size_guard);
+    #[cfg(feature = "dev-tools")]
+    app_state.mirror.configure(request_mirror);
+    #[cfg(feature = "dev-tools")]
+    let mirroring = {
+        let mirror = app_state.mirror.clone();
+        move || crate::http::middlewares::RequestMirroring::new(mirror.clone())
+    };
+    #[cfg(not(feature = "dev-tools"))]
+    let mirroring = || ntex::service::Identity;
+    #[cfg(feature = "remember-me")]
+    let remembered_cookies = || crate::helpers::remember_me::RememberMeCookies;
+    #[cfg(not(feature = "remember-me"))]
+    let remembered_cookies = || ntex::service::Identity;
+    let origin_cors = OriginCors::new(
+        config.origin_resolver,
+        config.origin_cache_ttl,
+        app_state.allowed_origins.clone(),
+        &cors_methods(app_state.allowed_methods.clone()),
+    );
+
+    let cors = config.cors;
+    if !cors {
+        info!("CORS is disabled for this server");
+    }
+
+    let framework = FrameworkMiddlewares {
+        size_limit: size_limit.clone(),
+        strict_length: strict_length.clone(),
+        envelopes: envelopes.clone(),
+        stats: stats.clone(),
+        panic_context: panic_context.clone(),
+        cors,
+        origin_cors: origin_cors.clone(),
+    };
+    let shared_state = app_state.clone();
+    let trusted_proxies = config.trusted_proxies;
+    let rate_limiters = RateLimiters::new(trusted_proxies.clone());
+    let shared_multipart = multipart.clone();
+    let shared_settings = settings.clone();
+    let factory = move || {
+        enter_worker(worker_name.as_deref());
+
+        let mut routes = match boot {
             None => alt_routes.clone(),
             Some(boot) => boot(),
         };
+        routes.extend(plugin_routes.clone());
+        let cors_bypass = Arc::new(cors_free_prefixes(&routes));
 
+        let well_known = well_known.clone();
+        let logger = match &well_known {
+            Some(well_known) => setup_logger_with_well_known(well_known),
+            None => setup_logger(),
+        };
         let app = web::App::new()
             .state(app_state.clone())
+            .state(rate_limiters.clone())
+            .state(settings.clone())
+            .configure(|cfg| multipart.register(cfg))
+            .configure(|cfg| {
+                if let Some(well_known) = well_known {
+                    register_well_known(cfg, well_known);
+                }
+            })
             .configure(|cfg| register_routes(cfg, routes))
-            .wrap(setup_logger())
-            .wrap(
-                setup_cors(
-                    app_state.allowed_origins.clone(),
-                    app_state.allowed_methods.clone(),
-                )
-                .finish(),
-            )
+            .configure(|cfg| {
+                for assets in embedded_assets.clone() {
+                    assets.register(cfg);
+                }
+            })
+            .wrap(remembered_cookies())
+            .wrap(size_limit.clone())
+            .wrap(strict_length.clone())
+            .wrap(envelopes.clone())
+            .wrap(RequestCancellation)
+            .wrap(plugin_middlewares.clone())
+            .wrap(mirroring())
+            .wrap(stats.clone())
+            .wrap(aliases.clone())
+            .wrap(LogSampling::new(logger))
+            .wrap(CorsSwitch::new(
+                cors.then(|| {
+                    setup_cors(
+                        app_state.allowed_origins.clone(),
+                        app_state.allowed_methods.clone(),
+                    )
+                    .finish()
+                }),
+                cors_bypass.clone(),
+            ))
+            .wrap(CorsSwitch::new(
+                cors.then(|| origin_cors.clone()),
+                cors_bypass,
+            ))
+            .wrap(panic_context.clone())
+            .wrap(SettingsScope)
             .default_service(ntex_default_service());
 
         if cfg!(feature = "static") {
@@ -86,14 +316,236 @@ where
         }
 
         app
+    };
+
+    let configured_address = format!("{}:{}", config.host, config.port);
+    // `None` binds the configured address
+    let (listeners, addresses) = match config.listener {
+        ListenerSource::Bind => (None, vec![configured_address]),
+        ListenerSource::Inherited => {
+            let listeners = inherited_listeners()?;
+            if listeners.is_empty() {
+                warn!("no inherited listener, binding {configured_address}");
+                (None, vec![configured_address])
+            } else {
+                let addresses = local_addresses(&listeners);
+                (Some(listeners), addresses)
+            }
+        }
+        ListenerSource::ReusePort => {
+            let listeners = bind_reuse_port((config.host.as_str(), config.port), config.backlog)?;
+            let addresses = local_addresses(&listeners);
+            (Some(listeners), addresses)
+        }
+    };
+
+    let main = match config.proxy_protocol {
+        true => {
+            info!("expecting a PROXY protocol header on every connection");
+            let listeners = match listeners {
+                Some(listeners) => listeners,
+                None => bind_listeners((config.host.as_str(), config.port), config.backlog)?,
+            };
+            let builder = ntex::server::build()
+                .backlog(config.backlog)
+                .workers(config.workers)
+                .maxconn(config.max_connections);
+            // what `HttpServer::maxconnrate` sets for the plain server
+            ntex::tls::max_concurrent_ssl_accept(config.max_connections_rate);
+            proxied_server(builder, listeners, factory, config.keep_alive)?.run()
+        }
+        false => {
+            let server = web::HttpServer::new(factory)
+                .backlog(config.backlog)
+                .workers(config.workers)
+                .maxconn(config.max_connections)
+                .maxconnrate(config.max_connections_rate)
+                .keep_alive(config.keep_alive);
+
+            match listeners {
+                None => server.bind((config.host.as_str(), config.port))?,
+                Some(listeners) => listeners
+                    .into_iter()
+                    .try_fold(server, |server, listener| server.listen(listener))?,
+            }
+            .run()
+        }
+    };
+
+    // listeners are bound, the previous instance can start draining
+    let _pid_file = match config.pid_file {
+        Some(path) => Some(take_over(path)?),
+        None => None,
+    };
+    timer.lap("bind");
+
+    if let Some(mut report) = report {
+        (report.tasks, report.total_ms) = timer.finish();
+        report.addresses = addresses;
+        report.booted_at = unix_now();
+        report.emit(config.boot_report_file.as_deref())?;
+    }
+
+    let result = match config.additional_servers.is_empty() {
+        true => main.await.map_err(Error::from),
+        false => {
+            let mut servers = vec![("main".to_string(), main)];
+            for server in config.additional_servers {
+                info!(
+                    "starting '{}' server on {}:{}",
+                    server.name, server.host, server.port
+                );
+                servers.push((
+                    server.name.clone(),
+                    start_additional_server(
+                        server,
+                        &shared_state,
+                        &trusted_proxies,
+                        &shared_multipart,
+                        &shared_settings,
+                        &framework,
+                    )?,
+                ));
+            }
+
+            run_servers(servers).await
+        }
+    };
+
+    for stopped in shared_state.components.shutdown().await {
+        info!(
+            "component '{}' shut down in {:?}: {:?}",
+            stopped.name, stopped.took, stopped.outcome
+        );
+    }
+
+    plugins.shutdown(&shared_state).await;
+    result
+}
+
+/// Multipart limits and config of the `ServerConfig`, registered as app state so each server
+/// reads requests with its own instead of the process defaults
+#[derive(Clone, Default)]
+struct MultipartState {
+    #[cfg(feature = "multipart")]
+    data_limits: Option<foxtive_ntex_multipart::DataLimits>,
+    #[cfg(feature = "multipart")]
+    duplicate_policy: foxtive_ntex_multipart::DuplicatePolicy,
+    #[cfg(feature = "multipart")]
+    config: Option<foxtive_ntex_multipart::MultipartConfig>,
+}
+
+impl MultipartState {
+    #[cfg_attr(not(feature = "multipart"), allow(unused_variables))]
+    fn register(&self, cfg: &mut web::ServiceConfig) {
+        #[cfg(feature = "multipart")]
+        {
+            if let Some(limits) = &self.data_limits {
+                cfg.state(limits.clone());
+            }
+            cfg.state(self.duplicate_policy);
+            if let Some(config) = &self.config {
+                cfg.state(config.clone());
+            }
+        }
+    }
+}
+
+fn local_addresses(listeners: &[TcpListener]) -> Vec<String> {
+    listeners
+        .iter()
+        .filter_map(|listener| listener.local_addr().ok())
+        .map(|address| address.to_string())
+        .collect()
+}
+
+/// Middlewares of the main server every additional server is wrapped with too, the
+/// aliases, plugin middlewares and request mirroring only apply to the main routes
+#[derive(Clone)]
+struct FrameworkMiddlewares {
+    size_limit: ResponseSizeLimit,
+    strict_length: StrictContentLength,
+    envelopes: EnvelopeNegotiation,
+    stats: StatsRecorder,
+    panic_context: PanicContext,
+    cors: bool,
+    origin_cors: OriginCors,
+}
+
+fn start_additional_server(
+    server: AdditionalServer,
+    app_state: &FoxtiveNtexState,
+    trusted_proxies: &[IpAddr],
+    multipart: &MultipartState,
+    settings: &Arc<ServerSettings>,
+    framework: &FrameworkMiddlewares,
+) -> AppResult<Server> {
+    let app_state = app_state.clone();
+    let multipart = multipart.clone();
+    let settings = settings.clone();
+    let framework = framework.clone();
+    let rate_limiters = RateLimiters::new(trusted_proxies.to_vec());
+    let routes = server.routes;
+    #[cfg(feature = "remember-me")]
+    let remembered_cookies = || crate::helpers::remember_me::RememberMeCookies;
+    #[cfg(not(feature = "remember-me"))]
+    let remembered_cookies = || ntex::service::Identity;
+
+    let handle = web::HttpServer::new(move || {
+        let routes = routes.clone();
+        let cors_bypass = Arc::new(cors_free_prefixes(&routes));
+        let cors = framework.cors;
+
+        web::App::new()
+            .state(app_state.clone())
+            .state(rate_limiters.clone())
+            .state(settings.clone())
+            .configure(|cfg| multipart.register(cfg))
+            .configure(|cfg| register_routes(cfg, routes))
+            .wrap(remembered_cookies())
+            .wrap(framework.size_limit.clone())
+            .wrap(framework.strict_length.clone())
+            .wrap(framework.envelopes.clone())
+            .wrap(RequestCancellation)
+            .wrap(framework.stats.clone())
+            .wrap(LogSampling::new(setup_logger()))
+            .wrap(CorsSwitch::new(
+                cors.then(|| {
+                    setup_cors(
+                        app_state.allowed_origins.clone(),
+                        app_state.allowed_methods.clone(),
+                    )
+                    .finish()
+                }),
+                cors_bypass.clone(),
+            ))
+            .wrap(CorsSwitch::new(
+                cors.then(|| framework.origin_cors.clone()),
+                cors_bypass,
+            ))
+            .wrap(framework.panic_context.clone())
+            .wrap(SettingsScope)
+            // the dynamic routes belong to the main server
+            .default_service(not_found_service())
     })
-    .backlog(config.backlog)
-    .workers(config.workers)
-    .maxconn(config.max_connections)
-    .maxconnrate(config.max_connections_rate)
-    .keep_alive(config.keep_alive)
-    .bind((config.host, config.port))?
-    .run()
-    .await
-    .map_err(Error::from)
+    .workers(server.workers)
+    .bind((server.host, server.port))?
+    .run();
+
+    Ok(handle)
+}
+
+/// Wait for the first server to stop, then gracefully stop the others
+async fn run_servers(servers: Vec<(String, Server)>) -> AppResult<()> {
+    let handles: Vec<Server> = servers.iter().map(|(_, server)| server.clone()).collect();
+    let (result, index, _) = select_all(servers.into_iter().map(|(_, server)| server)).await;
+
+    debug!("server #{index} stopped, stopping the others");
+    for (i, handle) in handles.iter().enumerate() {
+        if i != index {
+            handle.stop(true).await;
+        }
+    }
+
+    result.map_err(Error::from)
 }
@@ -0,0 +1,324 @@
+use ntex::http::body::MessageBody;
+use ntex::http::error::{DispatchError, ResponseError};
+use ntex::http::{HttpService, KeepAlive, Request, Response};
+use ntex::io::types::PeerAddr;
+use ntex::io::{FilterLayer, Io, ReadBuf, WriteBuf};
+use ntex::server::ServerBuilder;
+use ntex::service::{IntoServiceFactory, ServiceFactory, chain_factory, fn_service, map_config};
+use ntex::util::Buf;
+use ntex::web::dev::AppConfig;
+use std::any::{Any, TypeId};
+use std::cell::Cell;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+use std::{fmt, io};
+use tracing::debug;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+/// Longest v1 preamble, `\r\n` included
+const V1_MAX_LEN: usize = 107;
+
+/// Parsed PROXY protocol preamble
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Preamble {
+    len: usize,
+    /// `None` for health checks of the proxy itself (`LOCAL`, `UNKNOWN`)
+    source: Option<SocketAddr>,
+}
+
+/// Parse the preamble at the start of `buf`, `None` until it is complete
+fn parse(buf: &[u8]) -> io::Result<Option<Preamble>> {
+    if buf.starts_with(V2_SIGNATURE) {
+        return parse_v2(buf);
+    }
+    if buf.starts_with(V1_PREFIX) {
+        return parse_v1(buf);
+    }
+
+    let prefix_of = |preamble: &[u8]| preamble.starts_with(&buf[..buf.len().min(preamble.len())]);
+    match prefix_of(V1_PREFIX) || prefix_of(V2_SIGNATURE) {
+        true => Ok(None),
+        false => Err(invalid(
+            "connection did not start with a PROXY protocol header",
+        )),
+    }
+}
+
+/// `PROXY TCP4 <src> <dst> <src port> <dst port>\r\n`
+fn parse_v1(buf: &[u8]) -> io::Result<Option<Preamble>> {
+    let Some(end) = buf.windows(2).position(|window| window == b"\r\n") else {
+        return match buf.len() < V1_MAX_LEN {
+            true => Ok(None),
+            false => Err(invalid("PROXY v1 header is too long")),
+        };
+    };
+
+    let line = std::str::from_utf8(&buf[..end])
+        .map_err(|_| invalid("PROXY v1 header is not valid ASCII"))?;
+    let parts: Vec<&str> = line.split(' ').collect();
+
+    let source = match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => None,
+        ["PROXY", "TCP4" | "TCP6", source, _, port, _] => {
+            let ip: IpAddr = source
+                .parse()
+                .map_err(|_| invalid("PROXY v1 source address is invalid"))?;
+            let port: u16 = port
+                .parse()
+                .map_err(|_| invalid("PROXY v1 source port is invalid"))?;
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err(invalid("PROXY v1 header is malformed")),
+    };
+
+    Ok(Some(Preamble {
+        len: end + 2,
+        source,
+    }))
+}
+
+/// Binary header: signature, version/command, family, length, addresses
+fn parse_v2(buf: &[u8]) -> io::Result<Option<Preamble>> {
+    if buf.len() < 16 {
+        return Ok(None);
+    }
+
+    let (version, command) = (buf[12] >> 4, buf[12] & 0x0f);
+    if version != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+
+    let len = 16 + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if buf.len() < len {
+        return Ok(None);
+    }
+
+    let addresses = &buf[16..len];
+    let source = match (command, buf[13] >> 4) {
+        // LOCAL: connection opened by the proxy itself
+        (0, _) => None,
+        (1, 1) if addresses.len() >= 12 => {
+            let ip = Ipv4Addr::new(addresses[0], addresses[1], addresses[2], addresses[3]);
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Some(SocketAddr::new(ip.into(), port))
+        }
+        (1, 2) if addresses.len() >= 36 => {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(&addresses[..16]);
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Some(SocketAddr::new(Ipv6Addr::from(octets).into(), port))
+        }
+        // AF_UNSPEC or AF_UNIX, no IP to report
+        (1, _) => None,
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    };
+
+    Ok(Some(Preamble { len, source }))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Io filter stripping the PROXY protocol preamble, the client address it carries is
+/// reported as the peer address of the connection. Connections without a valid preamble
+/// are closed.
+#[derive(Default)]
+pub(crate) struct ProxyProtocol {
+    parsed: Cell<bool>,
+    source: Cell<Option<SocketAddr>>,
+}
+
+impl fmt::Debug for ProxyProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyProtocol")
+            .field("source", &self.source.get())
+            .finish()
+    }
+}
+
+impl FilterLayer for ProxyProtocol {
+    fn process_read_buf(&self, buf: &ReadBuf<'_>) -> io::Result<usize> {
+        if !self.parsed.get() {
+            let preamble = buf.with_src(|src| {
+                let Some(src) = src else {
+                    return Ok(None);
+                };
+
+                let preamble = parse(src)?;
+                if let Some(preamble) = preamble {
+                    src.advance(preamble.len);
+                }
+                Ok::<_, io::Error>(preamble)
+            })?;
+
+            let Some(preamble) = preamble else {
+                return Ok(0);
+            };
+
+            debug!("[proxy-protocol] client address: {:?}", preamble.source);
+            self.source.set(preamble.source);
+            self.parsed.set(true);
+        }
+
+        match buf.take_src() {
+            Some(src) => {
+                let len = src.len();
+                buf.set_dst(Some(src));
+                Ok(len)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn process_write_buf(&self, buf: &WriteBuf<'_>) -> io::Result<()> {
+        if let Some(src) = buf.take_src() {
+            buf.set_dst(Some(src));
+        }
+        Ok(())
+    }
+
+    fn query(&self, id: TypeId) -> Option<Box<dyn Any>> {
+        match self.source.get() {
+            Some(source) if id == TypeId::of::<PeerAddr>() => Some(Box::new(PeerAddr(source))),
+            _ => None,
+        }
+    }
+}
+
+/// Serve the application on `listeners`, expecting a PROXY protocol preamble on every
+/// connection
+pub(crate) fn proxied_server<F, I, S, B>(
+    builder: ServerBuilder,
+    listeners: Vec<TcpListener>,
+    factory: F,
+    keep_alive: KeepAlive,
+) -> io::Result<ServerBuilder>
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S, Request, AppConfig>,
+    S: ServiceFactory<Request, AppConfig> + 'static,
+    S::Error: ResponseError,
+    S::InitError: fmt::Debug,
+    S::Response: Into<Response<B>>,
+    B: MessageBody + 'static,
+{
+    listeners
+        .into_iter()
+        .try_fold(builder, |builder, listener| {
+            let addr = listener.local_addr()?;
+            let factory = factory.clone();
+
+            builder.listen(format!("proxied-{addr}"), listener, move |_| {
+                let config = AppConfig::new(false, addr, addr.to_string());
+                let app = map_config(factory(), move |_| config.clone());
+
+                chain_factory(fn_service(|io: Io| async move {
+                    Ok::<_, DispatchError>(io.add_filter(ProxyProtocol::default()))
+                }))
+                .and_then(HttpService::build().keep_alive(keep_alive).finish(app))
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::codec::BytesCodec;
+    use ntex::testing::IoTest;
+    use ntex::util::Bytes;
+
+    #[test]
+    fn test_parse_v1() {
+        let preamble = parse(b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 443\r\nGET / HTTP/1.1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(preamble.len, 43);
+        assert_eq!(preamble.source, Some("203.0.113.7:51234".parse().unwrap()));
+
+        let preamble = parse(b"PROXY UNKNOWN\r\n").unwrap().unwrap();
+        assert_eq!(preamble.source, None);
+
+        assert_eq!(parse(b"PROXY TCP4 203.0").unwrap(), None);
+        assert!(parse(b"GET / HTTP/1.1\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 nonsense\r\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[198, 51, 100, 9, 10, 0, 0, 1]);
+        header.extend_from_slice(&8080u16.to_be_bytes());
+        header.extend_from_slice(&443u16.to_be_bytes());
+
+        assert_eq!(parse(&header[..20]).unwrap(), None);
+
+        let preamble = parse(&header).unwrap().unwrap();
+        assert_eq!(preamble.len, 28);
+        assert_eq!(preamble.source, Some("198.51.100.9:8080".parse().unwrap()));
+
+        // LOCAL command, sent by the proxy health checks
+        let mut local = V2_SIGNATURE.to_vec();
+        local.extend_from_slice(&[0x20, 0x00, 0, 0]);
+        assert_eq!(parse(&local).unwrap().unwrap().source, None);
+    }
+
+    #[ntex::test]
+    async fn test_filter_reports_client_address() {
+        let (client, server) = IoTest::create();
+        client.remote_buffer_cap(1024);
+        let io = Io::new(server).add_filter(ProxyProtocol::default());
+
+        client.write("PROXY TCP6 2001:db8::7 2001:db8::1 40000 80\r\nhello");
+        let data = io.recv(&BytesCodec).await.unwrap().unwrap();
+        assert_eq!(data, Bytes::from_static(b"hello"));
+        assert_eq!(
+            io.query::<PeerAddr>().get().map(PeerAddr::into_inner),
+            Some("[2001:db8::7]:40000".parse().unwrap())
+        );
+    }
+
+    #[ntex::test]
+    async fn test_proxied_server_sees_client_address() {
+        use crate::helpers::request::RequestHelper;
+        use ntex::web::{self, App, HttpRequest};
+        use std::io::{Read, Write};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = proxied_server(
+            ntex::server::build().workers(1).disable_signals(),
+            vec![listener],
+            || {
+                App::new().route(
+                    "/",
+                    web::get().to(|req: HttpRequest| async move { req.ip().unwrap_or_default() }),
+                )
+            },
+            KeepAlive::Disabled,
+        )
+        .unwrap()
+        .run();
+
+        let response = ntex::rt::spawn_blocking(move || {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(
+                    b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 80\r\n\
+                      GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+                )
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        })
+        .await
+        .unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("203.0.113.7:51234"));
+        server.stop(false).await;
+    }
+}
@@ -0,0 +1,189 @@
+use crate::http::extractors::{BodyCharset, BodyNormalization, ExtractorTracing};
+use crate::http::middlewares::OutboxPublisher;
+use crate::http::response::debug::ErrorDebug;
+use crate::http::response::serializer::SerializerConfig;
+use crate::http::response::status::StatusOverrides;
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, WebRequest, WebResponse};
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<ServerSettings>>> = const { RefCell::new(None) };
+}
+
+/// Response, extractor and outbox settings of the `ServerConfig`, registered as app state
+/// so each server handles requests with its own instead of the process defaults.
+///
+/// The responders, extractors and error rendering don't always see the request, so
+/// [`SettingsScope`] makes the settings of the server handling it current while the
+/// request is processed; outside of one the process defaults apply.
+///
+/// The `MiddlewareChains` registry and the claims cache of `JwtAuthToken::decode_cached`
+/// stay shared by every server of the process.
+#[derive(Clone, Default)]
+pub(crate) struct ServerSettings {
+    pub(crate) no_content_for_empty: bool,
+    pub(crate) extractor_tracing: ExtractorTracing,
+    pub(crate) body_charset: BodyCharset,
+    pub(crate) body_normalization: BodyNormalization,
+    pub(crate) error_debug: ErrorDebug,
+    pub(crate) status_overrides: StatusOverrides,
+    pub(crate) serializer: SerializerConfig,
+    pub(crate) middleware_timings: bool,
+    pub(crate) retry_after: Option<u64>,
+    pub(crate) outbox_publisher: Option<OutboxPublisher>,
+}
+
+impl ServerSettings {
+    /// Read the settings of the server handling the current request, `None` outside of one
+    pub(crate) fn read<R>(read: impl FnOnce(&ServerSettings) -> R) -> Option<R> {
+        CURRENT.with(|current| current.borrow().as_deref().map(read))
+    }
+
+    /// Run `future` with `settings` current
+    pub(crate) fn scope<F: Future>(settings: Arc<ServerSettings>, future: F) -> Scoped<F> {
+        Scoped { settings, future }
+    }
+}
+
+/// Future polled with its settings current, see [`ServerSettings::scope`]
+pub(crate) struct Scoped<F> {
+    settings: Arc<ServerSettings>,
+    future: F,
+}
+
+impl<F: Future + Unpin> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let settings = self.settings.clone();
+        let previous = CURRENT.with(|current| current.replace(Some(settings)));
+        // restored on unwinding too, so a panicking handler doesn't leak its settings
+        let _restore = Restore(previous);
+
+        Pin::new(&mut self.future).poll(cx)
+    }
+}
+
+struct Restore(Option<Arc<ServerSettings>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// Middleware making the [`ServerSettings`] registered as app state current while the
+/// request is processed, wrapped around every app by the server
+#[derive(Clone)]
+pub(crate) struct SettingsScope;
+
+impl<S> ServiceMiddleware<S> for SettingsScope {
+    type Service = SettingsScopeService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        SettingsScopeService { service }
+    }
+}
+
+pub(crate) struct SettingsScopeService<S> {
+    service: S,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for SettingsScopeService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        let settings = req.app_state::<Arc<ServerSettings>>().cloned();
+        match settings {
+            Some(settings) => {
+                let call = std::pin::pin!(ctx.call(&self.service, req));
+                ServerSettings::scope(settings, call).await
+            }
+            None => ctx.call(&self.service, req).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::ResponseCode;
+    use crate::helpers::responder::Responder;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service};
+
+    #[ntex::test]
+    async fn test_servers_keep_their_own_settings() {
+        let app = |no_content_for_empty| {
+            let settings = ServerSettings {
+                no_content_for_empty,
+                ..Default::default()
+            };
+            App::new()
+                .state(Arc::new(settings))
+                .route(
+                    "/empty",
+                    web::get().to(|| async {
+                        assert!(ServerSettings::read(|_| ()).is_some());
+                        Responder::send((), ResponseCode::Ok)
+                    }),
+                )
+                .wrap(SettingsScope)
+        };
+
+        let first = init_service(app(true)).await;
+        let second = init_service(app(false)).await;
+
+        let req = || TestRequest::with_uri("/empty").to_request();
+        let resp = call_service(&first, req()).await;
+        assert_eq!(resp.status(), ntex::http::StatusCode::NO_CONTENT);
+        let resp = call_service(&second, req()).await;
+        assert_eq!(resp.status(), ntex::http::StatusCode::OK);
+
+        // nothing leaks out of the request
+        assert!(ServerSettings::read(|_| ()).is_none());
+    }
+
+    #[ntex::test]
+    async fn test_servers_keep_their_own_retry_after() {
+        let app = |retry_after| {
+            let settings = ServerSettings {
+                retry_after,
+                ..Default::default()
+            };
+            App::new()
+                .state(Arc::new(settings))
+                .route(
+                    "/busy",
+                    web::get().to(|| async { Responder::send((), ResponseCode::TooManyRequests) }),
+                )
+                .wrap(SettingsScope)
+        };
+
+        let first = init_service(app(Some(5))).await;
+        let second = init_service(app(None)).await;
+
+        let req = || TestRequest::with_uri("/busy").to_request();
+        let resp = call_service(&first, req()).await;
+        assert_eq!(resp.headers().get("retry-after").unwrap(), "5");
+        let resp = call_service(&second, req()).await;
+        assert_eq!(resp.headers().get("retry-after").unwrap(), "60");
+    }
+}
@@ -0,0 +1,276 @@
+use ntex::service::{Middleware as ServiceMiddleware, Service, ServiceCtx};
+use ntex::web::{self, WebRequest, WebResponse};
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::panic::PanicHookInfo;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use tracing::{debug, error};
+
+static WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// index of the server worker running on this thread
+    static WORKER: Cell<Option<usize>> = const { Cell::new(None) };
+    /// name given to the thread of that worker
+    static WORKER_NAME: RefCell<Option<String>> = const { RefCell::new(None) };
+    /// request being polled on this thread
+    static CURRENT_REQUEST: RefCell<Option<Rc<PanicRequest>>> = const { RefCell::new(None) };
+}
+
+/// Called with every panic happening on a server worker, see `ServerConfig::worker_panic_hook`
+pub type WorkerPanicHook = Arc<dyn Fn(&WorkerPanic) + Send + Sync>;
+
+/// Request a worker was handling when it panicked
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PanicRequest {
+    pub method: String,
+    pub path: String,
+    /// `x-request-id` header, when sent
+    pub request_id: Option<String>,
+}
+
+/// Panic of a server worker
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkerPanic {
+    pub worker: usize,
+    pub thread: String,
+    pub message: String,
+    /// `file:line:column` of the panic
+    pub location: Option<String>,
+    pub request: Option<PanicRequest>,
+}
+
+impl WorkerPanic {
+    /// Log the panic as one JSON line, usable as the hook itself
+    pub fn log(&self) {
+        error!(
+            "worker panic: {}",
+            serde_json::to_string(self).unwrap_or_default()
+        );
+    }
+
+    fn from_info(worker: usize, info: &PanicHookInfo) -> Self {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+
+        Self {
+            worker,
+            thread: WORKER_NAME
+                .try_with(|name| name.borrow().clone())
+                .ok()
+                .flatten()
+                .or_else(|| std::thread::current().name().map(str::to_string))
+                .unwrap_or_else(|| "unnamed".to_string()),
+            message,
+            location: info.location().map(|location| location.to_string()),
+            request: CURRENT_REQUEST
+                .try_with(|request| {
+                    request
+                        .try_borrow()
+                        .ok()
+                        .and_then(|request| request.as_deref().cloned())
+                })
+                .ok()
+                .flatten(),
+        }
+    }
+}
+
+/// Install `hook` for panics of server workers, other threads keep the previous hook only
+pub(crate) fn install_panic_hook(hook: WorkerPanicHook) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(worker) = WORKER.try_with(Cell::get).ok().flatten() {
+            hook(&WorkerPanic::from_info(worker, info));
+        }
+        previous(info);
+    }));
+}
+
+/// Mark the current thread as a server worker, naming it `{prefix}-{index}` at the OS level
+/// (as shown by `top -H`, `ps` and profilers). Only the first call of a thread counts.
+pub(crate) fn enter_worker(prefix: Option<&str>) {
+    if WORKER.get().is_some() {
+        return;
+    }
+
+    let index = WORKERS.fetch_add(1, Ordering::Relaxed);
+    WORKER.set(Some(index));
+
+    if let Some(prefix) = prefix {
+        let name = format!("{prefix}-{index}");
+        set_thread_name(&name);
+        WORKER_NAME.set(Some(name));
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_thread_name(name: &str) {
+    // the kernel keeps 15 bytes, plus the nul terminator
+    let truncated: String = name.chars().take(15).collect();
+    match std::ffi::CString::new(truncated) {
+        Ok(name) => unsafe {
+            libc::prctl(libc::PR_SET_NAME, name.as_ptr() as libc::c_ulong, 0, 0, 0);
+        },
+        Err(_) => debug!("[workers] invalid thread name '{name}'"),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_thread_name(name: &str) {
+    debug!("[workers] thread naming is not supported on this platform, skipping '{name}'");
+}
+
+/// Puts the previous request back, also when the poll unwinds
+struct RestoreRequest(Option<Rc<PanicRequest>>);
+
+impl Drop for RestoreRequest {
+    fn drop(&mut self) {
+        let _ = CURRENT_REQUEST.try_with(|current| current.replace(self.0.take()));
+    }
+}
+
+/// Future recording its request as the current one of the thread while being polled
+struct WithRequest<F> {
+    future: Pin<Box<F>>,
+    request: Rc<PanicRequest>,
+}
+
+impl<F: Future> Future for WithRequest<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let _restore = RestoreRequest(CURRENT_REQUEST.replace(Some(self.request.clone())));
+        self.future.as_mut().poll(cx)
+    }
+}
+
+/// Middleware exposing the request to the worker panic hook
+#[derive(Clone)]
+pub(crate) struct PanicContext {
+    enabled: bool,
+}
+
+impl PanicContext {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> ServiceMiddleware<S> for PanicContext {
+    type Service = PanicContextService<S>;
+
+    fn create(&self, service: S) -> Self::Service {
+        PanicContextService {
+            service,
+            enabled: self.enabled,
+        }
+    }
+}
+
+pub(crate) struct PanicContextService<S> {
+    service: S,
+    enabled: bool,
+}
+
+impl<S, Err> Service<WebRequest<Err>> for PanicContextService<S>
+where
+    S: Service<WebRequest<Err>, Response = WebResponse>,
+    Err: web::ErrorRenderer,
+{
+    type Response = WebResponse;
+    type Error = S::Error;
+
+    ntex::forward_ready!(service);
+
+    async fn call(
+        &self,
+        req: WebRequest<Err>,
+        ctx: ServiceCtx<'_, Self>,
+    ) -> Result<Self::Response, Self::Error> {
+        if !self.enabled {
+            return ctx.call(&self.service, req).await;
+        }
+
+        let request = PanicRequest {
+            method: req.method().to_string(),
+            path: req.path().to_string(),
+            request_id: req
+                .headers()
+                .get("x-request-id")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        };
+
+        WithRequest {
+            future: Box::pin(ctx.call(&self.service, req)),
+            request: Rc::new(request),
+        }
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_hook_receives_request_context() {
+        let panics = Arc::new(Mutex::new(vec![]));
+        let recorded = panics.clone();
+        install_panic_hook(Arc::new(move |panic: &WorkerPanic| {
+            recorded.lock().unwrap().push(panic.clone());
+        }));
+
+        let handle = std::thread::spawn(|| {
+            enter_worker(Some("api-worker"));
+            let future = WithRequest {
+                future: Box::pin(async { panic!("boom") }),
+                request: Rc::new(PanicRequest {
+                    method: "GET".to_string(),
+                    path: "/users".to_string(),
+                    request_id: Some("req-1".to_string()),
+                }),
+            };
+            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                futures_util::FutureExt::now_or_never(future)
+            }));
+
+            // restored once the poll unwound
+            assert!(CURRENT_REQUEST.with_borrow(Option::is_none));
+            panic!("after")
+        });
+        assert!(handle.join().is_err());
+
+        // a thread that isn't a worker doesn't reach the hook
+        assert!(std::thread::spawn(|| panic!("elsewhere")).join().is_err());
+
+        let panics = panics.lock().unwrap();
+        assert_eq!(panics.len(), 2);
+        assert!(panics[1].request.is_none());
+        assert_eq!(panics[0].message, "boom");
+        assert!(panics[0].thread.starts_with("api-worker-"));
+        assert!(
+            panics[0]
+                .location
+                .as_deref()
+                .unwrap()
+                .contains("workers.rs")
+        );
+        assert_eq!(panics[0].request.as_ref().unwrap().path, "/users");
+        assert_eq!(
+            panics[0].request.as_ref().unwrap().request_id.as_deref(),
+            Some("req-1")
+        );
+    }
+}
@@ -0,0 +1,455 @@
+use crate::FoxtiveNtexState;
+use crate::helpers::affinity::{AffinityFuture, AffinityStore};
+use crate::helpers::downstream::{DownstreamFuture, DownstreamTransport};
+use crate::http::kernel::{Route, ntex_default_service, register_routes};
+use crate::http::manifest::{ExampleOutcome, replay_examples};
+use crate::http::middlewares::RateLimiters;
+use ntex::http::{Method, Request, StatusCode};
+use ntex::util::Bytes;
+use ntex::web::test::{call_service, init_service};
+use ntex::web::{App, WebResponse};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// In-memory app serving `routes` with a fresh state, whose downstream client, session
+/// affinity store and webhook transport can be replaced by stubs recording the calls and
+/// returning canned responses, so handler tests don't reach external services.
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::kernel::Route;
+/// use foxtive_ntex::http::testing::{StubDownstream, TestApp};
+/// use ntex::http::{Method, StatusCode};
+/// use ntex::web::test::TestRequest;
+///
+/// # async fn example(routes: Vec<Route>) {
+/// let users = StubDownstream::default().respond(
+///     Method::GET,
+///     "http://users.internal/5",
+///     StatusCode::OK,
+///     r#"{"id":5}"#,
+/// );
+/// let app = TestApp::new(routes).downstream(users.clone());
+///
+/// let resp = app.call(TestRequest::with_uri("/api/profiles/5").to_request()).await;
+/// assert_eq!(users.calls().len(), 1);
+/// # }
+/// ```
+pub struct TestApp {
+    routes: Vec<Route>,
+    state: FoxtiveNtexState,
+}
+
+impl TestApp {
+    pub fn new(routes: Vec<Route>) -> Self {
+        Self {
+            routes,
+            state: FoxtiveNtexState::for_tests(),
+        }
+    }
+
+    /// State the handlers receive, e.g. to seed the runtime settings
+    pub fn state(&self) -> &FoxtiveNtexState {
+        &self.state
+    }
+
+    /// Answer the calls made through `state.downstream` with `stub`
+    pub fn downstream(self, stub: StubDownstream) -> Self {
+        self.state.downstream.use_transport(stub);
+        self
+    }
+
+    /// Keep the entries of `state.affinity` in `stub`
+    pub fn storage(self, stub: StubStore) -> Self {
+        self.state.affinity.use_store(stub);
+        self
+    }
+
+    /// Deliver the webhooks of `state.webhooks` to `stub`
+    #[cfg(feature = "webhooks")]
+    pub fn webhooks(self, stub: StubWebhookTransport) -> Self {
+        self.state.webhooks.use_transport(stub);
+        self
+    }
+
+    /// Handle `req`, the app is built for every call but shares the state and the stubs
+    pub async fn call(&self, req: Request) -> WebResponse {
+        let routes = self.routes.clone();
+        let app = init_service(
+            App::new()
+                .state(self.state.clone())
+                .state(RateLimiters::new(vec![]))
+                .configure(|cfg| register_routes(cfg, routes))
+                .default_service(ntex_default_service()),
+        )
+        .await;
+
+        call_service(&app, req).await
+    }
+
+    /// Replay the examples of the routes against the state and the stubs, see
+    /// [`replay_examples`]
+    pub async fn replay_examples(&self) -> Vec<ExampleOutcome> {
+        let state = self.state.clone();
+        replay_examples(self.routes.clone(), move |cfg| {
+            cfg.state(state);
+        })
+        .await
+    }
+}
+
+/// Call received by a [`StubDownstream`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownstreamCall {
+    pub method: Method,
+    pub url: String,
+    pub body: Option<Bytes>,
+}
+
+#[derive(Default)]
+struct DownstreamStubs {
+    responses: HashMap<(Method, String), VecDeque<(StatusCode, Bytes)>>,
+    calls: Vec<DownstreamCall>,
+}
+
+/// [`DownstreamTransport`] answering with canned responses per method and url.
+///
+/// Responses registered for the same request are returned in order, the last one
+/// repeating; requests without one are answered with `501 Not Implemented`.
+#[derive(Clone, Default)]
+pub struct StubDownstream {
+    inner: Arc<Mutex<DownstreamStubs>>,
+}
+
+impl StubDownstream {
+    pub fn respond(self, method: Method, url: &str, status: StatusCode, body: &str) -> Self {
+        if let Ok(mut stubs) = self.inner.lock() {
+            stubs
+                .responses
+                .entry((method, url.to_string()))
+                .or_default()
+                .push_back((status, Bytes::copy_from_slice(body.as_bytes())));
+        }
+        self
+    }
+
+    /// Calls received so far, in order
+    pub fn calls(&self) -> Vec<DownstreamCall> {
+        match self.inner.lock() {
+            Ok(stubs) => stubs.calls.clone(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+impl DownstreamTransport for StubDownstream {
+    fn send(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<Bytes>,
+    ) -> DownstreamFuture<(StatusCode, Bytes)> {
+        let response = self.inner.lock().ok().and_then(|mut stubs| {
+            stubs.calls.push(DownstreamCall {
+                method: method.clone(),
+                url: url.to_string(),
+                body,
+            });
+
+            let queued = stubs
+                .responses
+                .get_mut(&(method.clone(), url.to_string()))?;
+            match queued.len() {
+                0 => None,
+                1 => queued.front().cloned(),
+                _ => queued.pop_front(),
+            }
+        });
+
+        let response = response.unwrap_or_else(|| {
+            let message = format!("no stub for {method} {url}");
+            let body = json!({ "message": message }).to_string();
+            (StatusCode::NOT_IMPLEMENTED, Bytes::from(body))
+        });
+
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// Call received by a [`StubStore`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreCall {
+    Get(String),
+    Put(String, String, Duration),
+    Remove(String),
+}
+
+#[derive(Default)]
+struct StoreStubs {
+    entries: HashMap<String, String>,
+    calls: Vec<StoreCall>,
+}
+
+/// [`AffinityStore`] keeping its entries in memory without expiring them, seeded with
+/// canned entries
+#[derive(Clone, Default)]
+pub struct StubStore {
+    inner: Arc<Mutex<StoreStubs>>,
+}
+
+impl StubStore {
+    /// Serialized JSON `value` returned for `key`
+    pub fn entry(self, key: &str, value: &str) -> Self {
+        if let Ok(mut stubs) = self.inner.lock() {
+            stubs.entries.insert(key.to_string(), value.to_string());
+        }
+        self
+    }
+
+    /// Calls received so far, in order
+    pub fn calls(&self) -> Vec<StoreCall> {
+        match self.inner.lock() {
+            Ok(stubs) => stubs.calls.clone(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn record<R>(&self, call: StoreCall, apply: impl FnOnce(&mut StoreStubs) -> R) -> Option<R> {
+        let mut stubs = self.inner.lock().ok()?;
+        stubs.calls.push(call);
+        Some(apply(&mut stubs))
+    }
+}
+
+impl AffinityStore for StubStore {
+    fn get(&self, key: &str) -> AffinityFuture<Option<String>> {
+        let value = self
+            .record(StoreCall::Get(key.to_string()), |stubs| {
+                stubs.entries.get(key).cloned()
+            })
+            .flatten();
+
+        Box::pin(async move { Ok(value) })
+    }
+
+    fn put(&self, key: &str, value: String, ttl: Duration) -> AffinityFuture<()> {
+        let call = StoreCall::Put(key.to_string(), value.clone(), ttl);
+        self.record(call, |stubs| stubs.entries.insert(key.to_string(), value));
+
+        Box::pin(async { Ok(()) })
+    }
+
+    fn remove(&self, key: &str) -> AffinityFuture<()> {
+        self.record(StoreCall::Remove(key.to_string()), |stubs| {
+            stubs.entries.remove(key)
+        });
+
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(feature = "webhooks")]
+pub use webhooks::{StubWebhookTransport, WebhookCall};
+
+#[cfg(feature = "webhooks")]
+mod webhooks {
+    use crate::helpers::webhooks::{WebhookFuture, WebhookTransport};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// Delivery received by a [`StubWebhookTransport`]
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct WebhookCall {
+        pub url: String,
+        pub headers: Vec<(&'static str, String)>,
+        pub body: String,
+    }
+
+    #[derive(Default)]
+    struct WebhookStubs {
+        statuses: HashMap<String, u16>,
+        calls: Vec<WebhookCall>,
+    }
+
+    /// [`WebhookTransport`] answering with a canned status per url, 200 by default
+    #[derive(Clone, Default)]
+    pub struct StubWebhookTransport {
+        inner: Arc<Mutex<WebhookStubs>>,
+    }
+
+    impl StubWebhookTransport {
+        pub fn status(self, url: &str, status: u16) -> Self {
+            if let Ok(mut stubs) = self.inner.lock() {
+                stubs.statuses.insert(url.to_string(), status);
+            }
+            self
+        }
+
+        /// Deliveries received so far, in order
+        pub fn calls(&self) -> Vec<WebhookCall> {
+            match self.inner.lock() {
+                Ok(stubs) => stubs.calls.clone(),
+                Err(_) => vec![],
+            }
+        }
+    }
+
+    impl WebhookTransport for StubWebhookTransport {
+        fn post(
+            &self,
+            url: &str,
+            headers: Vec<(&'static str, String)>,
+            body: String,
+        ) -> WebhookFuture<u16> {
+            let status = match self.inner.lock() {
+                Ok(mut stubs) => {
+                    stubs.calls.push(WebhookCall {
+                        url: url.to_string(),
+                        headers,
+                        body,
+                    });
+                    stubs.statuses.get(url).copied().unwrap_or(200)
+                }
+                Err(_) => 500,
+            };
+
+            Box::pin(async move { Ok(status) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpResult;
+    use crate::http::kernel::Controller;
+    use crate::http::manifest::RouteExample;
+    use ntex::web::test::{TestRequest, read_body};
+    use ntex::web::types::{Path, State};
+    use ntex::web::{self, HttpResponse, ServiceConfig};
+    use serde_json::Value;
+
+    async fn profile(state: State<FoxtiveNtexState>, id: Path<u32>) -> HttpResult {
+        let url = format!("http://users.internal/{id}");
+        let user: Value = state.downstream.get(&url).await?;
+        state.affinity.attach("session-1", &user).await?;
+
+        Ok(HttpResponse::Ok().json(&user))
+    }
+
+    fn profiles(cfg: &mut ServiceConfig) {
+        cfg.route("/{id}", web::get().to(profile));
+    }
+
+    fn routes() -> Vec<Route> {
+        vec![
+            Route::new("/api").controller(
+                Controller::new("/profiles", profiles).example(
+                    RouteExample::new("fetch profile", Method::GET, "/5")
+                        .response_json(json!({"id": 5})),
+                ),
+            ),
+        ]
+    }
+
+    fn users() -> StubDownstream {
+        StubDownstream::default().respond(
+            Method::GET,
+            "http://users.internal/5",
+            StatusCode::OK,
+            r#"{"id":5}"#,
+        )
+    }
+
+    #[ntex::test]
+    async fn test_handlers_use_the_stubs() {
+        let users = users();
+        let store = StubStore::default();
+        let app = TestApp::new(routes())
+            .downstream(users.clone())
+            .storage(store.clone());
+
+        let resp = app
+            .call(TestRequest::with_uri("/api/profiles/5").to_request())
+            .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, r#"{"id":5}"#);
+
+        assert_eq!(
+            users.calls(),
+            vec![DownstreamCall {
+                method: Method::GET,
+                url: "http://users.internal/5".to_string(),
+                body: None,
+            }]
+        );
+        assert!(matches!(
+            &store.calls()[..],
+            [StoreCall::Put(key, value, _)] if key == "session-1" && value == r#"{"id":5}"#
+        ));
+    }
+
+    #[ntex::test]
+    async fn test_unstubbed_and_failing_downstream_calls() {
+        let users = users().respond(
+            Method::GET,
+            "http://users.internal/5",
+            StatusCode::NOT_FOUND,
+            r#"{"title":"Not Found","detail":"No such user"}"#,
+        );
+        let app = TestApp::new(routes()).downstream(users);
+
+        // the first canned response is consumed, the last one repeats
+        let req = || TestRequest::with_uri("/api/profiles/5").to_request();
+        assert_eq!(app.call(req()).await.status(), StatusCode::OK);
+        assert_eq!(app.call(req()).await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(app.call(req()).await.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::with_uri("/api/profiles/6").to_request();
+        assert_eq!(app.call(req).await.status(), StatusCode::NOT_IMPLEMENTED);
+    }
+
+    #[ntex::test]
+    async fn test_stored_entries_and_examples() {
+        let store = StubStore::default().entry("session-1", r#"{"id":7}"#);
+        let app = TestApp::new(routes())
+            .downstream(users())
+            .storage(store.clone());
+
+        let cached: Option<Value> = app.state().affinity.get("session-1").await.unwrap();
+        assert_eq!(cached, Some(json!({"id": 7})));
+
+        let outcomes = app.replay_examples().await;
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].passed(), "{:?}", outcomes[0].failure);
+        assert_eq!(store.calls().len(), 2);
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[ntex::test]
+    async fn test_webhook_deliveries_are_recorded() {
+        use crate::helpers::webhooks::WebhookEndpoint;
+
+        let transport = StubWebhookTransport::default().status("https://acme.test/down", 503);
+        let app = TestApp::new(vec![]).webhooks(transport.clone());
+        let webhooks = &app.state().webhooks;
+        webhooks.register_endpoint(WebhookEndpoint::new(
+            "ep-1",
+            "acme",
+            "https://acme.test/hooks",
+            "whsec_123",
+        ));
+
+        webhooks
+            .dispatch("acme", "invoice.paid", &json!({"invoice": 42}))
+            .unwrap();
+        webhooks.run_pending().await;
+
+        let calls = transport.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].url, "https://acme.test/hooks");
+        assert!(calls[0].body.contains("invoice.paid"));
+    }
+}
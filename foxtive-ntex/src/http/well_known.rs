@@ -0,0 +1,160 @@
+use ntex::http::header;
+use ntex::util::Bytes;
+use ntex::web;
+use ntex::web::{HttpResponse, ServiceConfig};
+
+pub const ROBOTS_TXT_PATH: &str = "/robots.txt";
+pub const SECURITY_TXT_PATH: &str = "/.well-known/security.txt";
+pub const FAVICON_PATH: &str = "/favicon.ico";
+
+/// robots.txt content asking crawlers to stay away, suitable for APIs
+pub const DISALLOW_ALL_ROBOTS_TXT: &str = "User-agent: *\nDisallow: /\n";
+
+/// Content of the standard well-known endpoints
+#[derive(Clone)]
+pub struct WellKnownConfig {
+    /// Content of `/robots.txt`, not registered when `None`
+    pub robots_txt: Option<String>,
+
+    /// Content of `/.well-known/security.txt`, not registered when `None`
+    pub security_txt: Option<String>,
+
+    /// `/favicon.ico` content, responds with `204 No Content` when `None`
+    pub favicon: Option<Bytes>,
+}
+
+impl Default for WellKnownConfig {
+    fn default() -> Self {
+        Self {
+            robots_txt: Some(DISALLOW_ALL_ROBOTS_TXT.to_string()),
+            security_txt: None,
+            favicon: None,
+        }
+    }
+}
+
+impl WellKnownConfig {
+    pub fn robots_txt(mut self, content: &str) -> Self {
+        self.robots_txt = Some(content.to_string());
+        self
+    }
+
+    /// Build `security.txt` (RFC 9116) from a contact uri and an expiry date (ISO 8601)
+    pub fn security_contact(mut self, contact: &str, expires: &str) -> Self {
+        self.security_txt = Some(format!("Contact: {contact}\nExpires: {expires}\n"));
+        self
+    }
+
+    pub fn security_txt(mut self, content: &str) -> Self {
+        self.security_txt = Some(content.to_string());
+        self
+    }
+
+    pub fn favicon(mut self, bytes: impl Into<Bytes>) -> Self {
+        self.favicon = Some(bytes.into());
+        self
+    }
+
+    /// Paths that will be registered, used to keep access log exclusions consistent
+    pub fn paths(&self) -> Vec<&'static str> {
+        let mut paths = vec![FAVICON_PATH];
+
+        if self.robots_txt.is_some() {
+            paths.push(ROBOTS_TXT_PATH);
+        }
+
+        if self.security_txt.is_some() {
+            paths.push(SECURITY_TXT_PATH);
+        }
+
+        paths
+    }
+}
+
+/// Register the well-known endpoints described by the config
+pub fn register_well_known(cfg: &mut ServiceConfig, config: WellKnownConfig) {
+    if let Some(robots) = config.robots_txt {
+        cfg.route(
+            ROBOTS_TXT_PATH,
+            web::get().to(move || {
+                let robots = robots.clone();
+                async move { text_response(robots) }
+            }),
+        );
+    }
+
+    if let Some(security) = config.security_txt {
+        cfg.route(
+            SECURITY_TXT_PATH,
+            web::get().to(move || {
+                let security = security.clone();
+                async move { text_response(security) }
+            }),
+        );
+    }
+
+    let favicon = config.favicon;
+    cfg.route(
+        FAVICON_PATH,
+        web::get().to(move || {
+            let favicon = favicon.clone();
+            async move {
+                match favicon {
+                    None => HttpResponse::NoContent().finish(),
+                    Some(bytes) => HttpResponse::Ok()
+                        .header(header::CONTENT_TYPE, "image/x-icon")
+                        .header(header::CACHE_CONTROL, "public, max-age=86400")
+                        .body(bytes),
+                }
+            }
+        }),
+    );
+}
+
+fn text_response(content: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ntex::http::StatusCode;
+    use ntex::web::App;
+    use ntex::web::test::{TestRequest, call_service, init_service, read_body};
+
+    #[ntex::test]
+    async fn test_default_endpoints() {
+        let app = init_service(
+            App::new().configure(|cfg| register_well_known(cfg, WellKnownConfig::default())),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::with_uri(ROBOTS_TXT_PATH).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(read_body(resp).await, DISALLOW_ALL_ROBOTS_TXT);
+
+        let resp = call_service(&app, TestRequest::with_uri(FAVICON_PATH).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let resp = call_service(&app, TestRequest::with_uri(SECURITY_TXT_PATH).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[ntex::test]
+    async fn test_security_txt() {
+        let config = WellKnownConfig::default()
+            .security_contact("mailto:security@example.com", "2030-01-01T00:00:00Z");
+
+        assert!(config.paths().contains(&SECURITY_TXT_PATH));
+
+        let app = init_service(App::new().configure(|cfg| register_well_known(cfg, config))).await;
+        let resp = call_service(&app, TestRequest::with_uri(SECURITY_TXT_PATH).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(resp).await,
+            "Contact: mailto:security@example.com\nExpires: 2030-01-01T00:00:00Z\n"
+        );
+    }
+}
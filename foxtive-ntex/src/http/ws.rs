@@ -0,0 +1,304 @@
+use crate::http::response::anyhow::ResponseError;
+use foxtive::prelude::AppResult;
+use ntex::service::{fn_factory_with_config, fn_service};
+use ntex::time;
+use ntex::util::Bytes;
+use ntex::web::ws::{self, CloseCode, CloseReason, Frame, Message, WsSink};
+use ntex::web::{self, HttpRequest, HttpResponse};
+use std::cell::Cell;
+use std::future::Future;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Handshake check, receives the token from the `Authorization: Bearer` header or the
+/// `token`/`access_token` query parameter (browsers can't set headers on WebSockets).
+/// Failing rejects the upgrade with the error's HTTP status.
+///
+/// With the `jwt` feature, the token can be verified with
+/// `JwtAuthToken::from(token).decode::<Claims>(secret, &validation)`.
+pub type WsAuthHook = fn(&HttpRequest, Option<&str>) -> AppResult<()>;
+
+/// Close codes sent by the server, application specific ones live in the 4000-4999 range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsCloseCode {
+    Normal,
+    GoingAway,
+    PolicyViolation,
+    ServerError,
+    /// 4001, the connection's credentials are missing or no longer valid
+    Unauthorized,
+    /// 4003
+    Forbidden,
+    /// 4008, the peer stopped answering heartbeats
+    IdleTimeout,
+    Custom(u16),
+}
+
+impl WsCloseCode {
+    pub fn code(&self) -> u16 {
+        match self {
+            WsCloseCode::Normal => 1000,
+            WsCloseCode::GoingAway => 1001,
+            WsCloseCode::PolicyViolation => 1008,
+            WsCloseCode::ServerError => 1011,
+            WsCloseCode::Unauthorized => 4001,
+            WsCloseCode::Forbidden => 4003,
+            WsCloseCode::IdleTimeout => 4008,
+            WsCloseCode::Custom(code) => *code,
+        }
+    }
+
+    /// Close frame carrying this code and an optional reason
+    pub fn message(self, reason: Option<&str>) -> Message {
+        Message::Close(Some(CloseReason {
+            code: CloseCode::from(self.code()),
+            description: reason.map(str::to_string),
+        }))
+    }
+}
+
+impl From<WsCloseCode> for CloseCode {
+    fn from(code: WsCloseCode) -> Self {
+        CloseCode::from(code.code())
+    }
+}
+
+/// Handshake authentication and heartbeat policy of a WebSocket endpoint
+///
+/// # Example
+/// ```
+/// use foxtive_ntex::http::ws::{WsConfig, WsSession, start_ws};
+/// use foxtive::prelude::{AppMessage, AppResult};
+/// use ntex::web::ws::{Frame, Message};
+/// use ntex::web::{HttpRequest, HttpResponse};
+/// use std::time::Duration;
+///
+/// fn authenticate(_req: &HttpRequest, token: Option<&str>) -> AppResult<()> {
+///     match token {
+///         Some("secret") => Ok(()),
+///         _ => AppMessage::Unauthorized.ar(),
+///     }
+/// }
+///
+/// async fn echo(req: HttpRequest) -> Result<HttpResponse, ntex::web::Error> {
+///     let config = WsConfig::default()
+///         .auth(authenticate)
+///         .heartbeat(Duration::from_secs(15), Duration::from_secs(45));
+///
+///     start_ws(req, config, |frame: Frame, _session: WsSession| async move {
+///         match frame {
+///             Frame::Text(text) => Some(Message::Text(String::from_utf8_lossy(&text).into())),
+///             _ => None,
+///         }
+///     })
+///     .await
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WsConfig {
+    auth: Option<WsAuthHook>,
+    /// interval between server pings
+    ping_interval: Duration,
+    /// connections without any frame for this long are closed
+    idle_timeout: Duration,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            auth: None,
+            ping_interval: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(90),
+        }
+    }
+}
+
+impl WsConfig {
+    pub fn auth(mut self, hook: WsAuthHook) -> Self {
+        self.auth = Some(hook);
+        self
+    }
+
+    pub fn heartbeat(mut self, ping_interval: Duration, idle_timeout: Duration) -> Self {
+        self.ping_interval = ping_interval;
+        self.idle_timeout = idle_timeout;
+        self
+    }
+}
+
+/// Connection handed to the frame handler
+#[derive(Clone)]
+pub struct WsSession {
+    sink: WsSink,
+    request: HttpRequest,
+}
+
+impl WsSession {
+    /// Handshake request, holding whatever the auth hook stored in its extensions
+    pub fn request(&self) -> &HttpRequest {
+        &self.request
+    }
+
+    pub async fn send(&self, message: Message) -> AppResult<()> {
+        self.sink
+            .send(message)
+            .await
+            .map_err(|err| foxtive::Error::msg(err.to_string()))
+    }
+
+    pub async fn close(&self, code: WsCloseCode, reason: Option<&str>) -> AppResult<()> {
+        self.send(code.message(reason)).await
+    }
+}
+
+/// Read the connection token from the bearer header or the query string
+pub fn ws_token(req: &HttpRequest) -> Option<String> {
+    let header = req
+        .headers()
+        .get(ntex::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            v.strip_prefix("Bearer ")
+                .or_else(|| v.strip_prefix("bearer "))
+        })
+        .map(|v| v.trim().to_string());
+
+    header.or_else(|| {
+        req.query_string().split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            match key {
+                "token" | "access_token" if !value.is_empty() => Some(value.to_string()),
+                _ => None,
+            }
+        })
+    })
+}
+
+/// Authenticate the handshake, upgrade the connection and dispatch incoming frames to
+/// `handler`. Ping/pong and close frames are answered automatically, the server pings
+/// the peer at the configured interval and closes idle connections with
+/// [`WsCloseCode::IdleTimeout`].
+pub async fn start_ws<H, Fut>(
+    req: HttpRequest,
+    config: WsConfig,
+    handler: H,
+) -> Result<HttpResponse, web::Error>
+where
+    H: Fn(Frame, WsSession) -> Fut + Clone + 'static,
+    Fut: Future<Output = Option<Message>> + 'static,
+{
+    if let Some(auth) = config.auth {
+        let token = ws_token(&req);
+        auth(&req, token.as_deref()).map_err(ResponseError::new)?;
+    }
+
+    let request = req.clone();
+    ws::start(
+        req,
+        fn_factory_with_config(move |sink: WsSink| {
+            let handler = handler.clone();
+            let session = WsSession {
+                sink: sink.clone(),
+                request: request.clone(),
+            };
+
+            async move {
+                let last_seen = Rc::new(Cell::new(Instant::now()));
+                ntex::rt::spawn(heartbeat(sink, config, last_seen.clone()));
+
+                Ok::<_, web::Error>(fn_service(move |frame: Frame| {
+                    let handler = handler.clone();
+                    let session = session.clone();
+                    last_seen.set(Instant::now());
+
+                    async move {
+                        let reply = match frame {
+                            Frame::Ping(payload) => Some(Message::Pong(payload)),
+                            Frame::Pong(_) => None,
+                            Frame::Close(reason) => Some(Message::Close(reason)),
+                            frame => handler(frame, session).await,
+                        };
+
+                        Ok::<_, web::Error>(reply)
+                    }
+                }))
+            }
+        }),
+    )
+    .await
+}
+
+async fn heartbeat(sink: WsSink, config: WsConfig, last_seen: Rc<Cell<Instant>>) {
+    loop {
+        time::sleep(config.ping_interval).await;
+
+        if sink.io().is_closed() {
+            debug!("[ws] connection closed, stopping heartbeat");
+            return;
+        }
+
+        if last_seen.get().elapsed() >= config.idle_timeout {
+            warn!("[ws] closing connection idle for {:?}", config.idle_timeout);
+            let _ = sink
+                .send(WsCloseCode::IdleTimeout.message(Some("idle timeout")))
+                .await;
+            sink.io().close();
+            return;
+        }
+
+        if sink.send(Message::Ping(Bytes::new())).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxtive::prelude::AppMessage;
+    use ntex::http::StatusCode;
+    use ntex::web::test::TestRequest;
+
+    fn only_secret(_req: &HttpRequest, token: Option<&str>) -> AppResult<()> {
+        match token {
+            Some("secret") => Ok(()),
+            _ => AppMessage::Unauthorized.ar(),
+        }
+    }
+
+    #[test]
+    fn test_token_from_header_or_query() {
+        let req = TestRequest::default()
+            .header("authorization", "Bearer abc")
+            .to_http_request();
+        assert_eq!(ws_token(&req).as_deref(), Some("abc"));
+
+        let req = TestRequest::with_uri("/ws?room=1&access_token=xyz").to_http_request();
+        assert_eq!(ws_token(&req).as_deref(), Some("xyz"));
+
+        let req = TestRequest::with_uri("/ws?token=").to_http_request();
+        assert_eq!(ws_token(&req), None);
+    }
+
+    #[ntex::test]
+    async fn test_rejects_unauthenticated_handshake() {
+        let req = TestRequest::with_uri("/ws?token=wrong").to_http_request();
+        let config = WsConfig::default().auth(only_secret);
+        let err = start_ws(req, config, |_, _| async { None })
+            .await
+            .unwrap_err();
+
+        let resp = err
+            .as_response_error()
+            .error_response(&TestRequest::default().to_http_request());
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_close_codes() {
+        assert_eq!(u16::from(CloseCode::from(WsCloseCode::IdleTimeout)), 4008);
+        assert_eq!(CloseCode::from(WsCloseCode::Normal), CloseCode::Normal);
+        assert_eq!(WsCloseCode::Custom(4100).code(), 4100);
+    }
+}
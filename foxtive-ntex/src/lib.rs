@@ -1,3 +1,6 @@
+// the layouts of the nested middleware futures of the server go past the default limit
+#![recursion_limit = "512"]
+
 use std::sync::OnceLock;
 
 pub mod contracts;
@@ -7,6 +10,7 @@ pub mod helpers;
 pub mod http;
 mod setup;
 
+pub use setup::runtime_settings::{RuntimeSettings, Settings};
 pub use setup::state::FoxtiveNtexState;
 
 pub static FOXTIVE_NTEX: OnceLock<FoxtiveNtexState> = OnceLock::new();
@@ -1,21 +1,27 @@
 use crate::FOXTIVE_NTEX;
+use crate::helpers::worker_pool::{WorkerPool, WorkerPools};
 use crate::http::Method;
 use foxtive::prelude::AppMessage;
 use foxtive::results::AppResult;
 use foxtive::setup::FoxtiveSetup;
+use runtime_settings::{RuntimeSettings, Settings};
 use state::FoxtiveNtexState;
 use tracing::debug;
 
+pub mod runtime_settings;
 pub mod state;
 
 pub struct FoxtiveNtexSetup {
     pub allowed_origins: Vec<String>,
     pub allowed_methods: Vec<Method>,
+    pub runtime_settings: Settings,
+    /// name and thread count of each dedicated worker pool
+    pub worker_pools: Vec<(String, usize)>,
     pub foxtive_setup: FoxtiveSetup,
 }
 
 pub async fn make_ntex_state(setup: FoxtiveNtexSetup) -> AppResult<FoxtiveNtexState> {
-    let app = create_app_state(&setup).await;
+    let app = create_app_state(&setup).await?;
 
     debug!("Creating Foxtive state");
     foxtive::setup::make_state(setup.foxtive_setup).await?;
@@ -27,9 +33,31 @@ pub async fn make_ntex_state(setup: FoxtiveNtexSetup) -> AppResult<FoxtiveNtexSt
     Ok(app)
 }
 
-async fn create_app_state(setup: &FoxtiveNtexSetup) -> FoxtiveNtexState {
-    FoxtiveNtexState {
+async fn create_app_state(setup: &FoxtiveNtexSetup) -> AppResult<FoxtiveNtexState> {
+    let worker_pools = setup
+        .worker_pools
+        .iter()
+        .map(|(name, threads)| WorkerPool::new(name, *threads))
+        .collect::<AppResult<Vec<_>>>()?;
+
+    Ok(FoxtiveNtexState {
         allowed_origins: setup.allowed_origins.clone(),
         allowed_methods: setup.allowed_methods.clone(),
-    }
+        runtime_settings: RuntimeSettings::new(setup.runtime_settings.clone()),
+        worker_pools: WorkerPools::new(worker_pools),
+        locks: Default::default(),
+        distributed_locks: Default::default(),
+        pubsub: Default::default(),
+        dynamic_routes: Default::default(),
+        components: Default::default(),
+        affinity: Default::default(),
+        clock: Default::default(),
+        rng: Default::default(),
+        stats: Default::default(),
+        downstream: Default::default(),
+        #[cfg(feature = "dev-tools")]
+        mirror: Default::default(),
+        #[cfg(feature = "webhooks")]
+        webhooks: Default::default(),
+    })
 }
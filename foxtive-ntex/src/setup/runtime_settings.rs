@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Values that can be changed while the server is running.
+///
+/// Middlewares read a snapshot of these per request, so updates take effect
+/// on the next request without restarting workers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Settings {
+    /// Whether the service should reject traffic with 503
+    #[serde(default)]
+    pub maintenance_mode: bool,
+
+    /// Message returned to clients while maintenance mode is on
+    #[serde(default)]
+    pub maintenance_message: Option<String>,
+
+    /// Allowed requests per minute per client, `None` means unlimited
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<u32>,
+
+    /// Fraction of requests (0.0 - 1.0) whose details should be logged
+    #[serde(default = "default_log_sample_rate")]
+    pub log_sample_rate: f64,
+
+    /// Application specific values
+    #[serde(default)]
+    pub extra: HashMap<String, Value>,
+}
+
+fn default_log_sample_rate() -> f64 {
+    1.0
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            maintenance_mode: false,
+            maintenance_message: None,
+            rate_limit_per_minute: None,
+            log_sample_rate: default_log_sample_rate(),
+            extra: HashMap::new(),
+        }
+    }
+}
+
+/// Watch-channel backed store of [`Settings`], shared by all workers.
+#[derive(Clone)]
+pub struct RuntimeSettings {
+    sender: Arc<watch::Sender<Arc<Settings>>>,
+}
+
+impl RuntimeSettings {
+    pub fn new(settings: Settings) -> Self {
+        let (sender, _) = watch::channel(Arc::new(settings));
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    /// Returns a snapshot of the current settings
+    pub fn current(&self) -> Arc<Settings> {
+        self.sender.borrow().clone()
+    }
+
+    /// Subscribe to settings changes
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Settings>> {
+        self.sender.subscribe()
+    }
+
+    /// Atomically replace the settings
+    pub fn replace(&self, settings: Settings) {
+        self.sender.send_replace(Arc::new(settings));
+    }
+
+    /// Atomically modify the settings, readers will either see the old or the new values
+    pub fn update<F>(&self, func: F)
+    where
+        F: FnOnce(&mut Settings),
+    {
+        self.sender.send_modify(|current| {
+            let mut settings = Settings::clone(current);
+            func(&mut settings);
+            *current = Arc::new(settings);
+        });
+    }
+
+    pub fn is_maintenance_mode(&self) -> bool {
+        self.sender.borrow().maintenance_mode
+    }
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self::new(Settings::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_is_visible_to_snapshots() {
+        let settings = RuntimeSettings::default();
+        let before = settings.current();
+
+        settings.update(|s| s.maintenance_mode = true);
+
+        assert!(!before.maintenance_mode);
+        assert!(settings.current().maintenance_mode);
+        assert!(settings.is_maintenance_mode());
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_are_notified() {
+        let settings = RuntimeSettings::default();
+        let mut receiver = settings.subscribe();
+
+        settings.replace(Settings {
+            rate_limit_per_minute: Some(60),
+            ..Default::default()
+        });
+
+        receiver.changed().await.unwrap();
+        assert_eq!(receiver.borrow().rate_limit_per_minute, Some(60));
+    }
+
+    #[test]
+    fn test_deserialize_with_defaults() {
+        let settings: Settings = serde_json::from_str(r#"{"maintenance_mode": true}"#).unwrap();
+        assert!(settings.maintenance_mode);
+        assert_eq!(settings.log_sample_rate, 1.0);
+        assert!(settings.extra.is_empty());
+    }
+}
@@ -1,5 +1,19 @@
+use crate::helpers::affinity::SessionAffinity;
+use crate::helpers::clock::{RequestClock, RequestRng};
+use crate::helpers::components::ComponentRegistry;
+use crate::helpers::distributed_lock::{DistributedLockGuard, DistributedLocks};
+use crate::helpers::downstream::Downstream;
+use crate::helpers::keyed_lock::{KeyedGuard, KeyedLocks};
+use crate::helpers::pubsub::PubSubBridge;
+use crate::helpers::stats::RequestStats;
+use crate::helpers::worker_pool::WorkerPools;
 use crate::http::Method;
+use crate::http::dynamic::DynamicRoutes;
+use crate::http::server::enabled_features;
+use crate::setup::runtime_settings::RuntimeSettings;
+use foxtive::prelude::AppResult;
 use std::fmt::{Debug, Formatter};
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct FoxtiveNtexState {
@@ -8,6 +22,101 @@ pub struct FoxtiveNtexState {
 
     /// list of allowed methods
     pub allowed_methods: Vec<Method>,
+
+    /// settings that can be updated while the server is running
+    pub runtime_settings: RuntimeSettings,
+
+    /// dedicated pools for isolating blocking work
+    pub worker_pools: WorkerPools,
+
+    /// per-resource locks serializing conflicting requests
+    pub locks: KeyedLocks,
+
+    /// locks shared with the other instances, see `ServerConfig::lock_provider`
+    pub distributed_locks: DistributedLocks,
+
+    /// fan-out of queue messages to connected WebSocket/SSE clients
+    pub pubsub: PubSubBridge,
+
+    /// route groups registered while the server is running
+    pub dynamic_routes: DynamicRoutes,
+
+    /// background components stopped during graceful shutdown
+    pub components: ComponentRegistry,
+
+    /// per-connection state of WebSocket/SSE sessions, shared with their HTTP requests
+    pub affinity: SessionAffinity,
+
+    /// time source of helpers and handlers, frozen in tests
+    pub clock: RequestClock,
+
+    /// randomness of helpers and handlers, seeded in tests
+    pub rng: RequestRng,
+
+    /// rolling-window request counters, see `ServerConfig::request_stats`
+    pub stats: RequestStats,
+
+    /// client of the other services
+    pub downstream: Downstream,
+
+    /// last requests and responses kept for live debugging, see `ServerConfig::request_mirror`
+    #[cfg(feature = "dev-tools")]
+    pub mirror: crate::helpers::mirror::RequestMirror,
+
+    /// outbound webhook endpoints and deliveries
+    #[cfg(feature = "webhooks")]
+    pub webhooks: crate::helpers::webhooks::Webhooks,
+}
+
+impl FoxtiveNtexState {
+    /// Serialize requests touching the same resource, see [`KeyedLocks::serialize_on`]
+    pub async fn serialize_on(&self, key: &str) -> KeyedGuard {
+        self.locks.serialize_on(key).await
+    }
+
+    /// Hold `key` across instances, see [`DistributedLocks::lock`]
+    pub async fn lock(&self, key: &str, ttl: Duration) -> AppResult<DistributedLockGuard> {
+        self.distributed_locks.lock(key, ttl).await
+    }
+
+    /// Hold `key` across instances unless already held, see [`DistributedLocks::try_lock`]
+    pub async fn try_lock(
+        &self,
+        key: &str,
+        ttl: Duration,
+    ) -> AppResult<Option<DistributedLockGuard>> {
+        self.distributed_locks.try_lock(key, ttl).await
+    }
+
+    /// Empty state for unit tests and `TestApp`, override fields with struct update syntax
+    #[cfg(any(test, feature = "dev-tools"))]
+    pub(crate) fn for_tests() -> Self {
+        Self {
+            allowed_origins: vec![],
+            allowed_methods: vec![],
+            runtime_settings: Default::default(),
+            worker_pools: Default::default(),
+            locks: Default::default(),
+            distributed_locks: Default::default(),
+            pubsub: Default::default(),
+            dynamic_routes: Default::default(),
+            components: Default::default(),
+            affinity: Default::default(),
+            clock: Default::default(),
+            rng: Default::default(),
+            stats: Default::default(),
+            downstream: Default::default(),
+            #[cfg(feature = "dev-tools")]
+            mirror: Default::default(),
+            #[cfg(feature = "webhooks")]
+            webhooks: Default::default(),
+        }
+    }
+
+    /// Cargo features `foxtive-ntex` was compiled with, e.g. `["jwt", "multipart"]`
+    pub fn features(&self) -> Vec<&'static str> {
+        enabled_features()
+    }
 }
 
 impl Debug for FoxtiveNtexState {